@@ -0,0 +1,69 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+
+//! Compares serializing a large week of full `Task`s with the same week as
+//! `TaskSummary`s, the shape returned by the weekly board.
+//!
+//! Run with `cargo bench -p common`.
+use chrono::{NaiveDate, Utc};
+use common::{Priority, Task, TaskSummary};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+const TASK_COUNT: i64 = 5_000;
+
+fn sample_tasks() -> Vec<Task> {
+    let monday = NaiveDate::from_ymd_opt(2025, 7, 7).unwrap();
+    (0..TASK_COUNT)
+        .map(|id| Task {
+            id,
+            client_id: id % 20,
+            client_name: format!("Client {}", id % 20),
+            description: format!("Task number {id} with a reasonably long description"),
+            task_date: monday + chrono::Duration::days(id % 7),
+            client_color: "#1f77b4".parse().unwrap(),
+            client_text_color: "#000000".to_string(),
+            client_background_color: "bg-blue-100".to_string(),
+            created_at: Utc::now(),
+            deleted_at: None,
+            updated_at: Utc::now(),
+            priority: Some(Priority::ALL[id as usize % 4]),
+            external_source: Some("todoist".to_string()),
+            external_id: Some(format!("ext-{id}")),
+            energy: None,
+            estimate_minutes: Some(30),
+            context: None,
+            pin_to_date: false,
+            rollover_target: None,
+            rollover_count: 0,
+            stale_since: None,
+            slots_total: 0,
+            slots_done: 0,
+            deferred: false,
+            display: Default::default(),
+        })
+        .collect()
+}
+
+fn bench_serialization(c: &mut Criterion) {
+    let tasks = sample_tasks();
+    let summaries: Vec<TaskSummary> = tasks.iter().cloned().map(TaskSummary::from).collect();
+
+    c.bench_function("serialize full tasks", |b| {
+        b.iter(|| serde_json::to_vec(black_box(&tasks)).unwrap())
+    });
+    c.bench_function("serialize task summaries", |b| {
+        b.iter(|| serde_json::to_vec(black_box(&summaries)).unwrap())
+    });
+    c.bench_function("convert tasks to summaries", |b| {
+        b.iter_batched(
+            || tasks.clone(),
+            |tasks| tasks.into_iter().map(TaskSummary::from).collect::<Vec<_>>(),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, bench_serialization);
+criterion_main!(benches);
@@ -4,6 +4,9 @@
 // See the LICENSE file in the project root for the full license text.
 use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
 
 #[allow(clippy::doc_overindented_list_items)]
 /// Represents a task within the system.
@@ -13,54 +16,1691 @@ use serde::{Deserialize, Serialize};
 /// - `Debug`: Enables displaying the structure for debugging (e.g., `println!("{:?}", task)`).
 /// - `Clone`: Allows creating copies of the object.
 /// - `sqlx::FromRow`: Allows `sqlx` to create a `Task` instance directly
-///    from a database result row.
-#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+///    from a database result row (with the `backend` feature only).
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
 pub struct Task {
-    #[sqlx(rename = "id")]
+    #[cfg_attr(feature = "backend", sqlx(rename = "id"))]
     pub id: i64,
 
-    #[sqlx(rename = "client_name")]
+    // Reference to the `clients` table. `client_name` and `client_color`
+    // are joined from there and cannot be changed through the task.
+    #[cfg_attr(feature = "backend", sqlx(rename = "client_id"))]
+    pub client_id: i64,
+
+    #[cfg_attr(feature = "backend", sqlx(rename = "client_name"))]
     pub client_name: String,
 
-    #[sqlx(rename = "description")]
+    #[cfg_attr(feature = "backend", sqlx(rename = "description"))]
     pub description: String,
 
     // We use NaiveDate because we are only interested in the day,
     // without a timezone.
-    #[sqlx(rename = "task_date")]
+    #[cfg_attr(feature = "backend", sqlx(rename = "task_date"))]
     pub task_date: NaiveDate,
 
-    #[sqlx(rename = "client_color")]
-    pub client_color: String,
+    #[cfg_attr(feature = "backend", sqlx(rename = "client_color"))]
+    pub client_color: HexColor,
 
-    #[sqlx(rename = "created_at")]
+    #[cfg_attr(feature = "backend", sqlx(rename = "created_at"))]
     pub created_at: DateTime<Utc>,
 
-    #[sqlx(rename = "deleted_at")]
+    #[cfg_attr(feature = "backend", sqlx(rename = "deleted_at"))]
     pub deleted_at: Option<DateTime<Utc>>,
 
-    #[sqlx(rename = "priority")]
-    pub priority: Option<i32>, // (e.g., 1 = high, lower number = higher priority)
+    // When the task was last written. Sent back in `If-Match`, it makes a
+    // change fail with 412 if another one came first.
+    #[cfg_attr(feature = "backend", sqlx(rename = "updated_at"))]
+    #[serde(default)]
+    pub updated_at: DateTime<Utc>,
+
+    #[cfg_attr(feature = "backend", sqlx(rename = "priority"))]
+    pub priority: Option<Priority>,
+
+    // Origin of an imported task (e.g., "csv", "todoist", "github") and its
+    // identifier in that system. Both are `None` for tasks created by hand.
+    #[cfg_attr(feature = "backend", sqlx(rename = "external_source"))]
+    pub external_source: Option<String>,
+
+    #[cfg_attr(feature = "backend", sqlx(rename = "external_id"))]
+    pub external_id: Option<String>,
+
+    // Kind of attention the task needs, and how long it should take.
+    #[cfg_attr(feature = "backend", sqlx(rename = "energy"))]
+    #[serde(default)]
+    pub energy: Option<Energy>,
+
+    #[cfg_attr(feature = "backend", sqlx(rename = "estimate_minutes"))]
+    #[serde(default)]
+    pub estimate_minutes: Option<i32>,
+
+    // GTD-style context where the task can be done, e.g. "@office".
+    #[cfg_attr(feature = "backend", sqlx(rename = "context"))]
+    #[serde(default)]
+    pub context: Option<String>,
+
+    // Tasks tied to their day, such as a meeting, are not rolled over.
+    #[cfg_attr(feature = "backend", sqlx(rename = "pin_to_date"))]
+    #[serde(default)]
+    pub pin_to_date: bool,
+
+    // Day the task rolls over to, in place of the one of the rollover
+    // policy. `None` follows the policy.
+    #[cfg_attr(feature = "backend", sqlx(rename = "rollover_target"))]
+    #[serde(default)]
+    pub rollover_target: Option<RolloverTarget>,
+
+    // Number of times the task was pushed to a later day, and when it was
+    // found postponed too often and escalated, if it was.
+    #[cfg_attr(feature = "backend", sqlx(rename = "rollover_count"))]
+    #[serde(default)]
+    pub rollover_count: i64,
+
+    #[cfg_attr(feature = "backend", sqlx(rename = "stale_since"))]
+    #[serde(default)]
+    pub stale_since: Option<DateTime<Utc>>,
+
+    // Readable text color (black or white) on `client_color`, and a lighter
+    // variant of it for backgrounds. Computed by the server, not stored.
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub client_text_color: String,
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub client_background_color: String,
+
+    // Number of work slots the task is split into across days, and how many
+    // of them are completed. Both are 0 for single-day tasks.
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub slots_total: i64,
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub slots_done: i64,
+
+    // Set in list responses when the task is hidden by the focus mode of its
+    // day. Computed by the server, not stored.
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub deferred: bool,
+
+    // How the task should be emphasized. Computed by the server, not stored.
+    #[cfg_attr(feature = "backend", sqlx(skip))]
+    #[serde(default)]
+    pub display: DisplayHints,
+}
+
+/// Lightweight view of a task with only the fields a board displays.
+/// Used by list-heavy endpoints such as the weekly board, where the full
+/// `Task` (client ID, timestamps, import references) is dead weight.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct TaskSummary {
+    pub id: i64,
+    pub client_name: String,
+    pub client_color: HexColor,
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub client_text_color: String,
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub client_background_color: String,
+    pub description: String,
+    pub task_date: NaiveDate,
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    pub context: Option<String>,
+    // The open slot shown on `task_date`, for tasks split across days.
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub slot_id: Option<i64>,
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub slots_total: i64,
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub slots_done: i64,
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub deferred: bool,
+    #[cfg_attr(feature = "backend", sqlx(skip))]
+    #[serde(default)]
+    pub display: DisplayHints,
+}
+
+impl From<Task> for TaskSummary {
+    /// Moves the displayed fields out of the task, without cloning any string.
+    fn from(task: Task) -> Self {
+        TaskSummary {
+            id: task.id,
+            client_name: task.client_name,
+            client_color: task.client_color,
+            client_text_color: task.client_text_color,
+            client_background_color: task.client_background_color,
+            description: task.description,
+            task_date: task.task_date,
+            priority: task.priority,
+            context: task.context,
+            slot_id: None,
+            slots_total: task.slots_total,
+            slots_done: task.slots_done,
+            deferred: task.deferred,
+            display: task.display,
+        }
+    }
+}
+
+/// Structure used to capture a task quickly, e.g. from a global hotkey.
+/// Only the description is required, the rest comes from the capture defaults.
+#[derive(Serialize, Deserialize, Debug, Default, ToSchema)]
+pub struct CapturePayload {
+    pub description: String,
+    #[serde(default)]
+    pub client_name: Option<String>,
+    #[serde(default)]
+    pub priority: Option<Priority>,
+}
+
+/// Display hints of a task, so that every frontend emphasizes tasks the
+/// same way instead of each one reimplementing the rules.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, ToSchema)]
+pub struct DisplayHints {
+    /// Client color shaded by priority: the full color for the highest
+    /// priorities, lighter ones for lower or no priority.
+    pub color: String,
+    /// Opacity to draw the task with, between 0 and 1. Done tasks are faded.
+    pub opacity: f64,
+    /// The task is still open but scheduled before today.
+    pub overdue: bool,
 }
 
 /// Structure used to receive task creation data from the API.
 /// It's a good practice to separate database models (`Task`)
 /// from API models (`CreateTaskPayload`), as they may have different fields.
 /// Here, `task_date` is optional.
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default, ToSchema)]
 pub struct CreateTaskPayload {
     pub client_name: String,
     pub description: String,
     // The day is optional. If not provided,
     // we'll use the current day on the server-side.
     pub task_date: Option<NaiveDate>,
-    pub priority: Option<i32>,
+    pub priority: Option<Priority>,
+    // When both are set, the task is upserted on this pair instead of being
+    // inserted again, so re-running an import updates the existing row.
+    pub external_source: Option<String>,
+    pub external_id: Option<String>,
+    #[serde(default)]
+    pub energy: Option<Energy>,
+    // Expected duration, used to suggest tasks fitting a free slot.
+    #[serde(default)]
+    pub estimate_minutes: Option<i32>,
+    // Such as "@home" or "errands"; normalized to lowercase with a leading "@".
+    #[serde(default)]
+    pub context: Option<String>,
+    // Keeps the task on its day instead of rolling it over.
+    #[serde(default)]
+    pub pin_to_date: bool,
+    // Rolls the task over to this day instead of following the policy.
+    #[serde(default)]
+    pub rollover_target: Option<RolloverTarget>,
+}
+
+/// How urgent a task is. Sent as its level, from 1 (critical) to 4 (low),
+/// so that more urgent tasks sort first; names such as `"high"` are
+/// accepted too. Anything else is refused when a request is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Critical = 1,
+    High = 2,
+    Medium = 3,
+    Low = 4,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 4] = [
+        Priority::Critical,
+        Priority::High,
+        Priority::Medium,
+        Priority::Low,
+    ];
+
+    /// Level of the priority, 1 for the most urgent.
+    pub fn level(self) -> i32 {
+        self as i32
+    }
+
+    pub fn from_level(level: i64) -> Option<Self> {
+        Priority::ALL
+            .into_iter()
+            .find(|priority| i64::from(priority.level()) == level)
+    }
+
+    /// Nearest priority to `level`: critical below 1, low above 4. Reads
+    /// the levels stored before they were checked, when any number went.
+    pub fn clamped(level: i64) -> Self {
+        Priority::from_level(level.clamp(1, 4)).unwrap_or(Priority::Low)
+    }
+
+    /// One level more urgent, critical staying critical.
+    pub fn raised(self) -> Self {
+        Priority::clamped(i64::from(self.level()) - 1)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Priority::Critical => "critical",
+            Priority::High => "high",
+            Priority::Medium => "medium",
+            Priority::Low => "low",
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Reads a level (`2`) or a name (`high`), ignoring case.
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let priority = match s.parse::<i64>() {
+            Ok(level) => Priority::from_level(level),
+            Err(_) => Priority::ALL
+                .into_iter()
+                .find(|priority| priority.as_str().eq_ignore_ascii_case(s)),
+        };
+        priority.ok_or_else(|| format!("invalid priority '{s}', expected 1 (critical) to 4 (low)"))
+    }
+}
+
+impl Serialize for Priority {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i32(self.level())
+    }
+}
+
+impl<'de> Deserialize<'de> for Priority {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PriorityVisitor;
+
+        impl serde::de::Visitor<'_> for PriorityVisitor {
+            type Value = Priority;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a priority from 1 (critical) to 4 (low), or its name")
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, level: i64) -> Result<Priority, E> {
+                Priority::from_level(level).ok_or_else(|| {
+                    E::custom(format!(
+                        "invalid priority {level}, expected 1 (critical) to 4 (low)"
+                    ))
+                })
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, level: u64) -> Result<Priority, E> {
+                self.visit_i64(i64::try_from(level).unwrap_or(i64::MAX))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Priority, E> {
+                s.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(PriorityVisitor)
+    }
+}
+
+/// Reads the priority of a full export made before priorities were
+/// checked, clamped into range rather than refused.
+fn lenient_priority<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Priority>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Level(i64),
+        Priority(Priority),
+    }
+    Ok(
+        Option::<Stored>::deserialize(deserializer)?.map(|stored| match stored {
+            Stored::Level(level) => Priority::clamped(level),
+            Stored::Priority(priority) => priority,
+        }),
+    )
+}
+
+/// Stored as its level.
+#[cfg(feature = "backend")]
+impl sqlx::Type<sqlx::Sqlite> for Priority {
+    fn type_info() -> sqlx::sqlite::SqliteTypeInfo {
+        <i32 as sqlx::Type<sqlx::Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &sqlx::sqlite::SqliteTypeInfo) -> bool {
+        <i64 as sqlx::Type<sqlx::Sqlite>>::compatible(ty)
+    }
+}
+
+#[cfg(feature = "backend")]
+impl<'q> sqlx::Encode<'q, sqlx::Sqlite> for Priority {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <sqlx::Sqlite as sqlx::Database>::ArgumentBuffer<'q>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        <i32 as sqlx::Encode<'q, sqlx::Sqlite>>::encode(self.level(), buf)
+    }
+}
+
+/// Levels out of range, left by older releases, are clamped.
+#[cfg(feature = "backend")]
+impl<'r> sqlx::Decode<'r, sqlx::Sqlite> for Priority {
+    fn decode(
+        value: <sqlx::Sqlite as sqlx::Database>::ValueRef<'r>,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        let level = <i64 as sqlx::Decode<'r, sqlx::Sqlite>>::decode(value)?;
+        Ok(Priority::clamped(level))
+    }
+}
+
+impl utoipa::PartialSchema for Priority {
+    fn schema() -> utoipa::openapi::RefOr<utoipa::openapi::schema::Schema> {
+        utoipa::openapi::ObjectBuilder::new()
+            .schema_type(utoipa::openapi::schema::Type::Integer)
+            .minimum(Some(1))
+            .maximum(Some(4))
+            .description(Some(
+                "Priority from 1 (critical) to 4 (low); `critical`, `high`, `medium` and `low` are accepted too",
+            ))
+            .into()
+    }
+}
+
+impl ToSchema for Priority {}
+
+/// The level of attention a task requires.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "backend", sqlx(rename_all = "lowercase"))]
+pub enum Energy {
+    /// Focused, uninterrupted work.
+    Deep,
+    /// Quick or low-effort work, e.g. between two meetings.
+    Shallow,
+}
+
+/// Day a task rolls over to, chosen per task in place of the rollover
+/// policy of the server.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[serde(rename_all = "kebab-case")]
+#[cfg_attr(feature = "backend", sqlx(rename_all = "kebab-case"))]
+pub enum RolloverTarget {
+    /// The next day, weekends included.
+    NextDay,
+    /// The next weekday that is not a holiday.
+    NextBusinessDay,
+    /// The next day of the week named, e.g. "monday" for a weekly meeting.
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+    /// The Friday of the week, or of the next one from Friday on.
+    EndOfWeek,
+}
+
+/// Body of `PUT /api/tasks/{id}/rollover-target`.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RolloverTargetPayload {
+    pub rollover_target: RolloverTarget,
+    /// `updated_at` of the task as last read, in place of `If-Match`.
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// A set of colors that new clients are assigned from.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Palette {
+    /// The original 20-color palette.
+    #[default]
+    Default,
+    /// Colors that stay distinguishable with the common forms of color blindness.
+    Colorblind,
+    /// Strong, saturated colors for low-vision users and bright screens.
+    HighContrast,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 3] = [Palette::Default, Palette::Colorblind, Palette::HighContrast];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Palette::Default => "default",
+            Palette::Colorblind => "colorblind",
+            Palette::HighContrast => "high-contrast",
+        }
+    }
+}
+
+impl std::str::FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Palette::ALL
+            .into_iter()
+            .find(|palette| palette.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("unknown palette '{s}'"))
+    }
+}
+
+/// The active palette and the ones that can be chosen instead.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct PaletteSettings {
+    pub palette: Palette,
+    /// Colors of the active palette, in assignment order.
+    pub colors: Vec<HexColor>,
+    pub available: Vec<Palette>,
+}
+
+/// The timezone whose midnight starts a new day, as configured.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct TimezoneSettings {
+    /// IANA name, e.g. `Australia/Sydney`.
+    pub timezone: String,
+    /// Current offset from UTC, e.g. `+10:00`.
+    pub utc_offset: String,
+    /// Current date in that timezone.
+    pub today: NaiveDate,
+}
+
+/// Payload for `PUT /api/settings/palette`.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct SetPalettePayload {
+    pub palette: Palette,
+}
+
+/// A color as a lowercase `#rrggbb` hex value. Read from `#rrggbb` or
+/// `#rgb` in any case; anything else is refused when a request is read, so
+/// that no malformed color is stored.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HexColor(String);
+
+impl HexColor {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Red, green and blue channels of the color.
+    pub fn rgb(&self) -> [u8; 3] {
+        let channel = |i: usize| u8::from_str_radix(&self.0[i..i + 2], 16).unwrap_or_default();
+        [channel(1), channel(3), channel(5)]
+    }
+}
+
+impl fmt::Display for HexColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for HexColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid color '{s}', expected a hex value such as #1f77b4");
+        let digits = s.trim().strip_prefix('#').ok_or_else(invalid)?;
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let digits = match digits.len() {
+            3 => digits.chars().flat_map(|c| [c, c]).collect(),
+            6 => digits.to_string(),
+            _ => return Err(invalid()),
+        };
+        Ok(HexColor(format!("#{}", digits.to_ascii_lowercase())))
+    }
+}
+
+impl std::ops::Deref for HexColor {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for HexColor {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for HexColor {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for HexColor {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl Serialize for HexColor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
 }
 
+impl<'de> Deserialize<'de> for HexColor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Stored as its text.
+#[cfg(feature = "backend")]
+impl sqlx::Type<sqlx::Sqlite> for HexColor {
+    fn type_info() -> sqlx::sqlite::SqliteTypeInfo {
+        <String as sqlx::Type<sqlx::Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &sqlx::sqlite::SqliteTypeInfo) -> bool {
+        <String as sqlx::Type<sqlx::Sqlite>>::compatible(ty)
+    }
+}
+
+#[cfg(feature = "backend")]
+impl<'q> sqlx::Encode<'q, sqlx::Sqlite> for HexColor {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <sqlx::Sqlite as sqlx::Database>::ArgumentBuffer<'q>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        <String as sqlx::Encode<'q, sqlx::Sqlite>>::encode(self.0.clone(), buf)
+    }
+}
+
+#[cfg(feature = "backend")]
+impl<'r> sqlx::Decode<'r, sqlx::Sqlite> for HexColor {
+    fn decode(
+        value: <sqlx::Sqlite as sqlx::Database>::ValueRef<'r>,
+    ) -> Result<Self, sqlx::error::BoxDynError> {
+        let color = <&str as sqlx::Decode<'r, sqlx::Sqlite>>::decode(value)?;
+        Ok(color.parse()?)
+    }
+}
+
+impl utoipa::PartialSchema for HexColor {
+    fn schema() -> utoipa::openapi::RefOr<utoipa::openapi::schema::Schema> {
+        utoipa::openapi::ObjectBuilder::new()
+            .schema_type(utoipa::openapi::schema::Type::String)
+            .pattern(Some("^#[0-9a-f]{6}$"))
+            .description(Some(
+                "Hex color such as `#1f77b4`; `#rgb` and uppercase digits are accepted in requests",
+            ))
+            .into()
+    }
+}
+
+impl ToSchema for HexColor {}
+
 /// Represents a client and their associated color.
-/// For now, this is a simple structure, but it could be extended.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Clients are stored in their own table and are the single source of truth
+/// for names and colors; tasks reference them by `id`.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
 pub struct Client {
+    pub id: i64,
     pub name: String,
-    pub color: String,
+    pub color: HexColor,
+    // Readable text color on `color`, and a lighter variant of `color` for
+    // backgrounds. Computed by the server, not stored.
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub text_color: String,
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub background_color: String,
+    // Archived clients are hidden from pickers and cannot receive new tasks,
+    // but their historical tasks are kept.
+    #[serde(default)]
+    pub archived_at: Option<DateTime<Utc>>,
+    // Given to new tasks of the client created without their own.
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub default_priority: Option<Priority>,
+    #[cfg_attr(feature = "backend", sqlx(default))]
+    #[serde(default)]
+    pub default_context: Option<String>,
+}
+
+/// Structure used to create a client explicitly. Its color is assigned
+/// automatically from the palette.
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct CreateClientPayload {
+    pub name: String,
+}
+
+/// Why the color of an existing client changed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "backend", sqlx(rename_all = "lowercase"))]
+pub enum ColorChangeReason {
+    /// Set by hand. Switching the palette keeps the colors of existing
+    /// clients, so this is the only reason for now.
+    Override,
+}
+
+/// A change of the color of a client. Frontends caching colors list the
+/// changes made since the last one they saw to refresh stale colors.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct ClientColorChange {
+    pub id: i64,
+    pub client_id: i64,
+    pub client_name: String,
+    pub old_color: HexColor,
+    pub new_color: HexColor,
+    pub reason: ColorChangeReason,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Structure used to override the color of a client, as a `#rrggbb` or
+/// `#rgb` hex value. Existing tasks show the new color right away.
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct SetClientColorPayload {
+    pub color: HexColor,
+}
+
+/// Structure used to set the defaults of the tasks of a client: new tasks
+/// created without a priority or context take these. Unset fields clear
+/// the default.
+#[derive(Deserialize, Debug, Default, ToSchema)]
+pub struct SetClientDefaultsPayload {
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    pub context: Option<String>,
+}
+
+/// Structure used to update a client. Tasks and aliases reference the client
+/// by `id`, so a rename is reflected on them immediately.
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct UpdateClientPayload {
+    pub name: String,
+}
+
+/// A row staged by a two-phase import, waiting to be reviewed and committed.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct ImportRow {
+    pub row_index: i64,
+    pub client_name: String,
+    pub description: String,
+    pub task_date: Option<NaiveDate>,
+    pub priority: Option<Priority>,
+    pub external_source: Option<String>,
+    pub external_id: Option<String>,
+}
+
+/// Validation error of one item of a bulk request, by its index in the request.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct ItemError {
+    pub index: usize,
+    pub error: String,
+}
+
+/// A staged row whose external reference already matches a live task.
+/// Committing the import will update that task instead of creating a new one.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ImportConflict {
+    pub row_index: i64,
+    pub task_id: i64,
+    pub existing_description: String,
+}
+
+/// What a staged import would do once committed.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ImportPreview {
+    pub import_id: i64,
+    pub created_at: DateTime<Utc>,
+    pub committed_at: Option<DateTime<Utc>>,
+    pub rows: Vec<ImportRow>,
+    pub conflicts: Vec<ImportConflict>,
+    // Client names that do not appear on any existing task yet.
+    pub new_clients: Vec<String>,
+}
+
+/// An alternative spelling that resolves to a canonical client name.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct ClientAlias {
+    pub alias: String,
+    pub client_name: String,
+}
+
+/// Structure used to register a new alias for a client.
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct AddClientAliasPayload {
+    pub alias: String,
+}
+
+/// How a client name would be interpreted when creating or importing tasks.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ClientNameResolution {
+    pub name: String,
+    // The canonical client this name maps to, if it is a known alias.
+    pub canonical: Option<String>,
+    // A known client with a very similar name, when the name is not known at all.
+    pub suggestion: Option<String>,
+}
+
+/// Number of tasks with a given priority (`None` for tasks without one).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct PriorityCount {
+    pub priority: Option<Priority>,
+    pub count: i64,
+}
+
+/// Activity of a client over a date range, e.g. for weekly billing.
+/// A task counts as completed once it has been marked done (`deleted_at`).
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ClientStats {
+    pub client_name: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub total_tasks: i64,
+    pub completed_tasks: i64,
+    pub open_tasks: i64,
+    // Share of completed tasks, between 0 and 1 (0 when there are no tasks).
+    pub completion_rate: f64,
+    // Total number of times tasks were pushed to the next day.
+    pub rollover_count: i64,
+    // Number of distinct tasks rolled over at least once.
+    pub rolled_over_tasks: i64,
+    pub priority_distribution: Vec<PriorityCount>,
+}
+
+/// What a weekly goal counts: tasks done, or the estimated hours of the
+/// tasks done.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "backend", sqlx(rename_all = "lowercase"))]
+pub enum GoalUnit {
+    Tasks,
+    Hours,
+}
+
+/// Amount of work to complete each week, as set with
+/// `PUT /api/settings/goal`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct WeeklyGoal {
+    pub unit: GoalUnit,
+    pub target: f64,
+    pub set_at: DateTime<Utc>,
+}
+
+/// Payload for `PUT /api/settings/goal`.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct SetWeeklyGoalPayload {
+    pub unit: GoalUnit,
+    pub target: f64,
+}
+
+/// Progress of the current week towards the weekly goal, as returned by
+/// `GET /api/stats/goal`. Amounts are in the unit of the goal.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct GoalProgress {
+    pub goal: WeeklyGoal,
+    pub week_start: NaiveDate,
+    pub week_end: NaiveDate,
+    pub done: f64,
+    pub remaining: f64,
+    // Share of the goal reached, 1 once met (may go past it).
+    pub progress: f64,
+    // Share of the week gone by, between 0 and 1.
+    pub week_elapsed: f64,
+    // What would be done by now at an even pace, and how far ahead of it
+    // the week is (negative when behind).
+    pub expected: f64,
+    pub ahead_by: f64,
+    // What the week would end on at the pace so far.
+    pub projected: f64,
+    pub on_track: bool,
+}
+
+/// One line of a full data archive, as downloaded chunk by chunk from
+/// `GET /api/export/archive`. Clients come first, then tasks, both by ID.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ArchiveRecord {
+    Client(Client),
+    Task(Box<Task>),
+}
+
+/// A day in focus mode: only its `top_n` highest priority tasks are listed.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct FocusDay {
+    pub day: NaiveDate,
+    pub top_n: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Priority of a task rewritten by a normalization.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct PriorityChange {
+    pub task_id: i64,
+    pub description: String,
+    pub old_priority: Priority,
+    pub new_priority: Priority,
+}
+
+/// Outcome of `POST /api/days/{date}/normalize-priorities`: the open tasks
+/// of the day with a priority, and those whose priority was rewritten.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct PriorityNormalization {
+    pub day: NaiveDate,
+    pub tasks: usize,
+    pub changes: Vec<PriorityChange>,
+}
+
+/// Tasks a rollover would move, as returned by `GET /api/tasks/rollover/preview`.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RolloverPreview {
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub tasks: Vec<Task>,
+    /// Day each task with its own `rollover_target` moves to, by task ID;
+    /// the others move to `to_date`.
+    #[serde(default)]
+    pub task_dates: std::collections::BTreeMap<i64, NaiveDate>,
+}
+
+/// What started a rollover.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "backend", sqlx(rename_all = "lowercase"))]
+pub enum RolloverTrigger {
+    /// The daily background check of the server.
+    Automatic,
+    /// A call to `PATCH /api/tasks/rollover`.
+    Manual,
+}
+
+/// A past rollover of the open tasks from one day to another.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct RolloverRun {
+    pub id: i64,
+    pub ran_at: DateTime<Utc>,
+    pub triggered_by: RolloverTrigger,
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub task_count: i64,
+}
+
+/// An open task that keeps being pushed to the next day.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct PostponedTask {
+    pub id: i64,
+    pub client_name: String,
+    pub description: String,
+    pub task_date: NaiveDate,
+    pub rollover_count: i64,
+}
+
+/// Recent rollovers, and the open tasks rolled over the most.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RolloverHistory {
+    // Most recent first.
+    pub runs: Vec<RolloverRun>,
+    // Most postponed first.
+    pub postponed_tasks: Vec<PostponedTask>,
+}
+
+/// A finished run of a background job, retries included.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct JobRun {
+    pub id: i64,
+    pub job: String,
+    pub scheduled_for: DateTime<Utc>,
+    // Instance that claimed and ran it.
+    pub instance: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub attempts: i64,
+    pub succeeded: bool,
+    // Summary of the work done, or the error of the last attempt.
+    pub message: String,
+}
+
+/// A background job, whether it runs on the instance answering, when it was
+/// paused for all instances if it is, and its latest runs on any instance,
+/// most recent first.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct JobStatus {
+    pub job: String,
+    pub enabled: bool,
+    pub paused_at: Option<DateTime<Utc>>,
+    pub runs: Vec<JobRun>,
+}
+
+/// Outcome of testing a notification channel with
+/// `POST /api/notifications/test`, with the reason when it failed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct ChannelCheck {
+    pub channel: String,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+/// A day off, skipped by the rollover when holidays are honored.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct Holiday {
+    pub day: NaiveDate,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Structure used to add a holiday.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AddHolidayPayload {
+    pub day: NaiveDate,
+    pub name: String,
+}
+
+/// A chunk of work on a task, scheduled on a given day. A task with slots
+/// stays one logical task and is completed along with its last slot.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct TaskSlot {
+    pub id: i64,
+    pub task_id: i64,
+    pub slot_date: NaiveDate,
+    pub estimate_minutes: Option<i32>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Structure used to move a task to another day.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct MoveTaskPayload {
+    pub task_date: NaiveDate,
+    /// `updated_at` of the task as last read, in place of `If-Match`.
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// One operation of `POST /api/batch`, e.g.
+/// `{"op": "move", "task_id": 4, "task_date": "2025-07-15"}`. An
+/// `updated_at` given with a task makes the batch fail with 412 if the task
+/// was changed since.
+#[derive(Deserialize, Debug, ToSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOperation {
+    /// Creates a task, like `POST /api/tasks`.
+    Create(CreateTaskPayload),
+    /// Moves an open task to another day, like `PATCH /api/tasks/{id}/move`.
+    Move {
+        task_id: i64,
+        task_date: NaiveDate,
+        #[serde(default)]
+        updated_at: Option<DateTime<Utc>>,
+    },
+    /// Marks an open task done.
+    Complete {
+        task_id: i64,
+        #[serde(default)]
+        updated_at: Option<DateTime<Utc>>,
+    },
+    /// Marks an open task done, like `DELETE /api/tasks/{id}`.
+    Delete {
+        task_id: i64,
+        #[serde(default)]
+        updated_at: Option<DateTime<Utc>>,
+    },
+}
+
+/// Result of one operation of `POST /api/batch`: the task as the operation
+/// left it.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BatchResult {
+    pub index: usize,
+    pub task: Task,
+}
+
+/// Which tasks a filter matches, by completion.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatusFilter {
+    /// Tasks still to do.
+    #[default]
+    Open,
+    /// Tasks already done.
+    Done,
+    /// Both open and done tasks.
+    All,
+}
+
+/// Tasks selected by a listing or a bulk operation, read from query strings
+/// (`?client_name=Acme&from=2025-07-14&status=all`) as well as from bodies.
+/// Unset fields match every task.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TaskFilter {
+    /// Client of the tasks, or one of its aliases.
+    pub client_name: Option<String>,
+    /// Day of the tasks, the same as `from` and `to` both set to it.
+    pub task_date: Option<NaiveDate>,
+    /// First day of the tasks, inclusive.
+    pub from: Option<NaiveDate>,
+    /// Last day of the tasks, inclusive.
+    pub to: Option<NaiveDate>,
+    /// `open` tasks by default, `done` or `all`.
+    #[serde(default)]
+    pub status: TaskStatusFilter,
+}
+
+impl TaskFilter {
+    /// First and last day of the tasks selected, either end being open when
+    /// unset. Fails on a day combined with a range, or on a range ending
+    /// before it starts.
+    pub fn dates(&self) -> Result<(Option<NaiveDate>, Option<NaiveDate>), String> {
+        match (self.task_date, self.from, self.to) {
+            (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+                Err("task_date cannot be combined with from or to".to_string())
+            }
+            (Some(day), None, None) => Ok((Some(day), Some(day))),
+            (None, Some(from), Some(to)) if from > to => {
+                Err(format!("from ({from}) is after to ({to})"))
+            }
+            (None, from, to) => Ok((from, to)),
+        }
+    }
+
+    /// Whether the filter names a client or a day, as bulk operations
+    /// require so that they never apply to every task by mistake.
+    pub fn is_narrowed(&self) -> bool {
+        self.client_name.is_some()
+            || self.task_date.is_some()
+            || self.from.is_some()
+            || self.to.is_some()
+    }
+
+    /// Query string selecting the same tasks, e.g. `client_name=Acme&status=open`.
+    #[cfg(feature = "qs")]
+    pub fn to_query_string(&self) -> Result<String, serde_qs::Error> {
+        serde_qs::to_string(self)
+    }
+
+    /// Filter read from a query string, as the server reads it.
+    #[cfg(feature = "qs")]
+    pub fn from_query_string(query: &str) -> Result<Self, serde_qs::Error> {
+        serde_qs::from_str(query)
+    }
+}
+
+/// Fields tasks can be sorted on. Anything else is refused, so a sort never
+/// reaches the database as free text.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskSortField {
+    Id,
+    ClientName,
+    Description,
+    TaskDate,
+    Priority,
+    CreatedAt,
+}
+
+impl TaskSortField {
+    pub const ALL: [TaskSortField; 6] = [
+        TaskSortField::Id,
+        TaskSortField::ClientName,
+        TaskSortField::Description,
+        TaskSortField::TaskDate,
+        TaskSortField::Priority,
+        TaskSortField::CreatedAt,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskSortField::Id => "id",
+            TaskSortField::ClientName => "client_name",
+            TaskSortField::Description => "description",
+            TaskSortField::TaskDate => "task_date",
+            TaskSortField::Priority => "priority",
+            TaskSortField::CreatedAt => "created_at",
+        }
+    }
+}
+
+/// One field of a sort, and its direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    pub field: TaskSortField,
+    pub descending: bool,
+}
+
+/// Ordering of a task listing, written as in `?sort=priority,-created_at`:
+/// fields by decreasing precedence, a leading `-` sorting in descending
+/// order. Empty, the listing keeps its default order (by day, then
+/// priority). Tasks without a priority always go last.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SortSpec(pub Vec<SortKey>);
+
+impl fmt::Display for SortSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, key) in self.0.iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            let direction = if key.descending { "-" } else { "" };
+            write!(f, "{separator}{direction}{}", key.field.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+/// Fails with the name of the first unknown field.
+impl FromStr for SortSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(|field| {
+                let (name, descending) = match field.strip_prefix('-') {
+                    Some(name) => (name, true),
+                    None => (field.strip_prefix('+').unwrap_or(field), false),
+                };
+                TaskSortField::ALL
+                    .into_iter()
+                    .find(|known| known.as_str() == name)
+                    .map(|field| SortKey { field, descending })
+                    .ok_or_else(|| name.to_string())
+            })
+            .collect::<Result<_, _>>()
+            .map(SortSpec)
+    }
+}
+
+impl Serialize for SortSpec {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SortSpec {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(|field| {
+            serde::de::Error::custom(format!("cannot sort on unknown field '{field}'"))
+        })
+    }
+}
+
+/// One page of a listing. Pass `next_cursor` back as `cursor` for the
+/// following page; it is unset on the last one. Cursors are opaque.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Whether pages follow this one.
+    pub fn has_more(&self) -> bool {
+        self.next_cursor.is_some()
+    }
+}
+
+/// Structure used to move every task matching a filter to another day.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct BulkMoveTasksPayload {
+    pub filter: TaskFilter,
+    pub target_date: NaiveDate,
+}
+
+/// Tasks moved by a bulk move, by ID.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct MovedTasks {
+    pub task_ids: Vec<i64>,
+}
+
+/// Structure used to schedule a slot of work on a task.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct CreateTaskSlotPayload {
+    pub slot_date: NaiveDate,
+    #[serde(default)]
+    pub estimate_minutes: Option<i32>,
+}
+
+/// Everything needed to plan next week, returned by `GET /api/plan/next-week`.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct NextWeekPlan {
+    pub week_start: NaiveDate,
+    pub week_end: NaiveDate,
+    // Open tasks dated before next week, to reschedule or let roll over.
+    pub carried_over: Vec<Task>,
+    // Tasks that came back most weeks recently and are not planned yet.
+    pub recurring_candidates: Vec<RecurringCandidate>,
+    // Room left on each day of next week.
+    pub capacity: Vec<DayCapacity>,
+}
+
+/// A task done in several of the last weeks, suggested again for next week.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct RecurringCandidate {
+    pub client_name: String,
+    pub description: String,
+    pub priority: Option<Priority>,
+    pub estimate_minutes: Option<i32>,
+    // Number of recent weeks the task appeared in.
+    pub weeks_seen: usize,
+    // Same weekday as its latest occurrence, in next week.
+    pub suggested_date: NaiveDate,
+}
+
+/// Planned work of a day compared to the time available.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct DayCapacity {
+    pub day: NaiveDate,
+    pub capacity_minutes: i64,
+    pub planned_tasks: i64,
+    // Sum of the estimates; tasks without one are not counted.
+    pub planned_minutes: i64,
+}
+
+/// Selections made in the planning flow, applied by `POST /api/plan/next-week`
+/// in a single transaction.
+#[derive(Deserialize, Debug, Default, ToSchema)]
+pub struct PlanSelectionPayload {
+    // Open tasks to move into next week.
+    #[serde(default)]
+    pub carry_over: Vec<CarryOverSelection>,
+    // Tasks to create, e.g. from the recurring candidates. Missing dates
+    // default to the Monday of next week.
+    #[serde(default)]
+    pub new_tasks: Vec<CreateTaskPayload>,
+}
+
+/// An open task moved to a day of next week.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct CarryOverSelection {
+    pub task_id: i64,
+    pub task_date: NaiveDate,
+}
+
+/// Tasks moved and created by the planning flow.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AppliedPlan {
+    pub moved: Vec<Task>,
+    pub created: Vec<Task>,
+}
+
+/// Snapshot of a closed week, kept in the archives.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct WeekArchive {
+    // ISO week, e.g. "2025-W29".
+    pub week: String,
+    pub week_start: NaiveDate,
+    pub week_end: NaiveDate,
+    pub archived_at: DateTime<Utc>,
+    pub tasks: Vec<Task>,
+    pub clients: Vec<WeekClientTotals>,
+    // Written in the language asked for when the archive is read, not stored.
+    #[serde(default)]
+    pub summary: Option<String>,
+}
+
+/// Work done for one client during an archived week.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct WeekClientTotals {
+    pub client_name: String,
+    pub tasks_done: i64,
+    // Sum of the estimates of the done tasks that have one.
+    pub estimate_minutes: i64,
+}
+
+/// What archiving a week found and did. A week is only archived once every
+/// task of it is done or carried over to another week.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct WeekIntegrityReport {
+    pub week: String,
+    pub tasks_done: i64,
+    // Tasks and slots still open on a day of the week, blocking the archive.
+    pub open_task_ids: Vec<i64>,
+    pub open_slot_ids: Vec<i64>,
+    pub archived: bool,
+    // Done tasks removed from the working tables once archived.
+    pub tasks_purged: u64,
+}
+
+/// Structure used to turn on focus mode for a day.
+#[derive(Deserialize, Debug, Default, ToSchema)]
+pub struct SetFocusPayload {
+    // Number of tasks kept visible, 3 when omitted.
+    pub top_n: Option<i64>,
+}
+
+/// Payload for `POST /api/session`, signing a browser in. Not `Debug`, so
+/// that the key cannot end up in the logs.
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct LoginPayload {
+    // One of the `API_KEYS` of the server.
+    pub api_key: String,
+}
+
+/// A browser session, as returned by `POST /api/session` and
+/// `GET /api/session/csrf`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct SessionInfo {
+    pub expires_at: DateTime<Utc>,
+    // Sent back in `X-CSRF-Token` with every request changing state.
+    pub csrf_token: String,
+}
+
+/// Payload for `POST /api/shares`.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct CreateSharePayload {
+    // ISO week, e.g. `2025-W29`.
+    pub week: String,
+    // Lifetime of the link, 7 days when omitted and 90 at most.
+    pub expires_in_hours: Option<i64>,
+}
+
+/// A read-only link to a week, as returned by `POST /api/shares`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct WeekShare {
+    pub week: String,
+    pub token: String,
+    // Path of the shared week, to append to the address of the server.
+    pub path: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A week seen through its share link, as returned by
+/// `GET /api/shared/{token}`: its open tasks, day by day.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct SharedWeek {
+    pub week: String,
+    pub week_start: NaiveDate,
+    pub week_end: NaiveDate,
+    pub expires_at: DateTime<Utc>,
+    pub days: std::collections::BTreeMap<NaiveDate, Vec<TaskSummary>>,
+}
+
+/// Command sent by a client over the live connection of `GET /api/ws`,
+/// e.g. `{"command": "move", "task_id": 4, "task_date": "2025-07-15"}`.
+/// An `updated_at` given with a task makes the command fail if the task was
+/// changed since, as `If-Match` does on the matching route.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum LiveCommand {
+    /// Marks a task done, like `DELETE /api/tasks/{id}`.
+    Complete {
+        task_id: i64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        updated_at: Option<DateTime<Utc>>,
+    },
+    /// Moves a task to another day, like `PUT /api/tasks/{id}/move`.
+    Move {
+        task_id: i64,
+        task_date: NaiveDate,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        updated_at: Option<DateTime<Utc>>,
+    },
+}
+
+/// Event a webhook can subscribe to, named as in its deliveries.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+pub enum WebhookEvent {
+    #[serde(rename = "task.created")]
+    #[cfg_attr(feature = "backend", sqlx(rename = "task.created"))]
+    TaskCreated,
+    #[serde(rename = "task.deleted")]
+    #[cfg_attr(feature = "backend", sqlx(rename = "task.deleted"))]
+    TaskDeleted,
+    #[serde(rename = "rollover.completed")]
+    #[cfg_attr(feature = "backend", sqlx(rename = "rollover.completed"))]
+    RolloverCompleted,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 3] = [
+        WebhookEvent::TaskCreated,
+        WebhookEvent::TaskDeleted,
+        WebhookEvent::RolloverCompleted,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::TaskCreated => "task.created",
+            WebhookEvent::TaskDeleted => "task.deleted",
+            WebhookEvent::RolloverCompleted => "rollover.completed",
+        }
+    }
+}
+
+/// Payload for `POST /api/webhooks`.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct CreateWebhookPayload {
+    // Address the events are posted to, over HTTP or HTTPS.
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+}
+
+/// A URL the server posts events to, as returned by `GET /api/webhooks`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub created_at: DateTime<Utc>,
+    // Key of the `X-Webhook-Signature` of the deliveries. Only returned by
+    // `POST /api/webhooks`: keep it then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+/// Where a webhook delivery stands.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::Type))]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "backend", sqlx(rename_all = "lowercase"))]
+pub enum DeliveryStatus {
+    /// Not sent yet, or to be retried at `next_attempt_at`.
+    Pending,
+    /// Accepted by the receiver with a 2xx response.
+    Delivered,
+    /// Given up on after the last attempt.
+    Failed,
+}
+
+/// An event sent, or to send, to a webhook, as listed by
+/// `GET /api/webhooks/{id}/deliveries`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event: WebhookEvent,
+    pub status: DeliveryStatus,
+    pub attempts: i64,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    // Status code of the last response, if the receiver answered.
+    pub response_status: Option<i64>,
+    // Why the last attempt failed.
+    pub error: Option<String>,
+}
+
+/// Payload of `POST /api/admin/dead-letters`, re-queuing failed webhook
+/// deliveries.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct RequeueDeliveriesPayload {
+    // Deliveries to send again, every failed one when omitted.
+    #[serde(default)]
+    pub delivery_ids: Option<Vec<i64>>,
+}
+
+/// Failed deliveries queued again by `POST /api/admin/dead-letters`, by ID.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct RequeuedDeliveries {
+    pub delivery_ids: Vec<i64>,
+}
+
+/// Version of the documents of `GET /api/export/full`, raised whenever their
+/// format changes in a way older servers could not read.
+pub const FULL_EXPORT_VERSION: u32 = 1;
+
+/// Every client, task and setting, as downloaded from `GET /api/export/full`
+/// and restored with `POST /api/import/full`. Records refer to each other by
+/// name rather than by ID, so that the document loads into any database.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct FullExport {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub clients: Vec<ExportedClient>,
+    pub tasks: Vec<ExportedTask>,
+    #[serde(default)]
+    pub settings: ExportedSettings,
+}
+
+/// A client of a full export, with its color and aliases.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct ExportedClient {
+    pub name: String,
+    pub color: HexColor,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub archived_at: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "lenient_priority")]
+    pub default_priority: Option<Priority>,
+    #[serde(default)]
+    pub default_context: Option<String>,
+    #[cfg_attr(feature = "backend", sqlx(skip))]
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+/// A task of a full export, filed under the name of its client.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct ExportedTask {
+    pub client_name: String,
+    pub description: String,
+    pub task_date: NaiveDate,
+    pub created_at: DateTime<Utc>,
+    // When the task was done, if it was.
+    #[serde(default)]
+    pub done_at: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "lenient_priority")]
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    pub energy: Option<Energy>,
+    #[serde(default)]
+    pub estimate_minutes: Option<i32>,
+    #[serde(default)]
+    pub context: Option<String>,
+    #[serde(default)]
+    pub pin_to_date: bool,
+    #[serde(default)]
+    pub rollover_target: Option<RolloverTarget>,
+    #[serde(default)]
+    pub rollover_count: i64,
+    #[serde(default)]
+    pub stale_since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub external_source: Option<String>,
+    #[serde(default)]
+    pub external_id: Option<String>,
+    #[cfg_attr(feature = "backend", sqlx(skip))]
+    #[serde(default)]
+    pub slots: Vec<ExportedTaskSlot>,
+}
+
+/// A slot of a task of a full export.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[cfg_attr(feature = "backend", derive(sqlx::FromRow))]
+pub struct ExportedTaskSlot {
+    pub slot_date: NaiveDate,
+    #[serde(default)]
+    pub estimate_minutes: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Settings of a full export.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct ExportedSettings {
+    #[serde(default)]
+    pub palette: Palette,
+    #[serde(default)]
+    pub weekly_goal: Option<WeeklyGoal>,
+    #[serde(default)]
+    pub holidays: Vec<Holiday>,
+    #[serde(default)]
+    pub focus_days: Vec<FocusDay>,
+    // Background jobs paused by hand, by name.
+    #[serde(default)]
+    pub paused_jobs: Vec<String>,
+}
+
+/// What `POST /api/import/full` does with the records already in the
+/// database. Clients match by name, holidays and focus days by day, tasks
+/// by external reference, or else by client, description, date and
+/// creation time.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStrategy {
+    /// Existing records are kept, only the missing ones are added.
+    #[default]
+    Skip,
+    /// Existing records are updated with the imported ones.
+    Overwrite,
+    /// Every client, task and setting is removed first, leaving only the
+    /// imported ones.
+    Replace,
+}
+
+/// How many records of a kind an import added, updated or left alone.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+pub struct ImportCounts {
+    pub created: usize,
+    pub updated: usize,
+    pub skipped: usize,
+}
+
+/// Outcome of `POST /api/import/full`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct FullImportSummary {
+    pub strategy: ImportStrategy,
+    pub clients: ImportCounts,
+    pub tasks: ImportCounts,
 }
@@ -0,0 +1,10 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+
+// Migrations are embedded at compile time by `sqlx::migrate!`; rebuild when
+// one is added or changed.
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,77 @@
+-- Schema as of the introduction of migrations.
+-- Statements are idempotent so that databases created before migrations
+-- existed (upgraded by `upgrade_unversioned_schema`) can be baselined.
+-- Later migrations do not need to be.
+
+-- Clients are the single source of truth for names and colors.
+CREATE TABLE IF NOT EXISTS clients (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    color TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    archived_at TIMESTAMP WITH TIME ZONE NULL
+);
+
+CREATE TABLE IF NOT EXISTS tasks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    client_id INTEGER NOT NULL REFERENCES clients (id),
+    description TEXT NOT NULL,
+    task_date DATE NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    deleted_at TIMESTAMP WITH TIME ZONE NULL,
+    priority INTEGER NULL,
+    external_source TEXT NULL,
+    external_id TEXT NULL,
+    -- Number of times a task was pushed to the next day, for statistics.
+    rollover_count INTEGER NOT NULL DEFAULT 0,
+    energy TEXT NULL,
+    estimate_minutes INTEGER NULL,
+    context TEXT NULL
+);
+
+-- SQLite treats NULLs as distinct, so manually created tasks never collide here.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tasks_external_ref ON tasks (external_source, external_id);
+
+-- Alternative spellings of client names, resolved case-insensitively.
+CREATE TABLE IF NOT EXISTS client_aliases (
+    alias TEXT PRIMARY KEY COLLATE NOCASE,
+    client_id INTEGER NOT NULL REFERENCES clients (id)
+);
+
+-- Staging area for two-phase imports: rows are reviewed before reaching `tasks`.
+CREATE TABLE IF NOT EXISTS imports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at TIMESTAMP NOT NULL,
+    committed_at TIMESTAMP WITH TIME ZONE NULL
+);
+
+CREATE TABLE IF NOT EXISTS import_rows (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    import_id INTEGER NOT NULL REFERENCES imports (id) ON DELETE CASCADE,
+    row_index INTEGER NOT NULL,
+    client_name TEXT NOT NULL,
+    description TEXT NOT NULL,
+    task_date DATE NULL,
+    priority INTEGER NULL,
+    external_source TEXT NULL,
+    external_id TEXT NULL
+);
+
+-- Chunks of work of a task spread over several days.
+CREATE TABLE IF NOT EXISTS task_slots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_id INTEGER NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    slot_date DATE NOT NULL,
+    estimate_minutes INTEGER NULL,
+    created_at TIMESTAMP NOT NULL,
+    completed_at TIMESTAMP WITH TIME ZONE NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_slots_task_id ON task_slots (task_id);
+
+-- Days in focus mode, where only the top priority tasks are listed.
+CREATE TABLE IF NOT EXISTS focus_days (
+    day DATE PRIMARY KEY,
+    top_n INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
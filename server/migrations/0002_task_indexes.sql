@@ -0,0 +1,5 @@
+-- Lists of tasks filter on a date range of open tasks (the current week,
+-- today, ...), and client statistics on one client over a date range.
+-- Without these indexes every such query scans the whole table.
+CREATE INDEX idx_tasks_date_deleted ON tasks (task_date, deleted_at);
+CREATE INDEX idx_tasks_client_date ON tasks (client_id, task_date);
@@ -0,0 +1,10 @@
+-- History of client color changes, so that frontends caching colors can
+-- catch up with the ones changed since they last looked.
+CREATE TABLE client_color_changes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    client_id INTEGER NOT NULL REFERENCES clients (id) ON DELETE CASCADE,
+    old_color TEXT NOT NULL,
+    new_color TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    changed_at TIMESTAMP NOT NULL
+);
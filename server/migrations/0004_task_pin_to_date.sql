@@ -0,0 +1,2 @@
+-- Tasks tied to a fixed day (e.g. a meeting) are left alone by the rollover.
+ALTER TABLE tasks ADD COLUMN pin_to_date BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,10 @@
+-- Closed weeks: a snapshot of their done tasks and totals, kept even when
+-- the working rows are purged.
+CREATE TABLE week_archives (
+    week TEXT PRIMARY KEY,
+    week_start DATE NOT NULL,
+    week_end DATE NOT NULL,
+    archived_at TIMESTAMP NOT NULL,
+    -- `WeekArchive` as JSON.
+    snapshot TEXT NOT NULL
+);
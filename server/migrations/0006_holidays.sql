@@ -0,0 +1,6 @@
+-- Days off, skipped by the rollover under the `business-day-holidays` policy.
+CREATE TABLE holidays (
+    day DATE PRIMARY KEY,
+    name TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,9 @@
+-- One row per rollover, automatic or manual, for the history report.
+CREATE TABLE rollover_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ran_at TIMESTAMP NOT NULL,
+    triggered_by TEXT NOT NULL CHECK (triggered_by IN ('automatic', 'manual')),
+    from_date DATE NOT NULL,
+    to_date DATE NOT NULL,
+    task_count INTEGER NOT NULL
+);
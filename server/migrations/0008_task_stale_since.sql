@@ -0,0 +1,2 @@
+-- Set when a task has been rolled over too many times, see STALE_AFTER_ROLLOVERS.
+ALTER TABLE tasks ADD COLUMN stale_since TIMESTAMP;
@@ -0,0 +1,9 @@
+-- One row per occurrence of a scheduled job, inserted by the instance that
+-- runs it, so that replicas sharing the database run each occurrence once.
+CREATE TABLE scheduled_job_claims (
+    job TEXT NOT NULL,
+    slot TIMESTAMP NOT NULL,
+    instance TEXT NOT NULL,
+    claimed_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (job, slot)
+);
@@ -0,0 +1,14 @@
+-- Outcome of each run of a background job, see GET /api/admin/jobs.
+CREATE TABLE job_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    job TEXT NOT NULL,
+    scheduled_for TIMESTAMP NOT NULL,
+    instance TEXT NOT NULL,
+    started_at TIMESTAMP NOT NULL,
+    finished_at TIMESTAMP NOT NULL,
+    attempts INTEGER NOT NULL,
+    succeeded BOOLEAN NOT NULL,
+    message TEXT NOT NULL
+);
+
+CREATE INDEX idx_job_runs_job ON job_runs (job, id);
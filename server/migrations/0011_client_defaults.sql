@@ -0,0 +1,4 @@
+-- Priority and context given to the new tasks of a client that do not set
+-- their own.
+ALTER TABLE clients ADD COLUMN default_priority INTEGER;
+ALTER TABLE clients ADD COLUMN default_context TEXT;
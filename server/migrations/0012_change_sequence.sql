@@ -0,0 +1,59 @@
+-- Position of each client and task in the order rows were last written, so
+-- that BI tools can pull only what changed since their previous refresh
+-- (`GET /api/export/table`). Kept up to date by the triggers below, whatever
+-- the statement changing the row.
+ALTER TABLE clients ADD COLUMN change_seq INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tasks ADD COLUMN change_seq INTEGER NOT NULL DEFAULT 0;
+
+-- Existing rows count as written in the order they were created.
+UPDATE clients SET change_seq = id;
+UPDATE tasks SET change_seq = id;
+
+CREATE INDEX idx_clients_change_seq ON clients (change_seq);
+CREATE INDEX idx_tasks_change_seq ON tasks (change_seq);
+
+CREATE TRIGGER clients_change_seq_insert AFTER INSERT ON clients
+BEGIN
+    UPDATE clients SET change_seq = (SELECT MAX(change_seq) + 1 FROM clients)
+    WHERE id = NEW.id;
+END;
+
+-- The guard skips the update made by the trigger itself.
+CREATE TRIGGER clients_change_seq_update AFTER UPDATE ON clients
+WHEN NEW.change_seq = OLD.change_seq
+BEGIN
+    UPDATE clients SET change_seq = (SELECT MAX(change_seq) + 1 FROM clients)
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER tasks_change_seq_insert AFTER INSERT ON tasks
+BEGIN
+    UPDATE tasks SET change_seq = (SELECT MAX(change_seq) + 1 FROM tasks)
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER tasks_change_seq_update AFTER UPDATE ON tasks
+WHEN NEW.change_seq = OLD.change_seq
+BEGIN
+    UPDATE tasks SET change_seq = (SELECT MAX(change_seq) + 1 FROM tasks)
+    WHERE id = NEW.id;
+END;
+
+-- Slots are exported as counts on their task, which changes with them.
+CREATE TRIGGER task_slots_change_seq_insert AFTER INSERT ON task_slots
+BEGIN
+    UPDATE tasks SET change_seq = (SELECT MAX(change_seq) + 1 FROM tasks)
+    WHERE id = NEW.task_id;
+END;
+
+CREATE TRIGGER task_slots_change_seq_update AFTER UPDATE ON task_slots
+BEGIN
+    UPDATE tasks SET change_seq = (SELECT MAX(change_seq) + 1 FROM tasks)
+    WHERE id = NEW.task_id;
+END;
+
+CREATE TRIGGER task_slots_change_seq_delete AFTER DELETE ON task_slots
+BEGIN
+    UPDATE tasks SET change_seq = (SELECT MAX(change_seq) + 1 FROM tasks)
+    WHERE id = OLD.task_id;
+END;
@@ -0,0 +1,12 @@
+-- Priorities rewritten by `POST /api/days/{date}/normalize-priorities`, one
+-- row per task changed, so that a normalization can be traced and undone.
+CREATE TABLE priority_changes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_id INTEGER NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    old_priority INTEGER NOT NULL,
+    new_priority INTEGER NOT NULL,
+    changed_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_priority_changes_task_id ON priority_changes (task_id);
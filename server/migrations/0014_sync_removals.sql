@@ -0,0 +1,18 @@
+-- Clients and tasks removed for good, so that the mobile sync
+-- (`GET /api/sync`) can tell devices to drop their copy. `seq` orders the
+-- removals the way `change_seq` orders the other writes.
+CREATE TABLE sync_removals (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    entity TEXT NOT NULL CHECK (entity IN ('clients', 'tasks')),
+    row_id INTEGER NOT NULL
+);
+
+CREATE TRIGGER clients_sync_removal AFTER DELETE ON clients
+BEGIN
+    INSERT INTO sync_removals (entity, row_id) VALUES ('clients', OLD.id);
+END;
+
+CREATE TRIGGER tasks_sync_removal AFTER DELETE ON tasks
+BEGIN
+    INSERT INTO sync_removals (entity, row_id) VALUES ('tasks', OLD.id);
+END;
@@ -0,0 +1,7 @@
+-- Background jobs paused from `POST /api/admin/jobs/{job}/pause`, e.g. the
+-- rollover for people who only roll tasks over by hand. Shared by all the
+-- instances on the database, and kept across restarts.
+CREATE TABLE paused_jobs (
+    job TEXT PRIMARY KEY,
+    paused_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,7 @@
+-- Day a task rolls over to in place of the one of ROLLOVER_POLICY, e.g.
+-- 'monday' for a task only worked on at the start of the week. NULL follows
+-- the policy.
+ALTER TABLE tasks ADD COLUMN rollover_target TEXT NULL CHECK (rollover_target IN (
+    'next-day', 'next-business-day', 'monday', 'tuesday', 'wednesday', 'thursday',
+    'friday', 'saturday', 'sunday', 'end-of-week'
+));
@@ -0,0 +1,8 @@
+-- The weekly completion goal set from `PUT /api/settings/goal`, in tasks
+-- or in estimated hours. At most one row.
+CREATE TABLE weekly_goal (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    unit TEXT NOT NULL CHECK (unit IN ('tasks', 'hours')),
+    target REAL NOT NULL CHECK (target > 0),
+    set_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,61 @@
+-- When each task was last written, so that a client can send back the
+-- version it read (`If-Match`) and be refused with 412 if another one
+-- changed the task in between. Kept up to date by the triggers of
+-- `change_seq`, redefined below to set both. Each write moves it forward
+-- by at least a millisecond, so that two quick writes never share one.
+ALTER TABLE tasks ADD COLUMN updated_at TEXT NOT NULL DEFAULT '1970-01-01T00:00:00Z';
+
+DROP TRIGGER tasks_change_seq_update;
+DROP TRIGGER task_slots_change_seq_insert;
+DROP TRIGGER task_slots_change_seq_update;
+DROP TRIGGER task_slots_change_seq_delete;
+DROP TRIGGER tasks_change_seq_insert;
+
+-- Existing tasks count as last written when created or done.
+UPDATE tasks SET updated_at = COALESCE(deleted_at, created_at);
+
+CREATE TRIGGER tasks_change_seq_insert AFTER INSERT ON tasks
+BEGIN
+    UPDATE tasks SET change_seq = (SELECT MAX(change_seq) + 1 FROM tasks),
+                     updated_at = NEW.created_at
+    WHERE id = NEW.id;
+END;
+
+-- The guard skips the update made by the trigger itself.
+CREATE TRIGGER tasks_change_seq_update AFTER UPDATE ON tasks
+WHEN NEW.change_seq = OLD.change_seq
+BEGIN
+    UPDATE tasks SET change_seq = (SELECT MAX(change_seq) + 1 FROM tasks),
+                     updated_at = MAX(
+                         strftime('%Y-%m-%dT%H:%M:%fZ', 'now'),
+                         strftime('%Y-%m-%dT%H:%M:%fZ', OLD.updated_at, '+0.001 seconds'))
+    WHERE id = NEW.id;
+END;
+
+-- Slots are shown as counts on their task, which changes with them.
+CREATE TRIGGER task_slots_change_seq_insert AFTER INSERT ON task_slots
+BEGIN
+    UPDATE tasks SET change_seq = (SELECT MAX(change_seq) + 1 FROM tasks),
+                     updated_at = MAX(
+                         strftime('%Y-%m-%dT%H:%M:%fZ', 'now'),
+                         strftime('%Y-%m-%dT%H:%M:%fZ', updated_at, '+0.001 seconds'))
+    WHERE id = NEW.task_id;
+END;
+
+CREATE TRIGGER task_slots_change_seq_update AFTER UPDATE ON task_slots
+BEGIN
+    UPDATE tasks SET change_seq = (SELECT MAX(change_seq) + 1 FROM tasks),
+                     updated_at = MAX(
+                         strftime('%Y-%m-%dT%H:%M:%fZ', 'now'),
+                         strftime('%Y-%m-%dT%H:%M:%fZ', updated_at, '+0.001 seconds'))
+    WHERE id = NEW.task_id;
+END;
+
+CREATE TRIGGER task_slots_change_seq_delete AFTER DELETE ON task_slots
+BEGIN
+    UPDATE tasks SET change_seq = (SELECT MAX(change_seq) + 1 FROM tasks),
+                     updated_at = MAX(
+                         strftime('%Y-%m-%dT%H:%M:%fZ', 'now'),
+                         strftime('%Y-%m-%dT%H:%M:%fZ', updated_at, '+0.001 seconds'))
+    WHERE id = OLD.task_id;
+END;
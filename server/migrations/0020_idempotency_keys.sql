@@ -0,0 +1,13 @@
+-- Responses of `POST /api/tasks` by the `Idempotency-Key` they were sent
+-- with, replayed when a client retries the request. `request_hash` tells a
+-- retry apart from another request reusing the key. Rows older than
+-- `IDEMPOTENCY_KEY_TTL_HOURS` are dropped on the next creation with a key.
+CREATE TABLE idempotency_keys (
+    key TEXT PRIMARY KEY,
+    request_hash TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_idempotency_keys_created_at ON idempotency_keys (created_at);
@@ -0,0 +1,39 @@
+-- Priorities are now one of four levels, from 1 (critical) to 4 (low).
+-- Those set before, when any number was accepted and lower meant more
+-- urgent, are brought into the levels by ranges, so that their order is
+-- kept as far as four levels allow:
+--   2 and below -> 1 (critical)
+--   3 and 4     -> 2 (high)
+--   5 to 7      -> 3 (normal)
+--   8 and above -> 4 (low)
+-- Only the rows whose priority changes are written, so that only those
+-- tasks are synced again. The history of `priority_changes` is left as it
+-- was recorded.
+UPDATE tasks SET priority = CASE
+    WHEN priority <= 2 THEN 1
+    WHEN priority <= 4 THEN 2
+    WHEN priority <= 7 THEN 3
+    ELSE 4
+END
+WHERE priority IS NOT NULL AND priority != CASE
+    WHEN priority <= 2 THEN 1
+    WHEN priority <= 4 THEN 2
+    WHEN priority <= 7 THEN 3
+    ELSE 4
+END;
+
+UPDATE clients SET default_priority = CASE
+    WHEN default_priority <= 2 THEN 1
+    WHEN default_priority <= 4 THEN 2
+    WHEN default_priority <= 7 THEN 3
+    ELSE 4
+END
+WHERE default_priority IS NOT NULL;
+
+UPDATE import_rows SET priority = CASE
+    WHEN priority <= 2 THEN 1
+    WHEN priority <= 4 THEN 2
+    WHEN priority <= 7 THEN 3
+    ELSE 4
+END
+WHERE priority IS NOT NULL;
@@ -0,0 +1,33 @@
+-- Colors are now read as `#rrggbb` hex values, and malformed ones are
+-- refused. Stored `#rgb` and uppercase colors are rewritten in that form;
+-- anything else, such as the class names of early releases, becomes the
+-- grey of the default palette.
+UPDATE clients SET color = lower(
+    '#' || substr(color, 2, 1) || substr(color, 2, 1)
+    || substr(color, 3, 1) || substr(color, 3, 1)
+    || substr(color, 4, 1) || substr(color, 4, 1))
+WHERE color GLOB '#[0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f]';
+UPDATE clients SET color = lower(color)
+WHERE color GLOB '#[0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f]' AND color != lower(color);
+UPDATE clients SET color = '#7f7f7f'
+WHERE NOT color GLOB '#[0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f]';
+
+UPDATE client_color_changes SET old_color = lower(
+    '#' || substr(old_color, 2, 1) || substr(old_color, 2, 1)
+    || substr(old_color, 3, 1) || substr(old_color, 3, 1)
+    || substr(old_color, 4, 1) || substr(old_color, 4, 1))
+WHERE old_color GLOB '#[0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f]';
+UPDATE client_color_changes SET old_color = lower(old_color)
+WHERE old_color GLOB '#[0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f]' AND old_color != lower(old_color);
+UPDATE client_color_changes SET old_color = '#7f7f7f'
+WHERE NOT old_color GLOB '#[0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f]';
+
+UPDATE client_color_changes SET new_color = lower(
+    '#' || substr(new_color, 2, 1) || substr(new_color, 2, 1)
+    || substr(new_color, 3, 1) || substr(new_color, 3, 1)
+    || substr(new_color, 4, 1) || substr(new_color, 4, 1))
+WHERE new_color GLOB '#[0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f]';
+UPDATE client_color_changes SET new_color = lower(new_color)
+WHERE new_color GLOB '#[0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f]' AND new_color != lower(new_color);
+UPDATE client_color_changes SET new_color = '#7f7f7f'
+WHERE NOT new_color GLOB '#[0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f]';
@@ -0,0 +1,42 @@
+-- `updated_at` now comes from the server clock, bound by each write, so
+-- that it can be frozen in end-to-end test mode like the other timestamps.
+-- The triggers only keep it moving forward by at least a millisecond, for
+-- writes that do not set it or that share a timestamp.
+DROP TRIGGER tasks_change_seq_update;
+DROP TRIGGER task_slots_change_seq_insert;
+DROP TRIGGER task_slots_change_seq_update;
+DROP TRIGGER task_slots_change_seq_delete;
+
+-- The guard skips the update made by the trigger itself.
+CREATE TRIGGER tasks_change_seq_update AFTER UPDATE ON tasks
+WHEN NEW.change_seq = OLD.change_seq
+BEGIN
+    UPDATE tasks SET change_seq = (SELECT MAX(change_seq) + 1 FROM tasks),
+                     updated_at = MAX(
+                         strftime('%Y-%m-%dT%H:%M:%fZ', NEW.updated_at),
+                         strftime('%Y-%m-%dT%H:%M:%fZ', OLD.updated_at, '+0.001 seconds'))
+    WHERE id = NEW.id;
+END;
+
+-- Slots are shown as counts on their task, which changes with them. The
+-- writes of slots set the version of the task themselves.
+CREATE TRIGGER task_slots_change_seq_insert AFTER INSERT ON task_slots
+BEGIN
+    UPDATE tasks SET change_seq = (SELECT MAX(change_seq) + 1 FROM tasks),
+                     updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', updated_at, '+0.001 seconds')
+    WHERE id = NEW.task_id;
+END;
+
+CREATE TRIGGER task_slots_change_seq_update AFTER UPDATE ON task_slots
+BEGIN
+    UPDATE tasks SET change_seq = (SELECT MAX(change_seq) + 1 FROM tasks),
+                     updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', updated_at, '+0.001 seconds')
+    WHERE id = NEW.task_id;
+END;
+
+CREATE TRIGGER task_slots_change_seq_delete AFTER DELETE ON task_slots
+BEGIN
+    UPDATE tasks SET change_seq = (SELECT MAX(change_seq) + 1 FROM tasks),
+                     updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', updated_at, '+0.001 seconds')
+    WHERE id = OLD.task_id;
+END;
@@ -0,0 +1,8 @@
+-- Sessions signed out before they expire, by the identifier their cookie
+-- carries. Those cookies keep a valid signature until `expires_at`, so
+-- `require_auth` looks them up here to turn them away. Rows past their
+-- expiry are dropped on the next sign-out.
+CREATE TABLE revoked_sessions (
+    id TEXT PRIMARY KEY,
+    expires_at TIMESTAMP NOT NULL
+);
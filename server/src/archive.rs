@@ -0,0 +1,129 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+/// Number of records in a chunk when the client does not ask for a size.
+pub const DEFAULT_CHUNK_SIZE: i64 = 1000;
+/// Upper bound on the records in a chunk, to keep responses bounded.
+pub const MAX_CHUNK_SIZE: i64 = 10_000;
+
+/// The part of the archive a cursor points into. Sections are downloaded
+/// in the order of the variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveSection {
+    Clients,
+    Tasks,
+}
+
+/// Position in the archive: everything in `section` with an ID greater than
+/// `after_id` is still to be downloaded.
+///
+/// Records are ordered by their immutable ID, so a cursor stays valid across
+/// requests and a download can resume from the last chunk that arrived intact.
+/// Records created after the download started may or may not be included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveCursor {
+    pub section: ArchiveSection,
+    pub after_id: i64,
+}
+
+impl ArchiveCursor {
+    /// The beginning of the archive.
+    pub fn start() -> Self {
+        ArchiveCursor {
+            section: ArchiveSection::Clients,
+            after_id: 0,
+        }
+    }
+
+    /// The cursor following a chunk of `count` records whose last ID is
+    /// `last_id`, or `None` when the archive is complete.
+    pub fn next(self, count: i64, limit: i64, last_id: Option<i64>) -> Option<Self> {
+        match (count < limit, self.section) {
+            (false, section) => Some(ArchiveCursor {
+                section,
+                after_id: last_id.unwrap_or(self.after_id),
+            }),
+            (true, ArchiveSection::Clients) => Some(ArchiveCursor {
+                section: ArchiveSection::Tasks,
+                after_id: 0,
+            }),
+            (true, ArchiveSection::Tasks) => None,
+        }
+    }
+}
+
+/// Cursors are exchanged as `<section>.<id>` tokens, e.g. `tasks.1200`.
+/// Clients should treat them as opaque.
+impl fmt::Display for ArchiveCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let section = match self.section {
+            ArchiveSection::Clients => "clients",
+            ArchiveSection::Tasks => "tasks",
+        };
+        write!(f, "{section}.{}", self.after_id)
+    }
+}
+
+impl FromStr for ArchiveCursor {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (section, after_id) = s.split_once('.').ok_or(())?;
+        let section = match section {
+            "clients" => ArchiveSection::Clients,
+            "tasks" => ArchiveSection::Tasks,
+            _ => return Err(()),
+        };
+        let after_id = after_id.parse().map_err(|_| ())?;
+        Ok(ArchiveCursor { section, after_id })
+    }
+}
+
+/// Checksum of a chunk body, as sent in the `X-Chunk-Checksum` header.
+pub fn chunk_checksum(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    let hex: String = digest.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("sha256={hex}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor: ArchiveCursor = "tasks.1200".parse().unwrap();
+        assert_eq!(cursor.section, ArchiveSection::Tasks);
+        assert_eq!(cursor.after_id, 1200);
+        assert_eq!(cursor.to_string(), "tasks.1200");
+        assert_eq!(ArchiveCursor::start().to_string(), "clients.0");
+
+        assert!("tasks".parse::<ArchiveCursor>().is_err());
+        assert!("users.1".parse::<ArchiveCursor>().is_err());
+        assert!("tasks.abc".parse::<ArchiveCursor>().is_err());
+    }
+
+    #[test]
+    fn test_cursor_advances_through_sections() {
+        let start = ArchiveCursor::start();
+        // A full chunk stays in the section, after the last record
+        assert_eq!(start.next(2, 2, Some(7)).unwrap().to_string(), "clients.7");
+        // A short chunk moves on to the next section, then to the end
+        let tasks = start.next(1, 2, Some(9)).unwrap();
+        assert_eq!(tasks.to_string(), "tasks.0");
+        assert_eq!(tasks.next(0, 2, None), None);
+    }
+
+    #[test]
+    fn test_chunk_checksum() {
+        assert_eq!(
+            chunk_checksum(b""),
+            "sha256=e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::Config;
+use axum::{
+    extract::{Path, Request, State},
+    http::{header, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use tower::ServiceExt;
+use tower_http::services::{ServeDir, ServeFile};
+
+/// Pages of the web UI, embedded in the binary so that a release is a
+/// single file. The first one is served at `/`.
+const PAGES: &[(&str, &str)] = &[
+    (
+        "client-app.html",
+        include_str!("../../javascript-client/client-app.html"),
+    ),
+    (
+        "dashboard-app.html",
+        include_str!("../../javascript-client/dashboard-app.html"),
+    ),
+];
+
+/// Finds an embedded page by file name.
+pub fn page(name: &str) -> Option<&'static str> {
+    PAGES
+        .iter()
+        .find(|(page_name, _)| *page_name == name)
+        .map(|(_, content)| *content)
+}
+
+fn html(content: &'static str) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "text/html; charset=utf-8"),
+            // The pages change with the binary, not with a cache lifetime.
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        content,
+    )
+        .into_response()
+}
+
+/// Handler serving the task list page at `/`.
+pub async fn index() -> Response {
+    html(PAGES[0].1)
+}
+
+/// Handler serving an embedded page by name, e.g. `/dashboard-app.html`.
+pub async fn serve_page(Path(name): Path<String>, uri: Uri) -> Response {
+    match page(&name) {
+        Some(content) => html(content),
+        None => app_fallback(uri).await,
+    }
+}
+
+fn is_api(path: &str) -> bool {
+    path == "/api" || path.starts_with("/api/")
+}
+
+/// Whether `path` is a route of the web UI rather than a file or an API
+/// endpoint: outside `/api/`, with no extension in its last segment, e.g.
+/// `/week/2025-W29`.
+fn is_app_route(path: &str) -> bool {
+    !is_api(path)
+        && !path
+            .rsplit('/')
+            .next()
+            .is_some_and(|segment| segment.contains('.'))
+}
+
+/// Handler of the paths matching no route. Routes of the web UI get the
+/// task list page, so that they can be bookmarked and reloaded; missing
+/// files and endpoints are not found.
+pub async fn app_fallback(uri: Uri) -> Response {
+    if is_app_route(uri.path()) {
+        html(PAGES[0].1)
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+/// Handler serving the frontend build of `WEB_ROOT` at `/`, its
+/// `index.html` standing in for the routes of the web UI as with the
+/// embedded pages.
+pub async fn serve_web_root(State(config): State<Arc<Config>>, request: Request) -> Response {
+    let Some(root) = config.web_root.as_deref() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if is_api(request.uri().path()) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let files = ServeDir::new(root);
+    let response = if is_app_route(request.uri().path()) {
+        files
+            .fallback(ServeFile::new(root.join("index.html")))
+            .oneshot(request)
+            .await
+    } else {
+        files.oneshot(request).await
+    };
+    match response {
+        Ok(response) => response.into_response(),
+        Err(never) => match never {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_routes() {
+        for path in ["/", "/week/2025-W29", "/dashboard", "/clients/"] {
+            assert!(is_app_route(path), "{path}");
+        }
+        for path in ["/app.js", "/assets/logo.svg", "/api", "/api/unknown"] {
+            assert!(!is_app_route(path), "{path}");
+        }
+    }
+}
@@ -0,0 +1,433 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::Config;
+use crate::handlers::AppError;
+use crate::{clock, database};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, SubsecRound, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::SqlitePool;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Shortest token accepted, so that it cannot be guessed.
+const MIN_TOKEN_LENGTH: usize = 16;
+
+/// Shortest `SESSION_SECRET` accepted.
+const MIN_SECRET_LENGTH: usize = 32;
+
+/// Name of the cookie holding the session of a browser.
+pub const SESSION_COOKIE: &str = "wtm_session";
+
+/// Header echoing the CSRF token of the session on state-changing requests.
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Feeds pulled by other tools (BI tools, calendars), which bring their
+/// own `EXPORT_API_TOKENS`.
+const EXPORT_PATHS: [&str; 2] = ["/api/export/table", "/api/export/calendar.ics"];
+
+/// Compares in constant time, so that response times do not reveal how much
+/// of a secret matched.
+fn constant_time_eq(known: &str, given: &str) -> bool {
+    let differences = known
+        .bytes()
+        .zip(given.bytes())
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    (known.len() == given.len()) & (differences == 0)
+}
+
+/// Tokens granting access to part of the API, such as the `API_KEYS` or the
+/// `EXPORT_API_TOKENS`. None by default.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct ApiTokens(Vec<String>);
+
+impl ApiTokens {
+    /// Whether `token` is one of the configured tokens.
+    pub fn accepts(&self, token: &str) -> bool {
+        self.0
+            .iter()
+            .fold(false, |found, known| found | constant_time_eq(known, token))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Comma-separated tokens of at least 16 characters; giving each consumer
+/// its own lets one be revoked without the others.
+impl FromStr for ApiTokens {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens: Vec<String> = s
+            .split(',')
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(String::from)
+            .collect();
+        if let Some(short) = tokens.iter().find(|t| t.len() < MIN_TOKEN_LENGTH) {
+            return Err(format!(
+                "token '{}...' is shorter than {MIN_TOKEN_LENGTH} characters",
+                short.chars().take(2).collect::<String>()
+            ));
+        }
+        Ok(ApiTokens(tokens))
+    }
+}
+
+/// Keeps the tokens out of the logs.
+impl fmt::Debug for ApiTokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ApiTokens({} configured)", self.0.len())
+    }
+}
+
+/// Key signing what the server hands out and checks later without storing
+/// it, such as session cookies (`SESSION_SECRET`) or share links
+/// (`SHARE_SECRET`). Changing it invalidates all of them.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SigningSecret(String);
+
+impl SigningSecret {
+    /// HMAC-SHA256 of `message`, in hex.
+    pub fn sign(&self, message: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.0.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(message.as_bytes());
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Whether `signature` is the one of `message`.
+    pub fn verify(&self, message: &str, signature: &str) -> bool {
+        constant_time_eq(&self.sign(message), signature)
+    }
+
+    /// A new random secret, for keys the server hands out itself such as
+    /// those of webhooks.
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        getrandom::getrandom(&mut bytes).expect("No source of randomness available");
+        SigningSecret(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+    }
+
+    /// The secret itself, to hand out or store.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for SigningSecret {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let secret = s.trim();
+        if secret.len() < MIN_SECRET_LENGTH {
+            return Err(format!("shorter than {MIN_SECRET_LENGTH} characters"));
+        }
+        Ok(SigningSecret(secret.to_string()))
+    }
+}
+
+/// Keeps the secret out of the logs.
+impl fmt::Debug for SigningSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SigningSecret(..)")
+    }
+}
+
+/// Cookie sessions of the web UI, signed in with one of the `API_KEYS` so
+/// that the key does not have to sit in the storage of the browser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionConfig {
+    pub secret: SigningSecret,
+    /// Lifetime of a session, after which the browser signs in again
+    /// (`SESSION_TTL_HOURS`).
+    pub ttl: Duration,
+    /// Only sends the cookie over HTTPS (`SESSION_COOKIE_SECURE`). Turn it
+    /// off for plain HTTP on a trusted network.
+    pub secure_cookie: bool,
+}
+
+impl SessionConfig {
+    /// Default lifetime of a session.
+    pub const DEFAULT_TTL_HOURS: i64 = 12;
+
+    pub fn new(secret: SigningSecret) -> Self {
+        SessionConfig {
+            secret,
+            ttl: Duration::hours(SessionConfig::DEFAULT_TTL_HOURS),
+            secure_cookie: true,
+        }
+    }
+
+    /// Opens a session expiring `ttl` after `now`.
+    pub fn issue(&self, now: DateTime<Utc>) -> Session {
+        let mut nonce = [0u8; 16];
+        // Without a source of randomness, the server cannot work anyway.
+        getrandom::getrandom(&mut nonce).expect("No source of randomness available");
+        let id: String = nonce.iter().map(|byte| format!("{byte:02x}")).collect();
+        // Whole seconds, as carried by the cookie
+        let expires_at = (now + self.ttl).trunc_subsecs(0);
+        let payload = format!("{}.{id}", expires_at.timestamp());
+        Session {
+            expires_at,
+            signature: self.secret.sign(&format!("session:{payload}")),
+            id,
+        }
+    }
+
+    /// The session of a cookie value, unless it was forged or has expired.
+    pub fn verify(&self, value: &str, now: DateTime<Utc>) -> Option<Session> {
+        let (payload, signature) = value.rsplit_once('.')?;
+        if !self.secret.verify(&format!("session:{payload}"), signature) {
+            return None;
+        }
+        let (expires_at, id) = payload.split_once('.')?;
+        let expires_at = DateTime::from_timestamp(expires_at.parse().ok()?, 0)?;
+        (expires_at > now).then(|| Session {
+            expires_at,
+            id: id.to_string(),
+            signature: signature.to_string(),
+        })
+    }
+
+    /// Token a page of the session sends back in `X-CSRF-Token`. Derived
+    /// from the session rather than stored, and out of reach of other sites,
+    /// which cannot read the responses of the API.
+    pub fn csrf_token(&self, session: &Session) -> String {
+        self.secret.sign(&format!("csrf:{}", session.id))
+    }
+
+    /// `Set-Cookie` value handing `session` to the browser, kept away from
+    /// scripts and from requests started by other sites.
+    pub fn cookie(&self, session: &Session, now: DateTime<Utc>) -> HeaderValue {
+        let max_age = (session.expires_at - now).num_seconds().max(0);
+        self.cookie_with(&session.value(), max_age)
+    }
+
+    /// `Set-Cookie` value removing the session cookie.
+    pub fn expired_cookie(&self) -> HeaderValue {
+        self.cookie_with("", 0)
+    }
+
+    fn cookie_with(&self, value: &str, max_age: i64) -> HeaderValue {
+        let secure = if self.secure_cookie { "; Secure" } else { "" };
+        let cookie = format!(
+            "{SESSION_COOKIE}={value}; Path=/api; HttpOnly; SameSite=Strict; Max-Age={max_age}{secure}"
+        );
+        // Made of hex digits, digits and ASCII punctuation only.
+        HeaderValue::from_str(&cookie).expect("Session cookies are valid header values")
+    }
+
+    /// The valid session in the `Cookie` header of a request, if any.
+    pub fn from_headers(&self, headers: &HeaderMap, now: DateTime<Utc>) -> Option<Session> {
+        headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .filter(|(name, _)| *name == SESSION_COOKIE)
+            .find_map(|(_, value)| self.verify(value, now))
+    }
+
+    /// The session a request is signed in with: a valid one in its
+    /// `Cookie` header, unless it was signed out since.
+    pub async fn signed_in(
+        &self,
+        pool: &SqlitePool,
+        headers: &HeaderMap,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<Option<Session>> {
+        let Some(session) = self.from_headers(headers, now) else {
+            return Ok(None);
+        };
+        if database::is_session_revoked_in_db(pool, &session.id).await? {
+            return Ok(None);
+        }
+        Ok(Some(session))
+    }
+}
+
+/// A signed-in browser. The cookie carries the expiry and identifier of the
+/// session, signed with `SESSION_SECRET`; the server only stores the
+/// identifiers of the sessions signed out before they expire.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub expires_at: DateTime<Utc>,
+    id: String,
+    signature: String,
+}
+
+impl Session {
+    /// Identifier of the session, by which it is revoked.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Value of the cookie.
+    fn value(&self) -> String {
+        format!(
+            "{}.{}.{}",
+            self.expires_at.timestamp(),
+            self.id,
+            self.signature
+        )
+    }
+}
+
+/// The bearer token of a request.
+pub(crate) fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Whether a request may change state, and so must prove it comes from a
+/// page of the app when sent with the session cookie.
+fn changes_state(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Middleware closing the API once `API_KEYS` are configured. Requests
+/// either carry one of the keys as a bearer token, as scripts and the
+/// mobile client do, or the session cookie of a browser signed in with one;
+/// those changing state with the cookie must also echo the CSRF token of the
+/// session in `X-CSRF-Token`, or get `403 Forbidden`. Sessions signed out
+/// with `DELETE /api/session` are refused even though their cookie is still
+/// validly signed.
+pub async fn require_auth(
+    State(config): State<Arc<Config>>,
+    State(pool): State<SqlitePool>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if config.api_keys.is_empty() || EXPORT_PATHS.contains(&request.uri().path()) {
+        return next.run(request).await;
+    }
+    let headers = request.headers();
+    if let Some(key) = bearer(headers) {
+        if config.api_keys.accepts(key) {
+            return next.run(request).await;
+        }
+        return AppError::new(StatusCode::UNAUTHORIZED, "Invalid API key.").into_response();
+    }
+
+    let session = match &config.sessions {
+        Some(sessions) => match sessions.signed_in(&pool, headers, clock::now()).await {
+            Ok(session) => session.map(|session| (sessions, session)),
+            Err(e) => return AppError::from(e).into_response(),
+        },
+        None => None,
+    };
+    if let Some((sessions, session)) = session {
+        if changes_state(request.method()) {
+            let expected = sessions.csrf_token(&session);
+            let given = headers
+                .get(CSRF_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            if !constant_time_eq(&expected, given) {
+                return AppError::new(StatusCode::FORBIDDEN, "A valid CSRF token is required.")
+                    .into_response();
+            }
+        }
+        return next.run(request).await;
+    }
+
+    AppError::new(
+        StatusCode::UNAUTHORIZED,
+        "An API key or a session is required.",
+    )
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn sessions() -> SessionConfig {
+        SessionConfig::new("a-session-secret-of-32-characters".parse().unwrap())
+    }
+
+    #[test]
+    fn test_tokens() {
+        let tokens: ApiTokens = "powerbi-0123456789, sheets-0123456789".parse().unwrap();
+        assert!(tokens.accepts("sheets-0123456789"));
+        assert!(!tokens.accepts("sheets-012345678"));
+        assert!(!tokens.accepts(""));
+        assert!(!ApiTokens::default().accepts(""));
+        assert!("short".parse::<ApiTokens>().is_err());
+        assert_eq!(format!("{tokens:?}"), "ApiTokens(2 configured)");
+    }
+
+    #[test]
+    fn test_sessions_are_signed_and_expire() {
+        let sessions = sessions();
+        let now = Utc.with_ymd_and_hms(2025, 7, 14, 9, 0, 0).unwrap();
+        let session = sessions.issue(now);
+        assert_eq!(session.expires_at, now + Duration::hours(12));
+        assert_eq!(
+            sessions.verify(&session.value(), now),
+            Some(session.clone())
+        );
+
+        // Expired, or extended by hand
+        assert_eq!(sessions.verify(&session.value(), session.expires_at), None);
+        let extended = session.value().replacen(
+            &session.expires_at.timestamp().to_string(),
+            &(session.expires_at.timestamp() + 3600).to_string(),
+            1,
+        );
+        assert_eq!(sessions.verify(&extended, now), None);
+        // Signed with another secret
+        let other = SessionConfig::new("another-secret-of-32-characters!!".parse().unwrap());
+        assert_eq!(other.verify(&session.value(), now), None);
+        assert!("too-short".parse::<SigningSecret>().is_err());
+
+        // Each session has its own CSRF token
+        let again = sessions.issue(now);
+        assert_ne!(sessions.csrf_token(&session), sessions.csrf_token(&again));
+    }
+
+    #[test]
+    fn test_session_cookie() {
+        let sessions = sessions();
+        let now = Utc.with_ymd_and_hms(2025, 7, 14, 9, 0, 0).unwrap();
+        let session = sessions.issue(now);
+        let cookie = sessions.cookie(&session, now);
+        let cookie = cookie.to_str().unwrap();
+        assert!(cookie.starts_with(&format!("{SESSION_COOKIE}={}", session.value())));
+        assert!(cookie.ends_with("; HttpOnly; SameSite=Strict; Max-Age=43200; Secure"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_str(&format!("theme=dark; {SESSION_COOKIE}={}", session.value()))
+                .unwrap(),
+        );
+        assert_eq!(sessions.from_headers(&headers, now), Some(session));
+        assert!(sessions
+            .expired_cookie()
+            .to_str()
+            .unwrap()
+            .contains(&format!("{SESSION_COOKIE}=; Path=/api")));
+    }
+}
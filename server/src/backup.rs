@@ -0,0 +1,171 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::jobs::Job;
+use crate::{clock, database};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tracing::info;
+
+/// Prefix and extension of the files written by scheduled backups. Only
+/// these are pruned, so other files in the directory are left alone.
+const BACKUP_FILE_PREFIX: &str = "backup-";
+const BACKUP_FILE_EXTENSION: &str = ".db";
+
+/// A file in the system temporary directory, removed when dropped.
+/// Used to hand backups over to and from the HTTP layer.
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    /// Picks a path that is not used yet; the file itself is not created.
+    pub fn new(purpose: &str) -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        TempFile(std::env::temp_dir().join(format!(
+            "weekly-task-manager-{purpose}-{}-{n}.db",
+            std::process::id()
+        )))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        // Also covers the journal files SQLite may have left next to it.
+        for suffix in ["", "-wal", "-shm", "-journal"] {
+            let mut path = self.0.clone().into_os_string();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Writes a backup named after the current time into `dir`, then removes
+/// the oldest ones so that at most `keep` remain.
+pub async fn write_scheduled_backup(pool: &SqlitePool, dir: &Path, keep: usize) -> Result<PathBuf> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("Failed to create backup directory {}", dir.display()))?;
+
+    let name = clock::now().format("%Y%m%dT%H%M%SZ");
+    let path = dir.join(format!("{BACKUP_FILE_PREFIX}{name}{BACKUP_FILE_EXTENSION}"));
+    database::backup_database_to(pool, &path).await?;
+    prune_backups(dir, keep).await?;
+    Ok(path)
+}
+
+/// Removes the oldest scheduled backups of `dir` beyond the `keep` latest.
+async fn prune_backups(dir: &Path, keep: usize) -> Result<()> {
+    let mut backups = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .with_context(|| format!("Failed to list backup directory {}", dir.display()))?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(BACKUP_FILE_PREFIX) && name.ends_with(BACKUP_FILE_EXTENSION) {
+            backups.push(entry.path());
+        }
+    }
+
+    // Names embed the time, so they sort chronologically.
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for old in &backups[..excess] {
+        tokio::fs::remove_file(old)
+            .await
+            .with_context(|| format!("Failed to remove old backup {}", old.display()))?;
+        info!("Removed old backup {}", old.display());
+    }
+    Ok(())
+}
+
+/// Start of the `interval` long period `now` falls in, counted from the
+/// Unix epoch so that all instances agree on it.
+fn backup_slot(now: DateTime<Utc>, interval: Duration) -> DateTime<Utc> {
+    let secs = interval.as_secs().max(1) as i64;
+    DateTime::from_timestamp(now.timestamp().div_euclid(secs) * secs, 0).unwrap_or(now)
+}
+
+/// Scheduled backups, one per `interval` long period. The current period is
+/// backed up at startup unless it already was.
+pub struct BackupJob {
+    pub dir: PathBuf,
+    pub interval: Duration,
+    pub keep: usize,
+}
+
+impl Job for BackupJob {
+    fn name(&self) -> &'static str {
+        "backup"
+    }
+
+    fn next_run(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let interval = chrono::Duration::from_std(self.interval).ok()?;
+        Some(backup_slot(after, self.interval) + interval)
+    }
+
+    fn first_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        Some(backup_slot(now, self.interval))
+    }
+
+    async fn run(&self, pool: &SqlitePool) -> Result<String> {
+        let path = write_scheduled_backup(pool, &self.dir, self.keep).await?;
+        Ok(format!("Database backed up to {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_scheduled_backups_are_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}", dir.path().join("sqlite.db").display());
+        let pool = database::establish_connection_pool(&url, &DatabaseConfig::default())
+            .await
+            .unwrap();
+        let backups = dir.path().join("backups");
+        std::fs::create_dir(&backups).unwrap();
+        std::fs::write(backups.join("notes.txt"), "kept").unwrap();
+        for name in ["backup-20250101T000000Z.db", "backup-20250102T000000Z.db"] {
+            std::fs::write(backups.join(name), "old").unwrap();
+        }
+
+        let latest = write_scheduled_backup(&pool, &backups, 2).await.unwrap();
+
+        // The oldest backup was removed, other files are left alone
+        let mut names: Vec<String> = std::fs::read_dir(&backups)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 3, "{names:?}");
+        assert_eq!(names[0], "backup-20250102T000000Z.db");
+        assert!(latest.exists());
+        assert!(names.contains(&"notes.txt".to_string()));
+    }
+
+    #[test]
+    fn test_backup_slot_is_shared_across_instances() {
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let daily = Duration::from_secs(24 * 60 * 60);
+        assert_eq!(
+            backup_slot(at("2025-07-18T09:30:00Z"), daily),
+            at("2025-07-18T00:00:00Z")
+        );
+        assert_eq!(
+            backup_slot(at("2025-07-18T23:59:59Z"), daily),
+            at("2025-07-18T00:00:00Z")
+        );
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use common::Task;
+
+/// Weeks before the current one the calendar feed still lists, so that
+/// recent days keep their tasks in the calendar.
+pub const PAST_WEEKS: i64 = 4;
+
+/// Longest content line, in bytes, before it is folded (RFC 5545, 3.1).
+const MAX_LINE_BYTES: usize = 75;
+
+/// Escapes a text value: backslashes, separators and line breaks.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Appends a content line, folded into lines of at most 75 bytes that
+/// continue with a space, without splitting a character.
+fn push_line(out: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_BYTES {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+fn format_timestamp(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Renders `tasks` as an iCalendar feed, each an all-day event on its day
+/// filed under its client, which calendars can color by category. Done
+/// tasks are marked `COMPLETED`. `host` makes the event UIDs unique.
+pub fn render(tasks: &[Task], host: &str, now: DateTime<Utc>) -> String {
+    let mut out = String::new();
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//sbksba//weekly-task-manager//EN",
+        "CALSCALE:GREGORIAN",
+        "METHOD:PUBLISH",
+        "X-WR-CALNAME:Weekly tasks",
+    ] {
+        push_line(&mut out, line);
+    }
+    let stamp = format_timestamp(now);
+    for task in tasks {
+        push_line(&mut out, "BEGIN:VEVENT");
+        push_line(&mut out, &format!("UID:task-{}@{}", task.id, host));
+        push_line(&mut out, &format!("DTSTAMP:{stamp}"));
+        push_line(
+            &mut out,
+            &format!("DTSTART;VALUE=DATE:{}", format_date(task.task_date)),
+        );
+        push_line(
+            &mut out,
+            &format!(
+                "DTEND;VALUE=DATE:{}",
+                format_date(task.task_date + Duration::days(1))
+            ),
+        );
+        push_line(
+            &mut out,
+            &format!("SUMMARY:{}", escape_text(&task.description)),
+        );
+        push_line(
+            &mut out,
+            &format!("CATEGORIES:{}", escape_text(&task.client_name)),
+        );
+        let details: Vec<String> = [
+            task.priority
+                .map(|priority| format!("Priority {}", priority.level())),
+            task.estimate_minutes
+                .map(|minutes| format!("Estimate {minutes} min")),
+            task.context
+                .as_ref()
+                .map(|context| format!("Context {context}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !details.is_empty() {
+            push_line(
+                &mut out,
+                &format!("DESCRIPTION:{}", escape_text(&details.join("\n"))),
+            );
+        }
+        if task.deleted_at.is_some() {
+            push_line(&mut out, "STATUS:COMPLETED");
+        }
+        // A task does not make its day busy.
+        push_line(&mut out, "TRANSP:TRANSPARENT");
+        push_line(&mut out, "END:VEVENT");
+    }
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use common::Priority;
+
+    #[test]
+    fn test_text_is_escaped() {
+        assert_eq!(
+            escape_text("Call back; then\r\nsend a, b \\ c"),
+            "Call back\\; then\\nsend a\\, b \\\\ c"
+        );
+    }
+
+    #[test]
+    fn test_long_lines_are_folded() {
+        let mut out = String::new();
+        let line = format!("SUMMARY:{}", "é".repeat(40));
+        push_line(&mut out, &line);
+        let lines: Vec<&str> = out.trim_end_matches("\r\n").split("\r\n").collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.len() <= MAX_LINE_BYTES));
+        assert!(lines[1].starts_with(' '));
+        assert_eq!(lines.concat().replacen(" ", "", 1), line);
+    }
+
+    #[test]
+    fn test_tasks_are_all_day_events() {
+        let now = Utc.with_ymd_and_hms(2025, 7, 14, 9, 0, 0).unwrap();
+        let task = Task {
+            id: 7,
+            client_id: 1,
+            client_name: "Acme, Inc".to_string(),
+            description: "Send invoice".to_string(),
+            task_date: NaiveDate::from_ymd_opt(2025, 7, 15).unwrap(),
+            client_color: "#0077cc".parse().unwrap(),
+            client_text_color: "#ffffff".to_string(),
+            client_background_color: "#cce4f5".to_string(),
+            created_at: now,
+            deleted_at: Some(now),
+            updated_at: now,
+            priority: Some(Priority::Critical),
+            external_source: None,
+            external_id: None,
+            energy: None,
+            estimate_minutes: None,
+            context: None,
+            pin_to_date: false,
+            rollover_target: None,
+            rollover_count: 0,
+            stale_since: None,
+            slots_total: 0,
+            slots_done: 0,
+            deferred: false,
+            display: Default::default(),
+        };
+        let feed = render(&[task], "tasks.example.com", now);
+        assert!(
+            feed.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"),
+            "{feed}"
+        );
+        for line in [
+            "UID:task-7@tasks.example.com",
+            "DTSTAMP:20250714T090000Z",
+            "DTSTART;VALUE=DATE:20250715",
+            "DTEND;VALUE=DATE:20250716",
+            "SUMMARY:Send invoice",
+            "CATEGORIES:Acme\\, Inc",
+            "DESCRIPTION:Priority 1",
+            "STATUS:COMPLETED",
+        ] {
+            assert!(
+                feed.contains(&format!("\r\n{line}\r\n")),
+                "{line} in {feed}"
+            );
+        }
+        assert!(feed.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::{parse_cron, Config, CorsOrigins, LogFormat, TlsConfig};
+use clap::{Parser, Subcommand};
+use croner::Cron;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+/// Command line of the server. Every flag overrides the environment
+/// variable and the configuration file entry of the same setting, see the
+/// Configuration section of the README for the others.
+#[derive(Parser, Debug, Default)]
+#[command(name = "server", version, about = "Weekly task manager server")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// TOML file of settings, overridden by the environment and the flags
+    #[arg(long, value_name = "PATH", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Address to listen on [env: BIND_ADDRESS, default: 0.0.0.0]
+    #[arg(long, value_name = "IP")]
+    pub bind: Option<IpAddr>,
+
+    /// Port to listen on [env: PORT, default: 3000]
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// PEM certificate chain to serve HTTPS with [env: TLS_CERT_PATH]
+    #[arg(long, value_name = "PATH", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key of the certificate [env: TLS_KEY_PATH]
+    #[arg(long, value_name = "PATH", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// SQLite database to open [env: DATABASE_URL]
+    #[arg(long, value_name = "URL", global = true)]
+    pub database_url: Option<String>,
+
+    /// Cron expression of the automatic rollover [env: ROLLOVER_SCHEDULE]
+    #[arg(long, value_name = "CRON", value_parser = cron_arg)]
+    pub rollover_schedule: Option<Cron>,
+
+    /// Browser origins allowed to call the API, `*`, `none` or a
+    /// comma-separated list [env: CORS_ALLOWED_ORIGINS]
+    #[arg(long, value_name = "ORIGINS")]
+    pub cors_origins: Option<CorsOrigins>,
+
+    /// Format of the log lines, `text` or `json` [env: LOG_FORMAT]
+    #[arg(long, value_name = "FORMAT")]
+    pub log_format: Option<LogFormat>,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Check the database for inconsistencies instead of serving it
+    Doctor {
+        /// Repair what can be repaired
+        #[arg(long)]
+        fix: bool,
+    },
+}
+
+fn cron_arg(expression: &str) -> Result<Cron, String> {
+    parse_cron(expression).map_err(|e| e.to_string())
+}
+
+impl Cli {
+    /// Applies the flags given on the command line to `config`, read from
+    /// the configuration file and the environment beforehand.
+    pub fn apply_to(&self, config: &mut Config) {
+        if let Some(address) = self.bind {
+            config.http.listen.set_ip(address);
+        }
+        if let Some(port) = self.port {
+            config.http.listen.set_port(port);
+        }
+        if let (Some(cert_path), Some(key_path)) = (&self.tls_cert, &self.tls_key) {
+            let reload_interval = match &config.http.tls {
+                Some(tls) => tls.reload_interval,
+                None => Some(TlsConfig::DEFAULT_RELOAD_INTERVAL),
+            };
+            config.http.tls = Some(TlsConfig {
+                cert_path: cert_path.clone(),
+                key_path: key_path.clone(),
+                reload_interval,
+            });
+        }
+        if let Some(database_url) = &self.database_url {
+            config.database_url = database_url.clone();
+        }
+        if let Some(cron) = &self.rollover_schedule {
+            config.rollover_schedule.cron = cron.clone();
+        }
+        if let Some(origins) = &self.cors_origins {
+            config.http.cors.allowed_origins = origins.clone();
+        }
+        if let Some(format) = self.log_format {
+            config.log_format = format;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_override_the_environment() {
+        let cli = Cli::try_parse_from([
+            "server",
+            "--port",
+            "8080",
+            "--bind",
+            "127.0.0.1",
+            "--cors-origins",
+            "https://tasks.example.com",
+            "--log-format",
+            "json",
+            "--tls-cert",
+            "/etc/tls/cert.pem",
+            "--tls-key",
+            "/etc/tls/key.pem",
+            "doctor",
+            "--fix",
+            "--database-url",
+            "sqlite://other.db",
+        ])
+        .unwrap();
+        assert_eq!(cli.command, Some(Command::Doctor { fix: true }));
+
+        let mut config = Config::default();
+        cli.apply_to(&mut config);
+        assert_eq!(config.http.listen.to_string(), "127.0.0.1:8080");
+        assert_eq!(config.database_url, "sqlite://other.db");
+        assert_eq!(
+            config.http.cors.allowed_origins,
+            CorsOrigins::List(vec!["https://tasks.example.com".to_string()])
+        );
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(
+            config.http.tls,
+            Some(TlsConfig::new(
+                PathBuf::from("/etc/tls/cert.pem"),
+                PathBuf::from("/etc/tls/key.pem")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_invalid_flags_are_rejected() {
+        for args in [
+            ["server", "--port", "99999"],
+            ["server", "--rollover-schedule", "every night"],
+            ["server", "--cors-origins", "tasks.example.com"],
+            ["server", "--log-format", "xml"],
+            ["server", "--tls-cert", "cert.pem"],
+        ] {
+            assert!(Cli::try_parse_from(args).is_err(), "{args:?}");
+        }
+    }
+}
@@ -0,0 +1,352 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::Config;
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{request::Parts, HeaderMap, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::debug;
+
+/// An IP network, e.g. `10.0.0.0/8` or `::1/128`. A bare address is a
+/// network of a single host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpCidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl IpCidr {
+    /// Checks whether an address belongs to this network.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                prefix_matches(network.to_bits(), ip.to_bits(), self.prefix)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                prefix_matches(network.to_bits(), ip.to_bits(), self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+// Compares the `prefix` most significant bits of two addresses.
+fn prefix_matches<T>(network: T, ip: T, prefix: u8) -> bool
+where
+    T: Copy + Eq + std::ops::Shr<u32, Output = T> + std::ops::BitXor<Output = T> + Default,
+{
+    let bits = (std::mem::size_of::<T>() * 8) as u32;
+    let host_bits = bits - u32::from(prefix);
+    host_bits == bits || (network ^ ip) >> host_bits == T::default()
+}
+
+impl FromStr for IpCidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = match s.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (s, None),
+        };
+        let network = address
+            .parse::<IpAddr>()
+            .map_err(|_| format!("'{address}' is not an IP address"))?
+            .to_canonical();
+        let max_prefix = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max_prefix)
+                .ok_or_else(|| format!("'{prefix}' is not a valid prefix length for {network}"))?,
+            None => max_prefix,
+        };
+        Ok(IpCidr { network, prefix })
+    }
+}
+
+/// The one header the trusted proxies write the client address to. Only that
+/// header is read, since the other reaches the server as the client sent it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ForwardedHeader {
+    /// `X-Forwarded-For` (`x-forwarded-for`).
+    #[default]
+    XForwardedFor,
+    /// The standard `Forwarded` header of RFC 7239 (`forwarded`).
+    Forwarded,
+}
+
+impl ForwardedHeader {
+    pub const ALL: [ForwardedHeader; 2] =
+        [ForwardedHeader::XForwardedFor, ForwardedHeader::Forwarded];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ForwardedHeader::XForwardedFor => "x-forwarded-for",
+            ForwardedHeader::Forwarded => "forwarded",
+        }
+    }
+}
+
+impl FromStr for ForwardedHeader {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ForwardedHeader::ALL
+            .into_iter()
+            .find(|header| header.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                format!("unknown forwarded header '{s}', expected x-forwarded-for or forwarded")
+            })
+    }
+}
+
+/// The reverse proxies whose forwarding headers are believed.
+/// Empty by default: headers are ignored and the peer address is the client.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Vec<IpCidr>);
+
+impl FromStr for TrustedProxies {
+    type Err = String;
+
+    /// Parses a comma-separated list of addresses and networks.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(TrustedProxies)
+    }
+}
+
+impl TrustedProxies {
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// Determines the address of the client behind the proxy chain.
+    ///
+    /// Hops of `header` are walked from the closest (the TCP peer) outwards,
+    /// and the first one that is not a trusted proxy is the client. This way a
+    /// client cannot spoof its address by sending its own `X-Forwarded-For`
+    /// header, since only entries appended by trusted proxies are considered.
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap, header: ForwardedHeader) -> IpAddr {
+        let mut client = peer.to_canonical();
+        if !self.is_trusted(client) {
+            return client;
+        }
+
+        for hop in forwarded_chain(headers, header).into_iter().rev() {
+            // An obfuscated or malformed hop cannot be attributed; the last
+            // proxy that reported it is the best we know.
+            let Some(hop) = hop else {
+                break;
+            };
+            client = hop.to_canonical();
+            if !self.is_trusted(client) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// Extracts the chain of forwarded addresses of `header`, oldest first.
+fn forwarded_chain(headers: &HeaderMap, header: ForwardedHeader) -> Vec<Option<IpAddr>> {
+    let elements = headers
+        .get_all(header.as_str())
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+    match header {
+        ForwardedHeader::XForwardedFor => elements.map(|hop| parse_node(hop.trim())).collect(),
+        ForwardedHeader::Forwarded => elements
+            .filter_map(|element| {
+                element.split(';').find_map(|pair| {
+                    let (key, value) = pair.trim().split_once('=')?;
+                    key.eq_ignore_ascii_case("for")
+                        .then(|| parse_node(value.trim_matches('"')))
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Parses a forwarded node: a bare IP, `ip:port`, or `[ipv6]:port`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Ok(ip) = node.parse() {
+        return Some(ip);
+    }
+    if let Some(rest) = node.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+    node.parse::<SocketAddr>().ok().map(|addr| addr.ip())
+}
+
+/// The resolved address of the client that sent the request.
+/// Set by the `resolve_client_ip` middleware for every request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<ClientIp>().copied().ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Client address is not available.",
+        ))
+    }
+}
+
+/// Middleware resolving the client address once, so that every later layer
+/// and handler (rate limiting, audit logs, allowlists) agrees on it.
+/// Requests without connection info (e.g. in tests) are passed through as-is.
+pub async fn resolve_client_ip(
+    State(config): State<Arc<Config>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    if let Some(peer) = peer {
+        let client =
+            config
+                .trusted_proxies
+                .resolve(peer, request.headers(), config.forwarded_header);
+        debug!("Request from {} (peer {})", client, peer);
+        request.extensions_mut().insert(ClientIp(client));
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_cidr_parsing_and_matching() {
+        let network: IpCidr = "10.0.0.0/8".parse().unwrap();
+        assert!(network.contains(ip("10.1.2.3")));
+        assert!(!network.contains(ip("11.0.0.1")));
+        // IPv4-mapped IPv6 addresses match their IPv4 network
+        assert!(network.contains(ip("::ffff:10.0.0.1")));
+
+        let host: IpCidr = "::1".parse().unwrap();
+        assert!(host.contains(ip("::1")));
+        assert!(!host.contains(ip("::2")));
+
+        let everything: IpCidr = "0.0.0.0/0".parse().unwrap();
+        assert!(everything.contains(ip("203.0.113.7")));
+
+        assert!("10.0.0.0/33".parse::<IpCidr>().is_err());
+        assert!("not-an-ip".parse::<IpCidr>().is_err());
+        assert!("10.0.0.1, bogus".parse::<TrustedProxies>().is_err());
+    }
+
+    #[test]
+    fn test_untrusted_peer_headers_are_ignored() {
+        let proxies = TrustedProxies::default();
+        let spoofed = headers(&[("x-forwarded-for", "1.2.3.4")]);
+        assert_eq!(
+            proxies.resolve(ip("203.0.113.7"), &spoofed, ForwardedHeader::XForwardedFor),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn test_client_is_first_untrusted_hop() {
+        let proxies: TrustedProxies = "127.0.0.1, 10.0.0.0/8".parse().unwrap();
+
+        // The client spoofed the first entry; only the one added by our proxy counts
+        let chain = headers(&[("x-forwarded-for", "6.6.6.6, 198.51.100.1, 10.0.0.2")]);
+        assert_eq!(
+            proxies.resolve(ip("127.0.0.1"), &chain, ForwardedHeader::XForwardedFor),
+            ip("198.51.100.1")
+        );
+
+        // Several header lines form a single list
+        let split = headers(&[
+            ("x-forwarded-for", "198.51.100.1"),
+            ("x-forwarded-for", "10.0.0.2"),
+        ]);
+        assert_eq!(
+            proxies.resolve(ip("127.0.0.1"), &split, ForwardedHeader::XForwardedFor),
+            ip("198.51.100.1")
+        );
+
+        // Without headers the trusted peer itself is the client
+        assert_eq!(
+            proxies.resolve(
+                ip("127.0.0.1"),
+                &HeaderMap::new(),
+                ForwardedHeader::XForwardedFor
+            ),
+            ip("127.0.0.1")
+        );
+    }
+
+    #[test]
+    fn test_only_the_configured_header_is_read() {
+        let proxies: TrustedProxies = "10.0.0.1".parse().unwrap();
+        // The proxy appends to X-Forwarded-For; the client sent its own Forwarded
+        let spoofed = headers(&[
+            ("forwarded", "for=6.6.6.6"),
+            ("x-forwarded-for", "198.51.100.1"),
+        ]);
+        assert_eq!(
+            proxies.resolve(ip("10.0.0.1"), &spoofed, ForwardedHeader::XForwardedFor),
+            ip("198.51.100.1")
+        );
+        // Without the header of the proxy, the proxy itself is the client
+        let forwarded_only = headers(&[("forwarded", "for=6.6.6.6")]);
+        assert_eq!(
+            proxies.resolve(
+                ip("10.0.0.1"),
+                &forwarded_only,
+                ForwardedHeader::XForwardedFor
+            ),
+            ip("10.0.0.1")
+        );
+
+        // A proxy writing Forwarded
+        let both = headers(&[
+            ("forwarded", r#"for="[2001:db8::7]:4711";proto=https"#),
+            ("x-forwarded-for", "6.6.6.6"),
+        ]);
+        assert_eq!(
+            proxies.resolve(ip("10.0.0.1"), &both, ForwardedHeader::Forwarded),
+            ip("2001:db8::7")
+        );
+
+        // An obfuscated hop stops the walk at the proxy that reported it
+        let hidden = headers(&[("forwarded", "for=_hidden, for=10.0.0.1")]);
+        assert_eq!(
+            proxies.resolve(ip("10.0.0.1"), &hidden, ForwardedHeader::Forwarded),
+            ip("10.0.0.1")
+        );
+    }
+}
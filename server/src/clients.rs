@@ -0,0 +1,86 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+
+// Maximum edit distance between two normalized names for them to be
+// considered a near-match worth suggesting.
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// Normalizes a client name for fuzzy comparison: lowercase, keeping only
+/// letters and digits, so "Acme Corp", "acme-corp" and "ACME CORP." compare equal.
+pub fn normalize_client_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Classic Levenshtein edit distance between two strings, computed on chars.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b_chars.len()).collect();
+    let mut current = vec![0; b_chars.len() + 1];
+
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b_chars.len()]
+}
+
+/// Finds the known name closest to `name`, if any is close enough to be a
+/// plausible typo or spelling variant. Exact matches are not suggestions.
+pub fn suggest_client_name<'a>(
+    name: &str,
+    known_names: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let normalized = normalize_client_name(name);
+
+    known_names
+        .into_iter()
+        .filter(|known| *known != name)
+        .map(|known| {
+            (
+                known,
+                levenshtein(&normalized, &normalize_client_name(known)),
+            )
+        })
+        .filter(|(_, distance)| *distance <= MAX_SUGGESTION_DISTANCE)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(known, _)| known)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_client_name() {
+        assert_eq!(normalize_client_name("Acme Corp."), "acmecorp");
+        assert_eq!(normalize_client_name("acme-corp"), "acmecorp");
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("acme", "acme"), 0);
+    }
+
+    #[test]
+    fn test_suggest_client_name() {
+        let known = ["Acme Corp", "Globex", "Initech"];
+
+        assert_eq!(suggest_client_name("ACME-CORP", known), Some("Acme Corp"));
+        assert_eq!(suggest_client_name("Globx", known), Some("Globex"));
+        assert_eq!(suggest_client_name("Umbrella", known), None);
+        // An exact, already known name needs no suggestion.
+        assert_eq!(suggest_client_name("Initech", known), None);
+    }
+}
@@ -0,0 +1,119 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use lazy_static::lazy_static;
+use parking_lot::{Mutex, RwLock};
+
+/// Source of the current time: the system clock, or a fixed clock in
+/// end-to-end test mode so that timestamps are reproducible.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Clock {
+    System,
+    /// Starts at `start` and moves forward by one second on every reading,
+    /// so that successive timestamps stay distinct and ordered.
+    Fixed {
+        start: DateTime<Utc>,
+        ticks: i64,
+    },
+}
+
+impl Clock {
+    pub fn now(&mut self) -> DateTime<Utc> {
+        match self {
+            Clock::System => Utc::now(),
+            Clock::Fixed { start, ticks } => {
+                let now = *start + Duration::seconds(*ticks);
+                *ticks += 1;
+                now
+            }
+        }
+    }
+
+    /// Current date in `timezone`, without moving a fixed clock forward.
+    pub fn today(&self, timezone: Tz) -> NaiveDate {
+        let now = match self {
+            Clock::System => Utc::now(),
+            Clock::Fixed { start, ticks } => *start + Duration::seconds(*ticks),
+        };
+        now.with_timezone(&timezone).date_naive()
+    }
+
+    /// Brings a fixed clock back to its start.
+    pub fn reset(&mut self) {
+        if let Clock::Fixed { ticks, .. } = self {
+            *ticks = 0;
+        }
+    }
+}
+
+lazy_static! {
+    // The clock used by the whole server, the system one unless frozen at startup.
+    static ref CLOCK: Mutex<Clock> = Mutex::new(Clock::System);
+    // Timezone whose midnight starts a new day, UTC unless configured.
+    static ref TIMEZONE: RwLock<Tz> = RwLock::new(Tz::UTC);
+}
+
+/// Current time, as read from the server clock.
+pub fn now() -> DateTime<Utc> {
+    CLOCK.lock().now()
+}
+
+/// Current date in the application timezone, as read from the server clock.
+pub fn today() -> NaiveDate {
+    CLOCK.lock().today(timezone())
+}
+
+/// Timezone the days (rollover, current week, overdue tasks) follow.
+pub fn timezone() -> Tz {
+    *TIMEZONE.read()
+}
+
+/// Makes the days follow `timezone` instead of UTC.
+pub fn set_timezone(timezone: Tz) {
+    *TIMEZONE.write() = timezone;
+}
+
+/// Replaces the system clock by a fixed clock starting at `start`.
+pub fn freeze(start: DateTime<Utc>) {
+    *CLOCK.lock() = Clock::Fixed { start, ticks: 0 };
+}
+
+/// Brings a frozen clock back to its start. No-op for the system clock.
+pub fn reset() {
+    CLOCK.lock().reset();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_clock_is_reproducible() {
+        let start: DateTime<Utc> = "2025-07-14T09:00:00Z".parse().unwrap();
+        let mut clock = Clock::Fixed { start, ticks: 0 };
+
+        assert_eq!(clock.now(), start);
+        assert_eq!(clock.now(), start + Duration::seconds(1));
+        assert_eq!(clock.today(Tz::UTC), start.date_naive());
+
+        clock.reset();
+        assert_eq!(clock.now(), start);
+    }
+
+    #[test]
+    fn test_today_follows_the_timezone() {
+        let start: DateTime<Utc> = "2025-07-14T15:00:00Z".parse().unwrap();
+        let clock = Clock::Fixed { start, ticks: 0 };
+
+        assert_eq!(clock.today(Tz::UTC), start.date_naive());
+        // Already the next day at UTC+10, still the same day at UTC-4
+        assert_eq!(
+            clock.today(Tz::Australia__Brisbane),
+            start.date_naive().succ_opt().unwrap()
+        );
+        assert_eq!(clock.today(Tz::America__New_York), start.date_naive());
+    }
+}
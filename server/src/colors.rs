@@ -4,23 +4,27 @@
 // See the LICENSE file in the project root for the full license text.
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use crate::config::DEFAULT_DATA_DIR;
+
+use common::{Client, HexColor, Palette, Task, TaskSummary};
 use lazy_static::lazy_static;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 
-// Define the directory where you want to store the data
-const DATA_DIR: &str = "database";
 const CLIENT_COLORS_FILE_NAME: &str = "client_colors.json";
 
 // Struct to hold the client color map
 #[derive(Serialize, Deserialize)]
 pub struct ClientColorMap {
-    colors: HashMap<String, String>,
+    colors: HashMap<String, HexColor>,
     #[serde(skip)] // Don't serialize the palette
-    palette: Arc<Vec<String>>,
+    palette: Arc<Vec<HexColor>>,
+    // Name of the palette new clients are assigned from
+    #[serde(default)]
+    active_palette: Palette,
     next_color_index: usize,
 }
 
@@ -28,36 +32,87 @@ impl Default for ClientColorMap {
     fn default() -> Self {
         Self {
             colors: HashMap::new(),
-            // A palette of 20 distinct, aesthetically pleasing colors.
-            // These colors are chosen to be relatively distinguishable and work well together.
-            palette: Arc::new(vec![
-                "#1f77b4".to_string(), // Muted blue
-                "#ff7f0e".to_string(), // Orange
-                "#2ca02c".to_string(), // Green
-                "#d62728".to_string(), // Red
-                "#9467bd".to_string(), // Purple
-                "#8c564b".to_string(), // Brown
-                "#e377c2".to_string(), // Pink
-                "#7f7f7f".to_string(), // Grey
-                "#bcbd22".to_string(), // Olive
-                "#17becf".to_string(), // Cyan
-                "#aec7e8".to_string(), // Light blue
-                "#ffbb78".to_string(), // Light orange
-                "#98df8a".to_string(), // Light green
-                "#ff9896".to_string(), // Light red
-                "#c5b0d5".to_string(), // Light purple
-                "#c49c94".to_string(), // Light brown
-                "#f7b6d2".to_string(), // Light pink
-                "#c7c7c7".to_string(), // Light grey
-                "#dbdb8d".to_string(), // Light olive
-                "#9edae5".to_string(), // Light cyan
-            ]),
+            palette: Arc::new(palette_colors(Palette::Default)),
+            active_palette: Palette::Default,
             next_color_index: 0,
         }
     }
 }
 
+impl ClientColorMap {
+    /// Switches the palette future assignments are made from. Clients that
+    /// already have a color keep it.
+    fn set_palette(&mut self, palette: Palette) {
+        if self.active_palette == palette {
+            return;
+        }
+        self.palette = Arc::new(palette_colors(palette));
+        self.active_palette = palette;
+        self.next_color_index = 0;
+    }
+}
+
+/// Returns the colors of a palette, in assignment order.
+pub fn palette_colors(palette: Palette) -> Vec<HexColor> {
+    let colors: &[&str] = match palette {
+        // A palette of 20 distinct, aesthetically pleasing colors.
+        // These colors are chosen to be relatively distinguishable and work well together.
+        Palette::Default => &[
+            "#1f77b4", // Muted blue
+            "#ff7f0e", // Orange
+            "#2ca02c", // Green
+            "#d62728", // Red
+            "#9467bd", // Purple
+            "#8c564b", // Brown
+            "#e377c2", // Pink
+            "#7f7f7f", // Grey
+            "#bcbd22", // Olive
+            "#17becf", // Cyan
+            "#aec7e8", // Light blue
+            "#ffbb78", // Light orange
+            "#98df8a", // Light green
+            "#ff9896", // Light red
+            "#c5b0d5", // Light purple
+            "#c49c94", // Light brown
+            "#f7b6d2", // Light pink
+            "#c7c7c7", // Light grey
+            "#dbdb8d", // Light olive
+            "#9edae5", // Light cyan
+        ],
+        // The Okabe-Ito palette, designed to be told apart with protanopia,
+        // deuteranopia and tritanopia.
+        Palette::Colorblind => &[
+            "#e69f00", // Orange
+            "#56b4e9", // Sky blue
+            "#009e73", // Bluish green
+            "#f0e442", // Yellow
+            "#0072b2", // Blue
+            "#d55e00", // Vermillion
+            "#cc79a7", // Reddish purple
+            "#000000", // Black
+        ],
+        // Dark, saturated colors that stand out against a light background.
+        Palette::HighContrast => &[
+            "#000000", // Black
+            "#004488", // Dark blue
+            "#bb5566", // Dark red
+            "#117733", // Dark green
+            "#882255", // Wine
+            "#ddaa33", // Dark yellow
+            "#332288", // Indigo
+            "#44aa99", // Teal
+        ],
+    };
+    colors
+        .iter()
+        .map(|color| color.parse().expect("palette colors are valid"))
+        .collect()
+}
+
 lazy_static! {
+    // Directory where the client_colors.json file is stored, set at startup.
+    static ref DATA_DIR: RwLock<PathBuf> = RwLock::new(PathBuf::from(DEFAULT_DATA_DIR));
+
     // This is the global, lazily initialized, thread-safe client color map.
     static ref CLIENT_COLORS: Arc<RwLock<ClientColorMap>> = {
         let colors_map = load_client_colors().unwrap_or_else(|e| {
@@ -70,10 +125,24 @@ lazy_static! {
 
 // Helper function to get the full path to the client_colors.json file
 fn get_client_colors_path() -> PathBuf {
-    let mut path = PathBuf::new();
-    path.push(DATA_DIR);
-    path.push(CLIENT_COLORS_FILE_NAME);
-    path
+    DATA_DIR.read().join(CLIENT_COLORS_FILE_NAME)
+}
+
+/// Stores client colors in `data_dir` instead of the default directory, and
+/// loads the colors already saved there.
+#[allow(clippy::uninlined_format_args)]
+pub fn set_data_dir(data_dir: &Path) {
+    *DATA_DIR.write() = data_dir.to_path_buf();
+
+    let colors_map = load_client_colors().unwrap_or_else(|e| {
+        eprintln!(
+            "Warning: Could not load client colors file: {}. Creating new map. Error: {}",
+            get_client_colors_path().display(),
+            e
+        );
+        ClientColorMap::default()
+    });
+    *CLIENT_COLORS.write() = colors_map;
 }
 
 // Function to load client colors from a JSON file
@@ -88,7 +157,7 @@ fn load_client_colors() -> Result<ClientColorMap, Box<dyn std::error::Error>> {
     let mut map: ClientColorMap = serde_json::from_str(&data)?;
 
     // Re-initialize the palette as it's skipped during serialization
-    map.palette = Arc::new(ClientColorMap::default().palette.as_ref().clone());
+    map.palette = Arc::new(palette_colors(map.active_palette));
     // Ensure next_color_index is within bounds after loading
     map.next_color_index %= map.palette.len();
 
@@ -111,7 +180,7 @@ fn save_client_colors(colors_map: &ClientColorMap) -> Result<(), Box<dyn std::er
 /// Function to get or assign a unique color to a client name.
 /// It persists the assignment to a file.
 #[allow(clippy::uninlined_format_args)]
-pub fn get_or_assign_client_color(client_name: &str) -> String {
+pub fn get_or_assign_client_color(client_name: &str) -> HexColor {
     let mut client_colors = CLIENT_COLORS.write(); // Acquire a write lock
 
     // Check if the client already has an assigned color
@@ -137,6 +206,142 @@ pub fn get_or_assign_client_color(client_name: &str) -> String {
     color_to_assign
 }
 
+/// Returns the color assigned to a client, without assigning one.
+pub fn assigned_client_color(client_name: &str) -> Option<HexColor> {
+    CLIENT_COLORS.read().colors.get(client_name).cloned()
+}
+
+/// Overrides the color of a client, e.g. when chosen by hand, and persists it.
+/// The palette position is left untouched.
+#[allow(clippy::uninlined_format_args)]
+pub fn set_client_color(client_name: &str, color: &HexColor) {
+    let mut client_colors = CLIENT_COLORS.write();
+    client_colors
+        .colors
+        .insert(client_name.to_string(), color.clone());
+
+    if let Err(e) = save_client_colors(&client_colors) {
+        eprintln!("Error saving client colors: {}", e);
+    }
+}
+
+/// Returns the palette new clients are currently assigned from.
+pub fn active_palette() -> Palette {
+    CLIENT_COLORS.read().active_palette
+}
+
+/// Switches the palette future color assignments are made from, and persists
+/// the choice. Existing clients keep their colors.
+#[allow(clippy::uninlined_format_args)]
+pub fn set_active_palette(palette: Palette) {
+    let mut client_colors = CLIENT_COLORS.write();
+    if client_colors.active_palette == palette {
+        return;
+    }
+    client_colors.set_palette(palette);
+
+    if let Err(e) = save_client_colors(&client_colors) {
+        eprintln!("Error saving client colors: {}", e);
+    }
+}
+
+/// Forgets every assigned color and restarts from the first color of the
+/// default palette, as on a fresh installation.
+#[allow(clippy::uninlined_format_args)]
+pub fn reset_client_colors() {
+    let mut client_colors = CLIENT_COLORS.write();
+    *client_colors = ClientColorMap::default();
+
+    if let Err(e) = save_client_colors(&client_colors) {
+        eprintln!("Error saving client colors: {}", e);
+    }
+}
+
+/// Parses a `#rrggbb` color into its red, green and blue channels.
+fn parse_rgb(color: &str) -> Option<[u8; 3]> {
+    let digits = color.strip_prefix('#').filter(|d| d.len() == 6)?;
+    let channel = |i: usize| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Relative luminance of a color, as defined by WCAG 2.
+fn relative_luminance([r, g, b]: [u8; 3]) -> f64 {
+    let linear = |channel: u8| {
+        let c = f64::from(channel) / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// Picks black or white, whichever has the higher WCAG contrast ratio on the
+/// given background. Colors that are not `#rrggbb` get black.
+pub fn text_color_for(background: &str) -> String {
+    let Some(rgb) = parse_rgb(background) else {
+        return "#000000".to_string();
+    };
+    let luminance = relative_luminance(rgb);
+    let contrast_with_white = 1.05 / (luminance + 0.05);
+    let contrast_with_black = (luminance + 0.05) / 0.05;
+    if contrast_with_white > contrast_with_black {
+        "#ffffff".to_string()
+    } else {
+        "#000000".to_string()
+    }
+}
+
+/// A lighter variant of a color, mixed with 80% white, for backgrounds such
+/// as task cards. Colors that are not `#rrggbb` are returned unchanged.
+pub fn light_variant_of(color: &str) -> String {
+    mix_with_white(color, 0.8)
+}
+
+/// Mixes a color with a share of white between 0 (the color itself) and 1
+/// (white). Colors that are not `#rrggbb` are returned unchanged.
+pub fn mix_with_white(color: &str, white: f64) -> String {
+    let Some(rgb) = parse_rgb(color) else {
+        return color.to_string();
+    };
+    let [r, g, b] = rgb.map(|c| c + ((255 - c) as f64 * white).round() as u8);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Types carrying a client color, whose derived colors are filled in before
+/// they are returned by the API.
+pub trait ColorMetadata {
+    fn fill_color_metadata(&mut self);
+}
+
+/// Fills the derived colors of a value and returns it, e.g. in `Option::map`.
+pub fn with_color_metadata<T: ColorMetadata>(mut value: T) -> T {
+    value.fill_color_metadata();
+    value
+}
+
+impl ColorMetadata for Task {
+    fn fill_color_metadata(&mut self) {
+        self.client_text_color = text_color_for(&self.client_color);
+        self.client_background_color = light_variant_of(&self.client_color);
+    }
+}
+
+impl ColorMetadata for TaskSummary {
+    fn fill_color_metadata(&mut self) {
+        self.client_text_color = text_color_for(&self.client_color);
+        self.client_background_color = light_variant_of(&self.client_color);
+    }
+}
+
+impl ColorMetadata for Client {
+    fn fill_color_metadata(&mut self) {
+        self.text_color = text_color_for(&self.color);
+        self.background_color = light_variant_of(&self.color);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -148,7 +353,7 @@ mod tests {
     #[allow(dead_code)]
     fn setup_test_env() -> PathBuf {
         let dir = tempdir().unwrap();
-        let db_dir = dir.path().join(DATA_DIR);
+        let db_dir = dir.path().join(DEFAULT_DATA_DIR);
         fs::create_dir(&db_dir).unwrap();
         db_dir.join(CLIENT_COLORS_FILE_NAME)
     }
@@ -223,10 +428,65 @@ mod tests {
         assert_eq!(map.next_color_index, 1);
     }
 
+    #[test]
+    fn test_hex_colors_are_normalized() {
+        let parse = |color: &str| color.parse::<HexColor>().ok();
+        assert_eq!(parse("#1F77B4").unwrap(), "#1f77b4");
+        assert_eq!(parse(" #abc ").unwrap(), "#aabbcc");
+        assert_eq!(parse("#1f77b4").unwrap().rgb(), [0x1f, 0x77, 0xb4]);
+        assert_eq!(parse("1f77b4"), None);
+        assert_eq!(parse("#1f77b"), None);
+        assert_eq!(parse("#ggghhh"), None);
+        assert_eq!(parse("bg-red-100"), None);
+
+        // Malformed colors are refused when read
+        assert!(serde_json::from_str::<HexColor>(r##""#1f77b4""##).is_ok());
+        assert!(serde_json::from_str::<HexColor>(r#""blue""#).is_err());
+    }
+
+    #[test]
+    fn test_switching_palette_keeps_existing_colors() {
+        let mut map = get_clean_map();
+        let existing = assign_color_to_client(&mut map, "Client A");
+
+        // Act
+        map.set_palette(Palette::Colorblind);
+        let next_color = assign_color_to_client(&mut map, "Client B");
+
+        // Assert: Only future assignments come from the new palette
+        assert_eq!(map.colors.get("Client A"), Some(&existing));
+        assert_eq!(next_color, palette_colors(Palette::Colorblind)[0]);
+        assert_eq!(map.next_color_index, 1);
+
+        // The active palette survives a save/load round trip
+        let data = serde_json::to_string(&map).unwrap();
+        let loaded: ClientColorMap = serde_json::from_str(&data).unwrap();
+        assert_eq!(loaded.active_palette, Palette::Colorblind);
+    }
+
+    #[test]
+    fn test_contrast_colors() {
+        // Dark colors get white text, light ones black text
+        assert_eq!(text_color_for("#000000"), "#ffffff");
+        assert_eq!(text_color_for("#1f77b4"), "#ffffff");
+        assert_eq!(text_color_for("#f0e442"), "#000000");
+        assert_eq!(text_color_for("#ffffff"), "#000000");
+        assert_eq!(text_color_for("bg-red-100"), "#000000");
+
+        assert_eq!(light_variant_of("#000000"), "#cccccc");
+        assert_eq!(light_variant_of("#1f77b4"), "#d2e4f0");
+        assert_eq!(light_variant_of("#ffffff"), "#ffffff");
+        assert_eq!(light_variant_of("bg-red-100"), "bg-red-100");
+        // The lighter variant is always readable with black text
+        for color in palette_colors(Palette::Default) {
+            assert_eq!(text_color_for(&light_variant_of(&color)), "#000000");
+        }
+    }
+
     /// This is a test-only helper function that mirrors the logic of
     /// `get_or_assign_client_color` but operates on a mutable map instance
     /// instead of the global `lazy_static`, making it suitable for isolated unit tests.
-    fn assign_color_to_client(map: &mut ClientColorMap, client_name: &str) -> String {
+    fn assign_color_to_client(map: &mut ClientColorMap, client_name: &str) -> HexColor {
         if let Some(color) = map.colors.get(client_name) {
             return color.clone();
         }
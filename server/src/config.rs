@@ -0,0 +1,1764 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::auth::{ApiTokens, SessionConfig, SigningSecret};
+use crate::client_ip::{ForwardedHeader, TrustedProxies};
+use crate::jobs;
+use crate::notify::{Template, Templates};
+use crate::summary::Locale;
+use anyhow::{anyhow, Context, Result};
+use axum::http::{header, HeaderName, Method, Uri};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use common::{Palette, Priority};
+use croner::Cron;
+use lettre::message::Mailbox;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fmt::{self, Display};
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Directory of the SQLite database and `client_colors.json` by default.
+pub const DEFAULT_DATA_DIR: &str = "database";
+
+/// Name of the directory of the application in the XDG data directory.
+const APP_DIR_NAME: &str = "weekly-task-manager";
+
+/// Name of the SQLite database file in the data directory.
+const DATABASE_FILE_NAME: &str = "sqlite.db";
+
+/// Cron expression of the automatic rollover: every day at 23:55.
+const DEFAULT_ROLLOVER_SCHEDULE: &str = "55 23 * * *";
+
+/// Cron expression of the morning digest: every day at 8:00.
+const DEFAULT_DIGEST_SCHEDULE: &str = "0 8 * * *";
+
+/// Runtime settings of the server, read from environment variables.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// SQLite database to open, created if missing (`DATABASE_URL`).
+    /// Defaults to `sqlite.db` in the data directory.
+    pub database_url: String,
+    /// Directory of the files kept next to the database, such as
+    /// `client_colors.json` (`DATA_DIR`).
+    pub data_dir: PathBuf,
+    /// Connection pool and SQLite settings.
+    pub db: DatabaseConfig,
+    /// Scheduled backups of the database.
+    pub backup: BackupConfig,
+    /// Reverse proxies allowed to report the client address through the
+    /// `forwarded_header` (`TRUSTED_PROXIES`).
+    pub trusted_proxies: TrustedProxies,
+    /// Header the trusted proxies write the client address to, the only one
+    /// read (`FORWARDED_HEADER`).
+    pub forwarded_header: ForwardedHeader,
+    /// HTTP connection settings.
+    pub http: HttpConfig,
+    /// Format of the log lines (`LOG_FORMAT`).
+    pub log_format: LogFormat,
+    /// Palette applied at startup, replacing the one chosen through the API
+    /// (`PALETTE`).
+    pub palette: Option<Palette>,
+    /// Minutes of work available on each weekday, used by the planning flow
+    /// (`DAILY_CAPACITY_MINUTES`).
+    pub daily_capacity_minutes: i64,
+    /// Weeks after the current one tasks can be scheduled on, none by
+    /// default (`PLANNING_HORIZON_WEEKS`).
+    pub planning_horizon_weeks: u32,
+    /// Defaults of the tasks created through quick capture.
+    pub capture: CaptureConfig,
+    /// Time the response of a task creation is kept for retries sent with
+    /// the same `Idempotency-Key` (`IDEMPOTENCY_KEY_TTL_HOURS`).
+    pub idempotency_key_ttl: Duration,
+    /// Day open tasks are rolled over to (`ROLLOVER_POLICY`).
+    pub rollover_policy: RolloverPolicy,
+    /// When the automatic rollover runs.
+    pub rollover_schedule: RolloverScheduleConfig,
+    /// Rollovers after which a task is marked stale and its priority raised,
+    /// never when unset (`STALE_AFTER_ROLLOVERS`).
+    pub stale_after_rollovers: Option<i64>,
+    /// Timezone whose midnight starts a new day for the rollover, the
+    /// current week and overdue tasks (`TIMEZONE`, e.g. `Australia/Sydney`).
+    pub timezone: Tz,
+    /// Limit on concurrent imports, exports, backups and reports.
+    pub heavy_ops: HeavyOpsConfig,
+    /// Requests allowed to each client over time.
+    pub rate_limit: RateLimitConfig,
+    /// Name recorded when this instance claims a scheduled rollover or
+    /// backup (`INSTANCE_NAME`). Defaults to the host name and process ID.
+    pub instance_name: String,
+    /// Background jobs turned off, and retries of the failed ones.
+    pub jobs: JobsConfig,
+    /// Retries and timeout of the webhook deliveries.
+    pub webhooks: WebhooksConfig,
+    /// Digest and rollover notifications posted to chat channels.
+    pub notifications: NotificationsConfig,
+    /// Email digest of the open tasks, not sent unless `SMTP_HOST` is set.
+    pub email: Option<EmailConfig>,
+    /// Tokens of the tools allowed to pull `/api/export/table` and
+    /// `/api/export/calendar.ics` (`EXPORT_API_TOKENS`). Both are closed
+    /// when there are none.
+    pub export_tokens: ApiTokens,
+    /// Keys granting access to the whole API (`API_KEYS`), as bearer tokens
+    /// or by signing a browser in. The API is open when there are none.
+    pub api_keys: ApiTokens,
+    /// Cookie sessions of the web UI, disabled unless `SESSION_SECRET` is
+    /// set.
+    pub sessions: Option<SessionConfig>,
+    /// Key signing the read-only share links of weeks (`SHARE_SECRET`).
+    /// Weeks cannot be shared when unset.
+    pub share_secret: Option<SigningSecret>,
+    /// End-to-end test mode (`E2E_MODE`): the clock is frozen, client colors
+    /// restart from the palette, and `POST /api/test/reset` wipes all data.
+    /// Never enable it on a database whose data matters.
+    pub e2e_mode: bool,
+    /// Time the frozen clock starts at in end-to-end test mode
+    /// (`E2E_START_TIME`, RFC 3339).
+    pub e2e_start_time: DateTime<Utc>,
+    /// Serves the embedded web UI at `/` (`SERVE_WEB_UI`). Turn it off when
+    /// the pages are served by a separate web server.
+    pub serve_web_ui: bool,
+    /// Frontend build served at `/` in place of the embedded pages
+    /// (`WEB_ROOT`), e.g. the `dist` directory of a single-page app.
+    pub web_root: Option<PathBuf>,
+    /// Exposes `/api/debug/faults`, where delays and error responses can be
+    /// injected into chosen routes (`FAULT_INJECTION`). Only honoured in
+    /// debug builds.
+    pub fault_injection: bool,
+}
+
+/// Connection pool and SQLite tuning. Connections run in WAL mode so that
+/// readers do not block the writer, and wait for a lock instead of failing
+/// at once with "database is locked".
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    /// Maximum number of open connections (`DB_MAX_CONNECTIONS`).
+    pub max_connections: u32,
+    /// Time a request waits for a free connection before failing
+    /// (`DB_ACQUIRE_TIMEOUT_SECS`).
+    pub acquire_timeout: Duration,
+    /// Time a connection waits for a lock held by another one before failing
+    /// (`DB_BUSY_TIMEOUT_MS`).
+    pub busy_timeout: Duration,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            max_connections: 10,
+            acquire_timeout: Duration::from_secs(30),
+            busy_timeout: Duration::from_millis(5000),
+        }
+    }
+}
+
+/// Scheduled backups, written to `dir` every `interval` and at startup.
+/// Disabled unless a directory is set.
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    /// Directory the backups are written to (`BACKUP_DIR`).
+    pub dir: Option<PathBuf>,
+    /// Time between two backups (`BACKUP_INTERVAL_HOURS`).
+    pub interval: Duration,
+    /// Number of backups kept, older ones are removed (`BACKUP_KEEP`).
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        BackupConfig {
+            dir: None,
+            interval: Duration::from_secs(24 * 60 * 60),
+            keep: 7,
+        }
+    }
+}
+
+/// Browser origins allowed to call the API, as `*` for any, `none` for
+/// the pages served by the server itself only, or a comma-separated list
+/// such as `https://tasks.example.com`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorsOrigins {
+    Any,
+    /// Same-origin only: cross-origin requests get no CORS headers, so
+    /// browsers refuse them.
+    None,
+    List(Vec<String>),
+}
+
+/// Any origin while developing, where the frontends are served from other
+/// ports; same-origin only in release builds.
+impl Default for CorsOrigins {
+    fn default() -> Self {
+        if cfg!(debug_assertions) {
+            CorsOrigins::Any
+        } else {
+            CorsOrigins::None
+        }
+    }
+}
+
+impl FromStr for CorsOrigins {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "*" => return Ok(CorsOrigins::Any),
+            "none" => return Ok(CorsOrigins::None),
+            _ => {}
+        }
+        let mut origins = Vec::new();
+        for origin in s.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+            // An origin is a scheme and a host, with an optional port but
+            // no path, not even a trailing slash.
+            let host = origin
+                .strip_prefix("https://")
+                .or_else(|| origin.strip_prefix("http://"))
+                .ok_or_else(|| format!("origin '{origin}' must start with http:// or https://"))?;
+            if host.is_empty() || host.contains('/') || !host.chars().all(|c| c.is_ascii_graphic())
+            {
+                return Err(format!(
+                    "origin '{origin}' must be a scheme and a host, without a path"
+                ));
+            }
+            origins.push(origin.to_string());
+        }
+        if origins.is_empty() {
+            return Err("expected '*', 'none' or at least one origin".to_string());
+        }
+        Ok(CorsOrigins::List(origins))
+    }
+}
+
+/// Methods or headers allowed in cross-origin requests: `*` for any, or a
+/// comma-separated list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorsList<T> {
+    Any,
+    List(Vec<T>),
+}
+
+impl<T: FromStr> FromStr for CorsList<T>
+where
+    T::Err: Display,
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "*" {
+            return Ok(CorsList::Any);
+        }
+        let items = s
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| item.parse().map_err(|e| format!("'{item}': {e}")))
+            .collect::<Result<Vec<T>, String>>()?;
+        if items.is_empty() {
+            return Err("expected '*' or at least one value".to_string());
+        }
+        Ok(CorsList::List(items))
+    }
+}
+
+/// Cross-origin requests accepted from browsers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API (`CORS_ALLOWED_ORIGINS`).
+    pub allowed_origins: CorsOrigins,
+    /// Methods they may use (`CORS_ALLOWED_METHODS`).
+    pub allowed_methods: CorsList<Method>,
+    /// Request headers they may send (`CORS_ALLOWED_HEADERS`).
+    pub allowed_headers: CorsList<HeaderName>,
+    /// Whether they may send cookies and `Authorization` headers
+    /// (`CORS_ALLOW_CREDENTIALS`).
+    pub allow_credentials: bool,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allowed_origins: CorsOrigins::default(),
+            allowed_methods: CorsList::List(vec![
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::PATCH,
+                Method::DELETE,
+            ]),
+            allowed_headers: CorsList::List(vec![header::CONTENT_TYPE, header::ACCEPT]),
+            allow_credentials: false,
+        }
+    }
+}
+
+impl CorsConfig {
+    /// Browsers ignore wildcards in credentialed requests, so credentials
+    /// require explicit origins, methods and headers.
+    pub fn check(&self) -> Result<()> {
+        if self.allow_credentials
+            && (self.allowed_origins == CorsOrigins::Any
+                || self.allowed_methods == CorsList::Any
+                || self.allowed_headers == CorsList::Any)
+        {
+            return Err(anyhow!(
+                "CORS_ALLOW_CREDENTIALS requires explicit CORS_ALLOWED_ORIGINS, CORS_ALLOWED_METHODS and CORS_ALLOWED_HEADERS, not '*'"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Format of the log lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human readable lines (`text`).
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors (`json`).
+    Json,
+}
+
+impl LogFormat {
+    pub const ALL: [LogFormat; 2] = [LogFormat::Text, LogFormat::Json];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LogFormat::ALL
+            .into_iter()
+            .find(|format| format.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("unknown log format '{s}', expected text or json"))
+    }
+}
+
+/// Day the open tasks of today are rolled over to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RolloverPolicy {
+    /// The next day, weekends included (`next-day`).
+    #[default]
+    NextDay,
+    /// The next day from Monday to Friday (`business-day`).
+    BusinessDay,
+    /// The next day from Monday to Friday that is not in the holidays
+    /// table (`business-day-holidays`).
+    BusinessDayHolidays,
+}
+
+impl RolloverPolicy {
+    pub const ALL: [RolloverPolicy; 3] = [
+        RolloverPolicy::NextDay,
+        RolloverPolicy::BusinessDay,
+        RolloverPolicy::BusinessDayHolidays,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RolloverPolicy::NextDay => "next-day",
+            RolloverPolicy::BusinessDay => "business-day",
+            RolloverPolicy::BusinessDayHolidays => "business-day-holidays",
+        }
+    }
+}
+
+impl FromStr for RolloverPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RolloverPolicy::ALL
+            .into_iter()
+            .find(|policy| policy.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("unknown rollover policy '{s}'"))
+    }
+}
+
+/// When the automatic rollover runs, in the application timezone. The
+/// default runs it just before midnight, so that the open tasks of the
+/// ending day move on.
+#[derive(Debug, Clone)]
+pub struct RolloverScheduleConfig {
+    /// Cron expression of the runs, minute first (`ROLLOVER_SCHEDULE`).
+    pub cron: Cron,
+    /// Upper bound of the random delay added to each run
+    /// (`ROLLOVER_JITTER_SECS`).
+    pub jitter: Duration,
+}
+
+impl Default for RolloverScheduleConfig {
+    fn default() -> Self {
+        RolloverScheduleConfig {
+            cron: Cron::new(DEFAULT_ROLLOVER_SCHEDULE)
+                .parse()
+                .expect("the default rollover schedule is valid"),
+            jitter: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Background jobs settings, see `jobs::run_job`.
+#[derive(Debug, Clone)]
+pub struct JobsConfig {
+    /// Jobs that do not run on this instance, from `jobs::JOB_NAMES`
+    /// (`JOBS_DISABLED`, comma-separated).
+    pub disabled: Vec<String>,
+    /// Attempts made at each run before it is recorded as failed
+    /// (`JOB_MAX_ATTEMPTS`).
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after each further failure
+    /// (`JOB_RETRY_BACKOFF_SECS`).
+    pub retry_backoff: Duration,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        JobsConfig {
+            disabled: Vec::new(),
+            max_attempts: 3,
+            retry_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Webhook delivery settings, see `webhooks::run_delivery_worker`.
+#[derive(Debug, Clone)]
+pub struct WebhooksConfig {
+    /// Attempts made at each delivery before it is recorded as failed
+    /// (`WEBHOOK_MAX_ATTEMPTS`).
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after each further failure
+    /// (`WEBHOOK_RETRY_BACKOFF_SECS`).
+    pub retry_backoff: Duration,
+    /// Time a receiver has to answer before the attempt fails
+    /// (`WEBHOOK_TIMEOUT_SECS`).
+    pub timeout: Duration,
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        WebhooksConfig {
+            max_attempts: 6,
+            retry_backoff: Duration::from_secs(30),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Notifications posted to chat channels, see `notify`. None is sent unless
+/// a channel is configured.
+#[derive(Clone)]
+pub struct NotificationsConfig {
+    /// Slack incoming webhook the notifications are posted to
+    /// (`SLACK_WEBHOOK_URL`). Anyone holding it can post, keep it secret.
+    pub slack_webhook_url: Option<String>,
+    /// When the digest of the open tasks of the day is posted, in the
+    /// application timezone (`NOTIFY_DIGEST_SCHEDULE`).
+    pub digest_schedule: Cron,
+    /// A scheduled rollover moving more tasks than this is notified
+    /// (`NOTIFY_ROLLOVER_THRESHOLD`).
+    pub rollover_threshold: usize,
+    /// Texts of the notifications.
+    pub templates: Templates,
+}
+
+impl NotificationsConfig {
+    pub fn has_channels(&self) -> bool {
+        self.slack_webhook_url.is_some()
+    }
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        NotificationsConfig {
+            slack_webhook_url: None,
+            digest_schedule: Cron::new(DEFAULT_DIGEST_SCHEDULE)
+                .parse()
+                .expect("the default digest schedule is valid"),
+            rollover_threshold: 10,
+            templates: Templates::default(),
+        }
+    }
+}
+
+impl fmt::Debug for NotificationsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NotificationsConfig")
+            .field("slack", &self.slack_webhook_url.is_some())
+            .field("digest_schedule", &self.digest_schedule)
+            .field("rollover_threshold", &self.rollover_threshold)
+            .field("templates", &self.templates)
+            .finish()
+    }
+}
+
+/// How the connection to the SMTP server is secured (`SMTP_TLS`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SmtpTls {
+    /// A plain connection upgraded with STARTTLS, on port 587 by default
+    /// (`starttls`).
+    #[default]
+    StartTls,
+    /// TLS from the start, on port 465 by default (`tls`).
+    Tls,
+    /// No encryption, on port 25 by default, for a relay on a trusted
+    /// network (`none`).
+    None,
+}
+
+impl SmtpTls {
+    pub const ALL: [SmtpTls; 3] = [SmtpTls::StartTls, SmtpTls::Tls, SmtpTls::None];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SmtpTls::StartTls => "starttls",
+            SmtpTls::Tls => "tls",
+            SmtpTls::None => "none",
+        }
+    }
+
+    pub fn default_port(&self) -> u16 {
+        match self {
+            SmtpTls::StartTls => 587,
+            SmtpTls::Tls => 465,
+            SmtpTls::None => 25,
+        }
+    }
+}
+
+impl FromStr for SmtpTls {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SmtpTls::ALL
+            .into_iter()
+            .find(|tls| tls.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("expected one of starttls, tls, none, got '{s}'"))
+    }
+}
+
+/// Open tasks listed by the email digest (`EMAIL_DIGEST_PERIOD`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DigestPeriod {
+    /// Those of the day it is sent (`day`).
+    #[default]
+    Day,
+    /// Those of the week it is sent, Monday to Sunday (`week`).
+    Week,
+}
+
+impl DigestPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestPeriod::Day => "day",
+            DigestPeriod::Week => "week",
+        }
+    }
+
+    /// Cron expression of the digest when none is configured: every day,
+    /// or every Monday, at 8:00.
+    fn default_schedule(&self) -> &'static str {
+        match self {
+            DigestPeriod::Day => DEFAULT_DIGEST_SCHEDULE,
+            DigestPeriod::Week => "0 8 * * 1",
+        }
+    }
+}
+
+impl FromStr for DigestPeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [DigestPeriod::Day, DigestPeriod::Week]
+            .into_iter()
+            .find(|period| period.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("expected day or week, got '{s}'"))
+    }
+}
+
+/// Email digest of the open tasks, sent over SMTP, see `email`. Enabled by
+/// `SMTP_HOST`.
+#[derive(Clone)]
+pub struct EmailConfig {
+    /// SMTP server the digest is sent through (`SMTP_HOST`).
+    pub host: String,
+    /// Port of the server (`SMTP_PORT`), by default the usual one of `tls`.
+    pub port: u16,
+    /// How the connection is secured (`SMTP_TLS`).
+    pub tls: SmtpTls,
+    /// User name and password signing in to the server, none when the
+    /// server takes mail without (`SMTP_USERNAME`, `SMTP_PASSWORD`).
+    pub credentials: Option<(String, String)>,
+    /// Sender of the digest (`EMAIL_FROM`), e.g. `Tasks <tasks@example.com>`.
+    pub from: Mailbox,
+    /// Recipients of the digest (`EMAIL_DIGEST_TO`, comma-separated).
+    pub recipients: Vec<Mailbox>,
+    /// When the digest is sent, in the application timezone
+    /// (`EMAIL_DIGEST_SCHEDULE`).
+    pub digest_schedule: Cron,
+    /// Open tasks the digest lists (`EMAIL_DIGEST_PERIOD`).
+    pub digest_period: DigestPeriod,
+    /// Language of the digest, its dates and the summary of the week before
+    /// (`EMAIL_DIGEST_LOCALE`).
+    pub digest_locale: Locale,
+}
+
+impl fmt::Debug for EmailConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmailConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("tls", &self.tls)
+            .field(
+                "username",
+                &self.credentials.as_ref().map(|(username, _)| username),
+            )
+            .field("from", &self.from)
+            .field("recipients", &self.recipients)
+            .field("digest_schedule", &self.digest_schedule)
+            .field("digest_period", &self.digest_period)
+            .field("digest_locale", &self.digest_locale)
+            .finish()
+    }
+}
+
+/// Defaults applied to the tasks created by `POST /api/capture`, which only
+/// needs a description.
+#[derive(Debug, Clone)]
+pub struct CaptureConfig {
+    /// Client of captured tasks that do not name one (`CAPTURE_DEFAULT_CLIENT`).
+    pub default_client: String,
+    /// Priority of captured tasks that do not give one, none when unset
+    /// (`CAPTURE_DEFAULT_PRIORITY`, a level or a name such as `high`).
+    pub default_priority: Option<Priority>,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        CaptureConfig {
+            default_client: "Inbox".to_string(),
+            default_priority: None,
+        }
+    }
+}
+
+/// Limit on the heavy operations running at once. Each of them can hold
+/// a connection for a while, so they are kept well below the pool size.
+#[derive(Debug, Clone)]
+pub struct HeavyOpsConfig {
+    /// Heavy operations allowed to run at once (`HEAVY_OPS_MAX_CONCURRENT`).
+    pub max_concurrent: usize,
+    /// Delay suggested to rejected clients in `Retry-After`
+    /// (`HEAVY_OPS_RETRY_AFTER_SECS`).
+    pub retry_after: Duration,
+}
+
+impl Default for HeavyOpsConfig {
+    fn default() -> Self {
+        HeavyOpsConfig {
+            max_concurrent: 2,
+            retry_after: Duration::from_secs(5),
+        }
+    }
+}
+
+/// What the requests of one client are counted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitKey {
+    /// Address of the client, behind trusted proxies included (`ip`).
+    #[default]
+    Ip,
+    /// The API key the request carries, or the address of the client when
+    /// it has none of the `API_KEYS` (`api-key`).
+    ApiKey,
+}
+
+impl RateLimitKey {
+    pub const ALL: [RateLimitKey; 2] = [RateLimitKey::Ip, RateLimitKey::ApiKey];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitKey::Ip => "ip",
+            RateLimitKey::ApiKey => "api-key",
+        }
+    }
+}
+
+impl FromStr for RateLimitKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RateLimitKey::ALL
+            .into_iter()
+            .find(|key| key.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("unknown rate limit key '{s}', expected ip or api-key"))
+    }
+}
+
+/// Requests allowed to each client, refilled at a steady rate, so that a
+/// runaway script cannot keep the SQLite writer busy. Off unless
+/// `RATE_LIMIT_PER_MINUTE` is set.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitConfig {
+    /// Requests a client may make per minute on average
+    /// (`RATE_LIMIT_PER_MINUTE`, 0 to disable).
+    pub per_minute: Option<u32>,
+    /// Requests a client may make at once after being idle
+    /// (`RATE_LIMIT_BURST`), the per-minute rate when unset.
+    pub burst: Option<u32>,
+    /// What requests are counted by (`RATE_LIMIT_KEY`).
+    pub key: RateLimitKey,
+}
+
+/// Connection-level HTTP tuning. The defaults suit a handful of clients;
+/// deployments serving long-lived streams to many dashboards may want to
+/// raise the stream limit and enable HTTP/2 pings.
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    /// Address and port the server listens on (`BIND_ADDRESS`, `PORT`).
+    pub listen: SocketAddr,
+    /// Cross-origin requests accepted from browsers.
+    pub cors: CorsConfig,
+    /// Accept HTTP/2 (prior knowledge / h2c) next to HTTP/1.1 (`HTTP2_ENABLED`).
+    pub http2_enabled: bool,
+    /// Keep HTTP/1.1 connections open between requests (`HTTP1_KEEP_ALIVE`).
+    pub http1_keep_alive: bool,
+    /// Time allowed for a client to send the request headers before the
+    /// connection is closed (`HTTP1_HEADER_READ_TIMEOUT_SECS`).
+    pub http1_header_read_timeout: Duration,
+    /// Interval of HTTP/2 keep-alive pings, disabled when unset
+    /// (`HTTP2_KEEP_ALIVE_INTERVAL_SECS`).
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// Time to wait for a ping acknowledgement before closing the
+    /// connection (`HTTP2_KEEP_ALIVE_TIMEOUT_SECS`).
+    pub http2_keep_alive_timeout: Duration,
+    /// Maximum number of concurrent streams per HTTP/2 connection
+    /// (`HTTP2_MAX_CONCURRENT_STREAMS`).
+    pub http2_max_concurrent_streams: u32,
+    /// Time allowed to handle a request, body included, before answering
+    /// `408 Request Timeout`; no limit when unset (`REQUEST_TIMEOUT_SECS`,
+    /// 0 to disable).
+    pub request_timeout: Option<Duration>,
+    /// Largest request body accepted, larger ones getting `413 Payload Too
+    /// Large` (`MAX_BODY_BYTES`). Backup restores have their own limit.
+    pub max_body_bytes: usize,
+    /// HTTPS served directly by the server, plain HTTP when unset.
+    pub tls: Option<TlsConfig>,
+}
+
+/// Certificate and private key of native HTTPS (`TLS_CERT_PATH`,
+/// `TLS_KEY_PATH`), both PEM files such as the ones issued by Let's Encrypt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// Certificate chain, leaf first.
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    /// How often the files are checked for a renewed certificate, never
+    /// when unset (`TLS_RELOAD_INTERVAL_SECS`, 0 to disable).
+    pub reload_interval: Option<Duration>,
+}
+
+impl TlsConfig {
+    /// Default interval between checks for a renewed certificate.
+    pub const DEFAULT_RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+    pub fn new(cert_path: PathBuf, key_path: PathBuf) -> Self {
+        TlsConfig {
+            cert_path,
+            key_path,
+            reload_interval: Some(TlsConfig::DEFAULT_RELOAD_INTERVAL),
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            listen: SocketAddr::from(([0, 0, 0, 0], 3000)),
+            cors: CorsConfig::default(),
+            http2_enabled: true,
+            http1_keep_alive: true,
+            http1_header_read_timeout: Duration::from_secs(30),
+            http2_keep_alive_interval: None,
+            http2_keep_alive_timeout: Duration::from_secs(20),
+            http2_max_concurrent_streams: 200,
+            request_timeout: Some(Duration::from_secs(30)),
+            // The default of axum
+            max_body_bytes: 2 * 1024 * 1024,
+            tls: None,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            database_url: database_url_in(&PathBuf::from(DEFAULT_DATA_DIR)),
+            data_dir: PathBuf::from(DEFAULT_DATA_DIR),
+            db: DatabaseConfig::default(),
+            backup: BackupConfig::default(),
+            trusted_proxies: TrustedProxies::default(),
+            forwarded_header: ForwardedHeader::default(),
+            http: HttpConfig::default(),
+            log_format: LogFormat::default(),
+            palette: None,
+            daily_capacity_minutes: 6 * 60,
+            planning_horizon_weeks: 0,
+            capture: CaptureConfig::default(),
+            idempotency_key_ttl: Duration::from_secs(24 * 60 * 60),
+            rollover_policy: RolloverPolicy::default(),
+            rollover_schedule: RolloverScheduleConfig::default(),
+            stale_after_rollovers: None,
+            timezone: Tz::UTC,
+            heavy_ops: HeavyOpsConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            instance_name: default_instance_name(),
+            jobs: JobsConfig::default(),
+            webhooks: WebhooksConfig::default(),
+            notifications: NotificationsConfig::default(),
+            email: None,
+            export_tokens: ApiTokens::default(),
+            api_keys: ApiTokens::default(),
+            sessions: None,
+            share_secret: None,
+            e2e_mode: false,
+            // 2025-07-14T09:00:00Z, a Monday so that the whole week is ahead
+            e2e_start_time: DateTime::from_timestamp(1_752_483_600, 0).unwrap_or_default(),
+            serve_web_ui: true,
+            web_root: None,
+            fault_injection: false,
+        }
+    }
+}
+
+impl Config {
+    /// Builds the configuration from the environment. Unset variables keep
+    /// their defaults; malformed ones are an error so a typo is noticed at startup.
+    pub fn from_env() -> Result<Self> {
+        Config::load(None)
+    }
+
+    /// Builds the configuration from the TOML file at `path`, if any, and
+    /// the environment, whose variables take precedence over the file.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let file = match path {
+            Some(path) => ConfigFile::read(path)?,
+            None => ConfigFile::default(),
+        };
+        let env = env::vars_os().map(|(name, value)| {
+            (
+                name.to_string_lossy().into_owned(),
+                value.to_string_lossy().into_owned(),
+            )
+        });
+        let settings = Settings::new(file, env);
+        let config = Config::from_settings(&settings)?;
+        settings.check_all_used()?;
+        Ok(config)
+    }
+
+    fn from_settings(settings: &Settings) -> Result<Self> {
+        let mut config = Config::default();
+
+        let data_dir = match settings.var::<PathBuf>("DATA_DIR")? {
+            Some(data_dir) => data_dir,
+            None => default_data_dir(),
+        };
+        config.database_url = database_url_in(&data_dir);
+        config.data_dir = data_dir;
+        if let Some(database_url) = settings.var("DATABASE_URL")? {
+            config.database_url = database_url;
+        }
+
+        let db = &mut config.db;
+        if let Some(connections) = settings.var("DB_MAX_CONNECTIONS")? {
+            db.max_connections = connections;
+        }
+        if let Some(secs) = settings.var("DB_ACQUIRE_TIMEOUT_SECS")? {
+            db.acquire_timeout = Duration::from_secs(secs);
+        }
+        if let Some(ms) = settings.var("DB_BUSY_TIMEOUT_MS")? {
+            db.busy_timeout = Duration::from_millis(ms);
+        }
+
+        let backup = &mut config.backup;
+        // A relative backup directory lives in the data directory.
+        backup.dir = settings
+            .var::<PathBuf>("BACKUP_DIR")?
+            .map(|dir| config.data_dir.join(dir));
+        if let Some(hours) = settings.var::<u64>("BACKUP_INTERVAL_HOURS")? {
+            if hours == 0 {
+                return Err(anyhow!(
+                    "Invalid BACKUP_INTERVAL_HOURS '0': must be at least 1"
+                ));
+            }
+            backup.interval = Duration::from_secs(hours * 60 * 60);
+        }
+        if let Some(keep) = settings.var("BACKUP_KEEP")? {
+            backup.keep = keep;
+        }
+
+        if let Some(trusted_proxies) = settings.var("TRUSTED_PROXIES")? {
+            config.trusted_proxies = trusted_proxies;
+        }
+        if let Some(header) = settings.var("FORWARDED_HEADER")? {
+            config.forwarded_header = header;
+        }
+
+        config.palette = settings.var("PALETTE")?;
+        if let Some(minutes) = settings.var("DAILY_CAPACITY_MINUTES")? {
+            config.daily_capacity_minutes = minutes;
+        }
+        if let Some(weeks) = settings.var("PLANNING_HORIZON_WEEKS")? {
+            config.planning_horizon_weeks = weeks;
+        }
+        if let Some(client) = settings.var::<String>("CAPTURE_DEFAULT_CLIENT")? {
+            if client.is_empty() {
+                return Err(anyhow!("Invalid CAPTURE_DEFAULT_CLIENT: must not be empty"));
+            }
+            config.capture.default_client = client;
+        }
+        config.capture.default_priority = settings.var("CAPTURE_DEFAULT_PRIORITY")?;
+        if let Some(hours) = settings.var::<u64>("IDEMPOTENCY_KEY_TTL_HOURS")? {
+            if hours == 0 {
+                return Err(anyhow!(
+                    "Invalid IDEMPOTENCY_KEY_TTL_HOURS '0': must be at least 1"
+                ));
+            }
+            config.idempotency_key_ttl = Duration::from_secs(hours * 60 * 60);
+        }
+        if let Some(policy) = settings.var("ROLLOVER_POLICY")? {
+            config.rollover_policy = policy;
+        }
+        if let Some(threshold) = settings.var::<i64>("STALE_AFTER_ROLLOVERS")? {
+            if threshold < 1 {
+                return Err(anyhow!(
+                    "Invalid STALE_AFTER_ROLLOVERS '{threshold}': must be at least 1"
+                ));
+            }
+            config.stale_after_rollovers = Some(threshold);
+        }
+        if let Some(timezone) = settings.var("TIMEZONE")? {
+            config.timezone = timezone;
+        }
+        if let Some(cron) = settings.var::<String>("ROLLOVER_SCHEDULE")? {
+            config.rollover_schedule.cron = parse_cron(&cron)
+                .map_err(|e| anyhow!("Invalid ROLLOVER_SCHEDULE '{cron}': {e}"))?;
+        }
+        if let Some(secs) = settings.var("ROLLOVER_JITTER_SECS")? {
+            config.rollover_schedule.jitter = Duration::from_secs(secs);
+        }
+        if let Some(max_concurrent) = settings.var::<usize>("HEAVY_OPS_MAX_CONCURRENT")? {
+            if max_concurrent == 0 {
+                return Err(anyhow!(
+                    "Invalid HEAVY_OPS_MAX_CONCURRENT '0': must be at least 1"
+                ));
+            }
+            config.heavy_ops.max_concurrent = max_concurrent;
+        }
+        if let Some(secs) = settings.var("HEAVY_OPS_RETRY_AFTER_SECS")? {
+            config.heavy_ops.retry_after = Duration::from_secs(secs);
+        }
+        if let Some(per_minute) = settings.var::<u32>("RATE_LIMIT_PER_MINUTE")? {
+            config.rate_limit.per_minute = (per_minute > 0).then_some(per_minute);
+        }
+        if let Some(burst) = settings.var::<u32>("RATE_LIMIT_BURST")? {
+            if burst == 0 {
+                return Err(anyhow!("Invalid RATE_LIMIT_BURST '0': must be at least 1"));
+            }
+            config.rate_limit.burst = Some(burst);
+        }
+        if let Some(key) = settings.var("RATE_LIMIT_KEY")? {
+            config.rate_limit.key = key;
+        }
+        if let Some(name) = settings.var::<String>("INSTANCE_NAME")? {
+            if name.is_empty() {
+                return Err(anyhow!("Invalid INSTANCE_NAME: must not be empty"));
+            }
+            config.instance_name = name;
+        }
+        if let Some(disabled) = settings.var::<String>("JOBS_DISABLED")? {
+            for name in disabled.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                if !jobs::JOB_NAMES.contains(&name) {
+                    return Err(anyhow!(
+                        "Invalid JOBS_DISABLED job '{name}': expected one of {}",
+                        jobs::JOB_NAMES.join(", ")
+                    ));
+                }
+                config.jobs.disabled.push(name.to_string());
+            }
+        }
+        if let Some(attempts) = settings.var::<u32>("JOB_MAX_ATTEMPTS")? {
+            if attempts == 0 {
+                return Err(anyhow!("Invalid JOB_MAX_ATTEMPTS '0': must be at least 1"));
+            }
+            config.jobs.max_attempts = attempts;
+        }
+        if let Some(secs) = settings.var("JOB_RETRY_BACKOFF_SECS")? {
+            config.jobs.retry_backoff = Duration::from_secs(secs);
+        }
+        if let Some(attempts) = settings.var::<u32>("WEBHOOK_MAX_ATTEMPTS")? {
+            if attempts == 0 {
+                return Err(anyhow!(
+                    "Invalid WEBHOOK_MAX_ATTEMPTS '0': must be at least 1"
+                ));
+            }
+            config.webhooks.max_attempts = attempts;
+        }
+        if let Some(secs) = settings.var("WEBHOOK_RETRY_BACKOFF_SECS")? {
+            config.webhooks.retry_backoff = Duration::from_secs(secs);
+        }
+        if let Some(secs) = settings.var::<u64>("WEBHOOK_TIMEOUT_SECS")? {
+            if secs == 0 {
+                return Err(anyhow!(
+                    "Invalid WEBHOOK_TIMEOUT_SECS '0': must be at least 1"
+                ));
+            }
+            config.webhooks.timeout = Duration::from_secs(secs);
+        }
+        // Parsed apart so that a rejected value is not echoed in the logs.
+        if let Some(url) = settings.var::<String>("SLACK_WEBHOOK_URL")? {
+            let valid = url.parse::<Uri>().is_ok_and(|uri| {
+                matches!(uri.scheme_str(), Some("http" | "https")) && uri.host().is_some()
+            });
+            if !valid {
+                return Err(anyhow!(
+                    "Invalid SLACK_WEBHOOK_URL: expected an http(s) URL"
+                ));
+            }
+            config.notifications.slack_webhook_url = Some(url);
+        }
+        if let Some(cron) = settings.var::<String>("NOTIFY_DIGEST_SCHEDULE")? {
+            config.notifications.digest_schedule = parse_cron(&cron)
+                .map_err(|e| anyhow!("Invalid NOTIFY_DIGEST_SCHEDULE '{cron}': {e}"))?;
+        }
+        if let Some(threshold) = settings.var("NOTIFY_ROLLOVER_THRESHOLD")? {
+            config.notifications.rollover_threshold = threshold;
+        }
+        let templates = &mut config.notifications.templates;
+        for (name, template, names) in [
+            (
+                "NOTIFY_DIGEST_TEMPLATE",
+                &mut templates.digest,
+                Templates::DIGEST_NAMES,
+            ),
+            (
+                "NOTIFY_DIGEST_TASK_TEMPLATE",
+                &mut templates.digest_task,
+                Templates::DIGEST_TASK_NAMES,
+            ),
+            (
+                "NOTIFY_ROLLOVER_TEMPLATE",
+                &mut templates.rollover,
+                Templates::ROLLOVER_NAMES,
+            ),
+        ] {
+            if let Some(text) = settings.var::<String>(name)? {
+                *template =
+                    Template::parse(&text, names).map_err(|e| anyhow!("Invalid {name}: {e}"))?;
+            }
+        }
+        config.email = email_config(settings)?;
+        if let Some(tokens) = settings.var::<String>("EXPORT_API_TOKENS")? {
+            config.export_tokens = tokens
+                .parse()
+                .map_err(|e| anyhow!("Invalid EXPORT_API_TOKENS: {e}"))?;
+        }
+        if let Some(keys) = settings.var::<String>("API_KEYS")? {
+            config.api_keys = keys.parse().map_err(|e| anyhow!("Invalid API_KEYS: {e}"))?;
+        }
+        if let Some(secret) = settings.var::<String>("SESSION_SECRET")? {
+            let secret: SigningSecret = secret
+                .parse()
+                .map_err(|e| anyhow!("Invalid SESSION_SECRET: {e}"))?;
+            if config.api_keys.is_empty() {
+                return Err(anyhow!(
+                    "SESSION_SECRET requires API_KEYS, which browsers sign in with"
+                ));
+            }
+            config.sessions = Some(SessionConfig::new(secret));
+        }
+        let session_ttl = settings.var::<i64>("SESSION_TTL_HOURS")?;
+        let secure_cookie = settings.var("SESSION_COOKIE_SECURE")?;
+        if let Some(sessions) = &mut config.sessions {
+            if let Some(hours) = session_ttl {
+                if hours <= 0 {
+                    return Err(anyhow!(
+                        "Invalid SESSION_TTL_HOURS '{hours}': must be at least 1"
+                    ));
+                }
+                sessions.ttl = chrono::Duration::hours(hours);
+            }
+            if let Some(secure) = secure_cookie {
+                sessions.secure_cookie = secure;
+            }
+        }
+        if let Some(secret) = settings.var::<String>("SHARE_SECRET")? {
+            config.share_secret = Some(
+                secret
+                    .parse()
+                    .map_err(|e| anyhow!("Invalid SHARE_SECRET: {e}"))?,
+            );
+        }
+
+        if let Some(e2e_mode) = settings.var("E2E_MODE")? {
+            config.e2e_mode = e2e_mode;
+        }
+        if let Some(start) = settings.var("E2E_START_TIME")? {
+            config.e2e_start_time = start;
+        }
+        if let Some(serve_web_ui) = settings.var("SERVE_WEB_UI")? {
+            config.serve_web_ui = serve_web_ui;
+        }
+        if let Some(web_root) = settings.var::<PathBuf>("WEB_ROOT")? {
+            if !web_root.join("index.html").is_file() {
+                return Err(anyhow!(
+                    "WEB_ROOT '{}' has no index.html",
+                    web_root.display()
+                ));
+            }
+            config.web_root = Some(web_root);
+        }
+        if let Some(fault_injection) = settings.var("FAULT_INJECTION")? {
+            config.fault_injection = fault_injection;
+        }
+
+        if let Some(format) = settings.var("LOG_FORMAT")? {
+            config.log_format = format;
+        }
+
+        let http = &mut config.http;
+        if let Some(address) = settings.var::<IpAddr>("BIND_ADDRESS")? {
+            http.listen.set_ip(address);
+        }
+        if let Some(port) = settings.var("PORT")? {
+            http.listen.set_port(port);
+        }
+        if let Some(origins) = settings.var("CORS_ALLOWED_ORIGINS")? {
+            http.cors.allowed_origins = origins;
+        }
+        // Methods are case-sensitive, and nobody means a custom `get` method.
+        if let Some(methods) = settings.var::<String>("CORS_ALLOWED_METHODS")? {
+            http.cors.allowed_methods = methods
+                .to_ascii_uppercase()
+                .parse()
+                .map_err(|e| anyhow!("Invalid CORS_ALLOWED_METHODS '{methods}': {e}"))?;
+        }
+        if let Some(headers) = settings.var("CORS_ALLOWED_HEADERS")? {
+            http.cors.allowed_headers = headers;
+        }
+        if let Some(credentials) = settings.var("CORS_ALLOW_CREDENTIALS")? {
+            http.cors.allow_credentials = credentials;
+        }
+        http.cors.check()?;
+        if let Some(enabled) = settings.var("HTTP2_ENABLED")? {
+            http.http2_enabled = enabled;
+        }
+        if let Some(keep_alive) = settings.var("HTTP1_KEEP_ALIVE")? {
+            http.http1_keep_alive = keep_alive;
+        }
+        if let Some(secs) = settings.var("HTTP1_HEADER_READ_TIMEOUT_SECS")? {
+            http.http1_header_read_timeout = Duration::from_secs(secs);
+        }
+        if let Some(secs) = settings.var("HTTP2_KEEP_ALIVE_INTERVAL_SECS")? {
+            http.http2_keep_alive_interval = Some(Duration::from_secs(secs));
+        }
+        if let Some(secs) = settings.var("HTTP2_KEEP_ALIVE_TIMEOUT_SECS")? {
+            http.http2_keep_alive_timeout = Duration::from_secs(secs);
+        }
+        if let Some(streams) = settings.var("HTTP2_MAX_CONCURRENT_STREAMS")? {
+            http.http2_max_concurrent_streams = streams;
+        }
+        if let Some(secs) = settings.var::<u64>("REQUEST_TIMEOUT_SECS")? {
+            http.request_timeout = (secs > 0).then(|| Duration::from_secs(secs));
+        }
+        if let Some(bytes) = settings.var("MAX_BODY_BYTES")? {
+            http.max_body_bytes = bytes;
+        }
+        http.tls = match (
+            settings.var::<PathBuf>("TLS_CERT_PATH")?,
+            settings.var::<PathBuf>("TLS_KEY_PATH")?,
+        ) {
+            (Some(cert_path), Some(key_path)) => Some(TlsConfig::new(cert_path, key_path)),
+            (None, None) => None,
+            _ => {
+                return Err(anyhow!(
+                    "TLS_CERT_PATH and TLS_KEY_PATH must be set together"
+                ))
+            }
+        };
+        let reload_secs = settings.var::<u64>("TLS_RELOAD_INTERVAL_SECS")?;
+        if let (Some(tls), Some(secs)) = (&mut http.tls, reload_secs) {
+            tls.reload_interval = (secs > 0).then(|| Duration::from_secs(secs));
+        }
+
+        Ok(config)
+    }
+}
+
+/// Data directory used when `DATA_DIR` is unset. A `database` directory in
+/// the working directory is kept if it exists, so that existing setups find
+/// their data; otherwise the per-user data directory of the platform is used:
+/// `%LOCALAPPDATA%` on Windows, the XDG data directory elsewhere.
+fn default_data_dir() -> PathBuf {
+    let legacy = PathBuf::from(DEFAULT_DATA_DIR);
+    if legacy.is_dir() {
+        return legacy;
+    }
+    let platform_dir = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .map(|dir| dir.join(APP_DIR_NAME))
+    } else {
+        xdg_data_dir(
+            env::var_os("XDG_DATA_HOME").map(PathBuf::from),
+            env::var_os("HOME").map(PathBuf::from),
+        )
+    };
+    platform_dir.unwrap_or(legacy)
+}
+
+/// Directory of the application in the XDG data directory:
+/// `$XDG_DATA_HOME/weekly-task-manager`, or `~/.local/share/...`.
+fn xdg_data_dir(xdg_data_home: Option<PathBuf>, home: Option<PathBuf>) -> Option<PathBuf> {
+    // Relative paths are invalid in XDG variables and must be ignored.
+    xdg_data_home
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| {
+            home.filter(|dir| dir.is_absolute())
+                .map(|home| home.join(".local").join("share"))
+        })
+        .map(|data_home| data_home.join(APP_DIR_NAME))
+}
+
+/// Creates the data directory on first run, readable by its owner only, and
+/// checks that it can be written to. A directory open to other users only
+/// gets a warning, as it may be shared on purpose.
+pub fn prepare_data_dir(dir: &Path) -> Result<()> {
+    if !dir.exists() {
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(dir)
+            .with_context(|| format!("Failed to create data directory {}", dir.display()))?;
+        info!("Created data directory {}", dir.display());
+    }
+    if !dir.is_dir() {
+        return Err(anyhow!(
+            "Data directory {} is not a directory",
+            dir.display()
+        ));
+    }
+
+    let probe = dir.join(".write-check");
+    fs::write(&probe, b"")
+        .and_then(|()| fs::remove_file(&probe))
+        .with_context(|| format!("Data directory {} is not writable", dir.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(dir)?.permissions().mode();
+        if mode & 0o002 != 0 {
+            warn!(
+                "Data directory {} is writable by any user (mode {:o})",
+                dir.display(),
+                mode & 0o777
+            );
+        }
+    }
+    Ok(())
+}
+
+/// URL of the default database file in `data_dir`.
+/// Characters of the path that would be read as part of the URL syntax are
+/// percent-encoded; others, Windows drive letters and backslashes included,
+/// are kept as they are.
+fn database_url_in(data_dir: &Path) -> String {
+    let path = data_dir.join(DATABASE_FILE_NAME).display().to_string();
+    let mut url = String::from("sqlite://");
+    for c in path.chars() {
+        match c {
+            '%' => url.push_str("%25"),
+            '?' => url.push_str("%3F"),
+            '#' => url.push_str("%23"),
+            c => url.push(c),
+        }
+    }
+    url
+}
+
+/// Host name and process ID, telling apart replicas and restarts of the
+/// same one in the scheduled job claims.
+fn default_instance_name() -> String {
+    let host = std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .unwrap_or_else(|_| "localhost".to_string());
+    format!("{host}-{}", std::process::id())
+}
+
+/// Email digest settings, none unless `SMTP_HOST` is set. The other
+/// settings are read either way, so that they are not reported as unknown.
+fn email_config(settings: &Settings) -> Result<Option<EmailConfig>> {
+    let host = settings.var::<String>("SMTP_HOST")?;
+    let tls = settings.var::<SmtpTls>("SMTP_TLS")?.unwrap_or_default();
+    let port = settings.var::<u16>("SMTP_PORT")?;
+    let username = settings.var::<String>("SMTP_USERNAME")?;
+    let password = settings.var::<String>("SMTP_PASSWORD")?;
+    let from = settings.var::<Mailbox>("EMAIL_FROM")?;
+    let recipients = settings.var::<String>("EMAIL_DIGEST_TO")?;
+    let period = settings
+        .var::<DigestPeriod>("EMAIL_DIGEST_PERIOD")?
+        .unwrap_or_default();
+    let schedule = settings.var::<String>("EMAIL_DIGEST_SCHEDULE")?;
+    let locale = settings
+        .var::<Locale>("EMAIL_DIGEST_LOCALE")?
+        .unwrap_or_default();
+    let Some(host) = host.filter(|host| !host.is_empty()) else {
+        return Ok(None);
+    };
+
+    let credentials = match (username, password) {
+        (Some(username), Some(password)) => Some((username, password)),
+        (None, None) => None,
+        _ => {
+            return Err(anyhow!(
+                "SMTP_USERNAME and SMTP_PASSWORD must be set together"
+            ))
+        }
+    };
+    let from = from.ok_or_else(|| anyhow!("SMTP_HOST requires EMAIL_FROM, the sender"))?;
+    let recipients = recipients
+        .iter()
+        .flat_map(|list| list.split(','))
+        .map(str::trim)
+        .filter(|recipient| !recipient.is_empty())
+        .map(|recipient| {
+            recipient
+                .parse()
+                .map_err(|e| anyhow!("Invalid EMAIL_DIGEST_TO address '{recipient}': {e}"))
+        })
+        .collect::<Result<Vec<Mailbox>>>()?;
+    if recipients.is_empty() {
+        return Err(anyhow!(
+            "SMTP_HOST requires EMAIL_DIGEST_TO, the recipients of the digest"
+        ));
+    }
+    let schedule = schedule.as_deref().unwrap_or(period.default_schedule());
+    let digest_schedule = parse_cron(schedule)
+        .map_err(|e| anyhow!("Invalid EMAIL_DIGEST_SCHEDULE '{schedule}': {e}"))?;
+    Ok(Some(EmailConfig {
+        host,
+        port: port.unwrap_or(tls.default_port()),
+        tls,
+        credentials,
+        from,
+        recipients,
+        digest_schedule,
+        digest_period: period,
+        digest_locale: locale,
+    }))
+}
+
+/// Parses a cron expression, minute first.
+pub fn parse_cron(expression: &str) -> Result<Cron, croner::errors::CronError> {
+    Cron::new(expression).parse()
+}
+
+/// Settings read from a configuration file, by variable name.
+///
+/// The file uses the names of the environment variables in lower case,
+/// either as is or grouped into tables named after their first words, so
+/// that `backup_keep = 14` and `[backup] keep = 14` both set `BACKUP_KEEP`.
+/// Lists are joined with commas, as in the variables.
+#[derive(Debug, Default)]
+pub struct ConfigFile {
+    path: PathBuf,
+    values: BTreeMap<String, String>,
+}
+
+impl ConfigFile {
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the configuration file {}", path.display()))?;
+        ConfigFile::parse(path, &text)
+    }
+
+    fn parse(path: &Path, text: &str) -> Result<Self> {
+        let table: toml::Table = toml::from_str(text)
+            .with_context(|| format!("Invalid configuration file {}", path.display()))?;
+        let mut file = ConfigFile {
+            path: path.to_path_buf(),
+            values: BTreeMap::new(),
+        };
+        file.flatten("", table)?;
+        Ok(file)
+    }
+
+    fn flatten(&mut self, prefix: &str, table: toml::Table) -> Result<()> {
+        for (key, value) in table {
+            let name = format!("{prefix}{}", key.to_ascii_uppercase());
+            let value = match value {
+                toml::Value::Table(table) => {
+                    self.flatten(&format!("{name}_"), table)?;
+                    continue;
+                }
+                toml::Value::String(text) => text,
+                toml::Value::Datetime(time) => time.to_string(),
+                toml::Value::Array(items) => items
+                    .into_iter()
+                    .map(|item| match item {
+                        toml::Value::String(text) => Ok(text),
+                        toml::Value::Datetime(time) => Ok(time.to_string()),
+                        toml::Value::Table(_) | toml::Value::Array(_) => Err(anyhow!(
+                            "Invalid {name} in {}: lists may only hold plain values",
+                            self.path.display()
+                        )),
+                        other => Ok(other.to_string()),
+                    })
+                    .collect::<Result<Vec<_>>>()?
+                    .join(","),
+                other => other.to_string(),
+            };
+            if self.values.insert(name.clone(), value).is_some() {
+                return Err(anyhow!("{name} is set twice in {}", self.path.display()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where the settings come from: the environment, then the configuration
+/// file. Keeps track of the names read, to reject unknown ones in the file.
+struct Settings {
+    file: ConfigFile,
+    env: BTreeMap<String, String>,
+    used: RefCell<BTreeSet<String>>,
+}
+
+impl Settings {
+    fn new(file: ConfigFile, env: impl IntoIterator<Item = (String, String)>) -> Self {
+        Settings {
+            file,
+            env: env.into_iter().collect(),
+            used: RefCell::default(),
+        }
+    }
+
+    /// Reads and parses a setting, `None` when it is set nowhere.
+    fn var<T>(&self, name: &str) -> Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.used.borrow_mut().insert(name.to_string());
+        let (value, origin) = match (self.env.get(name), self.file.values.get(name)) {
+            (Some(value), _) => (value, String::new()),
+            (None, Some(value)) => (value, format!(" (in {})", self.file.path.display())),
+            (None, None) => return Ok(None),
+        };
+        value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|e| anyhow!("Invalid {name} '{value}'{origin}: {e}"))
+    }
+
+    /// Fails on the first setting of the file that was never read, most
+    /// likely misspelled.
+    fn check_all_used(&self) -> Result<()> {
+        let used = self.used.borrow();
+        match self.file.values.keys().find(|name| !used.contains(*name)) {
+            Some(name) => Err(anyhow!(
+                "Unknown setting {} in {}",
+                name.to_ascii_lowercase(),
+                self.file.path.display()
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqliteConnectOptions;
+
+    #[test]
+    fn test_default_database_is_in_data_dir() {
+        let config = Config::default();
+        assert_eq!(config.database_url, "sqlite://database/sqlite.db");
+        assert_eq!(
+            database_url_in(&PathBuf::from("/var/lib/tasks")),
+            "sqlite:///var/lib/tasks/sqlite.db"
+        );
+    }
+
+    #[test]
+    fn test_cors_origins() {
+        assert_eq!("*".parse(), Ok(CorsOrigins::Any));
+        assert_eq!(
+            "https://tasks.example.com, http://localhost:8080".parse(),
+            Ok(CorsOrigins::List(vec![
+                "https://tasks.example.com".to_string(),
+                "http://localhost:8080".to_string()
+            ]))
+        );
+        assert_eq!("none".parse(), Ok(CorsOrigins::None));
+        for invalid in ["", "tasks.example.com", "https://tasks.example.com/"] {
+            assert!(invalid.parse::<CorsOrigins>().is_err(), "{invalid}");
+        }
+
+        assert_eq!(
+            "content-type, X-Requested-With".parse(),
+            Ok(CorsList::List(vec![
+                header::CONTENT_TYPE,
+                HeaderName::from_static("x-requested-with")
+            ]))
+        );
+        assert!("content type".parse::<CorsList<HeaderName>>().is_err());
+        assert_eq!("*".parse(), Ok(CorsList::<Method>::Any));
+
+        let credentials = CorsConfig {
+            allow_credentials: true,
+            allowed_origins: CorsOrigins::List(vec!["https://tasks.example.com".to_string()]),
+            ..CorsConfig::default()
+        };
+        assert!(credentials.check().is_ok());
+        let any_origin = CorsConfig {
+            allowed_origins: CorsOrigins::Any,
+            ..credentials
+        };
+        assert!(any_origin.check().is_err());
+    }
+
+    #[test]
+    fn test_config_file_with_env_overrides() {
+        let file = ConfigFile::parse(
+            Path::new("config.toml"),
+            r#"
+            database_url = "sqlite:///srv/tasks/sqlite.db"
+            trusted_proxies = ["127.0.0.1", "10.0.0.0/8"]
+            e2e_start_time = 2025-07-21T08:00:00Z
+
+            [backup]
+            dir = "backups"
+            keep = 14
+
+            [http2]
+            keep_alive_interval_secs = 30
+            "#,
+        )
+        .unwrap();
+        let settings = Settings::new(file, [("BACKUP_KEEP".to_string(), "3".to_string())]);
+        let config = Config::from_settings(&settings).unwrap();
+        settings.check_all_used().unwrap();
+
+        assert_eq!(config.database_url, "sqlite:///srv/tasks/sqlite.db");
+        assert_eq!(config.backup.keep, 3);
+        assert!(config.backup.dir.unwrap().ends_with("backups"));
+        assert_eq!(
+            config.http.http2_keep_alive_interval,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            config.e2e_start_time.to_rfc3339(),
+            "2025-07-21T08:00:00+00:00"
+        );
+        assert!(config
+            .trusted_proxies
+            .is_trusted("10.1.2.3".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_config_file_errors() {
+        let load = |text: &str| {
+            let file = ConfigFile::parse(Path::new("config.toml"), text)?;
+            let settings = Settings::new(file, []);
+            Config::from_settings(&settings)?;
+            settings.check_all_used()
+        };
+        let error = load("backup_kep = 3").unwrap_err().to_string();
+        assert_eq!(error, "Unknown setting backup_kep in config.toml");
+        let error = load("[backup]\nkeep = \"many\"").unwrap_err().to_string();
+        assert!(
+            error.starts_with("Invalid BACKUP_KEEP 'many' (in config.toml)"),
+            "{error}"
+        );
+        assert!(load("backup_keep = 3\n[backup]\nkeep = 4").is_err());
+        assert!(load("port = [[1]]").is_err());
+        assert!(load("port =").is_err());
+    }
+
+    #[test]
+    fn test_notification_settings() {
+        let load = |text: &str| {
+            let file = ConfigFile::parse(Path::new("config.toml"), text)?;
+            Config::from_settings(&Settings::new(file, []))
+        };
+        let config = load(
+            "[slack]\nwebhook_url = \"https://hooks.slack.com/services/T0/B0/x\"\n[notify]\nrollover_template = \"{count} tasks moved\"",
+        )
+        .unwrap();
+        assert!(config.notifications.has_channels());
+        assert_eq!(config.notifications.rollover_threshold, 10);
+
+        let error = load("slack_webhook_url = \"hooks.slack.com/x\"")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "Invalid SLACK_WEBHOOK_URL: expected an http(s) URL");
+        let error = load("notify_digest_task_template = \"{title}\"")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("Invalid NOTIFY_DIGEST_TASK_TEMPLATE: unknown placeholder '{title}'"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_email_settings() {
+        let load = |text: &str| {
+            let file = ConfigFile::parse(Path::new("config.toml"), text)?;
+            Config::from_settings(&Settings::new(file, []))
+        };
+        // Only SMTP_HOST turns the digest on
+        assert!(load("[email]\nfrom = \"tasks@example.com\"")
+            .unwrap()
+            .email
+            .is_none());
+
+        let config = load(
+            "[smtp]\nhost = \"smtp.example.com\"\ntls = \"tls\"\n[email]\nfrom = \"Tasks <tasks@example.com>\"\ndigest_to = [\"a@example.com\", \"b@example.com\"]\ndigest_period = \"week\"\ndigest_locale = \"fr\"",
+        )
+        .unwrap();
+        let email = config.email.unwrap();
+        assert_eq!(email.port, 465);
+        assert_eq!(email.recipients.len(), 2);
+        assert_eq!(email.digest_period, DigestPeriod::Week);
+        assert_eq!(email.digest_locale, Locale::Fr);
+        assert_eq!(email.digest_schedule.pattern.to_string(), "0 8 * * 1");
+
+        let error =
+            load("[smtp]\nhost = \"smtp.example.com\"\n[email]\nfrom = \"tasks@example.com\"")
+                .unwrap_err()
+                .to_string();
+        assert_eq!(
+            error,
+            "SMTP_HOST requires EMAIL_DIGEST_TO, the recipients of the digest"
+        );
+        let error = load("[smtp]\nhost = \"smtp.example.com\"\nusername = \"me\"")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "SMTP_USERNAME and SMTP_PASSWORD must be set together"
+        );
+    }
+
+    #[test]
+    fn test_sessions_need_api_keys() {
+        let load = |vars: &[(&str, &str)]| {
+            let file = ConfigFile::parse(Path::new("config.toml"), "").unwrap();
+            let vars = vars
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()));
+            Config::from_settings(&Settings::new(file, vars))
+        };
+        let secret = ("SESSION_SECRET", "a-session-secret-of-32-characters");
+
+        let config = load(&[
+            ("API_KEYS", "browser-key-0123456789"),
+            secret,
+            ("SESSION_TTL_HOURS", "8"),
+            ("SESSION_COOKIE_SECURE", "false"),
+        ])
+        .unwrap();
+        assert!(config.api_keys.accepts("browser-key-0123456789"));
+        let sessions = config.sessions.unwrap();
+        assert_eq!(sessions.ttl, chrono::Duration::hours(8));
+        assert!(!sessions.secure_cookie);
+        assert!(load(&[]).unwrap().sessions.is_none());
+
+        let error = load(&[secret]).unwrap_err().to_string();
+        assert!(
+            error.starts_with("SESSION_SECRET requires API_KEYS"),
+            "{error}"
+        );
+        // Rejected secrets are not echoed
+        let error = load(&[
+            ("API_KEYS", "browser-key-0123456789"),
+            ("SESSION_SECRET", "hunter2"),
+        ])
+        .unwrap_err()
+        .to_string();
+        assert!(!error.contains("hunter2"), "{error}");
+    }
+
+    #[test]
+    fn test_database_url_keeps_unusual_paths() {
+        for dir in [r"C:\Users\Ada\AppData\Local\tasks", "/srv/100% tasks?#1"] {
+            let url = database_url_in(Path::new(dir));
+            let options: SqliteConnectOptions = url.parse().unwrap();
+            assert_eq!(
+                options.get_filename(),
+                Path::new(dir).join(DATABASE_FILE_NAME)
+            );
+        }
+    }
+
+    #[test]
+    fn test_xdg_data_dir() {
+        let home = Some(PathBuf::from("/home/ada"));
+        let app_dir = |base: &str| Some(PathBuf::from(base).join(APP_DIR_NAME));
+        assert_eq!(
+            xdg_data_dir(Some(PathBuf::from("/data")), home.clone()),
+            app_dir("/data")
+        );
+        // A relative XDG_DATA_HOME is ignored
+        assert_eq!(
+            xdg_data_dir(Some(PathBuf::from("relative")), home.clone()),
+            app_dir("/home/ada/.local/share")
+        );
+        assert_eq!(xdg_data_dir(None, home), app_dir("/home/ada/.local/share"));
+        assert_eq!(xdg_data_dir(None, None), None);
+    }
+
+    #[test]
+    fn test_data_dir_is_created_private_and_checked() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("nested").join("data");
+
+        prepare_data_dir(&dir).unwrap();
+        assert!(dir.is_dir());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        // Existing directories are accepted as they are
+        prepare_data_dir(&dir).unwrap();
+
+        let file = root.path().join("file");
+        fs::write(&file, "").unwrap();
+        assert!(prepare_data_dir(&file).is_err());
+    }
+}
@@ -0,0 +1,38 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+
+/// Normalizes a GTD-style context such as `@office`, `@home` or `@errands`:
+/// trimmed, lowercase, with a leading `@` added if missing.
+/// Returns `None` when the name is empty or contains anything other than
+/// letters, digits, `-` and `_`.
+pub fn normalize_context(context: &str) -> Option<String> {
+    let trimmed = context.trim();
+    let name = trimmed.strip_prefix('@').unwrap_or(trimmed);
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return None;
+    }
+    Some(format!("@{}", name.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_context() {
+        assert_eq!(normalize_context("@Office"), Some("@office".to_string()));
+        assert_eq!(normalize_context(" errands "), Some("@errands".to_string()));
+        assert_eq!(
+            normalize_context("@deep-work"),
+            Some("@deep-work".to_string())
+        );
+        assert_eq!(normalize_context("@"), None);
+        assert_eq!(normalize_context("@at home"), None);
+    }
+}
@@ -2,18 +2,50 @@
 //
 // This software is licensed under the terms of the MIT License.
 // See the LICENSE file in the project root for the full license text.
-use crate::colors;
+use crate::config::{DatabaseConfig, RolloverPolicy};
+use crate::doctor::Inconsistency;
+use crate::sync::{SyncChanges, SyncCursor, SyncRemoval};
+use crate::table::{ClientRow, TaskRow};
+use crate::{clock, colors, presentation, priorities};
 
-use anyhow::{Context, Result};
-use chrono::{Utc, Weekday};
-use common::{CreateTaskPayload, Task};
-use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool}; // Added MigrateDatabase for database_exists/create_database
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Datelike, NaiveDate, Utc, Weekday};
+use common::{
+    AppliedPlan, BatchOperation, CarryOverSelection, Client, ClientAlias, ClientColorChange,
+    ClientStats, ColorChangeReason, CreateTaskPayload, CreateTaskSlotPayload, DeliveryStatus,
+    ExportedClient, ExportedSettings, ExportedTask, ExportedTaskSlot, FocusDay, FullExport,
+    FullImportSummary, GoalUnit, HexColor, Holiday, ImportConflict, ImportCounts, ImportPreview,
+    ImportRow, ImportStrategy, JobRun, PostponedTask, Priority, PriorityChange, PriorityCount,
+    PriorityNormalization, RolloverHistory, RolloverPreview, RolloverRun, RolloverTarget,
+    RolloverTrigger, SortSpec, Task, TaskFilter, TaskSlot, TaskSortField, TaskStatusFilter,
+    TaskSummary, Webhook, WebhookDelivery, WebhookEvent, WeekArchive, WeekClientTotals,
+    WeekIntegrityReport, WeeklyGoal, FULL_EXPORT_VERSION,
+};
+use futures_util::TryStreamExt;
+use serde::Serialize;
+use sqlx::{
+    migrate::MigrateDatabase,
+    migrate::Migrator,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+    Connection, Executor, Sqlite, SqliteConnection, SqlitePool, Transaction,
+}; // Added MigrateDatabase for database_exists/create_database
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+use tokio::sync::mpsc;
 use tracing::{debug, info};
 
 /// Establishes the database connection pool.
 /// If the database does not exist, it creates it.
-/// It also ensures the `tasks` table has the correct schema.
-pub async fn establish_connection_pool(database_url: &str) -> Result<SqlitePool> {
+/// It also runs the pending migrations, and fails if the schema has drifted.
+///
+/// Connections use WAL journaling with `synchronous=NORMAL`, which is safe
+/// in WAL mode and lets readers work alongside the writer, and wait up to
+/// the busy timeout for locks held by other connections.
+pub async fn establish_connection_pool(
+    database_url: &str,
+    config: &DatabaseConfig,
+) -> Result<SqlitePool> {
     if !Sqlite::database_exists(database_url).await.unwrap_or(false) {
         info!("Creating database {}", database_url);
         Sqlite::create_database(database_url) // Use the passed URL
@@ -23,248 +55,4854 @@ pub async fn establish_connection_pool(database_url: &str) -> Result<SqlitePool>
         info!("Database already exists.");
     }
 
-    let pool = SqlitePool::connect(database_url) // Use the passed URL
+    let options = SqliteConnectOptions::from_str(database_url) // Use the passed URL
+        .context("Invalid database URL")?
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        .busy_timeout(config.busy_timeout);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(config.max_connections)
+        .acquire_timeout(config.acquire_timeout)
+        .connect_with(options)
         .await
         .context("Failed to connect to database")?;
 
+    init_schema(&pool).await?;
+
+    Ok(pool)
+}
+
+/// Starts a transaction holding the write lock from the start. A deferred
+/// transaction that reads before writing fails at once with "database is
+/// locked" if another connection wrote in between, busy timeout or not.
+async fn begin_write(pool: &SqlitePool) -> Result<Transaction<'static, Sqlite>> {
+    pool.begin_with("BEGIN IMMEDIATE")
+        .await
+        .context("Failed to start transaction")
+}
+
+/// Migrations of the database schema, embedded from `server/migrations`.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Brings the database schema up to date by running the pending migrations,
+/// then checks that the result matches what the migrations describe.
+/// Shared by `establish_connection_pool` and the test suites so that the schema
+/// is defined in a single place.
+pub async fn init_schema(pool: &SqlitePool) -> Result<()> {
+    // Databases created before migrations were introduced have tables but
+    // no migration history.
+    if !table_exists(pool, "_sqlx_migrations").await? && table_exists(pool, "tasks").await? {
+        upgrade_unversioned_schema(pool).await?;
+    }
+
+    MIGRATOR
+        .run(pool)
+        .await
+        .context("Failed to run database migrations")?;
+    info!("Database schema is up to date.");
+
+    check_schema_drift(pool).await
+}
+
+/// Upgrades a database created before migrations existed to the shape of
+/// the initial migration, which is then recorded on top of it.
+async fn upgrade_unversioned_schema(pool: &SqlitePool) -> Result<()> {
+    info!("Upgrading a database without migration history...");
+
+    // Tables that may predate the `client_id` layout, in their current shape.
     sqlx::query(
         r#"
-        CREATE TABLE IF NOT EXISTS tasks (
+        CREATE TABLE IF NOT EXISTS clients (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
-            client_name TEXT NOT NULL,
-            description TEXT NOT NULL,
-            task_date DATE NOT NULL,
-            client_color TEXT NOT NULL,
+            name TEXT NOT NULL UNIQUE,
+            color TEXT NOT NULL,
             created_at TIMESTAMP NOT NULL,
-            deleted_at TIMESTAMP WITH TIME ZONE NULL,
-            priority INTEGER NULL
+            archived_at TIMESTAMP WITH TIME ZONE NULL
+        );
+        CREATE TABLE IF NOT EXISTS client_aliases (
+            alias TEXT PRIMARY KEY COLLATE NOCASE,
+            client_id INTEGER NOT NULL REFERENCES clients (id)
         );
         "#,
     )
-    .execute(&pool)
+    .execute(pool)
     .await
-    .context("Failed to create 'tasks' table")?;
+    .context("Failed to create client tables")?;
 
-    info!("'tasks' table is ready.");
+    // Older databases store the client name and color on every task.
+    migrate_legacy_client_columns(pool).await?;
 
-    Ok(pool)
-}
+    // Columns added after the first release of the `tasks` table.
+    for (column, definition) in [
+        ("rollover_count", "INTEGER NOT NULL DEFAULT 0"),
+        ("energy", "TEXT NULL"),
+        ("estimate_minutes", "INTEGER NULL"),
+        ("context", "TEXT NULL"),
+    ] {
+        if !column_exists(pool, "tasks", column).await? {
+            sqlx::query(&format!(
+                "ALTER TABLE tasks ADD COLUMN {column} {definition}"
+            ))
+            .execute(pool)
+            .await
+            .context(format!("Failed to add column 'tasks.{column}'"))?;
+        }
+    }
 
-/// Retrieves tasks for the current week (Monday to Sunday), excluding soft-deleted tasks.
-pub async fn get_current_week_tasks_from_db(pool: &SqlitePool) -> Result<Vec<Task>> {
-    let today = Utc::now().date_naive();
-    let week_start = today.week(Weekday::Mon).first_day();
-    let week_end = today.week(Weekday::Mon).last_day();
+    Ok(())
+}
 
-    let tasks = sqlx::query_as::<_, Task>(
-        "SELECT * FROM tasks WHERE task_date BETWEEN ? AND ? AND deleted_at IS NULL ORDER BY task_date ASC, priority ASC NULLS LAST;",
+/// Describes the columns of every application table as `(type, not null,
+/// primary key)` by table and column name.
+async fn schema_columns(
+    pool: &SqlitePool,
+) -> Result<BTreeMap<String, BTreeMap<String, (String, bool, bool)>>> {
+    let rows: Vec<(String, String, String, bool, bool)> = sqlx::query_as(
+        r#"
+        SELECT m.name, c.name, c.type, c."notnull", c.pk > 0
+        FROM sqlite_master m
+        JOIN pragma_table_info(m.name) c
+        WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%' AND m.name != '_sqlx_migrations'
+        "#,
     )
-    .bind(week_start)
-    .bind(week_end)
     .fetch_all(pool)
     .await
-    .context("Failed to retrieve current week's tasks from DB")?;
+    .context("Failed to inspect the database schema")?;
 
-    Ok(tasks)
+    let mut tables: BTreeMap<String, BTreeMap<String, _>> = BTreeMap::new();
+    for (table, column, column_type, not_null, primary_key) in rows {
+        tables
+            .entry(table)
+            .or_default()
+            .insert(column, (column_type, not_null, primary_key));
+    }
+    Ok(tables)
 }
 
-/// Inserts a new task into the database.
-pub async fn create_task_in_db(pool: &SqlitePool, payload: CreateTaskPayload) -> Result<Task> {
-    let task_date = payload.task_date.unwrap_or_else(|| Utc::now().date_naive());
-    let client_color = colors::get_or_assign_client_color(&payload.client_name);
-    let created_at = Utc::now();
+/// Fails if the tables of the database differ from the ones the migrations
+/// create on an empty database, e.g. after a hand edit or a migration that
+/// was changed once applied. Tables unknown to the migrations are ignored.
+async fn check_schema_drift(pool: &SqlitePool) -> Result<()> {
+    let reference = SqlitePool::connect("sqlite::memory:")
+        .await
+        .context("Failed to open the reference database")?;
+    MIGRATOR
+        .run(&reference)
+        .await
+        .context("Failed to run migrations on the reference database")?;
+    let expected = schema_columns(&reference).await?;
+    reference.close().await;
+
+    let actual = schema_columns(pool).await?;
+    let mut differences = Vec::new();
+    for (table, expected_columns) in &expected {
+        let Some(columns) = actual.get(table) else {
+            differences.push(format!("table '{table}' is missing"));
+            continue;
+        };
+        for (column, definition) in expected_columns {
+            match columns.get(column) {
+                None => differences.push(format!("column '{table}.{column}' is missing")),
+                Some(found) if found != definition => differences.push(format!(
+                    "column '{table}.{column}' is {found:?}, expected {definition:?}"
+                )),
+                Some(_) => {}
+            }
+        }
+        for column in columns
+            .keys()
+            .filter(|c| !expected_columns.contains_key(*c))
+        {
+            differences.push(format!("column '{table}.{column}' is unexpected"));
+        }
+    }
+
+    if !differences.is_empty() {
+        return Err(anyhow!(
+            "Database schema does not match the migrations: {}",
+            differences.join("; ")
+        ));
+    }
+    Ok(())
+}
+
+/// Moves databases where clients were keyed by name (with `client_name` and
+/// `client_color` copied on every task) to the normalized `client_id` layout.
+/// Each table is rebuilt only if it still has the legacy shape.
+async fn migrate_legacy_client_columns(pool: &SqlitePool) -> Result<()> {
+    let legacy_clients = !column_exists(pool, "clients", "id").await?;
+    let legacy_tasks = column_exists(pool, "tasks", "client_name").await?;
+    let legacy_aliases = column_exists(pool, "client_aliases", "client_name").await?;
+
+    if !(legacy_clients || legacy_tasks || legacy_aliases) {
+        return Ok(());
+    }
+
+    info!("Migrating clients to a foreign-key relationship...");
+    let mut tx = begin_write(pool).await?;
+
+    if legacy_clients {
+        sqlx::query(
+            r#"
+            CREATE TABLE clients_new (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                color TEXT NOT NULL,
+                created_at TIMESTAMP NOT NULL,
+                archived_at TIMESTAMP WITH TIME ZONE NULL
+            );
+            INSERT INTO clients_new (name, color, created_at, archived_at)
+                SELECT name, color, created_at, archived_at FROM clients ORDER BY created_at;
+            DROP TABLE clients;
+            ALTER TABLE clients_new RENAME TO clients;
+            "#,
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to migrate 'clients' table")?;
+    }
+
+    if legacy_tasks {
+        // Databases created before the import columns existed need them added.
+        for column in ["external_source", "external_id"] {
+            if !column_exists(&mut *tx, "tasks", column).await? {
+                sqlx::query(&format!("ALTER TABLE tasks ADD COLUMN {column} TEXT NULL"))
+                    .execute(&mut *tx)
+                    .await
+                    .context(format!("Failed to add column 'tasks.{column}'"))?;
+            }
+        }
+
+        // Register clients that only exist implicitly on tasks, keeping their color.
+        sqlx::query(
+            r#"
+            INSERT INTO clients (name, color, created_at)
+            SELECT client_name, MIN(client_color), MIN(created_at) FROM tasks
+            WHERE client_name NOT IN (SELECT name FROM clients)
+            GROUP BY client_name
+            "#,
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to backfill 'clients' from tasks")?;
+
+        sqlx::query(
+            r#"
+            DROP INDEX IF EXISTS idx_tasks_external_ref;
+            CREATE TABLE tasks_new (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                client_id INTEGER NOT NULL REFERENCES clients (id),
+                description TEXT NOT NULL,
+                task_date DATE NOT NULL,
+                created_at TIMESTAMP NOT NULL,
+                deleted_at TIMESTAMP WITH TIME ZONE NULL,
+                priority INTEGER NULL,
+                external_source TEXT NULL,
+                external_id TEXT NULL
+            );
+            INSERT INTO tasks_new (id, client_id, description, task_date, created_at, deleted_at, priority, external_source, external_id)
+                SELECT t.id, c.id, t.description, t.task_date, t.created_at, t.deleted_at, t.priority, t.external_source, t.external_id
+                FROM tasks t JOIN clients c ON c.name = t.client_name;
+            DROP TABLE tasks;
+            ALTER TABLE tasks_new RENAME TO tasks;
+            "#,
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to migrate 'tasks' table")?;
+    }
+
+    if legacy_aliases {
+        sqlx::query(
+            r#"
+            CREATE TABLE client_aliases_new (
+                alias TEXT PRIMARY KEY COLLATE NOCASE,
+                client_id INTEGER NOT NULL REFERENCES clients (id)
+            );
+            INSERT INTO client_aliases_new (alias, client_id)
+                SELECT a.alias, c.id FROM client_aliases a JOIN clients c ON c.name = a.client_name;
+            DROP TABLE client_aliases;
+            ALTER TABLE client_aliases_new RENAME TO client_aliases;
+            "#,
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to migrate 'client_aliases' table")?;
+    }
+
+    tx.commit()
+        .await
+        .context("Failed to commit client migration")?;
+    info!("Clients migrated to a foreign-key relationship.");
 
-    debug!("Insert values: client_name={}, description={}, task_date={}, client_color={}, created_at={}, priority={:?}",
-           payload.client_name, payload.description, task_date, client_color, created_at, payload.priority);
+    Ok(())
+}
 
-    // Make sure to include deleted_at in the column list and provide a value (NULL for new tasks)
-    let id = sqlx::query(
-        "INSERT INTO tasks (client_name, description, task_date, client_color, created_at, deleted_at, priority) VALUES (?, ?, ?, ?, ?, NULL, ?)"
+/// Deletes all the data of the application, keeping the schema, and restarts
+/// IDs from 1. Used by the end-to-end test mode.
+pub async fn reset_database(pool: &SqlitePool) -> Result<()> {
+    let tables: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name != '_sqlx_migrations'",
     )
-    .bind(&payload.client_name)
-    .bind(&payload.description)
-    .bind(task_date)
-    .bind(&client_color)
-    .bind(created_at)
-    .bind(payload.priority)
-    .execute(pool)
+    .fetch_all(pool)
     .await
-    .context("Failed to insert task into DB")?
-    .last_insert_rowid();
+    .context("Failed to list tables")?;
 
-    let new_task = Task {
-        id,
-        client_name: payload.client_name,
-        description: payload.description,
-        task_date,
-        client_color,
-        created_at,
-        deleted_at: None, // Newly created tasks are not deleted
-        priority: payload.priority,
+    let mut tx = begin_write(pool).await?;
+    // Foreign keys are checked on commit, once every table is empty.
+    sqlx::query("PRAGMA defer_foreign_keys = ON")
+        .execute(&mut *tx)
+        .await
+        .context("Failed to defer foreign key checks")?;
+    for table in &tables {
+        sqlx::query(&format!("DELETE FROM {table}"))
+            .execute(&mut *tx)
+            .await
+            .context(format!("Failed to empty '{table}'"))?;
+    }
+    sqlx::query("DELETE FROM sqlite_sequence")
+        .execute(&mut *tx)
+        .await
+        .context("Failed to reset IDs")?;
+    tx.commit().await.context("Failed to commit reset")?;
+
+    info!("Database reset ({} tables emptied).", tables.len());
+    Ok(())
+}
+
+/// Writes a consistent copy of the database to `path`, which must not exist.
+/// Other connections keep reading and writing while the copy is made.
+pub async fn backup_database_to(pool: &SqlitePool, path: &Path) -> Result<()> {
+    sqlx::query("VACUUM INTO ?")
+        .bind(path.to_string_lossy())
+        .execute(pool)
+        .await
+        .context(format!("Failed to back up database to {}", path.display()))?;
+    // In-memory databases are copied to memory, leaving no file behind.
+    if !path.exists() {
+        return Err(anyhow!("In-memory databases cannot be backed up"));
+    }
+    Ok(())
+}
+
+/// Result of restoring a backup.
+#[derive(Debug)]
+pub enum RestoreOutcome {
+    Restored,
+    /// The file is not a usable backup, e.g. corrupted or from a newer release.
+    Invalid(String),
+}
+
+/// Replaces all data with the content of the backup at `path`.
+///
+/// The backup is first brought up to date with the migrations and checked.
+/// Its rows are then copied over in a single transaction, so that the live
+/// database is either fully restored or left untouched, and open connections
+/// stay valid.
+pub async fn restore_database_from(pool: &SqlitePool, path: &Path) -> Result<RestoreOutcome> {
+    let options = SqliteConnectOptions::new().filename(path);
+    let backup = match SqlitePool::connect_with(options).await {
+        Ok(backup) => backup,
+        Err(e) => return Ok(RestoreOutcome::Invalid(format!("Not a database: {e}"))),
     };
+    let checked = validate_backup(&backup).await;
+    backup.close().await;
+    if let Err(e) = checked {
+        return Ok(RestoreOutcome::Invalid(format!("{e:#}")));
+    }
+
+    let mut conn = pool
+        .acquire()
+        .await
+        .context("Failed to acquire connection")?;
+    sqlx::query("ATTACH DATABASE ? AS backup")
+        .bind(path.to_string_lossy())
+        .execute(&mut *conn)
+        .await
+        .context("Failed to attach backup")?;
+    let restored = copy_attached_backup(&mut conn).await;
+    sqlx::query("DETACH DATABASE backup")
+        .execute(&mut *conn)
+        .await
+        .context("Failed to detach backup")?;
+    restored?;
 
-    Ok(new_task)
+    info!("Database restored from backup.");
+    Ok(RestoreOutcome::Restored)
 }
 
-/// Soft deletes a task from the database by setting its `deleted_at` timestamp.
-/// Returns true if a task was updated, false if no task with the given ID was found.
-#[allow(clippy::uninlined_format_args)]
-pub async fn soft_delete_task_in_db(pool: &SqlitePool, task_id: i64) -> Result<bool> {
-    debug!("Attempting to soft delete task with ID: {}", task_id);
-    let now = Utc::now();
-    let result = sqlx::query(
-        "UPDATE tasks SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL", // Only update if not already deleted
+/// Checks that a backup is intact and migrates it to the current schema.
+async fn validate_backup(backup: &SqlitePool) -> Result<()> {
+    let integrity: String = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_one(backup)
+        .await
+        .context("Not a database")?;
+    if integrity != "ok" {
+        return Err(anyhow!("Backup is corrupted: {integrity}"));
+    }
+    if !table_exists(backup, "tasks").await? {
+        return Err(anyhow!("Not a backup of this application"));
+    }
+    init_schema(backup).await
+}
+
+/// Replaces the rows of every table with those of the attached `backup`.
+/// Revoked sessions are kept as they are, so that a restore does not let
+/// sessions signed out since the backup back in.
+async fn copy_attached_backup(conn: &mut SqliteConnection) -> Result<()> {
+    let tables: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM main.sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name NOT IN ('_sqlx_migrations', 'revoked_sessions')",
     )
-    .bind(now)
-    .bind(task_id)
-    .execute(pool)
+    .fetch_all(&mut *conn)
+    .await
+    .context("Failed to list tables")?;
+
+    let mut tx = conn
+        .begin_with("BEGIN IMMEDIATE")
+        .await
+        .context("Failed to start transaction")?;
+    sqlx::query("PRAGMA defer_foreign_keys = ON")
+        .execute(&mut *tx)
+        .await
+        .context("Failed to defer foreign key checks")?;
+    for table in &tables {
+        // Columns are listed by name: a database upgraded from an old
+        // release may have them in a different order.
+        let columns: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM pragma_table_info(?, 'main')")
+                .bind(table)
+                .fetch_all(&mut *tx)
+                .await
+                .context(format!("Failed to list columns of '{table}'"))?;
+        let columns = columns
+            .iter()
+            .map(|column| format!("\"{column}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+        sqlx::query(&format!("DELETE FROM main.{table}"))
+            .execute(&mut *tx)
+            .await
+            .context(format!("Failed to empty '{table}'"))?;
+        sqlx::query(&format!(
+            "INSERT INTO main.{table} ({columns}) SELECT {columns} FROM backup.{table}"
+        ))
+        .execute(&mut *tx)
+        .await
+        .context(format!("Failed to restore '{table}'"))?;
+    }
+    for statement in [
+        "DELETE FROM main.sqlite_sequence",
+        "INSERT INTO main.sqlite_sequence SELECT * FROM backup.sqlite_sequence",
+    ] {
+        sqlx::query(statement)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to restore IDs")?;
+    }
+    tx.commit().await.context("Failed to commit restore")?;
+    Ok(())
+}
+
+/// A task of a full export, with the ID its slots refer to.
+#[derive(sqlx::FromRow)]
+struct ExportedTaskRow {
+    id: i64,
+    #[sqlx(flatten)]
+    task: ExportedTask,
+}
+
+/// A slot of a full export, with the ID of its task.
+#[derive(sqlx::FromRow)]
+struct ExportedSlotRow {
+    task_id: i64,
+    #[sqlx(flatten)]
+    slot: ExportedTaskSlot,
+}
+
+/// Reads every client, task and setting into a full export. The reads share
+/// one transaction, so that no task refers to a client missing from it.
+pub async fn get_full_export_from_db(pool: &SqlitePool) -> Result<FullExport> {
+    let mut tx = pool.begin().await.context("Failed to start transaction")?;
+
+    let mut clients: Vec<ExportedClient> = sqlx::query_as(
+        "SELECT name, color, created_at, archived_at, default_priority, default_context FROM clients ORDER BY id",
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .context("Failed to export clients")?;
+    let aliases: Vec<(String, String)> = sqlx::query_as(
+        "SELECT c.name, a.alias FROM client_aliases a JOIN clients c ON c.id = a.client_id ORDER BY a.alias",
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .context("Failed to export client aliases")?;
+    let mut aliases_by_client: HashMap<String, Vec<String>> = HashMap::new();
+    for (client_name, alias) in aliases {
+        aliases_by_client
+            .entry(client_name)
+            .or_default()
+            .push(alias);
+    }
+    for client in &mut clients {
+        client.aliases = aliases_by_client.remove(&client.name).unwrap_or_default();
+    }
+
+    let tasks: Vec<ExportedTaskRow> = sqlx::query_as(
+        r#"
+        SELECT t.id, c.name AS client_name, t.description, t.task_date, t.created_at,
+               t.deleted_at AS done_at, t.priority, t.energy, t.estimate_minutes, t.context,
+               t.pin_to_date, t.rollover_target, t.rollover_count, t.stale_since,
+               t.external_source, t.external_id
+        FROM tasks t
+        JOIN clients c ON c.id = t.client_id
+        ORDER BY t.id
+        "#,
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .context("Failed to export tasks")?;
+    let slots: Vec<ExportedSlotRow> = sqlx::query_as(
+        "SELECT task_id, slot_date, estimate_minutes, created_at, completed_at FROM task_slots ORDER BY slot_date, id",
+    )
+    .fetch_all(&mut *tx)
     .await
-    .context(format!("Failed to soft delete task with ID: {}", task_id))?;
+    .context("Failed to export task slots")?;
+    let mut slots_by_task: HashMap<i64, Vec<ExportedTaskSlot>> = HashMap::new();
+    for row in slots {
+        slots_by_task.entry(row.task_id).or_default().push(row.slot);
+    }
+    let tasks = tasks
+        .into_iter()
+        .map(|row| ExportedTask {
+            slots: slots_by_task.remove(&row.id).unwrap_or_default(),
+            ..row.task
+        })
+        .collect();
+
+    let settings = ExportedSettings {
+        palette: colors::active_palette(),
+        weekly_goal: sqlx::query_as("SELECT unit, target, set_at FROM weekly_goal WHERE id = 1")
+            .fetch_optional(&mut *tx)
+            .await
+            .context("Failed to export the weekly goal")?,
+        holidays: sqlx::query_as("SELECT day, name, created_at FROM holidays ORDER BY day")
+            .fetch_all(&mut *tx)
+            .await
+            .context("Failed to export holidays")?,
+        focus_days: sqlx::query_as("SELECT day, top_n, created_at FROM focus_days ORDER BY day")
+            .fetch_all(&mut *tx)
+            .await
+            .context("Failed to export focus days")?,
+        paused_jobs: sqlx::query_scalar("SELECT job FROM paused_jobs ORDER BY job")
+            .fetch_all(&mut *tx)
+            .await
+            .context("Failed to export paused jobs")?,
+    };
+    tx.commit().await.context("Failed to end export")?;
+
+    Ok(FullExport {
+        version: FULL_EXPORT_VERSION,
+        exported_at: clock::now(),
+        clients,
+        tasks,
+        settings,
+    })
+}
+
+/// Tables emptied by an import with the `replace` strategy, children first.
+/// History, archives and webhooks are left alone.
+const FULL_IMPORT_TABLES: [&str; 8] = [
+    "task_slots",
+    "tasks",
+    "client_aliases",
+    "clients",
+    "holidays",
+    "focus_days",
+    "weekly_goal",
+    "paused_jobs",
+];
+
+/// Loads a full export in one transaction, dealing with the records already
+/// there according to `strategy`. Every task must have its client in
+/// `export.clients`, and client colors must be normalized. Imported tasks
+/// raise no webhook event.
+pub async fn import_full_export_in_db(
+    pool: &SqlitePool,
+    export: &FullExport,
+    strategy: ImportStrategy,
+) -> Result<FullImportSummary> {
+    let mut summary = FullImportSummary {
+        strategy,
+        clients: ImportCounts::default(),
+        tasks: ImportCounts::default(),
+    };
+    let mut tx = begin_write(pool).await?;
+    // Foreign keys are checked on commit, once everything is in place.
+    sqlx::query("PRAGMA defer_foreign_keys = ON")
+        .execute(&mut *tx)
+        .await
+        .context("Failed to defer foreign key checks")?;
+    if strategy == ImportStrategy::Replace {
+        for table in FULL_IMPORT_TABLES {
+            sqlx::query(&format!("DELETE FROM {table}"))
+                .execute(&mut *tx)
+                .await
+                .context(format!("Failed to empty '{table}'"))?;
+        }
+    }
+    // Rows keyed by day or name are kept or replaced whole.
+    let on_conflict = match strategy {
+        ImportStrategy::Skip => "OR IGNORE",
+        ImportStrategy::Overwrite | ImportStrategy::Replace => "OR REPLACE",
+    };
+
+    let mut client_ids = HashMap::new();
+    let mut written_colors = Vec::new();
+    for client in &export.clients {
+        let existing: Option<(i64, HexColor)> =
+            sqlx::query_as("SELECT id, color FROM clients WHERE name = ?")
+                .bind(&client.name)
+                .fetch_optional(&mut *tx)
+                .await
+                .context(format!("Failed to look up client '{}'", client.name))?;
+        let client_id = match existing {
+            None => {
+                summary.clients.created += 1;
+                written_colors.push((client.name.as_str(), &client.color));
+                sqlx::query_scalar(
+                    "INSERT INTO clients (name, color, created_at, archived_at, default_priority, default_context) VALUES (?, ?, ?, ?, ?, ?) RETURNING id",
+                )
+                .bind(&client.name)
+                .bind(&client.color)
+                .bind(client.created_at)
+                .bind(client.archived_at)
+                .bind(client.default_priority)
+                .bind(&client.default_context)
+                .fetch_one(&mut *tx)
+                .await
+                .context(format!("Failed to import client '{}'", client.name))?
+            }
+            Some((client_id, _)) if strategy == ImportStrategy::Skip => {
+                summary.clients.skipped += 1;
+                client_id
+            }
+            Some((client_id, previous_color)) => {
+                summary.clients.updated += 1;
+                written_colors.push((client.name.as_str(), &client.color));
+                sqlx::query(
+                    "UPDATE clients SET color = ?, archived_at = ?, default_priority = ?, default_context = ? WHERE id = ?",
+                )
+                .bind(&client.color)
+                .bind(client.archived_at)
+                .bind(client.default_priority)
+                .bind(&client.default_context)
+                .bind(client_id)
+                .execute(&mut *tx)
+                .await
+                .context(format!("Failed to update client '{}'", client.name))?;
+                if previous_color != client.color {
+                    sqlx::query(
+                        "INSERT INTO client_color_changes (client_id, old_color, new_color, reason, changed_at) VALUES (?, ?, ?, ?, ?)",
+                    )
+                    .bind(client_id)
+                    .bind(&previous_color)
+                    .bind(&client.color)
+                    .bind(ColorChangeReason::Override)
+                    .bind(clock::now())
+                    .execute(&mut *tx)
+                    .await
+                    .context(format!("Failed to record color change of client '{}'", client.name))?;
+                }
+                client_id
+            }
+        };
+        for alias in &client.aliases {
+            sqlx::query(&format!(
+                "INSERT {on_conflict} INTO client_aliases (alias, client_id) VALUES (?, ?)"
+            ))
+            .bind(alias)
+            .bind(client_id)
+            .execute(&mut *tx)
+            .await
+            .context(format!("Failed to import alias '{alias}'"))?;
+        }
+        client_ids.insert(client.name.as_str(), client_id);
+    }
+
+    for task in &export.tasks {
+        let client_id = *client_ids
+            .get(task.client_name.as_str())
+            .with_context(|| format!("Client '{}' is not in the export", task.client_name))?;
+        let existing: Option<i64> = match (&task.external_source, &task.external_id) {
+            (Some(source), Some(id)) => {
+                sqlx::query_scalar("SELECT id FROM tasks WHERE external_source = ? AND external_id = ?")
+                    .bind(source)
+                    .bind(id)
+                    .fetch_optional(&mut *tx)
+                    .await
+            }
+            _ => {
+                sqlx::query_scalar("SELECT id FROM tasks WHERE client_id = ? AND description = ? AND task_date = ? AND created_at = ?")
+                    .bind(client_id)
+                    .bind(&task.description)
+                    .bind(task.task_date)
+                    .bind(task.created_at)
+                    .fetch_optional(&mut *tx)
+                    .await
+            }
+        }
+        .context(format!("Failed to look up task '{}'", task.description))?;
+        let task_id: i64 = match existing {
+            Some(_) if strategy == ImportStrategy::Skip => {
+                summary.tasks.skipped += 1;
+                continue;
+            }
+            Some(task_id) => {
+                summary.tasks.updated += 1;
+                sqlx::query(
+                    r#"
+                    UPDATE tasks SET client_id = ?, description = ?, task_date = ?, created_at = ?,
+                        deleted_at = ?, priority = ?, energy = ?, estimate_minutes = ?, context = ?,
+                        pin_to_date = ?, rollover_target = ?, rollover_count = ?, stale_since = ?,
+                        updated_at = ?
+                    WHERE id = ?
+                    "#,
+                )
+                .bind(client_id)
+                .bind(&task.description)
+                .bind(task.task_date)
+                .bind(task.created_at)
+                .bind(task.done_at)
+                .bind(task.priority)
+                .bind(task.energy)
+                .bind(task.estimate_minutes)
+                .bind(&task.context)
+                .bind(task.pin_to_date)
+                .bind(task.rollover_target)
+                .bind(task.rollover_count)
+                .bind(task.stale_since)
+                .bind(clock::now())
+                .bind(task_id)
+                .execute(&mut *tx)
+                .await
+                .context(format!("Failed to update task {task_id}"))?;
+                sqlx::query("DELETE FROM task_slots WHERE task_id = ?")
+                    .bind(task_id)
+                    .execute(&mut *tx)
+                    .await
+                    .context(format!("Failed to replace slots of task {task_id}"))?;
+                task_id
+            }
+            None => {
+                summary.tasks.created += 1;
+                sqlx::query_scalar(
+                    r#"
+                    INSERT INTO tasks (client_id, description, task_date, created_at, deleted_at,
+                        priority, energy, estimate_minutes, context, pin_to_date, rollover_target,
+                        rollover_count, stale_since, external_source, external_id)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    RETURNING id
+                    "#,
+                )
+                .bind(client_id)
+                .bind(&task.description)
+                .bind(task.task_date)
+                .bind(task.created_at)
+                .bind(task.done_at)
+                .bind(task.priority)
+                .bind(task.energy)
+                .bind(task.estimate_minutes)
+                .bind(&task.context)
+                .bind(task.pin_to_date)
+                .bind(task.rollover_target)
+                .bind(task.rollover_count)
+                .bind(task.stale_since)
+                .bind(&task.external_source)
+                .bind(&task.external_id)
+                .fetch_one(&mut *tx)
+                .await
+                .context(format!("Failed to import task '{}'", task.description))?
+            }
+        };
+        for slot in &task.slots {
+            sqlx::query(
+                "INSERT INTO task_slots (task_id, slot_date, estimate_minutes, created_at, completed_at) VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(task_id)
+            .bind(slot.slot_date)
+            .bind(slot.estimate_minutes)
+            .bind(slot.created_at)
+            .bind(slot.completed_at)
+            .execute(&mut *tx)
+            .await
+            .context(format!("Failed to import slots of task {task_id}"))?;
+        }
+    }
+
+    let settings = &export.settings;
+    if let Some(goal) = &settings.weekly_goal {
+        sqlx::query(&format!(
+            "INSERT {on_conflict} INTO weekly_goal (id, unit, target, set_at) VALUES (1, ?, ?, ?)"
+        ))
+        .bind(goal.unit)
+        .bind(goal.target)
+        .bind(goal.set_at)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to import the weekly goal")?;
+    }
+    for holiday in &settings.holidays {
+        sqlx::query(&format!(
+            "INSERT {on_conflict} INTO holidays (day, name, created_at) VALUES (?, ?, ?)"
+        ))
+        .bind(holiday.day)
+        .bind(&holiday.name)
+        .bind(holiday.created_at)
+        .execute(&mut *tx)
+        .await
+        .context(format!("Failed to import holiday on {}", holiday.day))?;
+    }
+    for focus_day in &settings.focus_days {
+        sqlx::query(&format!(
+            "INSERT {on_conflict} INTO focus_days (day, top_n, created_at) VALUES (?, ?, ?)"
+        ))
+        .bind(focus_day.day)
+        .bind(focus_day.top_n)
+        .bind(focus_day.created_at)
+        .execute(&mut *tx)
+        .await
+        .context(format!("Failed to import focus day {}", focus_day.day))?;
+    }
+    for job in &settings.paused_jobs {
+        sqlx::query("INSERT OR IGNORE INTO paused_jobs (job, paused_at) VALUES (?, ?)")
+            .bind(job)
+            .bind(export.exported_at)
+            .execute(&mut *tx)
+            .await
+            .context(format!("Failed to import paused job {job}"))?;
+    }
+    tx.commit().await.context("Failed to commit import")?;
 
-    let rows_affected = result.rows_affected();
+    // Colors are also kept outside the database, for assignment.
+    if strategy == ImportStrategy::Replace {
+        colors::reset_client_colors();
+    }
+    if strategy != ImportStrategy::Skip {
+        colors::set_active_palette(settings.palette);
+    }
+    for (name, color) in written_colors {
+        colors::set_client_color(name, color);
+    }
     info!(
-        "Soft deleted {} rows for task ID: {}",
-        rows_affected, task_id
+        "Full export imported ({:?}): clients {:?}, tasks {:?}",
+        strategy, summary.clients, summary.tasks
     );
 
-    Ok(rows_affected > 0)
+    Ok(summary)
 }
 
-/// Rolls over incomplete (not soft-deleted) tasks from today to tomorrow.
-pub async fn rollover_tasks_in_db(pool: &SqlitePool) -> Result<usize> {
-    let today = Utc::now().date_naive();
-    let tomorrow = today.succ_opt().context("Failed to get tomorrow's date")?;
+/// Checks whether a table exists.
+async fn table_exists(pool: &SqlitePool, table: &str) -> Result<bool> {
+    let exists = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)",
+    )
+    .bind(table)
+    .fetch_one(pool)
+    .await
+    .with_context(|| format!("Failed to look up table '{table}'"))?;
 
-    debug!(
-        "Attempting to roll over tasks from {} to {}",
-        today, tomorrow
-    );
+    Ok(exists)
+}
 
-    let result =
-        sqlx::query("UPDATE tasks SET task_date = ? WHERE task_date = ? AND deleted_at IS NULL")
-            .bind(tomorrow)
-            .bind(today)
-            .execute(pool)
+/// Checks whether a table has a given column.
+async fn column_exists<'e, E>(executor: E, table: &str, column: &str) -> Result<bool>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let exists =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pragma_table_info(?) WHERE name = ?)")
+            .bind(table)
+            .bind(column)
+            .fetch_one(executor)
             .await
-            .context("Failed to roll over tasks in DB")?;
+            .with_context(|| format!("Failed to inspect columns of '{table}'"))?;
 
-    let num_rolled_over = result.rows_affected() as usize;
-    info!("Successfully rolled over {} tasks.", num_rolled_over);
+    Ok(exists)
+}
 
-    Ok(num_rolled_over)
+/// Selects tasks together with the name and color of their client, so the
+/// result maps onto `Task`. Filters must refer to task columns as `t.<column>`.
+const TASK_SELECT: &str = r#"
+    SELECT t.id, t.client_id, c.name AS client_name, t.description, t.task_date,
+           c.color AS client_color, t.created_at, t.deleted_at, t.updated_at,
+           t.priority, t.external_source, t.external_id, t.energy, t.estimate_minutes, t.context,
+           t.pin_to_date, t.rollover_target, t.rollover_count, t.stale_since,
+           (SELECT COUNT(*) FROM task_slots WHERE task_id = t.id) AS slots_total,
+           (SELECT COUNT(completed_at) FROM task_slots WHERE task_id = t.id) AS slots_done
+    FROM tasks t
+    JOIN clients c ON c.id = t.client_id
+"#;
+
+/// Same as `TASK_SELECT`, restricted to the columns of `TaskSummary`.
+/// A task split into slots yields one row per open slot, dated on the slot's
+/// day; filters must use `SUMMARY_DATE` rather than `t.task_date` and keep
+/// `SUMMARY_SLOT_FILTER` so that such tasks are not listed on their own date too.
+const TASK_SUMMARY_SELECT: &str = r#"
+    SELECT t.id, c.name AS client_name, c.color AS client_color, t.description,
+           COALESCE(s.slot_date, t.task_date) AS task_date, t.priority, t.context,
+           s.id AS slot_id,
+           (SELECT COUNT(*) FROM task_slots WHERE task_id = t.id) AS slots_total,
+           (SELECT COUNT(completed_at) FROM task_slots WHERE task_id = t.id) AS slots_done
+    FROM tasks t
+    JOIN clients c ON c.id = t.client_id
+    LEFT JOIN task_slots s ON s.task_id = t.id AND s.completed_at IS NULL
+"#;
+
+/// Day a `TASK_SUMMARY_SELECT` row is listed on.
+const SUMMARY_DATE: &str = "COALESCE(s.slot_date, t.task_date)";
+
+/// Keeps the slot rows of split tasks and the tasks without any slot.
+const SUMMARY_SLOT_FILTER: &str =
+    "(s.id IS NOT NULL OR NOT EXISTS (SELECT 1 FROM task_slots WHERE task_id = t.id))";
+
+/// Retrieves a single task by ID, including soft-deleted ones.
+pub async fn get_task_by_id_from_db<'e, E>(executor: E, task_id: i64) -> Result<Option<Task>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let task = sqlx::query_as::<_, Task>(&format!("{TASK_SELECT} WHERE t.id = ?"))
+        .bind(task_id)
+        .fetch_optional(executor)
+        .await
+        .context(format!("Failed to retrieve task with ID: {task_id}"))?;
+
+    Ok(task.map(presentation::for_display))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use chrono::{Duration, Utc};
-    use common::CreateTaskPayload;
-    use std::fs;
-    use std::path::PathBuf;
+/// SQL expression of a field clients are allowed to sort on. Fields are
+/// parsed into `TaskSortField` first, so user input never reaches the query
+/// text.
+fn sort_column(field: TaskSortField) -> &'static str {
+    match field {
+        TaskSortField::Id => "t.id",
+        TaskSortField::ClientName => "c.name",
+        TaskSortField::Description => "t.description",
+        TaskSortField::TaskDate => "t.task_date",
+        TaskSortField::Priority => "t.priority",
+        TaskSortField::CreatedAt => "t.created_at",
+    }
+}
 
-    const TEST_TARGET_DIR_PATH: &str = "database";
+/// Default ordering of the task list: by day, then by priority.
+pub const DEFAULT_ORDER_BY: &str = "t.task_date ASC, t.priority ASC NULLS LAST";
 
-    /// Returns the absolute path to the test data directory.
-    fn get_test_data_dir() -> PathBuf {
-        // Path::new(".").canonicalize() gets the absolute path of the current working directory.
-        // This makes the cleanup more robust regardless of where `cargo test` is called from.
-        let mut path = std::env::current_dir()
-            .expect("Failed to get current working directory for test cleanup");
+/// Translates a sort specification such as `priority,-created_at` into an SQL
+/// `ORDER BY` clause (without the keyword). A leading `-` sorts descending.
+/// Returns the offending field name if it is not in the whitelist.
+pub fn build_order_by(spec: &str) -> std::result::Result<String, String> {
+    Ok(order_by(&spec.parse()?))
+}
+
+/// `ORDER BY` clause (without the keyword) of a parsed sort specification.
+pub fn order_by(spec: &SortSpec) -> String {
+    if spec.0.is_empty() {
+        return DEFAULT_ORDER_BY.to_string();
+    }
+    let clauses: Vec<String> = spec
+        .0
+        .iter()
+        .map(|key| {
+            let column = sort_column(key.field);
+            let direction = if key.descending { "DESC" } else { "ASC" };
+            // Tasks without a priority always go last, whatever the direction.
+            if key.field == TaskSortField::Priority {
+                format!("{column} {direction} NULLS LAST")
+            } else {
+                format!("{column} {direction}")
+            }
+        })
+        .collect();
+    clauses.join(", ")
+}
+
+/// Returns the first (Monday) and last (Sunday) day of the current week.
+pub fn current_week_range() -> (NaiveDate, NaiveDate) {
+    let week = clock::today().week(Weekday::Mon);
+    (week.first_day(), week.last_day())
+}
+
+/// Retrieves tasks for the current week (Monday to Sunday), excluding soft-deleted tasks.
+pub async fn get_current_week_tasks_from_db(pool: &SqlitePool) -> Result<Vec<Task>> {
+    get_current_week_tasks_sorted_from_db(pool, DEFAULT_ORDER_BY).await
+}
+
+/// Same as `get_current_week_tasks_from_db`, with an `ORDER BY` clause produced
+/// by `build_order_by`.
+pub async fn get_current_week_tasks_sorted_from_db(
+    pool: &SqlitePool,
+    order_by: &str,
+) -> Result<Vec<Task>> {
+    let (week_start, week_end) = current_week_range();
+
+    let tasks = sqlx::query_as::<_, Task>(&format!(
+        "{TASK_SELECT} WHERE t.task_date BETWEEN ? AND ? AND t.deleted_at IS NULL ORDER BY {order_by};"
+    ))
+    .bind(week_start)
+    .bind(week_end)
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve current week's tasks from DB")?;
+
+    Ok(tasks.into_iter().map(presentation::for_display).collect())
+}
+
+/// Retrieves up to `limit` tasks with an ID greater than `after_id`,
+/// soft-deleted ones included, ordered by ID.
+pub async fn get_tasks_after_from_db(
+    pool: &SqlitePool,
+    after_id: i64,
+    limit: i64,
+) -> Result<Vec<Task>> {
+    let tasks = sqlx::query_as::<_, Task>(&format!(
+        "{TASK_SELECT} WHERE t.id > ? ORDER BY t.id LIMIT ?"
+    ))
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context(format!("Failed to retrieve tasks after ID {after_id}"))?;
+
+    Ok(tasks.into_iter().map(presentation::for_display).collect())
+}
+
+// Keeps the tasks of a context along with those without any, which can be
+// done anywhere. Binds the context twice; a NULL context keeps everything.
+const CONTEXT_FILTER: &str = "(?1 IS NULL OR t.context IS NULL OR t.context = ?1)";
+
+/// Retrieves the open (not soft-deleted) tasks of a day, by priority,
+/// optionally restricted to a context.
+pub async fn get_open_tasks_for_day_from_db(
+    pool: &SqlitePool,
+    day: NaiveDate,
+    context: Option<&str>,
+) -> Result<Vec<Task>> {
+    let tasks = sqlx::query_as::<_, Task>(&format!(
+        "{TASK_SELECT} WHERE {CONTEXT_FILTER} AND t.task_date = ?2 AND t.deleted_at IS NULL ORDER BY {DEFAULT_ORDER_BY}"
+    ))
+    .bind(context)
+    .bind(day)
+    .fetch_all(pool)
+    .await
+    .context(format!("Failed to retrieve open tasks of {day}"))?;
+
+    Ok(tasks.into_iter().map(presentation::for_display).collect())
+}
+
+/// Summaries of the current week's tasks, ordered by an `ORDER BY` clause
+/// produced by `build_order_by` and optionally restricted to a context.
+/// Only the displayed columns are read.
+pub async fn get_current_week_task_summaries_from_db(
+    pool: &SqlitePool,
+    order_by: &str,
+    context: Option<&str>,
+) -> Result<Vec<TaskSummary>> {
+    get_task_summaries_between_from_db(pool, current_week_range(), order_by, context).await
+}
+
+/// Summaries of the open tasks from `first` to `last` included, like
+/// `get_current_week_task_summaries_from_db` for any week.
+pub async fn get_task_summaries_between_from_db(
+    pool: &SqlitePool,
+    (first, last): (NaiveDate, NaiveDate),
+    order_by: &str,
+    context: Option<&str>,
+) -> Result<Vec<TaskSummary>> {
+    let tasks = sqlx::query_as::<_, TaskSummary>(&format!(
+        "{TASK_SUMMARY_SELECT} WHERE {CONTEXT_FILTER} AND {SUMMARY_SLOT_FILTER} AND {SUMMARY_DATE} BETWEEN ?2 AND ?3 AND t.deleted_at IS NULL ORDER BY {order_by};"
+    ))
+    .bind(context)
+    .bind(first)
+    .bind(last)
+    .fetch_all(pool)
+    .await
+    .context(format!("Failed to retrieve task summaries from {first} to {last}"))?;
+
+    Ok(tasks.into_iter().map(presentation::for_display).collect())
+}
+
+/// Inserts a new task into the database.
+/// If the payload carries an `external_source`/`external_id` pair that already
+/// exists, the matching task is updated in place instead of duplicated.
+pub async fn create_task_in_db(pool: &SqlitePool, payload: CreateTaskPayload) -> Result<Task> {
+    let mut tx = begin_write(pool).await?;
+    let task = insert_task(&mut tx, payload).await?;
+    tx.commit()
+        .await
+        .context("Failed to commit task creation")?;
+    Ok(task)
+}
+
+/// Creates several tasks in a single transaction: either all of them are
+/// created, or none is.
+pub async fn create_tasks_in_db(
+    pool: &SqlitePool,
+    payloads: Vec<CreateTaskPayload>,
+) -> Result<Vec<Task>> {
+    let mut tx = begin_write(pool).await?;
+    let mut tasks = Vec::with_capacity(payloads.len());
+    for payload in payloads {
+        tasks.push(insert_task(&mut tx, payload).await?);
+    }
+    tx.commit()
+        .await
+        .context("Failed to commit bulk task creation")?;
+    Ok(tasks)
+}
+
+/// Result of capturing a task.
+#[derive(Debug)]
+pub enum CaptureOutcome {
+    Created(Box<Task>),
+    // An open task of the same client and day already had this description.
+    Duplicate(Box<Task>),
+}
+
+/// Creates a task unless an open task of the same client and day has the
+/// same description, ignoring case and surrounding spaces, in which case
+/// that task is returned instead. Capturing twice is thus harmless.
+pub async fn capture_task_in_db(
+    pool: &SqlitePool,
+    mut payload: CreateTaskPayload,
+) -> Result<CaptureOutcome> {
+    let mut tx = begin_write(pool).await?;
+
+    if let Some(canonical) = resolve_client_alias(&mut *tx, &payload.client_name).await? {
+        payload.client_name = canonical;
+    }
+    let task_date = payload.task_date.unwrap_or_else(clock::today);
+    let duplicate: Option<i64> = sqlx::query_scalar(
+        r#"
+        SELECT t.id FROM tasks t JOIN clients c ON c.id = t.client_id
+        WHERE c.name = ? AND t.task_date = ? AND t.deleted_at IS NULL
+          AND lower(trim(t.description)) = lower(trim(?))
+        LIMIT 1
+        "#,
+    )
+    .bind(&payload.client_name)
+    .bind(task_date)
+    .bind(&payload.description)
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to look for a duplicate of the captured task")?;
+
+    let outcome = match duplicate {
+        Some(id) => CaptureOutcome::Duplicate(Box::new(
+            get_task_by_id_from_db(&mut *tx, id)
+                .await?
+                .context("Duplicate task could not be read back")?,
+        )),
+        None => CaptureOutcome::Created(Box::new(insert_task(&mut tx, payload).await?)),
+    };
+
+    tx.commit()
+        .await
+        .context("Failed to commit captured task")?;
+    Ok(outcome)
+}
+
+/// Result of creating a task under an `Idempotency-Key`.
+#[derive(Debug)]
+pub enum IdempotentCreation {
+    Created(Box<Task>),
+    /// The key was already used for the same request: its response, as it
+    /// was first sent.
+    Replayed {
+        status: u16,
+        body: String,
+    },
+    /// The key was already used for another request.
+    Mismatch,
+}
+
+/// Creates a task once per idempotency key: the first request with `key`
+/// creates it and keeps the response, which retries with the same key and
+/// `request_hash` get back instead of a new task. Keys older than `ttl` are
+/// dropped first, so that they can be used again.
+pub async fn create_task_idempotently_in_db(
+    pool: &SqlitePool,
+    key: &str,
+    request_hash: &str,
+    payload: CreateTaskPayload,
+    ttl: std::time::Duration,
+) -> Result<IdempotentCreation> {
+    let mut tx = begin_write(pool).await?;
+    let now = clock::now();
+    let expired =
+        now - chrono::Duration::from_std(ttl).context("Idempotency key TTL out of range")?;
+    sqlx::query("DELETE FROM idempotency_keys WHERE created_at < ?")
+        .bind(expired)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to drop expired idempotency keys")?;
+
+    let stored: Option<(String, u16, String)> =
+        sqlx::query_as("SELECT request_hash, status, body FROM idempotency_keys WHERE key = ?")
+            .bind(key)
+            .fetch_optional(&mut *tx)
+            .await
+            .context("Failed to look up the idempotency key")?;
+    let outcome = match stored {
+        Some((hash, status, body)) if hash == request_hash => {
+            IdempotentCreation::Replayed { status, body }
+        }
+        Some(_) => IdempotentCreation::Mismatch,
+        None => {
+            let task = insert_task(&mut tx, payload).await?;
+            let body = serde_json::to_string(&task).context("Failed to serialize the task")?;
+            sqlx::query(
+                "INSERT INTO idempotency_keys (key, request_hash, status, body, created_at) VALUES (?, ?, 201, ?, ?)",
+            )
+            .bind(key)
+            .bind(request_hash)
+            .bind(body)
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to store the idempotency key")?;
+            IdempotentCreation::Created(Box::new(task))
+        }
+    };
+
+    tx.commit()
+        .await
+        .context("Failed to commit idempotent task creation")?;
+    Ok(outcome)
+}
+
+/// Inserts (or upserts) a task on the given connection, so that it can also run
+/// inside a transaction. Client aliases are resolved to their canonical name first,
+/// and the client is registered if it does not exist yet.
+async fn insert_task(conn: &mut SqliteConnection, mut payload: CreateTaskPayload) -> Result<Task> {
+    if let Some(canonical) = resolve_client_alias(&mut *conn, &payload.client_name).await? {
+        debug!(
+            "Client alias '{}' resolved to '{}'",
+            payload.client_name, canonical
+        );
+        payload.client_name = canonical;
+    }
+
+    let task_date = payload.task_date.unwrap_or_else(clock::today);
+    let client = ensure_client(&mut *conn, &payload.client_name).await?;
+    let created_at = clock::now();
+    payload.priority = payload.priority.or(client.default_priority);
+    if payload.context.is_none() {
+        payload.context = client.default_context.clone();
+    }
+
+    debug!("Insert values: client_id={}, client_name={}, description={}, task_date={}, created_at={}, priority={:?}, external_source={:?}, external_id={:?}",
+           client.id, client.name, payload.description, task_date, created_at, payload.priority, payload.external_source, payload.external_id);
+
+    // Make sure to include deleted_at in the column list and provide a value (NULL for new tasks).
+    // `created_at` and `deleted_at` are left untouched when an imported task is updated.
+    let id: i64 = sqlx::query_scalar(
+        r#"
+        INSERT INTO tasks (client_id, description, task_date, created_at, deleted_at, priority, external_source, external_id, energy, estimate_minutes, context, pin_to_date, rollover_target)
+        VALUES (?, ?, ?, ?, NULL, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (external_source, external_id) DO UPDATE SET
+            client_id = excluded.client_id,
+            description = excluded.description,
+            task_date = excluded.task_date,
+            priority = excluded.priority,
+            energy = excluded.energy,
+            estimate_minutes = excluded.estimate_minutes,
+            context = excluded.context,
+            pin_to_date = excluded.pin_to_date,
+            rollover_target = excluded.rollover_target,
+            updated_at = excluded.created_at
+        RETURNING id
+        "#,
+    )
+    .bind(client.id)
+    .bind(&payload.description)
+    .bind(task_date)
+    .bind(created_at)
+    .bind(payload.priority)
+    .bind(&payload.external_source)
+    .bind(&payload.external_id)
+    .bind(payload.energy)
+    .bind(payload.estimate_minutes)
+    .bind(&payload.context)
+    .bind(payload.pin_to_date)
+    .bind(payload.rollover_target)
+    .fetch_one(&mut *conn)
+    .await
+    .context("Failed to insert task into DB")?;
+
+    let task = get_task_by_id_from_db(&mut *conn, id)
+        .await?
+        .context("Inserted task could not be read back")?;
+    // An updated import keeps the creation date of its first import.
+    if task.created_at == created_at {
+        queue_webhook_event(conn, WebhookEvent::TaskCreated, &task).await?;
+    }
+    Ok(task)
+}
+
+/// Result of a change to a task, made at the version the client last read.
+#[derive(Debug)]
+pub enum TaskChange {
+    Changed(Box<Task>),
+    NotFound,
+    /// The task was written since that version; nothing was changed.
+    Stale,
+}
+
+/// Whether a task is still at the version a client last read, its
+/// `updated_at`. Always true without a version, or for a missing task so
+/// that it is reported as such.
+async fn task_version_matches(
+    conn: &mut SqliteConnection,
+    task_id: i64,
+    expected: Option<DateTime<Utc>>,
+) -> Result<bool> {
+    let Some(expected) = expected else {
+        return Ok(true);
+    };
+    let updated_at: Option<DateTime<Utc>> =
+        sqlx::query_scalar("SELECT updated_at FROM tasks WHERE id = ?")
+            .bind(task_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .context(format!("Failed to retrieve the version of task {task_id}"))?;
+    Ok(updated_at.is_none_or(|updated_at| updated_at == expected))
+}
+
+/// Moves the version of a task to `now` after a change to its slots, which
+/// the task shows as counts.
+async fn touch_task(conn: &mut SqliteConnection, task_id: i64, now: DateTime<Utc>) -> Result<()> {
+    sqlx::query("UPDATE tasks SET updated_at = ? WHERE id = ?")
+        .bind(now)
+        .bind(task_id)
+        .execute(&mut *conn)
+        .await
+        .context(format!("Failed to update the version of task {task_id}"))?;
+    Ok(())
+}
+
+/// Soft deletes a task from the database by setting its `deleted_at` timestamp,
+/// if it is still at the `expected` version. Tasks already done are not found.
+#[allow(clippy::uninlined_format_args)]
+pub async fn soft_delete_task_in_db(
+    pool: &SqlitePool,
+    task_id: i64,
+    expected: Option<DateTime<Utc>>,
+) -> Result<TaskChange> {
+    debug!("Attempting to soft delete task with ID: {}", task_id);
+    let mut tx = begin_write(pool).await?;
+    if !task_version_matches(&mut tx, task_id, expected).await? {
+        return Ok(TaskChange::Stale);
+    }
+    if !soft_delete_task(&mut tx, task_id).await? {
+        return Ok(TaskChange::NotFound);
+    }
+    let task = get_task_by_id_from_db(&mut *tx, task_id)
+        .await?
+        .context("Deleted task could not be read back")?;
+    tx.commit()
+        .await
+        .context(format!("Failed to commit deletion of task {}", task_id))?;
+    info!("Soft deleted task ID: {}", task_id);
+
+    Ok(TaskChange::Changed(Box::new(task)))
+}
+
+/// Soft deletes an open task on the given connection, so that it can also
+/// run inside a transaction. Returns false if there is no such open task.
+async fn soft_delete_task(conn: &mut SqliteConnection, task_id: i64) -> Result<bool> {
+    let now = clock::now();
+    let result = sqlx::query(
+        "UPDATE tasks SET deleted_at = ?, updated_at = ? WHERE id = ? AND deleted_at IS NULL", // Only update if not already deleted
+    )
+    .bind(now)
+    .bind(now)
+    .bind(task_id)
+    .execute(&mut *conn)
+    .await
+    .context(format!("Failed to soft delete task with ID: {task_id}"))?;
+
+    let deleted = result.rows_affected() > 0;
+    if deleted && let Some(task) = get_task_by_id_from_db(&mut *conn, task_id).await? {
+        queue_webhook_event(conn, WebhookEvent::TaskDeleted, &task).await?;
+    }
+    Ok(deleted)
+}
+
+/// Pins a task to its day, or unpins it, if it is still at the `expected`
+/// version.
+pub async fn set_task_pinned_in_db(
+    pool: &SqlitePool,
+    task_id: i64,
+    pinned: bool,
+    expected: Option<DateTime<Utc>>,
+) -> Result<TaskChange> {
+    let mut tx = begin_write(pool).await?;
+    if !task_version_matches(&mut tx, task_id, expected).await? {
+        return Ok(TaskChange::Stale);
+    }
+    let result = sqlx::query("UPDATE tasks SET pin_to_date = ?, updated_at = ? WHERE id = ?")
+        .bind(pinned)
+        .bind(clock::now())
+        .bind(task_id)
+        .execute(&mut *tx)
+        .await
+        .context(format!("Failed to update pin of task {task_id}"))?;
+
+    if result.rows_affected() == 0 {
+        return Ok(TaskChange::NotFound);
+    }
+    let task = get_task_by_id_from_db(&mut *tx, task_id)
+        .await?
+        .context("Pinned task could not be read back")?;
+    tx.commit().await.context("Failed to commit task pin")?;
+    info!("Task {} pinned to its date: {}", task_id, pinned);
+    Ok(TaskChange::Changed(Box::new(task)))
+}
+
+/// Sets the day a task rolls over to, or lets it follow the rollover policy
+/// again with `None`, if the task is still at the `expected` version.
+pub async fn set_task_rollover_target_in_db(
+    pool: &SqlitePool,
+    task_id: i64,
+    target: Option<RolloverTarget>,
+    expected: Option<DateTime<Utc>>,
+) -> Result<TaskChange> {
+    let mut tx = begin_write(pool).await?;
+    if !task_version_matches(&mut tx, task_id, expected).await? {
+        return Ok(TaskChange::Stale);
+    }
+    let result = sqlx::query("UPDATE tasks SET rollover_target = ?, updated_at = ? WHERE id = ?")
+        .bind(target)
+        .bind(clock::now())
+        .bind(task_id)
+        .execute(&mut *tx)
+        .await
+        .context(format!(
+            "Failed to update the rollover target of task {task_id}"
+        ))?;
+
+    if result.rows_affected() == 0 {
+        return Ok(TaskChange::NotFound);
+    }
+    let task = get_task_by_id_from_db(&mut *tx, task_id)
+        .await?
+        .context("Task could not be read back")?;
+    tx.commit()
+        .await
+        .context("Failed to commit the rollover target")?;
+    info!("Task {} rolls over to: {:?}", task_id, target);
+    Ok(TaskChange::Changed(Box::new(task)))
+}
+
+/// Result of archiving a week.
+#[derive(Debug)]
+pub enum ArchiveWeekOutcome {
+    AlreadyArchived,
+    // Tasks or slots of the week are still open; nothing was changed.
+    Incomplete(WeekIntegrityReport),
+    Archived(WeekIntegrityReport),
+}
+
+/// Closes a week: checks that none of its tasks and slots is still open,
+/// stores a snapshot of its done tasks with per-client totals, and, when
+/// `purge` is set, deletes those tasks from the working tables. Everything
+/// happens in one transaction.
+pub async fn archive_week_in_db(
+    pool: &SqlitePool,
+    week: &str,
+    (week_start, week_end): (NaiveDate, NaiveDate),
+    purge: bool,
+) -> Result<ArchiveWeekOutcome> {
+    let mut tx = begin_write(pool).await?;
+
+    let archived: Option<String> =
+        sqlx::query_scalar("SELECT week FROM week_archives WHERE week = ?")
+            .bind(week)
+            .fetch_optional(&mut *tx)
+            .await
+            .context(format!("Failed to look for the archive of {week}"))?;
+    if archived.is_some() {
+        return Ok(ArchiveWeekOutcome::AlreadyArchived);
+    }
+
+    let open_task_ids: Vec<i64> = sqlx::query_scalar(
+        "SELECT id FROM tasks WHERE task_date BETWEEN ? AND ? AND deleted_at IS NULL ORDER BY id",
+    )
+    .bind(week_start)
+    .bind(week_end)
+    .fetch_all(&mut *tx)
+    .await
+    .context(format!("Failed to list the open tasks of {week}"))?;
+    let open_slot_ids: Vec<i64> = sqlx::query_scalar(
+        "SELECT id FROM task_slots WHERE slot_date BETWEEN ? AND ? AND completed_at IS NULL ORDER BY id",
+    )
+    .bind(week_start)
+    .bind(week_end)
+    .fetch_all(&mut *tx)
+    .await
+    .context(format!("Failed to list the open slots of {week}"))?;
+
+    let tasks = sqlx::query_as::<_, Task>(&format!(
+        "{TASK_SELECT} WHERE t.task_date BETWEEN ? AND ? AND t.deleted_at IS NOT NULL ORDER BY t.task_date, t.id"
+    ))
+    .bind(week_start)
+    .bind(week_end)
+    .fetch_all(&mut *tx)
+    .await
+    .context(format!("Failed to retrieve the done tasks of {week}"))?;
+
+    let mut report = WeekIntegrityReport {
+        week: week.to_string(),
+        tasks_done: tasks.len() as i64,
+        open_task_ids,
+        open_slot_ids,
+        archived: false,
+        tasks_purged: 0,
+    };
+    if !report.open_task_ids.is_empty() || !report.open_slot_ids.is_empty() {
+        return Ok(ArchiveWeekOutcome::Incomplete(report));
+    }
+
+    let mut clients: BTreeMap<&str, WeekClientTotals> = BTreeMap::new();
+    for task in &tasks {
+        let totals = clients
+            .entry(&task.client_name)
+            .or_insert_with(|| WeekClientTotals {
+                client_name: task.client_name.clone(),
+                tasks_done: 0,
+                estimate_minutes: 0,
+            });
+        totals.tasks_done += 1;
+        totals.estimate_minutes += i64::from(task.estimate_minutes.unwrap_or(0));
+    }
+    let archive = WeekArchive {
+        week: week.to_string(),
+        week_start,
+        week_end,
+        archived_at: clock::now(),
+        clients: clients.into_values().collect(),
+        tasks: tasks.into_iter().map(presentation::for_display).collect(),
+        summary: None,
+    };
+
+    sqlx::query(
+        "INSERT INTO week_archives (week, week_start, week_end, archived_at, snapshot) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(week)
+    .bind(week_start)
+    .bind(week_end)
+    .bind(archive.archived_at)
+    .bind(serde_json::to_string(&archive).context("Failed to serialize the week archive")?)
+    .execute(&mut *tx)
+    .await
+    .context(format!("Failed to store the archive of {week}"))?;
+    report.archived = true;
+
+    if purge {
+        let task_ids: Vec<i64> = archive.tasks.iter().map(|task| task.id).collect();
+        for task_id in &task_ids {
+            sqlx::query("DELETE FROM task_slots WHERE task_id = ?")
+                .bind(task_id)
+                .execute(&mut *tx)
+                .await
+                .context(format!("Failed to purge the slots of task {task_id}"))?;
+            report.tasks_purged += sqlx::query("DELETE FROM tasks WHERE id = ?")
+                .bind(task_id)
+                .execute(&mut *tx)
+                .await
+                .context(format!("Failed to purge task {task_id}"))?
+                .rows_affected();
+        }
+    }
+
+    tx.commit().await.context("Failed to commit week archive")?;
+    info!(
+        "Week {} archived with {} tasks ({} purged)",
+        week, report.tasks_done, report.tasks_purged
+    );
+
+    Ok(ArchiveWeekOutcome::Archived(report))
+}
+
+/// Retrieves the snapshot of an archived week.
+pub async fn get_week_archive_from_db(
+    pool: &SqlitePool,
+    week: &str,
+) -> Result<Option<WeekArchive>> {
+    let snapshot: Option<String> =
+        sqlx::query_scalar("SELECT snapshot FROM week_archives WHERE week = ?")
+            .bind(week)
+            .fetch_optional(pool)
+            .await
+            .context(format!("Failed to retrieve the archive of {week}"))?;
+    snapshot
+        .map(|snapshot| {
+            serde_json::from_str(&snapshot)
+                .context(format!("Archive of {week} is not a valid snapshot"))
+        })
+        .transpose()
+}
+
+/// Lists the holidays, by day.
+pub async fn get_holidays_from_db(pool: &SqlitePool) -> Result<Vec<Holiday>> {
+    sqlx::query_as::<_, Holiday>("SELECT day, name, created_at FROM holidays ORDER BY day")
+        .fetch_all(pool)
+        .await
+        .context("Failed to retrieve holidays")
+}
+
+/// Adds a holiday, or renames it if the day already is one.
+pub async fn add_holiday_in_db(pool: &SqlitePool, day: NaiveDate, name: &str) -> Result<Holiday> {
+    let holiday = sqlx::query_as::<_, Holiday>(
+        r#"
+        INSERT INTO holidays (day, name, created_at) VALUES (?, ?, ?)
+        ON CONFLICT (day) DO UPDATE SET name = excluded.name
+        RETURNING day, name, created_at
+        "#,
+    )
+    .bind(day)
+    .bind(name)
+    .bind(clock::now())
+    .fetch_one(pool)
+    .await
+    .context(format!("Failed to add holiday on {day}"))?;
+
+    info!("Holiday '{}' added on {}", name, day);
+    Ok(holiday)
+}
+
+/// Removes a holiday. Returns false if the day was not one.
+pub async fn delete_holiday_in_db(pool: &SqlitePool, day: NaiveDate) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM holidays WHERE day = ?")
+        .bind(day)
+        .execute(pool)
+        .await
+        .context(format!("Failed to delete holiday on {day}"))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Result of moving a task to another day.
+#[derive(Debug)]
+pub enum MoveTaskOutcome {
+    NotFound,
+    // Done tasks keep the day they were done on.
+    Done,
+    // The task was written since the version the client read.
+    Stale,
+    Moved(Box<Task>),
+}
+
+/// Reschedules an open task to another day, if it is still at the
+/// `expected` version.
+pub async fn move_task_in_db(
+    pool: &SqlitePool,
+    task_id: i64,
+    task_date: NaiveDate,
+    expected: Option<DateTime<Utc>>,
+) -> Result<MoveTaskOutcome> {
+    let mut tx = begin_write(pool).await?;
+    let outcome = move_task(&mut tx, task_id, task_date, expected).await?;
+    tx.commit().await.context("Failed to commit task move")?;
+    if let MoveTaskOutcome::Moved(_) = outcome {
+        info!("Task {} moved to {}", task_id, task_date);
+    }
+
+    Ok(outcome)
+}
+
+/// Reschedules an open task on the given connection, so that it can also
+/// run inside a transaction.
+async fn move_task(
+    conn: &mut SqliteConnection,
+    task_id: i64,
+    task_date: NaiveDate,
+    expected: Option<DateTime<Utc>>,
+) -> Result<MoveTaskOutcome> {
+    if !task_version_matches(&mut *conn, task_id, expected).await? {
+        return Ok(MoveTaskOutcome::Stale);
+    }
+    let deleted_at: Option<Option<DateTime<Utc>>> =
+        sqlx::query_scalar("SELECT deleted_at FROM tasks WHERE id = ?")
+            .bind(task_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .context(format!("Failed to retrieve task {task_id}"))?;
+    match deleted_at {
+        None => return Ok(MoveTaskOutcome::NotFound),
+        Some(Some(_)) => return Ok(MoveTaskOutcome::Done),
+        Some(None) => {}
+    }
+
+    sqlx::query("UPDATE tasks SET task_date = ?, updated_at = ? WHERE id = ?")
+        .bind(task_date)
+        .bind(clock::now())
+        .bind(task_id)
+        .execute(&mut *conn)
+        .await
+        .context(format!("Failed to move task {task_id} to {task_date}"))?;
+    let task = get_task_by_id_from_db(&mut *conn, task_id)
+        .await?
+        .context("Moved task could not be read back")?;
+
+    Ok(MoveTaskOutcome::Moved(Box::new(task)))
+}
+
+/// Changes made at once to a task by the GraphQL `updateTask` mutation.
+#[derive(Debug, Default)]
+pub struct TaskUpdate {
+    pub task_date: Option<NaiveDate>,
+    pub pin_to_date: Option<bool>,
+    /// `Some(None)` lets the task follow the rollover policy again.
+    pub rollover_target: Option<Option<RolloverTarget>>,
+}
+
+/// Moves, pins and sets the rollover target of a task, in that order and in
+/// a single transaction, if it is still at the `expected` version: either
+/// every change is applied, or none is.
+pub async fn update_task_in_db(
+    pool: &SqlitePool,
+    task_id: i64,
+    update: TaskUpdate,
+    expected: Option<DateTime<Utc>>,
+) -> Result<MoveTaskOutcome> {
+    let mut tx = begin_write(pool).await?;
+    if !task_version_matches(&mut tx, task_id, expected).await? {
+        return Ok(MoveTaskOutcome::Stale);
+    }
+    if let Some(task_date) = update.task_date {
+        match move_task(&mut tx, task_id, task_date, None).await? {
+            MoveTaskOutcome::Moved(_) => {}
+            outcome => return Ok(outcome),
+        }
+    }
+    if let Some(pinned) = update.pin_to_date {
+        sqlx::query("UPDATE tasks SET pin_to_date = ?, updated_at = ? WHERE id = ?")
+            .bind(pinned)
+            .bind(clock::now())
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await
+            .context(format!("Failed to update pin of task {task_id}"))?;
+    }
+    if let Some(target) = update.rollover_target {
+        sqlx::query("UPDATE tasks SET rollover_target = ?, updated_at = ? WHERE id = ?")
+            .bind(target)
+            .bind(clock::now())
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await
+            .context(format!(
+                "Failed to update the rollover target of task {task_id}"
+            ))?;
+    }
+    let Some(task) = get_task_by_id_from_db(&mut *tx, task_id).await? else {
+        return Ok(MoveTaskOutcome::NotFound);
+    };
+    tx.commit().await.context("Failed to commit task update")?;
+    info!("Task {} updated: {:?}", task_id, update);
+    Ok(MoveTaskOutcome::Moved(Box::new(task)))
+}
+
+/// Result of applying a batch of operations.
+#[derive(Debug)]
+pub enum BatchOutcome {
+    /// Every operation was applied; the tasks as they left them, in order.
+    Applied(Vec<Task>),
+    /// The task of the operation at `index` does not exist; nothing was
+    /// applied.
+    NotFound { index: usize, task_id: i64 },
+    /// The task of the operation at `index` is already done; nothing was
+    /// applied.
+    Done { index: usize, task_id: i64 },
+    /// The task of the operation at `index` was written since the version
+    /// given with it; nothing was applied.
+    Stale { index: usize, task_id: i64 },
+}
+
+/// Applies `operations` in order in a single transaction: either all of
+/// them are applied, or none is. Created tasks are expected to have been
+/// checked already.
+pub async fn apply_batch_in_db(
+    pool: &SqlitePool,
+    operations: Vec<BatchOperation>,
+) -> Result<BatchOutcome> {
+    let mut tx = begin_write(pool).await?;
+    let mut tasks = Vec::with_capacity(operations.len());
+    for (index, operation) in operations.into_iter().enumerate() {
+        let (task_id, expected) = match operation {
+            BatchOperation::Create(payload) => {
+                tasks.push(insert_task(&mut tx, payload).await?);
+                continue;
+            }
+            BatchOperation::Move {
+                task_id,
+                task_date,
+                updated_at,
+            } => match move_task(&mut tx, task_id, task_date, updated_at).await? {
+                MoveTaskOutcome::Moved(task) => {
+                    tasks.push(*task);
+                    continue;
+                }
+                MoveTaskOutcome::Done => return Ok(BatchOutcome::Done { index, task_id }),
+                MoveTaskOutcome::Stale => return Ok(BatchOutcome::Stale { index, task_id }),
+                MoveTaskOutcome::NotFound => {
+                    return Ok(BatchOutcome::NotFound { index, task_id });
+                }
+            },
+            BatchOperation::Complete {
+                task_id,
+                updated_at,
+            }
+            | BatchOperation::Delete {
+                task_id,
+                updated_at,
+            } => (task_id, updated_at),
+        };
+        if !task_version_matches(&mut tx, task_id, expected).await? {
+            return Ok(BatchOutcome::Stale { index, task_id });
+        }
+        if !soft_delete_task(&mut tx, task_id).await? {
+            // Told apart only to report why, the transaction is dropped anyway
+            return Ok(match get_task_by_id_from_db(&mut *tx, task_id).await? {
+                Some(_) => BatchOutcome::Done { index, task_id },
+                None => BatchOutcome::NotFound { index, task_id },
+            });
+        }
+        tasks.push(
+            get_task_by_id_from_db(&mut *tx, task_id)
+                .await?
+                .context("Completed task could not be read back")?,
+        );
+    }
+    tx.commit()
+        .await
+        .context("Failed to commit the batch of operations")?;
+    info!("Applied a batch of {} operations.", tasks.len());
+
+    Ok(BatchOutcome::Applied(tasks))
+}
+
+/// Moves every open task matching `filter` to `target_date` in a single
+/// transaction, returning the IDs of the moved tasks. Their unfinished slots
+/// of the day they leave go with them, as in the rollover. Done tasks are
+/// never moved, whatever the status of the filter. The client name may be an
+/// alias.
+pub async fn move_matching_tasks_in_db(
+    pool: &SqlitePool,
+    filter: &TaskFilter,
+    target_date: NaiveDate,
+) -> Result<Vec<i64>> {
+    let mut tx = begin_write(pool).await?;
+
+    let client_name = match &filter.client_name {
+        Some(name) => Some(
+            resolve_client_alias(&mut *tx, name)
+                .await?
+                .unwrap_or_else(|| name.clone()),
+        ),
+        None => None,
+    };
+    let (from, to) = filter.dates().map_err(|e| anyhow!(e))?;
+    // The slot update goes first, while the tasks are still on their day.
+    sqlx::query(
+        r#"
+        UPDATE task_slots SET slot_date = ?1
+        WHERE completed_at IS NULL
+          AND task_id IN (
+            SELECT id FROM tasks
+            WHERE deleted_at IS NULL
+              AND (?2 IS NULL OR client_id = (SELECT id FROM clients WHERE name = ?2))
+              AND (?3 IS NULL OR task_date >= ?3)
+              AND (?4 IS NULL OR task_date <= ?4)
+          )
+          AND slot_date = (SELECT task_date FROM tasks WHERE tasks.id = task_slots.task_id)
+        "#,
+    )
+    .bind(target_date)
+    .bind(&client_name)
+    .bind(from)
+    .bind(to)
+    .execute(&mut *tx)
+    .await
+    .context(format!("Failed to move task slots to {target_date}"))?;
+
+    let mut task_ids: Vec<i64> = sqlx::query_scalar(
+        r#"
+        UPDATE tasks SET task_date = ?1, updated_at = ?5
+        WHERE deleted_at IS NULL
+          AND (?2 IS NULL OR client_id = (SELECT id FROM clients WHERE name = ?2))
+          AND (?3 IS NULL OR task_date >= ?3)
+          AND (?4 IS NULL OR task_date <= ?4)
+        RETURNING id
+        "#,
+    )
+    .bind(target_date)
+    .bind(&client_name)
+    .bind(from)
+    .bind(to)
+    .bind(clock::now())
+    .fetch_all(&mut *tx)
+    .await
+    .context(format!("Failed to move tasks to {target_date}"))?;
+
+    tx.commit().await.context("Failed to commit bulk move")?;
+    task_ids.sort_unstable();
+    info!("Moved {} tasks to {}", task_ids.len(), target_date);
+
+    Ok(task_ids)
+}
+
+/// Day the tasks of `today` roll over to under `policy`. Weekends are
+/// skipped by the business-day policies, and `holidays` too by the last one.
+fn rollover_target(
+    today: NaiveDate,
+    policy: RolloverPolicy,
+    holidays: &HashSet<NaiveDate>,
+) -> Option<NaiveDate> {
+    let is_day_off = |day: &NaiveDate| match policy {
+        RolloverPolicy::NextDay => false,
+        RolloverPolicy::BusinessDay => day.weekday().number_from_monday() > 5,
+        RolloverPolicy::BusinessDayHolidays => {
+            day.weekday().number_from_monday() > 5 || holidays.contains(day)
+        }
+    };
+    today.iter_days().skip(1).find(|day| !is_day_off(day))
+}
+
+/// Day a task of `from` rolls over to under its own `target`, whatever the
+/// policy. The next business day skips `holidays` as well as weekends.
+fn task_rollover_target(
+    from: NaiveDate,
+    target: RolloverTarget,
+    holidays: &HashSet<NaiveDate>,
+) -> Option<NaiveDate> {
+    let weekday = match target {
+        RolloverTarget::NextDay => return from.succ_opt(),
+        RolloverTarget::NextBusinessDay => {
+            return from
+                .iter_days()
+                .skip(1)
+                .find(|day| day.weekday().number_from_monday() <= 5 && !holidays.contains(day));
+        }
+        RolloverTarget::Monday => Weekday::Mon,
+        RolloverTarget::Tuesday => Weekday::Tue,
+        RolloverTarget::Wednesday => Weekday::Wed,
+        RolloverTarget::Thursday => Weekday::Thu,
+        RolloverTarget::Friday | RolloverTarget::EndOfWeek => Weekday::Fri,
+        RolloverTarget::Saturday => Weekday::Sat,
+        RolloverTarget::Sunday => Weekday::Sun,
+    };
+    from.iter_days()
+        .skip(1)
+        .find(|day| day.weekday() == weekday)
+}
+
+/// Holidays after `after`, skipped by the `BusinessDayHolidays` policy and
+/// by the tasks rolling over to the next business day.
+async fn holidays_after<'e, E>(executor: E, after: NaiveDate) -> Result<HashSet<NaiveDate>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    Ok(sqlx::query_scalar("SELECT day FROM holidays WHERE day > ?")
+        .bind(after)
+        .fetch_all(executor)
+        .await
+        .context("Failed to retrieve holidays")?
+        .into_iter()
+        .collect())
+}
+
+/// Holidays the policy honors: none unless it is `BusinessDayHolidays`.
+fn policy_holidays(policy: RolloverPolicy, holidays: &HashSet<NaiveDate>) -> HashSet<NaiveDate> {
+    if policy == RolloverPolicy::BusinessDayHolidays {
+        holidays.clone()
+    } else {
+        HashSet::new()
+    }
+}
+
+/// Returns today, the day its open tasks roll over to under `policy`, and
+/// the holidays after today. Shared by the rollover and its preview so that
+/// both pick the same days.
+async fn rollover_dates(
+    pool: &SqlitePool,
+    policy: RolloverPolicy,
+) -> Result<(NaiveDate, NaiveDate, HashSet<NaiveDate>)> {
+    let today = clock::today();
+    let holidays = holidays_after(pool, today).await?;
+    let target = rollover_target(today, policy, &policy_holidays(policy, &holidays))
+        .context("Failed to find the day to roll over to")?;
+    Ok((today, target, holidays))
+}
+
+/// Lists the tasks a rollover would move now, and where, without moving
+/// anything.
+pub async fn preview_rollover_in_db(
+    pool: &SqlitePool,
+    policy: RolloverPolicy,
+) -> Result<RolloverPreview> {
+    let (today, target, holidays) = rollover_dates(pool, policy).await?;
+    let tasks: Vec<Task> = sqlx::query_as(&format!(
+        "{TASK_SELECT} WHERE t.task_date = ? AND t.deleted_at IS NULL AND NOT t.pin_to_date ORDER BY t.id"
+    ))
+    .bind(today)
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the tasks to roll over")?;
+
+    let mut task_dates = BTreeMap::new();
+    for task in &tasks {
+        if let Some(own) = task.rollover_target {
+            let day = task_rollover_target(today, own, &holidays)
+                .context("Failed to find the day to roll over to")?;
+            task_dates.insert(task.id, day);
+        }
+    }
+
+    Ok(RolloverPreview {
+        from_date: today,
+        to_date: target,
+        tasks: tasks.into_iter().map(presentation::for_display).collect(),
+        task_dates,
+    })
+}
+
+/// Moves the open, unpinned tasks of `from` and their unfinished slots to
+/// `to`, or to the day of their own rollover target, no later than
+/// `latest` if given, and records the run. Returns the number of tasks
+/// moved. Tasks reaching `stale_after` rollovers are escalated on the way.
+async fn roll_day_over(
+    conn: &mut SqliteConnection,
+    from: NaiveDate,
+    to: NaiveDate,
+    latest: Option<NaiveDate>,
+    holidays: &HashSet<NaiveDate>,
+    triggered_by: RolloverTrigger,
+    stale_after: Option<i64>,
+) -> Result<usize> {
+    if let Some(threshold) = stale_after {
+        // Each rollover past the threshold raises the priority one more
+        // level, up to 1; tasks without a priority get the highest one.
+        let escalated = sqlx::query(
+            r#"
+            UPDATE tasks
+            SET stale_since = COALESCE(stale_since, ?1),
+                priority = CASE WHEN priority IS NULL THEN 1 ELSE MAX(priority - 1, 1) END,
+                updated_at = ?1
+            WHERE task_date = ?2 AND deleted_at IS NULL AND NOT pin_to_date
+              AND rollover_count + 1 >= ?3
+            "#,
+        )
+        .bind(clock::now())
+        .bind(from)
+        .bind(threshold)
+        .execute(&mut *conn)
+        .await
+        .context("Failed to escalate stale tasks")?
+        .rows_affected();
+        if escalated > 0 {
+            info!(
+                "{} stale tasks escalated while rolling over {}.",
+                escalated, from
+            );
+        }
+    }
+
+    // Tasks with a target of their own go first, one target at a time;
+    // `None` then moves the others to the day of the policy.
+    let own_targets: Vec<RolloverTarget> = sqlx::query_scalar(
+        "SELECT DISTINCT rollover_target FROM tasks WHERE task_date = ? AND deleted_at IS NULL AND NOT pin_to_date AND rollover_target IS NOT NULL",
+    )
+    .bind(from)
+    .fetch_all(&mut *conn)
+    .await
+    .context("Failed to retrieve the rollover targets of tasks")?;
+
+    let mut num_rolled_over = 0;
+    for own in own_targets.into_iter().map(Some).chain([None]) {
+        let target = match own {
+            Some(own) => task_rollover_target(from, own, holidays)
+                .context("Failed to find the day to roll over to")?,
+            None => to,
+        };
+        let target = latest.map_or(target, |latest| target.min(latest));
+
+        // The slot update goes first, while the tasks are still on `from`.
+        let slot_tasks: Vec<i64> = sqlx::query_scalar(
+            "UPDATE task_slots SET slot_date = ?1 WHERE slot_date = ?2 AND completed_at IS NULL AND task_id IN (SELECT id FROM tasks WHERE deleted_at IS NULL AND NOT pin_to_date AND rollover_target IS ?3) RETURNING task_id",
+        )
+        .bind(target)
+        .bind(from)
+        .bind(own)
+        .fetch_all(&mut *conn)
+        .await
+        .context("Failed to roll over task slots in DB")?;
+
+        let now = clock::now();
+        let result = sqlx::query(
+            "UPDATE tasks SET task_date = ?1, rollover_count = rollover_count + 1, updated_at = ?4 WHERE task_date = ?2 AND deleted_at IS NULL AND NOT pin_to_date AND rollover_target IS ?3",
+        )
+        .bind(target)
+        .bind(from)
+        .bind(own)
+        .bind(now)
+        .execute(&mut *conn)
+        .await
+        .context("Failed to roll over tasks in DB")?;
+        num_rolled_over += result.rows_affected() as usize;
+        // Tasks kept on another day whose slots moved changed too.
+        for task_id in slot_tasks.into_iter().collect::<HashSet<_>>() {
+            sqlx::query("UPDATE tasks SET updated_at = ? WHERE id = ? AND task_date != ?")
+                .bind(now)
+                .bind(task_id)
+                .bind(target)
+                .execute(&mut *conn)
+                .await
+                .context(format!("Failed to update the version of task {task_id}"))?;
+        }
+    }
+
+    let run: RolloverRun = sqlx::query_as(
+        "INSERT INTO rollover_runs (ran_at, triggered_by, from_date, to_date, task_count) VALUES (?, ?, ?, ?, ?) RETURNING id, ran_at, triggered_by, from_date, to_date, task_count",
+    )
+    .bind(clock::now())
+    .bind(triggered_by)
+    .bind(from)
+    .bind(to)
+    .bind(num_rolled_over as i64)
+    .fetch_one(&mut *conn)
+    .await
+    .context("Failed to record the rollover run")?;
+    queue_webhook_event(conn, WebhookEvent::RolloverCompleted, &run).await?;
+
+    Ok(num_rolled_over)
+}
+
+/// Rolls over incomplete (not soft-deleted) tasks from today to tomorrow,
+/// and records the run in the rollover history.
+pub async fn rollover_tasks_in_db(
+    pool: &SqlitePool,
+    policy: RolloverPolicy,
+    triggered_by: RolloverTrigger,
+    stale_after: Option<i64>,
+) -> Result<usize> {
+    let (today, tomorrow, holidays) = rollover_dates(pool, policy).await?;
+
+    debug!(
+        "Attempting to roll over tasks from {} to {}",
+        today, tomorrow
+    );
+
+    let mut tx = begin_write(pool).await?;
+    let num_rolled_over = roll_day_over(
+        &mut tx,
+        today,
+        tomorrow,
+        None,
+        &holidays,
+        triggered_by,
+        stale_after,
+    )
+    .await?;
+    tx.commit().await.context("Failed to commit the rollover")?;
+
+    info!("Successfully rolled over {} tasks.", num_rolled_over);
+    Ok(num_rolled_over)
+}
+
+/// Oldest day before `today` still holding open, unpinned tasks.
+async fn oldest_open_day_before(
+    conn: &mut SqliteConnection,
+    today: NaiveDate,
+) -> Result<Option<NaiveDate>> {
+    sqlx::query_scalar(
+        "SELECT MIN(task_date) FROM tasks WHERE task_date < ? AND deleted_at IS NULL AND NOT pin_to_date",
+    )
+    .bind(today)
+    .fetch_one(conn)
+    .await
+    .context("Failed to find the oldest open task")
+}
+
+/// Catches up on the rollovers missed while the server was down: open tasks
+/// left on past days are rolled forward day by day, following `policy` or
+/// their own target, until they reach today. Returns each step as
+/// `(from, to, tasks moved)`.
+pub async fn catch_up_rollover_in_db(
+    pool: &SqlitePool,
+    policy: RolloverPolicy,
+    stale_after: Option<i64>,
+) -> Result<Vec<(NaiveDate, NaiveDate, usize)>> {
+    let today = clock::today();
+    let mut tx = begin_write(pool).await?;
+    let Some(oldest) = oldest_open_day_before(&mut tx, today).await? else {
+        return Ok(Vec::new());
+    };
+    let holidays = holidays_after(&mut *tx, oldest).await?;
+    let skipped = policy_holidays(policy, &holidays);
+
+    let mut steps = Vec::new();
+    let mut next = Some(oldest);
+    while let Some(day) = next {
+        // A day off after today stops at today: the next rollover handles it.
+        let target = rollover_target(day, policy, &skipped)
+            .context("Failed to find the day to roll over to")?
+            .min(today);
+        let count = roll_day_over(
+            &mut tx,
+            day,
+            target,
+            Some(today),
+            &holidays,
+            RolloverTrigger::Automatic,
+            stale_after,
+        )
+        .await?;
+        info!(
+            "Catch-up rollover: {} tasks moved from {} to {}.",
+            count, day, target
+        );
+        steps.push((day, target, count));
+        next = oldest_open_day_before(&mut tx, today).await?;
+    }
+    tx.commit()
+        .await
+        .context("Failed to commit the catch-up rollover")?;
+
+    Ok(steps)
+}
+
+/// Days scheduled job claims are kept for.
+const JOB_CLAIM_RETENTION_DAYS: i64 = 30;
+
+/// Claims the occurrence of `job` starting at `slot` for `instance`.
+/// Returns `false` when another instance, or an earlier run of this one,
+/// already claimed it: the caller must then skip that occurrence.
+pub async fn claim_scheduled_job(
+    pool: &SqlitePool,
+    job: &str,
+    slot: DateTime<Utc>,
+    instance: &str,
+) -> Result<bool> {
+    let mut tx = begin_write(pool).await?;
+    sqlx::query("DELETE FROM scheduled_job_claims WHERE job = ? AND slot < ?")
+        .bind(job)
+        .bind(slot - chrono::Duration::days(JOB_CLAIM_RETENTION_DAYS))
+        .execute(&mut *tx)
+        .await
+        .context("Failed to prune old job claims")?;
+    let claimed = sqlx::query(
+        "INSERT OR IGNORE INTO scheduled_job_claims (job, slot, instance, claimed_at) VALUES (?, ?, ?, ?)",
+    )
+    .bind(job)
+    .bind(slot)
+    .bind(instance)
+    .bind(clock::now())
+    .execute(&mut *tx)
+    .await
+    .with_context(|| format!("Failed to claim scheduled job {job}"))?
+    .rows_affected()
+        == 1;
+    tx.commit()
+        .await
+        .context("Failed to commit the job claim")?;
+    Ok(claimed)
+}
+
+/// Outcome of a background job run, see `record_job_run`.
+pub struct NewJobRun<'a> {
+    pub job: &'a str,
+    pub scheduled_for: DateTime<Utc>,
+    pub instance: &'a str,
+    pub started_at: DateTime<Utc>,
+    pub attempts: u32,
+    pub succeeded: bool,
+    pub message: &'a str,
+}
+
+/// Records a finished job run, and forgets the runs of that job older than
+/// the claims.
+pub async fn record_job_run(pool: &SqlitePool, run: NewJobRun<'_>) -> Result<()> {
+    let finished_at = clock::now();
+    let mut tx = begin_write(pool).await?;
+    sqlx::query("DELETE FROM job_runs WHERE job = ? AND finished_at < ?")
+        .bind(run.job)
+        .bind(finished_at - chrono::Duration::days(JOB_CLAIM_RETENTION_DAYS))
+        .execute(&mut *tx)
+        .await
+        .context("Failed to prune old job runs")?;
+    sqlx::query(
+        r#"
+        INSERT INTO job_runs (job, scheduled_for, instance, started_at, finished_at, attempts, succeeded, message)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(run.job)
+    .bind(run.scheduled_for)
+    .bind(run.instance)
+    .bind(run.started_at)
+    .bind(finished_at)
+    .bind(run.attempts)
+    .bind(run.succeeded)
+    .bind(run.message)
+    .execute(&mut *tx)
+    .await
+    .with_context(|| format!("Failed to record the run of job {}", run.job))?;
+    tx.commit().await.context("Failed to commit the job run")?;
+    Ok(())
+}
+
+/// Pauses `job`, or resumes it, on every instance. Returns when it was
+/// paused, `None` once resumed. Pausing a paused job keeps its first date.
+pub async fn set_job_paused_in_db(
+    pool: &SqlitePool,
+    job: &str,
+    paused: bool,
+) -> Result<Option<DateTime<Utc>>> {
+    if paused {
+        sqlx::query("INSERT OR IGNORE INTO paused_jobs (job, paused_at) VALUES (?, ?)")
+            .bind(job)
+            .bind(clock::now())
+            .execute(pool)
+            .await
+            .with_context(|| format!("Failed to pause job {job}"))?;
+    } else {
+        sqlx::query("DELETE FROM paused_jobs WHERE job = ?")
+            .bind(job)
+            .execute(pool)
+            .await
+            .with_context(|| format!("Failed to resume job {job}"))?;
+    }
+    get_job_paused_at_from_db(pool, job).await
+}
+
+/// Retrieves when `job` was paused, if it is.
+pub async fn get_job_paused_at_from_db(
+    pool: &SqlitePool,
+    job: &str,
+) -> Result<Option<DateTime<Utc>>> {
+    sqlx::query_scalar("SELECT paused_at FROM paused_jobs WHERE job = ?")
+        .bind(job)
+        .fetch_optional(pool)
+        .await
+        .with_context(|| format!("Failed to check whether job {job} is paused"))
+}
+
+/// Retrieves the `limit` latest runs of `job`, most recent first.
+pub async fn get_job_runs_from_db(pool: &SqlitePool, job: &str, limit: i64) -> Result<Vec<JobRun>> {
+    sqlx::query_as(
+        "SELECT id, job, scheduled_for, instance, started_at, finished_at, attempts, succeeded, message FROM job_runs WHERE job = ? ORDER BY id DESC LIMIT ?",
+    )
+    .bind(job)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .with_context(|| format!("Failed to retrieve the runs of job {job}"))
+}
+
+/// Retrieves the open tasks escalated for being rolled over too often,
+/// most postponed first.
+pub async fn get_stale_tasks_from_db(pool: &SqlitePool) -> Result<Vec<Task>> {
+    let tasks: Vec<Task> = sqlx::query_as(&format!(
+        "{TASK_SELECT} WHERE t.stale_since IS NOT NULL AND t.deleted_at IS NULL ORDER BY t.rollover_count DESC, t.id"
+    ))
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve stale tasks")?;
+    Ok(tasks.into_iter().map(presentation::for_display).collect())
+}
+
+/// Retrieves the `limit` most recent rollover runs, and the open tasks
+/// rolled over at least `min_rollovers` times, most postponed first.
+pub async fn get_rollover_history_from_db(
+    pool: &SqlitePool,
+    limit: i64,
+    min_rollovers: i64,
+) -> Result<RolloverHistory> {
+    let runs = sqlx::query_as::<_, RolloverRun>(
+        "SELECT id, ran_at, triggered_by, from_date, to_date, task_count FROM rollover_runs ORDER BY ran_at DESC, id DESC LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve rollover runs")?;
+
+    let postponed_tasks = sqlx::query_as::<_, PostponedTask>(
+        r#"
+        SELECT t.id, c.name AS client_name, t.description, t.task_date, t.rollover_count
+        FROM tasks t
+        JOIN clients c ON c.id = t.client_id
+        WHERE t.deleted_at IS NULL AND t.rollover_count >= ?
+        ORDER BY t.rollover_count DESC, t.id
+        "#,
+    )
+    .bind(min_rollovers)
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve postponed tasks")?;
+
+    Ok(RolloverHistory {
+        runs,
+        postponed_tasks,
+    })
+}
+
+/// Retrieves every task dated between `from` and `to` (inclusive),
+/// soft-deleted ones included, by date.
+pub async fn get_tasks_between_from_db(
+    pool: &SqlitePool,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<Task>> {
+    let tasks = sqlx::query_as::<_, Task>(&format!(
+        "{TASK_SELECT} WHERE t.task_date BETWEEN ? AND ? ORDER BY t.task_date, t.id"
+    ))
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .context(format!("Failed to retrieve tasks from {from} to {to}"))?;
+
+    Ok(tasks.into_iter().map(presentation::for_display).collect())
+}
+
+/// SQL listing the tasks matching `filter` in the order of `order_by`, and
+/// the client to bind as `?1`, its alias resolved. The first and last days,
+/// from `TaskFilter::dates`, are bound as `?2` and `?3`.
+async fn task_list_sql(
+    pool: &SqlitePool,
+    filter: &TaskFilter,
+    order_by: &str,
+) -> Result<(String, Option<String>)> {
+    let status = match filter.status {
+        TaskStatusFilter::Open => "t.deleted_at IS NULL",
+        TaskStatusFilter::Done => "t.deleted_at IS NOT NULL",
+        TaskStatusFilter::All => "1",
+    };
+    let client_name = match &filter.client_name {
+        Some(name) => Some(
+            resolve_client_alias(pool, name)
+                .await?
+                .unwrap_or_else(|| name.clone()),
+        ),
+        None => None,
+    };
+    let sql = format!(
+        r#"
+        {TASK_SELECT}
+        WHERE {status}
+          AND (?1 IS NULL OR c.name = ?1)
+          AND (?2 IS NULL OR t.task_date >= ?2)
+          AND (?3 IS NULL OR t.task_date <= ?3)
+        ORDER BY {order_by}
+        "#
+    );
+    Ok((sql, client_name))
+}
+
+/// Retrieves the tasks matching `filter`, by date.
+pub async fn get_task_list_from_db(pool: &SqlitePool, filter: &TaskFilter) -> Result<Vec<Task>> {
+    let (sql, client_name) = task_list_sql(pool, filter, "t.task_date, t.id").await?;
+    let (from, to) = filter.dates().map_err(|e| anyhow!(e))?;
+    let tasks = sqlx::query_as::<_, Task>(&sql)
+        .bind(&client_name)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+        .context("Failed to retrieve tasks")?;
+
+    Ok(tasks.into_iter().map(presentation::for_display).collect())
+}
+
+/// Retrieves `limit` tasks matching `filter` in the order of `order_by`,
+/// skipping the first `offset`. Ties are broken by ID, so that pages never
+/// overlap.
+pub async fn get_task_page_from_db(
+    pool: &SqlitePool,
+    filter: &TaskFilter,
+    order_by: &str,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<Task>> {
+    let (sql, client_name) = task_list_sql(pool, filter, &format!("{order_by}, t.id")).await?;
+    let (from, to) = filter.dates().map_err(|e| anyhow!(e))?;
+    let tasks = sqlx::query_as::<_, Task>(&format!("{sql} LIMIT ?4 OFFSET ?5"))
+        .bind(&client_name)
+        .bind(from)
+        .bind(to)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await
+        .context("Failed to retrieve a page of tasks")?;
+
+    Ok(tasks.into_iter().map(presentation::for_display).collect())
+}
+
+/// Sends the tasks matching `filter` to `tasks` by date, as they are read,
+/// so that large exports are not loaded at once. Stops early when the
+/// receiver is dropped.
+pub async fn stream_tasks_from_db(
+    pool: &SqlitePool,
+    filter: &TaskFilter,
+    tasks: mpsc::Sender<Task>,
+) -> Result<()> {
+    let (sql, client_name) = task_list_sql(pool, filter, "t.task_date, t.id").await?;
+    let (from, to) = filter.dates().map_err(|e| anyhow!(e))?;
+    let mut rows = sqlx::query_as::<_, Task>(&sql)
+        .bind(&client_name)
+        .bind(from)
+        .bind(to)
+        .fetch(pool);
+    while let Some(task) = rows
+        .try_next()
+        .await
+        .context("Failed to retrieve tasks to export")?
+    {
+        if tasks.send(task).await.is_err() {
+            debug!("Task export abandoned by the receiver.");
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Retrieves the open tasks dated before `day`, by date and priority.
+pub async fn get_open_tasks_before_from_db(pool: &SqlitePool, day: NaiveDate) -> Result<Vec<Task>> {
+    let tasks = sqlx::query_as::<_, Task>(&format!(
+        "{TASK_SELECT} WHERE t.task_date < ? AND t.deleted_at IS NULL ORDER BY {DEFAULT_ORDER_BY}"
+    ))
+    .bind(day)
+    .fetch_all(pool)
+    .await
+    .context(format!("Failed to retrieve open tasks before {day}"))?;
+
+    Ok(tasks.into_iter().map(presentation::for_display).collect())
+}
+
+/// Counts the open work planned on each day between `from` and `to`, as the
+/// number of tasks and the sum of their estimates. Tasks split into slots
+/// count on their open slot days instead of their own date.
+pub async fn get_planned_load_from_db(
+    pool: &SqlitePool,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<HashMap<NaiveDate, (i64, i64)>> {
+    let rows: Vec<(NaiveDate, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT day, COUNT(*), COALESCE(SUM(minutes), 0) FROM (
+            SELECT t.task_date AS day, t.estimate_minutes AS minutes
+            FROM tasks t
+            WHERE t.deleted_at IS NULL
+              AND NOT EXISTS (SELECT 1 FROM task_slots WHERE task_id = t.id)
+            UNION ALL
+            SELECT s.slot_date, s.estimate_minutes
+            FROM task_slots s
+            JOIN tasks t ON t.id = s.task_id
+            WHERE s.completed_at IS NULL AND t.deleted_at IS NULL
+        )
+        WHERE day BETWEEN ? AND ?
+        GROUP BY day
+        "#,
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .context(format!(
+        "Failed to compute planned work from {from} to {to}"
+    ))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(day, tasks, minutes)| (day, (tasks, minutes)))
+        .collect())
+}
+
+/// Result of applying the selections of the planning flow.
+#[derive(Debug)]
+pub enum ApplyPlanOutcome {
+    Applied(AppliedPlan),
+    // The task to carry over does not exist or is already completed.
+    TaskNotOpen(i64),
+}
+
+/// Moves the selected open tasks and creates the new ones in a single
+/// transaction: either the whole plan is applied or nothing is.
+/// Moved tasks count as rolled over.
+pub async fn apply_plan_in_db(
+    pool: &SqlitePool,
+    carry_over: &[CarryOverSelection],
+    new_tasks: Vec<CreateTaskPayload>,
+) -> Result<ApplyPlanOutcome> {
+    let mut tx = begin_write(pool).await?;
+
+    let mut moved = Vec::with_capacity(carry_over.len());
+    for selection in carry_over {
+        let updated = sqlx::query(
+            "UPDATE tasks SET task_date = ?, rollover_count = rollover_count + 1, updated_at = ? WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(selection.task_date)
+        .bind(clock::now())
+        .bind(selection.task_id)
+        .execute(&mut *tx)
+        .await
+        .context(format!("Failed to move task {}", selection.task_id))?;
+        if updated.rows_affected() == 0 {
+            return Ok(ApplyPlanOutcome::TaskNotOpen(selection.task_id));
+        }
+        moved.push(
+            get_task_by_id_from_db(&mut *tx, selection.task_id)
+                .await?
+                .context("Moved task could not be read back")?,
+        );
+    }
+
+    let mut created = Vec::with_capacity(new_tasks.len());
+    for payload in new_tasks {
+        created.push(insert_task(&mut tx, payload).await?);
+    }
+
+    tx.commit().await.context("Failed to commit plan")?;
+    info!(
+        "Applied plan: {} tasks moved, {} created",
+        moved.len(),
+        created.len()
+    );
+
+    Ok(ApplyPlanOutcome::Applied(AppliedPlan { moved, created }))
+}
+
+/// Columns of `task_slots` that map onto `TaskSlot`.
+const TASK_SLOT_COLUMNS: &str = "id, task_id, slot_date, estimate_minutes, completed_at";
+
+/// Lists the slots of a task by day. Empty for tasks without slots.
+pub async fn get_task_slots_from_db(pool: &SqlitePool, task_id: i64) -> Result<Vec<TaskSlot>> {
+    let slots = sqlx::query_as::<_, TaskSlot>(&format!(
+        "SELECT {TASK_SLOT_COLUMNS} FROM task_slots WHERE task_id = ? ORDER BY slot_date, id"
+    ))
+    .bind(task_id)
+    .fetch_all(pool)
+    .await
+    .context(format!("Failed to retrieve slots of task {task_id}"))?;
+
+    Ok(slots)
+}
+
+/// Schedules a slot of work on an open task. Returns `None` if the task does
+/// not exist or is already completed.
+pub async fn add_task_slot_in_db(
+    pool: &SqlitePool,
+    task_id: i64,
+    payload: &CreateTaskSlotPayload,
+) -> Result<Option<TaskSlot>> {
+    let mut tx = begin_write(pool).await?;
+    let now = clock::now();
+    let slot = sqlx::query_as::<_, TaskSlot>(&format!(
+        r#"
+        INSERT INTO task_slots (task_id, slot_date, estimate_minutes, created_at)
+        SELECT id, ?, ?, ? FROM tasks WHERE id = ? AND deleted_at IS NULL
+        RETURNING {TASK_SLOT_COLUMNS}
+        "#
+    ))
+    .bind(payload.slot_date)
+    .bind(payload.estimate_minutes)
+    .bind(now)
+    .bind(task_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .context(format!("Failed to add a slot to task {task_id}"))?;
+
+    if let Some(slot) = &slot {
+        touch_task(&mut tx, task_id, now).await?;
+        tx.commit().await.context("Failed to commit the new slot")?;
+        info!("Task {} scheduled on {}", task_id, slot.slot_date);
+    }
+
+    Ok(slot)
+}
+
+/// Marks a slot of a task as completed. Completing the last open slot
+/// completes the task itself. Returns `None` if the slot does not exist;
+/// completing a slot twice keeps its first completion time.
+pub async fn complete_task_slot_in_db(
+    pool: &SqlitePool,
+    task_id: i64,
+    slot_id: i64,
+) -> Result<Option<TaskSlot>> {
+    let mut tx = begin_write(pool).await?;
+    let now = clock::now();
+
+    let slot = sqlx::query_as::<_, TaskSlot>(&format!(
+        r#"
+        UPDATE task_slots SET completed_at = COALESCE(completed_at, ?)
+        WHERE id = ? AND task_id = ?
+        RETURNING {TASK_SLOT_COLUMNS}
+        "#
+    ))
+    .bind(now)
+    .bind(slot_id)
+    .bind(task_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .context(format!(
+        "Failed to complete slot {slot_id} of task {task_id}"
+    ))?;
+
+    if slot.is_some() {
+        let completed = sqlx::query(
+            r#"
+            UPDATE tasks SET deleted_at = ?1, updated_at = ?1
+            WHERE id = ?2 AND deleted_at IS NULL
+              AND NOT EXISTS (SELECT 1 FROM task_slots WHERE task_id = ?2 AND completed_at IS NULL)
+            "#,
+        )
+        .bind(now)
+        .bind(task_id)
+        .execute(&mut *tx)
+        .await
+        .context(format!("Failed to complete task {task_id}"))?;
+        if completed.rows_affected() > 0 {
+            info!("Task {} completed along with its last slot", task_id);
+        } else {
+            touch_task(&mut tx, task_id, now).await?;
+        }
+    }
+
+    tx.commit()
+        .await
+        .context("Failed to commit slot completion")?;
+
+    Ok(slot)
+}
+
+/// Removes a slot from a task. Returns false if it does not exist.
+pub async fn delete_task_slot_in_db(pool: &SqlitePool, task_id: i64, slot_id: i64) -> Result<bool> {
+    let mut tx = begin_write(pool).await?;
+    let result = sqlx::query("DELETE FROM task_slots WHERE id = ? AND task_id = ?")
+        .bind(slot_id)
+        .bind(task_id)
+        .execute(&mut *tx)
+        .await
+        .context(format!("Failed to delete slot {slot_id} of task {task_id}"))?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+    touch_task(&mut tx, task_id, clock::now()).await?;
+    tx.commit()
+        .await
+        .context("Failed to commit the slot deletion")?;
+
+    Ok(true)
+}
+
+/// Stores the rows of an import in the staging tables without touching `tasks`.
+/// Returns the ID of the new import.
+pub async fn stage_import_in_db(pool: &SqlitePool, rows: Vec<CreateTaskPayload>) -> Result<i64> {
+    let mut tx = begin_write(pool).await?;
+
+    let import_id = sqlx::query("INSERT INTO imports (created_at, committed_at) VALUES (?, NULL)")
+        .bind(clock::now())
+        .execute(&mut *tx)
+        .await
+        .context("Failed to create import")?
+        .last_insert_rowid();
+
+    for (row_index, row) in rows.into_iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO import_rows (import_id, row_index, client_name, description, task_date, priority, external_source, external_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(import_id)
+        .bind(row_index as i64)
+        .bind(row.client_name)
+        .bind(row.description)
+        .bind(row.task_date)
+        .bind(row.priority)
+        .bind(row.external_source)
+        .bind(row.external_id)
+        .execute(&mut *tx)
+        .await
+        .context(format!("Failed to stage import row {row_index}"))?;
+    }
+
+    tx.commit()
+        .await
+        .context("Failed to commit staged import")?;
+    info!("Staged import {}", import_id);
+
+    Ok(import_id)
+}
+
+/// Builds the preview of a staged import: its rows, the rows that would update
+/// existing tasks, and the clients that would be seen for the first time.
+/// Returns `None` if no import has the given ID.
+pub async fn get_import_preview_from_db(
+    pool: &SqlitePool,
+    import_id: i64,
+) -> Result<Option<ImportPreview>> {
+    let Some((created_at, committed_at)) =
+        sqlx::query_as::<_, (DateTime<Utc>, Option<DateTime<Utc>>)>(
+            "SELECT created_at, committed_at FROM imports WHERE id = ?",
+        )
+        .bind(import_id)
+        .fetch_optional(pool)
+        .await
+        .context(format!("Failed to retrieve import {import_id}"))?
+    else {
+        return Ok(None);
+    };
+
+    let rows = get_import_rows_from_db(pool, import_id).await?;
+
+    let conflicts = sqlx::query_as::<_, (i64, i64, String)>(
+        r#"
+        SELECT r.row_index, t.id, t.description
+        FROM import_rows r
+        JOIN tasks t ON t.external_source = r.external_source AND t.external_id = r.external_id
+        WHERE r.import_id = ?
+        ORDER BY r.row_index
+        "#,
+    )
+    .bind(import_id)
+    .fetch_all(pool)
+    .await
+    .context(format!(
+        "Failed to compute conflicts for import {import_id}"
+    ))?
+    .into_iter()
+    .map(
+        |(row_index, task_id, existing_description)| ImportConflict {
+            row_index,
+            task_id,
+            existing_description,
+        },
+    )
+    .collect();
+
+    let new_clients = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT DISTINCT client_name FROM import_rows
+        WHERE import_id = ?
+            AND client_name NOT IN (SELECT name FROM clients)
+            AND client_name COLLATE NOCASE NOT IN (SELECT alias FROM client_aliases)
+        ORDER BY client_name
+        "#,
+    )
+    .bind(import_id)
+    .fetch_all(pool)
+    .await
+    .context(format!(
+        "Failed to compute new clients for import {import_id}"
+    ))?;
+
+    Ok(Some(ImportPreview {
+        import_id,
+        created_at,
+        committed_at,
+        rows,
+        conflicts,
+        new_clients,
+    }))
+}
+
+async fn get_import_rows_from_db<'e, E>(executor: E, import_id: i64) -> Result<Vec<ImportRow>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let rows = sqlx::query_as::<_, ImportRow>(
+        "SELECT row_index, client_name, description, task_date, priority, external_source, external_id FROM import_rows WHERE import_id = ? ORDER BY row_index",
+    )
+    .bind(import_id)
+    .fetch_all(executor)
+    .await
+    .context(format!("Failed to retrieve rows of import {import_id}"))?;
+
+    Ok(rows)
+}
+
+/// Result of committing a staged import.
+#[derive(Debug)]
+pub enum CommitImportOutcome {
+    NotFound,
+    AlreadyCommitted,
+    Committed(Vec<Task>),
+}
+
+/// Applies every row of a staged import to `tasks` in a single transaction.
+/// Either all rows land or none do.
+pub async fn commit_import_in_db(pool: &SqlitePool, import_id: i64) -> Result<CommitImportOutcome> {
+    let mut tx = begin_write(pool).await?;
+
+    let committed_at: Option<Option<DateTime<Utc>>> =
+        sqlx::query_scalar("SELECT committed_at FROM imports WHERE id = ?")
+            .bind(import_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .context(format!("Failed to retrieve import {import_id}"))?;
+
+    match committed_at {
+        None => return Ok(CommitImportOutcome::NotFound),
+        Some(Some(_)) => return Ok(CommitImportOutcome::AlreadyCommitted),
+        Some(None) => {}
+    }
+
+    let rows = get_import_rows_from_db(&mut *tx, import_id).await?;
+    let mut tasks = Vec::with_capacity(rows.len());
+    for row in rows {
+        let payload = CreateTaskPayload {
+            client_name: row.client_name,
+            description: row.description,
+            task_date: row.task_date,
+            priority: row.priority,
+            external_source: row.external_source,
+            external_id: row.external_id,
+            ..Default::default()
+        };
+        tasks.push(insert_task(&mut tx, payload).await?);
+    }
+
+    sqlx::query("UPDATE imports SET committed_at = ? WHERE id = ?")
+        .bind(clock::now())
+        .bind(import_id)
+        .execute(&mut *tx)
+        .await
+        .context(format!("Failed to mark import {import_id} as committed"))?;
+
+    tx.commit().await.context("Failed to commit import")?;
+    info!("Committed import {} ({} tasks)", import_id, tasks.len());
+
+    Ok(CommitImportOutcome::Committed(tasks))
+}
+
+/// Returns the canonical client name if `name` is a registered alias.
+pub async fn resolve_client_alias<'e, E>(executor: E, name: &str) -> Result<Option<String>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let canonical = sqlx::query_scalar("SELECT c.name FROM client_aliases a JOIN clients c ON c.id = a.client_id WHERE a.alias = ?")
+        .bind(name)
+        .fetch_optional(executor)
+        .await
+        .context(format!("Failed to resolve client alias '{name}'"))?;
+
+    Ok(canonical)
+}
+
+/// Lists every client name known to the system, archived ones included.
+pub async fn get_known_client_names_from_db(pool: &SqlitePool) -> Result<Vec<String>> {
+    let names = sqlx::query_scalar("SELECT name FROM clients ORDER BY name")
+        .fetch_all(pool)
+        .await
+        .context("Failed to retrieve known client names")?;
+
+    Ok(names)
+}
+
+/// Lists the aliases registered for a client.
+pub async fn get_client_aliases_from_db(
+    pool: &SqlitePool,
+    client_name: &str,
+) -> Result<Vec<ClientAlias>> {
+    let aliases = sqlx::query_as::<_, ClientAlias>(
+        "SELECT a.alias, c.name AS client_name FROM client_aliases a JOIN clients c ON c.id = a.client_id WHERE c.name = ? ORDER BY a.alias",
+    )
+    .bind(client_name)
+    .fetch_all(pool)
+    .await
+    .context(format!(
+        "Failed to retrieve aliases of client '{client_name}'"
+    ))?;
+
+    Ok(aliases)
+}
+
+/// Registers an alias for an existing client.
+/// Returns false if the alias is already in use (for this or another client).
+pub async fn add_client_alias_in_db(
+    pool: &SqlitePool,
+    client_name: &str,
+    alias: &str,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "INSERT INTO client_aliases (alias, client_id) SELECT ?, id FROM clients WHERE name = ? ON CONFLICT (alias) DO NOTHING",
+    )
+    .bind(alias)
+    .bind(client_name)
+    .execute(pool)
+    .await
+    .context(format!("Failed to add alias '{alias}' to client '{client_name}'"))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Removes an alias from a client.
+/// Returns false if the client has no such alias.
+pub async fn delete_client_alias_in_db(
+    pool: &SqlitePool,
+    client_name: &str,
+    alias: &str,
+) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM client_aliases WHERE alias = ? AND client_id = (SELECT id FROM clients WHERE name = ?)")
+        .bind(alias)
+        .bind(client_name)
+        .execute(pool)
+        .await
+        .context(format!(
+            "Failed to delete alias '{alias}' of client '{client_name}'"
+        ))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Lists clients. Archived clients are only included when `include_archived` is set.
+pub async fn get_clients_from_db(pool: &SqlitePool, include_archived: bool) -> Result<Vec<Client>> {
+    let clients = sqlx::query_as::<_, Client>(
+        "SELECT id, name, color, archived_at, default_priority, default_context FROM clients WHERE ? OR archived_at IS NULL ORDER BY name",
+    )
+    .bind(include_archived)
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve clients")?;
+
+    Ok(clients
+        .into_iter()
+        .map(colors::with_color_metadata)
+        .collect())
+}
+
+/// Aggregates the tasks of a client dated between `from` and `to` (inclusive).
+/// Soft-deleted tasks count as completed. Returns `None` if the client does not exist.
+pub async fn get_client_stats_from_db(
+    pool: &SqlitePool,
+    name: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Option<ClientStats>> {
+    let Some(client) = get_client_from_db(pool, name).await? else {
+        return Ok(None);
+    };
+
+    let (total_tasks, completed_tasks, rollover_count, rolled_over_tasks): (i64, i64, i64, i64) =
+        sqlx::query_as(
+            r#"
+            SELECT COUNT(*),
+                   COUNT(deleted_at),
+                   COALESCE(SUM(rollover_count), 0),
+                   COUNT(CASE WHEN rollover_count > 0 THEN 1 END)
+            FROM tasks
+            WHERE client_id = ? AND task_date BETWEEN ? AND ?
+            "#,
+        )
+        .bind(client.id)
+        .bind(from)
+        .bind(to)
+        .fetch_one(pool)
+        .await
+        .context(format!("Failed to compute statistics of client '{name}'"))?;
+
+    let priority_distribution = sqlx::query_as::<_, PriorityCount>(
+        r#"
+        SELECT priority, COUNT(*) AS count
+        FROM tasks
+        WHERE client_id = ? AND task_date BETWEEN ? AND ?
+        GROUP BY priority
+        ORDER BY priority ASC NULLS LAST
+        "#,
+    )
+    .bind(client.id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .context(format!(
+        "Failed to compute priority distribution of client '{name}'"
+    ))?;
+
+    let completion_rate = if total_tasks > 0 {
+        completed_tasks as f64 / total_tasks as f64
+    } else {
+        0.0
+    };
+
+    Ok(Some(ClientStats {
+        client_name: client.name,
+        from,
+        to,
+        total_tasks,
+        completed_tasks,
+        open_tasks: total_tasks - completed_tasks,
+        completion_rate,
+        rollover_count,
+        rolled_over_tasks,
+        priority_distribution,
+    }))
+}
+
+/// Retrieves the weekly goal, if one is set.
+pub async fn get_weekly_goal_from_db(pool: &SqlitePool) -> Result<Option<WeeklyGoal>> {
+    sqlx::query_as("SELECT unit, target, set_at FROM weekly_goal WHERE id = 1")
+        .fetch_optional(pool)
+        .await
+        .context("Failed to retrieve the weekly goal")
+}
+
+/// Sets the weekly goal, replacing the previous one, or removes it with
+/// `None`.
+pub async fn set_weekly_goal_in_db(
+    pool: &SqlitePool,
+    goal: Option<(GoalUnit, f64)>,
+) -> Result<Option<WeeklyGoal>> {
+    match goal {
+        Some((unit, target)) => {
+            sqlx::query(
+                "INSERT OR REPLACE INTO weekly_goal (id, unit, target, set_at) VALUES (1, ?, ?, ?)",
+            )
+            .bind(unit)
+            .bind(target)
+            .bind(clock::now())
+            .execute(pool)
+            .await
+            .context("Failed to set the weekly goal")?;
+            info!("Weekly goal set to {} {:?}.", target, unit);
+        }
+        None => {
+            sqlx::query("DELETE FROM weekly_goal")
+                .execute(pool)
+                .await
+                .context("Failed to remove the weekly goal")?;
+            info!("Weekly goal removed.");
+        }
+    }
+    get_weekly_goal_from_db(pool).await
+}
+
+/// Counts the tasks of `from` to `to` marked done, and the sum of their
+/// estimates in minutes (tasks without one count for nothing).
+pub async fn get_completed_work_from_db(
+    pool: &SqlitePool,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<(i64, i64)> {
+    sqlx::query_as(
+        r#"
+        SELECT COUNT(*), COALESCE(SUM(estimate_minutes), 0)
+        FROM tasks
+        WHERE task_date BETWEEN ? AND ? AND deleted_at IS NOT NULL
+        "#,
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_one(pool)
+    .await
+    .context(format!("Failed to count the work done from {from} to {to}"))
+}
+
+/// Retrieves up to `limit` clients with an ID greater than `after_id`,
+/// archived ones included, ordered by ID.
+pub async fn get_clients_after_from_db(
+    pool: &SqlitePool,
+    after_id: i64,
+    limit: i64,
+) -> Result<Vec<Client>> {
+    let clients = sqlx::query_as::<_, Client>(
+        "SELECT id, name, color, archived_at, default_priority, default_context FROM clients WHERE id > ? ORDER BY id LIMIT ?",
+    )
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context(format!("Failed to retrieve clients after ID {after_id}"))?;
+
+    Ok(clients
+        .into_iter()
+        .map(colors::with_color_metadata)
+        .collect())
+}
+
+/// Retrieves the clients written after position `after_seq` of their change
+/// sequence, in that order, for the BI table export and the mobile sync.
+pub async fn get_client_table_rows_from_db<'e, E>(
+    executor: E,
+    after_seq: i64,
+    limit: i64,
+) -> Result<Vec<ClientRow>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, ClientRow>(
+        r#"
+        SELECT id, name, color, created_at, archived_at, default_priority, default_context,
+               change_seq
+        FROM clients
+        WHERE change_seq > ?
+        ORDER BY change_seq
+        LIMIT ?
+        "#,
+    )
+    .bind(after_seq)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+    .context(format!(
+        "Failed to retrieve clients changed after {after_seq}"
+    ))
+}
+
+/// Retrieves the tasks written after position `after_seq` of their change
+/// sequence, in that order, for the BI table export and the mobile sync.
+pub async fn get_task_table_rows_from_db<'e, E>(
+    executor: E,
+    after_seq: i64,
+    limit: i64,
+) -> Result<Vec<TaskRow>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as::<_, TaskRow>(
+        r#"
+        SELECT t.id, t.client_id, t.description, t.task_date, t.created_at,
+               t.deleted_at AS done_at, t.priority, t.energy, t.estimate_minutes, t.context,
+               t.pin_to_date, t.rollover_count, t.stale_since,
+               (SELECT COUNT(*) FROM task_slots WHERE task_id = t.id) AS slots_total,
+               (SELECT COUNT(completed_at) FROM task_slots WHERE task_id = t.id) AS slots_done,
+               t.external_source, t.external_id, t.rollover_target, t.change_seq
+        FROM tasks t
+        WHERE t.change_seq > ?
+        ORDER BY t.change_seq
+        LIMIT ?
+        "#,
+    )
+    .bind(after_seq)
+    .bind(limit)
+    .fetch_all(executor)
+    .await
+    .context(format!(
+        "Failed to retrieve tasks changed after {after_seq}"
+    ))
+}
+
+/// Retrieves what the mobile sync reports after `since`: clients, tasks and
+/// removals, at most `limit` of each, in the order they were written. All
+/// three are read from the same snapshot, so that a row removed and then
+/// written again under the same ID cannot come out in the wrong order.
+pub async fn get_sync_changes_from_db(
+    pool: &SqlitePool,
+    since: SyncCursor,
+    limit: i64,
+) -> Result<SyncChanges> {
+    let mut tx = pool
+        .begin()
+        .await
+        .context("Failed to begin sync transaction")?;
+    let clients = get_client_table_rows_from_db(&mut *tx, since.clients, limit).await?;
+    let tasks = get_task_table_rows_from_db(&mut *tx, since.tasks, limit).await?;
+    let removals = sqlx::query_as::<_, SyncRemoval>(
+        "SELECT seq, entity, row_id FROM sync_removals WHERE seq > ? ORDER BY seq LIMIT ?",
+    )
+    .bind(since.removals)
+    .bind(limit)
+    .fetch_all(&mut *tx)
+    .await
+    .context(format!(
+        "Failed to retrieve removals after {}",
+        since.removals
+    ))?;
+    tx.commit()
+        .await
+        .context("Failed to end sync transaction")?;
+    Ok(SyncChanges {
+        clients,
+        tasks,
+        removals,
+    })
+}
+
+/// Position of the latest change of each sequence the sync follows, from
+/// which a device that has just loaded everything can follow the changes.
+pub async fn get_sync_cursor_from_db(pool: &SqlitePool) -> Result<SyncCursor> {
+    let (clients, tasks, removals) = sqlx::query_as::<_, (i64, i64, i64)>(
+        "SELECT (SELECT COALESCE(MAX(change_seq), 0) FROM clients),
+                (SELECT COALESCE(MAX(change_seq), 0) FROM tasks),
+                (SELECT COALESCE(MAX(seq), 0) FROM sync_removals)",
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to retrieve the latest sync position")?;
+    Ok(SyncCursor {
+        clients,
+        tasks,
+        removals,
+    })
+}
+
+/// Days finished webhook deliveries are kept in the delivery log.
+const WEBHOOK_DELIVERY_RETENTION_DAYS: i64 = 30;
+
+/// Columns of a webhook, without its secret, as read by `webhook_from_row`.
+const WEBHOOK_COLUMNS: &str = "id, url, events, created_at";
+
+type WebhookRecord = (i64, String, String, DateTime<Utc>);
+
+/// Builds a webhook from its row, where events are stored comma-separated.
+fn webhook_from_row((id, url, events, created_at): WebhookRecord) -> Webhook {
+    Webhook {
+        id,
+        url,
+        events: WebhookEvent::ALL
+            .into_iter()
+            .filter(|event| events.split(',').any(|name| name == event.as_str()))
+            .collect(),
+        created_at,
+        secret: None,
+    }
+}
+
+/// Registers a webhook receiving `events`, whose deliveries are signed with
+/// `secret`. The secret is returned this once.
+pub async fn create_webhook_in_db(
+    pool: &SqlitePool,
+    url: &str,
+    events: &[WebhookEvent],
+    secret: &str,
+) -> Result<Webhook> {
+    let events = events
+        .iter()
+        .map(WebhookEvent::as_str)
+        .collect::<Vec<_>>()
+        .join(",");
+    let row: WebhookRecord = sqlx::query_as(&format!(
+        "INSERT INTO webhooks (url, events, secret, created_at) VALUES (?, ?, ?, ?) RETURNING {WEBHOOK_COLUMNS}"
+    ))
+    .bind(url)
+    .bind(events)
+    .bind(secret)
+    .bind(clock::now())
+    .fetch_one(pool)
+    .await
+    .context("Failed to register webhook")?;
+    Ok(Webhook {
+        secret: Some(secret.to_string()),
+        ..webhook_from_row(row)
+    })
+}
+
+/// Retrieves all webhooks, oldest first.
+pub async fn get_webhooks_from_db(pool: &SqlitePool) -> Result<Vec<Webhook>> {
+    let rows: Vec<WebhookRecord> = sqlx::query_as(&format!(
+        "SELECT {WEBHOOK_COLUMNS} FROM webhooks ORDER BY id"
+    ))
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve webhooks")?;
+    Ok(rows.into_iter().map(webhook_from_row).collect())
+}
+
+/// Retrieves a single webhook by ID.
+pub async fn get_webhook_from_db(pool: &SqlitePool, webhook_id: i64) -> Result<Option<Webhook>> {
+    let row: Option<WebhookRecord> = sqlx::query_as(&format!(
+        "SELECT {WEBHOOK_COLUMNS} FROM webhooks WHERE id = ?"
+    ))
+    .bind(webhook_id)
+    .fetch_optional(pool)
+    .await
+    .with_context(|| format!("Failed to retrieve webhook {webhook_id}"))?;
+    Ok(row.map(webhook_from_row))
+}
+
+/// Removes a webhook and its deliveries, sent or not. Returns false if it
+/// does not exist.
+pub async fn delete_webhook_in_db(pool: &SqlitePool, webhook_id: i64) -> Result<bool> {
+    let mut tx = begin_write(pool).await?;
+    sqlx::query("DELETE FROM webhook_deliveries WHERE webhook_id = ?")
+        .bind(webhook_id)
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to delete the deliveries of webhook {webhook_id}"))?;
+    let deleted = sqlx::query("DELETE FROM webhooks WHERE id = ?")
+        .bind(webhook_id)
+        .execute(&mut *tx)
+        .await
+        .with_context(|| format!("Failed to delete webhook {webhook_id}"))?
+        .rows_affected()
+        > 0;
+    tx.commit()
+        .await
+        .context("Failed to commit webhook deletion")?;
+    Ok(deleted)
+}
+
+/// Queues a delivery of `event` to each webhook subscribed to it. Runs in
+/// the transaction of the change raising the event, so that an event is
+/// delivered if and only if its change is committed.
+async fn queue_webhook_event(
+    conn: &mut SqliteConnection,
+    event: WebhookEvent,
+    data: &impl Serialize,
+) -> Result<()> {
+    let webhooks: Vec<(i64, String)> = sqlx::query_as("SELECT id, events FROM webhooks")
+        .fetch_all(&mut *conn)
+        .await
+        .context("Failed to retrieve webhooks")?;
+    let now = clock::now();
+    let mut payload = None;
+    for (webhook_id, events) in webhooks {
+        if !events.split(',').any(|name| name == event.as_str()) {
+            continue;
+        }
+        let payload = match &payload {
+            Some(payload) => payload,
+            None => payload.insert(
+                serde_json::json!({ "event": event, "occurred_at": now, "data": data }).to_string(),
+            ),
+        };
+        sqlx::query(
+            "INSERT INTO webhook_deliveries (webhook_id, event, payload, status, created_at, next_attempt_at) VALUES (?, ?, ?, 'pending', ?, ?)",
+        )
+        .bind(webhook_id)
+        .bind(event)
+        .bind(payload.as_str())
+        .bind(now)
+        .bind(now)
+        .execute(&mut *conn)
+        .await
+        .with_context(|| format!("Failed to queue {} for webhook {webhook_id}", event.as_str()))?;
+    }
+    Ok(())
+}
+
+/// A webhook delivery due to be sent, see `claim_webhook_deliveries_in_db`.
+#[derive(Debug, sqlx::FromRow)]
+pub struct DueDelivery {
+    pub id: i64,
+    pub url: String,
+    pub secret: String,
+    pub event: WebhookEvent,
+    pub payload: String,
+    // Attempts already made.
+    pub attempts: i64,
+}
+
+/// Claims up to `limit` pending deliveries due at `now`, oldest first, and
+/// puts their next attempt off until `lease_until`, so that another worker
+/// sharing the database does not send them meanwhile.
+pub async fn claim_webhook_deliveries_in_db(
+    pool: &SqlitePool,
+    now: DateTime<Utc>,
+    lease_until: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<DueDelivery>> {
+    let mut tx = begin_write(pool).await?;
+    let due: Vec<DueDelivery> = sqlx::query_as(
+        r#"
+        SELECT d.id, w.url, w.secret, d.event, d.payload, d.attempts
+        FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id
+        WHERE d.status = 'pending' AND d.next_attempt_at <= ?
+        ORDER BY d.next_attempt_at, d.id
+        LIMIT ?
+        "#,
+    )
+    .bind(now)
+    .bind(limit)
+    .fetch_all(&mut *tx)
+    .await
+    .context("Failed to retrieve due webhook deliveries")?;
+    for delivery in &due {
+        sqlx::query("UPDATE webhook_deliveries SET next_attempt_at = ? WHERE id = ?")
+            .bind(lease_until)
+            .bind(delivery.id)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to claim webhook delivery {}", delivery.id))?;
+    }
+    tx.commit()
+        .await
+        .context("Failed to commit the webhook delivery claims")?;
+    Ok(due)
+}
+
+/// Outcome of an attempt at a webhook delivery, see
+/// `record_webhook_attempt_in_db`.
+pub struct WebhookAttempt<'a> {
+    pub delivery_id: i64,
+    pub attempted_at: DateTime<Utc>,
+    pub status: DeliveryStatus,
+    /// When to try again, while still pending.
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub response_status: Option<u16>,
+    pub error: Option<&'a str>,
+}
+
+/// Records an attempt at a webhook delivery, and forgets the deliveries
+/// finished long ago.
+pub async fn record_webhook_attempt_in_db(
+    pool: &SqlitePool,
+    attempt: WebhookAttempt<'_>,
+) -> Result<()> {
+    let mut tx = begin_write(pool).await?;
+    sqlx::query("DELETE FROM webhook_deliveries WHERE status != 'pending' AND created_at < ?")
+        .bind(attempt.attempted_at - chrono::Duration::days(WEBHOOK_DELIVERY_RETENTION_DAYS))
+        .execute(&mut *tx)
+        .await
+        .context("Failed to prune old webhook deliveries")?;
+    sqlx::query(
+        r#"
+        UPDATE webhook_deliveries
+        SET attempts = attempts + 1, last_attempt_at = ?, status = ?, next_attempt_at = ?,
+            response_status = ?, error = ?
+        WHERE id = ?
+        "#,
+    )
+    .bind(attempt.attempted_at)
+    .bind(attempt.status)
+    .bind(attempt.next_attempt_at)
+    .bind(attempt.response_status)
+    .bind(attempt.error)
+    .bind(attempt.delivery_id)
+    .execute(&mut *tx)
+    .await
+    .with_context(|| {
+        format!(
+            "Failed to record the attempt at webhook delivery {}",
+            attempt.delivery_id
+        )
+    })?;
+    tx.commit()
+        .await
+        .context("Failed to commit the webhook attempt")?;
+    Ok(())
+}
+
+/// Retrieves the `limit` latest deliveries of a webhook, most recent first.
+pub async fn get_webhook_deliveries_from_db(
+    pool: &SqlitePool,
+    webhook_id: i64,
+    limit: i64,
+) -> Result<Vec<WebhookDelivery>> {
+    sqlx::query_as(
+        "SELECT id, webhook_id, event, status, attempts, created_at, next_attempt_at, last_attempt_at, response_status, error FROM webhook_deliveries WHERE webhook_id = ? ORDER BY id DESC LIMIT ?",
+    )
+    .bind(webhook_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .with_context(|| format!("Failed to retrieve the deliveries of webhook {webhook_id}"))
+}
+
+/// Retrieves the `limit` latest deliveries given up on, of every webhook,
+/// most recent first.
+pub async fn get_failed_webhook_deliveries_from_db(
+    pool: &SqlitePool,
+    limit: i64,
+) -> Result<Vec<WebhookDelivery>> {
+    sqlx::query_as(
+        "SELECT id, webhook_id, event, status, attempts, created_at, next_attempt_at, last_attempt_at, response_status, error FROM webhook_deliveries WHERE status = 'failed' ORDER BY id DESC LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the failed webhook deliveries")
+}
+
+/// Queues failed deliveries again, those of `delivery_ids` or all of them,
+/// to be sent at `now` with a fresh count of attempts. Returns the IDs of
+/// those queued; deliveries that did not fail are left alone.
+pub async fn requeue_failed_webhook_deliveries_in_db(
+    pool: &SqlitePool,
+    delivery_ids: Option<&[i64]>,
+    now: DateTime<Utc>,
+) -> Result<Vec<i64>> {
+    const REQUEUE: &str = "UPDATE webhook_deliveries SET status = 'pending', attempts = 0, next_attempt_at = ?1 WHERE status = 'failed' AND (?2 IS NULL OR id = ?2) RETURNING id";
+    let mut tx = begin_write(pool).await?;
+    let mut requeued: Vec<i64> = Vec::new();
+    match delivery_ids {
+        Some(ids) => {
+            for id in ids {
+                let found: Option<i64> = sqlx::query_scalar(REQUEUE)
+                    .bind(now)
+                    .bind(id)
+                    .fetch_optional(&mut *tx)
+                    .await
+                    .with_context(|| format!("Failed to queue webhook delivery {id} again"))?;
+                requeued.extend(found);
+            }
+        }
+        None => {
+            requeued = sqlx::query_scalar(REQUEUE)
+                .bind(now)
+                .bind(None::<i64>)
+                .fetch_all(&mut *tx)
+                .await
+                .context("Failed to queue the failed webhook deliveries again")?;
+        }
+    }
+    tx.commit()
+        .await
+        .context("Failed to commit the requeued webhook deliveries")?;
+    requeued.sort_unstable();
+    requeued.dedup();
+    Ok(requeued)
+}
+
+/// Retrieves a single client by name.
+pub async fn get_client_from_db<'e, E>(executor: E, name: &str) -> Result<Option<Client>>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let client = sqlx::query_as::<_, Client>(
+        "SELECT id, name, color, archived_at, default_priority, default_context FROM clients WHERE name = ?",
+    )
+    .bind(name)
+    .fetch_optional(executor)
+    .await
+    .context(format!("Failed to retrieve client '{name}'"))?;
+
+    Ok(client.map(colors::with_color_metadata))
+}
+
+/// Returns the client with the given name, registering it with a palette
+/// color first if it does not exist yet.
+async fn ensure_client(conn: &mut SqliteConnection, name: &str) -> Result<Client> {
+    if let Some(client) = get_client_from_db(&mut *conn, name).await? {
+        return Ok(client);
+    }
+
+    // Another connection may register the same client in the meantime.
+    let client = sqlx::query_as::<_, Client>(
+        "INSERT INTO clients (name, color, created_at) VALUES (?, ?, ?) ON CONFLICT (name) DO NOTHING RETURNING id, name, color, archived_at, default_priority, default_context",
+    )
+    .bind(name)
+    .bind(colors::get_or_assign_client_color(name))
+    .bind(clock::now())
+    .fetch_optional(&mut *conn)
+    .await
+    .context(format!("Failed to register client '{name}'"))?;
+
+    match client {
+        Some(client) => {
+            info!("Registered new client '{}'", name);
+            Ok(colors::with_color_metadata(client))
+        }
+        None => get_client_from_db(conn, name)
+            .await?
+            .context(format!("Client '{name}' vanished while being registered")),
+    }
+}
+
+/// Creates a client. Returns `None` if a client with that name already exists.
+pub async fn create_client_in_db(pool: &SqlitePool, name: &str) -> Result<Option<Client>> {
+    let mut conn = pool
+        .acquire()
+        .await
+        .context("Failed to acquire connection")?;
+    if get_client_from_db(&mut *conn, name).await?.is_some() {
+        return Ok(None);
+    }
+
+    ensure_client(&mut conn, name).await.map(Some)
+}
+
+/// Result of renaming a client.
+#[derive(Debug)]
+pub enum RenameClientOutcome {
+    NotFound,
+    NameTaken,
+    Renamed(Client),
+}
+
+/// Renames a client. Its tasks and aliases keep pointing at it.
+pub async fn rename_client_in_db(
+    pool: &SqlitePool,
+    name: &str,
+    new_name: &str,
+) -> Result<RenameClientOutcome> {
+    let mut tx = begin_write(pool).await?;
+
+    if get_client_from_db(&mut *tx, name).await?.is_none() {
+        return Ok(RenameClientOutcome::NotFound);
+    }
+    if name != new_name && get_client_from_db(&mut *tx, new_name).await?.is_some() {
+        return Ok(RenameClientOutcome::NameTaken);
+    }
+
+    // Tasks and aliases reference the client by ID, so they follow automatically.
+    sqlx::query("UPDATE clients SET name = ? WHERE name = ?")
+        .bind(new_name)
+        .bind(name)
+        .execute(&mut *tx)
+        .await
+        .context(format!("Failed to rename client '{name}' to '{new_name}'"))?;
+
+    let client = get_client_from_db(&mut *tx, new_name)
+        .await?
+        .context("Renamed client could not be read back")?;
+    tx.commit()
+        .await
+        .context("Failed to commit client rename")?;
+    info!("Client '{}' renamed to '{}'", name, new_name);
+
+    Ok(RenameClientOutcome::Renamed(client))
+}
+
+/// Result of deleting a client.
+#[derive(Debug)]
+pub enum DeleteClientOutcome {
+    NotFound,
+    // Clients with tasks (even soft-deleted ones) must be archived instead.
+    HasTasks(i64),
+    Deleted,
+}
+
+/// Deletes a client and its aliases, provided no task references it.
+pub async fn delete_client_in_db(pool: &SqlitePool, name: &str) -> Result<DeleteClientOutcome> {
+    let mut tx = begin_write(pool).await?;
+
+    let task_count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM tasks t JOIN clients c ON c.id = t.client_id WHERE c.name = ?",
+    )
+    .bind(name)
+    .fetch_one(&mut *tx)
+    .await
+    .context(format!("Failed to count tasks of client '{name}'"))?;
+    if task_count > 0 {
+        return Ok(DeleteClientOutcome::HasTasks(task_count));
+    }
+
+    sqlx::query(
+        "DELETE FROM client_aliases WHERE client_id = (SELECT id FROM clients WHERE name = ?)",
+    )
+    .bind(name)
+    .execute(&mut *tx)
+    .await
+    .context(format!("Failed to delete aliases of client '{name}'"))?;
+
+    let deleted = sqlx::query("DELETE FROM clients WHERE name = ?")
+        .bind(name)
+        .execute(&mut *tx)
+        .await
+        .context(format!("Failed to delete client '{name}'"))?
+        .rows_affected();
+    if deleted == 0 {
+        return Ok(DeleteClientOutcome::NotFound);
+    }
+
+    tx.commit()
+        .await
+        .context("Failed to commit client deletion")?;
+    info!("Client '{}' deleted", name);
+
+    Ok(DeleteClientOutcome::Deleted)
+}
+
+/// Returns when a client was archived, or `None` if it is active or unknown.
+/// Aliases are resolved to their canonical client first.
+pub async fn get_client_archived_at_from_db(
+    pool: &SqlitePool,
+    client_name: &str,
+) -> Result<Option<DateTime<Utc>>> {
+    let archived_at: Option<Option<DateTime<Utc>>> = sqlx::query_scalar(
+        r#"
+        SELECT archived_at FROM clients
+        WHERE id = COALESCE(
+            (SELECT client_id FROM client_aliases WHERE alias = ?),
+            (SELECT id FROM clients WHERE name = ?)
+        )
+        "#,
+    )
+    .bind(client_name)
+    .bind(client_name)
+    .fetch_optional(pool)
+    .await
+    .context(format!(
+        "Failed to retrieve archive status of client '{client_name}'"
+    ))?;
+
+    Ok(archived_at.flatten())
+}
+
+/// Archives or unarchives a client. Its tasks are left untouched.
+/// Returns `None` if the client does not exist.
+pub async fn set_client_archived_in_db(
+    pool: &SqlitePool,
+    client_name: &str,
+    archived: bool,
+) -> Result<Option<Client>> {
+    let client = sqlx::query_as::<_, Client>(
+        "UPDATE clients SET archived_at = ? WHERE name = ? RETURNING id, name, color, archived_at, default_priority, default_context",
+    )
+    .bind(archived.then(clock::now))
+    .bind(client_name)
+    .fetch_optional(pool)
+    .await
+    .context(format!(
+        "Failed to update archive status of client '{client_name}'"
+    ))?;
+
+    if client.is_some() {
+        info!("Client '{}' archived: {}", client_name, archived);
+    }
+
+    Ok(client.map(colors::with_color_metadata))
+}
+
+/// Turns on focus mode for a day, or changes its number of visible tasks.
+pub async fn set_focus_day_in_db(
+    pool: &SqlitePool,
+    day: NaiveDate,
+    top_n: i64,
+) -> Result<FocusDay> {
+    let focus_day = sqlx::query_as::<_, FocusDay>(
+        r#"
+        INSERT INTO focus_days (day, top_n, created_at) VALUES (?, ?, ?)
+        ON CONFLICT (day) DO UPDATE SET top_n = excluded.top_n
+        RETURNING day, top_n, created_at
+        "#,
+    )
+    .bind(day)
+    .bind(top_n)
+    .bind(clock::now())
+    .fetch_one(pool)
+    .await
+    .context(format!("Failed to turn on focus mode for {day}"))?;
+
+    info!("Focus mode on for {} (top {})", day, top_n);
+    Ok(focus_day)
+}
+
+/// Rewrites the priorities of the open tasks of `day` as a clean sequence
+/// keeping their order (see `priorities::normalized`), in one transaction
+/// that records each change in `priority_changes`. Tasks without a priority
+/// are left alone; tasks with equal priorities keep their creation order.
+pub async fn normalize_priorities_in_db(
+    pool: &SqlitePool,
+    day: NaiveDate,
+    keep_ties: bool,
+) -> Result<PriorityNormalization> {
+    let mut tx = begin_write(pool).await?;
+    let tasks: Vec<(i64, String, Priority)> = sqlx::query_as(
+        r#"
+        SELECT id, description, priority FROM tasks
+        WHERE task_date = ? AND deleted_at IS NULL AND priority IS NOT NULL
+        ORDER BY priority, id
+        "#,
+    )
+    .bind(day)
+    .fetch_all(&mut *tx)
+    .await
+    .context(format!("Failed to retrieve the priorities of {day}"))?;
+
+    let old: Vec<Priority> = tasks.iter().map(|(_, _, priority)| *priority).collect();
+    let new = priorities::normalized(&old, keep_ties);
+    let now = clock::now();
+    let mut changes = Vec::new();
+    for ((task_id, description, old_priority), new_priority) in tasks.iter().zip(new) {
+        if *old_priority == new_priority {
+            continue;
+        }
+        sqlx::query("UPDATE tasks SET priority = ?, updated_at = ? WHERE id = ?")
+            .bind(new_priority)
+            .bind(now)
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await
+            .context(format!("Failed to update the priority of task {task_id}"))?;
+        sqlx::query(
+            "INSERT INTO priority_changes (task_id, day, old_priority, new_priority, changed_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(task_id)
+        .bind(day)
+        .bind(old_priority)
+        .bind(new_priority)
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .context(format!("Failed to record the priority change of task {task_id}"))?;
+        changes.push(PriorityChange {
+            task_id: *task_id,
+            description: description.clone(),
+            old_priority: *old_priority,
+            new_priority,
+        });
+    }
+    tx.commit()
+        .await
+        .context("Failed to commit priority normalization")?;
+
+    info!(
+        "Priorities of {} normalized ({} of {} tasks changed)",
+        day,
+        changes.len(),
+        tasks.len()
+    );
+    Ok(PriorityNormalization {
+        day,
+        tasks: tasks.len(),
+        changes,
+    })
+}
+
+/// Turns off focus mode for a day. Returns false if it was not on.
+pub async fn clear_focus_day_in_db(pool: &SqlitePool, day: NaiveDate) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM focus_days WHERE day = ?")
+        .bind(day)
+        .execute(pool)
+        .await
+        .context(format!("Failed to turn off focus mode for {day}"))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// The days in focus mode between `from` and `to` (inclusive), with their `top_n`.
+pub async fn get_focus_days_from_db(
+    pool: &SqlitePool,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<HashMap<NaiveDate, i64>> {
+    let days: Vec<(NaiveDate, i64)> =
+        sqlx::query_as("SELECT day, top_n FROM focus_days WHERE day BETWEEN ? AND ?")
+            .bind(from)
+            .bind(to)
+            .fetch_all(pool)
+            .await
+            .context("Failed to retrieve focus days")?;
+
+    Ok(days.into_iter().collect())
+}
+
+/// Sets the color of a client. Tasks pick it up immediately since their color
+/// is read from the client. An actual change is recorded in the history of
+/// color changes. Returns `None` if the client does not exist.
+pub async fn set_client_color_in_db(
+    pool: &SqlitePool,
+    client_name: &str,
+    color: &HexColor,
+) -> Result<Option<Client>> {
+    let mut tx = begin_write(pool).await?;
+
+    let Some(previous) = get_client_from_db(&mut *tx, client_name).await? else {
+        return Ok(None);
+    };
+
+    let client = sqlx::query_as::<_, Client>(
+        "UPDATE clients SET color = ? WHERE id = ? RETURNING id, name, color, archived_at, default_priority, default_context",
+    )
+    .bind(color)
+    .bind(previous.id)
+    .fetch_one(&mut *tx)
+    .await
+    .context(format!("Failed to update color of client '{client_name}'"))?;
+
+    if previous.color != *color {
+        sqlx::query(
+            "INSERT INTO client_color_changes (client_id, old_color, new_color, reason, changed_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(client.id)
+        .bind(&previous.color)
+        .bind(color)
+        .bind(ColorChangeReason::Override)
+        .bind(clock::now())
+        .execute(&mut *tx)
+        .await
+        .context(format!("Failed to record color change of client '{client_name}'"))?;
+    }
+    tx.commit().await.context("Failed to commit color change")?;
+
+    colors::set_client_color(client_name, color);
+    info!(
+        "Client '{}' color set to {} (was {})",
+        client_name, color, previous.color
+    );
+
+    Ok(Some(colors::with_color_metadata(client)))
+}
+
+/// Sets the priority and context given to the new tasks of a client created
+/// without their own. Existing tasks are left as they are. Returns `None`
+/// if the client does not exist.
+pub async fn set_client_defaults_in_db(
+    pool: &SqlitePool,
+    client_name: &str,
+    priority: Option<Priority>,
+    context: Option<&str>,
+) -> Result<Option<Client>> {
+    let client = sqlx::query_as::<_, Client>(
+        "UPDATE clients SET default_priority = ?, default_context = ? WHERE name = ? RETURNING id, name, color, archived_at, default_priority, default_context",
+    )
+    .bind(priority)
+    .bind(context)
+    .bind(client_name)
+    .fetch_optional(pool)
+    .await
+    .context(format!("Failed to update defaults of client '{client_name}'"))?;
+
+    Ok(client.map(colors::with_color_metadata))
+}
+
+/// Retrieves the color changes with an ID greater than `after_id`, oldest first.
+pub async fn get_color_changes_after_from_db(
+    pool: &SqlitePool,
+    after_id: i64,
+) -> Result<Vec<ClientColorChange>> {
+    sqlx::query_as::<_, ClientColorChange>(
+        r#"
+        SELECT h.id, h.client_id, c.name AS client_name, h.old_color, h.new_color,
+               h.reason, h.changed_at
+        FROM client_color_changes h
+        JOIN clients c ON c.id = h.client_id
+        WHERE h.id > ?
+        ORDER BY h.id
+        "#,
+    )
+    .bind(after_id)
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve client color changes")
+}
+
+/// Rows matching an inconsistency, as a `FROM ... WHERE ...` clause, and the
+/// statement fixing them, if any. `None` for client colors, which the
+/// doctor checks against the color store itself.
+fn inconsistency_queries(
+    inconsistency: Inconsistency,
+) -> Option<(&'static str, Option<&'static str>)> {
+    Some(match inconsistency {
+        Inconsistency::TasksWithoutClient => (
+            "FROM tasks WHERE client_id NOT IN (SELECT id FROM clients)",
+            None,
+        ),
+        Inconsistency::SlotsWithoutTask => (
+            "FROM task_slots WHERE task_id NOT IN (SELECT id FROM tasks)",
+            Some("DELETE FROM task_slots WHERE task_id NOT IN (SELECT id FROM tasks)"),
+        ),
+        Inconsistency::AliasesWithoutClient => (
+            "FROM client_aliases WHERE client_id NOT IN (SELECT id FROM clients)",
+            Some("DELETE FROM client_aliases WHERE client_id NOT IN (SELECT id FROM clients)"),
+        ),
+        Inconsistency::ImportRowsWithoutImport => (
+            "FROM import_rows WHERE import_id NOT IN (SELECT id FROM imports)",
+            Some("DELETE FROM import_rows WHERE import_id NOT IN (SELECT id FROM imports)"),
+        ),
+        Inconsistency::ColorChangesWithoutClient => (
+            "FROM client_color_changes WHERE client_id NOT IN (SELECT id FROM clients)",
+            Some(
+                "DELETE FROM client_color_changes WHERE client_id NOT IN (SELECT id FROM clients)",
+            ),
+        ),
+        Inconsistency::OpenTasksWithAllSlotsDone => (
+            r#"
+            FROM tasks t
+            WHERE t.deleted_at IS NULL
+              AND EXISTS (SELECT 1 FROM task_slots s WHERE s.task_id = t.id)
+              AND NOT EXISTS (SELECT 1 FROM task_slots s WHERE s.task_id = t.id AND s.completed_at IS NULL)
+            "#,
+            Some(
+                r#"
+                UPDATE tasks
+                SET deleted_at = (SELECT MAX(s.completed_at) FROM task_slots s WHERE s.task_id = tasks.id)
+                WHERE deleted_at IS NULL
+                  AND EXISTS (SELECT 1 FROM task_slots s WHERE s.task_id = tasks.id)
+                  AND NOT EXISTS (SELECT 1 FROM task_slots s WHERE s.task_id = tasks.id AND s.completed_at IS NULL)
+                "#,
+            ),
+        ),
+        Inconsistency::DoneTasksWithOpenSlots => (
+            r#"
+            FROM task_slots s JOIN tasks t ON t.id = s.task_id
+            WHERE s.completed_at IS NULL AND t.deleted_at IS NOT NULL
+            "#,
+            Some(
+                r#"
+                UPDATE task_slots
+                SET completed_at = (SELECT t.deleted_at FROM tasks t WHERE t.id = task_slots.task_id)
+                WHERE completed_at IS NULL
+                  AND task_id IN (SELECT id FROM tasks WHERE deleted_at IS NOT NULL)
+                "#,
+            ),
+        ),
+        Inconsistency::ClientsMissingFromColorStore => return None,
+    })
+}
+
+/// Counts the rows affected by an inconsistency.
+pub async fn count_inconsistent_rows(
+    pool: &SqlitePool,
+    inconsistency: Inconsistency,
+) -> Result<u64> {
+    let Some((rows, _)) = inconsistency_queries(inconsistency) else {
+        return Ok(0);
+    };
+    let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) {rows}"))
+        .fetch_one(pool)
+        .await
+        .context(format!("Failed to count {inconsistency}"))?;
+    Ok(count as u64)
+}
+
+/// Fixes the rows affected by an inconsistency, returning how many were.
+/// Nothing is done for inconsistencies that cannot be fixed automatically.
+pub async fn repair_inconsistent_rows(
+    pool: &SqlitePool,
+    inconsistency: Inconsistency,
+) -> Result<u64> {
+    let Some((_, Some(repair))) = inconsistency_queries(inconsistency) else {
+        return Ok(0);
+    };
+    let mut tx = begin_write(pool).await?;
+    let result = sqlx::query(repair)
+        .execute(&mut *tx)
+        .await
+        .context(format!("Failed to fix {inconsistency}"))?;
+    tx.commit()
+        .await
+        .context(format!("Failed to commit the fix of {inconsistency}"))?;
+    Ok(result.rows_affected())
+}
+
+/// Revokes the session `id` until it expires at `expires_at`, dropping the
+/// revoked sessions that have expired since.
+pub async fn revoke_session_in_db(
+    pool: &SqlitePool,
+    id: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<()> {
+    let mut tx = begin_write(pool).await?;
+    sqlx::query("DELETE FROM revoked_sessions WHERE expires_at <= ?")
+        .bind(clock::now())
+        .execute(&mut *tx)
+        .await
+        .context("Failed to drop expired revoked sessions")?;
+    sqlx::query("INSERT OR IGNORE INTO revoked_sessions (id, expires_at) VALUES (?, ?)")
+        .bind(id)
+        .bind(expires_at)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to revoke session")?;
+    tx.commit()
+        .await
+        .context("Failed to commit session revocation")?;
+    Ok(())
+}
+
+/// Whether the session `id` was signed out.
+pub async fn is_session_revoked_in_db(pool: &SqlitePool, id: &str) -> Result<bool> {
+    let revoked: Option<String> =
+        sqlx::query_scalar("SELECT id FROM revoked_sessions WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .context("Failed to look up revoked sessions")?;
+    Ok(revoked.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use common::CreateTaskPayload;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const TEST_TARGET_DIR_PATH: &str = "database";
+
+    /// Returns the absolute path to the test data directory.
+    fn get_test_data_dir() -> PathBuf {
+        // Path::new(".").canonicalize() gets the absolute path of the current working directory.
+        // This makes the cleanup more robust regardless of where `cargo test` is called from.
+        let mut path = std::env::current_dir()
+            .expect("Failed to get current working directory for test cleanup");
+
+        // Append the relative path to your database directory
+        path.push(TEST_TARGET_DIR_PATH);
+        path
+    }
+
+    /// Cleans up the test environment.
+    fn teardown_test_env_for_file_cleanup(db_dir: &PathBuf) {
+        if db_dir.exists()
+            && let Err(e) = fs::remove_dir_all(db_dir)
+        {
+            eprintln!(
+                "Error: Failed to remove test database directory {:?}: {}",
+                db_dir, e
+            );
+        }
+    }
+
+    /// Helper function to set up an in-memory SQLite database for testing.
+    /// This creates a fresh, empty database for each test, ensuring they are isolated.
+    async fn setup_test_db() -> Result<SqlitePool> {
+        // Use :memory: to create an in-memory database
+        let pool = SqlitePool::connect("sqlite::memory:").await?;
+
+        // Run the same schema creation as the main application
+        init_schema(&pool).await?;
+
+        Ok(pool)
+    }
+
+    #[tokio::test]
+    async fn test_create_and_get_task() {
+        let pool = setup_test_db().await.unwrap();
+        let today = Utc::now().date_naive();
+        let payload = CreateTaskPayload {
+            client_name: "Test Client".to_string(),
+            description: "Test the database".to_string(),
+            task_date: Some(today),
+            priority: Some(Priority::Medium),
+            ..Default::default()
+        };
+
+        // Act: Create a new task in the test database
+        let created_task = create_task_in_db(&pool, payload).await.unwrap();
+
+        // Assert: The created task has the correct data
+        assert_eq!(created_task.client_name, "Test Client");
+        assert_eq!(created_task.description, "Test the database");
+        assert_eq!(created_task.task_date, today);
+        assert_eq!(created_task.priority, Some(Priority::Medium));
+        assert!(created_task.id > 0); // Should have been assigned an ID by the DB
+
+        // Act: Retrieve tasks for the current week
+        let week_tasks = get_current_week_tasks_from_db(&pool).await.unwrap();
+
+        // Assert: The newly created task is in the list
+        assert_eq!(week_tasks.len(), 1);
+        assert_eq!(week_tasks[0].id, created_task.id);
+        assert_eq!(week_tasks[0].priority, Some(Priority::Medium));
+
+        // Call this last to remove the created directory and its contents
+        teardown_test_env_for_file_cleanup(&get_test_data_dir());
+    }
+
+    #[tokio::test]
+    async fn test_create_task_without_priority() {
+        let pool = setup_test_db().await.unwrap();
+        let today = Utc::now().date_naive();
+        let payload = CreateTaskPayload {
+            client_name: "Client No Prio".to_string(),
+            description: "Task without priority".to_string(),
+            task_date: Some(today),
+            priority: None, // No priority
+            ..Default::default()
+        };
+
+        let created_task = create_task_in_db(&pool, payload).await.unwrap();
+        assert_eq!(created_task.priority, None); // Assert priority is None
+
+        let week_tasks = get_current_week_tasks_from_db(&pool).await.unwrap();
+        assert_eq!(week_tasks.len(), 1);
+        assert_eq!(week_tasks[0].priority, None); // Assert retrieved priority is None
+    }
+
+    #[tokio::test]
+    async fn test_create_task_with_external_ref_is_upserted() {
+        let pool = setup_test_db().await.unwrap();
+        let today = Utc::now().date_naive();
+        let payload = || CreateTaskPayload {
+            client_name: "Imported Client".to_string(),
+            description: "Imported task".to_string(),
+            task_date: Some(today),
+            priority: Some(Priority::Medium),
+            external_source: Some("todoist".to_string()),
+            external_id: Some("123".to_string()),
+            ..Default::default()
+        };
+
+        let first = create_task_in_db(&pool, payload()).await.unwrap();
+
+        // Act: Import the same external task again with an updated description
+        let second = create_task_in_db(
+            &pool,
+            CreateTaskPayload {
+                description: "Imported task (edited)".to_string(),
+                ..payload()
+            },
+        )
+        .await
+        .unwrap();
+
+        // Assert: The existing row was updated rather than duplicated
+        assert_eq!(first.id, second.id);
+        assert_eq!(second.description, "Imported task (edited)");
+        assert_eq!(second.created_at, first.created_at);
+        let week_tasks = get_current_week_tasks_from_db(&pool).await.unwrap();
+        assert_eq!(week_tasks.len(), 1);
+
+        // Act: The same ID from another source is a different task
+        create_task_in_db(
+            &pool,
+            CreateTaskPayload {
+                external_source: Some("github".to_string()),
+                ..payload()
+            },
+        )
+        .await
+        .unwrap();
+
+        let week_tasks = get_current_week_tasks_from_db(&pool).await.unwrap();
+        assert_eq!(week_tasks.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stage_preview_and_commit_import() {
+        let pool = setup_test_db().await.unwrap();
+        let today = Utc::now().date_naive();
+        let existing = create_task_in_db(
+            &pool,
+            CreateTaskPayload {
+                client_name: "Known Client".to_string(),
+                description: "Already imported".to_string(),
+                task_date: Some(today),
+                external_source: Some("csv".to_string()),
+                external_id: Some("1".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let import_id = stage_import_in_db(
+            &pool,
+            vec![
+                CreateTaskPayload {
+                    client_name: "Known Client".to_string(),
+                    description: "Updated by import".to_string(),
+                    task_date: Some(today),
+                    external_source: Some("csv".to_string()),
+                    external_id: Some("1".to_string()),
+                    ..Default::default()
+                },
+                CreateTaskPayload {
+                    client_name: "Brand New Client".to_string(),
+                    description: "Fresh row".to_string(),
+                    task_date: Some(today),
+                    external_source: Some("csv".to_string()),
+                    external_id: Some("2".to_string()),
+                    ..Default::default()
+                },
+            ],
+        )
+        .await
+        .unwrap();
+
+        // Assert: Staging does not touch live data
+        assert_eq!(
+            get_current_week_tasks_from_db(&pool).await.unwrap().len(),
+            1
+        );
+
+        let preview = get_import_preview_from_db(&pool, import_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(preview.rows.len(), 2);
+        assert_eq!(preview.conflicts.len(), 1);
+        assert_eq!(preview.conflicts[0].row_index, 0);
+        assert_eq!(preview.conflicts[0].task_id, existing.id);
+        assert_eq!(preview.new_clients, vec!["Brand New Client".to_string()]);
+
+        // Act: Commit the import
+        let outcome = commit_import_in_db(&pool, import_id).await.unwrap();
+        let CommitImportOutcome::Committed(tasks) = outcome else {
+            panic!("Import should have been committed, got {outcome:?}");
+        };
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].id, existing.id);
+        assert_eq!(tasks[0].description, "Updated by import");
+
+        let week_tasks = get_current_week_tasks_from_db(&pool).await.unwrap();
+        assert_eq!(week_tasks.len(), 2);
+
+        // Assert: An import can only be committed once
+        assert!(matches!(
+            commit_import_in_db(&pool, import_id).await.unwrap(),
+            CommitImportOutcome::AlreadyCommitted
+        ));
+        assert!(matches!(
+            commit_import_in_db(&pool, import_id + 1).await.unwrap(),
+            CommitImportOutcome::NotFound
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_client_alias_resolves_to_canonical_name() {
+        let pool = setup_test_db().await.unwrap();
+        create_client_in_db(&pool, "ACME").await.unwrap();
+        assert!(add_client_alias_in_db(&pool, "ACME", "Acme Corp")
+            .await
+            .unwrap());
+        // The same alias cannot be registered twice, even with another casing
+        assert!(!add_client_alias_in_db(&pool, "Other", "acme corp")
+            .await
+            .unwrap());
+
+        let task = create_task_in_db(
+            &pool,
+            CreateTaskPayload {
+                client_name: "acme corp".to_string(),
+                description: "Aliased task".to_string(),
+                task_date: Some(Utc::now().date_naive()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(task.client_name, "ACME");
+        assert_eq!(
+            get_known_client_names_from_db(&pool).await.unwrap(),
+            vec!["ACME".to_string()]
+        );
+
+        assert!(delete_client_alias_in_db(&pool, "ACME", "Acme Corp")
+            .await
+            .unwrap());
+        assert!(get_client_aliases_from_db(&pool, "ACME")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_archived_clients_are_hidden_but_keep_their_tasks() {
+        let pool = setup_test_db().await.unwrap();
+        create_task_in_db(
+            &pool,
+            CreateTaskPayload {
+                client_name: "Dead Project".to_string(),
+                description: "Historical task".to_string(),
+                task_date: Some(Utc::now().date_naive()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        add_client_alias_in_db(&pool, "Dead Project", "DP")
+            .await
+            .unwrap();
+
+        set_client_archived_in_db(&pool, "Dead Project", true)
+            .await
+            .unwrap();
+
+        // Assert: Hidden by default, listed on request, resolvable through aliases
+        assert!(get_clients_from_db(&pool, false).await.unwrap().is_empty());
+        assert_eq!(get_clients_from_db(&pool, true).await.unwrap().len(), 1);
+        assert!(get_client_archived_at_from_db(&pool, "DP")
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            get_current_week_tasks_from_db(&pool).await.unwrap().len(),
+            1
+        );
+
+        set_client_archived_in_db(&pool, "Dead Project", false)
+            .await
+            .unwrap();
+        assert_eq!(get_clients_from_db(&pool, false).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_client_stats() {
+        let pool = setup_test_db().await.unwrap();
+        let today = Utc::now().date_naive();
+        let mut ids = Vec::new();
+        for priority in [Some(Priority::Critical), Some(Priority::Critical), None] {
+            let task = create_task_in_db(
+                &pool,
+                CreateTaskPayload {
+                    client_name: "Billed Client".to_string(),
+                    description: "Billable work".to_string(),
+                    task_date: Some(today),
+                    priority,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+            ids.push(task.id);
+        }
+        soft_delete_task_in_db(&pool, ids[0], None).await.unwrap();
+        rollover_tasks_in_db(
+            &pool,
+            RolloverPolicy::NextDay,
+            RolloverTrigger::Manual,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Act
+        let stats = get_client_stats_from_db(
+            &pool,
+            "Billed Client",
+            today,
+            today + chrono::Duration::days(1),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        // Assert
+        assert_eq!(stats.total_tasks, 3);
+        assert_eq!(stats.completed_tasks, 1);
+        assert_eq!(stats.open_tasks, 2);
+        assert!((stats.completion_rate - 1.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(stats.rollover_count, 2);
+        assert_eq!(stats.rolled_over_tasks, 2);
+        assert_eq!(
+            stats.priority_distribution,
+            vec![
+                PriorityCount {
+                    priority: Some(Priority::Critical),
+                    count: 2
+                },
+                PriorityCount {
+                    priority: None,
+                    count: 1
+                },
+            ]
+        );
+
+        // An empty range has no tasks and a zero completion rate
+        let yesterday = today - chrono::Duration::days(1);
+        let empty = get_client_stats_from_db(&pool, "Billed Client", yesterday, yesterday)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(empty.total_tasks, 0);
+        assert_eq!(empty.completion_rate, 0.0);
+
+        assert!(get_client_stats_from_db(&pool, "Nobody", today, today)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_client_columns_are_migrated() {
+        // Arrange: A database with the old name-keyed schema
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::query(
+            r#"
+            CREATE TABLE tasks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                client_name TEXT NOT NULL,
+                description TEXT NOT NULL,
+                task_date DATE NOT NULL,
+                client_color TEXT NOT NULL,
+                created_at TIMESTAMP NOT NULL,
+                deleted_at TIMESTAMP WITH TIME ZONE NULL,
+                priority INTEGER NULL
+            );
+            INSERT INTO tasks (client_name, description, task_date, client_color, created_at)
+                VALUES ('Legacy Client', 'Old task', '2025-07-07', 'bg-red-100', '2025-07-07 09:00:00');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // Act
+        init_schema(&pool).await.unwrap();
+
+        // Assert: The client was registered and the task points at it; its
+        // class name color, which is not a hex value, became grey
+        let client = get_client_from_db(&pool, "Legacy Client")
+            .await
+            .unwrap()
+            .expect("Legacy client should have been backfilled");
+        assert_eq!(client.color, "#7f7f7f");
+        let task = get_task_by_id_from_db(&pool, 1).await.unwrap().unwrap();
+        assert_eq!(task.client_id, client.id);
+        assert_eq!(task.client_name, "Legacy Client");
+        assert_eq!(task.client_color, "#7f7f7f");
+
+        // Running the schema setup again is a no-op
+        init_schema(&pool).await.unwrap();
+        assert_eq!(get_clients_from_db(&pool, true).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_old_priorities_are_brought_into_levels() {
+        // Arrange: A database from before the four levels, with priorities
+        // of any number
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let before = Migrator {
+            migrations: MIGRATOR
+                .iter()
+                .take_while(|migration| migration.version < 21)
+                .cloned()
+                .collect(),
+            ..Migrator::DEFAULT
+        };
+        before.run(&pool).await.unwrap();
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO clients (name, color, created_at, default_priority)
+                VALUES ('Old Client', '#1f77b4', '2025-07-07 09:00:00', 9);
+            INSERT INTO tasks (client_id, description, task_date, created_at, priority)
+                VALUES (1, 'p0', '2025-07-07', '2025-07-07 09:00:00', 0),
+                       (1, 'p2', '2025-07-07', '2025-07-07 09:00:00', 2),
+                       (1, 'p3', '2025-07-07', '2025-07-07 09:00:00', 3),
+                       (1, 'p4', '2025-07-07', '2025-07-07 09:00:00', 4),
+                       (1, 'p5', '2025-07-07', '2025-07-07 09:00:00', 5),
+                       (1, 'p7', '2025-07-07', '2025-07-07 09:00:00', 7),
+                       (1, 'p8', '2025-07-07', '2025-07-07 09:00:00', 8),
+                       (1, 'p10', '2025-07-07', '2025-07-07 09:00:00', 10),
+                       (1, 'none', '2025-07-07', '2025-07-07 09:00:00', NULL);
+            INSERT INTO imports (created_at) VALUES ('2025-07-07 09:00:00');
+            INSERT INTO import_rows (import_id, row_index, client_name, description, priority)
+                VALUES (1, 0, 'Old Client', 'Imported', 6);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // Act
+        init_schema(&pool).await.unwrap();
+
+        // Assert: Ranges of old priorities map to the levels in order
+        let priorities: Vec<(String, Option<i64>)> =
+            sqlx::query_as("SELECT description, priority FROM tasks ORDER BY id")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        let expected = [
+            ("p0", Some(1)),
+            ("p2", Some(1)),
+            ("p3", Some(2)),
+            ("p4", Some(2)),
+            ("p5", Some(3)),
+            ("p7", Some(3)),
+            ("p8", Some(4)),
+            ("p10", Some(4)),
+            ("none", None),
+        ];
+        assert_eq!(
+            priorities,
+            expected.map(|(description, priority)| (description.to_string(), priority))
+        );
+        let default_priority: Option<i64> =
+            sqlx::query_scalar("SELECT default_priority FROM clients")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(default_priority, Some(4));
+        let imported: Option<i64> = sqlx::query_scalar("SELECT priority FROM import_rows")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(imported, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_unversioned_database_is_baselined() {
+        // Arrange: A database created by the inline schema of earlier releases
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::raw_sql(include_str!("../migrations/0001_initial_schema.sql"))
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO clients (name, color, created_at) VALUES ('Kept', '#1f77b4', '2025-07-07 09:00:00')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
 
-        // Append the relative path to your database directory
-        path.push(TEST_TARGET_DIR_PATH);
-        path
+        // Act
+        init_schema(&pool).await.unwrap();
+
+        // Assert: The data is kept and the initial migration is recorded
+        assert!(get_client_from_db(&pool, "Kept").await.unwrap().is_some());
+        let applied: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(applied, MIGRATOR.iter().count() as i64);
     }
 
-    /// Cleans up the test environment.
-    fn teardown_test_env_for_file_cleanup(db_dir: &PathBuf) {
-        if db_dir.exists() {
-            if let Err(e) = fs::remove_dir_all(db_dir) {
-                eprintln!(
-                    "Error: Failed to remove test database directory {:?}: {}",
-                    db_dir, e
-                );
-            }
+    #[tokio::test]
+    async fn test_file_database_handles_concurrent_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}", dir.path().join("sqlite.db").display());
+        let pool = establish_connection_pool(&url, &DatabaseConfig::default())
+            .await
+            .unwrap();
+
+        let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+
+        // Writers queue behind each other instead of failing with "database is locked"
+        let writers: Vec<_> = (0..20)
+            .map(|i| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    create_task_in_db(
+                        &pool,
+                        CreateTaskPayload {
+                            client_name: "Busy Client".to_string(),
+                            description: format!("Task {i}"),
+                            ..Default::default()
+                        },
+                    )
+                    .await
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.await.unwrap().unwrap();
         }
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tasks")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 20);
     }
 
-    /// Helper function to set up an in-memory SQLite database for testing.
-    /// This creates a fresh, empty database for each test, ensuring they are isolated.
-    async fn setup_test_db() -> Result<SqlitePool> {
-        // Use :memory: to create an in-memory database
-        let pool = SqlitePool::connect("sqlite::memory:").await?;
-
-        // Run the same table creation query as the main application
-        sqlx::query(
+    #[tokio::test]
+    async fn test_task_queries_use_indexes() {
+        // Arrange: A year of tasks over 20 clients, half of them done, with
+        // statistics gathered so that the planner sees realistic data
+        let pool = setup_test_db().await.unwrap();
+        sqlx::raw_sql(
             r#"
-            CREATE TABLE IF NOT EXISTS tasks (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                client_name TEXT NOT NULL,
-                description TEXT NOT NULL,
-                task_date DATE NOT NULL,
-                client_color TEXT NOT NULL,
-                created_at TIMESTAMP NOT NULL,
-                deleted_at TIMESTAMP WITH TIME ZONE NULL,
-                priority INTEGER NULL
-            );
+            WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 20)
+            INSERT INTO clients (name, color, created_at)
+            SELECT 'Client ' || i, '#d62728', '2024-01-01T00:00:00Z' FROM n;
+            WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 5000)
+            INSERT INTO tasks (client_id, description, task_date, created_at, deleted_at)
+            SELECT i % 20 + 1, 'Task ' || i, date('2024-01-01', '+' || (i % 365) || ' days'),
+                   '2024-01-01T00:00:00Z', CASE WHEN i % 2 = 0 THEN '2024-06-01T00:00:00Z' END
+            FROM n;
+            ANALYZE;
             "#,
         )
         .execute(&pool)
-        .await?;
-
-        Ok(pool)
-    }
+        .await
+        .unwrap();
 
-    #[tokio::test]
-    async fn test_create_and_get_task() {
-        let pool = setup_test_db().await.unwrap();
-        let today = Utc::now().date_naive();
-        let payload = CreateTaskPayload {
-            client_name: "Test Client".to_string(),
-            description: "Test the database".to_string(),
-            task_date: Some(today),
-            priority: Some(5),
+        let query_plan = |query: String| {
+            let pool = pool.clone();
+            async move {
+                let rows: Vec<(i64, i64, i64, String)> =
+                    sqlx::query_as(&format!("EXPLAIN QUERY PLAN {query}"))
+                        .bind("2024-03-04")
+                        .bind("2024-03-10")
+                        .fetch_all(&pool)
+                        .await
+                        .unwrap();
+                rows.into_iter()
+                    .map(|(_, _, _, detail)| detail)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
         };
 
-        // Act: Create a new task in the test database
-        let created_task = create_task_in_db(&pool, payload).await.unwrap();
+        // Assert: The week range query of the task lists searches an index
+        // (by date, or by client then date) instead of scanning the table
+        let plan = query_plan(format!(
+            "{TASK_SELECT} WHERE t.task_date BETWEEN ? AND ? AND t.deleted_at IS NULL ORDER BY t.id"
+        ))
+        .await;
+        assert!(!plan.lines().any(|step| step == "SCAN t"), "{plan}");
+        assert!(plan.contains("SEARCH t USING INDEX idx_tasks_"), "{plan}");
 
-        // Assert: The created task has the correct data
-        assert_eq!(created_task.client_name, "Test Client");
-        assert_eq!(created_task.description, "Test the database");
-        assert_eq!(created_task.task_date, today);
-        assert_eq!(created_task.priority, Some(5));
-        assert!(created_task.id > 0); // Should have been assigned an ID by the DB
+        // Assert: So do the per-client queries of the statistics
+        let plan = query_plan(
+            "SELECT COUNT(*) FROM tasks WHERE client_id = 1 AND task_date BETWEEN ? AND ?"
+                .to_string(),
+        )
+        .await;
+        assert!(plan.contains("idx_tasks_client_date"), "{plan}");
+    }
 
-        // Act: Retrieve tasks for the current week
-        let week_tasks = get_current_week_tasks_from_db(&pool).await.unwrap();
+    #[tokio::test]
+    async fn test_schema_drift_fails_startup() {
+        let pool = setup_test_db().await.unwrap();
+        sqlx::query("ALTER TABLE tasks DROP COLUMN context")
+            .execute(&pool)
+            .await
+            .unwrap();
 
-        // Assert: The newly created task is in the list
-        assert_eq!(week_tasks.len(), 1);
-        assert_eq!(week_tasks[0].id, created_task.id);
-        assert_eq!(week_tasks[0].priority, Some(5));
+        let error = init_schema(&pool).await.unwrap_err();
 
-        // Call this last to remove the created directory and its contents
-        teardown_test_env_for_file_cleanup(&get_test_data_dir());
+        assert!(
+            error.to_string().contains("'tasks.context' is missing"),
+            "{error}"
+        );
     }
 
     #[tokio::test]
-    async fn test_create_task_without_priority() {
+    async fn test_client_crud() {
         let pool = setup_test_db().await.unwrap();
-        let today = Utc::now().date_naive();
-        let payload = CreateTaskPayload {
-            client_name: "Client No Prio".to_string(),
-            description: "Task without priority".to_string(),
-            task_date: Some(today),
-            priority: None, // No priority
-        };
 
-        let created_task = create_task_in_db(&pool, payload).await.unwrap();
-        assert_eq!(created_task.priority, None); // Assert priority is None
+        let client = create_client_in_db(&pool, "Initech")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(client.color.starts_with('#'));
+        assert!(create_client_in_db(&pool, "Initech")
+            .await
+            .unwrap()
+            .is_none());
 
-        let week_tasks = get_current_week_tasks_from_db(&pool).await.unwrap();
-        assert_eq!(week_tasks.len(), 1);
-        assert_eq!(week_tasks[0].priority, None); // Assert retrieved priority is None
+        // Tasks reuse the client's color
+        let task = create_task_in_db(
+            &pool,
+            CreateTaskPayload {
+                client_name: "Initech".to_string(),
+                description: "TPS reports".to_string(),
+                task_date: Some(Utc::now().date_naive()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(task.client_color, client.color);
+
+        // Renaming carries the tasks along
+        let RenameClientOutcome::Renamed(renamed) =
+            rename_client_in_db(&pool, "Initech", "Initrode")
+                .await
+                .unwrap()
+        else {
+            panic!("Client should have been renamed");
+        };
+        assert_eq!(renamed.color, client.color);
+        let tasks = get_current_week_tasks_from_db(&pool).await.unwrap();
+        assert_eq!(tasks[0].client_name, "Initrode");
+
+        // A client with tasks cannot be deleted, an unused one can
+        assert!(matches!(
+            delete_client_in_db(&pool, "Initrode").await.unwrap(),
+            DeleteClientOutcome::HasTasks(1)
+        ));
+        create_client_in_db(&pool, "Unused").await.unwrap();
+        assert!(matches!(
+            delete_client_in_db(&pool, "Unused").await.unwrap(),
+            DeleteClientOutcome::Deleted
+        ));
+        assert!(matches!(
+            delete_client_in_db(&pool, "Unused").await.unwrap(),
+            DeleteClientOutcome::NotFound
+        ));
     }
 
     #[tokio::test]
@@ -274,7 +4912,8 @@ mod tests {
             client_name: "Client to Delete".to_string(),
             description: "This task will be deleted".to_string(),
             task_date: Some(Utc::now().date_naive()),
-            priority: Some(1),
+            priority: Some(Priority::Critical),
+            ..Default::default()
         };
         let task_to_delete = create_task_in_db(&pool, payload).await.unwrap();
 
@@ -283,18 +4922,66 @@ mod tests {
         assert_eq!(tasks_before_delete.len(), 1);
 
         // Act: Soft delete the task
-        let was_deleted = soft_delete_task_in_db(&pool, task_to_delete.id)
+        let change = soft_delete_task_in_db(&pool, task_to_delete.id, None)
             .await
             .unwrap();
 
         // Assert
-        assert!(was_deleted); // The function should report success.
+        assert!(matches!(change, TaskChange::Changed(_))); // The function should report success.
 
         // Assert: The task is no longer retrieved by the standard query
         let tasks_after_delete = get_current_week_tasks_from_db(&pool).await.unwrap();
         assert_eq!(tasks_after_delete.len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_changes_are_refused_at_a_stale_version() {
+        let pool = setup_test_db().await.unwrap();
+        let payload = CreateTaskPayload {
+            client_name: "Versioned Client".to_string(),
+            description: "Changed twice".to_string(),
+            task_date: Some(Utc::now().date_naive()),
+            ..Default::default()
+        };
+        let task = create_task_in_db(&pool, payload).await.unwrap();
+        assert_eq!(task.updated_at, task.created_at);
+
+        // Act: Pin it at the version read, which moves the version on
+        let TaskChange::Changed(pinned) =
+            set_task_pinned_in_db(&pool, task.id, true, Some(task.updated_at))
+                .await
+                .unwrap()
+        else {
+            panic!("the task should be pinned at its current version");
+        };
+        assert!(pinned.updated_at > task.updated_at);
+
+        // Assert: The version first read is now stale, for every change
+        let stale = Some(task.updated_at);
+        assert!(matches!(
+            set_task_rollover_target_in_db(&pool, task.id, None, stale)
+                .await
+                .unwrap(),
+            TaskChange::Stale
+        ));
+        assert!(matches!(
+            move_task_in_db(&pool, task.id, task.task_date, stale)
+                .await
+                .unwrap(),
+            MoveTaskOutcome::Stale
+        ));
+        assert!(matches!(
+            soft_delete_task_in_db(&pool, task.id, stale).await.unwrap(),
+            TaskChange::Stale
+        ));
+        assert!(matches!(
+            soft_delete_task_in_db(&pool, task.id, Some(pinned.updated_at))
+                .await
+                .unwrap(),
+            TaskChange::Changed(_)
+        ));
+    }
+
     #[tokio::test]
     async fn test_rollover_tasks() {
         let pool = setup_test_db().await.unwrap();
@@ -306,7 +4993,8 @@ mod tests {
             client_name: "Rollover Client".to_string(),
             description: "A task for today".to_string(),
             task_date: Some(today),
-            priority: Some(10),
+            priority: Some(Priority::Low),
+            ..Default::default()
         };
         create_task_in_db(&pool, payload_today).await.unwrap();
 
@@ -316,25 +5004,314 @@ mod tests {
             client_name: "Other Client".to_string(),
             description: "A task from another day".to_string(),
             task_date: Some(other_date),
-            priority: Some(20),
+            priority: Some(Priority::Low),
+            ..Default::default()
         };
         create_task_in_db(&pool, payload_other).await.unwrap();
 
+        // Assert: The preview lists the task without moving it
+        let preview = preview_rollover_in_db(&pool, RolloverPolicy::NextDay)
+            .await
+            .unwrap();
+        assert_eq!(preview.from_date, today);
+        assert_eq!(preview.to_date, tomorrow);
+        assert_eq!(preview.tasks.len(), 1);
+        assert_eq!(preview.tasks[0].task_date, today);
+
         // Act: Run the rollover function
-        let num_rolled_over = rollover_tasks_in_db(&pool).await.unwrap();
+        let num_rolled_over = rollover_tasks_in_db(
+            &pool,
+            RolloverPolicy::NextDay,
+            RolloverTrigger::Manual,
+            None,
+        )
+        .await
+        .unwrap();
 
         // Assert: Exactly one task should have been rolled over
         assert_eq!(num_rolled_over, 1);
 
         // Assert: The task's date is now tomorrow
         let tasks: Vec<Task> =
-            sqlx::query_as("SELECT * FROM tasks WHERE client_name = 'Rollover Client'")
+            sqlx::query_as(&format!("{TASK_SELECT} WHERE c.name = 'Rollover Client'"))
                 .fetch_all(&pool)
                 .await
                 .unwrap();
         assert_eq!(tasks.len(), 1);
         assert_eq!(tasks[0].task_date, tomorrow);
-        assert_eq!(tasks[0].priority, Some(10));
+        assert_eq!(tasks[0].priority, Some(Priority::Low));
+
+        // Assert: The run is recorded, and the task counts as postponed once
+        let history = get_rollover_history_from_db(&pool, 10, 1).await.unwrap();
+        assert_eq!(history.runs.len(), 1);
+        assert_eq!(history.runs[0].triggered_by, RolloverTrigger::Manual);
+        assert_eq!(history.runs[0].from_date, today);
+        assert_eq!(history.runs[0].to_date, tomorrow);
+        assert_eq!(history.runs[0].task_count, 1);
+        assert_eq!(history.postponed_tasks.len(), 1);
+        assert_eq!(history.postponed_tasks[0].id, tasks[0].id);
+        assert_eq!(history.postponed_tasks[0].rollover_count, 1);
+        let history = get_rollover_history_from_db(&pool, 10, 2).await.unwrap();
+        assert!(history.postponed_tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rollover_escalates_stale_tasks() {
+        let pool = setup_test_db().await.unwrap();
+        let today = Utc::now().date_naive();
+
+        let postponed = create_task_in_db(
+            &pool,
+            CreateTaskPayload {
+                client_name: "Stale Client".to_string(),
+                description: "Postponed once already".to_string(),
+                task_date: Some(today),
+                priority: Some(Priority::Medium),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        sqlx::query("UPDATE tasks SET rollover_count = 1 WHERE id = ?")
+            .bind(postponed.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        create_task_in_db(
+            &pool,
+            CreateTaskPayload {
+                client_name: "Stale Client".to_string(),
+                description: "Never postponed".to_string(),
+                task_date: Some(today),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        // Act: The second rollover of the first task reaches the threshold
+        rollover_tasks_in_db(
+            &pool,
+            RolloverPolicy::NextDay,
+            RolloverTrigger::Manual,
+            Some(2),
+        )
+        .await
+        .unwrap();
+
+        // Assert: Only that task is marked stale, one priority level higher
+        let stale = get_stale_tasks_from_db(&pool).await.unwrap();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].id, postponed.id);
+        assert_eq!(stale[0].priority, Some(Priority::High));
+        assert_eq!(stale[0].rollover_count, 2);
+        assert!(stale[0].stale_since.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_job_claimed_once() {
+        let pool = setup_test_db().await.unwrap();
+        let slot = Utc::now();
+
+        assert!(claim_scheduled_job(&pool, "rollover", slot, "a")
+            .await
+            .unwrap());
+        // Another instance, or the same one again, finds it taken
+        assert!(!claim_scheduled_job(&pool, "rollover", slot, "b")
+            .await
+            .unwrap());
+        assert!(!claim_scheduled_job(&pool, "rollover", slot, "a")
+            .await
+            .unwrap());
+        // Other jobs and other occurrences are claimed separately
+        assert!(claim_scheduled_job(&pool, "backup", slot, "b")
+            .await
+            .unwrap());
+        let next = slot + Duration::days(1);
+        assert!(claim_scheduled_job(&pool, "rollover", next, "b")
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_catch_up_rollover_after_downtime() {
+        let pool = setup_test_db().await.unwrap();
+        let today = clock::today();
+        let mut ids = Vec::new();
+        for (days_ago, pin_to_date) in [(3, false), (1, false), (2, true), (3, false)] {
+            let payload = CreateTaskPayload {
+                client_name: "Catch-up Client".to_string(),
+                description: format!("{days_ago} days ago"),
+                task_date: Some(today - Duration::days(days_ago)),
+                pin_to_date,
+                ..Default::default()
+            };
+            ids.push(create_task_in_db(&pool, payload).await.unwrap().id);
+        }
+        soft_delete_task_in_db(&pool, ids[3], None).await.unwrap();
+
+        // Act: Catch up on three missed days
+        let steps = catch_up_rollover_in_db(&pool, RolloverPolicy::NextDay, None)
+            .await
+            .unwrap();
+
+        // Assert: One step per missed day, the tasks accumulating on the way
+        let counts: Vec<usize> = steps.iter().map(|(_, _, count)| *count).collect();
+        assert_eq!(counts, [1, 1, 2]);
+        assert_eq!(steps[2].1, today);
+        let dated = |id: i64| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query_as::<_, (NaiveDate, i64)>(
+                    "SELECT task_date, rollover_count FROM tasks WHERE id = ?",
+                )
+                .bind(id)
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+            }
+        };
+        assert_eq!(dated(ids[0]).await, (today, 3));
+        assert_eq!(dated(ids[1]).await, (today, 1));
+        // Pinned and done tasks stay where they are
+        assert_eq!(dated(ids[2]).await, (today - Duration::days(2), 0));
+        assert_eq!(dated(ids[3]).await, (today - Duration::days(3), 0));
+
+        // Assert: Nothing is left to catch up on
+        let steps = catch_up_rollover_in_db(&pool, RolloverPolicy::NextDay, None)
+            .await
+            .unwrap();
+        assert!(steps.is_empty());
+    }
+
+    #[test]
+    fn test_rollover_target_skips_days_off() {
+        let friday = NaiveDate::from_ymd_opt(2025, 7, 18).unwrap();
+        let saturday = NaiveDate::from_ymd_opt(2025, 7, 19).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2025, 7, 21).unwrap();
+        let tuesday = NaiveDate::from_ymd_opt(2025, 7, 22).unwrap();
+        let holidays = HashSet::from([monday]);
+
+        assert_eq!(
+            rollover_target(friday, RolloverPolicy::NextDay, &holidays),
+            Some(saturday)
+        );
+        assert_eq!(
+            rollover_target(friday, RolloverPolicy::BusinessDay, &holidays),
+            Some(monday)
+        );
+        assert_eq!(
+            rollover_target(friday, RolloverPolicy::BusinessDayHolidays, &holidays),
+            Some(tuesday)
+        );
+        // From a weekend day, the business-day policies still land on Monday
+        assert_eq!(
+            rollover_target(saturday, RolloverPolicy::BusinessDay, &HashSet::new()),
+            Some(monday)
+        );
+    }
+
+    #[test]
+    fn test_task_rollover_target_overrides_policy() {
+        let wednesday = NaiveDate::from_ymd_opt(2025, 7, 16).unwrap();
+        let friday = NaiveDate::from_ymd_opt(2025, 7, 18).unwrap();
+        let saturday = NaiveDate::from_ymd_opt(2025, 7, 19).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2025, 7, 21).unwrap();
+        let tuesday = NaiveDate::from_ymd_opt(2025, 7, 22).unwrap();
+        let holidays = HashSet::from([monday]);
+        let target = |from, target| task_rollover_target(from, target, &holidays).unwrap();
+
+        assert_eq!(target(friday, RolloverTarget::NextDay), saturday);
+        assert_eq!(target(friday, RolloverTarget::NextBusinessDay), tuesday);
+        assert_eq!(target(wednesday, RolloverTarget::Monday), monday);
+        // The day named is always a later one, a week later from that day
+        assert_eq!(
+            target(monday, RolloverTarget::Monday),
+            monday + Duration::days(7)
+        );
+        assert_eq!(target(wednesday, RolloverTarget::EndOfWeek), friday);
+        assert_eq!(
+            target(friday, RolloverTarget::EndOfWeek),
+            friday + Duration::days(7)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rollover_honors_task_targets() {
+        let pool = setup_test_db().await.unwrap();
+        let today = clock::today();
+        let next_monday = today
+            .iter_days()
+            .skip(1)
+            .find(|day| day.weekday() == Weekday::Mon)
+            .unwrap();
+        let mut ids = Vec::new();
+        for (days_ago, rollover_target) in [
+            (0, None),
+            (0, Some(RolloverTarget::Monday)),
+            (3, Some(RolloverTarget::NextDay)),
+        ] {
+            let payload = CreateTaskPayload {
+                client_name: "Target Client".to_string(),
+                description: format!("{rollover_target:?}"),
+                task_date: Some(today - Duration::days(days_ago)),
+                rollover_target,
+                ..Default::default()
+            };
+            ids.push(create_task_in_db(&pool, payload).await.unwrap().id);
+        }
+        let date_of = |id: i64| {
+            let pool = pool.clone();
+            async move {
+                get_task_by_id_from_db(&pool, id)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .task_date
+            }
+        };
+
+        // Act: Catch up, then preview and run the rollover of today
+        let steps = catch_up_rollover_in_db(&pool, RolloverPolicy::BusinessDay, None)
+            .await
+            .unwrap();
+        let preview = preview_rollover_in_db(&pool, RolloverPolicy::NextDay)
+            .await
+            .unwrap();
+        let moved = rollover_tasks_in_db(
+            &pool,
+            RolloverPolicy::NextDay,
+            RolloverTrigger::Manual,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Assert: The catch-up moves the late task day by day whatever the policy
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[2].1, today);
+        assert_eq!(preview.task_dates.get(&ids[1]), Some(&next_monday));
+        assert_eq!(preview.task_dates.get(&ids[2]), Some(&preview.to_date));
+        assert!(!preview.task_dates.contains_key(&ids[0]));
+        assert_eq!(moved, 3);
+        assert_eq!(date_of(ids[0]).await, today.succ_opt().unwrap());
+        assert_eq!(date_of(ids[1]).await, next_monday);
+        assert_eq!(date_of(ids[2]).await, today.succ_opt().unwrap());
+
+        // Assert: Cleared, the target gives way to the policy again
+        let TaskChange::Changed(task) = set_task_rollover_target_in_db(&pool, ids[1], None, None)
+            .await
+            .unwrap()
+        else {
+            panic!("the target should be cleared");
+        };
+        assert_eq!(task.rollover_target, None);
+        assert!(matches!(
+            set_task_rollover_target_in_db(&pool, 0, None, None)
+                .await
+                .unwrap(),
+            TaskChange::NotFound
+        ));
     }
 
     #[tokio::test]
@@ -349,7 +5326,8 @@ mod tests {
                 client_name: "Client A".to_string(),
                 description: "Task Low Prio".to_string(),
                 task_date: Some(today),
-                priority: Some(10),
+                priority: Some(Priority::Low),
+                ..Default::default()
             },
         )
         .await
@@ -361,7 +5339,8 @@ mod tests {
                 client_name: "Client B".to_string(),
                 description: "Task High Prio".to_string(),
                 task_date: Some(today),
-                priority: Some(1),
+                priority: Some(Priority::Critical),
+                ..Default::default()
             },
         )
         .await
@@ -373,7 +5352,8 @@ mod tests {
                 client_name: "Client C".to_string(),
                 description: "Task Medium Prio".to_string(),
                 task_date: Some(today),
-                priority: Some(5),
+                priority: Some(Priority::Medium),
+                ..Default::default()
             },
         )
         .await
@@ -386,6 +5366,7 @@ mod tests {
                 description: "Task No Prio".to_string(),
                 task_date: Some(today),
                 priority: None, // No priority
+                ..Default::default()
             },
         )
         .await
@@ -403,15 +5384,15 @@ mod tests {
         // Filter to only tasks for today to test priority ordering directly
         let today_tasks: Vec<Task> = tasks.into_iter().filter(|t| t.task_date == today).collect();
 
-        // Sort order should be: priority 1, priority 5, priority 10, None
+        // Sort order should be: critical, medium, low, None
         assert_eq!(today_tasks.len(), 4); // Client A, C, D (all for today)
-        assert_eq!(today_tasks[0].priority, Some(1)); // Client B, if it was for today.
-        assert_eq!(today_tasks[0].description, "Task High Prio".to_string()); // This task has priority 1
-        assert_eq!(today_tasks[1].priority, Some(5)); // This task has priority 5
-        assert_eq!(today_tasks[2].priority, Some(10)); // This task has priority 10
-                                                       // The task with None priority will be last if there are other tasks with None priority.
-                                                       // In this case, `NULLS LAST` will put it after 10.
-                                                       // Let's refine the test to make sure only tasks for today are considered and their order.
+        assert_eq!(today_tasks[0].priority, Some(Priority::Critical)); // Client B, if it was for today.
+        assert_eq!(today_tasks[0].description, "Task High Prio".to_string()); // This task is of critical priority
+        assert_eq!(today_tasks[1].priority, Some(Priority::Medium)); // This task is of medium priority
+        assert_eq!(today_tasks[2].priority, Some(Priority::Low)); // This task is of low priority
+                                                                  // The task with None priority will be last if there are other tasks with None priority.
+                                                                  // In this case, `NULLS LAST` will put it after 10.
+                                                                  // Let's refine the test to make sure only tasks for today are considered and their order.
 
         // To properly test the priority order, we should ensure all tasks are for the same date
         // and then check their relative order.
@@ -419,6 +5400,55 @@ mod tests {
         // Let's create a new test that specifically checks the priority order for tasks on the same date.
     }
 
+    #[test]
+    fn test_build_order_by() {
+        assert_eq!(
+            build_order_by("priority,-created_at").unwrap(),
+            "t.priority ASC NULLS LAST, t.created_at DESC"
+        );
+        assert_eq!(build_order_by("").unwrap(), DEFAULT_ORDER_BY);
+        assert_eq!(build_order_by("+client_name").unwrap(), "c.name ASC");
+
+        // Unknown columns and injection attempts are rejected
+        assert_eq!(build_order_by("id,password").unwrap_err(), "password");
+        assert!(build_order_by("id; DROP TABLE tasks").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_tasks_with_custom_sort() {
+        let pool = setup_test_db().await.unwrap();
+        let today = Utc::now().date_naive();
+
+        for (client_name, priority) in [
+            ("Client A", Some(Priority::Critical)),
+            ("Client B", Some(Priority::Medium)),
+            ("Client C", None),
+        ] {
+            create_task_in_db(
+                &pool,
+                CreateTaskPayload {
+                    client_name: client_name.to_string(),
+                    description: "Sorted task".to_string(),
+                    task_date: Some(today),
+                    priority,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let order_by = build_order_by("-priority").unwrap();
+        let tasks = get_current_week_tasks_sorted_from_db(&pool, &order_by)
+            .await
+            .unwrap();
+
+        // Descending priority, with the task lacking a priority still last
+        assert_eq!(tasks[0].client_name, "Client B");
+        assert_eq!(tasks[1].client_name, "Client A");
+        assert_eq!(tasks[2].client_name, "Client C");
+    }
+
     #[tokio::test]
     async fn test_priority_ordering_on_same_date() {
         let pool = setup_test_db().await.unwrap();
@@ -431,7 +5461,8 @@ mod tests {
                 client_name: "Client C".to_string(),
                 description: "Task Medium Prio".to_string(),
                 task_date: Some(today),
-                priority: Some(5),
+                priority: Some(Priority::Medium),
+                ..Default::default()
             },
         )
         .await
@@ -443,7 +5474,8 @@ mod tests {
                 client_name: "Client A".to_string(),
                 description: "Task Low Prio".to_string(),
                 task_date: Some(today),
-                priority: Some(10),
+                priority: Some(Priority::Low),
+                ..Default::default()
             },
         )
         .await
@@ -455,7 +5487,8 @@ mod tests {
                 client_name: "Client B".to_string(),
                 description: "Task High Prio".to_string(),
                 task_date: Some(today),
-                priority: Some(1),
+                priority: Some(Priority::Critical),
+                ..Default::default()
             },
         )
         .await
@@ -468,6 +5501,7 @@ mod tests {
                 description: "Task No Prio".to_string(),
                 task_date: Some(today),
                 priority: None,
+                ..Default::default()
             },
         )
         .await
@@ -483,4 +5517,86 @@ mod tests {
         assert_eq!(tasks[2].description, "Task Low Prio"); // Priority 10
         assert_eq!(tasks[3].description, "Task No Prio"); // Priority None (NULLS LAST)
     }
+
+    #[tokio::test]
+    async fn test_doctor_finds_and_fixes_inconsistencies() {
+        let pool = setup_test_db().await.unwrap();
+        let today = Utc::now().date_naive();
+        let task = create_task_in_db(
+            &pool,
+            CreateTaskPayload {
+                client_name: "Doctor Client".to_string(),
+                description: "Split over two days".to_string(),
+                task_date: Some(today),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        // What a crash during a write, with foreign keys off, could leave behind
+        let mut conn = pool.acquire().await.unwrap();
+        for statement in [
+            "PRAGMA foreign_keys = OFF",
+            "INSERT INTO task_slots (task_id, slot_date, created_at, completed_at) VALUES (999, '2025-07-14', '2025-07-14T09:00:00Z', NULL)",
+            "INSERT INTO client_aliases (alias, client_id) VALUES ('ghost', 999)",
+            "INSERT INTO tasks (client_id, description, task_date, created_at) VALUES (999, 'Lost client', '2025-07-14', '2025-07-14T09:00:00Z')",
+            "PRAGMA foreign_keys = ON",
+        ] {
+            sqlx::query(statement).execute(&mut *conn).await.unwrap();
+        }
+        drop(conn);
+        sqlx::query("INSERT INTO task_slots (task_id, slot_date, created_at, completed_at) VALUES (?, '2025-07-14', '2025-07-14T09:00:00Z', '2025-07-14T17:00:00Z')")
+            .bind(task.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let found = |report: &crate::doctor::DoctorReport, inconsistency| {
+            report
+                .findings
+                .iter()
+                .find(|f| f.inconsistency == inconsistency)
+                .map(|f| (f.found, f.fixed))
+                .unwrap()
+        };
+
+        let report = crate::doctor::check(&pool, false).await.unwrap();
+        assert!(!report.is_healthy());
+        assert_eq!(found(&report, Inconsistency::SlotsWithoutTask), (1, 0));
+        assert_eq!(found(&report, Inconsistency::AliasesWithoutClient), (1, 0));
+        assert_eq!(found(&report, Inconsistency::TasksWithoutClient), (1, 0));
+        assert_eq!(
+            found(&report, Inconsistency::OpenTasksWithAllSlotsDone),
+            (1, 0)
+        );
+        assert_eq!(
+            found(&report, Inconsistency::ClientsMissingFromColorStore),
+            (0, 0)
+        );
+
+        let report = crate::doctor::check(&pool, true).await.unwrap();
+        assert_eq!(found(&report, Inconsistency::SlotsWithoutTask), (1, 1));
+        assert_eq!(
+            found(&report, Inconsistency::OpenTasksWithAllSlotsDone),
+            (1, 1)
+        );
+        // The task of an unknown client needs a manual fix
+        assert_eq!(found(&report, Inconsistency::TasksWithoutClient), (1, 0));
+        assert!(!report.is_healthy());
+
+        let completed = get_task_by_id_from_db(&pool, task.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            completed.deleted_at.map(|d| d.to_rfc3339()),
+            Some("2025-07-14T17:00:00+00:00".to_string())
+        );
+        let report = crate::doctor::check(&pool, false).await.unwrap();
+        assert_eq!(found(&report, Inconsistency::SlotsWithoutTask), (0, 0));
+        assert_eq!(found(&report, Inconsistency::AliasesWithoutClient), (0, 0));
+
+        teardown_test_env_for_file_cleanup(&get_test_data_dir());
+    }
 }
@@ -0,0 +1,152 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::{colors, database};
+use anyhow::Result;
+use common::HexColor;
+use sqlx::SqlitePool;
+use std::fmt;
+
+/// A kind of inconsistent data, e.g. left behind by a crash during a write
+/// or by foreign keys not being enforced at the time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// Tasks whose client does not exist. Not fixable: the client is unknown.
+    TasksWithoutClient,
+    /// Slots of a task that does not exist. Fixed by removing them.
+    SlotsWithoutTask,
+    /// Aliases of a client that does not exist. Fixed by removing them.
+    AliasesWithoutClient,
+    /// Staged rows of an import that does not exist. Fixed by removing them.
+    ImportRowsWithoutImport,
+    /// Color changes of a client that does not exist. Fixed by removing them.
+    ColorChangesWithoutClient,
+    /// Open tasks whose slots are all done. Fixed by marking the task done
+    /// when its last slot was.
+    OpenTasksWithAllSlotsDone,
+    /// Done tasks with slots still open. Fixed by marking the slots done
+    /// when the task was.
+    DoneTasksWithOpenSlots,
+    /// Clients whose color is missing from, or differs in, the color
+    /// assignment store. Fixed by storing the color of the client.
+    ClientsMissingFromColorStore,
+}
+
+impl Inconsistency {
+    pub const ALL: [Inconsistency; 8] = [
+        Inconsistency::TasksWithoutClient,
+        Inconsistency::SlotsWithoutTask,
+        Inconsistency::AliasesWithoutClient,
+        Inconsistency::ImportRowsWithoutImport,
+        Inconsistency::ColorChangesWithoutClient,
+        Inconsistency::OpenTasksWithAllSlotsDone,
+        Inconsistency::DoneTasksWithOpenSlots,
+        Inconsistency::ClientsMissingFromColorStore,
+    ];
+
+    pub fn is_fixable(self) -> bool {
+        self != Inconsistency::TasksWithoutClient
+    }
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Inconsistency::TasksWithoutClient => "tasks without client",
+            Inconsistency::SlotsWithoutTask => "slots without task",
+            Inconsistency::AliasesWithoutClient => "aliases without client",
+            Inconsistency::ImportRowsWithoutImport => "import rows without import",
+            Inconsistency::ColorChangesWithoutClient => "color changes without client",
+            Inconsistency::OpenTasksWithAllSlotsDone => "open tasks with all slots done",
+            Inconsistency::DoneTasksWithOpenSlots => "done tasks with open slots",
+            Inconsistency::ClientsMissingFromColorStore => "clients missing from color store",
+        })
+    }
+}
+
+/// Number of rows found, and fixed, for one kind of inconsistency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Finding {
+    pub inconsistency: Inconsistency,
+    pub found: u64,
+    pub fixed: u64,
+}
+
+/// Outcome of a check, with a line per kind of inconsistency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoctorReport {
+    pub findings: Vec<Finding>,
+}
+
+impl DoctorReport {
+    /// Whether inconsistencies remain once the fixes, if any, were applied.
+    pub fn is_healthy(&self) -> bool {
+        self.findings.iter().all(|f| f.found == f.fixed)
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for finding in &self.findings {
+            write!(f, "{:<34} {:>6}", finding.inconsistency, finding.found)?;
+            if finding.fixed > 0 {
+                write!(f, "  ({} fixed)", finding.fixed)?;
+            } else if finding.found > 0 && !finding.inconsistency.is_fixable() {
+                write!(f, "  (needs a manual fix)")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Looks for every kind of inconsistency, and fixes the fixable ones when
+/// `fix` is set.
+pub async fn check(pool: &SqlitePool, fix: bool) -> Result<DoctorReport> {
+    let mut findings = Vec::new();
+    for inconsistency in Inconsistency::ALL {
+        let found = count(pool, inconsistency).await?;
+        let fixed = if fix && found > 0 && inconsistency.is_fixable() {
+            repair(pool, inconsistency).await?
+        } else {
+            0
+        };
+        findings.push(Finding {
+            inconsistency,
+            found,
+            fixed,
+        });
+    }
+    Ok(DoctorReport { findings })
+}
+
+async fn count(pool: &SqlitePool, inconsistency: Inconsistency) -> Result<u64> {
+    if inconsistency == Inconsistency::ClientsMissingFromColorStore {
+        return Ok(clients_missing_from_color_store(pool).await?.len() as u64);
+    }
+    database::count_inconsistent_rows(pool, inconsistency).await
+}
+
+async fn repair(pool: &SqlitePool, inconsistency: Inconsistency) -> Result<u64> {
+    if inconsistency == Inconsistency::ClientsMissingFromColorStore {
+        let clients = clients_missing_from_color_store(pool).await?;
+        for (name, color) in &clients {
+            colors::set_client_color(name, color);
+        }
+        return Ok(clients.len() as u64);
+    }
+    database::repair_inconsistent_rows(pool, inconsistency).await
+}
+
+/// Names and colors of the clients whose color is not the one in the store.
+async fn clients_missing_from_color_store(pool: &SqlitePool) -> Result<Vec<(String, HexColor)>> {
+    Ok(database::get_clients_from_db(pool, true)
+        .await?
+        .into_iter()
+        .filter(|client| {
+            colors::assigned_client_color(&client.name).as_ref() != Some(&client.color)
+        })
+        .map(|client| (client.name, client.color))
+        .collect())
+}
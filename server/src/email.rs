@@ -0,0 +1,339 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::{DigestPeriod, EmailConfig, SmtpTls};
+use crate::jobs::Job;
+use crate::summary::{self, Locale};
+use crate::{clock, database, scheduler};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Days, NaiveDate, Utc, Weekday};
+use common::Task;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+use tracing::info;
+
+/// Time the SMTP server has to answer each command.
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sends mail through the configured SMTP server.
+#[derive(Clone)]
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    recipients: Vec<Mailbox>,
+}
+
+impl Mailer {
+    pub fn from_config(config: &EmailConfig) -> Result<Self> {
+        let builder = match config.tls {
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host),
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host),
+            SmtpTls::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                &config.host,
+            )),
+        }
+        .with_context(|| format!("Invalid SMTP server '{}'", config.host))?;
+        let mut builder = builder.port(config.port).timeout(Some(SMTP_TIMEOUT));
+        if let Some((username, password)) = &config.credentials {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        Ok(Mailer {
+            transport: builder.build(),
+            from: config.from.clone(),
+            recipients: config.recipients.clone(),
+        })
+    }
+
+    pub fn recipients(&self) -> &[Mailbox] {
+        &self.recipients
+    }
+
+    /// Connects and signs in to the server without sending anything, failing
+    /// with the reason the server gave.
+    pub async fn verify(&self) -> Result<()> {
+        if !self
+            .transport
+            .test_connection()
+            .await
+            .context("SMTP connection failed")?
+        {
+            return Err(anyhow!("The SMTP server did not answer"));
+        }
+        Ok(())
+    }
+
+    /// Sends a message with an HTML body and its plain text version to the
+    /// recipients.
+    pub async fn send(&self, subject: &str, html: String, text: String) -> Result<()> {
+        let mut message = Message::builder().from(self.from.clone());
+        for recipient in &self.recipients {
+            message = message.to(recipient.clone());
+        }
+        let message = message
+            .subject(subject)
+            .multipart(MultiPart::alternative_plain_html(text, html))
+            .context("Invalid email")?;
+        self.transport
+            .send(message)
+            .await
+            .context("Failed to send email")?;
+        Ok(())
+    }
+}
+
+/// Escapes text for HTML content and attribute values.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Subject of the digest of `period` sent on `day`.
+fn digest_subject(period: DigestPeriod, locale: Locale, day: NaiveDate, count: usize) -> String {
+    let week_start = day.week(Weekday::Mon).first_day();
+    match (period, locale) {
+        (DigestPeriod::Day, Locale::En) => {
+            format!("Open tasks for {} ({count})", locale.long_date(day))
+        }
+        (DigestPeriod::Day, Locale::Fr) => {
+            format!("Tâches ouvertes du {} ({count})", locale.long_date(day))
+        }
+        (DigestPeriod::Week, Locale::En) => format!(
+            "Open tasks for the week of {} ({count})",
+            locale.date(week_start)
+        ),
+        (DigestPeriod::Week, Locale::Fr) => format!(
+            "Tâches ouvertes de la semaine du {} ({count})",
+            locale.date(week_start)
+        ),
+    }
+}
+
+/// Renders the digest of `tasks` as HTML and as plain text in `locale`,
+/// grouped by client in alphabetical order. Weekly digests give the day of
+/// each task, and open with `summary`, that of the week before, when it was
+/// archived.
+pub fn render_digest(
+    period: DigestPeriod,
+    locale: Locale,
+    day: NaiveDate,
+    tasks: &[Task],
+    summary: Option<&str>,
+) -> (String, String) {
+    let mut by_client: BTreeMap<&str, Vec<&Task>> = BTreeMap::new();
+    for task in tasks {
+        by_client.entry(&task.client_name).or_default().push(task);
+    }
+    let title = digest_subject(period, locale, day, tasks.len());
+
+    let mut html = String::new();
+    let mut text = format!("{title}\n");
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html><body style=\"font-family: sans-serif\">\n<h1>{}</h1>\n",
+        escape_html(&title)
+    );
+    if let Some(summary) = summary {
+        let heading = match locale {
+            Locale::En => "Last week",
+            Locale::Fr => "La semaine dernière",
+        };
+        let _ = writeln!(html, "<h2>{heading}</h2>\n<p>{}</p>", escape_html(summary));
+        let _ = write!(text, "\n{heading}\n{summary}\n");
+    }
+    for (client, tasks) in by_client {
+        let first = tasks[0];
+        let _ = writeln!(
+            html,
+            "<h2><span style=\"background: {}; color: {}; padding: 2px 8px; border-radius: 4px\">{}</span> {}</h2>\n<ul>",
+            escape_html(&first.client_color),
+            escape_html(&first.client_text_color),
+            escape_html(client),
+            tasks.len()
+        );
+        let _ = write!(text, "\n{client} ({})\n", tasks.len());
+        for task in tasks {
+            let mut details = Vec::new();
+            if period == DigestPeriod::Week {
+                details.push(locale.short_date(task.task_date));
+            }
+            if let Some(priority) = task.priority {
+                details.push(match locale {
+                    Locale::En => format!("priority {}", priority.level()),
+                    Locale::Fr => format!("priorité {}", priority.level()),
+                });
+            }
+            let details = if details.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", details.join(", "))
+            };
+            let _ = writeln!(
+                html,
+                "<li>{}{}</li>",
+                escape_html(&task.description),
+                escape_html(&details)
+            );
+            let _ = writeln!(text, "- {}{details}", task.description);
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</body></html>\n");
+    (html, text)
+}
+
+/// The email digest: the open tasks of the day or the week, by client, sent
+/// at each occurrence of the configured cron expression. Nothing is sent
+/// when there are none. Weekly digests open with the summary of the week
+/// before, once archived.
+pub struct EmailDigestJob {
+    pub mailer: Mailer,
+    pub schedule: croner::Cron,
+    pub period: DigestPeriod,
+    pub locale: Locale,
+}
+
+impl Job for EmailDigestJob {
+    fn name(&self) -> &'static str {
+        "email-digest"
+    }
+
+    fn next_run(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        scheduler::next_run(&self.schedule, after, clock::timezone())
+    }
+
+    async fn run(&self, pool: &SqlitePool) -> Result<String> {
+        let day = clock::today();
+        let tasks = match self.period {
+            DigestPeriod::Day => database::get_open_tasks_for_day_from_db(pool, day, None).await?,
+            DigestPeriod::Week => database::get_current_week_tasks_from_db(pool).await?,
+        };
+        if tasks.is_empty() {
+            return Ok(format!(
+                "No open tasks this {}, no email digest sent.",
+                self.period.as_str()
+            ));
+        }
+        let summary = match self.period {
+            DigestPeriod::Day => None,
+            DigestPeriod::Week => {
+                let last_week = (day - Days::new(7)).format("%G-W%V").to_string();
+                database::get_week_archive_from_db(pool, &last_week)
+                    .await?
+                    .map(|archive| summary::week_summary(&archive, self.locale))
+            }
+        };
+        let subject = digest_subject(self.period, self.locale, day, tasks.len());
+        let (html, text) = render_digest(self.period, self.locale, day, &tasks, summary.as_deref());
+        self.mailer.send(&subject, html, text).await?;
+        info!("Email digest of {} tasks sent.", tasks.len());
+        Ok(format!(
+            "Sent the email digest of {} tasks to {} recipients.",
+            tasks.len(),
+            self.mailer.recipients().len()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::Priority;
+
+    fn task(client: &str, description: &str, day: NaiveDate, priority: Option<Priority>) -> Task {
+        Task {
+            id: 1,
+            client_id: 1,
+            client_name: client.to_string(),
+            description: description.to_string(),
+            task_date: day,
+            client_color: "#0077cc".parse().unwrap(),
+            client_text_color: "#ffffff".to_string(),
+            client_background_color: "#cce4f5".to_string(),
+            created_at: Utc::now(),
+            deleted_at: None,
+            updated_at: Utc::now(),
+            priority,
+            external_source: None,
+            external_id: None,
+            energy: None,
+            estimate_minutes: None,
+            context: None,
+            pin_to_date: false,
+            rollover_target: None,
+            rollover_count: 0,
+            stale_since: None,
+            slots_total: 0,
+            slots_done: 0,
+            deferred: false,
+            display: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_digest_is_grouped_by_client() {
+        let monday = NaiveDate::from_ymd_opt(2025, 7, 14).unwrap();
+        let tuesday = NaiveDate::from_ymd_opt(2025, 7, 15).unwrap();
+        let tasks = [
+            task("Globex", "Call <back>", monday, None),
+            task("ACME", "Write report", tuesday, Some(Priority::Critical)),
+            task("Globex", "Send invoice", tuesday, None),
+        ];
+
+        let (html, text) = render_digest(DigestPeriod::Week, Locale::En, tuesday, &tasks, None);
+        assert_eq!(
+            text,
+            "Open tasks for the week of 14 July 2025 (3)\n\
+             \nACME (1)\n- Write report (Tue 15 Jul, priority 1)\n\
+             \nGlobex (2)\n- Call <back> (Mon 14 Jul)\n- Send invoice (Tue 15 Jul)\n"
+        );
+        assert!(
+            html.contains("<li>Call &lt;back&gt; (Mon 14 Jul)</li>"),
+            "{html}"
+        );
+        assert!(html.find(">ACME<").unwrap() < html.find(">Globex<").unwrap());
+
+        let (_, text) = render_digest(DigestPeriod::Day, Locale::En, monday, &tasks[..1], None);
+        assert!(text.starts_with("Open tasks for Monday 14 July 2025 (1)\n"));
+        assert!(text.ends_with("- Call <back>\n"), "{text}");
+    }
+
+    #[test]
+    fn test_digest_in_french_with_the_summary() {
+        let tuesday = NaiveDate::from_ymd_opt(2025, 7, 15).unwrap();
+        let tasks = [task("ACME", "Rapport", tuesday, Some(Priority::High))];
+
+        let (html, text) = render_digest(
+            DigestPeriod::Week,
+            Locale::Fr,
+            tuesday,
+            &tasks,
+            Some("Vous avez terminé 2 tâches pour 1 client."),
+        );
+        assert_eq!(
+            text,
+            "Tâches ouvertes de la semaine du 14 juillet 2025 (1)\n\
+             \nLa semaine dernière\nVous avez terminé 2 tâches pour 1 client.\n\
+             \nACME (1)\n- Rapport (mar. 15 juil., priorité 2)\n"
+        );
+        assert!(
+            html.contains("<h2>La semaine dernière</h2>\n<p>Vous avez terminé"),
+            "{html}"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::formats::BodyFormat;
+use axum::{
+    body::{self, Body},
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use tracing::error;
+
+/// Weak ETag of a listing body, sent in `format`. Derived from the body
+/// itself rather than from the tasks, so that anything changing the listing
+/// (focus days, the day of the week, client colors) changes it too. The
+/// body is still JSON here, translated afterwards by `negotiate_formats`,
+/// hence the format mixed in: each encoding of a listing gets its own tag.
+pub fn weak_etag(body: &[u8], format: BodyFormat) -> String {
+    let digest = Sha256::new()
+        .chain_update(format.media_type())
+        .chain_update(body)
+        .finalize();
+    let hex: String = digest[..16]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("W/\"{hex}\"")
+}
+
+/// Whether an `If-None-Match` header lists `etag`, compared weakly as
+/// required for `GET`.
+fn matches(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag);
+    if_none_match
+        .split(',')
+        .any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+/// Middleware tagging successful responses with a weak `ETag`, and
+/// answering `304 Not Modified` without a body when the `If-None-Match` of
+/// the request already lists it. Meant for the listings the frontends poll.
+/// Both vary on `Accept`, which picks the encoding of the body.
+pub async fn conditional_get(request: Request, next: Next) -> Response {
+    let format = BodyFormat::from_accept(
+        request
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok()),
+    );
+    let if_none_match = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Failed to read a response body to tag: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let etag = weak_etag(&bytes, format);
+    let value = HeaderValue::from_str(&etag).expect("ETags are ASCII");
+    // Stored by browsers, but checked again at each poll
+    let cache_control = HeaderValue::from_static("no-cache");
+    let vary = HeaderValue::from_static("accept");
+
+    if if_none_match.is_some_and(|tags| matches(&tags, &etag)) {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [
+                (header::ETAG, value),
+                (header::CACHE_CONTROL, cache_control),
+                (header::VARY, vary),
+            ],
+        )
+            .into_response();
+    }
+    parts.headers.insert(header::ETAG, value);
+    parts.headers.insert(header::CACHE_CONTROL, cache_control);
+    parts.headers.append(header::VARY, vary);
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weak_etag_follows_the_body() {
+        let etag = weak_etag(b"[]", BodyFormat::Json);
+        assert!(etag.starts_with("W/\"") && etag.ends_with('"'), "{etag}");
+        assert_eq!(etag.len(), 4 + 32);
+        assert_eq!(weak_etag(b"[]", BodyFormat::Json), etag);
+        assert_ne!(weak_etag(b"[{}]", BodyFormat::Json), etag);
+    }
+
+    #[test]
+    fn test_weak_etag_follows_the_format() {
+        let json = weak_etag(b"[]", BodyFormat::Json);
+        let msgpack = weak_etag(b"[]", BodyFormat::MessagePack);
+        let cbor = weak_etag(b"[]", BodyFormat::Cbor);
+        assert_ne!(json, msgpack);
+        assert_ne!(json, cbor);
+        assert_ne!(msgpack, cbor);
+    }
+
+    #[test]
+    fn test_if_none_match_is_compared_weakly() {
+        let etag = weak_etag(b"[]", BodyFormat::Json);
+        let opaque = etag.trim_start_matches("W/");
+        assert!(matches(&etag, &etag));
+        assert!(matches(opaque, &etag));
+        assert!(matches(&format!("\"other\", {etag}"), &etag));
+        assert!(matches("*", &etag));
+        assert!(!matches("W/\"other\"", &etag));
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use serde::Deserialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+// Fields removed from records exported with the `client` profile: internal
+// identifiers and references to other systems. Fields holding user
+// information or private notes must be listed here as they are introduced.
+const CLIENT_HIDDEN_FIELDS: &[&str] = &["id", "client_id", "external_source", "external_id"];
+
+/// Who an export is meant for, which decides what it may contain.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportProfile {
+    /// Everything, for backups and internal use.
+    #[default]
+    Full,
+    /// Safe to hand over to a client: no internal IDs, user info or private notes.
+    Client,
+}
+
+impl ExportProfile {
+    /// Filters a serialized record in place. Applied after serialization so that
+    /// every exporter shares the same rules regardless of the record type.
+    pub fn apply(self, record: &mut Value) {
+        let hidden = match self {
+            ExportProfile::Full => return,
+            ExportProfile::Client => CLIENT_HIDDEN_FIELDS,
+        };
+        if let Value::Object(fields) = record {
+            for field in hidden {
+                fields.remove(*field);
+            }
+        }
+    }
+
+    /// Serializes a record with this profile applied.
+    pub fn to_value<T: serde::Serialize>(self, record: &T) -> serde_json::Result<Value> {
+        let mut value = serde_json::to_value(record)?;
+        self.apply(&mut value);
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_client_profile_strips_internal_fields() {
+        let record = json!({
+            "type": "task",
+            "id": 12,
+            "client_id": 3,
+            "client_name": "Acme",
+            "description": "Write report",
+            "external_source": "todoist",
+            "external_id": "abc"
+        });
+
+        let mut full = record.clone();
+        ExportProfile::Full.apply(&mut full);
+        assert_eq!(full, record);
+
+        let mut client = record;
+        ExportProfile::Client.apply(&mut client);
+        assert_eq!(
+            client,
+            json!({ "type": "task", "client_name": "Acme", "description": "Write report" })
+        );
+    }
+}
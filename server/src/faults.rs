@@ -0,0 +1,228 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use tracing::debug;
+
+/// A fault injected into the requests of one route, so that the frontend
+/// loading and retry states can be exercised against a slow or failing server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FaultRule {
+    /// Route as declared in the router (e.g. `/api/tasks/{id}`), or a
+    /// concrete request path (e.g. `/api/tasks/42`).
+    pub route: String,
+    /// HTTP method the rule is limited to, any method when unset.
+    #[serde(default)]
+    pub method: Option<String>,
+    /// Delay added before the request is handled, in milliseconds.
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Error status returned instead of calling the handler, if any.
+    #[serde(default)]
+    pub status: Option<u16>,
+    /// Share of the matching requests answered with `status`, between 0 and 1.
+    /// Failures are spread evenly rather than drawn at random, so that a
+    /// scenario replays identically: 0.5 fails every other request.
+    #[serde(default = "always")]
+    pub failure_rate: f64,
+}
+
+fn always() -> f64 {
+    1.0
+}
+
+impl FaultRule {
+    /// Checks the rule before it is installed.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.route.starts_with('/') {
+            return Err(format!("Route '{}' must start with '/'.", self.route));
+        }
+        if let Some(method) = &self.method {
+            Method::from_bytes(method.as_bytes())
+                .map_err(|_| format!("'{method}' is not an HTTP method."))?;
+        }
+        if let Some(status) = self.status.filter(|s| !(400..=599).contains(s)) {
+            return Err(format!("Status {status} is not an error status."));
+        }
+        if !(0.0..=1.0).contains(&self.failure_rate) {
+            return Err("Failure rate must be between 0 and 1.".to_string());
+        }
+        Ok(())
+    }
+
+    fn matches(&self, method: &Method, route: Option<&str>, path: &str) -> bool {
+        let method_matches = self
+            .method
+            .as_deref()
+            .is_none_or(|m| m.eq_ignore_ascii_case(method.as_str()));
+        method_matches && (route == Some(self.route.as_str()) || path == self.route)
+    }
+}
+
+/// What to do with a request matching a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fault {
+    pub delay: Duration,
+    pub status: Option<StatusCode>,
+}
+
+/// Installed rules, with the number of requests each one has matched.
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    rules: RwLock<Vec<(FaultRule, u64)>>,
+}
+
+impl FaultInjector {
+    pub fn rules(&self) -> Vec<FaultRule> {
+        self.rules
+            .read()
+            .iter()
+            .map(|(rule, _)| rule.clone())
+            .collect()
+    }
+
+    /// Replaces all rules, restarting their request counts.
+    pub fn set_rules(&self, rules: Vec<FaultRule>) {
+        *self.rules.write() = rules.into_iter().map(|rule| (rule, 0)).collect();
+    }
+
+    /// Finds the fault of a request. When several rules match, the first one wins.
+    pub fn fault_for(&self, method: &Method, route: Option<&str>, path: &str) -> Option<Fault> {
+        let mut rules = self.rules.write();
+        let (rule, count) = rules
+            .iter_mut()
+            .find(|(rule, _)| rule.matches(method, route, path))?;
+        *count += 1;
+
+        // The request fails when it brings the number of expected failures
+        // to the next integer, e.g. the 2nd, 4th, ... for a rate of 0.5.
+        let expected = |n: u64| (n as f64 * rule.failure_rate).floor();
+        let fails = expected(*count) > expected(*count - 1);
+        Some(Fault {
+            delay: Duration::from_millis(rule.delay_ms),
+            status: rule
+                .status
+                .filter(|_| fails)
+                .and_then(|status| StatusCode::from_u16(status).ok()),
+        })
+    }
+}
+
+/// Middleware applying the installed faults. It runs after routing, so the
+/// rules can name routes with their path parameters.
+pub async fn inject_faults(
+    State(injector): State<Arc<FaultInjector>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|route| route.as_str().to_owned());
+    let fault = injector.fault_for(request.method(), route.as_deref(), request.uri().path());
+
+    if let Some(fault) = fault {
+        debug!(
+            "Injecting a fault into {} {}: {:?}",
+            request.method(),
+            request.uri().path(),
+            fault
+        );
+        if !fault.delay.is_zero() {
+            sleep(fault.delay).await;
+        }
+        if let Some(status) = fault.status {
+            return (
+                status,
+                Json(serde_json::json!({ "error": "Injected fault." })),
+            )
+                .into_response();
+        }
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(route: &str) -> FaultRule {
+        FaultRule {
+            route: route.to_string(),
+            method: None,
+            delay_ms: 0,
+            status: Some(503),
+            failure_rate: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_rules_match_route_or_path_and_method() {
+        let injector = FaultInjector::default();
+        injector.set_rules(vec![
+            FaultRule {
+                method: Some("delete".to_string()),
+                ..rule("/api/tasks/{id}")
+            },
+            rule("/api/clients"),
+        ]);
+
+        let fault = injector.fault_for(&Method::DELETE, Some("/api/tasks/{id}"), "/api/tasks/3");
+        assert_eq!(fault.unwrap().status, Some(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(injector
+            .fault_for(&Method::GET, Some("/api/tasks/{id}"), "/api/tasks/3")
+            .is_none());
+        assert!(injector
+            .fault_for(&Method::GET, None, "/api/clients")
+            .is_some());
+        assert!(injector
+            .fault_for(&Method::GET, Some("/api/tasks"), "/api/tasks")
+            .is_none());
+    }
+
+    #[test]
+    fn test_failure_rate_is_spread_evenly() {
+        let injector = FaultInjector::default();
+        injector.set_rules(vec![FaultRule {
+            delay_ms: 250,
+            failure_rate: 0.5,
+            ..rule("/api/tasks")
+        }]);
+
+        let statuses: Vec<bool> = (0..4)
+            .map(|_| {
+                let fault = injector
+                    .fault_for(&Method::GET, None, "/api/tasks")
+                    .unwrap();
+                assert_eq!(fault.delay, Duration::from_millis(250));
+                fault.status.is_some()
+            })
+            .collect();
+        assert_eq!(statuses, [false, true, false, true]);
+
+        assert!(rule("api/tasks").validate().is_err());
+        assert!(FaultRule {
+            status: Some(200),
+            ..rule("/api/tasks")
+        }
+        .validate()
+        .is_err());
+        assert!(FaultRule {
+            failure_rate: 1.5,
+            ..rule("/api/tasks")
+        }
+        .validate()
+        .is_err());
+    }
+}
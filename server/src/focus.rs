@@ -0,0 +1,127 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use chrono::NaiveDate;
+use common::{Priority, Task, TaskSummary};
+use std::collections::HashMap;
+
+/// Number of tasks kept visible on a focus day when none is given.
+pub const DEFAULT_FOCUS_TOP_N: i64 = 3;
+
+/// A task as seen by the focus filter.
+pub trait Focusable {
+    fn task_date(&self) -> NaiveDate;
+    /// Ranking key within a day: priority first (missing last), then ID.
+    fn focus_rank(&self) -> (bool, Option<Priority>, i64);
+    fn is_deferred(&self) -> bool;
+    fn set_deferred(&mut self);
+}
+
+impl Focusable for Task {
+    fn task_date(&self) -> NaiveDate {
+        self.task_date
+    }
+    fn focus_rank(&self) -> (bool, Option<Priority>, i64) {
+        (self.priority.is_none(), self.priority, self.id)
+    }
+    fn is_deferred(&self) -> bool {
+        self.deferred
+    }
+    fn set_deferred(&mut self) {
+        self.deferred = true;
+    }
+}
+
+impl Focusable for TaskSummary {
+    fn task_date(&self) -> NaiveDate {
+        self.task_date
+    }
+    fn focus_rank(&self) -> (bool, Option<Priority>, i64) {
+        (self.priority.is_none(), self.priority, self.id)
+    }
+    fn is_deferred(&self) -> bool {
+        self.deferred
+    }
+    fn set_deferred(&mut self) {
+        self.deferred = true;
+    }
+}
+
+/// Applies focus mode to a list of tasks. On each day of `focus_days`, only
+/// the `top_n` highest priority tasks stay visible; the others are marked
+/// deferred for the day, their date is not changed. Deferred tasks are
+/// removed unless `include_deferred` is set. The order of the list is kept.
+pub fn apply_focus<T: Focusable>(
+    tasks: &mut Vec<T>,
+    focus_days: &HashMap<NaiveDate, i64>,
+    include_deferred: bool,
+) {
+    for (day, top_n) in focus_days {
+        let mut ranked: Vec<usize> = (0..tasks.len())
+            .filter(|&i| tasks[i].task_date() == *day)
+            .collect();
+        ranked.sort_by_key(|&i| tasks[i].focus_rank());
+        for &i in ranked.iter().skip(usize::try_from(*top_n).unwrap_or(0)) {
+            tasks[i].set_deferred();
+        }
+    }
+
+    if !include_deferred {
+        tasks.retain(|task| !task.is_deferred());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(id: i64, task_date: NaiveDate, priority: Option<Priority>) -> TaskSummary {
+        TaskSummary {
+            id,
+            client_name: "Client".to_string(),
+            client_color: "#1f77b4".parse().unwrap(),
+            client_text_color: "#ffffff".to_string(),
+            client_background_color: "#d2e4f0".to_string(),
+            description: "Task".to_string(),
+            task_date,
+            priority,
+            context: None,
+            slot_id: None,
+            slots_total: 0,
+            slots_done: 0,
+            deferred: false,
+            display: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_focus_keeps_top_priorities_of_the_day() {
+        let monday = NaiveDate::from_ymd_opt(2025, 7, 7).unwrap();
+        let tuesday = monday.succ_opt().unwrap();
+        let tasks = vec![
+            summary(1, monday, None),
+            summary(2, monday, Some(Priority::Medium)),
+            summary(3, monday, Some(Priority::Critical)),
+            summary(4, monday, Some(Priority::Medium)),
+            summary(5, tuesday, None),
+        ];
+        let focus_days = HashMap::from([(monday, 2)]);
+
+        // Hidden by default, in the original order
+        let mut visible = tasks.clone();
+        apply_focus(&mut visible, &focus_days, false);
+        let ids: Vec<i64> = visible.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![2, 3, 5]);
+
+        // Flagged when requested, ties broken by ID and missing priorities last
+        let mut flagged = tasks;
+        apply_focus(&mut flagged, &focus_days, true);
+        let deferred: Vec<i64> = flagged
+            .iter()
+            .filter(|t| t.deferred)
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(deferred, vec![1, 4]);
+    }
+}
@@ -0,0 +1,284 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::Config;
+use axum::{
+    body::{self, Body},
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::{error, warn};
+
+/// Encodings of the request and response bodies of the API. Handlers only
+/// deal in JSON; the others are translated to and from it by
+/// `negotiate_formats`, for clients where JSON costs too much to parse or
+/// to send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BodyFormat {
+    #[default]
+    Json,
+    MessagePack,
+    Cbor,
+}
+
+impl BodyFormat {
+    pub fn media_type(self) -> &'static str {
+        match self {
+            BodyFormat::Json => "application/json",
+            BodyFormat::MessagePack => "application/msgpack",
+            BodyFormat::Cbor => "application/cbor",
+        }
+    }
+
+    fn from_media_type(media_type: &str) -> Option<BodyFormat> {
+        match media_type.trim().to_ascii_lowercase().as_str() {
+            "application/json" => Some(BodyFormat::Json),
+            "application/msgpack" | "application/x-msgpack" => Some(BodyFormat::MessagePack),
+            "application/cbor" => Some(BodyFormat::Cbor),
+            _ => None,
+        }
+    }
+
+    /// Format of a body from its `Content-Type` header, ignoring parameters
+    /// such as `charset`.
+    pub fn from_content_type(headers: &HeaderMap) -> Option<BodyFormat> {
+        let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+        BodyFormat::from_media_type(content_type.split(';').next().unwrap_or_default())
+    }
+
+    /// Picks the format preferred by an `Accept` header, e.g.
+    /// `application/cbor, application/json;q=0.5`. JSON when the header
+    /// names none of them.
+    pub fn from_accept(header: Option<&str>) -> BodyFormat {
+        let mut best: Option<(f32, BodyFormat)> = None;
+        for entry in header.unwrap_or_default().split(',') {
+            let mut parts = entry.split(';').map(str::trim);
+            let Some(format) = BodyFormat::from_media_type(parts.next().unwrap_or_default()) else {
+                continue;
+            };
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if quality > 0.0 && best.is_none_or(|(q, _)| quality > q) {
+                best = Some((quality, format));
+            }
+        }
+        best.map(|(_, format)| format).unwrap_or_default()
+    }
+
+    fn decode(self, bytes: &[u8]) -> Result<Value, String> {
+        match self {
+            BodyFormat::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            BodyFormat::MessagePack => rmp_serde::from_slice(bytes).map_err(|e| e.to_string()),
+            BodyFormat::Cbor => ciborium::from_reader(bytes).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn encode(self, value: &Value) -> Result<Vec<u8>, String> {
+        match self {
+            BodyFormat::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            // Objects keep their field names, as in JSON.
+            BodyFormat::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+            BodyFormat::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes).map_err(|e| e.to_string())?;
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+fn error_response(code: StatusCode, message: String) -> Response {
+    (code, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// Middleware accepting MessagePack and CBOR request bodies, given with
+/// their `Content-Type`, and answering in the format preferred by the
+/// `Accept` header. JSON responses are re-encoded; others, such as CSV
+/// exports or backups, are left as they are. Request bodies are held to
+/// `MAX_BODY_BYTES`, like the JSON ones.
+pub async fn negotiate_formats(
+    State(config): State<Arc<Config>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let accept = BodyFormat::from_accept(
+        request
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok()),
+    );
+
+    let response = match BodyFormat::from_content_type(request.headers()) {
+        Some(format @ (BodyFormat::MessagePack | BodyFormat::Cbor)) => {
+            let (mut parts, body) = request.into_parts();
+            let limit = config.http.max_body_bytes;
+            let json = match body::to_bytes(body, limit).await {
+                Ok(bytes) => format
+                    .decode(&bytes)
+                    .and_then(|value| BodyFormat::Json.encode(&value))
+                    .map_err(|e| {
+                        warn!("Rejected a {} request body: {}", format.media_type(), e);
+                        error_response(
+                            StatusCode::BAD_REQUEST,
+                            format!("Invalid {} request body: {e}", format.media_type()),
+                        )
+                    }),
+                Err(_) => Err(error_response(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    format!("The request body is larger than {limit} bytes."),
+                )),
+            };
+            match json {
+                Ok(json) => {
+                    parts.headers.insert(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static(BodyFormat::Json.media_type()),
+                    );
+                    parts.headers.remove(header::CONTENT_LENGTH);
+                    next.run(Request::from_parts(parts, Body::from(json))).await
+                }
+                Err(response) => response,
+            }
+        }
+        _ => next.run(request).await,
+    };
+
+    let mut response = match (accept, BodyFormat::from_content_type(response.headers())) {
+        (BodyFormat::MessagePack | BodyFormat::Cbor, Some(BodyFormat::Json)) => {
+            transcode(response, accept).await
+        }
+        _ => response,
+    };
+    if BodyFormat::from_content_type(response.headers()).is_some() {
+        response
+            .headers_mut()
+            .append(header::VARY, HeaderValue::from_static("accept"));
+    }
+    response
+}
+
+/// Re-encodes the JSON body of `response` in `format`.
+async fn transcode(response: Response, format: BodyFormat) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let encoded = match body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => BodyFormat::Json
+            .decode(&bytes)
+            .and_then(|value| format.encode(&value)),
+        Err(e) => Err(e.to_string()),
+    };
+    match encoded {
+        Ok(bytes) => {
+            parts.headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(format.media_type()),
+            );
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(bytes))
+        }
+        Err(e) => {
+            error!(
+                "Failed to encode a response as {}: {}",
+                format.media_type(),
+                e
+            );
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "An internal error occurred.".to_string(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware, routing::post, Router};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_accept_header_picks_the_format() {
+        assert_eq!(BodyFormat::from_accept(None), BodyFormat::Json);
+        assert_eq!(
+            BodyFormat::from_accept(Some("application/cbor")),
+            BodyFormat::Cbor
+        );
+        assert_eq!(
+            BodyFormat::from_accept(Some("application/json;q=0.5, application/x-msgpack")),
+            BodyFormat::MessagePack
+        );
+        assert_eq!(
+            BodyFormat::from_accept(Some("application/msgpack;q=0, */*")),
+            BodyFormat::Json
+        );
+    }
+
+    async fn call(request: Request) -> Response {
+        let app = Router::new()
+            .route(
+                "/echo",
+                post(|Json(value): Json<Value>| async move { Json(value) }),
+            )
+            .layer(middleware::from_fn_with_state(
+                Arc::new(Config::default()),
+                negotiate_formats,
+            ));
+        app.oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_bodies_are_translated_both_ways() {
+        let value = json!({ "client_name": "ACME", "priority": 2, "tags": [true, null] });
+
+        for (content_type, accept) in [
+            (BodyFormat::MessagePack, BodyFormat::Cbor),
+            (BodyFormat::Cbor, BodyFormat::MessagePack),
+            (BodyFormat::Cbor, BodyFormat::Json),
+        ] {
+            let request = Request::post("/echo")
+                .header(header::CONTENT_TYPE, content_type.media_type())
+                .header(header::ACCEPT, accept.media_type())
+                .body(Body::from(content_type.encode(&value).unwrap()))
+                .unwrap();
+            let response = call(request).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(
+                BodyFormat::from_content_type(response.headers()),
+                Some(accept)
+            );
+            assert_eq!(response.headers()[header::VARY], "accept");
+            let bytes = body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(accept.decode(&bytes).unwrap(), value);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_malformed_body_is_rejected() {
+        let request = Request::post("/echo")
+            .header(header::CONTENT_TYPE, "application/cbor")
+            .header(header::ACCEPT, "application/msgpack")
+            .body(Body::from(vec![0xff, 0x00]))
+            .unwrap();
+        let response = call(request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        // The error itself is answered in the format asked for.
+        let bytes = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let error = BodyFormat::MessagePack.decode(&bytes).unwrap();
+        assert!(error["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid application/cbor"));
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use common::{GoalProgress, GoalUnit, WeeklyGoal};
+
+const WEEK_SECONDS: f64 = 7.0 * 24.0 * 3600.0;
+
+/// Keeps two decimals, enough for hours and shares of the week.
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Measures the week starting on `week_start` against `goal`, with
+/// `done_tasks` tasks done so far adding up to `done_minutes` of estimates.
+/// `now` is the local time, from which the share of the week gone by, and
+/// so the expected pace, is taken.
+pub fn progress(
+    goal: WeeklyGoal,
+    week_start: NaiveDate,
+    now: NaiveDateTime,
+    done_tasks: i64,
+    done_minutes: i64,
+) -> GoalProgress {
+    let done = match goal.unit {
+        GoalUnit::Tasks => done_tasks as f64,
+        GoalUnit::Hours => done_minutes as f64 / 60.0,
+    };
+    let elapsed = ((now - week_start.and_time(NaiveTime::MIN)).num_seconds() as f64 / WEEK_SECONDS)
+        .clamp(0.0, 1.0);
+    let expected = goal.target * elapsed;
+    let projected = if elapsed > 0.0 { done / elapsed } else { done };
+
+    GoalProgress {
+        week_start,
+        week_end: week_start + Duration::days(6),
+        done: round(done),
+        remaining: round((goal.target - done).max(0.0)),
+        progress: round(done / goal.target),
+        week_elapsed: round(elapsed),
+        expected: round(expected),
+        ahead_by: round(done - expected),
+        projected: round(projected),
+        on_track: done >= expected || done >= goal.target,
+        goal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn goal(unit: GoalUnit, target: f64) -> WeeklyGoal {
+        WeeklyGoal {
+            unit,
+            target,
+            set_at: Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_progress_against_the_pace_of_the_week() {
+        let monday = NaiveDate::from_ymd_opt(2025, 7, 14).unwrap();
+        // Thursday at midnight: three days out of seven gone by
+        let thursday = NaiveDate::from_ymd_opt(2025, 7, 17)
+            .unwrap()
+            .and_time(NaiveTime::MIN);
+
+        let behind = progress(goal(GoalUnit::Tasks, 14.0), monday, thursday, 4, 0);
+        assert_eq!(
+            behind.week_end,
+            NaiveDate::from_ymd_opt(2025, 7, 20).unwrap()
+        );
+        assert_eq!(behind.week_elapsed, 0.43);
+        assert_eq!(behind.expected, 6.0);
+        assert_eq!(behind.ahead_by, -2.0);
+        assert_eq!(behind.remaining, 10.0);
+        assert_eq!(behind.projected, 9.33);
+        assert!(!behind.on_track);
+
+        // Hours come from the estimates of the tasks done
+        let ahead = progress(goal(GoalUnit::Hours, 21.0), monday, thursday, 6, 630);
+        assert_eq!(ahead.done, 10.5);
+        assert_eq!(ahead.progress, 0.5);
+        assert_eq!(ahead.ahead_by, 1.5);
+        assert_eq!(ahead.projected, 24.5);
+        assert!(ahead.on_track);
+    }
+
+    #[test]
+    fn test_progress_at_the_edges_of_the_week() {
+        let monday = NaiveDate::from_ymd_opt(2025, 7, 14).unwrap();
+
+        let start = progress(
+            goal(GoalUnit::Tasks, 10.0),
+            monday,
+            monday.and_time(NaiveTime::MIN),
+            0,
+            0,
+        );
+        assert_eq!(start.week_elapsed, 0.0);
+        assert_eq!(start.projected, 0.0);
+        assert!(start.on_track);
+
+        // Going past the goal leaves nothing remaining
+        let sunday_night = NaiveDate::from_ymd_opt(2025, 7, 20)
+            .unwrap()
+            .and_hms_opt(23, 59, 59)
+            .unwrap();
+        let met = progress(goal(GoalUnit::Tasks, 10.0), monday, sunday_night, 12, 0);
+        assert_eq!(met.week_elapsed, 1.0);
+        assert_eq!(met.remaining, 0.0);
+        assert_eq!(met.progress, 1.2);
+        assert!(met.on_track);
+    }
+}
@@ -0,0 +1,481 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::Config;
+use crate::database::{self, MoveTaskOutcome, TaskUpdate};
+use crate::handlers::{self, AppError};
+use async_graphql::{
+    Context, EmptySubscription, Enum, ErrorExtensions, InputObject, MaybeUndefined, Object, Schema,
+};
+use axum::http::StatusCode;
+use chrono::{DateTime, NaiveDate, Utc};
+use common::{Client, CreateTaskPayload, Priority, RolloverTrigger, Task};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tracing::info;
+
+/// Deepest nesting of a query, e.g. `tasks { client { tasks { ... } } }`
+/// stops here rather than walking the database back and forth.
+const MAX_DEPTH: usize = 8;
+
+/// Schema served at `/api/graphql`. The pool and the configuration are
+/// passed along with each request.
+pub type ApiSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+pub fn schema() -> ApiSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .finish()
+}
+
+/// Errors are told as the REST routes would, their status in the `status`
+/// extension.
+impl From<AppError> for async_graphql::Error {
+    fn from(error: AppError) -> Self {
+        let status = error.code().as_u16();
+        async_graphql::Error::new(error.message()).extend_with(|_, e| e.set("status", status))
+    }
+}
+
+/// Same as `anyhow::Error` into `AppError`: logged, and reported as an
+/// internal error without its details.
+fn internal(error: anyhow::Error) -> AppError {
+    AppError::from(error)
+}
+
+fn pool<'a>(ctx: &Context<'a>) -> &'a SqlitePool {
+    ctx.data_unchecked::<SqlitePool>()
+}
+
+fn config<'a>(ctx: &Context<'a>) -> &'a Config {
+    ctx.data_unchecked::<Arc<Config>>()
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+#[graphql(name = "Energy", remote = "common::Energy")]
+enum Energy {
+    Deep,
+    Shallow,
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+#[graphql(name = "RolloverTarget", remote = "common::RolloverTarget")]
+enum RolloverTarget {
+    NextDay,
+    NextBusinessDay,
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+    EndOfWeek,
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq, Default)]
+#[graphql(name = "TaskStatus", remote = "common::TaskStatusFilter")]
+enum TaskStatus {
+    /// Tasks still to do.
+    #[default]
+    Open,
+    /// Tasks already done.
+    Done,
+    /// Both open and done tasks.
+    All,
+}
+
+/// Tasks to list. Without dates, those of the current week.
+#[derive(InputObject, Default)]
+struct TaskFilter {
+    /// Client of the tasks, or one of its aliases.
+    client: Option<String>,
+    /// First day, Monday of the current week by default.
+    from: Option<NaiveDate>,
+    /// Last day, inclusive, Sunday of the current week by default.
+    to: Option<NaiveDate>,
+    #[graphql(default)]
+    status: TaskStatus,
+}
+
+/// The tasks of `client` matching `filter`.
+async fn list_tasks(
+    ctx: &Context<'_>,
+    client: Option<String>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    status: TaskStatus,
+) -> async_graphql::Result<Vec<TaskObject>> {
+    let (week_start, week_end) = database::current_week_range();
+    let filter = common::TaskFilter {
+        client_name: client,
+        task_date: None,
+        from: Some(from.unwrap_or(week_start)),
+        to: Some(to.unwrap_or(week_end)),
+        status: status.into(),
+    };
+    filter.dates().map_err(|e| handlers::invalid_filter(&e))?;
+    let tasks = database::get_task_list_from_db(pool(ctx), &filter)
+        .await
+        .map_err(internal)?;
+    Ok(tasks.into_iter().map(TaskObject).collect())
+}
+
+/// A task, done or not.
+struct TaskObject(Task);
+
+#[Object(name = "Task")]
+impl TaskObject {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn description(&self) -> &str {
+        &self.0.description
+    }
+
+    async fn task_date(&self) -> NaiveDate {
+        self.0.task_date
+    }
+
+    /// From 1 (critical) to 4 (low).
+    async fn priority(&self) -> Option<i32> {
+        self.0.priority.map(Priority::level)
+    }
+
+    async fn done(&self) -> bool {
+        self.0.deleted_at.is_some()
+    }
+
+    async fn done_at(&self) -> Option<DateTime<Utc>> {
+        self.0.deleted_at
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    /// Version to send back in `updatedAt` to change the task.
+    async fn updated_at(&self) -> DateTime<Utc> {
+        self.0.updated_at
+    }
+
+    async fn energy(&self) -> Option<Energy> {
+        self.0.energy.map(Energy::from)
+    }
+
+    async fn estimate_minutes(&self) -> Option<i32> {
+        self.0.estimate_minutes
+    }
+
+    async fn context(&self) -> Option<&str> {
+        self.0.context.as_deref()
+    }
+
+    /// Left on its day by the rollover.
+    async fn pin_to_date(&self) -> bool {
+        self.0.pin_to_date
+    }
+
+    /// Day the task rolls over to, in place of the rollover policy.
+    async fn rollover_target(&self) -> Option<RolloverTarget> {
+        self.0.rollover_target.map(RolloverTarget::from)
+    }
+
+    async fn rollover_count(&self) -> i64 {
+        self.0.rollover_count
+    }
+
+    async fn stale_since(&self) -> Option<DateTime<Utc>> {
+        self.0.stale_since
+    }
+
+    async fn slots_total(&self) -> i64 {
+        self.0.slots_total
+    }
+
+    async fn slots_done(&self) -> i64 {
+        self.0.slots_done
+    }
+
+    async fn external_source(&self) -> Option<&str> {
+        self.0.external_source.as_deref()
+    }
+
+    async fn external_id(&self) -> Option<&str> {
+        self.0.external_id.as_deref()
+    }
+
+    async fn client(&self, ctx: &Context<'_>) -> async_graphql::Result<ClientObject> {
+        let client = database::get_client_from_db(pool(ctx), &self.0.client_name)
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| {
+                internal(anyhow::anyhow!(
+                    "Client '{}' of task {} is missing",
+                    self.0.client_name,
+                    self.0.id
+                ))
+            })?;
+        Ok(ClientObject(client))
+    }
+}
+
+/// A client, and its color.
+struct ClientObject(Client);
+
+#[Object(name = "Client")]
+impl ClientObject {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn color(&self) -> &str {
+        &self.0.color
+    }
+
+    /// Readable text color on `color`.
+    async fn text_color(&self) -> &str {
+        &self.0.text_color
+    }
+
+    /// Lighter variant of `color`, for backgrounds.
+    async fn background_color(&self) -> &str {
+        &self.0.background_color
+    }
+
+    async fn archived_at(&self) -> Option<DateTime<Utc>> {
+        self.0.archived_at
+    }
+
+    async fn default_priority(&self) -> Option<i32> {
+        self.0.default_priority.map(Priority::level)
+    }
+
+    async fn default_context(&self) -> Option<&str> {
+        self.0.default_context.as_deref()
+    }
+
+    /// Tasks of the client, by date. Without dates, those of the current week.
+    async fn tasks(
+        &self,
+        ctx: &Context<'_>,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        #[graphql(default)] status: TaskStatus,
+    ) -> async_graphql::Result<Vec<TaskObject>> {
+        list_tasks(ctx, Some(self.0.name.clone()), from, to, status).await
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Tasks matching the filter, by date.
+    async fn tasks(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] filter: TaskFilter,
+    ) -> async_graphql::Result<Vec<TaskObject>> {
+        list_tasks(ctx, filter.client, filter.from, filter.to, filter.status).await
+    }
+
+    /// A task by ID, done or not.
+    async fn task(&self, ctx: &Context<'_>, id: i64) -> async_graphql::Result<Option<TaskObject>> {
+        let task = database::get_task_by_id_from_db(pool(ctx), id)
+            .await
+            .map_err(internal)?;
+        Ok(task.map(TaskObject))
+    }
+
+    /// Clients by name, the archived ones only when asked for.
+    async fn clients(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] include_archived: bool,
+    ) -> async_graphql::Result<Vec<ClientObject>> {
+        let clients = database::get_clients_from_db(pool(ctx), include_archived)
+            .await
+            .map_err(internal)?;
+        Ok(clients.into_iter().map(ClientObject).collect())
+    }
+
+    async fn client(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+    ) -> async_graphql::Result<Option<ClientObject>> {
+        let client = database::get_client_from_db(pool(ctx), &name)
+            .await
+            .map_err(internal)?;
+        Ok(client.map(ClientObject))
+    }
+}
+
+/// Fields of a new task, as in `POST /api/tasks`.
+#[derive(InputObject)]
+struct CreateTaskInput {
+    /// Name of the client, or one of its aliases. Unknown clients are created.
+    client_name: String,
+    description: String,
+    /// Today by default.
+    task_date: Option<NaiveDate>,
+    /// From 1 (critical) to 4 (low).
+    priority: Option<i32>,
+    energy: Option<Energy>,
+    estimate_minutes: Option<i32>,
+    context: Option<String>,
+    #[graphql(default)]
+    pin_to_date: bool,
+    rollover_target: Option<RolloverTarget>,
+    external_source: Option<String>,
+    external_id: Option<String>,
+}
+
+/// Changes to a task. Unset fields are left as they are.
+#[derive(InputObject)]
+struct UpdateTaskInput {
+    /// Moves the open task to this day, as `PATCH /api/tasks/{id}/move`.
+    task_date: Option<NaiveDate>,
+    /// Pins the task to its day, or lets it roll over again.
+    pin_to_date: Option<bool>,
+    /// Day the task rolls over to; `null` lets it follow the policy again.
+    rollover_target: MaybeUndefined<RolloverTarget>,
+    /// `updatedAt` of the task as last read; the update fails with status
+    /// 412 if it changed since.
+    updated_at: Option<DateTime<Utc>>,
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    /// Creates a task, checked as by `POST /api/tasks`.
+    async fn create_task(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateTaskInput,
+        #[graphql(default)] allow_archived: bool,
+    ) -> async_graphql::Result<TaskObject> {
+        let priority = input
+            .priority
+            .map(|level| {
+                Priority::from_level(level.into()).ok_or_else(|| {
+                    AppError::new(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        &format!("Invalid priority {level}, expected 1 (critical) to 4 (low)."),
+                    )
+                })
+            })
+            .transpose()?;
+        let mut payload = CreateTaskPayload {
+            client_name: input.client_name,
+            description: input.description,
+            task_date: input.task_date,
+            priority,
+            external_source: input.external_source,
+            external_id: input.external_id,
+            energy: input.energy.map(Into::into),
+            estimate_minutes: input.estimate_minutes,
+            context: input.context,
+            pin_to_date: input.pin_to_date,
+            rollover_target: input.rollover_target.map(Into::into),
+        };
+        let pool = pool(ctx);
+        handlers::check_new_task(pool, config(ctx), &mut payload, allow_archived).await?;
+        let task = database::create_task_in_db(pool, payload)
+            .await
+            .map_err(internal)?;
+        info!("Task created through GraphQL with ID: {}", task.id);
+        Ok(TaskObject(task))
+    }
+
+    /// Moves, pins or sets the rollover target of a task, in that order.
+    /// Either every change is made, or none is.
+    async fn update_task(
+        &self,
+        ctx: &Context<'_>,
+        id: i64,
+        input: UpdateTaskInput,
+    ) -> async_graphql::Result<TaskObject> {
+        if let Some(task_date) = input.task_date {
+            handlers::check_schedulable(task_date, config(ctx).planning_horizon_weeks)?;
+        }
+        let update = TaskUpdate {
+            task_date: input.task_date,
+            pin_to_date: input.pin_to_date,
+            rollover_target: match input.rollover_target {
+                MaybeUndefined::Undefined => None,
+                MaybeUndefined::Null => Some(None),
+                MaybeUndefined::Value(target) => Some(Some(target.into())),
+            },
+        };
+        match database::update_task_in_db(pool(ctx), id, update, input.updated_at)
+            .await
+            .map_err(internal)?
+        {
+            MoveTaskOutcome::Moved(task) => Ok(TaskObject(*task)),
+            MoveTaskOutcome::Done => Err(AppError::new(
+                StatusCode::CONFLICT,
+                &format!("Task with ID {id} is done and cannot be moved."),
+            )
+            .into()),
+            MoveTaskOutcome::Stale => Err(handlers::task_changed(id).into()),
+            MoveTaskOutcome::NotFound => Err(task_not_found(id).into()),
+        }
+    }
+
+    /// Marks a task done, as `DELETE /api/tasks/{id}`. With `updatedAt`,
+    /// fails with status 412 if the task changed since.
+    async fn delete_task(
+        &self,
+        ctx: &Context<'_>,
+        id: i64,
+        updated_at: Option<DateTime<Utc>>,
+    ) -> async_graphql::Result<bool> {
+        match database::soft_delete_task_in_db(pool(ctx), id, updated_at)
+            .await
+            .map_err(internal)?
+        {
+            database::TaskChange::Changed(_) => {}
+            database::TaskChange::NotFound => {
+                return Err(AppError::new(
+                    StatusCode::NOT_FOUND,
+                    &format!("Task with ID {id} not found for deletion."),
+                )
+                .into());
+            }
+            database::TaskChange::Stale => return Err(handlers::task_changed(id).into()),
+        }
+        Ok(true)
+    }
+
+    /// Rolls the open tasks of today over, as `PATCH /api/tasks/rollover`,
+    /// returning how many were moved.
+    async fn rollover(&self, ctx: &Context<'_>) -> async_graphql::Result<usize> {
+        let config = config(ctx);
+        let count = database::rollover_tasks_in_db(
+            pool(ctx),
+            config.rollover_policy,
+            RolloverTrigger::Manual,
+            config.stale_after_rollovers,
+        )
+        .await
+        .map_err(internal)?;
+        info!("Rolled over {} tasks through GraphQL.", count);
+        Ok(count)
+    }
+}
+
+fn task_not_found(task_id: i64) -> AppError {
+    AppError::new(
+        StatusCode::NOT_FOUND,
+        &format!("Task with ID {task_id} not found."),
+    )
+}
@@ -2,115 +2,3724 @@
 //
 // This software is licensed under the terms of the MIT License.
 // See the LICENSE file in the project root for the full license text.
-use crate::database;
+use crate::archive::{self, ArchiveCursor, ArchiveSection};
+use crate::auth::SigningSecret;
+use crate::config::Config;
+use crate::database::RestoreOutcome;
+use crate::email::Mailer;
+use crate::export::ExportProfile;
+use crate::faults::{FaultInjector, FaultRule};
+use crate::focus::{self, Focusable};
+use crate::formats::BodyFormat;
+use crate::graphql::ApiSchema;
+use crate::live::{self, ChangeFeed};
+use crate::notify::Notifier;
+use crate::openapi::{Binary, ErrorBody, RolloverOutcome};
+use crate::share::{self, ShareToken};
+use crate::sync::{self, SyncCursor, SyncDelta};
+use crate::table::{self, ClientRow, TableCursor, TableEntity, TableFormat, TablePage, TaskRow};
+use crate::{
+    backup, calendar, clients, clock, colors, contexts, database, goal, jobs, plan, suggest,
+    summary,
+};
+use anyhow::Context;
 use axum::{
-    extract::{Json, Path, State},
-    http::StatusCode,
-    response::{IntoResponse, Response},
+    body::{Body, Bytes},
+    extract::{ws::WebSocketUpgrade, Json, Path, Query, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode, Uri},
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, NaiveDate, SubsecRound, Utc, Weekday};
+use common::{
+    AddClientAliasPayload, AddHolidayPayload, AppliedPlan, ArchiveRecord, BatchOperation,
+    BatchResult, BulkMoveTasksPayload, CapturePayload, ChannelCheck, Client, ClientAlias,
+    ClientColorChange, ClientNameResolution, ClientStats, CreateClientPayload, CreateSharePayload,
+    CreateTaskPayload, CreateTaskSlotPayload, CreateWebhookPayload, Energy, FocusDay, FullExport,
+    FullImportSummary, GoalProgress, Holiday, ImportPreview, ImportStrategy, ItemError, JobStatus,
+    LoginPayload, MoveTaskPayload, MovedTasks, NextWeekPlan, Page, Palette, PaletteSettings,
+    PlanSelectionPayload, PriorityNormalization, RequeueDeliveriesPayload, RequeuedDeliveries,
+    RolloverHistory, RolloverPreview, RolloverTargetPayload, RolloverTrigger, SessionInfo,
+    SetClientColorPayload, SetClientDefaultsPayload, SetFocusPayload, SetPalettePayload,
+    SetWeeklyGoalPayload, SharedWeek, Task, TaskFilter, TaskSlot, TaskStatusFilter, TaskSummary,
+    TimezoneSettings, UpdateClientPayload, Webhook, WebhookDelivery, WebhookEvent, WeekArchive,
+    WeekIntegrityReport, WeekShare, WeeklyGoal, FULL_EXPORT_VERSION,
 };
-use chrono::{Utc, Weekday};
-use common::{CreateTaskPayload, Task};
+use futures_util::{future, stream, StreamExt};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_util::io::ReaderStream;
 use tracing::{debug, error, info};
+use utoipa::IntoParams;
+
+/// Query parameters accepted by `GET /api/tasks`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListTasksQuery {
+    /// Comma-separated list of columns, e.g. `priority,-created_at`.
+    /// A leading `-` sorts in descending order.
+    pub sort: Option<String>,
+    /// On focus days, also return the tasks hidden by focus mode, flagged `deferred`.
+    #[serde(default)]
+    pub include_deferred: bool,
+}
+
+/// Handler for listing tasks for the current week.
+#[utoipa::path(
+    get,
+    path = "/api/tasks",
+    tag = "tasks",
+    params(ListTasksQuery),
+    responses(
+        (status = 200, description = "Tasks of the current week", body = Vec<Task>),
+        (status = 304, description = "Unchanged since the ETag sent in If-None-Match"),
+        (status = 400, description = "Unknown sort field", body = ErrorBody),
+    )
+)]
+pub async fn list_tasks(
+    State(pool): State<SqlitePool>, // State injection (DB pool)
+    Query(query): Query<ListTasksQuery>,
+) -> Result<Json<Vec<Task>>, AppError> {
+    let order_by = parse_sort(query.sort.as_deref())?;
+
+    let mut tasks = database::get_current_week_tasks_sorted_from_db(&pool, &order_by).await?;
+    apply_week_focus(&pool, &mut tasks, query.include_deferred).await?;
+    info!("Successfully retrieved {} tasks.", tasks.len());
+    Ok(Json(tasks))
+}
+
+/// Tasks on a page of `GET /api/tasks/search` when the client does not ask
+/// for a number.
+const DEFAULT_SEARCH_PAGE_SIZE: i64 = 50;
+/// Upper bound on the tasks on a page of `GET /api/tasks/search`.
+const MAX_SEARCH_PAGE_SIZE: i64 = 500;
+
+/// Ordering and page of `GET /api/tasks/search`, next to its `TaskFilter`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchTasksQuery {
+    /// Same syntax as for `GET /api/tasks`.
+    pub sort: Option<String>,
+    /// `next_cursor` of the previous page; the first page without it.
+    pub cursor: Option<String>,
+    /// Tasks per page (50 by default, at most 500).
+    pub limit: Option<i64>,
+}
+
+/// Handler listing the tasks of any week matching a filter, page by page.
+/// Pages are cut by position, so a task created or completed while paging
+/// can shift the following ones by a row.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/search",
+    tag = "tasks",
+    params(TaskFilter, SearchTasksQuery),
+    responses(
+        (status = 200, description = "Page of the matching tasks", body = Page<Task>),
+        (status = 400, description = "Invalid filter, sort, cursor or limit", body = ErrorBody),
+    )
+)]
+pub async fn search_tasks(
+    State(pool): State<SqlitePool>,
+    Query(filter): Query<TaskFilter>,
+    Query(query): Query<SearchTasksQuery>,
+) -> Result<Json<Page<Task>>, AppError> {
+    filter.dates().map_err(|e| invalid_filter(&e))?;
+    let order_by = parse_sort(query.sort.as_deref())?;
+    let offset = match query.cursor.as_deref() {
+        Some(cursor) => cursor
+            .strip_prefix('o')
+            .and_then(|offset| offset.parse::<i64>().ok())
+            .filter(|offset| *offset >= 0)
+            .ok_or_else(|| {
+                AppError::new(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Invalid search cursor '{cursor}'."),
+                )
+            })?,
+        None => 0,
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_PAGE_SIZE);
+    if !(1..=MAX_SEARCH_PAGE_SIZE).contains(&limit) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Page limit must be between 1 and {MAX_SEARCH_PAGE_SIZE}."),
+        ));
+    }
+
+    // One task more than asked tells whether a page follows
+    let mut items =
+        database::get_task_page_from_db(&pool, &filter, &order_by, offset, limit + 1).await?;
+    let next_cursor = (items.len() as i64 > limit).then(|| format!("o{}", offset + limit));
+    items.truncate(limit as usize);
+    debug!(
+        "Task search {:?} from {}: {} tasks",
+        filter,
+        offset,
+        items.len()
+    );
+    Ok(Json(Page { items, next_cursor }))
+}
+
+/// Query parameters accepted by `GET /api/tasks/week`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WeekBoardQuery {
+    /// Only `day` is supported, and it is the default.
+    pub group_by: Option<String>,
+    /// Ordering of the tasks within each day, same syntax as `GET /api/tasks`.
+    pub sort: Option<String>,
+    /// Same as for `GET /api/tasks`.
+    #[serde(default)]
+    pub include_deferred: bool,
+    /// Only tasks of this context, plus those without any.
+    pub context: Option<String>,
+}
+
+/// Handler for the weekly board: tasks of the current week bucketed by day.
+/// Every day of the week is present in the response, even when it has no task.
+/// Tasks are returned as `TaskSummary`, the board has no use for the rest.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/week",
+    tag = "tasks",
+    params(WeekBoardQuery),
+    responses(
+        (
+            status = 200,
+            description = "Tasks of each day of the current week",
+            body = BTreeMap<NaiveDate, Vec<TaskSummary>>,
+        ),
+        (status = 304, description = "Unchanged since the ETag sent in If-None-Match"),
+        (status = 400, description = "Invalid grouping, sort or context", body = ErrorBody),
+    )
+)]
+pub async fn week_board(
+    State(pool): State<SqlitePool>,
+    Query(query): Query<WeekBoardQuery>,
+) -> Result<Json<BTreeMap<NaiveDate, Vec<TaskSummary>>>, AppError> {
+    if let Some(group_by) = query.group_by.as_deref().filter(|g| *g != "day") {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Cannot group tasks by '{group_by}', only 'day' is supported."),
+        ));
+    }
+
+    let order_by = parse_sort(query.sort.as_deref())?;
+    let context = parse_context(query.context.as_deref())?;
+    let mut tasks =
+        database::get_current_week_task_summaries_from_db(&pool, &order_by, context.as_deref())
+            .await?;
+    apply_week_focus(&pool, &mut tasks, query.include_deferred).await?;
+    let (week_start, _) = database::current_week_range();
+
+    Ok(Json(group_tasks_by_day(tasks, week_start)))
+}
+
+/// Hides (or flags) the tasks deferred by the focus days of the current week.
+async fn apply_week_focus<T: Focusable>(
+    pool: &SqlitePool,
+    tasks: &mut Vec<T>,
+    include_deferred: bool,
+) -> Result<(), AppError> {
+    let (week_start, week_end) = database::current_week_range();
+    let focus_days = database::get_focus_days_from_db(pool, week_start, week_end).await?;
+    focus::apply_focus(tasks, &focus_days, include_deferred);
+    Ok(())
+}
+
+/// Buckets tasks into the seven days starting at `week_start`, keeping the
+/// order in which they were given. Tasks outside that week are dropped.
+fn group_tasks_by_day(
+    tasks: Vec<TaskSummary>,
+    week_start: NaiveDate,
+) -> BTreeMap<NaiveDate, Vec<TaskSummary>> {
+    let mut days: BTreeMap<NaiveDate, Vec<TaskSummary>> = week_start
+        .iter_days()
+        .take(7)
+        .map(|day| (day, Vec::new()))
+        .collect();
+
+    for task in tasks {
+        if let Some(day) = days.get_mut(&task.task_date) {
+            day.push(task);
+        }
+    }
+
+    days
+}
+
+/// Error of a filter selecting no day, such as a range ending before it
+/// starts.
+pub(crate) fn invalid_filter(error: &str) -> AppError {
+    AppError::new(
+        StatusCode::BAD_REQUEST,
+        &format!("Invalid filter: {error}."),
+    )
+}
+
+/// Translates an optional `?sort=` value into an `ORDER BY` clause,
+/// rejecting unknown columns with a 400.
+fn parse_sort(sort: Option<&str>) -> Result<String, AppError> {
+    match sort {
+        Some(spec) => database::build_order_by(spec).map_err(|field| {
+            error!("Validation failed: cannot sort on '{}'.", field);
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                &format!("Cannot sort on unknown field '{field}'."),
+            )
+        }),
+        None => Ok(database::DEFAULT_ORDER_BY.to_string()),
+    }
+}
+
+/// Handler for turning on focus mode for a day: list responses only show its
+/// `top_n` highest priority tasks. The other tasks keep their date.
+#[utoipa::path(
+    post,
+    path = "/api/days/{date}/focus",
+    tag = "planning",
+    params(("date" = NaiveDate, Path, description = "Day, e.g. 2025-07-14")),
+    request_body = SetFocusPayload,
+    responses(
+        (status = 200, description = "Focus mode on", body = FocusDay),
+        (status = 400, description = "Invalid top_n", body = ErrorBody),
+    )
+)]
+pub async fn set_focus_day(
+    State(pool): State<SqlitePool>,
+    Path(day): Path<NaiveDate>,
+    Json(payload): Json<SetFocusPayload>,
+) -> Result<Json<FocusDay>, AppError> {
+    let top_n = payload.top_n.unwrap_or(focus::DEFAULT_FOCUS_TOP_N);
+    if top_n < 1 {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "A focus day must keep at least one task.",
+        ));
+    }
+
+    Ok(Json(
+        database::set_focus_day_in_db(&pool, day, top_n).await?,
+    ))
+}
+
+/// Query parameters accepted by `POST /api/days/{date}/normalize-priorities`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NormalizePrioritiesQuery {
+    /// Tasks sharing a priority keep sharing one, instead of being numbered
+    /// one after the other.
+    #[serde(default)]
+    pub keep_ties: bool,
+}
+
+/// Handler rewriting the priorities of a day's open tasks as a clean
+/// sequence (1, 2, 3, ...) that keeps their order.
+#[utoipa::path(
+    post,
+    path = "/api/days/{date}/normalize-priorities",
+    tag = "planning",
+    params(
+        ("date" = NaiveDate, Path, description = "Day, e.g. 2025-07-14"),
+        NormalizePrioritiesQuery,
+    ),
+    responses(
+        (status = 200, description = "Priorities rewritten", body = PriorityNormalization),
+    )
+)]
+pub async fn normalize_priorities(
+    State(pool): State<SqlitePool>,
+    Path(day): Path<NaiveDate>,
+    Query(query): Query<NormalizePrioritiesQuery>,
+) -> Result<Json<PriorityNormalization>, AppError> {
+    Ok(Json(
+        database::normalize_priorities_in_db(&pool, day, query.keep_ties).await?,
+    ))
+}
+
+/// Handler for turning off focus mode for a day.
+#[utoipa::path(
+    delete,
+    path = "/api/days/{date}/focus",
+    tag = "planning",
+    params(("date" = NaiveDate, Path, description = "Day, e.g. 2025-07-14")),
+    responses(
+        (status = 204, description = "Focus mode off"),
+        (status = 404, description = "Day not in focus mode", body = ErrorBody),
+    )
+)]
+pub async fn clear_focus_day(
+    State(pool): State<SqlitePool>,
+    Path(day): Path<NaiveDate>,
+) -> Result<StatusCode, AppError> {
+    if database::clear_focus_day_in_db(&pool, day).await? {
+        info!("Focus mode off for {}.", day);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            &format!("{day} is not in focus mode."),
+        ))
+    }
+}
+
+/// Query parameters accepted by `POST /api/tasks`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CreateTaskQuery {
+    /// Allows adding a task to an archived client.
+    #[serde(default)]
+    pub allow_archived: bool,
+}
+
+/// Longest `Idempotency-Key` accepted, enough for a UUID or a hash.
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// Handler for creating a new task. With an `Idempotency-Key`, retries of
+/// the same request get the response of the first one back instead of
+/// creating the task again.
+#[utoipa::path(
+    post,
+    path = "/api/tasks",
+    tag = "tasks",
+    params(
+        CreateTaskQuery,
+        ("Idempotency-Key" = Option<String>, Header, description = "Key unique to this request, sent again with its retries"),
+    ),
+    request_body = CreateTaskPayload,
+    responses(
+        (status = 201, description = "Task created, or created by an earlier request with the same Idempotency-Key (Idempotent-Replayed: true)", body = Task),
+        (status = 400, description = "Invalid task", body = ErrorBody),
+        (status = 409, description = "Client is archived", body = ErrorBody),
+        (status = 422, description = "Idempotency-Key already used for another request", body = ErrorBody),
+    )
+)]
+pub async fn create_task(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+    Query(query): Query<CreateTaskQuery>,
+    headers: HeaderMap,
+    Json(mut payload): Json<CreateTaskPayload>, // Extracting the request body as JSON
+) -> Result<Response, AppError> {
+    debug!(
+        "Received request to create task for client: {}",
+        payload.client_name
+    );
+    let key = idempotency_key(&headers)?;
+    // Hashed as sent, before the checks normalize it
+    let request_hash = request_hash(&payload, query.allow_archived)?;
+    check_new_task(&pool, &config, &mut payload, query.allow_archived).await?;
+
+    let Some(key) = key else {
+        let new_task = database::create_task_in_db(&pool, payload).await?;
+        info!("Task created successfully with ID: {}", new_task.id);
+
+        // Return a 201 Created status with the new task as JSON.
+        return Ok((StatusCode::CREATED, Json(new_task)).into_response());
+    };
+    let ttl = config.idempotency_key_ttl;
+    match database::create_task_idempotently_in_db(&pool, key, &request_hash, payload, ttl).await? {
+        database::IdempotentCreation::Created(task) => {
+            info!("Task created successfully with ID: {}", task.id);
+            Ok((StatusCode::CREATED, Json(*task)).into_response())
+        }
+        database::IdempotentCreation::Replayed { status, body } => {
+            info!("Replaying the task creation of Idempotency-Key '{}'", key);
+            let status = StatusCode::from_u16(status).context("Invalid stored status")?;
+            Ok((
+                status,
+                [
+                    (header::CONTENT_TYPE, "application/json"),
+                    (HeaderName::from_static("idempotent-replayed"), "true"),
+                ],
+                body,
+            )
+                .into_response())
+        }
+        database::IdempotentCreation::Mismatch => Err(AppError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Idempotency-Key was already used for another request; use a new key for each task.",
+        )),
+    }
+}
+
+/// The `Idempotency-Key` of a request, if it was sent with one.
+fn idempotency_key(headers: &HeaderMap) -> Result<Option<&str>, AppError> {
+    let Some(value) = headers.get("idempotency-key") else {
+        return Ok(None);
+    };
+    match value.to_str().map(str::trim) {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN => Ok(Some(key)),
+        _ => Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Invalid Idempotency-Key, expected 1 to {MAX_IDEMPOTENCY_KEY_LEN} visible ASCII characters."
+            ),
+        )),
+    }
+}
+
+/// Hash telling the retries of a task creation from other requests.
+fn request_hash(payload: &CreateTaskPayload, allow_archived: bool) -> Result<String, AppError> {
+    let body = serde_json::to_vec(&(payload, allow_archived)).context("Failed to hash the task")?;
+    Ok(Sha256::digest(body)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/// Handler for creating several tasks at once, all or none of them.
+/// Every invalid task is reported with its index in the array.
+#[utoipa::path(
+    post,
+    path = "/api/tasks/bulk",
+    tag = "tasks",
+    params(CreateTaskQuery),
+    request_body = Vec<CreateTaskPayload>,
+    responses(
+        (status = 201, description = "Tasks created", body = Vec<Task>),
+        (status = 400, description = "Invalid tasks, listed in errors by index", body = ErrorBody),
+    )
+)]
+pub async fn create_tasks_bulk(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+    Query(query): Query<CreateTaskQuery>,
+    Json(mut payloads): Json<Vec<CreateTaskPayload>>,
+) -> Result<(StatusCode, Json<Vec<Task>>), AppError> {
+    debug!(
+        "Received request to create {} tasks at once.",
+        payloads.len()
+    );
+
+    if payloads.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "At least one task must be provided.",
+        ));
+    }
+
+    let mut errors = Vec::new();
+    for (index, payload) in payloads.iter_mut().enumerate() {
+        if let Err(err) = check_new_task(&pool, &config, payload, query.allow_archived).await {
+            errors.push(ItemError {
+                index,
+                error: err.message,
+            });
+        }
+    }
+    if !errors.is_empty() {
+        return Err(AppError::with_items(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "{} of {} tasks are invalid, none were created.",
+                errors.len(),
+                payloads.len()
+            ),
+            errors,
+        ));
+    }
+
+    let tasks = database::create_tasks_in_db(&pool, payloads).await?;
+
+    info!("{} tasks created at once.", tasks.len());
+    Ok((StatusCode::CREATED, Json(tasks)))
+}
+
+/// Handler for quick capture: creates a task for today from a description,
+/// filling the client and priority from the capture defaults. Capturing
+/// the same task twice returns the existing one with `200 OK`.
+#[utoipa::path(
+    post,
+    path = "/api/capture",
+    tag = "tasks",
+    request_body = CapturePayload,
+    responses(
+        (status = 201, description = "Task captured", body = Task),
+        (status = 200, description = "Same task already captured", body = Task),
+        (status = 400, description = "Invalid task", body = ErrorBody),
+        (status = 409, description = "Client is archived", body = ErrorBody),
+    )
+)]
+pub async fn capture_task(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+    Json(capture): Json<CapturePayload>,
+) -> Result<(StatusCode, Json<Task>), AppError> {
+    let mut payload = CreateTaskPayload {
+        client_name: capture
+            .client_name
+            .unwrap_or_else(|| config.capture.default_client.clone()),
+        description: capture.description.trim().to_string(),
+        task_date: Some(clock::today()),
+        priority: capture.priority.or(config.capture.default_priority),
+        ..Default::default()
+    };
+    check_new_task(&pool, &config, &mut payload, false).await?;
+
+    match database::capture_task_in_db(&pool, payload).await? {
+        database::CaptureOutcome::Created(task) => {
+            info!("Task {} captured.", task.id);
+            Ok((StatusCode::CREATED, Json(*task)))
+        }
+        database::CaptureOutcome::Duplicate(task) => {
+            debug!("Captured task is a duplicate of task {}.", task.id);
+            Ok((StatusCode::OK, Json(*task)))
+        }
+    }
+}
+
+/// Validates a new task, normalizing its context, before it is created.
+#[allow(clippy::uninlined_format_args)]
+pub(crate) async fn check_new_task(
+    pool: &SqlitePool,
+    config: &Config,
+    payload: &mut CreateTaskPayload,
+    allow_archived: bool,
+) -> Result<(), AppError> {
+    // Validate the payload : name, description and date
+    validate_task_payload(payload)?;
+    payload.context = parse_context(payload.context.as_deref())?;
+
+    // Determine the actual task_date to be used
+    let task_date_to_use = payload.task_date.unwrap_or_else(clock::today);
+    check_schedulable(task_date_to_use, config.planning_horizon_weeks)?;
+
+    if !allow_archived
+        && database::get_client_archived_at_from_db(pool, &payload.client_name)
+            .await?
+            .is_some()
+    {
+        error!(
+            "Validation failed: Client {} is archived.",
+            payload.client_name
+        );
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            &format!(
+                "Client '{}' is archived. Use ?allow_archived=true to add a task anyway.",
+                payload.client_name
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks that a task date falls within the current week, or the
+/// `horizon_weeks` following it when planning ahead is allowed. Days before
+/// the current week are always rejected.
+#[allow(clippy::uninlined_format_args)]
+pub(crate) fn check_schedulable(task_date: NaiveDate, horizon_weeks: u32) -> Result<(), AppError> {
+    let today = clock::today();
+    let current_week_start = today.week(Weekday::Mon).first_day();
+    let last_day = today.week(Weekday::Mon).last_day() + Duration::weeks(horizon_weeks.into());
+
+    if task_date < current_week_start || task_date > last_day {
+        error!(
+            "Validation failed: Task date {} is outside the schedulable days ({} to {}).",
+            task_date, current_week_start, last_day
+        );
+        let window = match horizon_weeks {
+            0 => "the current week".to_string(),
+            1 => "the current or the next week".to_string(),
+            n => format!("the current week or the next {n} weeks"),
+        };
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Task date must be within {} (from {} to {}).",
+                window, current_week_start, last_day
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn parse_context(context: Option<&str>) -> Result<Option<String>, AppError> {
+    context
+        .map(|raw| {
+            contexts::normalize_context(raw).ok_or_else(|| {
+                error!("Validation failed: invalid context '{}'.", raw);
+                AppError::new(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Invalid context '{raw}', expected a name such as @office."),
+                )
+            })
+        })
+        .transpose()
+}
+
+/// Checks the fields of a task payload that do not depend on the current date.
+/// Shared by task creation and imports.
+fn validate_task_payload(payload: &CreateTaskPayload) -> Result<(), AppError> {
+    if payload.client_name.is_empty() || payload.description.is_empty() {
+        error!("Validation failed: Client name or description is empty.");
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Client name and description cannot be empty.",
+        ));
+    }
+
+    if payload.estimate_minutes.is_some_and(|m| m <= 0) {
+        error!("Validation failed: estimate_minutes must be positive.");
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "estimate_minutes must be a positive number of minutes.",
+        ));
+    }
+
+    // An external reference is only meaningful as a pair.
+    if payload.external_source.is_some() != payload.external_id.is_some() {
+        error!("Validation failed: external_source and external_id must be provided together.");
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "external_source and external_id must be provided together.",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Handler for the first step of weekly planning: the open tasks to carry
+/// over, the tasks that usually come back, and the room left on each day.
+#[utoipa::path(
+    get,
+    path = "/api/plan/next-week",
+    tag = "planning",
+    responses(
+        (status = 200, description = "Plan of next week", body = NextWeekPlan),
+    )
+)]
+pub async fn get_next_week_plan(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+) -> Result<Json<NextWeekPlan>, AppError> {
+    let (week_start, week_end) = plan::next_week_range(clock::today());
+
+    let carried_over = database::get_open_tasks_before_from_db(&pool, week_start).await?;
+    let history =
+        database::get_tasks_between_from_db(&pool, plan::lookback_start(week_start), week_end)
+            .await?;
+    let planned = database::get_planned_load_from_db(&pool, week_start, week_end).await?;
+
+    Ok(Json(NextWeekPlan {
+        week_start,
+        week_end,
+        carried_over,
+        recurring_candidates: plan::recurring_candidates(&history, week_start),
+        capacity: plan::week_capacity(week_start, &planned, config.daily_capacity_minutes),
+    }))
+}
+
+/// Handler for the second step of weekly planning: moves the selected tasks
+/// and creates the new ones, all or nothing.
+#[utoipa::path(
+    post,
+    path = "/api/plan/next-week",
+    tag = "planning",
+    request_body = PlanSelectionPayload,
+    responses(
+        (status = 201, description = "Plan applied", body = AppliedPlan),
+        (status = 400, description = "Date outside next week or invalid task", body = ErrorBody),
+        (status = 409, description = "Task not open or client archived", body = ErrorBody),
+    )
+)]
+pub async fn apply_next_week_plan(
+    State(pool): State<SqlitePool>,
+    Json(mut payload): Json<PlanSelectionPayload>,
+) -> Result<(StatusCode, Json<AppliedPlan>), AppError> {
+    let (week_start, week_end) = plan::next_week_range(clock::today());
+    let outside_week = |day: NaiveDate| {
+        error!("Validation failed: {} is not in next week.", day);
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Planned dates must be within next week (from {week_start} to {week_end})."),
+        )
+    };
+
+    for selection in &payload.carry_over {
+        if selection.task_date < week_start || selection.task_date > week_end {
+            return Err(outside_week(selection.task_date));
+        }
+    }
+    for task in &mut payload.new_tasks {
+        validate_task_payload(task)?;
+        task.context = parse_context(task.context.as_deref())?;
+        let task_date = *task.task_date.get_or_insert(week_start);
+        if task_date < week_start || task_date > week_end {
+            return Err(outside_week(task_date));
+        }
+        if database::get_client_archived_at_from_db(&pool, &task.client_name)
+            .await?
+            .is_some()
+        {
+            return Err(AppError::new(
+                StatusCode::CONFLICT,
+                &format!("Client '{}' is archived.", task.client_name),
+            ));
+        }
+    }
+
+    match database::apply_plan_in_db(&pool, &payload.carry_over, payload.new_tasks).await? {
+        database::ApplyPlanOutcome::Applied(applied) => Ok((StatusCode::CREATED, Json(applied))),
+        database::ApplyPlanOutcome::TaskNotOpen(task_id) => Err(AppError::new(
+            StatusCode::CONFLICT,
+            &format!("Task with ID {task_id} not found or already completed; nothing was planned."),
+        )),
+    }
+}
+
+/// Query parameters accepted by `GET /api/tasks/today`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TodayQuery {
+    /// Only tasks actionable in this context (e.g. `@home`), plus those without any.
+    pub context: Option<String>,
+    /// Same as for `GET /api/tasks`.
+    #[serde(default)]
+    pub include_deferred: bool,
+}
+
+/// Handler for the agenda of today: its open tasks by priority, optionally
+/// restricted to where the user currently is.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/today",
+    tag = "tasks",
+    params(TodayQuery),
+    responses(
+        (status = 200, description = "Open tasks of today", body = Vec<Task>),
+        (status = 304, description = "Unchanged since the ETag sent in If-None-Match"),
+        (status = 400, description = "Invalid context", body = ErrorBody),
+    )
+)]
+pub async fn today_tasks(
+    State(pool): State<SqlitePool>,
+    Query(query): Query<TodayQuery>,
+) -> Result<Json<Vec<Task>>, AppError> {
+    let context = parse_context(query.context.as_deref())?;
+    let today = clock::today();
+    let mut tasks =
+        database::get_open_tasks_for_day_from_db(&pool, today, context.as_deref()).await?;
+    apply_week_focus(&pool, &mut tasks, query.include_deferred).await?;
+    Ok(Json(tasks))
+}
+
+/// Query parameters accepted by `GET /api/tasks/suggest`.
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SuggestTasksQuery {
+    /// Only suggest tasks needing this kind of attention.
+    pub energy: Option<Energy>,
+    /// Length of the free slot to fill.
+    pub minutes: i32,
+    /// Only tasks of this context, plus those without any.
+    pub context: Option<String>,
+}
+
+/// Handler suggesting tasks from today's list that fit in a free slot,
+/// e.g. the half hour before a meeting.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/suggest",
+    tag = "tasks",
+    params(SuggestTasksQuery),
+    responses(
+        (status = 200, description = "Tasks fitting in the slot", body = Vec<Task>),
+        (status = 400, description = "Invalid slot or context", body = ErrorBody),
+    )
+)]
+pub async fn suggest_tasks(
+    State(pool): State<SqlitePool>,
+    Query(query): Query<SuggestTasksQuery>,
+) -> Result<Json<Vec<Task>>, AppError> {
+    if query.minutes <= 0 {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "minutes must be a positive number.",
+        ));
+    }
+
+    let context = parse_context(query.context.as_deref())?;
+    let today = clock::today();
+    let tasks = database::get_open_tasks_for_day_from_db(&pool, today, context.as_deref()).await?;
+    let suggestions = suggest::suggest_tasks(tasks, query.energy, query.minutes);
+    debug!(
+        "Suggested {} tasks for a {} minute slot.",
+        suggestions.len(),
+        query.minutes
+    );
+    Ok(Json(suggestions))
+}
+
+/// Handler for deleting a task by ID, which marks it done.
+#[utoipa::path(
+    delete,
+    path = "/api/tasks/{id}",
+    tag = "tasks",
+    params(("id" = i64, Path, description = "ID of the task"), ("If-Match" = Option<String>, Header, description = "`updated_at` of the task as last read, quoted; 412 if it changed since")),
+    responses(
+        (status = 204, description = "Task deleted"),
+        (status = 404, description = "Task not found", body = ErrorBody),
+        (status = 412, description = "Task changed since the version in If-Match", body = ErrorBody),
+    )
+)]
+pub async fn delete_task(
+    State(pool): State<SqlitePool>,
+    Path(task_id): Path<i64>, // Extract task ID from the URL path
+    headers: HeaderMap,
+) -> Result<StatusCode, AppError> {
+    debug!("Attempting to delete task with ID: {}", task_id);
+    let expected = expected_version(&headers, None)?;
+
+    //let deleted = database::delete_task_from_db(&pool, task_id).await?;
+    match database::soft_delete_task_in_db(&pool, task_id, expected).await? {
+        database::TaskChange::Changed(_) => {
+            info!("Task with ID {} deleted successfully.", task_id);
+            Ok(StatusCode::NO_CONTENT) // 204 No Content for successful deletion
+        }
+        database::TaskChange::NotFound => {
+            error!("Task with ID {} not found for deletion.", task_id);
+            Err(AppError::new(
+                StatusCode::NOT_FOUND,
+                &format!("Task with ID {task_id} not found for deletion."),
+            ))
+        }
+        database::TaskChange::Stale => Err(task_changed(task_id)),
+    }
+}
+
+/// Handler for moving an open task to another day of the current week (or
+/// of the planning horizon), e.g. when it is dragged to another column of
+/// the board.
+#[utoipa::path(
+    patch,
+    path = "/api/tasks/{id}/move",
+    tag = "tasks",
+    params(("id" = i64, Path, description = "ID of the task"), ("If-Match" = Option<String>, Header, description = "`updated_at` of the task as last read, quoted; 412 if it changed since")),
+    request_body = MoveTaskPayload,
+    responses(
+        (status = 200, description = "Task moved", body = Task),
+        (status = 400, description = "Date out of the schedulable days", body = ErrorBody),
+        (status = 404, description = "Task not found", body = ErrorBody),
+        (status = 409, description = "Task is done", body = ErrorBody),
+        (status = 412, description = "Task changed since the version in If-Match", body = ErrorBody),
+    )
+)]
+pub async fn move_task(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+    Path(task_id): Path<i64>,
+    headers: HeaderMap,
+    Json(payload): Json<MoveTaskPayload>,
+) -> Result<Json<Task>, AppError> {
+    debug!("Moving task {} to {}", task_id, payload.task_date);
+    check_schedulable(payload.task_date, config.planning_horizon_weeks)?;
+    let expected = expected_version(&headers, payload.updated_at)?;
+
+    match database::move_task_in_db(&pool, task_id, payload.task_date, expected).await? {
+        database::MoveTaskOutcome::Moved(task) => Ok(Json(*task)),
+        database::MoveTaskOutcome::Done => Err(AppError::new(
+            StatusCode::CONFLICT,
+            &format!("Task with ID {task_id} is done and cannot be moved."),
+        )),
+        database::MoveTaskOutcome::Stale => Err(task_changed(task_id)),
+        database::MoveTaskOutcome::NotFound => Err(task_not_found(task_id)),
+    }
+}
+
+/// Handler for pinning a task to its day, so that the rollover leaves it there.
+#[utoipa::path(
+    post,
+    path = "/api/tasks/{id}/pin",
+    tag = "tasks",
+    params(("id" = i64, Path, description = "ID of the task"), ("If-Match" = Option<String>, Header, description = "`updated_at` of the task as last read, quoted; 412 if it changed since")),
+    responses(
+        (status = 200, description = "Task pinned", body = Task),
+        (status = 404, description = "Task not found", body = ErrorBody),
+        (status = 412, description = "Task changed since the version in If-Match", body = ErrorBody),
+    )
+)]
+pub async fn pin_task(
+    State(pool): State<SqlitePool>,
+    Path(task_id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<Json<Task>, AppError> {
+    let expected = expected_version(&headers, None)?;
+    changed_task(
+        task_id,
+        database::set_task_pinned_in_db(&pool, task_id, true, expected).await?,
+    )
+}
+
+/// Handler for letting a pinned task roll over again.
+#[utoipa::path(
+    delete,
+    path = "/api/tasks/{id}/pin",
+    tag = "tasks",
+    params(("id" = i64, Path, description = "ID of the task"), ("If-Match" = Option<String>, Header, description = "`updated_at` of the task as last read, quoted; 412 if it changed since")),
+    responses(
+        (status = 200, description = "Task unpinned", body = Task),
+        (status = 404, description = "Task not found", body = ErrorBody),
+        (status = 412, description = "Task changed since the version in If-Match", body = ErrorBody),
+    )
+)]
+pub async fn unpin_task(
+    State(pool): State<SqlitePool>,
+    Path(task_id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<Json<Task>, AppError> {
+    let expected = expected_version(&headers, None)?;
+    changed_task(
+        task_id,
+        database::set_task_pinned_in_db(&pool, task_id, false, expected).await?,
+    )
+}
+
+/// Handler for choosing the day a task rolls over to, e.g. `monday` or
+/// `end-of-week`, in place of the rollover policy.
+#[utoipa::path(
+    put,
+    path = "/api/tasks/{id}/rollover-target",
+    tag = "tasks",
+    params(("id" = i64, Path, description = "ID of the task"), ("If-Match" = Option<String>, Header, description = "`updated_at` of the task as last read, quoted; 412 if it changed since")),
+    request_body = RolloverTargetPayload,
+    responses(
+        (status = 200, description = "Rollover target set", body = Task),
+        (status = 404, description = "Task not found", body = ErrorBody),
+        (status = 412, description = "Task changed since the version in If-Match", body = ErrorBody),
+    )
+)]
+pub async fn set_task_rollover_target(
+    State(pool): State<SqlitePool>,
+    Path(task_id): Path<i64>,
+    headers: HeaderMap,
+    Json(payload): Json<RolloverTargetPayload>,
+) -> Result<Json<Task>, AppError> {
+    let expected = expected_version(&headers, payload.updated_at)?;
+    let target = Some(payload.rollover_target);
+    changed_task(
+        task_id,
+        database::set_task_rollover_target_in_db(&pool, task_id, target, expected).await?,
+    )
+}
+
+/// Handler for letting a task follow the rollover policy again.
+#[utoipa::path(
+    delete,
+    path = "/api/tasks/{id}/rollover-target",
+    tag = "tasks",
+    params(("id" = i64, Path, description = "ID of the task"), ("If-Match" = Option<String>, Header, description = "`updated_at` of the task as last read, quoted; 412 if it changed since")),
+    responses(
+        (status = 200, description = "Rollover target cleared", body = Task),
+        (status = 404, description = "Task not found", body = ErrorBody),
+        (status = 412, description = "Task changed since the version in If-Match", body = ErrorBody),
+    )
+)]
+pub async fn clear_task_rollover_target(
+    State(pool): State<SqlitePool>,
+    Path(task_id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<Json<Task>, AppError> {
+    let expected = expected_version(&headers, None)?;
+    changed_task(
+        task_id,
+        database::set_task_rollover_target_in_db(&pool, task_id, None, expected).await?,
+    )
+}
+
+/// Handler for moving every open task matching a filter to another day at
+/// once, e.g. all of a client's tasks from Thursday to next Monday, along
+/// with their unfinished slots of the day they leave. The target may be any
+/// day tasks can be scheduled on.
+#[utoipa::path(
+    post,
+    path = "/api/tasks/bulk-move",
+    tag = "tasks",
+    request_body = BulkMoveTasksPayload,
+    responses(
+        (status = 200, description = "Tasks moved", body = MovedTasks),
+        (status = 400, description = "Invalid filter, filter not limited to open tasks, or date out of the schedulable days", body = ErrorBody),
+    )
+)]
+pub async fn bulk_move_tasks(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+    Json(payload): Json<BulkMoveTasksPayload>,
+) -> Result<Json<MovedTasks>, AppError> {
+    let filter = &payload.filter;
+    debug!(
+        "Moving tasks matching {:?} to {}",
+        filter, payload.target_date
+    );
+
+    if !filter.is_narrowed() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "The filter must name a client, a date, or both.",
+        ));
+    }
+    filter.dates().map_err(|e| invalid_filter(&e))?;
+    // Done tasks stay on the day they were done, as with a single move.
+    if filter.status != TaskStatusFilter::Open {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Only open tasks can be moved; the filter status must be open.",
+        ));
+    }
+    check_schedulable(payload.target_date, config.planning_horizon_weeks)?;
+
+    let task_ids = database::move_matching_tasks_in_db(&pool, filter, payload.target_date).await?;
+    Ok(Json(MovedTasks { task_ids }))
+}
+
+/// Handler for applying several edits at once, in order, all or none of
+/// them, e.g. when the edits made offline are sent back in one request.
+/// Invalid operations are all reported with their index before anything
+/// is applied; a task missing or already done stops the batch at its index.
+#[utoipa::path(
+    post,
+    path = "/api/batch",
+    tag = "tasks",
+    params(CreateTaskQuery),
+    request_body = Vec<BatchOperation>,
+    responses(
+        (status = 200, description = "Operations applied, with the task each left, in order", body = Vec<BatchResult>),
+        (status = 400, description = "Invalid operations, listed in errors by index", body = ErrorBody),
+        (status = 404, description = "Task of an operation not found, listed in errors", body = ErrorBody),
+        (status = 409, description = "Task of an operation already done, listed in errors", body = ErrorBody),
+        (status = 412, description = "Task of an operation changed since its updated_at, listed in errors", body = ErrorBody),
+    )
+)]
+pub async fn apply_batch(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+    Query(query): Query<CreateTaskQuery>,
+    Json(mut operations): Json<Vec<BatchOperation>>,
+) -> Result<Json<Vec<BatchResult>>, AppError> {
+    debug!("Received a batch of {} operations.", operations.len());
+
+    if operations.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "At least one operation must be provided.",
+        ));
+    }
+
+    let mut errors = Vec::new();
+    for (index, operation) in operations.iter_mut().enumerate() {
+        let checked = match operation {
+            BatchOperation::Create(payload) => {
+                check_new_task(&pool, &config, payload, query.allow_archived).await
+            }
+            BatchOperation::Move { task_date, .. } => {
+                check_schedulable(*task_date, config.planning_horizon_weeks)
+            }
+            BatchOperation::Complete { .. } | BatchOperation::Delete { .. } => Ok(()),
+        };
+        if let Err(err) = checked {
+            errors.push(ItemError {
+                index,
+                error: err.message,
+            });
+        }
+    }
+    if !errors.is_empty() {
+        return Err(AppError::with_items(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "{} of {} operations are invalid, none were applied.",
+                errors.len(),
+                operations.len()
+            ),
+            errors,
+        ));
+    }
+
+    let (code, index, error) = match database::apply_batch_in_db(&pool, operations).await? {
+        database::BatchOutcome::Applied(tasks) => {
+            let results = tasks
+                .into_iter()
+                .enumerate()
+                .map(|(index, task)| BatchResult { index, task })
+                .collect();
+            return Ok(Json(results));
+        }
+        database::BatchOutcome::NotFound { index, task_id } => (
+            StatusCode::NOT_FOUND,
+            index,
+            format!("Task with ID {task_id} not found."),
+        ),
+        database::BatchOutcome::Done { index, task_id } => (
+            StatusCode::CONFLICT,
+            index,
+            format!("Task with ID {task_id} is already done."),
+        ),
+        database::BatchOutcome::Stale { index, task_id } => (
+            StatusCode::PRECONDITION_FAILED,
+            index,
+            format!("Task with ID {task_id} was changed since it was read."),
+        ),
+    };
+    Err(AppError::with_items(
+        code,
+        &format!("Operation {index} failed, none were applied."),
+        vec![ItemError { index, error }],
+    ))
+}
+
+/// Query parameters accepted by `POST /api/weeks/{week}/archive`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ArchiveWeekQuery {
+    /// Also delete the archived tasks from the working tables.
+    #[serde(default)]
+    pub purge: bool,
+}
+
+/// Parses the ISO week of a path, e.g. `2025-W29`.
+fn parse_week(week: &str) -> Result<(NaiveDate, NaiveDate), AppError> {
+    plan::parse_iso_week(week).ok_or_else(|| {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Invalid week '{week}', expected an ISO week such as 2025-W29."),
+        )
+    })
+}
+
+/// Handler closing the books on a past week: once all its tasks are done or
+/// carried over, their snapshot is archived. Returns the integrity report,
+/// with `409 Conflict` when open tasks or slots prevent the archive.
+#[utoipa::path(
+    post,
+    path = "/api/weeks/{week}/archive",
+    tag = "weeks",
+    params(
+        ("week" = String, Path, description = "ISO week, e.g. 2025-W29"),
+        ArchiveWeekQuery,
+    ),
+    responses(
+        (status = 201, description = "Week archived", body = WeekIntegrityReport),
+        (status = 400, description = "Invalid week or week not over", body = ErrorBody),
+        (
+            status = 409,
+            description = "Open tasks or slots left, or already archived",
+            body = WeekIntegrityReport,
+        ),
+    )
+)]
+pub async fn archive_week(
+    State(pool): State<SqlitePool>,
+    Path(week): Path<String>,
+    Query(query): Query<ArchiveWeekQuery>,
+) -> Result<(StatusCode, Json<WeekIntegrityReport>), AppError> {
+    let range = parse_week(&week)?;
+    if range.1 >= clock::today() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Week {week} is not over yet."),
+        ));
+    }
+
+    match database::archive_week_in_db(&pool, &week, range, query.purge).await? {
+        database::ArchiveWeekOutcome::Archived(report) => Ok((StatusCode::CREATED, Json(report))),
+        database::ArchiveWeekOutcome::Incomplete(report) => {
+            error!(
+                "Week {} cannot be archived: {} tasks and {} slots are still open.",
+                week,
+                report.open_task_ids.len(),
+                report.open_slot_ids.len()
+            );
+            Ok((StatusCode::CONFLICT, Json(report)))
+        }
+        database::ArchiveWeekOutcome::AlreadyArchived => Err(AppError::new(
+            StatusCode::CONFLICT,
+            &format!("Week {week} is already archived."),
+        )),
+    }
+}
+
+fn shares_disabled() -> AppError {
+    AppError::new(StatusCode::NOT_FOUND, "Share links are not enabled.")
+}
+
+/// Handler creating a read-only link to a week, to show its plan to
+/// someone without access to the API.
+#[utoipa::path(
+    post,
+    path = "/api/shares",
+    tag = "weeks",
+    request_body = CreateSharePayload,
+    responses(
+        (status = 201, description = "Share link created", body = WeekShare),
+        (status = 400, description = "Invalid week or expiry", body = ErrorBody),
+        (status = 404, description = "Share links not enabled", body = ErrorBody),
+    )
+)]
+pub async fn create_share(
+    State(config): State<Arc<Config>>,
+    Json(payload): Json<CreateSharePayload>,
+) -> Result<(StatusCode, Json<WeekShare>), AppError> {
+    let secret = config.share_secret.as_ref().ok_or_else(shares_disabled)?;
+    parse_week(&payload.week)?;
+    let ttl = match payload.expires_in_hours {
+        None => share::DEFAULT_TTL,
+        Some(hours) if hours > 0 && Duration::hours(hours) <= share::MAX_TTL => {
+            Duration::hours(hours)
+        }
+        Some(hours) => {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                &format!(
+                    "Invalid expires_in_hours {hours}, expected 1 to {}.",
+                    share::MAX_TTL.num_hours()
+                ),
+            ))
+        }
+    };
+
+    let share = ShareToken {
+        week: payload.week,
+        expires_at: (clock::now() + ttl).trunc_subsecs(0),
+    };
+    let token = share.sign(secret);
+    info!("Week {} shared until {}.", share.week, share.expires_at);
+    Ok((
+        StatusCode::CREATED,
+        Json(WeekShare {
+            path: format!("/api/shared/{token}"),
+            token,
+            week: share.week,
+            expires_at: share.expires_at,
+        }),
+    ))
+}
+
+/// Handler showing a shared week to the holder of its link, read-only.
+/// Forged and expired links are answered `404 Not Found`, like unknown ones.
+#[utoipa::path(
+    get,
+    path = "/api/shared/{token}",
+    tag = "weeks",
+    params(("token" = String, Path, description = "Token of the share link")),
+    security(()),
+    responses(
+        (status = 200, description = "Shared week", body = SharedWeek),
+        (status = 404, description = "Invalid or expired link", body = ErrorBody),
+    )
+)]
+pub async fn get_shared_week(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+    Path(token): Path<String>,
+) -> Result<Json<SharedWeek>, AppError> {
+    let secret = config.share_secret.as_ref().ok_or_else(shares_disabled)?;
+    let share = ShareToken::verify(secret, &token, clock::now()).ok_or_else(|| {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            "This share link is invalid or has expired.",
+        )
+    })?;
+    let (week_start, week_end) = parse_week(&share.week)?;
+    let tasks = database::get_task_summaries_between_from_db(
+        &pool,
+        (week_start, week_end),
+        database::DEFAULT_ORDER_BY,
+        None,
+    )
+    .await?;
+
+    Ok(Json(SharedWeek {
+        week: share.week,
+        week_start,
+        week_end,
+        expires_at: share.expires_at,
+        days: group_tasks_by_day(tasks, week_start),
+    }))
+}
+
+/// Handler returning the snapshot of an archived week, with a summary in
+/// the language of the `Accept-Language` header (English or French).
+#[utoipa::path(
+    get,
+    path = "/api/weeks/{week}/archive",
+    tag = "weeks",
+    params(("week" = String, Path, description = "ISO week, e.g. 2025-W29")),
+    responses(
+        (status = 200, description = "Archived week, with its summary", body = WeekArchive),
+        (status = 400, description = "Invalid week", body = ErrorBody),
+        (status = 404, description = "Week not archived", body = ErrorBody),
+    )
+)]
+pub async fn get_week_archive(
+    State(pool): State<SqlitePool>,
+    Path(week): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<WeekArchive>, AppError> {
+    parse_week(&week)?;
+    let mut archive = database::get_week_archive_from_db(&pool, &week)
+        .await?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                &format!("Week {week} is not archived."),
+            )
+        })?;
+
+    let accept_language = headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok());
+    let locale = summary::Locale::from_accept_language(accept_language);
+    archive.summary = Some(summary::week_summary(&archive, locale));
+    Ok(Json(archive))
+}
+
+/// Handler for listing the slots a task is split into.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/{id}/slots",
+    tag = "tasks",
+    params(("id" = i64, Path, description = "ID of the task")),
+    responses(
+        (status = 200, description = "Slots of the task", body = Vec<TaskSlot>),
+        (status = 404, description = "Task not found", body = ErrorBody),
+    )
+)]
+pub async fn list_task_slots(
+    State(pool): State<SqlitePool>,
+    Path(task_id): Path<i64>,
+) -> Result<Json<Vec<TaskSlot>>, AppError> {
+    if database::get_task_by_id_from_db(&pool, task_id)
+        .await?
+        .is_none()
+    {
+        return Err(task_not_found(task_id));
+    }
+
+    Ok(Json(
+        database::get_task_slots_from_db(&pool, task_id).await?,
+    ))
+}
+
+/// Handler for scheduling a chunk of work of a task on a day. The task is
+/// then listed on the board on each of its open slot days.
+#[utoipa::path(
+    post,
+    path = "/api/tasks/{id}/slots",
+    tag = "tasks",
+    params(("id" = i64, Path, description = "ID of the task")),
+    request_body = CreateTaskSlotPayload,
+    responses(
+        (status = 201, description = "Slot added", body = TaskSlot),
+        (status = 400, description = "Invalid estimate", body = ErrorBody),
+        (status = 404, description = "Task not found or already completed", body = ErrorBody),
+    )
+)]
+pub async fn add_task_slot(
+    State(pool): State<SqlitePool>,
+    Path(task_id): Path<i64>,
+    Json(payload): Json<CreateTaskSlotPayload>,
+) -> Result<(StatusCode, Json<TaskSlot>), AppError> {
+    if payload.estimate_minutes.is_some_and(|minutes| minutes <= 0) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "The estimate of a slot must be a positive number of minutes.",
+        ));
+    }
+
+    match database::add_task_slot_in_db(&pool, task_id, &payload).await? {
+        Some(slot) => Ok((StatusCode::CREATED, Json(slot))),
+        None => Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            &format!("Task with ID {task_id} not found or already completed."),
+        )),
+    }
+}
+
+/// Handler for completing a slot of a task. The task is completed along
+/// with its last open slot.
+#[utoipa::path(
+    post,
+    path = "/api/tasks/{id}/slots/{slot_id}/done",
+    tag = "tasks",
+    params(
+        ("id" = i64, Path, description = "ID of the task"),
+        ("slot_id" = i64, Path, description = "ID of the slot"),
+    ),
+    responses(
+        (status = 200, description = "Slot completed", body = TaskSlot),
+        (status = 404, description = "Slot not found", body = ErrorBody),
+    )
+)]
+pub async fn complete_task_slot(
+    State(pool): State<SqlitePool>,
+    Path((task_id, slot_id)): Path<(i64, i64)>,
+) -> Result<Json<TaskSlot>, AppError> {
+    database::complete_task_slot_in_db(&pool, task_id, slot_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| slot_not_found(task_id, slot_id))
+}
+
+/// Handler for removing a slot from a task.
+#[utoipa::path(
+    delete,
+    path = "/api/tasks/{id}/slots/{slot_id}",
+    tag = "tasks",
+    params(
+        ("id" = i64, Path, description = "ID of the task"),
+        ("slot_id" = i64, Path, description = "ID of the slot"),
+    ),
+    responses(
+        (status = 204, description = "Slot removed"),
+        (status = 404, description = "Slot not found", body = ErrorBody),
+    )
+)]
+pub async fn delete_task_slot(
+    State(pool): State<SqlitePool>,
+    Path((task_id, slot_id)): Path<(i64, i64)>,
+) -> Result<StatusCode, AppError> {
+    if database::delete_task_slot_in_db(&pool, task_id, slot_id).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(slot_not_found(task_id, slot_id))
+    }
+}
+
+fn task_not_found(task_id: i64) -> AppError {
+    AppError::new(
+        StatusCode::NOT_FOUND,
+        &format!("Task with ID {task_id} not found."),
+    )
+}
+
+pub(crate) fn task_changed(task_id: i64) -> AppError {
+    AppError::new(
+        StatusCode::PRECONDITION_FAILED,
+        &format!("Task with ID {task_id} was changed since it was read; reload it and try again."),
+    )
+}
+
+/// The task left by a change, or why there is none.
+fn changed_task(task_id: i64, change: database::TaskChange) -> Result<Json<Task>, AppError> {
+    match change {
+        database::TaskChange::Changed(task) => Ok(Json(*task)),
+        database::TaskChange::NotFound => Err(task_not_found(task_id)),
+        database::TaskChange::Stale => Err(task_changed(task_id)),
+    }
+}
+
+/// Version of a task a change is made at: the `updated_at` the client last
+/// read, quoted in `If-Match`, or else sent along in the payload. Without
+/// either, or with `If-Match: *`, the change is made whatever the version.
+pub(crate) fn expected_version(
+    headers: &HeaderMap,
+    payload: Option<DateTime<Utc>>,
+) -> Result<Option<DateTime<Utc>>, AppError> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Ok(payload);
+    };
+    let invalid = || {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Invalid If-Match header, expected the quoted updated_at of the task.",
+        )
+    };
+    let value = value.to_str().map_err(|_| invalid())?.trim();
+    if value == "*" {
+        return Ok(None);
+    }
+    let version = value.trim_matches('"');
+    DateTime::parse_from_rfc3339(version)
+        .map(|version| Some(version.to_utc()))
+        .map_err(|_| invalid())
+}
+
+fn slot_not_found(task_id: i64, slot_id: i64) -> AppError {
+    AppError::new(
+        StatusCode::NOT_FOUND,
+        &format!("Slot {slot_id} of task {task_id} not found."),
+    )
+}
+
+/// Handler wiping all data in end-to-end test mode, so that every test
+/// starts from the same state: empty tables, IDs from 1, the clock back at
+/// its start and colors from the start of the default palette.
+pub async fn reset_test_state(State(pool): State<SqlitePool>) -> Result<StatusCode, AppError> {
+    database::reset_database(&pool).await?;
+    colors::reset_client_colors();
+    clock::reset();
+    info!("Test state reset.");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn sessions_disabled() -> AppError {
+    AppError::new(StatusCode::NOT_FOUND, "Sessions are not enabled.")
+}
+
+/// Handler signing a browser in with one of the `API_KEYS`. The session is
+/// set as a cookie; the CSRF token to send back along with it is in the
+/// body.
+#[utoipa::path(
+    post,
+    path = "/api/session",
+    tag = "session",
+    request_body = LoginPayload,
+    security(()),
+    responses(
+        (
+            status = 201,
+            description = "Signed in, the session is set as a cookie",
+            body = SessionInfo,
+        ),
+        (status = 401, description = "Invalid API key", body = ErrorBody),
+        (status = 404, description = "Sessions not enabled", body = ErrorBody),
+    )
+)]
+pub async fn create_session(
+    State(config): State<Arc<Config>>,
+    Json(payload): Json<LoginPayload>,
+) -> Result<Response, AppError> {
+    let sessions = config.sessions.as_ref().ok_or_else(sessions_disabled)?;
+    if !config.api_keys.accepts(payload.api_key.trim()) {
+        return Err(AppError::new(StatusCode::UNAUTHORIZED, "Invalid API key."));
+    }
+    let now = clock::now();
+    let session = sessions.issue(now);
+    info!("Session opened until {}.", session.expires_at);
+    let info = SessionInfo {
+        expires_at: session.expires_at,
+        csrf_token: sessions.csrf_token(&session),
+    };
+    Ok((
+        StatusCode::CREATED,
+        [(header::SET_COOKIE, sessions.cookie(&session, now))],
+        Json(info),
+    )
+        .into_response())
+}
+
+/// Handler giving the CSRF token of the current session, for pages loaded
+/// after signing in.
+#[utoipa::path(
+    get,
+    path = "/api/session/csrf",
+    tag = "session",
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "CSRF token of the session", body = SessionInfo),
+        (status = 401, description = "No valid session", body = ErrorBody),
+        (status = 404, description = "Sessions not enabled", body = ErrorBody),
+    )
+)]
+pub async fn get_session_csrf(
+    State(config): State<Arc<Config>>,
+    State(pool): State<SqlitePool>,
+    headers: HeaderMap,
+) -> Result<Json<SessionInfo>, AppError> {
+    let sessions = config.sessions.as_ref().ok_or_else(sessions_disabled)?;
+    let session = sessions
+        .signed_in(&pool, &headers, clock::now())
+        .await?
+        .ok_or_else(|| AppError::new(StatusCode::UNAUTHORIZED, "No valid session."))?;
+    Ok(Json(SessionInfo {
+        expires_at: session.expires_at,
+        csrf_token: sessions.csrf_token(&session),
+    }))
+}
+
+/// Handler signing a browser out: its session is revoked until it expires,
+/// so that a copy of the cookie is refused too, and the cookie is removed.
+#[utoipa::path(
+    delete,
+    path = "/api/session",
+    tag = "session",
+    security(()),
+    responses(
+        (status = 204, description = "Signed out"),
+        (status = 404, description = "Sessions not enabled", body = ErrorBody),
+    )
+)]
+pub async fn delete_session(
+    State(config): State<Arc<Config>>,
+    State(pool): State<SqlitePool>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let sessions = config.sessions.as_ref().ok_or_else(sessions_disabled)?;
+    if let Some(session) = sessions.from_headers(&headers, clock::now()) {
+        database::revoke_session_in_db(&pool, session.id(), session.expires_at).await?;
+        info!("Session signed out before {}.", session.expires_at);
+    }
+    Ok((
+        StatusCode::NO_CONTENT,
+        [(header::SET_COOKIE, sessions.expired_cookie())],
+    )
+        .into_response())
+}
+
+/// Handler listing the faults injected into the API (debug builds only).
+pub async fn list_faults(State(injector): State<Arc<FaultInjector>>) -> Json<Vec<FaultRule>> {
+    Json(injector.rules())
+}
+
+/// Handler replacing the faults injected into the API (debug builds only).
+pub async fn set_faults(
+    State(injector): State<Arc<FaultInjector>>,
+    Json(rules): Json<Vec<FaultRule>>,
+) -> Result<Json<Vec<FaultRule>>, AppError> {
+    for rule in &rules {
+        rule.validate()
+            .map_err(|e| AppError::new(StatusCode::BAD_REQUEST, &e))?;
+    }
+    info!("Injecting faults into {} route(s).", rules.len());
+    injector.set_rules(rules);
+    Ok(Json(injector.rules()))
+}
+
+/// Handler removing all injected faults (debug builds only).
+pub async fn clear_faults(State(injector): State<Arc<FaultInjector>>) -> StatusCode {
+    injector.set_rules(Vec::new());
+    info!("Injected faults cleared.");
+    StatusCode::NO_CONTENT
+}
+
+/// Handler listing the holidays skipped by the `business-day-holidays`
+/// rollover policy.
+#[utoipa::path(
+    get,
+    path = "/api/holidays",
+    tag = "planning",
+    responses(
+        (status = 200, description = "Holidays", body = Vec<Holiday>),
+    )
+)]
+pub async fn get_holidays(State(pool): State<SqlitePool>) -> Result<Json<Vec<Holiday>>, AppError> {
+    Ok(Json(database::get_holidays_from_db(&pool).await?))
+}
+
+/// Handler for adding a holiday. Adding an existing day renames it.
+#[utoipa::path(
+    post,
+    path = "/api/holidays",
+    tag = "planning",
+    request_body = AddHolidayPayload,
+    responses(
+        (status = 201, description = "Holiday added", body = Holiday),
+        (status = 400, description = "Missing name", body = ErrorBody),
+    )
+)]
+pub async fn add_holiday(
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<AddHolidayPayload>,
+) -> Result<(StatusCode, Json<Holiday>), AppError> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "A holiday needs a name.",
+        ));
+    }
+
+    let holiday = database::add_holiday_in_db(&pool, payload.day, name).await?;
+    Ok((StatusCode::CREATED, Json(holiday)))
+}
+
+/// Handler for removing a holiday.
+#[utoipa::path(
+    delete,
+    path = "/api/holidays/{day}",
+    tag = "planning",
+    params(("day" = NaiveDate, Path, description = "Day of the holiday")),
+    responses(
+        (status = 204, description = "Holiday removed"),
+        (status = 404, description = "Not a holiday", body = ErrorBody),
+    )
+)]
+pub async fn delete_holiday(
+    State(pool): State<SqlitePool>,
+    Path(day): Path<NaiveDate>,
+) -> Result<StatusCode, AppError> {
+    if database::delete_holiday_in_db(&pool, day).await? {
+        info!("Holiday on {} removed.", day);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            &format!("{day} is not a holiday."),
+        ))
+    }
+}
+
+/// Handler listing the open tasks marked stale for being rolled over too
+/// often, most postponed first.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/stale",
+    tag = "tasks",
+    responses(
+        (status = 200, description = "Stale open tasks", body = Vec<Task>),
+    )
+)]
+pub async fn stale_tasks(State(pool): State<SqlitePool>) -> Result<Json<Vec<Task>>, AppError> {
+    Ok(Json(database::get_stale_tasks_from_db(&pool).await?))
+}
+
+/// Handler listing the tasks the rollover would move, and their target day,
+/// without moving them.
+#[utoipa::path(
+    get,
+    path = "/api/tasks/rollover/preview",
+    tag = "rollover",
+    responses(
+        (status = 200, description = "Tasks the rollover would move", body = RolloverPreview),
+    )
+)]
+pub async fn preview_rollover(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+) -> Result<Json<RolloverPreview>, AppError> {
+    Ok(Json(
+        database::preview_rollover_in_db(&pool, config.rollover_policy).await?,
+    ))
+}
+
+/// Handler for rollover tasks on the next day.
+#[utoipa::path(
+    patch,
+    path = "/api/tasks/rollover",
+    tag = "rollover",
+    responses(
+        (status = 200, description = "Open tasks rolled over", body = RolloverOutcome),
+    )
+)]
+pub async fn rollover_tasks(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    // Return JSON for message/count
+    debug!("Received request to roll over tasks.");
+
+    let num_rolled_over = database::rollover_tasks_in_db(
+        &pool,
+        config.rollover_policy,
+        RolloverTrigger::Manual,
+        config.stale_after_rollovers,
+    )
+    .await?;
+
+    info!("Successfully rolled over {} tasks.", num_rolled_over);
+
+    Ok(Json(serde_json::json!({
+        "message": format!("Successfully rolled over {} tasks.", num_rolled_over),
+        "tasks_rolled_over": num_rolled_over
+    })))
+}
+
+/// Query parameters accepted by `GET /api/rollover/history`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RolloverHistoryQuery {
+    /// Number of runs to return, most recent first (30 by default).
+    pub limit: Option<i64>,
+    /// Rollovers an open task needs to be reported as postponed (3 by default).
+    pub min_rollovers: Option<i64>,
+}
+
+/// Handler for the rollover history: recent runs, and the open tasks that
+/// keep being postponed.
+#[utoipa::path(
+    get,
+    path = "/api/rollover/history",
+    tag = "rollover",
+    params(RolloverHistoryQuery),
+    responses(
+        (status = 200, description = "Recent runs and postponed tasks", body = RolloverHistory),
+        (status = 400, description = "Invalid limit", body = ErrorBody),
+    )
+)]
+pub async fn rollover_history(
+    State(pool): State<SqlitePool>,
+    Query(query): Query<RolloverHistoryQuery>,
+) -> Result<Json<RolloverHistory>, AppError> {
+    let limit = query.limit.unwrap_or(30);
+    let min_rollovers = query.min_rollovers.unwrap_or(3);
+    if limit < 1 || min_rollovers < 1 {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "limit and min_rollovers must be at least 1.",
+        ));
+    }
+
+    Ok(Json(
+        database::get_rollover_history_from_db(&pool, limit, min_rollovers).await?,
+    ))
+}
+
+/// Handler for staging an import. Rows are validated and stored for review,
+/// but no task is created until the import is committed.
+#[utoipa::path(
+    post,
+    path = "/api/import",
+    tag = "import",
+    request_body = Vec<CreateTaskPayload>,
+    responses(
+        (status = 201, description = "Import staged", body = ImportPreview),
+        (status = 400, description = "Invalid rows", body = ErrorBody),
+    )
+)]
+pub async fn stage_import(
+    State(pool): State<SqlitePool>,
+    Json(rows): Json<Vec<CreateTaskPayload>>,
+) -> Result<(StatusCode, Json<ImportPreview>), AppError> {
+    debug!(
+        "Received request to stage an import of {} rows.",
+        rows.len()
+    );
+
+    if rows.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "An import must contain at least one row.",
+        ));
+    }
+
+    // Report every invalid row at once rather than failing on the first one.
+    let invalid_rows: Vec<String> = rows
+        .iter()
+        .enumerate()
+        .filter_map(|(index, row)| {
+            validate_task_payload(row)
+                .err()
+                .map(|err| format!("row {index}: {}", err.message))
+        })
+        .collect();
+    if !invalid_rows.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Invalid import rows: {}", invalid_rows.join("; ")),
+        ));
+    }
+
+    let import_id = database::stage_import_in_db(&pool, rows).await?;
+    let preview = database::get_import_preview_from_db(&pool, import_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Staged import {import_id} disappeared"))?;
+
+    info!(
+        "Import {} staged with {} rows.",
+        import_id,
+        preview.rows.len()
+    );
+
+    Ok((StatusCode::CREATED, Json(preview)))
+}
+
+/// Handler for reviewing a staged import before committing it.
+#[utoipa::path(
+    get,
+    path = "/api/import/{id}/preview",
+    tag = "import",
+    params(("id" = i64, Path, description = "ID of the staged import")),
+    responses(
+        (status = 200, description = "Staged import", body = ImportPreview),
+        (status = 404, description = "Import not found", body = ErrorBody),
+    )
+)]
+pub async fn preview_import(
+    State(pool): State<SqlitePool>,
+    Path(import_id): Path<i64>,
+) -> Result<Json<ImportPreview>, AppError> {
+    let preview = database::get_import_preview_from_db(&pool, import_id)
+        .await?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                &format!("Import with ID {import_id} not found."),
+            )
+        })?;
+
+    Ok(Json(preview))
+}
+
+/// Handler for applying a staged import to the live tasks, all-or-nothing.
+#[utoipa::path(
+    post,
+    path = "/api/import/{id}/commit",
+    tag = "import",
+    params(("id" = i64, Path, description = "ID of the staged import")),
+    responses(
+        (status = 200, description = "Tasks created", body = Vec<Task>),
+        (status = 404, description = "Import not found", body = ErrorBody),
+        (status = 409, description = "Import already committed", body = ErrorBody),
+    )
+)]
+pub async fn commit_import(
+    State(pool): State<SqlitePool>,
+    Path(import_id): Path<i64>,
+) -> Result<Json<Vec<Task>>, AppError> {
+    debug!("Received request to commit import {}.", import_id);
+
+    match database::commit_import_in_db(&pool, import_id).await? {
+        database::CommitImportOutcome::Committed(tasks) => {
+            info!("Import {} committed ({} tasks).", import_id, tasks.len());
+            Ok(Json(tasks))
+        }
+        database::CommitImportOutcome::AlreadyCommitted => Err(AppError::new(
+            StatusCode::CONFLICT,
+            &format!("Import with ID {import_id} has already been committed."),
+        )),
+        database::CommitImportOutcome::NotFound => Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            &format!("Import with ID {import_id} not found."),
+        )),
+    }
+}
+
+/// Query parameters accepted by `GET /api/clients`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListClientsQuery {
+    /// Also list the archived clients.
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+/// Handler for listing clients. Archived clients are hidden unless requested.
+#[utoipa::path(
+    get,
+    path = "/api/clients",
+    tag = "clients",
+    params(ListClientsQuery),
+    responses(
+        (status = 200, description = "Clients", body = Vec<Client>),
+    )
+)]
+pub async fn list_clients(
+    State(pool): State<SqlitePool>,
+    Query(query): Query<ListClientsQuery>,
+) -> Result<Json<Vec<Client>>, AppError> {
+    let clients = database::get_clients_from_db(&pool, query.include_archived).await?;
+    info!("Successfully retrieved {} clients.", clients.len());
+    Ok(Json(clients))
+}
+
+/// Handler for retrieving a single client.
+#[utoipa::path(
+    get,
+    path = "/api/clients/{name}",
+    tag = "clients",
+    params(("name" = String, Path, description = "Name of the client")),
+    responses(
+        (status = 200, description = "Client", body = Client),
+        (status = 404, description = "Client not found", body = ErrorBody),
+    )
+)]
+pub async fn get_client(
+    State(pool): State<SqlitePool>,
+    Path(client_name): Path<String>,
+) -> Result<Json<Client>, AppError> {
+    database::get_client_from_db(&pool, &client_name)
+        .await?
+        .map(Json)
+        .ok_or_else(|| client_not_found(&client_name))
+}
+
+/// Query parameters accepted by `GET /api/clients/{name}/stats`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ClientStatsQuery {
+    /// First day of the range, defaults to Monday of the current week.
+    pub from: Option<NaiveDate>,
+    /// Last day of the range (inclusive), defaults to Sunday of the current week.
+    pub to: Option<NaiveDate>,
+}
+
+/// Handler for the statistics of a client over a date range.
+#[utoipa::path(
+    get,
+    path = "/api/clients/{name}/stats",
+    tag = "clients",
+    params(
+        ("name" = String, Path, description = "Name of the client"),
+        ClientStatsQuery,
+    ),
+    responses(
+        (status = 200, description = "Statistics of the client", body = ClientStats),
+        (status = 400, description = "Invalid range", body = ErrorBody),
+        (status = 404, description = "Client not found", body = ErrorBody),
+    )
+)]
+pub async fn client_stats(
+    State(pool): State<SqlitePool>,
+    Path(client_name): Path<String>,
+    Query(query): Query<ClientStatsQuery>,
+) -> Result<Json<ClientStats>, AppError> {
+    let (week_start, week_end) = database::current_week_range();
+    let from = query.from.unwrap_or(week_start);
+    let to = query.to.unwrap_or(week_end);
+    if from > to {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Invalid range: {from} is after {to}."),
+        ));
+    }
+
+    database::get_client_stats_from_db(&pool, &client_name, from, to)
+        .await?
+        .map(Json)
+        .ok_or_else(|| client_not_found(&client_name))
+}
+
+/// Handler for getting the weekly goal.
+#[utoipa::path(
+    get,
+    path = "/api/settings/goal",
+    tag = "settings",
+    responses(
+        (status = 200, description = "Weekly goal", body = WeeklyGoal),
+        (status = 404, description = "No goal set", body = ErrorBody),
+    )
+)]
+pub async fn get_weekly_goal(State(pool): State<SqlitePool>) -> Result<Json<WeeklyGoal>, AppError> {
+    database::get_weekly_goal_from_db(&pool)
+        .await?
+        .map(Json)
+        .ok_or_else(goal_not_set)
+}
+
+/// Handler for setting the weekly goal, in tasks or in estimated hours.
+#[utoipa::path(
+    put,
+    path = "/api/settings/goal",
+    tag = "settings",
+    request_body = SetWeeklyGoalPayload,
+    responses(
+        (status = 200, description = "Weekly goal set", body = WeeklyGoal),
+        (status = 400, description = "Invalid target", body = ErrorBody),
+    )
+)]
+pub async fn set_weekly_goal(
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<SetWeeklyGoalPayload>,
+) -> Result<Json<WeeklyGoal>, AppError> {
+    if !payload.target.is_finite() || payload.target <= 0.0 {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "The weekly goal must be a positive number.",
+        ));
+    }
+    database::set_weekly_goal_in_db(&pool, Some((payload.unit, payload.target)))
+        .await?
+        .map(Json)
+        .ok_or_else(goal_not_set)
+}
+
+/// Handler for removing the weekly goal.
+#[utoipa::path(
+    delete,
+    path = "/api/settings/goal",
+    tag = "settings",
+    responses(
+        (status = 204, description = "Weekly goal removed"),
+    )
+)]
+pub async fn delete_weekly_goal(State(pool): State<SqlitePool>) -> Result<StatusCode, AppError> {
+    database::set_weekly_goal_in_db(&pool, None).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for the progress of the current week towards the weekly goal:
+/// how much is done, whether that keeps up with the days gone by, and
+/// where the week would end at this pace.
+#[utoipa::path(
+    get,
+    path = "/api/stats/goal",
+    tag = "settings",
+    responses(
+        (status = 200, description = "Progress of the current week", body = GoalProgress),
+        (status = 404, description = "No goal set", body = ErrorBody),
+    )
+)]
+pub async fn goal_progress(State(pool): State<SqlitePool>) -> Result<Json<GoalProgress>, AppError> {
+    let goal = database::get_weekly_goal_from_db(&pool)
+        .await?
+        .ok_or_else(goal_not_set)?;
+    let (week_start, week_end) = database::current_week_range();
+    let (done_tasks, done_minutes) =
+        database::get_completed_work_from_db(&pool, week_start, week_end).await?;
+    let now = clock::now().with_timezone(&clock::timezone()).naive_local();
+    Ok(Json(goal::progress(
+        goal,
+        week_start,
+        now,
+        done_tasks,
+        done_minutes,
+    )))
+}
+
+fn goal_not_set() -> AppError {
+    AppError::new(StatusCode::NOT_FOUND, "No weekly goal is set.")
+}
+
+/// Handler for creating a client explicitly.
+#[utoipa::path(
+    post,
+    path = "/api/clients",
+    tag = "clients",
+    request_body = CreateClientPayload,
+    responses(
+        (status = 201, description = "Client created", body = Client),
+        (status = 400, description = "Empty name", body = ErrorBody),
+        (status = 409, description = "Client already exists", body = ErrorBody),
+    )
+)]
+pub async fn create_client(
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<CreateClientPayload>,
+) -> Result<(StatusCode, Json<Client>), AppError> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Client name cannot be empty.",
+        ));
+    }
+
+    match database::create_client_in_db(&pool, name).await? {
+        Some(client) => {
+            info!("Client '{}' created.", client.name);
+            Ok((StatusCode::CREATED, Json(client)))
+        }
+        None => Err(AppError::new(
+            StatusCode::CONFLICT,
+            &format!("Client '{name}' already exists."),
+        )),
+    }
+}
+
+/// Handler for updating (renaming) a client.
+#[utoipa::path(
+    put,
+    path = "/api/clients/{name}",
+    tag = "clients",
+    params(("name" = String, Path, description = "Name of the client")),
+    request_body = UpdateClientPayload,
+    responses(
+        (status = 200, description = "Client renamed", body = Client),
+        (status = 400, description = "Empty name", body = ErrorBody),
+        (status = 404, description = "Client not found", body = ErrorBody),
+        (status = 409, description = "Name already taken", body = ErrorBody),
+    )
+)]
+pub async fn update_client(
+    State(pool): State<SqlitePool>,
+    Path(client_name): Path<String>,
+    Json(payload): Json<UpdateClientPayload>,
+) -> Result<Json<Client>, AppError> {
+    let new_name = payload.name.trim();
+    if new_name.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Client name cannot be empty.",
+        ));
+    }
+
+    match database::rename_client_in_db(&pool, &client_name, new_name).await? {
+        database::RenameClientOutcome::Renamed(client) => Ok(Json(client)),
+        database::RenameClientOutcome::NameTaken => Err(AppError::new(
+            StatusCode::CONFLICT,
+            &format!("Client '{new_name}' already exists."),
+        )),
+        database::RenameClientOutcome::NotFound => Err(client_not_found(&client_name)),
+    }
+}
+
+/// Handler for overriding the palette color of a client.
+#[utoipa::path(
+    put,
+    path = "/api/clients/{name}/color",
+    tag = "clients",
+    params(("name" = String, Path, description = "Name of the client")),
+    request_body = SetClientColorPayload,
+    responses(
+        (status = 200, description = "Color set", body = Client),
+        (status = 422, description = "Invalid color"),
+        (status = 404, description = "Client not found", body = ErrorBody),
+    )
+)]
+pub async fn set_client_color(
+    State(pool): State<SqlitePool>,
+    Path(client_name): Path<String>,
+    Json(payload): Json<SetClientColorPayload>,
+) -> Result<Json<Client>, AppError> {
+    database::set_client_color_in_db(&pool, &client_name, &payload.color)
+        .await?
+        .map(Json)
+        .ok_or_else(|| client_not_found(&client_name))
+}
+
+/// Handler for setting the priority and context new tasks of a client
+/// inherit when created without their own.
+#[utoipa::path(
+    put,
+    path = "/api/clients/{name}/defaults",
+    tag = "clients",
+    params(("name" = String, Path, description = "Name of the client")),
+    request_body = SetClientDefaultsPayload,
+    responses(
+        (status = 200, description = "Defaults set", body = Client),
+        (status = 400, description = "Invalid context", body = ErrorBody),
+        (status = 404, description = "Client not found", body = ErrorBody),
+    )
+)]
+pub async fn set_client_defaults(
+    State(pool): State<SqlitePool>,
+    Path(client_name): Path<String>,
+    Json(payload): Json<SetClientDefaultsPayload>,
+) -> Result<Json<Client>, AppError> {
+    let context = parse_context(payload.context.as_deref())?;
+
+    database::set_client_defaults_in_db(&pool, &client_name, payload.priority, context.as_deref())
+        .await?
+        .map(Json)
+        .ok_or_else(|| client_not_found(&client_name))
+}
+
+/// Query parameters accepted by `GET /api/clients/color-changes`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ColorChangesQuery {
+    /// ID of the last change already seen; all changes are listed when unset.
+    #[serde(default)]
+    pub after: i64,
+}
+
+/// Handler listing the client color changes made since the last one a
+/// frontend saw, so that it can refresh the colors it cached.
+#[utoipa::path(
+    get,
+    path = "/api/clients/color-changes",
+    tag = "clients",
+    params(ColorChangesQuery),
+    responses(
+        (status = 200, description = "Color changes, oldest first", body = Vec<ClientColorChange>),
+    )
+)]
+pub async fn list_color_changes(
+    State(pool): State<SqlitePool>,
+    Query(query): Query<ColorChangesQuery>,
+) -> Result<Json<Vec<ClientColorChange>>, AppError> {
+    let changes = database::get_color_changes_after_from_db(&pool, query.after).await?;
+    Ok(Json(changes))
+}
+
+/// Describes the active palette and the ones available.
+fn palette_settings(palette: Palette) -> PaletteSettings {
+    PaletteSettings {
+        palette,
+        colors: colors::palette_colors(palette),
+        available: Palette::ALL.to_vec(),
+    }
+}
+
+/// Handler for getting the palette new clients are assigned colors from.
+#[utoipa::path(
+    get,
+    path = "/api/settings/palette",
+    tag = "settings",
+    responses(
+        (status = 200, description = "Active palette", body = PaletteSettings),
+    )
+)]
+pub async fn get_palette() -> Json<PaletteSettings> {
+    Json(palette_settings(colors::active_palette()))
+}
+
+/// Handler for switching the palette. Only clients created afterwards get
+/// colors from it; existing ones keep theirs.
+#[utoipa::path(
+    put,
+    path = "/api/settings/palette",
+    tag = "settings",
+    request_body = SetPalettePayload,
+    responses(
+        (status = 200, description = "Palette switched", body = PaletteSettings),
+    )
+)]
+pub async fn set_palette(Json(payload): Json<SetPalettePayload>) -> Json<PaletteSettings> {
+    info!(
+        "Switching to the {} color palette.",
+        payload.palette.as_str()
+    );
+    colors::set_active_palette(payload.palette);
+    Json(palette_settings(payload.palette))
+}
+
+/// Handler for getting the timezone the days follow.
+#[utoipa::path(
+    get,
+    path = "/api/settings/timezone",
+    tag = "settings",
+    responses(
+        (status = 200, description = "Timezone of the days", body = TimezoneSettings),
+    )
+)]
+pub async fn get_timezone() -> Json<TimezoneSettings> {
+    let timezone = clock::timezone();
+    Json(TimezoneSettings {
+        timezone: timezone.name().to_string(),
+        utc_offset: clock::now()
+            .with_timezone(&timezone)
+            .format("%:z")
+            .to_string(),
+        today: clock::today(),
+    })
+}
+
+/// Handler for deleting a client that has never been used on a task.
+#[utoipa::path(
+    delete,
+    path = "/api/clients/{name}",
+    tag = "clients",
+    params(("name" = String, Path, description = "Name of the client")),
+    responses(
+        (status = 204, description = "Client deleted"),
+        (status = 404, description = "Client not found", body = ErrorBody),
+        (status = 409, description = "Client has tasks", body = ErrorBody),
+    )
+)]
+pub async fn delete_client(
+    State(pool): State<SqlitePool>,
+    Path(client_name): Path<String>,
+) -> Result<StatusCode, AppError> {
+    match database::delete_client_in_db(&pool, &client_name).await? {
+        database::DeleteClientOutcome::Deleted => {
+            info!("Client '{}' deleted.", client_name);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        database::DeleteClientOutcome::HasTasks(count) => Err(AppError::new(
+            StatusCode::CONFLICT,
+            &format!("Client '{client_name}' still has {count} tasks. Archive it instead."),
+        )),
+        database::DeleteClientOutcome::NotFound => Err(client_not_found(&client_name)),
+    }
+}
+
+/// Handler for archiving a client.
+#[utoipa::path(
+    post,
+    path = "/api/clients/{name}/archive",
+    tag = "clients",
+    params(("name" = String, Path, description = "Name of the client")),
+    responses(
+        (status = 200, description = "Client archived", body = Client),
+        (status = 404, description = "Client not found", body = ErrorBody),
+    )
+)]
+pub async fn archive_client(
+    State(pool): State<SqlitePool>,
+    Path(client_name): Path<String>,
+) -> Result<Json<Client>, AppError> {
+    database::set_client_archived_in_db(&pool, &client_name, true)
+        .await?
+        .map(Json)
+        .ok_or_else(|| client_not_found(&client_name))
+}
+
+/// Handler for bringing an archived client back.
+#[utoipa::path(
+    delete,
+    path = "/api/clients/{name}/archive",
+    tag = "clients",
+    params(("name" = String, Path, description = "Name of the client")),
+    responses(
+        (status = 200, description = "Client brought back", body = Client),
+        (status = 404, description = "Client not found", body = ErrorBody),
+    )
+)]
+pub async fn unarchive_client(
+    State(pool): State<SqlitePool>,
+    Path(client_name): Path<String>,
+) -> Result<Json<Client>, AppError> {
+    database::set_client_archived_in_db(&pool, &client_name, false)
+        .await?
+        .map(Json)
+        .ok_or_else(|| client_not_found(&client_name))
+}
+
+fn client_not_found(client_name: &str) -> AppError {
+    AppError::new(
+        StatusCode::NOT_FOUND,
+        &format!("Client '{client_name}' not found."),
+    )
+}
+
+/// Query parameters accepted by `GET /api/clients/resolve`.
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ResolveClientQuery {
+    /// Client name or alias to resolve.
+    pub name: String,
+}
+
+/// Handler for checking how a client name would be interpreted: as a known
+/// alias, or as an unknown name with a near-match suggestion.
+#[utoipa::path(
+    get,
+    path = "/api/clients/resolve",
+    tag = "clients",
+    params(ResolveClientQuery),
+    responses(
+        (status = 200, description = "How the name is interpreted", body = ClientNameResolution),
+    )
+)]
+pub async fn resolve_client_name(
+    State(pool): State<SqlitePool>,
+    Query(query): Query<ResolveClientQuery>,
+) -> Result<Json<ClientNameResolution>, AppError> {
+    let canonical = database::resolve_client_alias(&pool, &query.name).await?;
+    let known_names = database::get_known_client_names_from_db(&pool).await?;
+
+    // Only suggest something when the name is neither an alias nor a known client.
+    let suggestion = if canonical.is_none() && !known_names.contains(&query.name) {
+        clients::suggest_client_name(&query.name, known_names.iter().map(String::as_str))
+            .map(str::to_string)
+    } else {
+        None
+    };
+
+    Ok(Json(ClientNameResolution {
+        name: query.name,
+        canonical,
+        suggestion,
+    }))
+}
+
+/// Handler for listing the aliases of a client.
+#[utoipa::path(
+    get,
+    path = "/api/clients/{name}/aliases",
+    tag = "clients",
+    params(("name" = String, Path, description = "Name of the client")),
+    responses(
+        (status = 200, description = "Aliases of the client", body = Vec<ClientAlias>),
+    )
+)]
+pub async fn list_client_aliases(
+    State(pool): State<SqlitePool>,
+    Path(client_name): Path<String>,
+) -> Result<Json<Vec<ClientAlias>>, AppError> {
+    let aliases = database::get_client_aliases_from_db(&pool, &client_name).await?;
+    Ok(Json(aliases))
+}
+
+/// Handler for registering a new alias for a client.
+#[utoipa::path(
+    post,
+    path = "/api/clients/{name}/aliases",
+    tag = "clients",
+    params(("name" = String, Path, description = "Name of the client")),
+    request_body = AddClientAliasPayload,
+    responses(
+        (status = 201, description = "Alias added", body = ClientAlias),
+        (status = 400, description = "Invalid alias", body = ErrorBody),
+        (status = 404, description = "Client not found", body = ErrorBody),
+        (status = 409, description = "Alias already in use", body = ErrorBody),
+    )
+)]
+pub async fn add_client_alias(
+    State(pool): State<SqlitePool>,
+    Path(client_name): Path<String>,
+    Json(payload): Json<AddClientAliasPayload>,
+) -> Result<(StatusCode, Json<ClientAlias>), AppError> {
+    let alias = payload.alias.trim().to_string();
+    if alias.is_empty() || alias == client_name {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Alias cannot be empty or identical to the client name.",
+        ));
+    }
+
+    if database::get_client_from_db(&pool, &client_name)
+        .await?
+        .is_none()
+    {
+        return Err(client_not_found(&client_name));
+    }
+
+    // Aliases always point at a canonical client, never at another alias.
+    if let Some(canonical) = database::resolve_client_alias(&pool, &client_name).await? {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("'{client_name}' is itself an alias of '{canonical}'."),
+        ));
+    }
+
+    if !database::add_client_alias_in_db(&pool, &client_name, &alias).await? {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            &format!("Alias '{alias}' is already in use."),
+        ));
+    }
+
+    info!("Alias '{}' added to client '{}'.", alias, client_name);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ClientAlias { alias, client_name }),
+    ))
+}
+
+/// Handler for removing an alias from a client.
+#[utoipa::path(
+    delete,
+    path = "/api/clients/{name}/aliases/{alias}",
+    tag = "clients",
+    params(
+        ("name" = String, Path, description = "Name of the client"),
+        ("alias" = String, Path, description = "Alias of the client"),
+    ),
+    responses(
+        (status = 204, description = "Alias removed"),
+        (status = 404, description = "Alias not found", body = ErrorBody),
+    )
+)]
+pub async fn delete_client_alias(
+    State(pool): State<SqlitePool>,
+    Path((client_name, alias)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    if database::delete_client_alias_in_db(&pool, &client_name, &alias).await? {
+        info!("Alias '{}' removed from client '{}'.", alias, client_name);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            &format!("Client '{client_name}' has no alias '{alias}'."),
+        ))
+    }
+}
 
-/// Handler for listing tasks for the current week.
-pub async fn list_tasks(
-    State(pool): State<SqlitePool>, // State injection (DB pool)
-) -> Result<Json<Vec<Task>>, AppError> {
-    let tasks = database::get_current_week_tasks_from_db(&pool).await?;
-    info!("Successfully retrieved {} tasks.", tasks.len());
-    Ok(Json(tasks))
+/// Query parameters accepted by `GET /api/export/archive`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ArchiveChunkQuery {
+    /// Where to resume, as returned in `X-Next-Cursor`. Starts from the beginning when absent.
+    pub cursor: Option<String>,
+    /// Maximum number of records in the chunk.
+    pub limit: Option<i64>,
+    /// `client` strips internal IDs for archives handed over to clients.
+    #[serde(default)]
+    pub profile: ExportProfile,
 }
 
-/// Handler for creating a new task.
-#[allow(clippy::unnecessary_lazy_evaluations)]
-#[allow(clippy::uninlined_format_args)]
-pub async fn create_task(
+/// Handler for downloading the full archive (clients, then tasks) one chunk
+/// at a time, as newline-delimited `ArchiveRecord`s.
+///
+/// Each response carries the SHA-256 of its body in `X-Chunk-Checksum` and,
+/// unless it is the last chunk, the cursor of the next one in `X-Next-Cursor`.
+/// A chunk that fails verification can simply be requested again.
+#[utoipa::path(
+    get,
+    path = "/api/export/archive",
+    tag = "export",
+    params(ArchiveChunkQuery),
+    responses(
+        (
+            status = 200,
+            description = "Chunk of archive records, one JSON object per line",
+            body = String,
+            content_type = "application/x-ndjson",
+            headers(
+                ("X-Chunk-Checksum" = String, description = "SHA-256 of the body"),
+                ("X-Next-Cursor" = String, description = "Cursor of the next chunk, absent on the last one"),
+            ),
+        ),
+        (status = 400, description = "Invalid cursor or limit", body = ErrorBody),
+    )
+)]
+pub async fn archive_chunk(
     State(pool): State<SqlitePool>,
-    Json(payload): Json<CreateTaskPayload>, // Extracting the request body as JSON
-) -> Result<(StatusCode, Json<Task>), AppError> {
+    Query(query): Query<ArchiveChunkQuery>,
+) -> Result<Response, AppError> {
+    let cursor = match query.cursor.as_deref() {
+        Some(token) => token.parse().map_err(|_| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                &format!("Invalid archive cursor '{token}'."),
+            )
+        })?,
+        None => ArchiveCursor::start(),
+    };
+    let limit = query.limit.unwrap_or(archive::DEFAULT_CHUNK_SIZE);
+    if !(1..=archive::MAX_CHUNK_SIZE).contains(&limit) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Chunk limit must be between 1 and {}.",
+                archive::MAX_CHUNK_SIZE
+            ),
+        ));
+    }
+
+    let (records, last_id): (Vec<ArchiveRecord>, Option<i64>) = match cursor.section {
+        ArchiveSection::Clients => {
+            let clients =
+                database::get_clients_after_from_db(&pool, cursor.after_id, limit).await?;
+            let last_id = clients.last().map(|c| c.id);
+            (
+                clients.into_iter().map(ArchiveRecord::Client).collect(),
+                last_id,
+            )
+        }
+        ArchiveSection::Tasks => {
+            let tasks = database::get_tasks_after_from_db(&pool, cursor.after_id, limit).await?;
+            let last_id = tasks.last().map(|t| t.id);
+            (
+                tasks
+                    .into_iter()
+                    .map(|task| ArchiveRecord::Task(Box::new(task)))
+                    .collect(),
+                last_id,
+            )
+        }
+    };
+
+    let mut body = Vec::new();
+    for record in &records {
+        let value = query
+            .profile
+            .to_value(record)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize archive record: {e}"))?;
+        serde_json::to_writer(&mut body, &value)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize archive record: {e}"))?;
+        body.push(b'\n');
+    }
+
+    let next = cursor.next(records.len() as i64, limit, last_id);
     debug!(
-        "Received request to create task for client: {}",
-        payload.client_name
+        "Archive chunk at '{}': {} records, next cursor {:?}",
+        cursor,
+        records.len(),
+        next.map(|c| c.to_string())
     );
-    // Validate the payload : name, description and date
-    if payload.client_name.is_empty() || payload.description.is_empty() {
-        error!("Validation failed: Client name or description is empty.");
+
+    let mut response = (
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (
+                HeaderName::from_static("x-chunk-checksum"),
+                archive::chunk_checksum(&body),
+            ),
+        ],
+        body,
+    )
+        .into_response();
+    if let Some(next) = next {
+        response.headers_mut().insert(
+            HeaderName::from_static("x-next-cursor"),
+            HeaderValue::from_str(&next.to_string()).expect("cursor is a valid header value"),
+        );
+    }
+    Ok(response)
+}
+
+/// Query parameters accepted by `GET /api/export/table`.
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportTableQuery {
+    /// Table to pull: `clients` or `tasks`.
+    pub entity: TableEntity,
+    /// Cursor returned by the previous page or refresh, as `next_since`.
+    /// Starts from the beginning of the table when absent.
+    pub since: Option<String>,
+    /// Maximum number of rows in the page.
+    pub limit: Option<i64>,
+    /// `json` by default, or `csv`.
+    #[serde(default)]
+    pub format: TableFormat,
+    /// Export token, for tools that cannot send an `Authorization` header.
+    pub token: Option<String>,
+}
+
+/// Checks that a feed request carries one of the `EXPORT_API_TOKENS`, as a
+/// bearer token or in its `token` query parameter.
+fn check_export_token(
+    config: &Config,
+    headers: &HeaderMap,
+    token: Option<&str>,
+) -> Result<(), AppError> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match bearer.or(token) {
+        Some(token) if config.export_tokens.accepts(token.trim()) => Ok(()),
+        _ => Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            "A valid export token is required.",
+        )),
+    }
+}
+
+/// Handler of the tabular export pulled by BI tools (Power BI, Google
+/// Sheets, ...): one page of a table, rows in a fixed column order, dates
+/// and times in ISO 8601. Passing back the `next_since` of the previous
+/// response returns only the rows added or changed since.
+///
+/// Requires one of the `EXPORT_API_TOKENS`, as a bearer token or in `token`.
+#[utoipa::path(
+    get,
+    path = "/api/export/table",
+    tag = "export",
+    params(ExportTableQuery),
+    security(("export_token" = [])),
+    responses(
+        (
+            status = 200,
+            description = "Page of the table",
+            content(
+                (TablePage = "application/json"),
+                (String = "text/csv"),
+            ),
+            headers(
+                ("X-Next-Cursor" = String),
+                ("X-Has-More" = bool),
+            ),
+        ),
+        (status = 400, description = "Invalid cursor or limit", body = ErrorBody),
+        (status = 401, description = "Missing export token", body = ErrorBody),
+    )
+)]
+pub async fn export_table(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+    headers: HeaderMap,
+    Query(query): Query<ExportTableQuery>,
+) -> Result<Response, AppError> {
+    check_export_token(&config, &headers, query.token.as_deref())?;
+
+    let since = match query.since.as_deref() {
+        Some(token) => token.parse().map_err(|_| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                &format!("Invalid export cursor '{token}'."),
+            )
+        })?,
+        None => TableCursor::default(),
+    };
+    let limit = query.limit.unwrap_or(table::DEFAULT_PAGE_SIZE);
+    if !(1..=table::MAX_PAGE_SIZE).contains(&limit) {
         return Err(AppError::new(
             StatusCode::BAD_REQUEST,
-            "Client name and description cannot be empty.",
+            &format!("Page limit must be between 1 and {}.", table::MAX_PAGE_SIZE),
         ));
     }
 
-    let today = Utc::now().date_naive();
-    let current_week_start = today.week(Weekday::Mon).first_day();
-    let current_week_end = today.week(Weekday::Mon).last_day();
+    let (rows, last_seq): (Vec<Vec<serde_json::Value>>, Option<i64>) = match query.entity {
+        TableEntity::Clients => {
+            let clients =
+                database::get_client_table_rows_from_db(&pool, since.change_seq, limit).await?;
+            let last_seq = clients.last().map(|c| c.change_seq);
+            (
+                clients.into_iter().map(ClientRow::into_values).collect(),
+                last_seq,
+            )
+        }
+        TableEntity::Tasks => {
+            let tasks =
+                database::get_task_table_rows_from_db(&pool, since.change_seq, limit).await?;
+            let last_seq = tasks.last().map(|t| t.change_seq);
+            (
+                tasks.into_iter().map(TaskRow::into_values).collect(),
+                last_seq,
+            )
+        }
+    };
+    let next = TableCursor {
+        change_seq: last_seq.unwrap_or(since.change_seq),
+    };
+    let page = TablePage {
+        entity: query.entity,
+        columns: query.entity.columns().to_vec(),
+        has_more: rows.len() as i64 == limit,
+        rows,
+        next_since: next.to_string(),
+    };
+    debug!(
+        "Table export of {} since '{}': {} rows, next '{}'",
+        page.entity.as_str(),
+        since,
+        page.rows.len(),
+        page.next_since
+    );
 
-    // Determine the actual task_date to be used
-    let task_date_to_use = payload.task_date.unwrap_or_else(|| today);
+    let mut response = match query.format {
+        TableFormat::Json => Json(&page).into_response(),
+        TableFormat::Csv => (
+            [(header::CONTENT_TYPE, "text/csv; charset=utf-8")],
+            table::to_csv(&page),
+        )
+            .into_response(),
+    };
+    let headers = response.headers_mut();
+    headers.insert(
+        HeaderName::from_static("x-next-cursor"),
+        HeaderValue::from_str(&page.next_since).expect("cursor is a valid header value"),
+    );
+    headers.insert(
+        HeaderName::from_static("x-has-more"),
+        HeaderValue::from_static(if page.has_more { "true" } else { "false" }),
+    );
+    Ok(response)
+}
 
-    // Validate if the provided or default task_date is within the current week
-    if task_date_to_use < current_week_start || task_date_to_use > current_week_end {
-        error!(
-            "Validation failed: Task date {} is outside the current week ({} to {}).",
-            task_date_to_use, current_week_start, current_week_end
-        );
+/// Query parameters accepted by `GET /api/export/calendar.ics`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportCalendarQuery {
+    /// Export token, as calendars cannot send an `Authorization` header.
+    pub token: Option<String>,
+}
+
+/// Handler of the iCalendar feed calendars subscribe to (Google Calendar,
+/// Thunderbird, ...): the tasks of the last weeks, the current one and the
+/// planning horizon, as all-day events, rendered afresh at each refresh.
+///
+/// Requires one of the `EXPORT_API_TOKENS`, as a bearer token or in `token`.
+#[utoipa::path(
+    get,
+    path = "/api/export/calendar.ics",
+    tag = "export",
+    params(ExportCalendarQuery),
+    security(("export_token" = [])),
+    responses(
+        (
+            status = 200,
+            description = "Tasks as all-day events",
+            body = String,
+            content_type = "text/calendar",
+        ),
+        (status = 401, description = "Missing export token", body = ErrorBody),
+    )
+)]
+pub async fn export_calendar(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+    headers: HeaderMap,
+    Query(query): Query<ExportCalendarQuery>,
+) -> Result<Response, AppError> {
+    check_export_token(&config, &headers, query.token.as_deref())?;
+
+    let (week_start, week_end) = database::current_week_range();
+    let from = week_start - Duration::weeks(calendar::PAST_WEEKS);
+    let to = week_end + Duration::weeks(i64::from(config.planning_horizon_weeks));
+    let tasks = database::get_tasks_between_from_db(&pool, from, to).await?;
+    // Event UIDs name the server the calendar subscribed to.
+    let host = headers
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .map(|host| {
+            host.rsplit_once(':')
+                .filter(|(_, port)| port.bytes().all(|b| b.is_ascii_digit()))
+                .map_or(host, |(name, _)| name)
+        })
+        .filter(|host| !host.is_empty())
+        .unwrap_or("weekly-task-manager");
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        calendar::render(&tasks, host, clock::now()),
+    )
+        .into_response())
+}
+
+/// Query parameters accepted by `GET /api/export/tasks.csv`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportTasksCsvQuery {
+    /// ISO week of the tasks, e.g. `2025-W29`.
+    pub week: Option<String>,
+    /// Client of the tasks, or one of its aliases.
+    pub client: Option<String>,
+    /// `open` tasks by default, `done` or `all`.
+    #[serde(default)]
+    pub status: TaskStatusFilter,
+    /// Field separator, `,` by default. `;` suits spreadsheets of locales
+    /// writing decimals with a comma.
+    pub delimiter: Option<String>,
+}
+
+/// Delimiter of the CSV export: a single character that cannot be mistaken
+/// for a value, such as `,`, `;`, `|` or a tab.
+fn parse_csv_delimiter(delimiter: Option<&str>) -> Result<char, AppError> {
+    let Some(delimiter) = delimiter else {
+        return Ok(',');
+    };
+    let mut chars = delimiter.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c == '\t' || (c.is_ascii_punctuation() && c != '"') => Ok(c),
+        _ => Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Invalid CSV delimiter '{delimiter}', expected a punctuation character or a tab."
+            ),
+        )),
+    }
+}
+
+/// Handler exporting the tasks matching the filters as CSV, by date, with a
+/// header line. Rows are streamed as they are read from the database.
+#[utoipa::path(
+    get,
+    path = "/api/export/tasks.csv",
+    tag = "export",
+    params(ExportTasksCsvQuery),
+    responses(
+        (
+            status = 200,
+            description = "Matching tasks, by date",
+            body = String,
+            content_type = "text/csv",
+        ),
+        (status = 400, description = "Invalid week or delimiter", body = ErrorBody),
+    )
+)]
+pub async fn export_tasks_csv(
+    State(pool): State<SqlitePool>,
+    Query(query): Query<ExportTasksCsvQuery>,
+) -> Result<Response, AppError> {
+    let delimiter = parse_csv_delimiter(query.delimiter.as_deref())?;
+    let dates = match query.week.as_deref() {
+        Some(week) => Some(plan::parse_iso_week(week).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                &format!("Invalid week '{week}', expected e.g. 2025-W29."),
+            )
+        })?),
+        None => None,
+    };
+    let (from, to) = dates.unzip();
+    let filter = TaskFilter {
+        client_name: query.client,
+        task_date: None,
+        from,
+        to,
+        status: query.status,
+    };
+
+    let (sender, receiver) = mpsc::channel(table::TASK_CSV_BUFFER);
+    let reading =
+        tokio::spawn(async move { database::stream_tasks_from_db(&pool, &filter, sender).await });
+    let header = stream::once(future::ready(Ok(Bytes::from(table::task_csv_header(
+        delimiter,
+    )))));
+    let rows = stream::unfold(receiver, move |mut receiver| async move {
+        let task = receiver.recv().await?;
+        let line = Bytes::from(table::task_csv_line(&task, delimiter));
+        Some((Ok(line), receiver))
+    });
+    // A failure cuts the response short, rather than ending it like a
+    // complete export.
+    let outcome = stream::once(async move {
+        let error = match reading.await {
+            Ok(Ok(())) => return None,
+            Ok(Err(e)) => format!("{e:?}"),
+            Err(e) => e.to_string(),
+        };
+        error!("Failed to export tasks as CSV: {}", error);
+        Some(Err(std::io::Error::other("Task export failed")))
+    })
+    .filter_map(future::ready);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"tasks.csv\"",
+            ),
+        ],
+        Body::from_stream(header.chain(rows).chain(outcome)),
+    )
+        .into_response())
+}
+
+/// Query parameters accepted by `GET /api/ws`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LiveQuery {
+    /// Sync cursor to push the changes from. Only the changes made after
+    /// the connection is opened are pushed when absent.
+    pub since: Option<String>,
+}
+
+/// Handler upgrading to a live connection for realtime boards: the changes
+/// made by anyone are pushed as they happen, in the format of the sync, and
+/// tasks can be completed or moved by sending commands.
+#[utoipa::path(
+    get,
+    path = "/api/ws",
+    tag = "tasks",
+    params(LiveQuery),
+    responses(
+        (status = 101, description = "Switched to a WebSocket"),
+        (status = 400, description = "Invalid sync cursor", body = ErrorBody),
+    )
+)]
+pub async fn live_updates(
+    ws: WebSocketUpgrade,
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+    State(feed): State<Arc<ChangeFeed>>,
+    Query(query): Query<LiveQuery>,
+) -> Result<Response, AppError> {
+    let since = match query.since.as_deref() {
+        Some(token) => Some(token.parse::<SyncCursor>().map_err(|_| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                &format!("Invalid sync cursor '{token}'."),
+            )
+        })?),
+        None => None,
+    };
+    Ok(ws.on_upgrade(move |socket| live::serve_connection(socket, pool, config, feed, since)))
+}
+
+/// Handler running a GraphQL query or mutation on the tasks and clients.
+/// Errors are reported in the body, with the status the REST routes
+/// would answer in their `status` extension.
+pub async fn graphql(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+    State(schema): State<ApiSchema>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    Json(schema.execute(request.data(pool).data(config)).await)
+}
+
+/// Handler serving the GraphQL playground, in debug builds only.
+pub async fn graphql_playground() -> Html<String> {
+    Html(async_graphql::http::playground_source(
+        async_graphql::http::GraphQLPlaygroundConfig::new("/api/graphql"),
+    ))
+}
+
+/// Query parameters accepted by `GET /api/sync`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SyncQuery {
+    /// Cursor returned by the previous sync, as `cursor`. A full sync is
+    /// sent when absent.
+    pub since: Option<String>,
+    /// Maximum number of clients, tasks and removals in the page, each.
+    pub limit: Option<i64>,
+}
+
+/// Handler of the sync of the mobile client: the clients and tasks written
+/// since the device's cursor, and those removed, with each row sent as an
+/// array of values rather than an object. In MessagePack, the payload
+/// itself is written as an array too.
+#[utoipa::path(
+    get,
+    path = "/api/sync",
+    tag = "export",
+    params(SyncQuery),
+    responses(
+        (
+            status = 200,
+            description = "Rows changed since the cursor",
+            content(
+                (SyncDelta = "application/json"),
+                (SyncDelta = "application/msgpack"),
+                (SyncDelta = "application/cbor"),
+            ),
+        ),
+        (status = 400, description = "Invalid cursor or limit", body = ErrorBody),
+    )
+)]
+pub async fn sync(
+    State(pool): State<SqlitePool>,
+    headers: HeaderMap,
+    Query(query): Query<SyncQuery>,
+) -> Result<Response, AppError> {
+    let since = match query.since.as_deref() {
+        Some(token) => Some(token.parse::<SyncCursor>().map_err(|_| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                &format!("Invalid sync cursor '{token}'."),
+            )
+        })?),
+        None => None,
+    };
+    let limit = query.limit.unwrap_or(table::DEFAULT_PAGE_SIZE);
+    if !(1..=table::MAX_PAGE_SIZE).contains(&limit) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Page limit must be between 1 and {}.", table::MAX_PAGE_SIZE),
+        ));
+    }
+
+    let changes =
+        database::get_sync_changes_from_db(&pool, since.unwrap_or_default(), limit).await?;
+    let delta = SyncDelta::new(since, changes, limit);
+    let format = BodyFormat::from_accept(
+        headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok()),
+    );
+    debug!(
+        "Sync since '{}' as {:?}: {} clients, {} tasks, {} removals, next '{}'",
+        since.unwrap_or_default(),
+        format,
+        delta.clients.len(),
+        delta.tasks.len(),
+        delta.removed_clients.len() + delta.removed_tasks.len(),
+        delta.cursor
+    );
+
+    Ok(match format {
+        BodyFormat::MessagePack => (
+            [(header::CONTENT_TYPE, format.media_type())],
+            sync::to_message_pack(&delta).context("Failed to encode the sync payload")?,
+        )
+            .into_response(),
+        // Translated to CBOR, when asked for, by `negotiate_formats`.
+        _ => Json(&delta).into_response(),
+    })
+}
+
+/// Query parameters accepted by `GET /api/admin/jobs`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobsQuery {
+    /// Number of runs to return per job, most recent first (10 by default).
+    pub limit: Option<i64>,
+}
+
+/// Handler listing the background jobs and their latest runs.
+#[utoipa::path(
+    get,
+    path = "/api/admin/jobs",
+    tag = "admin",
+    params(JobsQuery),
+    responses(
+        (status = 200, description = "Jobs and their latest runs", body = Vec<JobStatus>),
+        (status = 400, description = "Invalid limit", body = ErrorBody),
+    )
+)]
+pub async fn list_jobs(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+    Query(query): Query<JobsQuery>,
+) -> Result<Json<Vec<JobStatus>>, AppError> {
+    let limit = query.limit.unwrap_or(10);
+    if limit < 1 {
         return Err(AppError::new(
             StatusCode::BAD_REQUEST,
+            "limit must be at least 1.",
+        ));
+    }
+
+    let mut statuses = Vec::new();
+    for job in jobs::JOB_NAMES {
+        statuses.push(job_status(&pool, &config, job, limit).await?);
+    }
+    Ok(Json(statuses))
+}
+
+async fn job_status(
+    pool: &SqlitePool,
+    config: &Config,
+    job: &str,
+    limit: i64,
+) -> Result<JobStatus, AppError> {
+    Ok(JobStatus {
+        job: job.to_string(),
+        enabled: jobs::is_enabled(config, job),
+        paused_at: database::get_job_paused_at_from_db(pool, job).await?,
+        runs: database::get_job_runs_from_db(pool, job, limit).await?,
+    })
+}
+
+/// Pauses or resumes the background job `job` on every instance.
+async fn set_job_paused(
+    pool: SqlitePool,
+    config: &Config,
+    job: String,
+    paused: bool,
+) -> Result<Json<JobStatus>, AppError> {
+    if !jobs::JOB_NAMES.contains(&job.as_str()) {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
             &format!(
-                "Task date must be within the current week (from {} to {}).",
-                current_week_start, current_week_end
+                "Unknown job '{job}', expected one of: {}.",
+                jobs::JOB_NAMES.join(", ")
             ),
         ));
     }
+    database::set_job_paused_in_db(&pool, &job, paused).await?;
+    info!(
+        "Job '{}' {}.",
+        job,
+        if paused { "paused" } else { "resumed" }
+    );
+    Ok(Json(job_status(&pool, config, &job, 10).await?))
+}
+
+/// Handler pausing a background job, e.g. the rollover for people who only
+/// roll tasks over by hand with `PATCH /api/tasks/rollover`. Its occurrences
+/// are skipped by every instance until it is resumed, restarts included.
+#[utoipa::path(
+    post,
+    path = "/api/admin/jobs/{job}/pause",
+    tag = "admin",
+    params(("job" = String, Path, description = "Name of the job, e.g. rollover")),
+    responses(
+        (status = 200, description = "Job paused", body = JobStatus),
+        (status = 404, description = "Unknown job", body = ErrorBody),
+    )
+)]
+pub async fn pause_job(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+    Path(job): Path<String>,
+) -> Result<Json<JobStatus>, AppError> {
+    set_job_paused(pool, &config, job, true).await
+}
+
+/// Handler resuming a paused background job from its next occurrence.
+#[utoipa::path(
+    delete,
+    path = "/api/admin/jobs/{job}/pause",
+    tag = "admin",
+    params(("job" = String, Path, description = "Name of the job, e.g. rollover")),
+    responses(
+        (status = 200, description = "Job resumed", body = JobStatus),
+        (status = 404, description = "Unknown job", body = ErrorBody),
+    )
+)]
+pub async fn resume_job(
+    State(pool): State<SqlitePool>,
+    State(config): State<Arc<Config>>,
+    Path(job): Path<String>,
+) -> Result<Json<JobStatus>, AppError> {
+    set_job_paused(pool, &config, job, false).await
+}
+
+/// Handler testing each configured notification channel: a test message
+/// is posted to the chat channels, and the SMTP server of the email digest
+/// is signed in to without sending anything. Answers 502 Bad Gateway when
+/// any of them failed.
+#[utoipa::path(
+    post,
+    path = "/api/notifications/test",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Every channel works", body = Vec<ChannelCheck>),
+        (status = 404, description = "No channel configured", body = ErrorBody),
+        (status = 502, description = "A channel failed", body = Vec<ChannelCheck>),
+    )
+)]
+pub async fn test_notifications(
+    State(config): State<Arc<Config>>,
+) -> Result<(StatusCode, Json<Vec<ChannelCheck>>), AppError> {
+    let notifier = Notifier::from_config(&config.notifications)?;
+    let mailer = config.email.as_ref().map(Mailer::from_config).transpose()?;
+    if !notifier.is_enabled() && mailer.is_none() {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            "No notification channel is configured.",
+        ));
+    }
+
+    let mut outcomes = notifier.send_test().await;
+    if let Some(mailer) = mailer {
+        outcomes.push(("email", mailer.verify().await));
+    }
+    let checks: Vec<ChannelCheck> = outcomes
+        .into_iter()
+        .map(|(channel, outcome)| ChannelCheck {
+            channel: channel.to_string(),
+            ok: outcome.is_ok(),
+            error: outcome.err().map(|e| format!("{e:#}")),
+        })
+        .collect();
+    let status = if checks.iter().all(|check| check.ok) {
+        StatusCode::OK
+    } else {
+        StatusCode::BAD_GATEWAY
+    };
+    Ok((status, Json(checks)))
+}
 
-    let new_task = database::create_task_in_db(&pool, payload).await?;
+/// Handler registering a webhook. The events it subscribes to are posted
+/// to its URL by the delivery worker, signed with the secret returned here.
+#[utoipa::path(
+    post,
+    path = "/api/webhooks",
+    tag = "admin",
+    request_body = CreateWebhookPayload,
+    responses(
+        (status = 201, description = "Webhook registered, with its secret", body = Webhook),
+        (status = 400, description = "Invalid URL or events", body = ErrorBody),
+    )
+)]
+pub async fn create_webhook(
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<CreateWebhookPayload>,
+) -> Result<(StatusCode, Json<Webhook>), AppError> {
+    let url = payload.url.trim();
+    let valid_url = url.parse::<Uri>().is_ok_and(|uri| {
+        matches!(uri.scheme_str(), Some("http" | "https")) && uri.host().is_some()
+    });
+    if !valid_url {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "The URL of a webhook must be an absolute http:// or https:// address.",
+        ));
+    }
+    let mut events = payload.events;
+    events.sort_by_key(|event| WebhookEvent::ALL.iter().position(|known| known == event));
+    events.dedup();
+    if events.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "A webhook needs at least one event.",
+        ));
+    }
 
-    info!("Task created successfully with ID: {}", new_task.id);
+    let secret = SigningSecret::generate();
+    let webhook = database::create_webhook_in_db(&pool, url, &events, secret.expose()).await?;
+    info!("Webhook {} registered for {}.", webhook.id, url);
+    Ok((StatusCode::CREATED, Json(webhook)))
+}
 
-    // Return a 201 Created status with the new task as JSON.
-    Ok((StatusCode::CREATED, Json(new_task)))
+/// Handler listing the webhooks, without their secrets.
+#[utoipa::path(
+    get,
+    path = "/api/webhooks",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Webhooks", body = Vec<Webhook>),
+    )
+)]
+pub async fn list_webhooks(State(pool): State<SqlitePool>) -> Result<Json<Vec<Webhook>>, AppError> {
+    Ok(Json(database::get_webhooks_from_db(&pool).await?))
 }
 
-/// Handler for deleting a task by ID.
-#[allow(clippy::needless_return)]
-#[allow(clippy::uninlined_format_args)]
-pub async fn delete_task(
+/// Handler removing a webhook, along with its pending deliveries.
+#[utoipa::path(
+    delete,
+    path = "/api/webhooks/{id}",
+    tag = "admin",
+    params(("id" = i64, Path, description = "ID of the webhook")),
+    responses(
+        (status = 204, description = "Webhook removed"),
+        (status = 404, description = "Webhook not found", body = ErrorBody),
+    )
+)]
+pub async fn delete_webhook(
     State(pool): State<SqlitePool>,
-    Path(task_id): Path<i64>, // Extract task ID from the URL path
+    Path(webhook_id): Path<i64>,
 ) -> Result<StatusCode, AppError> {
-    debug!("Attempting to delete task with ID: {}", task_id);
+    if database::delete_webhook_in_db(&pool, webhook_id).await? {
+        info!("Webhook {} removed.", webhook_id);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(webhook_not_found(webhook_id))
+    }
+}
 
-    //let deleted = database::delete_task_from_db(&pool, task_id).await?;
-    let deleted = database::soft_delete_task_in_db(&pool, task_id).await?;
+fn webhook_not_found(webhook_id: i64) -> AppError {
+    AppError::new(
+        StatusCode::NOT_FOUND,
+        &format!("Webhook with ID {webhook_id} not found."),
+    )
+}
 
-    if deleted {
-        info!("Task with ID {} deleted successfully.", task_id);
-        Ok(StatusCode::NO_CONTENT) // 204 No Content for successful deletion
-    } else {
-        error!("Task with ID {} not found for deletion.", task_id);
+/// Query parameters accepted by `GET /api/webhooks/{id}/deliveries`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WebhookDeliveriesQuery {
+    /// Number of deliveries to return, most recent first (50 by default).
+    pub limit: Option<i64>,
+}
+
+/// Handler for the delivery log of a webhook: the events sent or still to
+/// send, with the outcome of their last attempt.
+#[utoipa::path(
+    get,
+    path = "/api/webhooks/{id}/deliveries",
+    tag = "admin",
+    params(
+        ("id" = i64, Path, description = "ID of the webhook"),
+        WebhookDeliveriesQuery,
+    ),
+    responses(
+        (status = 200, description = "Deliveries, most recent first", body = Vec<WebhookDelivery>),
+        (status = 400, description = "Invalid limit", body = ErrorBody),
+        (status = 404, description = "Webhook not found", body = ErrorBody),
+    )
+)]
+pub async fn list_webhook_deliveries(
+    State(pool): State<SqlitePool>,
+    Path(webhook_id): Path<i64>,
+    Query(query): Query<WebhookDeliveriesQuery>,
+) -> Result<Json<Vec<WebhookDelivery>>, AppError> {
+    let limit = query.limit.unwrap_or(50);
+    if limit < 1 {
         return Err(AppError::new(
-            StatusCode::NOT_FOUND,
-            &format!("Task with ID {} not found for deletion.", task_id),
+            StatusCode::BAD_REQUEST,
+            "limit must be at least 1.",
         ));
     }
+    if database::get_webhook_from_db(&pool, webhook_id)
+        .await?
+        .is_none()
+    {
+        return Err(webhook_not_found(webhook_id));
+    }
+    Ok(Json(
+        database::get_webhook_deliveries_from_db(&pool, webhook_id, limit).await?,
+    ))
 }
 
-/// Handler for rollover tasks on the next day.
-pub async fn rollover_tasks(
+/// Handler for the dead letters: the webhook deliveries given up on after
+/// their last attempt, of every webhook.
+#[utoipa::path(
+    get,
+    path = "/api/admin/dead-letters",
+    tag = "admin",
+    params(WebhookDeliveriesQuery),
+    responses(
+        (status = 200, description = "Failed deliveries, most recent first", body = Vec<WebhookDelivery>),
+        (status = 400, description = "Invalid limit", body = ErrorBody),
+    )
+)]
+pub async fn list_dead_letters(
     State(pool): State<SqlitePool>,
-) -> Result<Json<serde_json::Value>, AppError> {
-    // Return JSON for message/count
-    debug!("Received request to roll over tasks.");
+    Query(query): Query<WebhookDeliveriesQuery>,
+) -> Result<Json<Vec<WebhookDelivery>>, AppError> {
+    let limit = query.limit.unwrap_or(50);
+    if limit < 1 {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "limit must be at least 1.",
+        ));
+    }
+    Ok(Json(
+        database::get_failed_webhook_deliveries_from_db(&pool, limit).await?,
+    ))
+}
 
-    let num_rolled_over = database::rollover_tasks_in_db(&pool).await?;
+/// Handler sending failed webhook deliveries again, e.g. once the receiver
+/// is back up. They are queued with a fresh count of attempts.
+#[utoipa::path(
+    post,
+    path = "/api/admin/dead-letters",
+    tag = "admin",
+    request_body = RequeueDeliveriesPayload,
+    responses(
+        (status = 200, description = "Deliveries queued again; those that had not failed are left out", body = RequeuedDeliveries),
+    )
+)]
+pub async fn requeue_dead_letters(
+    State(pool): State<SqlitePool>,
+    Json(payload): Json<RequeueDeliveriesPayload>,
+) -> Result<Json<RequeuedDeliveries>, AppError> {
+    let delivery_ids = database::requeue_failed_webhook_deliveries_in_db(
+        &pool,
+        payload.delivery_ids.as_deref(),
+        clock::now(),
+    )
+    .await?;
+    info!(
+        "{} failed webhook deliveries queued again.",
+        delivery_ids.len()
+    );
+    Ok(Json(RequeuedDeliveries { delivery_ids }))
+}
 
-    info!("Successfully rolled over {} tasks.", num_rolled_over);
+/// Handler downloading a consistent snapshot of the database, taken while
+/// the server keeps running. It is a plain SQLite file.
+#[utoipa::path(
+    get,
+    path = "/api/admin/backup",
+    tag = "admin",
+    responses(
+        (
+            status = 200,
+            description = "SQLite file of the database",
+            body = Binary,
+            content_type = "application/vnd.sqlite3",
+        ),
+    )
+)]
+pub async fn download_backup(State(pool): State<SqlitePool>) -> Result<Response, AppError> {
+    let snapshot = backup::TempFile::new("backup");
+    database::backup_database_to(&pool, snapshot.path()).await?;
+    // The open file stays readable once the snapshot is removed on return.
+    let file = tokio::fs::File::open(snapshot.path())
+        .await
+        .context("Failed to open database backup")?;
+    let file_name = format!(
+        "weekly-task-manager-{}.db",
+        clock::now().format("%Y%m%dT%H%M%SZ")
+    );
+    info!("Streaming database backup {}.", file_name);
 
-    Ok(Json(serde_json::json!({
-        "message": format!("Successfully rolled over {} tasks.", num_rolled_over),
-        "tasks_rolled_over": num_rolled_over
-    })))
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/vnd.sqlite3".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{file_name}\""),
+            ),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
+
+/// Handler replacing all data with an uploaded backup, as downloaded from
+/// `GET /api/admin/backup`. Nothing is changed if the backup is invalid.
+#[utoipa::path(
+    post,
+    path = "/api/admin/restore",
+    tag = "admin",
+    request_body(content = Binary, content_type = "application/vnd.sqlite3"),
+    responses(
+        (status = 204, description = "Database restored"),
+        (status = 400, description = "Invalid backup", body = ErrorBody),
+    )
+)]
+pub async fn restore_backup(
+    State(pool): State<SqlitePool>,
+    body: Bytes,
+) -> Result<StatusCode, AppError> {
+    let upload = backup::TempFile::new("restore");
+    tokio::fs::write(upload.path(), &body)
+        .await
+        .context("Failed to store uploaded backup")?;
+
+    match database::restore_database_from(&pool, upload.path()).await? {
+        RestoreOutcome::Restored => {
+            info!("Database restored from an uploaded backup.");
+            Ok(StatusCode::NO_CONTENT)
+        }
+        RestoreOutcome::Invalid(reason) => Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!("Invalid backup: {reason}"),
+        )),
+    }
+}
+
+/// Handler downloading every client, task and setting as a versioned JSON
+/// document, which `POST /api/import/full` loads back, into this database
+/// or another one.
+#[utoipa::path(
+    get,
+    path = "/api/export/full",
+    tag = "export",
+    responses(
+        (status = 200, description = "Every client, task and setting", body = FullExport),
+    )
+)]
+pub async fn export_full(State(pool): State<SqlitePool>) -> Result<Response, AppError> {
+    let export = database::get_full_export_from_db(&pool).await?;
+    let file_name = format!(
+        "weekly-task-manager-{}.json",
+        export.exported_at.format("%Y%m%dT%H%M%SZ")
+    );
+    info!(
+        "Full export of {} clients and {} tasks.",
+        export.clients.len(),
+        export.tasks.len()
+    );
+
+    Ok((
+        [(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{file_name}\""),
+        )],
+        Json(export),
+    )
+        .into_response())
+}
+
+/// Query parameters accepted by `POST /api/import/full`.
+#[derive(Deserialize, Debug, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportFullQuery {
+    /// What to do with the records already there, `skip` by default.
+    #[serde(default)]
+    pub on_conflict: ImportStrategy,
+}
+
+/// Handler loading a document of `GET /api/export/full`. Nothing is changed
+/// if it is invalid.
+#[utoipa::path(
+    post,
+    path = "/api/import/full",
+    tag = "import",
+    params(ImportFullQuery),
+    request_body = FullExport,
+    responses(
+        (status = 200, description = "Records imported", body = FullImportSummary),
+        (
+            status = 400,
+            description = "Invalid export, tasks listed in errors by index",
+            body = ErrorBody,
+        ),
+    )
+)]
+pub async fn import_full(
+    State(pool): State<SqlitePool>,
+    Query(query): Query<ImportFullQuery>,
+    Json(export): Json<FullExport>,
+) -> Result<Json<FullImportSummary>, AppError> {
+    if export.version == 0 || export.version > FULL_EXPORT_VERSION {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "Unsupported export version {}, this server reads up to version {}.",
+                export.version, FULL_EXPORT_VERSION
+            ),
+        ));
+    }
+    let client_names: HashSet<&str> = export.clients.iter().map(|c| c.name.as_str()).collect();
+    let errors: Vec<ItemError> = export
+        .tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, task)| !client_names.contains(task.client_name.as_str()))
+        .map(|(index, task)| ItemError {
+            index,
+            error: format!("Client '{}' is not in the export.", task.client_name),
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Err(AppError::with_items(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "{} of {} tasks have no client, nothing was imported.",
+                errors.len(),
+                export.tasks.len()
+            ),
+            errors,
+        ));
+    }
+
+    let summary = database::import_full_export_in_db(&pool, &export, query.on_conflict).await?;
+    Ok(Json(summary))
 }
 
 // --- Custom Error Handling ---
@@ -121,13 +3730,32 @@ pub async fn rollover_tasks(
 pub struct AppError {
     code: StatusCode,
     message: String,
+    /// Errors of the individual items of a bulk request, if any.
+    items: Vec<ItemError>,
 }
 
 impl AppError {
-    fn new(code: StatusCode, message: &str) -> Self {
+    pub(crate) fn new(code: StatusCode, message: &str) -> Self {
         Self {
             code,
             message: message.to_string(),
+            items: Vec::new(),
+        }
+    }
+
+    /// What went wrong, as told to the client.
+    pub(crate) fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub(crate) fn code(&self) -> StatusCode {
+        self.code
+    }
+
+    fn with_items(code: StatusCode, message: &str, items: Vec<ItemError>) -> Self {
+        Self {
+            items,
+            ..Self::new(code, message)
         }
     }
 }
@@ -141,6 +3769,7 @@ impl From<anyhow::Error> for AppError {
         Self {
             code: StatusCode::INTERNAL_SERVER_ERROR,
             message: "An internal error occurred.".to_string(),
+            items: Vec::new(),
         }
     }
 }
@@ -153,19 +3782,20 @@ impl IntoResponse for AppError {
             self.code.as_u16(),
             self.message
         );
-        (
-            self.code,
-            Json(serde_json::json!({ "error": self.message })),
-        )
-            .into_response()
+        let body = if self.items.is_empty() {
+            serde_json::json!({ "error": self.message })
+        } else {
+            serde_json::json!({ "error": self.message, "errors": self.items })
+        };
+        (self.code, Json(body)).into_response()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::NaiveDate;
-    use common::CreateTaskPayload;
+    use chrono::Utc;
+    use common::{CreateTaskPayload, Priority};
     use sqlx::SqlitePool;
 
     // Helper to create a payload for tests
@@ -173,13 +3803,14 @@ mod tests {
         client_name: &str,
         description: &str,
         date: Option<NaiveDate>,
-        priority: Option<i32>,
+        priority: Option<Priority>,
     ) -> Json<CreateTaskPayload> {
         Json(CreateTaskPayload {
             client_name: client_name.to_string(),
             description: description.to_string(),
             task_date: date,
-            priority: priority,
+            priority,
+            ..Default::default()
         })
     }
 
@@ -194,7 +3825,14 @@ mod tests {
             None,
         );
 
-        let result = create_task(State(pool), payload).await;
+        let result = create_task(
+            State(pool),
+            State(Arc::new(Config::default())),
+            Query(CreateTaskQuery::default()),
+            HeaderMap::new(),
+            payload,
+        )
+        .await;
 
         assert!(result.is_err());
         let err = result.unwrap_err();
@@ -209,7 +3847,14 @@ mod tests {
         let payload =
             create_test_payload("Test Client", "A valid description", Some(past_date), None);
 
-        let result = create_task(State(pool), payload).await;
+        let result = create_task(
+            State(pool),
+            State(Arc::new(Config::default())),
+            Query(CreateTaskQuery::default()),
+            HeaderMap::new(),
+            payload,
+        )
+        .await;
 
         assert!(result.is_err());
         let err = result.unwrap_err();
@@ -218,4 +3863,68 @@ mod tests {
             .message
             .contains("Task date must be within the current week"));
     }
+
+    #[test]
+    fn test_group_tasks_by_day_includes_empty_days() {
+        let monday = NaiveDate::from_ymd_opt(2025, 7, 7).unwrap();
+        let task = |id: i64, task_date: NaiveDate| TaskSummary {
+            id,
+            client_name: "Client".to_string(),
+            client_color: "#1f77b4".parse().unwrap(),
+            client_text_color: "#ffffff".to_string(),
+            client_background_color: "#d2e4f0".to_string(),
+            description: "Task".to_string(),
+            task_date,
+            priority: None,
+            context: None,
+            slot_id: None,
+            slots_total: 0,
+            slots_done: 0,
+            deferred: false,
+            display: Default::default(),
+        };
+        let wednesday = monday + chrono::Duration::days(2);
+        let next_monday = monday + chrono::Duration::days(7);
+
+        let days = group_tasks_by_day(
+            vec![task(2, wednesday), task(1, wednesday), task(3, next_monday)],
+            monday,
+        );
+
+        assert_eq!(days.len(), 7);
+        assert!(days[&monday].is_empty());
+        // Order within a day is preserved, tasks outside the week are dropped
+        let ids: Vec<i64> = days[&wednesday].iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![2, 1]);
+        assert!(!days.contains_key(&next_monday));
+    }
+
+    #[tokio::test]
+    async fn test_create_task_validation_partial_external_ref() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let mut payload = create_test_payload(
+            "Test Client",
+            "A valid description",
+            Some(Utc::now().date_naive()),
+            None,
+        );
+        payload.external_id = Some("42".to_string());
+
+        let result = create_task(
+            State(pool),
+            State(Arc::new(Config::default())),
+            Query(CreateTaskQuery::default()),
+            HeaderMap::new(),
+            payload,
+        )
+        .await;
+
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert_eq!(err.code, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.message,
+            "external_source and external_id must be provided together."
+        );
+    }
 }
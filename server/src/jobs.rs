@@ -0,0 +1,196 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::clock;
+use crate::config::{Config, JobsConfig};
+use crate::database::{self, NewJobRun};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Names of the background jobs, as accepted by `JOBS_DISABLED`.
+pub const JOB_NAMES: [&str; 4] = ["rollover", "backup", "digest", "email-digest"];
+
+/// Work run in the background on a schedule, see `run_job`.
+pub trait Job: Send + Sync + 'static {
+    /// Name the job is claimed, recorded and configured under.
+    fn name(&self) -> &'static str;
+
+    /// Next occurrence strictly after `after`, if the job ever runs again.
+    fn next_run(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>>;
+
+    /// First occurrence when the server starts at `now`. It may be in the
+    /// past, in which case the job runs at once.
+    fn first_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.next_run(now)
+    }
+
+    /// Upper bound of the random delay added to each occurrence.
+    fn jitter(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// Does the work of one occurrence, returning a summary for the logs
+    /// and the run records.
+    fn run(&self, pool: &SqlitePool) -> impl Future<Output = Result<String>> + Send;
+}
+
+/// Whether the job `name` runs on this instance: not disabled through
+/// `JOBS_DISABLED`, and configured when it needs to be.
+pub fn is_enabled(config: &Config, name: &str) -> bool {
+    if config.jobs.disabled.iter().any(|disabled| disabled == name) {
+        return false;
+    }
+    match name {
+        "backup" => config.backup.dir.is_some(),
+        "digest" => config.notifications.has_channels(),
+        "email-digest" => config.email.is_some(),
+        _ => true,
+    }
+}
+
+/// Random delay between zero and `max`, spreading runs that would otherwise
+/// all start on the exact minute.
+fn random_jitter(max: Duration) -> Duration {
+    let max_ms = max.as_millis() as u64;
+    if max_ms == 0 {
+        return Duration::ZERO;
+    }
+    let random = RandomState::new().hash_one(clock::now());
+    Duration::from_millis(random % (max_ms + 1))
+}
+
+/// Delay before the attempt following the `attempt`th failure: the
+/// configured backoff, doubled after each further failure.
+fn retry_delay(retry: &JobsConfig, attempt: u32) -> Duration {
+    retry
+        .retry_backoff
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+}
+
+/// Runs `job` at each of its occurrences, forever. Each occurrence is
+/// claimed in the database first, so that only one of the instances
+/// sharing it runs the job; failed attempts are retried with backoff, and
+/// the outcome is recorded in the job runs. Occurrences falling while the
+/// job is paused are skipped, and those missed while the process was
+/// suspended are not replayed.
+pub async fn run_job<J: Job>(pool: SqlitePool, job: J, retry: JobsConfig, instance: String) {
+    let name = job.name();
+    let mut next = job.first_run(clock::now());
+    loop {
+        let Some(scheduled_at) = next else {
+            error!(job = name, "Job has no upcoming run, stopping it.");
+            return;
+        };
+        let wait = (scheduled_at - clock::now()).to_std().unwrap_or_default()
+            + random_jitter(job.jitter());
+        info!(
+            job = name,
+            %scheduled_at,
+            wait_secs = wait.as_secs(),
+            "Next job run scheduled."
+        );
+        tokio::time::sleep(wait).await;
+        next = job.next_run(scheduled_at.max(clock::now()));
+
+        match database::get_job_paused_at_from_db(&pool, name).await {
+            Ok(None) => {}
+            Ok(Some(paused_at)) => {
+                info!(job = name, %scheduled_at, %paused_at, "Job paused, skipping.");
+                continue;
+            }
+            Err(e) => error!(
+                job = name,
+                "Failed to check whether the job is paused: {:?}", e
+            ),
+        }
+
+        match database::claim_scheduled_job(&pool, name, scheduled_at, &instance).await {
+            Ok(true) => {}
+            Ok(false) => {
+                info!(job = name, %scheduled_at, "Job already run by another instance, skipping.");
+                continue;
+            }
+            // Better a job run twice than not at all: the rollover moves
+            // nothing the second time, and a backup is only a spare copy.
+            Err(e) => error!(job = name, %scheduled_at, "Failed to claim the job run: {:?}", e),
+        }
+
+        let started_at = clock::now();
+        let mut attempt = 1;
+        let outcome = loop {
+            match job.run(&pool).await {
+                Ok(summary) => break Ok(summary),
+                Err(e) if attempt < retry.max_attempts => {
+                    let delay = retry_delay(&retry, attempt);
+                    warn!(
+                        job = name,
+                        attempt,
+                        retry_in_secs = delay.as_secs(),
+                        "Job run failed, retrying: {:?}",
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => break Err(e),
+            }
+        };
+        let (succeeded, message) = match outcome {
+            Ok(summary) => {
+                info!(job = name, %scheduled_at, attempt, "{}", summary);
+                (true, summary)
+            }
+            Err(e) => {
+                error!(job = name, %scheduled_at, attempt, "Job run failed: {:?}", e);
+                (false, format!("{e:#}"))
+            }
+        };
+        if let Err(e) = database::record_job_run(
+            &pool,
+            NewJobRun {
+                job: name,
+                scheduled_for: scheduled_at,
+                instance: &instance,
+                started_at,
+                attempts: attempt,
+                succeeded,
+                message: &message,
+            },
+        )
+        .await
+        {
+            error!(job = name, "Failed to record the job run: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        assert_eq!(random_jitter(Duration::ZERO), Duration::ZERO);
+        for _ in 0..100 {
+            assert!(random_jitter(Duration::from_secs(2)) <= Duration::from_secs(2));
+        }
+    }
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        let retry = JobsConfig {
+            retry_backoff: Duration::from_secs(30),
+            ..JobsConfig::default()
+        };
+        assert_eq!(retry_delay(&retry, 1), Duration::from_secs(30));
+        assert_eq!(retry_delay(&retry, 2), Duration::from_secs(60));
+        assert_eq!(retry_delay(&retry, 3), Duration::from_secs(120));
+    }
+}
@@ -2,7 +2,49 @@
 //
 // This software is licensed under the terms of the MIT License.
 // See the LICENSE file in the project root for the full license text.
+pub mod archive;
+pub mod assets;
+pub mod auth;
+pub mod backup;
+pub mod calendar;
+pub mod cli;
+pub mod client_ip;
+pub mod clients;
+pub mod clock;
 pub mod colors;
+pub mod config;
+pub mod contexts;
 pub mod database;
+pub mod doctor;
+pub mod email;
+pub mod etag;
+pub mod export;
+pub mod faults;
+pub mod focus;
+pub mod formats;
+pub mod goal;
+pub mod graphql;
 pub mod handlers;
+pub mod jobs;
+pub mod limits;
+pub mod live;
+pub mod notify;
+pub mod openapi;
+pub mod plan;
+pub mod presentation;
+pub mod priorities;
+pub mod rate_limit;
 pub mod routes;
+pub mod scheduler;
+pub mod serve;
+pub mod share;
+pub mod state;
+pub mod suggest;
+pub mod summary;
+pub mod sync;
+pub mod table;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+pub mod throttle;
+pub mod tls;
+pub mod webhooks;
@@ -0,0 +1,175 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::Config;
+use crate::formats::BodyFormat;
+use crate::handlers::AppError;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Middleware answering `408 Request Timeout` once a request has taken
+/// longer than `REQUEST_TIMEOUT_SECS`, reading its body included. The
+/// handler is dropped, rolling back the transaction it may hold, so that a
+/// slow client cannot keep the single SQLite writer busy.
+pub async fn limit_request_time(
+    State(config): State<Arc<Config>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(timeout) = config.http.request_timeout else {
+        return next.run(request).await;
+    };
+    let (method, path) = (request.method().clone(), request.uri().path().to_string());
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!("{} {} timed out after {:?}", method, path, timeout);
+            AppError::new(
+                StatusCode::REQUEST_TIMEOUT,
+                &format!(
+                    "The request took longer than {} seconds.",
+                    timeout.as_secs()
+                ),
+            )
+            .into_response()
+        }
+    }
+}
+
+fn too_large(limit: usize) -> Response {
+    AppError::new(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        &format!("The request body is larger than {limit} bytes."),
+    )
+    .into_response()
+}
+
+/// Middleware answering `413 Payload Too Large` to bodies over
+/// `MAX_BODY_BYTES`: at once when their `Content-Length` says so, and in
+/// place of the plain-text answer of the extractors for those sent without
+/// one, which are only found too large while being read.
+pub async fn limit_body_size(
+    State(config): State<Arc<Config>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limit = config.http.max_body_bytes;
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > limit as u64) {
+        return too_large(limit);
+    }
+    let response = next.run(request).await;
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE
+        && BodyFormat::from_content_type(response.headers()) != Some(BodyFormat::Json)
+    {
+        return too_large(limit);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HttpConfig;
+    use axum::{body::Body, extract::DefaultBodyLimit, middleware, routing::post, Json, Router};
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use std::time::Duration;
+    use tokio_util::io::ReaderStream;
+    use tower::ServiceExt;
+
+    fn app(config: Config) -> Router {
+        let config = Arc::new(config);
+        Router::new()
+            .route(
+                "/echo",
+                post(|Json(value): Json<Value>| async move {
+                    if value["slow"] == true {
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                    Json(value)
+                }),
+            )
+            .route_layer(middleware::from_fn_with_state(
+                config.clone(),
+                limit_body_size,
+            ))
+            .route_layer(middleware::from_fn_with_state(
+                config.clone(),
+                limit_request_time,
+            ))
+            .layer(DefaultBodyLimit::max(config.http.max_body_bytes))
+    }
+
+    fn config(request_timeout: Option<Duration>, max_body_bytes: usize) -> Config {
+        Config {
+            http: HttpConfig {
+                request_timeout,
+                max_body_bytes,
+                ..HttpConfig::default()
+            },
+            ..Config::default()
+        }
+    }
+
+    async fn error_of(response: Response) -> String {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let error: Value = serde_json::from_slice(&body).unwrap();
+        error["error"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_slow_requests_time_out() {
+        let app = app(config(Some(Duration::from_millis(50)), 1024));
+        let request = Request::post("/echo")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"slow":true}"#))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+        assert!(error_of(response).await.contains("took longer"));
+
+        let request = Request::post("/echo")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"slow":false}"#))
+            .unwrap();
+        assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_large_bodies_are_rejected() {
+        let app = app(config(None, 16));
+        let large = format!(r#"{{"text":"{}"}}"#, "x".repeat(32));
+
+        // With a length, rejected before being read
+        let request = Request::post("/echo")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::CONTENT_LENGTH, large.len())
+            .body(Body::from(large.clone()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(error_of(response).await.contains("larger than 16 bytes"));
+
+        // Streamed without one, rejected while being read
+        let chunks = ReaderStream::new(std::io::Cursor::new(large));
+        let request = Request::post("/echo")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from_stream(chunks))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(error_of(response).await.contains("larger than 16 bytes"));
+    }
+}
@@ -0,0 +1,298 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::Config;
+use crate::database;
+use crate::handlers::{self, AppError};
+use crate::sync::{SyncColumns, SyncCursor, SyncDelta};
+use crate::table::{ClientRow, TaskRow, DEFAULT_PAGE_SIZE};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Json, Path, Request, State,
+    },
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use common::{LiveCommand, MoveTaskPayload};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{debug, warn};
+
+/// How often live connections look for changes no request announced, such
+/// as those of the scheduled rollover.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Wakes the live connections up when data may have changed. Carries no
+/// data itself: each connection reads what changed after its own cursor,
+/// as `GET /api/sync` does.
+#[derive(Debug)]
+pub struct ChangeFeed {
+    sender: watch::Sender<u64>,
+}
+
+impl Default for ChangeFeed {
+    fn default() -> Self {
+        ChangeFeed {
+            sender: watch::Sender::new(0),
+        }
+    }
+}
+
+impl ChangeFeed {
+    pub fn notify(&self) {
+        self.sender.send_modify(|generation| *generation += 1);
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.sender.subscribe()
+    }
+}
+
+/// Middleware announcing the successful requests that may have changed
+/// data to the live connections.
+pub async fn publish_changes(
+    State(feed): State<Arc<ChangeFeed>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let reads_only = request.method().is_safe();
+    let response = next.run(request).await;
+    if !reads_only && response.status().is_success() {
+        feed.notify();
+    }
+    response
+}
+
+/// Message sent to a client over its live connection, as JSON text.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LiveMessage {
+    /// First message: the position changes are followed from, and the
+    /// columns of the rows of the following `changes`.
+    Ready {
+        cursor: String,
+        columns: SyncColumns,
+    },
+    /// Rows written after the previous message, as in `GET /api/sync`.
+    Changes(SyncDelta),
+    /// A command was applied; its effect follows as `changes`.
+    Done { command: LiveCommand },
+    /// A message could not be understood or its command applied.
+    Error { error: String },
+}
+
+async fn send(socket: &mut WebSocket, message: &LiveMessage) -> bool {
+    match serde_json::to_string(message) {
+        Ok(text) => socket.send(Message::Text(text.into())).await.is_ok(),
+        Err(e) => {
+            warn!("Failed to encode a live message: {}", e);
+            false
+        }
+    }
+}
+
+/// Applies a command with the checks of the matching route.
+async fn apply(
+    pool: &SqlitePool,
+    config: &Arc<Config>,
+    command: &LiveCommand,
+) -> Result<(), AppError> {
+    match *command {
+        LiveCommand::Complete {
+            task_id,
+            updated_at,
+        } => {
+            let mut headers = HeaderMap::new();
+            if let Some(updated_at) = updated_at {
+                // A quoted RFC 3339 timestamp is a valid header value.
+                let version = HeaderValue::from_str(&format!("\"{}\"", updated_at.to_rfc3339()))
+                    .expect("Timestamps are valid header values");
+                headers.insert(header::IF_MATCH, version);
+            }
+            handlers::delete_task(State(pool.clone()), Path(task_id), headers)
+                .await
+                .map(drop)
+        }
+        LiveCommand::Move {
+            task_id,
+            task_date,
+            updated_at,
+        } => handlers::move_task(
+            State(pool.clone()),
+            State(config.clone()),
+            Path(task_id),
+            HeaderMap::new(),
+            Json(MoveTaskPayload {
+                task_date,
+                updated_at,
+            }),
+        )
+        .await
+        .map(drop),
+    }
+}
+
+/// Sends what changed after `cursor`, page by page, returning the new
+/// cursor, or `None` once the connection is gone.
+async fn send_changes(
+    socket: &mut WebSocket,
+    pool: &SqlitePool,
+    mut cursor: SyncCursor,
+) -> Option<SyncCursor> {
+    loop {
+        let changes =
+            match database::get_sync_changes_from_db(pool, cursor, DEFAULT_PAGE_SIZE).await {
+                Ok(changes) => changes,
+                Err(e) => {
+                    warn!("Failed to read the changes of a live connection: {:?}", e);
+                    return Some(cursor);
+                }
+            };
+        let delta = SyncDelta::new(Some(cursor), changes, DEFAULT_PAGE_SIZE);
+        let has_more = delta.has_more;
+        // The cursor of a delta is one the sync gave out, so it parses.
+        cursor = delta.cursor.parse().unwrap_or(cursor);
+        let empty = delta.clients.is_empty()
+            && delta.tasks.is_empty()
+            && delta.removed_clients.is_empty()
+            && delta.removed_tasks.is_empty();
+        if !empty && !send(socket, &LiveMessage::Changes(delta)).await {
+            return None;
+        }
+        if !has_more {
+            return Some(cursor);
+        }
+    }
+}
+
+/// Serves a live connection: pushes the changes made after `since`, or
+/// after the connection was opened, and applies the commands received,
+/// until the client goes away.
+pub async fn serve_connection(
+    mut socket: WebSocket,
+    pool: SqlitePool,
+    config: Arc<Config>,
+    feed: Arc<ChangeFeed>,
+    since: Option<SyncCursor>,
+) {
+    // Subscribed before reading the position, so that no change is missed.
+    let mut changed = feed.subscribe();
+    let mut cursor = match since {
+        Some(since) => since,
+        None => match database::get_sync_cursor_from_db(&pool).await {
+            Ok(cursor) => cursor,
+            Err(e) => {
+                warn!("Failed to open a live connection: {:?}", e);
+                return;
+            }
+        },
+    };
+    let ready = LiveMessage::Ready {
+        cursor: cursor.to_string(),
+        columns: SyncColumns {
+            clients: ClientRow::COLUMNS.to_vec(),
+            tasks: TaskRow::COLUMNS.to_vec(),
+        },
+    };
+    if !send(&mut socket, &ready).await {
+        return;
+    }
+    if since.is_some() {
+        let Some(next) = send_changes(&mut socket, &pool, cursor).await else {
+            return;
+        };
+        cursor = next;
+    }
+
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+    poll.tick().await;
+    loop {
+        tokio::select! {
+            received = socket.recv() => {
+                let text = match received {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    // Pings are answered by axum
+                    Some(Ok(_)) => continue,
+                };
+                let reply = match serde_json::from_str::<LiveCommand>(&text) {
+                    Ok(command) => match apply(&pool, &config, &command).await {
+                        Ok(()) => {
+                            debug!("Live command applied: {:?}", command);
+                            feed.notify();
+                            LiveMessage::Done { command }
+                        }
+                        Err(e) => LiveMessage::Error { error: e.message().to_string() },
+                    },
+                    Err(e) => LiveMessage::Error { error: format!("Invalid command: {e}") },
+                };
+                if !send(&mut socket, &reply).await {
+                    break;
+                }
+            }
+            result = changed.changed() => {
+                if result.is_err() {
+                    break;
+                }
+                match send_changes(&mut socket, &pool, cursor).await {
+                    Some(next) => cursor = next,
+                    None => break,
+                }
+            }
+            _ = poll.tick() => {
+                match send_changes(&mut socket, &pool, cursor).await {
+                    Some(next) => cursor = next,
+                    None => break,
+                }
+            }
+        }
+    }
+    debug!("Live connection closed at {}", cursor);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_are_tagged() {
+        let done = LiveMessage::Done {
+            command: LiveCommand::Complete {
+                task_id: 4,
+                updated_at: None,
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&done).unwrap(),
+            serde_json::json!({ "type": "done", "command": { "command": "complete", "task_id": 4 } })
+        );
+        let command: LiveCommand =
+            serde_json::from_str(r#"{"command":"move","task_id":4,"task_date":"2025-07-15"}"#)
+                .unwrap();
+        assert_eq!(
+            command,
+            LiveCommand::Move {
+                task_id: 4,
+                task_date: chrono::NaiveDate::from_ymd_opt(2025, 7, 15).unwrap(),
+                updated_at: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_feed_wakes_every_subscriber() {
+        let feed = ChangeFeed::default();
+        let mut first = feed.subscribe();
+        let mut second = feed.subscribe();
+        feed.notify();
+        first.changed().await.unwrap();
+        second.changed().await.unwrap();
+        assert_eq!(*second.borrow_and_update(), 1);
+    }
+}
@@ -2,33 +2,80 @@
 //
 // This software is licensed under the terms of the MIT License.
 // See the LICENSE file in the project root for the full license text.
-mod colors;
-mod database;
-mod handlers;
-mod routes;
-
-use axum::http::HeaderName;
-use chrono::Utc;
-use std::net::SocketAddr;
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use tokio::time::{self, Duration};
-use tower_http::cors::{Any, CorsLayer};
-
-// Define the DB_URL here for the main application's use.
-const MAIN_DB_URL: &str = "sqlite://database/sqlite.db";
+use clap::Parser;
+use server::backup::BackupJob;
+use server::cli::{Cli, Command};
+use server::config::{self, Config, LogFormat};
+use server::email::{EmailDigestJob, Mailer};
+use server::notify::{DigestJob, Notifier};
+use server::scheduler::RolloverJob;
+use server::{clock, colors, database, doctor, jobs, routes, serve, tls, webhooks};
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt()
+    // `server doctor [--fix]` checks the data instead of serving it.
+    let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref()).and_then(|mut config| {
+        cli.apply_to(&mut config);
+        // Flags may have changed the origins checked with the file and env.
+        config.http.cors.check()?;
+        Ok(config)
+    });
+
+    let logs = tracing_subscriber::fmt()
         .with_target(false)
-        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-        .init();
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env());
+    match config.as_ref().map(|config| config.log_format) {
+        Ok(LogFormat::Json) => logs.json().init(),
+        _ => logs.init(),
+    }
+
+    let doctor_fix = cli.command.map(|Command::Doctor { fix }| fix);
+    if doctor_fix.is_none() {
+        tracing::info!("Starting up the server...");
+    }
+
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::error!("Invalid configuration: {:?}", e);
+            std::process::exit(1);
+        }
+    };
 
-    tracing::info!("Starting up the server...");
+    // The database may live in the data directory, which must exist first.
+    if let Err(e) = config::prepare_data_dir(&config.data_dir) {
+        tracing::error!("Unusable data directory: {:?}", e);
+        std::process::exit(1);
+    }
+    tracing::info!("Data directory: {}", config.data_dir.display());
+    colors::set_data_dir(&config.data_dir);
+
+    clock::set_timezone(config.timezone);
+    if config.e2e_mode {
+        tracing::warn!(
+            "End-to-end test mode: the clock is frozen at {} and POST /api/test/reset wipes all data.",
+            config.e2e_start_time
+        );
+        clock::freeze(config.e2e_start_time);
+    }
+
+    if config.fault_injection {
+        if cfg!(debug_assertions) {
+            tracing::warn!("Fault injection enabled: faults are configured at /api/debug/faults.");
+        } else {
+            tracing::warn!("FAULT_INJECTION is ignored in release builds.");
+        }
+    }
+
+    if let Some(palette) = config.palette {
+        tracing::info!("Using the {} color palette.", palette.as_str());
+        colors::set_active_palette(palette);
+    }
 
     //let db_pool = match database::establish_connection_pool().await
-    let db_pool = match database::establish_connection_pool(MAIN_DB_URL).await {
+    let db_pool = match database::establish_connection_pool(&config.database_url, &config.db).await
+    {
         Ok(pool) => {
             tracing::info!("Database connection was made successfully.");
             pool
@@ -39,54 +86,164 @@ async fn main() {
         }
     };
 
-    let rollover_pool = db_pool.clone(); // Clone the pool for the rollover task
-    let last_rollover_date = Arc::new(Mutex::new(Utc::now().date_naive())); // Store last date rollover happened
-
-    tokio::spawn(async move {
-        // Set an interval for checking.
-        // For testing, you might use `Duration::from_secs(60)` for every minute.
-        let mut interval = time::interval(Duration::from_secs(5 * 60)); // Check every 5 minutes
-
-        // The first tick completes immediately. Skip it to wait for the first interval.
-        interval.tick().await;
+    if let Some(fix) = doctor_fix {
+        match doctor::check(&db_pool, fix).await {
+            Ok(report) => {
+                print!("{report}");
+                std::process::exit(if report.is_healthy() { 0 } else { 1 });
+            }
+            Err(e) => {
+                tracing::error!("Failed to check the database: {:?}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    match doctor::check(&db_pool, false).await {
+        Ok(report) if !report.is_healthy() => {
+            tracing::warn!(
+                "Inconsistent data found, run `server doctor` for details and `server doctor --fix` to repair it:\n{}",
+                report
+            );
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Failed to check the database for inconsistencies: {:?}", e),
+    }
 
-        loop {
-            interval.tick().await; // Wait for the next interval tick
+    if let Some(backup_dir) = config.backup.dir.clone()
+        && jobs::is_enabled(&config, "backup")
+    {
+        tracing::info!(
+            "Backing up the database to {} every {:?}, keeping {} backups.",
+            backup_dir.display(),
+            config.backup.interval,
+            config.backup.keep
+        );
+        tokio::spawn(jobs::run_job(
+            db_pool.clone(),
+            BackupJob {
+                dir: backup_dir,
+                interval: config.backup.interval,
+                keep: config.backup.keep,
+            },
+            config.jobs.clone(),
+            config.instance_name.clone(),
+        ));
+    }
 
-            let current_date = Utc::now().date_naive();
-            let mut last_date_guard = last_rollover_date.lock().await;
+    let notifier = Notifier::from_config(&config.notifications).unwrap_or_else(|e| {
+        tracing::error!("Notifications will not be sent: {:?}", e);
+        Notifier::new(Vec::new(), config.notifications.templates.clone())
+    });
+    if jobs::is_enabled(&config, "digest") && notifier.is_enabled() && !config.e2e_mode {
+        tracing::info!(
+            "Posting the digest of the day on schedule '{}'.",
+            config.notifications.digest_schedule
+        );
+        tokio::spawn(jobs::run_job(
+            db_pool.clone(),
+            DigestJob {
+                notifier: notifier.clone(),
+                schedule: config.notifications.digest_schedule.clone(),
+            },
+            config.jobs.clone(),
+            config.instance_name.clone(),
+        ));
+    }
 
-            if *last_date_guard < current_date {
-                // If the current date is greater than the last date we rolled over for,
-                // it means a new day has started.
+    if let Some(email) = &config.email
+        && jobs::is_enabled(&config, "email-digest")
+        && !config.e2e_mode
+    {
+        match Mailer::from_config(email) {
+            Ok(mailer) => {
                 tracing::info!(
-                    "New day detected: {}, performing task rollover.",
-                    current_date
+                    "Emailing the digest of the {} on schedule '{}'.",
+                    email.digest_period.as_str(),
+                    email.digest_schedule
                 );
-                match database::rollover_tasks_in_db(&rollover_pool).await {
-                    Ok(count) => {
-                        tracing::info!(
-                            "Successfully rolled over {} tasks for {}.",
-                            count,
-                            current_date
-                        );
-                        *last_date_guard = current_date; // Update the last processed date
-                    }
-                    Err(e) => {
-                        tracing::error!("Error during automatic task rollover: {:?}", e);
-                    }
-                }
-            } else {
-                tracing::debug!(
-                    "No new day yet. Current date: {}. Last rollover date: {}.",
-                    current_date,
-                    *last_date_guard
+                tokio::spawn(jobs::run_job(
+                    db_pool.clone(),
+                    EmailDigestJob {
+                        mailer,
+                        schedule: email.digest_schedule.clone(),
+                        period: email.digest_period,
+                        locale: email.digest_locale,
+                    },
+                    config.jobs.clone(),
+                    config.instance_name.clone(),
+                ));
+            }
+            Err(e) => tracing::error!("The email digest will not be sent: {:?}", e),
+        }
+    }
+
+    if jobs::is_enabled(&config, "rollover") {
+        // Scheduled runs only roll one day forward: catch up first on the
+        // days missed while the server was down, unless rolling over was
+        // left to the user.
+        let paused = database::get_job_paused_at_from_db(&db_pool, "rollover")
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("Failed to check whether the rollover is paused: {:?}", e);
+                None
+            });
+        let catch_up = match paused {
+            Some(paused_at) => {
+                tracing::info!("Rollover paused since {}, no catch-up.", paused_at);
+                Ok(Vec::new())
+            }
+            None => {
+                database::catch_up_rollover_in_db(
+                    &db_pool,
+                    config.rollover_policy,
+                    config.stale_after_rollovers,
+                )
+                .await
+            }
+        };
+        match catch_up {
+            Ok(steps) if !steps.is_empty() => {
+                let count: usize = steps.iter().map(|(_, _, count)| count).sum();
+                tracing::info!(
+                    "Caught up on {} missed rollover days, {} task moves.",
+                    steps.len(),
+                    count
                 );
             }
+            Ok(_) => {}
+            Err(e) => tracing::error!("Error during catch-up task rollover: {:?}", e),
         }
-    });
 
-    let app_routes = routes::create_router(db_pool);
+        // The frozen clock of end-to-end test mode never reaches the next run.
+        if !config.e2e_mode {
+            tracing::info!(
+                "Rolling tasks over on schedule '{}' (jitter up to {:?}).",
+                config.rollover_schedule.cron,
+                config.rollover_schedule.jitter
+            );
+            tokio::spawn(jobs::run_job(
+                db_pool.clone(),
+                RolloverJob {
+                    schedule: config.rollover_schedule.clone(),
+                    policy: config.rollover_policy,
+                    stale_after: config.stale_after_rollovers,
+                    notifier: notifier.clone(),
+                    notify_above: config.notifications.rollover_threshold,
+                },
+                config.jobs.clone(),
+                config.instance_name.clone(),
+            ));
+        }
+    }
+
+    // Queued deliveries are picked up by whichever instance claims them first.
+    tokio::spawn(webhooks::run_delivery_worker(
+        db_pool.clone(),
+        config.webhooks.clone(),
+    ));
+
+    let http_config = config.http.clone();
+    let app_routes = routes::create_router_with_config(db_pool, config);
 
     // Configure CORS here, applying it globally to the router
     /*
@@ -95,25 +252,36 @@ async fn main() {
         .allow_headers(Any) // Allow all headers
         .allow_origin(Any); // Allow all origins
     */
-    let cors = CorsLayer::new()
-        .allow_methods(Any) // Autorise toutes les méthodes HTTP
-        // Liste explicite des en-têtes que votre frontend pourrait envoyer.
-        // Si vous n'utilisez pas d'authentification par token, 'authorization' n'est pas nécessaire.
-        .allow_headers([
-            HeaderName::from_static("content-type"),
-            HeaderName::from_static("accept"),
-            // Si vous prévoyez d'envoyer des tokens d'authentification:
-            // HeaderName::from_static("authorization"),
-        ])
-        .allow_origin(Any); // Autorise toutes les origines
-                            // Assurez-vous que .allow_credentials(true) est bien COMMENTÉ ou SUPPRIMÉ
-                            // si vous utilisez .allow_origin(Any) ou .allow_headers(Any)
+    let cors = routes::cors_layer(&http_config.cors);
 
     let app = app_routes.layer(cors); // Apply the CORS layer
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
-    tracing::info!("The server listens on http://{}", addr);
+    let tls = match &http_config.tls {
+        Some(tls_config) => match tls::acceptor(tls_config, http_config.http2_enabled) {
+            Ok((acceptor, resolver)) => {
+                tokio::spawn(tls::watch_certificate(tls_config.clone(), resolver));
+                Some(acceptor)
+            }
+            Err(e) => {
+                tracing::error!("Failed to set up HTTPS: {:?}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let addr = http_config.listen;
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    tracing::info!("The server listens on {}://{}", scheme, addr);
 
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!("Failed to listen on {}: {}", addr, e);
+            std::process::exit(1);
+        }
+    };
+    serve::serve(listener, app, &http_config, tls)
+        .await
+        .unwrap();
 }
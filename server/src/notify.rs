@@ -0,0 +1,480 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::NotificationsConfig;
+use crate::jobs::Job;
+use crate::webhooks::{self, WebhookClient};
+use crate::{clock, database, goal, scheduler};
+use anyhow::{anyhow, Context, Result};
+use axum::{
+    body::Bytes,
+    http::{header, Request},
+};
+use chrono::{DateTime, NaiveDate, Utc, Weekday};
+use common::{GoalProgress, GoalUnit, Task};
+use http_body_util::Full;
+use sqlx::SqlitePool;
+use std::fmt;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Time a channel has to accept a message.
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Text posted by `POST /api/notifications/test`.
+const TEST_MESSAGE: &str = "Test notification from the weekly task manager.";
+
+/// Default text of the morning digest.
+pub const DEFAULT_DIGEST_TEMPLATE: &str = "*Tasks for {date}* ({count})\n{tasks}{goal}";
+/// Default line of each task in the morning digest.
+pub const DEFAULT_DIGEST_TASK_TEMPLATE: &str = "• {client}: {description}";
+/// Default text of the notification of a large rollover.
+pub const DEFAULT_ROLLOVER_TEMPLATE: &str = "The rollover moved {count} open tasks on from {date}.";
+
+/// Text of a message with `{name}` placeholders, filled in when it is sent.
+/// Placeholders are checked when the template is parsed, so that a typo is
+/// noticed at startup rather than posted.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Template(String);
+
+impl Template {
+    /// Parses `text`, whose placeholders must all be in `names`.
+    pub fn parse(text: &str, names: &[&str]) -> Result<Self, String> {
+        let mut rest = text;
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}') else {
+                return Err("unclosed '{'".to_string());
+            };
+            let name = &rest[start + 1..start + end];
+            if !names.contains(&name) {
+                return Err(format!(
+                    "unknown placeholder '{{{name}}}', expected one of {}",
+                    names
+                        .iter()
+                        .map(|name| format!("{{{name}}}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+            rest = &rest[start + end + 1..];
+        }
+        Ok(Template(text.to_string()))
+    }
+
+    /// The text with each placeholder replaced by its value, in one pass so
+    /// that values are never read as templates themselves. Values are
+    /// escaped, so that a task described as `<!channel>` does not ping the
+    /// channel nor a `<url|text>` post a disguised link.
+    pub fn render(&self, values: &[(&str, &str)]) -> String {
+        self.render_nested(values, &[])
+    }
+
+    /// Like `render`, with the `rendered` values inserted as they are: text
+    /// already rendered from other templates, such as the lines of the
+    /// digest.
+    pub fn render_nested(&self, values: &[(&str, &str)], rendered: &[(&str, &str)]) -> String {
+        let mut text = String::with_capacity(self.0.len());
+        let mut rest = self.0.as_str();
+        while let Some(start) = rest.find('{') {
+            text.push_str(&rest[..start]);
+            let end = start + rest[start..].find('}').expect("checked when parsed");
+            let name = &rest[start + 1..end];
+            if let Some((_, value)) = values.iter().find(|(n, _)| *n == name) {
+                text.push_str(&escape_mrkdwn(value));
+            } else if let Some((_, value)) = rendered.iter().find(|(n, _)| *n == name) {
+                text.push_str(value);
+            }
+            rest = &rest[end + 1..];
+        }
+        text.push_str(rest);
+        text
+    }
+}
+
+/// Escapes the characters Slack reads as mentions, links and entities in
+/// mrkdwn.
+fn escape_mrkdwn(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl fmt::Debug for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+/// Texts of the notifications, the same for every channel.
+#[derive(Debug, Clone)]
+pub struct Templates {
+    /// Morning digest (`NOTIFY_DIGEST_TEMPLATE`): `{date}`, `{count}`,
+    /// `{tasks}`, the lines of the tasks, and `{goal}`, the progress towards
+    /// the weekly goal on a line of its own (empty without a goal).
+    pub digest: Template,
+    /// Line of each task of the digest (`NOTIFY_DIGEST_TASK_TEMPLATE`):
+    /// `{client}`, `{description}` and `{priority}`.
+    pub digest_task: Template,
+    /// Large rollover (`NOTIFY_ROLLOVER_TEMPLATE`): `{count}` and `{date}`,
+    /// the day the tasks were moved from.
+    pub rollover: Template,
+}
+
+impl Templates {
+    pub const DIGEST_NAMES: &[&str] = &["date", "count", "tasks", "goal"];
+    pub const DIGEST_TASK_NAMES: &[&str] = &["client", "description", "priority"];
+    pub const ROLLOVER_NAMES: &[&str] = &["count", "date"];
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        let parse =
+            |text, names| Template::parse(text, names).expect("default templates are valid");
+        Templates {
+            digest: parse(DEFAULT_DIGEST_TEMPLATE, Templates::DIGEST_NAMES),
+            digest_task: parse(DEFAULT_DIGEST_TASK_TEMPLATE, Templates::DIGEST_TASK_NAMES),
+            rollover: parse(DEFAULT_ROLLOVER_TEMPLATE, Templates::ROLLOVER_NAMES),
+        }
+    }
+}
+
+/// Something worth telling the team about.
+#[derive(Debug)]
+pub enum Notification<'a> {
+    /// The open tasks of `day`, sent each morning, with the progress of its
+    /// week towards the weekly goal when one is set.
+    Digest {
+        day: NaiveDate,
+        tasks: &'a [Task],
+        goal: Option<&'a GoalProgress>,
+    },
+    /// The rollover moved `count` tasks on from `day`.
+    Rollover { day: NaiveDate, count: usize },
+}
+
+/// Where notifications are posted.
+#[derive(Clone)]
+pub enum Channel {
+    /// A Slack incoming webhook, which takes `{"text": ...}` in mrkdwn.
+    Slack { url: String, client: WebhookClient },
+}
+
+impl Channel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::Slack { .. } => "slack",
+        }
+    }
+
+    /// Posts `text`, failing unless the channel accepts it.
+    async fn send(&self, text: &str) -> Result<()> {
+        match self {
+            Channel::Slack { url, client } => {
+                let body = serde_json::json!({ "text": text }).to_string();
+                let request = Request::post(url)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Full::new(Bytes::from(body)))
+                    .context("Invalid Slack request")?;
+                let response = tokio::time::timeout(SEND_TIMEOUT, client.request(request))
+                    .await
+                    .map_err(|_| anyhow!("No response within {} seconds", SEND_TIMEOUT.as_secs()))?
+                    .context("Request failed")?;
+                if !response.status().is_success() {
+                    return Err(anyhow!("Slack answered {}", response.status()));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Renders notifications with the configured templates and posts them to
+/// every configured channel.
+#[derive(Clone)]
+pub struct Notifier {
+    channels: Vec<Channel>,
+    templates: Templates,
+}
+
+impl Notifier {
+    pub fn new(channels: Vec<Channel>, templates: Templates) -> Self {
+        Notifier {
+            channels,
+            templates,
+        }
+    }
+
+    /// The channels of `config`, none when nothing is configured.
+    pub fn from_config(config: &NotificationsConfig) -> Result<Self> {
+        let mut channels = Vec::new();
+        if let Some(url) = &config.slack_webhook_url {
+            channels.push(Channel::Slack {
+                url: url.clone(),
+                client: webhooks::client()?,
+            });
+        }
+        Ok(Notifier::new(channels, config.templates.clone()))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.channels.is_empty()
+    }
+
+    /// Text of `notification`.
+    pub fn render(&self, notification: &Notification) -> String {
+        match notification {
+            Notification::Digest { day, tasks, goal } => {
+                let lines: Vec<String> = tasks
+                    .iter()
+                    .map(|task| {
+                        let priority = task
+                            .priority
+                            .map(|p| p.level().to_string())
+                            .unwrap_or_default();
+                        self.templates.digest_task.render(&[
+                            ("client", &task.client_name),
+                            ("description", &task.description),
+                            ("priority", &priority),
+                        ])
+                    })
+                    .collect();
+                let goal = goal.map(goal_line).unwrap_or_default();
+                self.templates.digest.render_nested(
+                    &[
+                        ("date", &day.to_string()),
+                        ("count", &tasks.len().to_string()),
+                        ("goal", &goal),
+                    ],
+                    &[("tasks", &lines.join("\n"))],
+                )
+            }
+            Notification::Rollover { day, count } => self
+                .templates
+                .rollover
+                .render(&[("count", &count.to_string()), ("date", &day.to_string())]),
+        }
+    }
+
+    /// Posts `notification` to every channel. Fails if any of them did not
+    /// accept it, after trying them all.
+    pub async fn notify(&self, notification: &Notification<'_>) -> Result<()> {
+        let text = self.render(notification);
+        let mut failed = Vec::new();
+        for channel in &self.channels {
+            if let Err(e) = channel.send(&text).await {
+                error!(
+                    channel = channel.name(),
+                    "Failed to send notification: {:?}", e
+                );
+                failed.push(channel.name());
+            }
+        }
+        if !failed.is_empty() {
+            return Err(anyhow!("Notification not sent to {}", failed.join(", ")));
+        }
+        Ok(())
+    }
+
+    /// Posts a test message to every channel, with the outcome of each.
+    pub async fn send_test(&self) -> Vec<(&'static str, Result<()>)> {
+        let mut outcomes = Vec::new();
+        for channel in &self.channels {
+            let outcome = channel.send(TEST_MESSAGE).await;
+            outcomes.push((channel.name(), outcome));
+        }
+        outcomes
+    }
+}
+
+/// Line of the digest on the progress towards the weekly goal, e.g.
+/// `Weekly goal: 4 of 14 tasks done, 2 behind pace`.
+fn goal_line(progress: &GoalProgress) -> String {
+    let unit = match progress.goal.unit {
+        GoalUnit::Tasks => "tasks",
+        GoalUnit::Hours => "hours",
+    };
+    let pace = if progress.remaining == 0.0 {
+        "goal met".to_string()
+    } else if progress.on_track {
+        "on track".to_string()
+    } else {
+        format!("{} behind pace", -progress.ahead_by)
+    };
+    format!(
+        "\nWeekly goal: {} of {} {unit} done, {pace}",
+        progress.done, progress.goal.target
+    )
+}
+
+/// The morning digest: the open tasks of the day, posted at each occurrence
+/// of the configured cron expression. Nothing is posted on empty days.
+pub struct DigestJob {
+    pub notifier: Notifier,
+    pub schedule: croner::Cron,
+}
+
+impl Job for DigestJob {
+    fn name(&self) -> &'static str {
+        "digest"
+    }
+
+    fn next_run(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        scheduler::next_run(&self.schedule, after, clock::timezone())
+    }
+
+    async fn run(&self, pool: &SqlitePool) -> Result<String> {
+        let day = clock::today();
+        let tasks = database::get_open_tasks_for_day_from_db(pool, day, None).await?;
+        if tasks.is_empty() {
+            return Ok(format!("No open tasks on {day}, no digest sent."));
+        }
+        let progress = match database::get_weekly_goal_from_db(pool).await? {
+            Some(weekly_goal) => {
+                let week = day.week(Weekday::Mon);
+                let (done_tasks, done_minutes) =
+                    database::get_completed_work_from_db(pool, week.first_day(), week.last_day())
+                        .await?;
+                let now = clock::now().with_timezone(&clock::timezone()).naive_local();
+                Some(goal::progress(
+                    weekly_goal,
+                    week.first_day(),
+                    now,
+                    done_tasks,
+                    done_minutes,
+                ))
+            }
+            None => None,
+        };
+        self.notifier
+            .notify(&Notification::Digest {
+                day,
+                tasks: &tasks,
+                goal: progress.as_ref(),
+            })
+            .await?;
+        info!("Digest of {} tasks sent.", tasks.len());
+        Ok(format!(
+            "Sent the digest of {} tasks of {}.",
+            tasks.len(),
+            day
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_placeholders_are_checked() {
+        let names = Templates::ROLLOVER_NAMES;
+        assert!(Template::parse("Moved {count} tasks", names).is_ok());
+        assert!(Template::parse("No placeholder", names).is_ok());
+        let error = Template::parse("Moved {total} tasks", names).unwrap_err();
+        assert_eq!(
+            error,
+            "unknown placeholder '{total}', expected one of {count}, {date}"
+        );
+        assert!(Template::parse("Moved {count tasks", names).is_err());
+
+        // Values are not templates
+        let template = Template::parse("{count} on {date}", names).unwrap();
+        assert_eq!(
+            template.render(&[("count", "{date}"), ("date", "Monday")]),
+            "{date} on Monday"
+        );
+    }
+
+    #[test]
+    fn test_digest_values_are_escaped() {
+        let notifier = Notifier::new(Vec::new(), Templates::default());
+        let day = NaiveDate::from_ymd_opt(2025, 7, 14).unwrap();
+        let task = Task {
+            id: 1,
+            client_id: 1,
+            client_name: "R&D <team>".to_string(),
+            description: "<!channel> see <https://evil.example|the plan>".to_string(),
+            task_date: day,
+            client_color: "#0077cc".parse().unwrap(),
+            client_text_color: "#ffffff".to_string(),
+            client_background_color: "#cce4f5".to_string(),
+            created_at: Utc::now(),
+            deleted_at: None,
+            updated_at: Utc::now(),
+            priority: None,
+            external_source: None,
+            external_id: None,
+            energy: None,
+            estimate_minutes: None,
+            context: None,
+            pin_to_date: false,
+            rollover_target: None,
+            rollover_count: 0,
+            stale_since: None,
+            slots_total: 0,
+            slots_done: 0,
+            deferred: false,
+            display: Default::default(),
+        };
+        let text = notifier.render(&Notification::Digest {
+            day,
+            tasks: &[task],
+            goal: None,
+        });
+        assert_eq!(
+            text,
+            "*Tasks for 2025-07-14* (1)\n\
+             • R&amp;D &lt;team&gt;: &lt;!channel&gt; see &lt;https://evil.example|the plan&gt;"
+        );
+    }
+
+    #[test]
+    fn test_goal_line() {
+        let monday = NaiveDate::from_ymd_opt(2025, 7, 14).unwrap();
+        let thursday = NaiveDate::from_ymd_opt(2025, 7, 17)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let goal = |unit, target| common::WeeklyGoal {
+            unit,
+            target,
+            set_at: Utc::now(),
+        };
+        let behind = goal::progress(goal(GoalUnit::Tasks, 14.0), monday, thursday, 4, 0);
+        assert_eq!(
+            goal_line(&behind),
+            "\nWeekly goal: 4 of 14 tasks done, 2 behind pace"
+        );
+        let ahead = goal::progress(goal(GoalUnit::Hours, 21.0), monday, thursday, 6, 630);
+        assert_eq!(
+            goal_line(&ahead),
+            "\nWeekly goal: 10.5 of 21 hours done, on track"
+        );
+        let met = goal::progress(goal(GoalUnit::Tasks, 3.0), monday, thursday, 3, 0);
+        assert_eq!(
+            goal_line(&met),
+            "\nWeekly goal: 3 of 3 tasks done, goal met"
+        );
+    }
+
+    #[test]
+    fn test_rollover_message() {
+        let notifier = Notifier::new(
+            Vec::new(),
+            Templates {
+                rollover: Template::parse(
+                    "{count} tasks left {date}, {count}!",
+                    Templates::ROLLOVER_NAMES,
+                )
+                .unwrap(),
+                ..Templates::default()
+            },
+        );
+        let day = NaiveDate::from_ymd_opt(2025, 7, 14).unwrap();
+        assert_eq!(
+            notifier.render(&Notification::Rollover { day, count: 12 }),
+            "12 tasks left 2025-07-14, 12!"
+        );
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::auth::SESSION_COOKIE;
+use crate::export::ExportProfile;
+use crate::handlers;
+use crate::table::TableFormat;
+use common::ItemError;
+use serde::Serialize;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi, ToSchema};
+
+/// Body of every error response.
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    /// What went wrong.
+    pub error: String,
+    /// Errors of the individual items of a bulk request, by index.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<Vec<ItemError>>,
+}
+
+/// Body of `PATCH /api/tasks/rollover`.
+#[derive(Serialize, ToSchema)]
+pub struct RolloverOutcome {
+    pub message: String,
+    pub tasks_rolled_over: u64,
+}
+
+/// Raw bytes of a file, such as a database backup.
+#[derive(ToSchema)]
+#[schema(value_type = String, format = Binary)]
+pub struct Binary(pub Vec<u8>);
+
+/// Declares the ways of proving who sends a request.
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("One of the `API_KEYS`."))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::with_description(
+                SESSION_COOKIE,
+                "Session of a browser signed in with `POST /api/session`. Requests \
+                 changing data also send its CSRF token in `X-CSRF-Token`.",
+            ))),
+        );
+        components.add_security_scheme(
+            "export_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some(
+                        "One of the `EXPORT_API_TOKENS`, which may also be passed as `token`.",
+                    ))
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Description of the API, served at `/api/openapi.json`. Every route of
+/// `routes.rs` is listed, except those only mounted for tests.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Weekly Task Manager API",
+        description = "Tasks of the week by client, rolled over from one day to the next until done."
+    ),
+    // Only referred to by query parameters, whose schemas are not collected
+    components(schemas(ExportProfile, TableFormat)),
+    modifiers(&SecuritySchemes),
+    security(("api_key" = []), ("session" = [])),
+    tags(
+        (name = "tasks", description = "Tasks of the current week and the planning horizon"),
+        (name = "rollover", description = "Open tasks moved on to the following days"),
+        (name = "planning", description = "Weekly planning, focus days and holidays"),
+        (name = "clients", description = "Clients, their colors and aliases"),
+        (name = "settings", description = "Palette, timezone and weekly goal"),
+        (name = "weeks", description = "Archived and shared weeks"),
+        (name = "import", description = "Imports of tasks and full exports"),
+        (name = "export", description = "Exports, feeds and the sync of the mobile client"),
+        (name = "admin", description = "Background jobs, backups, webhooks and notifications"),
+        (name = "session", description = "Browser sessions"),
+    ),
+    paths(
+        handlers::list_tasks,
+        handlers::create_task,
+        handlers::create_tasks_bulk,
+        handlers::capture_task,
+        handlers::bulk_move_tasks,
+        handlers::apply_batch,
+        handlers::today_tasks,
+        handlers::search_tasks,
+        handlers::suggest_tasks,
+        handlers::week_board,
+        handlers::live_updates,
+        handlers::delete_task,
+        handlers::move_task,
+        handlers::pin_task,
+        handlers::unpin_task,
+        handlers::set_task_rollover_target,
+        handlers::clear_task_rollover_target,
+        handlers::list_task_slots,
+        handlers::add_task_slot,
+        handlers::delete_task_slot,
+        handlers::complete_task_slot,
+        handlers::stale_tasks,
+        handlers::rollover_tasks,
+        handlers::preview_rollover,
+        handlers::rollover_history,
+        handlers::list_jobs,
+        handlers::pause_job,
+        handlers::resume_job,
+        handlers::list_webhooks,
+        handlers::create_webhook,
+        handlers::delete_webhook,
+        handlers::list_webhook_deliveries,
+        handlers::list_dead_letters,
+        handlers::requeue_dead_letters,
+        handlers::test_notifications,
+        handlers::get_holidays,
+        handlers::add_holiday,
+        handlers::delete_holiday,
+        handlers::get_next_week_plan,
+        handlers::apply_next_week_plan,
+        handlers::set_focus_day,
+        handlers::clear_focus_day,
+        handlers::normalize_priorities,
+        handlers::list_clients,
+        handlers::create_client,
+        handlers::get_client,
+        handlers::update_client,
+        handlers::delete_client,
+        handlers::set_client_color,
+        handlers::set_client_defaults,
+        handlers::list_color_changes,
+        handlers::archive_client,
+        handlers::unarchive_client,
+        handlers::resolve_client_name,
+        handlers::list_client_aliases,
+        handlers::add_client_alias,
+        handlers::delete_client_alias,
+        handlers::client_stats,
+        handlers::get_palette,
+        handlers::set_palette,
+        handlers::get_timezone,
+        handlers::get_weekly_goal,
+        handlers::set_weekly_goal,
+        handlers::delete_weekly_goal,
+        handlers::goal_progress,
+        handlers::create_share,
+        handlers::get_shared_week,
+        handlers::get_week_archive,
+        handlers::archive_week,
+        handlers::stage_import,
+        handlers::preview_import,
+        handlers::commit_import,
+        handlers::import_full,
+        handlers::archive_chunk,
+        handlers::export_full,
+        handlers::export_table,
+        handlers::export_calendar,
+        handlers::export_tasks_csv,
+        handlers::sync,
+        handlers::download_backup,
+        handlers::restore_backup,
+        handlers::create_session,
+        handlers::delete_session,
+        handlers::get_session_csrf,
+    )
+)]
+pub struct ApiDoc;
@@ -0,0 +1,234 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use common::{DayCapacity, RecurringCandidate, Task};
+use std::collections::{BTreeSet, HashMap};
+
+/// Number of past weeks looked at to find recurring tasks.
+pub const RECURRING_LOOKBACK_WEEKS: u64 = 4;
+
+/// Minimum number of those weeks a task must appear in to be suggested again.
+pub const RECURRING_MIN_WEEKS: usize = 3;
+
+/// Returns the first (Monday) and last (Sunday) day of the week after `today`.
+pub fn next_week_range(today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let week_start = today.week(Weekday::Mon).first_day() + Days::new(7);
+    (week_start, week_start + Days::new(6))
+}
+
+/// Returns the first (Monday) and last (Sunday) day of an ISO week written
+/// as `2025-W29`, or `None` if it is not one.
+pub fn parse_iso_week(week: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let (year, number) = week.split_once("-W")?;
+    let week_start =
+        NaiveDate::from_isoywd_opt(year.parse().ok()?, number.parse().ok()?, Weekday::Mon)?;
+    Some((week_start, week_start + Days::new(6)))
+}
+
+/// First day of the recurring lookback period ending right before `week_start`.
+pub fn lookback_start(week_start: NaiveDate) -> NaiveDate {
+    week_start - Days::new(7 * RECURRING_LOOKBACK_WEEKS)
+}
+
+// Tasks are considered the same when they have the same client and
+// description, ignoring case and surrounding spaces.
+fn recurrence_key(task: &Task) -> (String, String) {
+    (
+        task.client_name.clone(),
+        task.description.trim().to_lowercase(),
+    )
+}
+
+/// Finds the tasks of `history` that appeared in at least
+/// `RECURRING_MIN_WEEKS` different weeks before `week_start`.
+///
+/// `history` holds every task (completed ones included) from the lookback
+/// period up to the end of next week. A task is not suggested when it is
+/// already planned next week or still open, since it would be carried over.
+pub fn recurring_candidates(history: &[Task], week_start: NaiveDate) -> Vec<RecurringCandidate> {
+    let mut weeks: HashMap<(String, String), BTreeSet<NaiveDate>> = HashMap::new();
+    let mut latest: HashMap<(String, String), &Task> = HashMap::new();
+    let mut excluded = BTreeSet::new();
+
+    for task in history {
+        let key = recurrence_key(task);
+        if task.task_date >= week_start || task.deleted_at.is_none() {
+            excluded.insert(key);
+            continue;
+        }
+        weeks
+            .entry(key.clone())
+            .or_default()
+            .insert(task.task_date.week(Weekday::Mon).first_day());
+        let entry = latest.entry(key).or_insert(task);
+        if (task.task_date, task.id) > (entry.task_date, entry.id) {
+            *entry = task;
+        }
+    }
+
+    let mut candidates: Vec<RecurringCandidate> = weeks
+        .into_iter()
+        .filter(|(key, weeks)| weeks.len() >= RECURRING_MIN_WEEKS && !excluded.contains(key))
+        .map(|(key, weeks)| {
+            let task = latest[&key];
+            let weekday = task.task_date.weekday().num_days_from_monday();
+            RecurringCandidate {
+                client_name: task.client_name.clone(),
+                description: task.description.trim().to_string(),
+                priority: task.priority,
+                estimate_minutes: task.estimate_minutes,
+                weeks_seen: weeks.len(),
+                suggested_date: week_start + Days::new(u64::from(weekday)),
+            }
+        })
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.weeks_seen
+            .cmp(&a.weeks_seen)
+            .then_with(|| a.suggested_date.cmp(&b.suggested_date))
+            .then_with(|| a.client_name.cmp(&b.client_name))
+            .then_with(|| a.description.cmp(&b.description))
+    });
+    candidates
+}
+
+/// Builds the capacity of each day of the week starting at `week_start`.
+/// `planned` maps days to their number of planned tasks and estimated
+/// minutes. Weekdays get `daily_capacity_minutes`, weekends none.
+pub fn week_capacity(
+    week_start: NaiveDate,
+    planned: &HashMap<NaiveDate, (i64, i64)>,
+    daily_capacity_minutes: i64,
+) -> Vec<DayCapacity> {
+    week_start
+        .iter_days()
+        .take(7)
+        .map(|day| {
+            let (planned_tasks, planned_minutes) = planned.get(&day).copied().unwrap_or((0, 0));
+            let weekend = matches!(day.weekday(), Weekday::Sat | Weekday::Sun);
+            DayCapacity {
+                day,
+                capacity_minutes: if weekend { 0 } else { daily_capacity_minutes },
+                planned_tasks,
+                planned_minutes,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use common::Priority;
+
+    fn task(id: i64, description: &str, task_date: NaiveDate, done: bool) -> Task {
+        Task {
+            id,
+            client_id: 1,
+            client_name: "ACME".to_string(),
+            description: description.to_string(),
+            task_date,
+            client_color: "#1f77b4".parse().unwrap(),
+            client_text_color: "#ffffff".to_string(),
+            client_background_color: "#d2e4f0".to_string(),
+            created_at: Utc::now(),
+            deleted_at: done.then(Utc::now),
+            updated_at: Utc::now(),
+            priority: Some(Priority::High),
+            external_source: None,
+            external_id: None,
+            energy: None,
+            estimate_minutes: Some(30),
+            context: None,
+            pin_to_date: false,
+            rollover_target: None,
+            rollover_count: 0,
+            stale_since: None,
+            slots_total: 0,
+            slots_done: 0,
+            deferred: false,
+            display: Default::default(),
+        }
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_iso_week() {
+        assert_eq!(
+            parse_iso_week("2025-W29"),
+            Some((date("2025-07-14"), date("2025-07-20")))
+        );
+        // 2026 starts on a Thursday, so its first week starts in 2025
+        assert_eq!(
+            parse_iso_week("2026-W01"),
+            Some((date("2025-12-29"), date("2026-01-04")))
+        );
+        assert_eq!(parse_iso_week("2025-W54"), None);
+        assert_eq!(parse_iso_week("2025-07-14"), None);
+    }
+
+    #[test]
+    fn test_next_week_range() {
+        // 2025-07-16 is a Wednesday
+        assert_eq!(
+            next_week_range(date("2025-07-16")),
+            (date("2025-07-21"), date("2025-07-27"))
+        );
+        assert_eq!(
+            next_week_range(date("2025-07-20")),
+            (date("2025-07-21"), date("2025-07-27"))
+        );
+    }
+
+    #[test]
+    fn test_recurring_candidates() {
+        let week_start = date("2025-07-21");
+        let history = vec![
+            // Done on three of the last four Fridays
+            task(1, "Weekly report", date("2025-06-27"), true),
+            task(2, "Weekly report", date("2025-07-04"), true),
+            task(3, "weekly report ", date("2025-07-18"), true),
+            // Only twice
+            task(4, "Invoice", date("2025-07-01"), true),
+            task(5, "Invoice", date("2025-07-15"), true),
+            // Recurring, but already planned next week
+            task(6, "Standup notes", date("2025-06-30"), true),
+            task(7, "Standup notes", date("2025-07-07"), true),
+            task(8, "Standup notes", date("2025-07-14"), true),
+            task(9, "Standup notes", date("2025-07-21"), false),
+            // Recurring, but still open and carried over
+            task(10, "Backup", date("2025-07-02"), true),
+            task(11, "Backup", date("2025-07-09"), true),
+            task(12, "Backup", date("2025-07-16"), false),
+            task(13, "Backup", date("2025-06-25"), true),
+        ];
+
+        let candidates = recurring_candidates(&history, week_start);
+
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].description, "weekly report");
+        assert_eq!(candidates[0].weeks_seen, 3);
+        assert_eq!(candidates[0].suggested_date, date("2025-07-25"));
+    }
+
+    #[test]
+    fn test_week_capacity() {
+        let week_start = date("2025-07-21");
+        let planned = HashMap::from([(week_start, (2, 90))]);
+
+        let capacity = week_capacity(week_start, &planned, 360);
+
+        assert_eq!(capacity.len(), 7);
+        assert_eq!(capacity[0].planned_tasks, 2);
+        assert_eq!(capacity[0].planned_minutes, 90);
+        assert_eq!(capacity[1].planned_tasks, 0);
+        assert_eq!(capacity[4].capacity_minutes, 360);
+        assert_eq!(capacity[5].capacity_minutes, 0);
+    }
+}
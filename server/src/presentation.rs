@@ -0,0 +1,103 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::clock;
+use crate::colors::{self, ColorMetadata};
+use chrono::NaiveDate;
+use common::{DisplayHints, Priority, Task, TaskSummary};
+
+/// Opacity of done tasks, which stay visible but step back.
+const DONE_OPACITY: f64 = 0.5;
+
+/// Share of white mixed into the client color, by priority. Tasks without
+/// one are shaded like the lowest.
+fn priority_shade(priority: Option<Priority>) -> f64 {
+    match priority {
+        Some(Priority::Critical) => 0.0,
+        Some(Priority::High | Priority::Medium) => 0.25,
+        Some(Priority::Low) | None => 0.5,
+    }
+}
+
+/// Computes the display hints of a task.
+pub fn display_hints(
+    client_color: &str,
+    priority: Option<Priority>,
+    task_date: NaiveDate,
+    done: bool,
+    today: NaiveDate,
+) -> DisplayHints {
+    DisplayHints {
+        color: colors::mix_with_white(client_color, priority_shade(priority)),
+        opacity: if done { DONE_OPACITY } else { 1.0 },
+        overdue: !done && task_date < today,
+    }
+}
+
+/// Types returned to the frontends, whose computed display fields (derived
+/// colors and hints) are filled in before they are sent.
+pub trait Presentable: ColorMetadata {
+    fn fill_display_hints(&mut self, today: NaiveDate);
+}
+
+/// Fills the computed display fields of a value and returns it, e.g. in
+/// `Option::map`.
+pub fn for_display<T: Presentable>(mut value: T) -> T {
+    value.fill_color_metadata();
+    value.fill_display_hints(clock::today());
+    value
+}
+
+impl Presentable for Task {
+    fn fill_display_hints(&mut self, today: NaiveDate) {
+        self.display = display_hints(
+            &self.client_color,
+            self.priority,
+            self.task_date,
+            self.deleted_at.is_some(),
+            today,
+        );
+    }
+}
+
+impl Presentable for TaskSummary {
+    fn fill_display_hints(&mut self, today: NaiveDate) {
+        // Summaries are only built for open tasks.
+        self.display = display_hints(
+            &self.client_color,
+            self.priority,
+            self.task_date,
+            false,
+            today,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_hints() {
+        let today = NaiveDate::from_ymd_opt(2025, 7, 16).unwrap();
+        let yesterday = today.pred_opt().unwrap();
+
+        let urgent = display_hints("#1f77b4", Some(Priority::Critical), today, false, today);
+        assert_eq!(urgent.color, "#1f77b4");
+        assert_eq!(urgent.opacity, 1.0);
+        assert!(!urgent.overdue);
+
+        // Lower priorities are lighter, tasks without one the lightest
+        let medium = display_hints("#1f77b4", Some(Priority::Medium), today, false, today);
+        let none = display_hints("#1f77b4", None, today, false, today);
+        assert_eq!(medium.color, "#5799c7");
+        assert_eq!(none.color, "#8fbbda");
+
+        let late = display_hints("#1f77b4", Some(Priority::Critical), yesterday, false, today);
+        assert!(late.overdue);
+        let done = display_hints("#1f77b4", Some(Priority::Critical), yesterday, true, today);
+        assert!(!done.overdue);
+        assert_eq!(done.opacity, DONE_OPACITY);
+    }
+}
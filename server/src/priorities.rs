@@ -0,0 +1,47 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use common::Priority;
+
+/// Rewrites `priorities`, sorted from the most urgent down, as a clean
+/// sequence starting at critical that keeps their order: `1, 1, 3, 4`
+/// becomes `1, 2, 3, 4`, or `1, 1, 2, 3` when `keep_ties` is set. Past the
+/// fourth, the tasks all stay low.
+pub fn normalized(priorities: &[Priority], keep_ties: bool) -> Vec<Priority> {
+    let mut next = 0;
+    let mut previous = None;
+    priorities
+        .iter()
+        .map(|&priority| {
+            if !(keep_ties && previous == Some(priority)) {
+                next += 1;
+            }
+            previous = Some(priority);
+            Priority::clamped(next)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalized_keeps_order() {
+        use Priority::*;
+        assert_eq!(
+            normalized(&[Critical, Critical, Medium, Low], false),
+            vec![Critical, High, Medium, Low]
+        );
+        assert_eq!(
+            normalized(&[Critical, Critical, Medium, Low], true),
+            vec![Critical, Critical, High, Medium]
+        );
+        assert_eq!(
+            normalized(&[High, High, Low, Low, Low], false),
+            vec![Critical, High, Medium, Low, Low]
+        );
+        assert!(normalized(&[], false).is_empty());
+    }
+}
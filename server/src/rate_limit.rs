@@ -0,0 +1,272 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::auth;
+use crate::client_ip::ClientIp;
+use crate::config::{Config, RateLimitConfig, RateLimitKey};
+use crate::handlers::AppError;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Clients tracked at most; past that, the one that sent no request for
+/// the longest time is forgotten to make room.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Who a request is counted against.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ClientKey {
+    /// Unknown when the server was not given the connection info, as in
+    /// tests.
+    Ip(Option<IpAddr>),
+    ApiKey(String),
+}
+
+/// The address a client is counted by. IPv6 clients usually get a whole
+/// `/64` network, so they are counted by it rather than by each address.
+fn counted_address(ip: IpAddr) -> IpAddr {
+    match ip.to_canonical() {
+        IpAddr::V6(ip) => IpAddr::V6(Ipv6Addr::from_bits(ip.to_bits() & !u128::from(u64::MAX))),
+        ip => ip,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// Position of the last request of the client in `Buckets::by_age`.
+    seq: u64,
+}
+
+/// The buckets of the clients, and the order they were last used in.
+#[derive(Debug, Default)]
+struct Buckets {
+    by_client: HashMap<ClientKey, Bucket>,
+    by_age: BTreeMap<u64, ClientKey>,
+    next_seq: u64,
+}
+
+/// Token buckets of the clients of the API: each request takes a token,
+/// and tokens come back at `RATE_LIMIT_PER_MINUTE`, up to
+/// `RATE_LIMIT_BURST` of them.
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Tokens per second, and size of the buckets; no limit when unset.
+    rate: Option<(f64, f64)>,
+    key: RateLimitKey,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        RateLimiter {
+            rate: config.per_minute.map(|per_minute| {
+                let burst = config.burst.unwrap_or(per_minute);
+                (per_minute as f64 / 60.0, burst as f64)
+            }),
+            key: config.key,
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Takes a token from the bucket of `client`, or tells how long until
+    /// one comes back.
+    fn acquire(&self, client: ClientKey, now: Instant) -> Result<(), Duration> {
+        let Some((per_second, burst)) = self.rate else {
+            return Ok(());
+        };
+        let refill = |bucket: &Bucket| {
+            let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+            (bucket.tokens + elapsed * per_second).min(burst)
+        };
+
+        let mut buckets = self.buckets.lock();
+        let Buckets {
+            by_client,
+            by_age,
+            next_seq,
+        } = &mut *buckets;
+        if by_client.len() >= MAX_TRACKED_CLIENTS
+            && !by_client.contains_key(&client)
+            && let Some((_, oldest)) = by_age.pop_first()
+        {
+            by_client.remove(&oldest);
+        }
+        let seq = *next_seq;
+        *next_seq += 1;
+        let bucket = by_client.entry(client.clone()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+            seq,
+        });
+        by_age.remove(&bucket.seq);
+        by_age.insert(seq, client);
+        bucket.seq = seq;
+        bucket.tokens = refill(bucket);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+
+    /// Number of clients currently tracked.
+    pub fn tracked(&self) -> usize {
+        self.buckets.lock().by_client.len()
+    }
+}
+
+/// Middleware answering `429 Too Many Requests`, with a `Retry-After`
+/// header, to clients out of tokens.
+pub async fn limit_rate(
+    State(limiter): State<Arc<RateLimiter>>,
+    State(config): State<Arc<Config>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if limiter.rate.is_none() {
+        return next.run(request).await;
+    }
+    let ip = request
+        .extensions()
+        .get::<ClientIp>()
+        .map(|ClientIp(ip)| *ip);
+    let client = match auth::bearer(request.headers()) {
+        // Only the configured keys get their own bucket, so that made-up
+        // ones cannot grow the table or escape the limit of their address.
+        Some(key) if limiter.key == RateLimitKey::ApiKey && config.api_keys.accepts(key) => {
+            ClientKey::ApiKey(key.to_string())
+        }
+        _ => ClientKey::Ip(ip.map(counted_address)),
+    };
+
+    if let Err(wait) = limiter.acquire(client, Instant::now()) {
+        // Rounded up, so that retrying then succeeds.
+        let retry_after = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+        warn!(
+            "Rate limit reached by {}, rejecting {} {}",
+            ip.map_or("an unknown client".to_string(), |ip| ip.to_string()),
+            request.method(),
+            request.uri().path()
+        );
+        return (
+            [(header::RETRY_AFTER, retry_after.to_string())],
+            AppError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                &format!("Too many requests, retry in {retry_after} seconds."),
+            ),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(per_minute: u32, burst: Option<u32>) -> RateLimiter {
+        RateLimiter::new(&RateLimitConfig {
+            per_minute: Some(per_minute),
+            burst,
+            key: RateLimitKey::Ip,
+        })
+    }
+
+    fn client(ip: &str) -> ClientKey {
+        ClientKey::Ip(Some(ip.parse().unwrap()))
+    }
+
+    #[test]
+    fn test_buckets_empty_and_refill() {
+        let limiter = limiter(60, Some(3));
+        let start = Instant::now();
+
+        // A burst goes through, then the bucket is empty
+        for _ in 0..3 {
+            assert_eq!(limiter.acquire(client("10.0.0.1"), start), Ok(()));
+        }
+        let wait = limiter.acquire(client("10.0.0.1"), start).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+        // Other clients have their own bucket
+        assert_eq!(limiter.acquire(client("10.0.0.2"), start), Ok(()));
+
+        // One token back per second
+        let later = start + Duration::from_millis(1500);
+        assert_eq!(limiter.acquire(client("10.0.0.1"), later), Ok(()));
+        assert!(limiter.acquire(client("10.0.0.1"), later).is_err());
+
+        // Never more than the burst, however long the client was idle
+        let much_later = start + Duration::from_secs(3600);
+        for _ in 0..3 {
+            assert_eq!(limiter.acquire(client("10.0.0.1"), much_later), Ok(()));
+        }
+        assert!(limiter.acquire(client("10.0.0.1"), much_later).is_err());
+    }
+
+    #[test]
+    fn test_tracked_clients_are_capped() {
+        let limiter = limiter(60, None);
+        let start = Instant::now();
+        let nth = |i: usize| ClientKey::Ip(Some(IpAddr::from((i as u32).to_be_bytes())));
+        for i in 0..MAX_TRACKED_CLIENTS {
+            limiter.acquire(nth(i), start).unwrap();
+        }
+        assert_eq!(limiter.tracked(), MAX_TRACKED_CLIENTS);
+        // The first client comes back, so the second is now the least recent
+        limiter.acquire(nth(0), start).unwrap();
+
+        // Act: another client arrives while all of them are still active
+        limiter.acquire(nth(MAX_TRACKED_CLIENTS), start).unwrap();
+
+        // Assert: the least recent client made room for it
+        assert_eq!(limiter.tracked(), MAX_TRACKED_CLIENTS);
+        let buckets = limiter.buckets.lock();
+        assert!(!buckets.by_client.contains_key(&nth(1)));
+        assert!(buckets.by_client.contains_key(&nth(0)));
+        assert!(buckets.by_client.contains_key(&nth(MAX_TRACKED_CLIENTS)));
+        assert_eq!(buckets.by_age.len(), MAX_TRACKED_CLIENTS);
+    }
+
+    #[test]
+    fn test_ipv6_clients_are_counted_by_network() {
+        let counted = |ip: &str| counted_address(ip.parse().unwrap());
+        assert_eq!(
+            counted("2001:db8:1:2:aaaa::1"),
+            counted("2001:db8:1:2:bbbb::2")
+        );
+        assert_ne!(counted("2001:db8:1:2::1"), counted("2001:db8:1:3::1"));
+        assert_eq!(
+            counted("2001:db8:1:2:aaaa::1"),
+            "2001:db8:1:2::".parse::<IpAddr>().unwrap()
+        );
+        // IPv4 clients, mapped or not, keep their own address
+        assert_eq!(
+            counted("::ffff:10.0.0.1"),
+            "10.0.0.1".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(counted("10.0.0.1"), "10.0.0.1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_no_limit_by_default() {
+        let limiter = RateLimiter::new(&RateLimitConfig::default());
+        for _ in 0..1000 {
+            assert_eq!(limiter.acquire(client("10.0.0.1"), Instant::now()), Ok(()));
+        }
+        assert_eq!(limiter.tracked(), 0);
+    }
+}
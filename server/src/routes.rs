@@ -2,24 +2,513 @@
 //
 // This software is licensed under the terms of the MIT License.
 // See the LICENSE file in the project root for the full license text.
-use crate::handlers;
+use crate::config::{Config, CorsConfig, CorsList, CorsOrigins};
+use crate::openapi::ApiDoc;
+use crate::state::AppState;
+use crate::{
+    assets, auth, client_ip, etag, faults, formats, handlers, limits, live, rate_limit, throttle,
+};
+use axum::http::{header, HeaderValue};
 use axum::{
-    routing::{delete, get, patch, post},
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, patch, post, put, MethodRouter},
     Router,
 };
 use sqlx::SqlitePool;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
-/// Creates and configures the application router.
+/// Largest backup accepted by `POST /api/admin/restore` and
+/// `POST /api/import/full`, well above the default request body limit.
+const MAX_BACKUP_UPLOAD_BYTES: usize = 1024 * 1024 * 1024;
+
+/// Every path under `/api/` the router may mount, whatever the configuration.
+/// Mounting one missing from this table panics, so that the tests listing the
+/// routes from it cannot miss any.
+pub const ROUTES: &[&str] = &[
+    "/api/tasks",
+    "/api/tasks/bulk",
+    "/api/capture",
+    "/api/tasks/bulk-move",
+    "/api/batch",
+    "/api/tasks/today",
+    "/api/tasks/search",
+    "/api/tasks/suggest",
+    "/api/tasks/week",
+    "/api/ws",
+    "/api/tasks/{id}",
+    "/api/tasks/{id}/move",
+    "/api/tasks/{id}/pin",
+    "/api/tasks/{id}/rollover-target",
+    "/api/tasks/{id}/slots",
+    "/api/tasks/{id}/slots/{slot_id}",
+    "/api/tasks/{id}/slots/{slot_id}/done",
+    "/api/tasks/rollover",
+    "/api/tasks/rollover/preview",
+    "/api/rollover/history",
+    "/api/admin/jobs",
+    "/api/admin/jobs/{job}/pause",
+    "/api/webhooks",
+    "/api/webhooks/{id}",
+    "/api/webhooks/{id}/deliveries",
+    "/api/admin/dead-letters",
+    "/api/notifications/test",
+    "/api/tasks/stale",
+    "/api/holidays",
+    "/api/holidays/{day}",
+    "/api/plan/next-week",
+    "/api/days/{date}/focus",
+    "/api/days/{date}/normalize-priorities",
+    "/api/clients",
+    "/api/clients/{name}",
+    "/api/clients/{name}/color",
+    "/api/clients/{name}/defaults",
+    "/api/clients/color-changes",
+    "/api/settings/palette",
+    "/api/settings/timezone",
+    "/api/settings/goal",
+    "/api/stats/goal",
+    "/api/shares",
+    "/api/clients/{name}/archive",
+    "/api/clients/resolve",
+    "/api/clients/{name}/aliases",
+    "/api/clients/{name}/aliases/{alias}",
+    "/api/graphql",
+    "/api/weeks/{week}/archive",
+    "/api/import",
+    "/api/import/{id}/preview",
+    "/api/import/{id}/commit",
+    "/api/export/archive",
+    "/api/export/full",
+    "/api/export/table",
+    "/api/export/calendar.ics",
+    "/api/export/tasks.csv",
+    "/api/sync",
+    "/api/clients/{name}/stats",
+    "/api/admin/backup",
+    "/api/import/full",
+    "/api/admin/restore",
+    "/api/debug/faults",
+    "/api/session",
+    "/api/session/csrf",
+    "/api/shared/{token}",
+    "/api/docs",
+    "/api/openapi.json",
+    "/api/test/reset",
+];
+
+/// `path`, checked to be in `ROUTES`.
+fn api(path: &'static str) -> &'static str {
+    assert!(ROUTES.contains(&path), "{path} is missing from ROUTES");
+    path
+}
+
+/// Builds the layer answering browsers' cross-origin checks from the CORS
+/// settings, applied around the whole router.
+pub fn cors_layer(cors: &CorsConfig) -> CorsLayer {
+    let origins = match &cors.allowed_origins {
+        CorsOrigins::Any => AllowOrigin::any(),
+        // No origin allowed: cross-origin responses get no CORS headers.
+        CorsOrigins::None => AllowOrigin::list([]),
+        // Origins were checked to be valid header values with the configuration.
+        CorsOrigins::List(origins) => AllowOrigin::list(
+            origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+        ),
+    };
+    let methods = match &cors.allowed_methods {
+        CorsList::Any => AllowMethods::any(),
+        CorsList::List(methods) => AllowMethods::list(methods.iter().cloned()),
+    };
+    let headers = match &cors.allowed_headers {
+        CorsList::Any => AllowHeaders::any(),
+        CorsList::List(headers) => AllowHeaders::list(headers.iter().cloned()),
+    };
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        // Frontends polling the listings send it back in `If-None-Match`
+        .expose_headers([header::ETAG])
+        .allow_credentials(cors.allow_credentials)
+}
+
+/// Applies `REQUEST_TIMEOUT_SECS` and `MAX_BODY_BYTES` to the routes of
+/// `router`, answering with structured 408 and 413 errors.
+fn with_request_limits(router: Router<AppState>, state: &AppState) -> Router<AppState> {
+    router
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            limits::limit_body_size,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            limits::limit_request_time,
+        ))
+}
+
+/// Queries and mutations are posted to `/api/graphql`; debug builds also
+/// serve the GraphQL playground on `GET`.
+fn graphql_route() -> MethodRouter<AppState> {
+    let route = post(handlers::graphql);
+    if cfg!(debug_assertions) {
+        route.get(handlers::graphql_playground)
+    } else {
+        route
+    }
+}
+
+/// Creates the application router with the default configuration.
 pub fn create_router(pool: SqlitePool) -> Router {
-    Router::new()
+    create_router_with_config(pool, Config::default())
+}
+
+/// Creates and configures the application router.
+pub fn create_router_with_config(pool: SqlitePool, config: Config) -> Router {
+    let e2e_mode = config.e2e_mode;
+    let serve_web_ui = config.serve_web_ui;
+    let has_web_root = config.web_root.is_some();
+    let max_body_bytes = config.http.max_body_bytes;
+    let fault_injection = config.fault_injection && cfg!(debug_assertions);
+    // Without API keys, anyone reaching the server could take or replace the
+    // whole database, so the admin routes and the full export and import are
+    // left out.
+    let admin_routes = !config.api_keys.is_empty();
+    let state = AppState::new(pool, config);
+
+    let router = Router::new()
         // Associates the `GET /api/tasks` route with the `list_tasks` handler
-        .route("/api/tasks", get(handlers::list_tasks))
+        .route(
+            api("/api/tasks"),
+            get(handlers::list_tasks).layer(middleware::from_fn(etag::conditional_get)),
+        )
         // Associates the `POST /api/tasks` route with the `create_task` handler
-        .route("/api/tasks", post(handlers::create_task))
+        .route(api("/api/tasks"), post(handlers::create_task))
+        // Associates the `POST /api/tasks/bulk` route with the `create_tasks_bulk` handler
+        .route(api("/api/tasks/bulk"), post(handlers::create_tasks_bulk))
+        // Associates the `POST /api/capture` route with the `capture_task` handler
+        .route(api("/api/capture"), post(handlers::capture_task))
+        // Associates the `POST /api/tasks/bulk-move` route with the `bulk_move_tasks` handler
+        .route(api("/api/tasks/bulk-move"), post(handlers::bulk_move_tasks))
+        // Creates, moves and completes tasks in one transaction, all or none
+        .route(api("/api/batch"), post(handlers::apply_batch))
+        // Today's open tasks, optionally for a single context
+        .route(
+            api("/api/tasks/today"),
+            get(handlers::today_tasks).layer(middleware::from_fn(etag::conditional_get)),
+        )
+        // Tasks of any week matching a filter, page by page
+        .route(api("/api/tasks/search"), get(handlers::search_tasks))
+        // Suggests tasks of today fitting in a free slot
+        .route(api("/api/tasks/suggest"), get(handlers::suggest_tasks))
+        // Associates the `GET /api/tasks/week` route with the `week_board` handler
+        .route(
+            api("/api/tasks/week"),
+            get(handlers::week_board).layer(middleware::from_fn(etag::conditional_get)),
+        )
+        // WebSocket pushing the changes live, and taking task commands
+        .route(api("/api/ws"), get(handlers::live_updates))
         // Associates the `DELETE /api/tasks/{id}` route with the `delete_task` handler
-        .route("/api/tasks/{id}", delete(handlers::delete_task))
+        .route(api("/api/tasks/{id}"), delete(handlers::delete_task))
+        // Associates the `PATCH /api/tasks/{id}/move` route with the `move_task` handler
+        .route(api("/api/tasks/{id}/move"), patch(handlers::move_task))
+        // Tasks pinned to their day are left alone by the rollover
+        .route(
+            api("/api/tasks/{id}/pin"),
+            post(handlers::pin_task).delete(handlers::unpin_task),
+        )
+        // Tasks rolling over to a day of their own rather than the policy's
+        .route(
+            api("/api/tasks/{id}/rollover-target"),
+            put(handlers::set_task_rollover_target).delete(handlers::clear_task_rollover_target),
+        )
+        // Work on a task split into slots over several days
+        .route(
+            api("/api/tasks/{id}/slots"),
+            get(handlers::list_task_slots).post(handlers::add_task_slot),
+        )
+        .route(
+            api("/api/tasks/{id}/slots/{slot_id}"),
+            delete(handlers::delete_task_slot),
+        )
+        .route(
+            api("/api/tasks/{id}/slots/{slot_id}/done"),
+            post(handlers::complete_task_slot),
+        )
         // Associates the `PATCH /api/tasks/rollover` route with the `rollover` handler
-        .route("/api/tasks/rollover", patch(handlers::rollover_tasks))
-        // Adds the database pool to the application state
-        .with_state(pool)
+        .route(api("/api/tasks/rollover"), patch(handlers::rollover_tasks))
+        .route(
+            api("/api/tasks/rollover/preview"),
+            get(handlers::preview_rollover),
+        )
+        .route(
+            api("/api/rollover/history"),
+            get(handlers::rollover_history),
+        )
+        // Background jobs and their latest runs
+        .route(api("/api/admin/jobs"), get(handlers::list_jobs))
+        .route(
+            api("/api/admin/jobs/{job}/pause"),
+            post(handlers::pause_job).delete(handlers::resume_job),
+        )
+        // Outgoing webhooks and their delivery log
+        .route(
+            api("/api/webhooks"),
+            get(handlers::list_webhooks).post(handlers::create_webhook),
+        )
+        .route(api("/api/webhooks/{id}"), delete(handlers::delete_webhook))
+        .route(
+            api("/api/webhooks/{id}/deliveries"),
+            get(handlers::list_webhook_deliveries),
+        )
+        // Deliveries given up on, to look into and send again
+        .route(
+            api("/api/admin/dead-letters"),
+            get(handlers::list_dead_letters).post(handlers::requeue_dead_letters),
+        )
+        // Checks the Slack and email settings without waiting for a digest
+        .route(
+            api("/api/notifications/test"),
+            post(handlers::test_notifications),
+        )
+        // Tasks postponed past STALE_AFTER_ROLLOVERS
+        .route(api("/api/tasks/stale"), get(handlers::stale_tasks))
+        // Holidays skipped by the `business-day-holidays` rollover policy
+        .route(
+            api("/api/holidays"),
+            get(handlers::get_holidays).post(handlers::add_holiday),
+        )
+        .route(api("/api/holidays/{day}"), delete(handlers::delete_holiday))
+        // Weekly planning: review next week, then apply the selections at once
+        .route(
+            api("/api/plan/next-week"),
+            get(handlers::get_next_week_plan).post(handlers::apply_next_week_plan),
+        )
+        // Focus mode: only the top priority tasks of a day are listed
+        .route(
+            api("/api/days/{date}/focus"),
+            post(handlers::set_focus_day).delete(handlers::clear_focus_day),
+        )
+        // Priorities of a day rewritten as a clean sequence
+        .route(
+            api("/api/days/{date}/normalize-priorities"),
+            post(handlers::normalize_priorities),
+        )
+        // Clients, with archived ones hidden unless requested
+        .route(
+            api("/api/clients"),
+            get(handlers::list_clients).post(handlers::create_client),
+        )
+        .route(
+            api("/api/clients/{name}"),
+            get(handlers::get_client)
+                .put(handlers::update_client)
+                .delete(handlers::delete_client),
+        )
+        .route(
+            api("/api/clients/{name}/color"),
+            put(handlers::set_client_color),
+        )
+        // Priority and context inherited by new tasks of the client
+        .route(
+            api("/api/clients/{name}/defaults"),
+            put(handlers::set_client_defaults),
+        )
+        // Color changes since a given one, for frontends caching colors
+        .route(
+            api("/api/clients/color-changes"),
+            get(handlers::list_color_changes),
+        )
+        // Palette new clients are assigned colors from
+        .route(
+            api("/api/settings/palette"),
+            get(handlers::get_palette).put(handlers::set_palette),
+        )
+        // Timezone whose midnight starts a new day
+        .route(api("/api/settings/timezone"), get(handlers::get_timezone))
+        // Weekly completion goal, and where the week stands against it
+        .route(
+            api("/api/settings/goal"),
+            get(handlers::get_weekly_goal)
+                .put(handlers::set_weekly_goal)
+                .delete(handlers::delete_weekly_goal),
+        )
+        .route(api("/api/stats/goal"), get(handlers::goal_progress))
+        // Signed, expiring read-only links to a week
+        .route(api("/api/shares"), post(handlers::create_share))
+        .route(
+            api("/api/clients/{name}/archive"),
+            post(handlers::archive_client).delete(handlers::unarchive_client),
+        )
+        // Client aliases: several spellings mapped onto one canonical client
+        .route(
+            api("/api/clients/resolve"),
+            get(handlers::resolve_client_name),
+        )
+        .route(
+            api("/api/clients/{name}/aliases"),
+            get(handlers::list_client_aliases).post(handlers::add_client_alias),
+        )
+        .route(
+            api("/api/clients/{name}/aliases/{alias}"),
+            delete(handlers::delete_client_alias),
+        )
+        // Tasks and clients through GraphQL, with the playground in debug builds
+        .route(api("/api/graphql"), graphql_route());
+    let router = with_request_limits(router, &state);
+
+    // Imports, exports, backups and reports share a few slots so that they
+    // cannot starve the pool; the others are answered 503 with Retry-After.
+    let heavy_routes = Router::new()
+        // Closed weeks, snapshotted into the archives
+        .route(
+            api("/api/weeks/{week}/archive"),
+            get(handlers::get_week_archive).post(handlers::archive_week),
+        )
+        // Two-phase import: stage rows, review them, then commit atomically
+        .route(api("/api/import"), post(handlers::stage_import))
+        .route(
+            api("/api/import/{id}/preview"),
+            get(handlers::preview_import),
+        )
+        .route(
+            api("/api/import/{id}/commit"),
+            post(handlers::commit_import),
+        )
+        // Resumable full archive download, chunk by chunk
+        .route(api("/api/export/archive"), get(handlers::archive_chunk))
+        // Tables pulled by BI tools, incrementally
+        .route(api("/api/export/table"), get(handlers::export_table))
+        // Calendar feed of the tasks, subscribed to by calendar apps
+        .route(
+            api("/api/export/calendar.ics"),
+            get(handlers::export_calendar),
+        )
+        // Filtered task list as CSV, streamed
+        .route(
+            api("/api/export/tasks.csv"),
+            get(handlers::export_tasks_csv),
+        )
+        // Delta sync of the mobile client, in JSON or MessagePack
+        .route(api("/api/sync"), get(handlers::sync))
+        .route(
+            api("/api/clients/{name}/stats"),
+            get(handlers::client_stats),
+        );
+    // Database snapshot download, and everything as one versioned JSON
+    // document to move between databases
+    let heavy_routes = if admin_routes {
+        heavy_routes
+            .route(api("/api/admin/backup"), get(handlers::download_backup))
+            .route(api("/api/export/full"), get(handlers::export_full))
+    } else {
+        heavy_routes
+    };
+    // Restoring a snapshot, or importing such a document, replaces the data.
+    // Uploading a large one may take longer than any request should.
+    let heavy_routes = with_request_limits(heavy_routes, &state);
+    let heavy_routes = if admin_routes {
+        heavy_routes
+            .route(
+                api("/api/import/full"),
+                post(handlers::import_full).layer(DefaultBodyLimit::max(MAX_BACKUP_UPLOAD_BYTES)),
+            )
+            .route(
+                api("/api/admin/restore"),
+                post(handlers::restore_backup)
+                    .layer(DefaultBodyLimit::max(MAX_BACKUP_UPLOAD_BYTES)),
+            )
+    } else {
+        heavy_routes
+    };
+    let heavy_routes = heavy_routes.route_layer(middleware::from_fn_with_state(
+        state.clone(),
+        throttle::limit_heavy_ops,
+    ));
+    let router = router.merge(heavy_routes);
+
+    // Delays and errors injected into chosen routes, in debug builds only.
+    // Applied to the routes above, not to the endpoint configuring them.
+    let router = if fault_injection {
+        router
+            .route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                faults::inject_faults,
+            ))
+            .route(
+                api("/api/debug/faults"),
+                get(handlers::list_faults)
+                    .put(handlers::set_faults)
+                    .delete(handlers::clear_faults),
+            )
+    } else {
+        router
+    };
+
+    // Every route above needs one of the API_KEYS, or a browser session,
+    // once any is configured; signing in does not.
+    let router = router
+        // Live connections are told when a request may have changed data
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            live::publish_changes,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_auth,
+        ))
+        .route(
+            api("/api/session"),
+            post(handlers::create_session).delete(handlers::delete_session),
+        )
+        .route(api("/api/session/csrf"), get(handlers::get_session_csrf))
+        // Read-only view of a week for the holder of its share link
+        .route(api("/api/shared/{token}"), get(handlers::get_shared_week))
+        // Description of the API, and the pages to browse and try it
+        .merge(SwaggerUi::new(api("/api/docs")).url(api("/api/openapi.json"), ApiDoc::openapi()))
+        // Clients over RATE_LIMIT_PER_MINUTE are answered 429, before any
+        // key is checked, so that guessing them is slowed down too
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::limit_rate,
+        ));
+
+    // Web UI: a frontend build on disk, or the pages embedded in the binary,
+    // with the routes of the app falling back to its index page
+    let router = if !serve_web_ui {
+        router
+    } else if has_web_root {
+        router.fallback(assets::serve_web_root)
+    } else {
+        router
+            .route("/", get(assets::index))
+            .route("/{page}", get(assets::serve_page))
+            .fallback(assets::app_fallback)
+    };
+
+    // Wipes all data between end-to-end tests; never exposed otherwise
+    let router = if e2e_mode {
+        router.route(api("/api/test/reset"), post(handlers::reset_test_state))
+    } else {
+        router
+    };
+
+    router
+        // Bodies over MAX_BODY_BYTES are refused by the extractors
+        .layer(DefaultBodyLimit::max(max_body_bytes))
+        // Reads and writes MessagePack and CBOR bodies as well as JSON
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            formats::negotiate_formats,
+        ))
+        // Resolves the real client address behind trusted reverse proxies
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            client_ip::resolve_client_ip,
+        ))
+        // Adds the database pool and configuration to the application state
+        .with_state(state)
 }
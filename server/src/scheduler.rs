@@ -0,0 +1,112 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::{RolloverPolicy, RolloverScheduleConfig};
+use crate::jobs::Job;
+use crate::notify::{Notification, Notifier};
+use crate::{clock, database};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use common::RolloverTrigger;
+use croner::Cron;
+use sqlx::SqlitePool;
+use std::time::Duration;
+use tracing::warn;
+
+/// Next run of `schedule`, read in `timezone`, strictly after `after`, if it
+/// ever runs again.
+pub fn next_run(schedule: &Cron, after: DateTime<Utc>, timezone: Tz) -> Option<DateTime<Utc>> {
+    schedule
+        .find_next_occurrence(&after.with_timezone(&timezone), false)
+        .ok()
+        .map(|next| next.with_timezone(&Utc))
+}
+
+/// The automatic rollover, run at each occurrence of the configured cron
+/// expression. The startup catch-up covers the runs missed while the
+/// server was down. Runs moving more than `notify_above` tasks are notified.
+pub struct RolloverJob {
+    pub schedule: RolloverScheduleConfig,
+    pub policy: RolloverPolicy,
+    pub stale_after: Option<i64>,
+    pub notifier: Notifier,
+    pub notify_above: usize,
+}
+
+impl Job for RolloverJob {
+    fn name(&self) -> &'static str {
+        "rollover"
+    }
+
+    fn next_run(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        next_run(&self.schedule.cron, after, clock::timezone())
+    }
+
+    fn jitter(&self) -> Duration {
+        self.schedule.jitter
+    }
+
+    async fn run(&self, pool: &SqlitePool) -> Result<String> {
+        let day = clock::today();
+        let count = database::rollover_tasks_in_db(
+            pool,
+            self.policy,
+            RolloverTrigger::Automatic,
+            self.stale_after,
+        )
+        .await?;
+        // A failure is only logged: failing the run would roll over again.
+        if count > self.notify_above
+            && self.notifier.is_enabled()
+            && self
+                .notifier
+                .notify(&Notification::Rollover { day, count })
+                .await
+                .is_err()
+        {
+            warn!("The rollover of {} tasks could not be notified.", count);
+        }
+        Ok(format!(
+            "Rolled over {} tasks ({} policy).",
+            count,
+            self.policy.as_str()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_run_follows_the_cron_expression() {
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let nightly = Cron::new("55 23 * * *").parse().unwrap();
+        assert_eq!(
+            next_run(&nightly, at("2025-07-18T10:00:00Z"), Tz::UTC),
+            Some(at("2025-07-18T23:55:00Z"))
+        );
+        // A run that just happened is not picked again
+        assert_eq!(
+            next_run(&nightly, at("2025-07-18T23:55:00Z"), Tz::UTC),
+            Some(at("2025-07-19T23:55:00Z"))
+        );
+
+        let weekdays = Cron::new("0 18 * * 1-5").parse().unwrap();
+        assert_eq!(
+            next_run(&weekdays, at("2025-07-18T19:00:00Z"), Tz::UTC),
+            Some(at("2025-07-21T18:00:00Z"))
+        );
+        // 23:55 in Brisbane (UTC+10) is 13:55 UTC
+        assert_eq!(
+            next_run(
+                &nightly,
+                at("2025-07-18T10:00:00Z"),
+                Tz::Australia__Brisbane
+            ),
+            Some(at("2025-07-18T13:55:00Z"))
+        );
+    }
+}
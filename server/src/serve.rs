@@ -0,0 +1,236 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::HttpConfig;
+use axum::{extract::ConnectInfo, response::Response, BoxError, Router};
+use hyper::{body::Incoming, server::conn::http1, Request};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo, TokioTimer},
+    server::conn::auto,
+    service::TowerToHyperService,
+};
+use std::io;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::time::{sleep, timeout, Duration};
+use tokio_rustls::TlsAcceptor;
+use tower::{Service, ServiceExt};
+use tracing::{debug, error};
+
+/// Connection builder matching the HTTP tuning options.
+#[derive(Clone)]
+enum ConnectionBuilder {
+    /// HTTP/1.1 and HTTP/2, picked per connection from the first bytes sent.
+    Auto(auto::Builder<TokioExecutor>),
+    /// HTTP/1.1 only. The auto builder ignores `http1_only` once upgrades are
+    /// enabled, so the plain HTTP/1 builder is used instead.
+    Http1(http1::Builder),
+}
+
+impl ConnectionBuilder {
+    fn new(http: &HttpConfig) -> Self {
+        if !http.http2_enabled {
+            let mut builder = http1::Builder::new();
+            builder
+                .timer(TokioTimer::new())
+                .keep_alive(http.http1_keep_alive)
+                .header_read_timeout(http.http1_header_read_timeout);
+            return ConnectionBuilder::Http1(builder);
+        }
+
+        let mut builder = auto::Builder::new(TokioExecutor::new());
+        builder
+            .http1()
+            .timer(TokioTimer::new())
+            .keep_alive(http.http1_keep_alive)
+            .header_read_timeout(http.http1_header_read_timeout);
+        builder
+            .http2()
+            .timer(TokioTimer::new())
+            .keep_alive_interval(http.http2_keep_alive_interval)
+            .keep_alive_timeout(http.http2_keep_alive_timeout)
+            .max_concurrent_streams(http.http2_max_concurrent_streams);
+        ConnectionBuilder::Auto(builder)
+    }
+
+    /// Serves a single connection until the client or the server closes it.
+    async fn serve_connection<I, S>(&self, stream: I, service: S) -> Result<(), BoxError>
+    where
+        I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        S: Service<Request<Incoming>, Response = Response> + Clone + Send + 'static,
+        S::Future: Send + 'static,
+        S::Error: Into<BoxError>,
+    {
+        let io = TokioIo::new(stream);
+        let service = TowerToHyperService::new(service);
+        match self {
+            ConnectionBuilder::Auto(builder) => {
+                builder.serve_connection_with_upgrades(io, service).await
+            }
+            ConnectionBuilder::Http1(builder) => Ok(builder
+                .serve_connection(io, service)
+                .with_upgrades()
+                .await?),
+        }
+    }
+}
+
+/// Serves the application on `listener` until the process stops, over TLS
+/// when given an acceptor.
+///
+/// Replaces `axum::serve`, which does not expose connection settings. Every
+/// request carries the peer address as `ConnectInfo<SocketAddr>`, just like
+/// `into_make_service_with_connect_info` would provide.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    http: &HttpConfig,
+    tls: Option<TlsAcceptor>,
+) -> io::Result<()> {
+    let builder = ConnectionBuilder::new(http);
+    // The handshake gets as long as the request headers would.
+    let handshake_timeout = http.http1_header_read_timeout;
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                // Typically running out of file descriptors; back off instead of spinning.
+                error!("Failed to accept connection: {}", e);
+                sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        if let Err(e) = stream.set_nodelay(true) {
+            debug!("Failed to set TCP_NODELAY for {}: {}", peer, e);
+        }
+
+        let service = app
+            .clone()
+            .map_request(move |mut request: Request<Incoming>| {
+                request.extensions_mut().insert(ConnectInfo(peer));
+                request
+            });
+        let builder = builder.clone();
+        let tls = tls.clone();
+
+        tokio::spawn(async move {
+            let result = match tls {
+                None => builder.serve_connection(stream, service).await,
+                Some(tls) => match timeout(handshake_timeout, tls.accept(stream)).await {
+                    Ok(Ok(stream)) => builder.serve_connection(stream, service).await,
+                    Ok(Err(e)) => Err(format!("TLS handshake failed: {e}").into()),
+                    Err(_) => Err("TLS handshake timed out".into()),
+                },
+            };
+            if let Err(e) = result {
+                debug!("Connection with {} closed with an error: {}", peer, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TlsConfig;
+    use crate::tls::{self, tests::fixture};
+    use axum::routing::get;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use tokio_rustls::rustls::{
+        crypto::ring,
+        pki_types::{pem::PemObject, CertificateDer, ServerName},
+        ClientConfig, RootCertStore,
+    };
+    use tokio_rustls::TlsConnector;
+
+    const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+    async fn spawn_server(http: HttpConfig) -> SocketAddr {
+        spawn_server_with_tls(http, None).await
+    }
+
+    async fn spawn_server_with_tls(http: HttpConfig, tls: Option<TlsAcceptor>) -> SocketAddr {
+        let app = Router::new().route(
+            "/",
+            get(|ConnectInfo(peer): ConnectInfo<SocketAddr>| async move { peer.ip().to_string() }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { serve(listener, app, &http, tls).await });
+        addr
+    }
+
+    async fn send(addr: SocketAddr, bytes: &[u8]) -> Vec<u8> {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(bytes).await.unwrap();
+        let mut buffer = vec![0; 1024];
+        let read = stream.read(&mut buffer).await.unwrap();
+        buffer.truncate(read);
+        buffer
+    }
+
+    #[tokio::test]
+    async fn test_http1_request_has_connect_info() {
+        let addr = spawn_server(HttpConfig::default()).await;
+
+        let response = send(addr, b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+        let response = String::from_utf8_lossy(&response);
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.ends_with("127.0.0.1"), "{response}");
+    }
+
+    #[tokio::test]
+    async fn test_http2_can_be_disabled() {
+        // With HTTP/2 enabled, the server answers the preface with a SETTINGS frame
+        let addr = spawn_server(HttpConfig::default()).await;
+        let response = send(addr, HTTP2_PREFACE).await;
+        assert!(response.len() >= 9 && response[3] == 0x4, "{response:?}");
+
+        let addr = spawn_server(HttpConfig {
+            http2_enabled: false,
+            ..Default::default()
+        })
+        .await;
+        let response = send(addr, HTTP2_PREFACE).await;
+        assert!(!(response.len() >= 9 && response[3] == 0x4), "{response:?}");
+    }
+
+    #[tokio::test]
+    async fn test_https_request() {
+        let tls_config = TlsConfig::new(fixture("localhost.pem"), fixture("localhost.key"));
+        let (acceptor, _) = tls::acceptor(&tls_config, true).unwrap();
+        let addr = spawn_server_with_tls(HttpConfig::default(), Some(acceptor)).await;
+
+        // Plain HTTP is not answered on the HTTPS port
+        let response = send(addr, b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+        assert!(!response.starts_with(b"HTTP/1.1"), "{response:?}");
+
+        let mut roots = RootCertStore::empty();
+        let ca = CertificateDer::from_pem_file(fixture("ca.pem")).unwrap();
+        roots.add(ca).unwrap();
+        let client_config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut stream = TlsConnector::from(Arc::new(client_config))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await
+            .unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.unwrap();
+        let response = String::from_utf8_lossy(&response);
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.ends_with("127.0.0.1"), "{response}");
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::auth::SigningSecret;
+use crate::plan;
+use chrono::{DateTime, Duration, Utc};
+
+/// Lifetime of a share link when none is asked for.
+pub const DEFAULT_TTL: Duration = Duration::days(7);
+/// Longest lifetime of a share link; a link sent by mail should not outlive
+/// the plans it shows by much.
+pub const MAX_TTL: Duration = Duration::days(90);
+
+/// A week shared read-only, as carried by its link: nothing is stored, the
+/// token is the ISO week and expiry, signed with `SHARE_SECRET`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareToken {
+    pub week: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl ShareToken {
+    fn payload(&self) -> String {
+        format!("{}.{}", self.week, self.expires_at.timestamp())
+    }
+
+    /// The token of the link, e.g. `2025-W29.1753050000.<signature>`.
+    pub fn sign(&self, secret: &SigningSecret) -> String {
+        let payload = self.payload();
+        let signature = secret.sign(&format!("share:{payload}"));
+        format!("{payload}.{signature}")
+    }
+
+    /// The share of a link token, unless it was forged or has expired.
+    pub fn verify(secret: &SigningSecret, token: &str, now: DateTime<Utc>) -> Option<ShareToken> {
+        let (payload, signature) = token.rsplit_once('.')?;
+        if !secret.verify(&format!("share:{payload}"), signature) {
+            return None;
+        }
+        let (week, expires_at) = payload.split_once('.')?;
+        plan::parse_iso_week(week)?;
+        let expires_at = DateTime::from_timestamp(expires_at.parse().ok()?, 0)?;
+        (expires_at > now).then(|| ShareToken {
+            week: week.to_string(),
+            expires_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_share_tokens_are_signed_and_expire() {
+        let secret: SigningSecret = "a-share-secret-of-32-characters!!".parse().unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 7, 14, 9, 0, 0).unwrap();
+        let share = ShareToken {
+            week: "2025-W29".to_string(),
+            expires_at: now + DEFAULT_TTL,
+        };
+        let token = share.sign(&secret);
+        assert!(token.starts_with("2025-W29.1753088400."), "{token}");
+        assert_eq!(
+            ShareToken::verify(&secret, &token, now),
+            Some(share.clone())
+        );
+
+        // Expired, or pointed at another week
+        assert_eq!(ShareToken::verify(&secret, &token, share.expires_at), None);
+        let other_week = token.replacen("2025-W29", "2025-W30", 1);
+        assert_eq!(ShareToken::verify(&secret, &other_week, now), None);
+        // Signed with another secret
+        let other: SigningSecret = "another-secret-of-32-characters!!".parse().unwrap();
+        assert_eq!(ShareToken::verify(&other, &token, now), None);
+        assert_eq!(ShareToken::verify(&secret, "not-a-token", now), None);
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::Config;
+use crate::faults::FaultInjector;
+use crate::graphql::{self, ApiSchema};
+use crate::live::ChangeFeed;
+use crate::rate_limit::RateLimiter;
+use crate::throttle::HeavyOps;
+use axum::extract::FromRef;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+/// Shared state of the application. Handlers extract only the part they
+/// need, e.g. `State<SqlitePool>`.
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: SqlitePool,
+    pub config: Arc<Config>,
+    pub faults: Arc<FaultInjector>,
+    pub heavy_ops: Arc<HeavyOps>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub changes: Arc<ChangeFeed>,
+    pub graphql: ApiSchema,
+}
+
+impl AppState {
+    pub fn new(pool: SqlitePool, config: Config) -> Self {
+        AppState {
+            pool,
+            heavy_ops: Arc::new(HeavyOps::new(&config.heavy_ops)),
+            rate_limiter: Arc::new(RateLimiter::new(&config.rate_limit)),
+            config: Arc::new(config),
+            faults: Arc::default(),
+            changes: Arc::default(),
+            graphql: graphql::schema(),
+        }
+    }
+}
+
+impl FromRef<AppState> for SqlitePool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Config> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<FaultInjector> {
+    fn from_ref(state: &AppState) -> Self {
+        state.faults.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<HeavyOps> {
+    fn from_ref(state: &AppState) -> Self {
+        state.heavy_ops.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RateLimiter> {
+    fn from_ref(state: &AppState) -> Self {
+        state.rate_limiter.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<ChangeFeed> {
+    fn from_ref(state: &AppState) -> Self {
+        state.changes.clone()
+    }
+}
+
+impl FromRef<AppState> for ApiSchema {
+    fn from_ref(state: &AppState) -> Self {
+        state.graphql.clone()
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use common::{Energy, Task};
+
+/// Picks tasks that fit together in a free slot of `minutes`.
+///
+/// Only tasks with an estimate are considered, since the others cannot be
+/// known to fit; when `energy` is given, only tasks tagged with it are.
+/// Candidates are taken by priority (missing last), then shortest first, and
+/// added as long as the total stays within the slot.
+pub fn suggest_tasks(tasks: Vec<Task>, energy: Option<Energy>, minutes: i32) -> Vec<Task> {
+    let mut candidates: Vec<Task> = tasks
+        .into_iter()
+        .filter(|task| energy.is_none() || task.energy == energy)
+        .filter(|task| task.estimate_minutes.is_some_and(|m| m <= minutes))
+        .collect();
+    candidates.sort_by_key(|task| {
+        (
+            task.priority.is_none(),
+            task.priority,
+            task.estimate_minutes,
+            task.id,
+        )
+    });
+
+    let mut remaining = minutes;
+    candidates
+        .into_iter()
+        .filter(|task| {
+            let estimate = task.estimate_minutes.unwrap_or(0);
+            let fits = estimate <= remaining;
+            if fits {
+                remaining -= estimate;
+            }
+            fits
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use common::Priority;
+
+    fn task(
+        id: i64,
+        priority: Option<Priority>,
+        energy: Option<Energy>,
+        estimate: Option<i32>,
+    ) -> Task {
+        Task {
+            id,
+            client_id: 1,
+            client_name: "Client".to_string(),
+            description: format!("Task {id}"),
+            task_date: Utc::now().date_naive(),
+            client_color: "#1f77b4".parse().unwrap(),
+            client_text_color: "#ffffff".to_string(),
+            client_background_color: "#d2e4f0".to_string(),
+            created_at: Utc::now(),
+            deleted_at: None,
+            updated_at: Utc::now(),
+            priority,
+            external_source: None,
+            external_id: None,
+            energy,
+            estimate_minutes: estimate,
+            context: None,
+            pin_to_date: false,
+            rollover_target: None,
+            rollover_count: 0,
+            stale_since: None,
+            slots_total: 0,
+            slots_done: 0,
+            deferred: false,
+            display: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_suggestions_fill_the_slot_by_priority() {
+        let tasks = vec![
+            task(1, Some(Priority::High), Some(Energy::Shallow), Some(20)),
+            task(2, Some(Priority::Critical), Some(Energy::Shallow), Some(15)),
+            task(3, None, Some(Energy::Shallow), Some(10)),
+            task(4, Some(Priority::Critical), Some(Energy::Deep), Some(10)),
+            task(5, Some(Priority::Critical), Some(Energy::Shallow), None),
+            task(6, Some(Priority::Medium), Some(Energy::Shallow), Some(5)),
+        ];
+
+        // Task 2 (15) then task 1 (20) would overflow; 6 (5) and 3 (10) fill the rest
+        let ids: Vec<i64> = suggest_tasks(tasks.clone(), Some(Energy::Shallow), 30)
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![2, 6, 3]);
+
+        // Without an energy filter, deep work is considered too
+        let ids: Vec<i64> = suggest_tasks(tasks, None, 25)
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![4, 2]);
+    }
+}
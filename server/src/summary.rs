@@ -0,0 +1,374 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use chrono::{Datelike, NaiveDate, Weekday};
+use common::{WeekArchive, WeekClientTotals};
+use std::str::FromStr;
+
+/// Languages the week summaries and the email digest are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+}
+
+impl Locale {
+    /// Picks the supported language preferred by an `Accept-Language`
+    /// header, e.g. `fr-CH, fr;q=0.9, en;q=0.8`. English when none is.
+    pub fn from_accept_language(header: Option<&str>) -> Locale {
+        let mut best: Option<(f32, Locale)> = None;
+        for entry in header.unwrap_or_default().split(',') {
+            let mut parts = entry.split(';').map(str::trim);
+            let tag = parts.next().unwrap_or_default().to_ascii_lowercase();
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            let locale = match tag.split('-').next() {
+                Some("en") => Locale::En,
+                Some("fr") => Locale::Fr,
+                _ => continue,
+            };
+            if quality > 0.0 && best.is_none_or(|(q, _)| quality > q) {
+                best = Some((quality, locale));
+            }
+        }
+        best.map(|(_, locale)| locale).unwrap_or_default()
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Fr => "fr",
+        }
+    }
+
+    fn weekday(self, day: Weekday) -> &'static str {
+        const EN: [&str; 7] = [
+            "Monday",
+            "Tuesday",
+            "Wednesday",
+            "Thursday",
+            "Friday",
+            "Saturday",
+            "Sunday",
+        ];
+        const FR: [&str; 7] = [
+            "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+        ];
+        let names = match self {
+            Locale::En => EN,
+            Locale::Fr => FR,
+        };
+        names[day.num_days_from_monday() as usize]
+    }
+
+    fn short_weekday(self, day: Weekday) -> &'static str {
+        const FR: [&str; 7] = ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."];
+        match self {
+            Locale::En => &self.weekday(day)[..3],
+            Locale::Fr => FR[day.num_days_from_monday() as usize],
+        }
+    }
+
+    fn month(self, month: u32) -> &'static str {
+        const EN: [&str; 12] = [
+            "January",
+            "February",
+            "March",
+            "April",
+            "May",
+            "June",
+            "July",
+            "August",
+            "September",
+            "October",
+            "November",
+            "December",
+        ];
+        const FR: [&str; 12] = [
+            "janvier",
+            "février",
+            "mars",
+            "avril",
+            "mai",
+            "juin",
+            "juillet",
+            "août",
+            "septembre",
+            "octobre",
+            "novembre",
+            "décembre",
+        ];
+        let names = match self {
+            Locale::En => EN,
+            Locale::Fr => FR,
+        };
+        names[month as usize - 1]
+    }
+
+    fn short_month(self, month: u32) -> &'static str {
+        const FR: [&str; 12] = [
+            "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.",
+            "nov.", "déc.",
+        ];
+        match self {
+            Locale::En => &self.month(month)[..3],
+            Locale::Fr => FR[month as usize - 1],
+        }
+    }
+
+    /// A day with its year, e.g. "14 July 2025" or "14 juillet 2025".
+    pub fn date(self, day: NaiveDate) -> String {
+        format!("{} {} {}", day.day(), self.month(day.month()), day.year())
+    }
+
+    /// A day with its weekday and year, e.g. "Monday 14 July 2025" or
+    /// "lundi 14 juillet 2025".
+    pub fn long_date(self, day: NaiveDate) -> String {
+        format!("{} {}", self.weekday(day.weekday()), self.date(day))
+    }
+
+    /// A day of the week shown, e.g. "Mon 14 Jul" or "lun. 14 juil.".
+    pub fn short_date(self, day: NaiveDate) -> String {
+        format!(
+            "{} {} {}",
+            self.short_weekday(day.weekday()),
+            day.day(),
+            self.short_month(day.month())
+        )
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Locale::En, Locale::Fr]
+            .into_iter()
+            .find(|locale| locale.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("expected en or fr, got '{s}'"))
+    }
+}
+
+/// Counts a noun, e.g. "1 task" or "3 tasks".
+fn count(n: usize, singular: &str, plural: &str) -> String {
+    format!("{n} {}", if n == 1 { singular } else { plural })
+}
+
+/// Writes a duration in minutes the way people read it, e.g. "6h30".
+fn duration(minutes: i64, locale: Locale) -> String {
+    let (hours, minutes) = (minutes / 60, minutes % 60);
+    match (locale, hours, minutes) {
+        (Locale::En, 0, m) => format!("{m} min"),
+        (Locale::En, h, 0) => format!("{h}h"),
+        (Locale::En, h, m) => format!("{h}h{m:02}"),
+        (Locale::Fr, 0, m) => format!("{m} min"),
+        (Locale::Fr, h, 0) => format!("{h} h"),
+        (Locale::Fr, h, m) => format!("{h} h {m:02}"),
+    }
+}
+
+/// A short paragraph describing an archived week: tasks done, the client
+/// that took the largest share of the time (or of the tasks, when none was
+/// estimated), and how many tasks had been postponed. Built from fixed
+/// sentences so that the same week always reads the same.
+pub fn week_summary(archive: &WeekArchive, locale: Locale) -> String {
+    let done = archive.tasks.len();
+    if done == 0 {
+        return match locale {
+            Locale::En => "No task was completed this week.".to_string(),
+            Locale::Fr => "Aucune tâche n'a été terminée cette semaine.".to_string(),
+        };
+    }
+
+    let mut sentences = vec![match locale {
+        Locale::En => format!(
+            "You completed {} for {}.",
+            count(done, "task", "tasks"),
+            count(archive.clients.len(), "client", "clients")
+        ),
+        Locale::Fr => format!(
+            "Vous avez terminé {} pour {}.",
+            count(done, "tâche", "tâches"),
+            count(archive.clients.len(), "client", "clients")
+        ),
+    }];
+
+    // Clients are sorted by name, so ties go to the first one.
+    let total_minutes: i64 = archive.clients.iter().map(|c| c.estimate_minutes).sum();
+    let by_time = total_minutes > 0;
+    let share = |c: &WeekClientTotals| {
+        if by_time {
+            c.estimate_minutes
+        } else {
+            c.tasks_done
+        }
+    };
+    let top = archive
+        .clients
+        .iter()
+        .reduce(|best, c| if share(c) > share(best) { c } else { best });
+    if let Some(top) = top.filter(|_| archive.clients.len() > 1) {
+        let total = if by_time { total_minutes } else { done as i64 };
+        let percent = (share(top) * 100 + total / 2) / total;
+        sentences.push(match (locale, by_time) {
+            (Locale::En, true) => format!(
+                "{} took {}% of your estimated time ({} of {}).",
+                top.client_name,
+                percent,
+                duration(top.estimate_minutes, locale),
+                duration(total_minutes, locale)
+            ),
+            (Locale::En, false) => {
+                format!("{} accounted for {}% of them.", top.client_name, percent)
+            }
+            (Locale::Fr, true) => format!(
+                "{} a occupé {} % de votre temps estimé ({} sur {}).",
+                top.client_name,
+                percent,
+                duration(top.estimate_minutes, locale),
+                duration(total_minutes, locale)
+            ),
+            (Locale::Fr, false) => {
+                format!("{} en représente {} %.", top.client_name, percent)
+            }
+        });
+    }
+
+    let postponed = archive
+        .tasks
+        .iter()
+        .filter(|t| t.rollover_count > 0)
+        .count();
+    if postponed > 0 {
+        sentences.push(match (locale, postponed) {
+            (Locale::En, 1) => "1 of them had been postponed at least once.".to_string(),
+            (Locale::En, n) => format!("{n} of them had been postponed at least once."),
+            (Locale::Fr, 1) => "1 d'entre elles avait été reportée au moins une fois.".to_string(),
+            (Locale::Fr, n) => {
+                format!("{n} d'entre elles avaient été reportées au moins une fois.")
+            }
+        });
+    }
+
+    sentences.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, Utc};
+    use common::Task;
+
+    fn archive(clients: &[(&str, i64, i64)], postponed: usize) -> WeekArchive {
+        let mut tasks = Vec::new();
+        for (name, done, _) in clients {
+            for _ in 0..*done {
+                tasks.push(Task {
+                    id: tasks.len() as i64 + 1,
+                    client_id: 1,
+                    client_name: name.to_string(),
+                    description: "Done".to_string(),
+                    task_date: NaiveDate::from_ymd_opt(2025, 7, 15).unwrap(),
+                    client_color: "#1f77b4".parse().unwrap(),
+                    client_text_color: "#ffffff".to_string(),
+                    client_background_color: "#d2e4f0".to_string(),
+                    created_at: Utc::now(),
+                    deleted_at: Some(Utc::now()),
+                    updated_at: Utc::now(),
+                    priority: None,
+                    external_source: None,
+                    external_id: None,
+                    energy: None,
+                    estimate_minutes: None,
+                    context: None,
+                    pin_to_date: false,
+                    rollover_target: None,
+                    rollover_count: i64::from(tasks.len() < postponed),
+                    stale_since: None,
+                    slots_total: 0,
+                    slots_done: 0,
+                    deferred: false,
+                    display: Default::default(),
+                });
+            }
+        }
+        WeekArchive {
+            week: "2025-W29".to_string(),
+            week_start: NaiveDate::from_ymd_opt(2025, 7, 14).unwrap(),
+            week_end: NaiveDate::from_ymd_opt(2025, 7, 20).unwrap(),
+            archived_at: Utc::now(),
+            tasks,
+            clients: clients
+                .iter()
+                .map(|(name, done, minutes)| WeekClientTotals {
+                    client_name: name.to_string(),
+                    tasks_done: *done,
+                    estimate_minutes: *minutes,
+                })
+                .collect(),
+            summary: None,
+        }
+    }
+
+    #[test]
+    fn test_accept_language_picks_the_preferred_supported_locale() {
+        assert_eq!(Locale::from_accept_language(None), Locale::En);
+        assert_eq!(Locale::from_accept_language(Some("fr-CH")), Locale::Fr);
+        assert_eq!(
+            Locale::from_accept_language(Some("de, en;q=0.5, fr;q=0.8")),
+            Locale::Fr
+        );
+        assert_eq!(
+            Locale::from_accept_language(Some("de, *;q=0.5")),
+            Locale::En
+        );
+        assert_eq!(Locale::from_accept_language(Some("fr;q=0")), Locale::En);
+    }
+
+    #[test]
+    fn test_dates_are_written_in_the_locale() {
+        let day = NaiveDate::from_ymd_opt(2025, 2, 3).unwrap();
+        assert_eq!(Locale::En.long_date(day), "Monday 3 February 2025");
+        assert_eq!(Locale::Fr.long_date(day), "lundi 3 février 2025");
+        assert_eq!(Locale::En.date(day), "3 February 2025");
+        assert_eq!(Locale::En.short_date(day), "Mon 3 Feb");
+        assert_eq!(Locale::Fr.short_date(day), "lun. 3 févr.");
+        assert_eq!("FR".parse::<Locale>(), Ok(Locale::Fr));
+        assert!("de".parse::<Locale>().is_err());
+    }
+
+    #[test]
+    fn test_week_summary_by_estimated_time() {
+        let week = archive(&[("ACME", 3, 360), ("Globex", 2, 240)], 2);
+        assert_eq!(
+            week_summary(&week, Locale::En),
+            "You completed 5 tasks for 2 clients. ACME took 60% of your estimated time (6h of 10h). 2 of them had been postponed at least once."
+        );
+        assert_eq!(
+            week_summary(&week, Locale::Fr),
+            "Vous avez terminé 5 tâches pour 2 clients. ACME a occupé 60 % de votre temps estimé (6 h sur 10 h). 2 d'entre elles avaient été reportées au moins une fois."
+        );
+    }
+
+    #[test]
+    fn test_week_summary_without_estimates() {
+        let week = archive(&[("ACME", 1, 0), ("Globex", 3, 0)], 0);
+        assert_eq!(
+            week_summary(&week, Locale::En),
+            "You completed 4 tasks for 2 clients. Globex accounted for 75% of them."
+        );
+        let single = archive(&[("ACME", 1, 90)], 1);
+        assert_eq!(
+            week_summary(&single, Locale::En),
+            "You completed 1 task for 1 client. 1 of them had been postponed at least once."
+        );
+        assert_eq!(
+            week_summary(&archive(&[], 0), Locale::Fr),
+            "Aucune tâche n'a été terminée cette semaine."
+        );
+    }
+}
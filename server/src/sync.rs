@@ -0,0 +1,227 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::table::{ClientRow, TableEntity, TaskRow};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Position reached by a device in each of the sequences the sync follows:
+/// the change sequences of the clients and of the tasks, and the removals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SyncCursor {
+    pub clients: i64,
+    pub tasks: i64,
+    pub removals: i64,
+}
+
+/// Cursors are exchanged as `c<clients>t<tasks>r<removals>` tokens, e.g.
+/// `c12t340r5`. Devices should treat them as opaque.
+impl fmt::Display for SyncCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "c{}t{}r{}", self.clients, self.tasks, self.removals)
+    }
+}
+
+impl FromStr for SyncCursor {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix('c').ok_or(())?;
+        let (clients, s) = s.split_once('t').ok_or(())?;
+        let (tasks, removals) = s.split_once('r').ok_or(())?;
+        let position = |n: &str| {
+            if n.is_empty() || !n.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(());
+            }
+            n.parse::<i64>().map_err(|_| ())
+        };
+        Ok(SyncCursor {
+            clients: position(clients)?,
+            tasks: position(tasks)?,
+            removals: position(removals)?,
+        })
+    }
+}
+
+/// A client or task removed for good, as recorded in `sync_removals`.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SyncRemoval {
+    pub seq: i64,
+    pub entity: String,
+    pub row_id: i64,
+}
+
+/// What was written after a cursor, as read from the database.
+#[derive(Debug, Clone, Default)]
+pub struct SyncChanges {
+    pub clients: Vec<ClientRow>,
+    pub tasks: Vec<TaskRow>,
+    pub removals: Vec<SyncRemoval>,
+}
+
+/// Names of the values of the client and task rows, in order.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct SyncColumns {
+    pub clients: Vec<&'static str>,
+    pub tasks: Vec<&'static str>,
+}
+
+/// Payload of `GET /api/sync`: the rows written since the cursor of the
+/// device, as arrays of values in the order of the table export columns.
+/// Devices apply the removals first, then replace their copy of each row.
+///
+/// In MessagePack, this object is itself an array of its fields in the
+/// order below, which is kept from one release to the next.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct SyncDelta {
+    /// Cursor to pass back as `since`, for the next page or sync.
+    pub cursor: String,
+    /// Whether rows are left after this page.
+    pub has_more: bool,
+    /// Columns of the rows, only sent with a full sync (no `since`).
+    pub columns: Option<SyncColumns>,
+    pub clients: Vec<Vec<Value>>,
+    pub tasks: Vec<Vec<Value>>,
+    pub removed_clients: Vec<i64>,
+    pub removed_tasks: Vec<i64>,
+}
+
+impl SyncDelta {
+    /// Builds the page following `since` out of `changes`, read with at
+    /// most `limit` rows of each kind.
+    pub fn new(since: Option<SyncCursor>, changes: SyncChanges, limit: i64) -> SyncDelta {
+        let start = since.unwrap_or_default();
+        let cursor = SyncCursor {
+            clients: changes
+                .clients
+                .last()
+                .map_or(start.clients, |c| c.change_seq),
+            tasks: changes.tasks.last().map_or(start.tasks, |t| t.change_seq),
+            removals: changes.removals.last().map_or(start.removals, |r| r.seq),
+        };
+        let has_more = [
+            changes.clients.len(),
+            changes.tasks.len(),
+            changes.removals.len(),
+        ]
+        .into_iter()
+        .any(|count| count as i64 == limit);
+        let removed = |entity: TableEntity| {
+            changes
+                .removals
+                .iter()
+                .filter(|r| r.entity == entity.as_str())
+                .map(|r| r.row_id)
+                .collect()
+        };
+        SyncDelta {
+            cursor: cursor.to_string(),
+            has_more,
+            columns: since.is_none().then(|| SyncColumns {
+                clients: ClientRow::COLUMNS.to_vec(),
+                tasks: TaskRow::COLUMNS.to_vec(),
+            }),
+            removed_clients: removed(TableEntity::Clients),
+            removed_tasks: removed(TableEntity::Tasks),
+            clients: changes
+                .clients
+                .into_iter()
+                .map(ClientRow::into_values)
+                .collect(),
+            tasks: changes
+                .tasks
+                .into_iter()
+                .map(TaskRow::into_values)
+                .collect(),
+        }
+    }
+}
+
+/// Writes `delta` as MessagePack, objects being written as arrays of their
+/// fields rather than maps keyed by name.
+pub fn to_message_pack(delta: &SyncDelta) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    rmp_serde::to_vec(delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor: SyncCursor = "c12t340r5".parse().unwrap();
+        assert_eq!(
+            cursor,
+            SyncCursor {
+                clients: 12,
+                tasks: 340,
+                removals: 5
+            }
+        );
+        assert_eq!(cursor.to_string(), "c12t340r5");
+        assert_eq!(SyncCursor::default().to_string(), "c0t0r0");
+
+        for invalid in [
+            "c12", "c12t340", "c12t-1r5", "c1200", "t1r1c1", "c1t2r3x", "c+1t2r3",
+        ] {
+            assert!(invalid.parse::<SyncCursor>().is_err(), "{invalid}");
+        }
+    }
+
+    /// `SyncDelta` as decoded by a device, field by field.
+    type Packed = (
+        String,
+        bool,
+        Option<Value>,
+        Vec<Vec<Value>>,
+        Vec<Vec<Value>>,
+        Vec<i64>,
+        Vec<i64>,
+    );
+
+    #[test]
+    fn test_message_pack_is_smaller_than_json() {
+        let client = ClientRow {
+            id: 1,
+            name: "ACME".to_string(),
+            color: "#1f77b4".to_string(),
+            created_at: Utc.with_ymd_and_hms(2025, 7, 14, 9, 0, 0).unwrap(),
+            archived_at: None,
+            default_priority: None,
+            default_context: None,
+            change_seq: 7,
+        };
+        let changes = SyncChanges {
+            clients: vec![client],
+            removals: vec![SyncRemoval {
+                seq: 3,
+                entity: "tasks".to_string(),
+                row_id: 42,
+            }],
+            ..SyncChanges::default()
+        };
+        let since = SyncCursor {
+            clients: 2,
+            tasks: 10,
+            removals: 1,
+        };
+        let delta = SyncDelta::new(Some(since), changes, 100);
+        assert_eq!(delta.cursor, "c7t10r3");
+        assert!(!delta.has_more);
+        assert_eq!(delta.columns, None);
+        assert_eq!(delta.removed_tasks, vec![42]);
+
+        let packed = to_message_pack(&delta).unwrap();
+        let (cursor, _, _, clients, _, _, removed_tasks): Packed =
+            rmp_serde::from_slice(&packed).unwrap();
+        assert_eq!(cursor, "c7t10r3");
+        assert_eq!(clients[0][1], "ACME");
+        assert_eq!(removed_tasks, vec![42]);
+        assert!(packed.len() < serde_json::to_vec(&delta).unwrap().len() / 2);
+    }
+}
@@ -0,0 +1,371 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use common::{Priority, Task};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Number of rows in a page when the client does not ask for a size.
+pub const DEFAULT_PAGE_SIZE: i64 = 1000;
+/// Upper bound on the rows in a page, to keep responses bounded.
+pub const MAX_PAGE_SIZE: i64 = 10_000;
+
+/// Tables exposed to BI tools by `GET /api/export/table`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TableEntity {
+    Clients,
+    Tasks,
+}
+
+impl TableEntity {
+    pub const ALL: [TableEntity; 2] = [TableEntity::Clients, TableEntity::Tasks];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TableEntity::Clients => "clients",
+            TableEntity::Tasks => "tasks",
+        }
+    }
+
+    /// Columns of the table, in the order of the values of each row.
+    pub fn columns(self) -> &'static [&'static str] {
+        match self {
+            TableEntity::Clients => ClientRow::COLUMNS,
+            TableEntity::Tasks => TaskRow::COLUMNS,
+        }
+    }
+}
+
+impl FromStr for TableEntity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TableEntity::ALL
+            .into_iter()
+            .find(|entity| entity.as_str() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = TableEntity::ALL.iter().map(|e| e.as_str()).collect();
+                format!(
+                    "Unknown entity '{s}', expected one of: {}.",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+impl<'de> Deserialize<'de> for TableEntity {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Incremental cursor: rows written after the one at position `change_seq`
+/// in the change sequence of the table are still to be pulled.
+///
+/// Every insert or update of a row moves it to the end of the sequence, so a
+/// connector that stores the cursor of its last page and passes it back as
+/// `since` receives exactly the rows added or modified in between. Rows
+/// removed for good (purged tasks) are not reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TableCursor {
+    pub change_seq: i64,
+}
+
+/// Cursors are exchanged as `c<sequence>` tokens, e.g. `c1200`. Clients
+/// should treat them as opaque.
+impl fmt::Display for TableCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "c{}", self.change_seq)
+    }
+}
+
+impl FromStr for TableCursor {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let change_seq = s.strip_prefix('c').ok_or(())?.parse().map_err(|_| ())?;
+        if change_seq < 0 {
+            return Err(());
+        }
+        Ok(TableCursor { change_seq })
+    }
+}
+
+/// A client as exported to BI tools.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ClientRow {
+    pub id: i64,
+    pub name: String,
+    pub color: String,
+    pub created_at: DateTime<Utc>,
+    pub archived_at: Option<DateTime<Utc>>,
+    pub default_priority: Option<i32>,
+    pub default_context: Option<String>,
+    pub change_seq: i64,
+}
+
+impl ClientRow {
+    // New columns are only ever appended, here and below, so that
+    // connectors mapping columns by position keep working.
+    pub const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "color",
+        "created_at",
+        "archived_at",
+        "default_priority",
+        "default_context",
+    ];
+
+    pub fn into_values(self) -> Vec<Value> {
+        vec![
+            self.id.into(),
+            self.name.into(),
+            self.color.into(),
+            time_value(Some(self.created_at)),
+            time_value(self.archived_at),
+            self.default_priority.into(),
+            self.default_context.into(),
+        ]
+    }
+}
+
+/// A task as exported to BI tools. Its client is given by ID, to be joined
+/// with the clients table: renaming a client does not change its tasks.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TaskRow {
+    pub id: i64,
+    pub client_id: i64,
+    pub description: String,
+    pub task_date: NaiveDate,
+    pub created_at: DateTime<Utc>,
+    pub done_at: Option<DateTime<Utc>>,
+    pub priority: Option<i32>,
+    pub energy: Option<String>,
+    pub estimate_minutes: Option<i64>,
+    pub context: Option<String>,
+    pub pin_to_date: bool,
+    pub rollover_count: i64,
+    pub stale_since: Option<DateTime<Utc>>,
+    pub slots_total: i64,
+    pub slots_done: i64,
+    pub external_source: Option<String>,
+    pub external_id: Option<String>,
+    pub rollover_target: Option<String>,
+    pub change_seq: i64,
+}
+
+impl TaskRow {
+    pub const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "client_id",
+        "description",
+        "task_date",
+        "created_at",
+        "done_at",
+        "priority",
+        "energy",
+        "estimate_minutes",
+        "context",
+        "pin_to_date",
+        "rollover_count",
+        "stale_since",
+        "slots_total",
+        "slots_done",
+        "external_source",
+        "external_id",
+        "rollover_target",
+    ];
+
+    pub fn into_values(self) -> Vec<Value> {
+        vec![
+            self.id.into(),
+            self.client_id.into(),
+            self.description.into(),
+            date_value(self.task_date),
+            time_value(Some(self.created_at)),
+            time_value(self.done_at),
+            self.priority.into(),
+            self.energy.into(),
+            self.estimate_minutes.into(),
+            self.context.into(),
+            self.pin_to_date.into(),
+            self.rollover_count.into(),
+            time_value(self.stale_since),
+            self.slots_total.into(),
+            self.slots_done.into(),
+            self.external_source.into(),
+            self.external_id.into(),
+            self.rollover_target.into(),
+        ]
+    }
+}
+
+/// Format of a table page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TableFormat {
+    /// A `TablePage` object.
+    #[default]
+    Json,
+    /// A header line followed by one line per row, the cursor being sent in
+    /// the `X-Next-Cursor` header. Suited to spreadsheet imports.
+    Csv,
+}
+
+/// One page of a table. `next_since` is always set: pass it back as `since`
+/// for the following page, or for the next refresh once `has_more` is false.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct TablePage {
+    pub entity: TableEntity,
+    pub columns: Vec<&'static str>,
+    pub rows: Vec<Vec<Value>>,
+    pub next_since: String,
+    pub has_more: bool,
+}
+
+/// Dates are written as `YYYY-MM-DD`.
+fn date_value(date: NaiveDate) -> Value {
+    Value::String(date.format("%Y-%m-%d").to_string())
+}
+
+/// Times are written in UTC to the second, e.g. `2025-07-14T09:00:00Z`.
+fn time_value(time: Option<DateTime<Utc>>) -> Value {
+    time.map_or(Value::Null, |time| {
+        Value::String(time.to_rfc3339_opts(SecondsFormat::Secs, true))
+    })
+}
+
+/// Writes `page` as CSV, with its column names as the header line. Empty
+/// fields stand for missing values.
+pub fn to_csv(page: &TablePage) -> String {
+    let mut csv = csv_line(page.columns.iter().map(|c| Value::from(*c)), ',');
+    for row in &page.rows {
+        csv.push_str(&csv_line(row.iter().cloned(), ','));
+    }
+    csv
+}
+
+/// Columns of the task CSV export, in the order of `task_csv_line`.
+pub const TASK_CSV_COLUMNS: &[&str] = &[
+    "id",
+    "task_date",
+    "client",
+    "description",
+    "status",
+    "done_at",
+    "priority",
+    "energy",
+    "estimate_minutes",
+    "context",
+    "created_at",
+];
+
+/// Rows of the task CSV export read ahead of the client.
+pub const TASK_CSV_BUFFER: usize = 64;
+
+/// Header line of the task CSV export.
+pub fn task_csv_header(delimiter: char) -> String {
+    csv_line(TASK_CSV_COLUMNS.iter().map(|c| Value::from(*c)), delimiter)
+}
+
+/// Line of `task` in the task CSV export, `status` being `open` or `done`.
+pub fn task_csv_line(task: &Task, delimiter: char) -> String {
+    let status = if task.deleted_at.is_some() {
+        "done"
+    } else {
+        "open"
+    };
+    let values = [
+        task.id.into(),
+        date_value(task.task_date),
+        task.client_name.as_str().into(),
+        task.description.as_str().into(),
+        status.into(),
+        time_value(task.deleted_at),
+        task.priority.map(Priority::level).into(),
+        serde_json::to_value(task.energy).unwrap_or(Value::Null),
+        task.estimate_minutes.into(),
+        task.context.as_deref().into(),
+        time_value(Some(task.created_at)),
+    ];
+    csv_line(values.into_iter(), delimiter)
+}
+
+/// Writes one CSV line, ending with CRLF.
+fn csv_line(values: impl Iterator<Item = Value>, delimiter: char) -> String {
+    let fields: Vec<String> = values.map(|value| csv_field(&value, delimiter)).collect();
+    let mut line = fields.join(delimiter.encode_utf8(&mut [0; 4]));
+    line.push_str("\r\n");
+    line
+}
+
+fn csv_field(value: &Value, delimiter: char) -> String {
+    let text = match value {
+        Value::Null => return String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    if text.contains([delimiter, '"', '\r', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor: TableCursor = "c1200".parse().unwrap();
+        assert_eq!(cursor.change_seq, 1200);
+        assert_eq!(cursor.to_string(), "c1200");
+        assert_eq!(TableCursor::default().to_string(), "c0");
+
+        for invalid in ["1200", "c", "c-1", "tasks.1200"] {
+            assert!(invalid.parse::<TableCursor>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_csv_quotes_what_needs_it() {
+        let page = TablePage {
+            entity: TableEntity::Tasks,
+            columns: vec!["id", "description", "priority", "pin_to_date"],
+            rows: vec![vec![
+                json!(1),
+                json!("Call \"ACME\", then report"),
+                Value::Null,
+                json!(true),
+            ]],
+            next_since: "c1".to_string(),
+            has_more: false,
+        };
+        assert_eq!(
+            to_csv(&page),
+            "id,description,priority,pin_to_date\r\n1,\"Call \"\"ACME\"\", then report\",,true\r\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_quotes_the_delimiter() {
+        let line = csv_line(
+            [json!("Lunch; then call"), json!("1,5"), json!(2)].into_iter(),
+            ';',
+        );
+        assert_eq!(line, "\"Lunch; then call\";1,5;2\r\n");
+        let line = csv_line([json!("a\tb"), json!("c")].into_iter(), '\t');
+        assert_eq!(line, "\"a\tb\"\tc\r\n");
+    }
+}
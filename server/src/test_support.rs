@@ -0,0 +1,342 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::database::{
+    add_holiday_in_db, add_task_slot_in_db, complete_task_slot_in_db, create_client_in_db,
+    create_task_in_db, current_week_range, get_task_by_id_from_db, set_client_archived_in_db,
+    soft_delete_task_in_db,
+};
+use anyhow::{Context, Result};
+use chrono::{Duration, NaiveDate, Weekday};
+use common::{CreateTaskPayload, CreateTaskSlotPayload, Energy, Priority, RolloverTarget, Task};
+use sqlx::SqlitePool;
+
+/// `weekday` of the week `week` weeks after the one of `week_start`.
+fn day_of(week_start: NaiveDate, week: i64, weekday: Weekday) -> NaiveDate {
+    week_start + Duration::weeks(week) + Duration::days(weekday.num_days_from_monday() as i64)
+}
+
+/// A task to seed, placed on a weekday of a week counted from the week of
+/// the scenario: `TaskSpec::new("ACME", "Report").week(-1).on(Weekday::Fri)`
+/// is last Friday's report.
+#[derive(Debug, Clone)]
+pub struct TaskSpec {
+    client_name: String,
+    description: String,
+    week: i64,
+    weekday: Weekday,
+    date: Option<NaiveDate>,
+    priority: Option<Priority>,
+    energy: Option<Energy>,
+    estimate_minutes: Option<i32>,
+    context: Option<String>,
+    pin_to_date: bool,
+    rollover_target: Option<RolloverTarget>,
+    rollover_count: i64,
+    slots: Vec<(Weekday, bool)>,
+    done: bool,
+}
+
+impl TaskSpec {
+    /// An open task on the Monday of the scenario week.
+    pub fn new(client_name: &str, description: impl Into<String>) -> Self {
+        TaskSpec {
+            client_name: client_name.to_string(),
+            description: description.into(),
+            week: 0,
+            weekday: Weekday::Mon,
+            date: None,
+            priority: None,
+            energy: None,
+            estimate_minutes: None,
+            context: None,
+            pin_to_date: false,
+            rollover_target: None,
+            rollover_count: 0,
+            slots: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Weeks after the scenario week, or before it when negative.
+    pub fn week(mut self, offset: i64) -> Self {
+        self.week = offset;
+        self
+    }
+
+    pub fn on(mut self, weekday: Weekday) -> Self {
+        self.weekday = weekday;
+        self
+    }
+
+    /// A fixed day, whatever the scenario week, e.g. `clock::today()`.
+    pub fn on_date(mut self, date: NaiveDate) -> Self {
+        self.date = Some(date);
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    pub fn energy(mut self, energy: Energy) -> Self {
+        self.energy = Some(energy);
+        self
+    }
+
+    pub fn estimate(mut self, minutes: i32) -> Self {
+        self.estimate_minutes = Some(minutes);
+        self
+    }
+
+    pub fn context(mut self, context: &str) -> Self {
+        self.context = Some(context.to_string());
+        self
+    }
+
+    pub fn pinned(mut self) -> Self {
+        self.pin_to_date = true;
+        self
+    }
+
+    pub fn rolls_over_to(mut self, target: RolloverTarget) -> Self {
+        self.rollover_target = Some(target);
+        self
+    }
+
+    /// Counts the task as already postponed `times` times.
+    pub fn rolled_over(mut self, times: i64) -> Self {
+        self.rollover_count = times;
+        self
+    }
+
+    /// Adds a work slot on a weekday of the task's week, done or not.
+    pub fn slot(mut self, weekday: Weekday, done: bool) -> Self {
+        self.slots.push((weekday, done));
+        self
+    }
+
+    /// Marks the task done.
+    pub fn done(mut self) -> Self {
+        self.done = true;
+        self
+    }
+}
+
+/// Declares clients, tasks and holidays spread over weeks, then writes them
+/// to a database in one go:
+///
+/// ```ignore
+/// let scenario = ScenarioBuilder::new()
+///     .client("Idle Client")
+///     .task(TaskSpec::new("ACME", "Report").on(Weekday::Tue).estimate(90).done())
+///     .tasks(5, |i| TaskSpec::new("ACME", format!("Chore {i}")).week(-1))
+///     .seed(&pool)
+///     .await?;
+/// ```
+///
+/// Clients of the tasks are created along with them. Only built with the
+/// `test-support` feature, which the dev-dependencies of the crate enable
+/// for the integration tests.
+#[derive(Debug, Clone)]
+pub struct ScenarioBuilder {
+    week_start: NaiveDate,
+    clients: Vec<(String, bool)>,
+    tasks: Vec<TaskSpec>,
+    holidays: Vec<(i64, Weekday, String)>,
+}
+
+impl Default for ScenarioBuilder {
+    fn default() -> Self {
+        ScenarioBuilder::new()
+    }
+}
+
+impl ScenarioBuilder {
+    /// A scenario around the current week.
+    pub fn new() -> Self {
+        ScenarioBuilder::starting(current_week_range().0)
+    }
+
+    /// A scenario around the week starting on `week_start`, a Monday.
+    pub fn starting(week_start: NaiveDate) -> Self {
+        ScenarioBuilder {
+            week_start,
+            clients: Vec::new(),
+            tasks: Vec::new(),
+            holidays: Vec::new(),
+        }
+    }
+
+    /// A client without any task.
+    pub fn client(mut self, name: &str) -> Self {
+        self.clients.push((name.to_string(), false));
+        self
+    }
+
+    pub fn archived_client(mut self, name: &str) -> Self {
+        self.clients.push((name.to_string(), true));
+        self
+    }
+
+    pub fn task(mut self, task: TaskSpec) -> Self {
+        self.tasks.push(task);
+        self
+    }
+
+    /// `count` tasks, the `i`th one given by `task(i)`.
+    pub fn tasks(mut self, count: usize, task: impl Fn(usize) -> TaskSpec) -> Self {
+        self.tasks.extend((0..count).map(task));
+        self
+    }
+
+    pub fn holiday(mut self, week: i64, weekday: Weekday, name: &str) -> Self {
+        self.holidays.push((week, weekday, name.to_string()));
+        self
+    }
+
+    /// Writes the scenario, the tasks in the order they were declared.
+    pub async fn seed(self, pool: &SqlitePool) -> Result<Scenario> {
+        for (name, archived) in &self.clients {
+            create_client_in_db(pool, name).await?;
+            if *archived {
+                set_client_archived_in_db(pool, name, true).await?;
+            }
+        }
+        for (week, weekday, name) in &self.holidays {
+            add_holiday_in_db(pool, day_of(self.week_start, *week, *weekday), name).await?;
+        }
+
+        let mut tasks = Vec::with_capacity(self.tasks.len());
+        for spec in &self.tasks {
+            let payload = CreateTaskPayload {
+                client_name: spec.client_name.clone(),
+                description: spec.description.clone(),
+                task_date: Some(
+                    spec.date
+                        .unwrap_or_else(|| day_of(self.week_start, spec.week, spec.weekday)),
+                ),
+                priority: spec.priority,
+                energy: spec.energy,
+                estimate_minutes: spec.estimate_minutes,
+                context: spec.context.clone(),
+                pin_to_date: spec.pin_to_date,
+                rollover_target: spec.rollover_target,
+                ..Default::default()
+            };
+            let id = create_task_in_db(pool, payload).await?.id;
+
+            // All the slots are added before any is completed, as completing
+            // the last open one completes the task.
+            let mut done_slots = Vec::new();
+            for (weekday, done) in &spec.slots {
+                let slot = CreateTaskSlotPayload {
+                    slot_date: day_of(self.week_start, spec.week, *weekday),
+                    estimate_minutes: None,
+                };
+                let slot = add_task_slot_in_db(pool, id, &slot)
+                    .await?
+                    .context("Seeded task not found")?;
+                if *done {
+                    done_slots.push(slot.id);
+                }
+            }
+            for slot_id in done_slots {
+                complete_task_slot_in_db(pool, id, slot_id).await?;
+            }
+            if spec.rollover_count > 0 {
+                sqlx::query("UPDATE tasks SET rollover_count = ? WHERE id = ?")
+                    .bind(spec.rollover_count)
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+            if spec.done {
+                soft_delete_task_in_db(pool, id, None).await?;
+            }
+            tasks.push(
+                get_task_by_id_from_db(pool, id)
+                    .await?
+                    .context("Seeded task not found")?,
+            );
+        }
+
+        Ok(Scenario {
+            week_start: self.week_start,
+            tasks,
+        })
+    }
+}
+
+/// What a `ScenarioBuilder` wrote.
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub week_start: NaiveDate,
+    /// As read back after seeding, in the order they were declared.
+    pub tasks: Vec<Task>,
+}
+
+impl Scenario {
+    /// The seeded task with this description.
+    pub fn task(&self, description: &str) -> &Task {
+        self.tasks
+            .iter()
+            .find(|task| task.description == description)
+            .unwrap_or_else(|| panic!("No seeded task named '{description}'"))
+    }
+
+    /// A day of a week counted from the scenario week.
+    pub fn day(&self, week: i64, weekday: Weekday) -> NaiveDate {
+        day_of(self.week_start, week, weekday)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::init_schema;
+
+    #[tokio::test]
+    async fn test_scenario_is_seeded() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        init_schema(&pool).await.unwrap();
+        let monday = NaiveDate::from_ymd_opt(2025, 7, 14).unwrap();
+
+        let scenario = ScenarioBuilder::starting(monday)
+            .archived_client("Former Client")
+            .holiday(0, Weekday::Mon, "Bastille Day")
+            .task(
+                TaskSpec::new("ACME", "Report")
+                    .on(Weekday::Wed)
+                    .estimate(90)
+                    .rolled_over(2)
+                    .done(),
+            )
+            .task(
+                TaskSpec::new("ACME", "Migration")
+                    .week(-1)
+                    .slot(Weekday::Thu, true)
+                    .slot(Weekday::Fri, false),
+            )
+            .tasks(3, |i| TaskSpec::new("Beta", format!("Chore {i}")).week(1))
+            .seed(&pool)
+            .await
+            .unwrap();
+
+        let report = scenario.task("Report");
+        assert_eq!(report.task_date, scenario.day(0, Weekday::Wed));
+        assert_eq!(report.estimate_minutes, Some(90));
+        assert_eq!(report.rollover_count, 2);
+        assert!(report.deleted_at.is_some());
+        let migration = scenario.task("Migration");
+        assert_eq!(migration.task_date, monday - Duration::days(7));
+        assert_eq!((migration.slots_total, migration.slots_done), (2, 1));
+        assert_eq!(scenario.tasks.len(), 5);
+        assert_eq!(
+            scenario.task("Chore 2").task_date,
+            monday + Duration::days(7)
+        );
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::HeavyOpsConfig;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+/// Shared limit on the heavy operations (imports, exports, backups and
+/// reports), so that they cannot take every connection of the small SQLite
+/// pool and stall the board.
+#[derive(Debug)]
+pub struct HeavyOps {
+    permits: Arc<Semaphore>,
+    retry_after: Duration,
+}
+
+impl HeavyOps {
+    pub fn new(config: &HeavyOpsConfig) -> Self {
+        HeavyOps {
+            permits: Arc::new(Semaphore::new(config.max_concurrent)),
+            retry_after: config.retry_after,
+        }
+    }
+
+    /// Number of heavy operations that can start right now.
+    pub fn available(&self) -> usize {
+        self.permits.available_permits()
+    }
+}
+
+/// Middleware running a heavy operation only when a slot is free. Requests
+/// arriving while all slots are taken are answered at once with
+/// `503 Service Unavailable` and a `Retry-After` header rather than queued.
+pub async fn limit_heavy_ops(
+    State(ops): State<Arc<HeavyOps>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Ok(_permit) = ops.permits.clone().try_acquire_owned() else {
+        warn!(
+            "Too many heavy operations in progress, rejecting {} {}",
+            request.method(),
+            request.uri().path()
+        );
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, ops.retry_after.as_secs().to_string())],
+            Json(serde_json::json!({
+                "error": "Too many heavy operations in progress, retry later."
+            })),
+        )
+            .into_response();
+    };
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tokio::sync::Notify;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_saturated_heavy_ops_are_rejected() {
+        let ops = Arc::new(HeavyOps::new(&HeavyOpsConfig {
+            max_concurrent: 1,
+            retry_after: Duration::from_secs(7),
+        }));
+        let release = Arc::new(Notify::new());
+        let handler_release = release.clone();
+        let app = Router::new()
+            .route(
+                "/heavy",
+                get(move || {
+                    let release = handler_release.clone();
+                    async move { release.notified().await }
+                }),
+            )
+            .route_layer(middleware::from_fn_with_state(ops.clone(), limit_heavy_ops));
+        let request = || {
+            Request::builder()
+                .uri("/heavy")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // Act: Start a first operation, taking the only slot
+        let first = tokio::spawn(app.clone().oneshot(request()));
+        while ops.available() > 0 {
+            tokio::task::yield_now().await;
+        }
+
+        // Assert: A second one is turned away with a hint of when to retry
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "7");
+
+        // Assert: The slot is given back once the first one is done
+        release.notify_one();
+        assert_eq!(first.await.unwrap().unwrap().status(), StatusCode::OK);
+        assert_eq!(ops.available(), 1);
+        release.notify_one();
+        let response = app.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::config::TlsConfig;
+use anyhow::{anyhow, Context, Result};
+use parking_lot::RwLock;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio_rustls::rustls::{
+    crypto::ring,
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+    InconsistentKeys, ServerConfig,
+};
+use tokio_rustls::TlsAcceptor;
+use tracing::{info, warn};
+
+/// Reads the certificate chain and private key of `config`, checking that
+/// they belong together.
+pub fn load_certified_key(config: &TlsConfig) -> Result<CertifiedKey> {
+    let certs = CertificateDer::pem_file_iter(&config.cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| {
+            format!(
+                "Failed to read the certificate {}",
+                config.cert_path.display()
+            )
+        })?;
+    if certs.is_empty() {
+        return Err(anyhow!(
+            "No certificate found in {}",
+            config.cert_path.display()
+        ));
+    }
+    let key = PrivateKeyDer::from_pem_file(&config.key_path).with_context(|| {
+        format!(
+            "Failed to read the private key {}",
+            config.key_path.display()
+        )
+    })?;
+    let signing_key = ring::sign::any_supported_type(&key)
+        .with_context(|| format!("Unsupported private key {}", config.key_path.display()))?;
+
+    let certified = CertifiedKey::new(certs, signing_key);
+    match certified.keys_match() {
+        Err(tokio_rustls::rustls::Error::InconsistentKeys(InconsistentKeys::KeyMismatch)) => {
+            Err(anyhow!(
+                "The private key {} does not match the certificate {}",
+                config.key_path.display(),
+                config.cert_path.display()
+            ))
+        }
+        // Keys whose public half cannot be derived are taken on trust.
+        _ => Ok(certified),
+    }
+}
+
+/// Certificate presented to clients. It can be swapped while the server
+/// runs: connections opened afterwards get the new one.
+#[derive(Debug)]
+pub struct CertificateResolver {
+    current: RwLock<Arc<CertifiedKey>>,
+}
+
+impl CertificateResolver {
+    pub fn new(certified: CertifiedKey) -> Self {
+        CertificateResolver {
+            current: RwLock::new(Arc::new(certified)),
+        }
+    }
+
+    pub fn replace(&self, certified: CertifiedKey) {
+        *self.current.write() = Arc::new(certified);
+    }
+}
+
+impl ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.read().clone())
+    }
+}
+
+/// Builds the TLS side of the server from the certificate in `config`.
+/// HTTP/2 is offered through ALPN when enabled.
+pub fn acceptor(
+    config: &TlsConfig,
+    http2_enabled: bool,
+) -> Result<(TlsAcceptor, Arc<CertificateResolver>)> {
+    let resolver = Arc::new(CertificateResolver::new(load_certified_key(config)?));
+    let mut server_config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .context("Failed to set up TLS")?
+        .with_no_client_auth()
+        .with_cert_resolver(resolver.clone());
+    server_config.alpn_protocols = if http2_enabled {
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    } else {
+        vec![b"http/1.1".to_vec()]
+    };
+    Ok((TlsAcceptor::from(Arc::new(server_config)), resolver))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Reloads the certificate whenever its files change, e.g. after a
+/// renewal by certbot, checking every `reload_interval`. A renewal that
+/// cannot be loaded (say, the key not written yet) keeps the previous
+/// certificate and is retried at the next check.
+pub async fn watch_certificate(config: TlsConfig, resolver: Arc<CertificateResolver>) {
+    let Some(interval) = config.reload_interval else {
+        return;
+    };
+    let mut loaded = (modified(&config.cert_path), modified(&config.key_path));
+    loop {
+        tokio::time::sleep(interval).await;
+        let current = (modified(&config.cert_path), modified(&config.key_path));
+        if current == loaded {
+            continue;
+        }
+        match load_certified_key(&config) {
+            Ok(certified) => {
+                resolver.replace(certified);
+                loaded = current;
+                info!(
+                    "Reloaded the TLS certificate {}.",
+                    config.cert_path.display()
+                );
+            }
+            Err(e) => warn!("Keeping the previous TLS certificate: {:?}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    /// Test certificates for `localhost`, issued by the CA of `ca.pem`.
+    pub(crate) fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/tls")
+            .join(name)
+    }
+
+    fn leaf(certified: &CertifiedKey) -> Vec<u8> {
+        certified.end_entity_cert().unwrap().to_vec()
+    }
+
+    #[test]
+    fn test_key_must_match_certificate() {
+        let config = TlsConfig::new(fixture("localhost.pem"), fixture("localhost.key"));
+        assert!(load_certified_key(&config).is_ok());
+
+        let mismatched = TlsConfig::new(fixture("localhost.pem"), fixture("renewed.key"));
+        let error = load_certified_key(&mismatched).unwrap_err().to_string();
+        assert!(error.contains("does not match"), "{error}");
+
+        let missing = TlsConfig::new(fixture("missing.pem"), fixture("localhost.key"));
+        assert!(load_certified_key(&missing).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_renewed_certificate_is_reloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = TlsConfig {
+            reload_interval: Some(Duration::from_millis(20)),
+            ..TlsConfig::new(dir.path().join("cert.pem"), dir.path().join("key.pem"))
+        };
+        fs::copy(fixture("localhost.pem"), &config.cert_path).unwrap();
+        fs::copy(fixture("localhost.key"), &config.key_path).unwrap();
+        let (_, resolver) = acceptor(&config, true).unwrap();
+        let watcher = tokio::spawn(watch_certificate(config.clone(), resolver.clone()));
+
+        // Act: Renew the certificate, as certbot would
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        fs::copy(fixture("renewed.key"), &config.key_path).unwrap();
+        fs::copy(fixture("renewed.pem"), &config.cert_path).unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // Assert: New connections get the renewed certificate
+        let renewed = load_certified_key(&TlsConfig::new(
+            fixture("renewed.pem"),
+            fixture("renewed.key"),
+        ))
+        .unwrap();
+        assert_eq!(leaf(&resolver.current.read()), leaf(&renewed));
+        watcher.abort();
+    }
+}
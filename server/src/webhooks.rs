@@ -0,0 +1,215 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+use crate::auth::SigningSecret;
+use crate::clock;
+use crate::config::WebhooksConfig;
+use crate::database::{self, DueDelivery, WebhookAttempt};
+use anyhow::{anyhow, Context, Result};
+use axum::{
+    body::Bytes,
+    http::{header, Request, StatusCode},
+};
+use common::DeliveryStatus;
+use http_body_util::Full;
+use hyper_rustls::HttpsConnector;
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client},
+    rt::TokioExecutor,
+};
+use sqlx::SqlitePool;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio_rustls::rustls::crypto::ring;
+use tracing::{debug, error, info, warn};
+
+/// How often the worker looks for deliveries due.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Deliveries sent at once by each pass of the worker.
+const BATCH_SIZE: i64 = 20;
+
+/// Longest wait between two attempts at a delivery.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Client posting the deliveries, over HTTP or HTTPS.
+pub type WebhookClient = Client<HttpsConnector<HttpConnector>, Full<Bytes>>;
+
+/// A client trusting the Mozilla root certificates, so that it does not
+/// depend on those installed in the container.
+pub fn client() -> Result<WebhookClient> {
+    let https = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_provider_and_webpki_roots(ring::default_provider())
+        .context("Failed to set up TLS for webhook deliveries")?
+        .https_or_http()
+        .enable_http1()
+        .build();
+    Ok(Client::builder(TokioExecutor::new()).build(https))
+}
+
+/// Delay before the attempt following the `attempt`th failure: the
+/// configured backoff, doubled after each further failure, up to a day.
+fn retry_delay(config: &WebhooksConfig, attempt: u32) -> Duration {
+    config
+        .retry_backoff
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Posts a delivery, returning the status code of the response. The body
+/// is signed with the secret of the webhook, as `X-Webhook-Signature:
+/// sha256=<HMAC-SHA256 in hex>`, for the receiver to check.
+async fn post(
+    client: &WebhookClient,
+    delivery: &DueDelivery,
+    timeout: Duration,
+) -> Result<StatusCode> {
+    let secret: SigningSecret = delivery
+        .secret
+        .parse()
+        .map_err(|e| anyhow!("Invalid webhook secret: {e}"))?;
+    let request = Request::post(&delivery.url)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(
+            header::USER_AGENT,
+            concat!("weekly-task-manager/", env!("CARGO_PKG_VERSION")),
+        )
+        .header("x-webhook-event", delivery.event.as_str())
+        .header("x-webhook-delivery", delivery.id)
+        .header(
+            "x-webhook-signature",
+            format!("sha256={}", secret.sign(&delivery.payload)),
+        )
+        .body(Full::new(Bytes::from(delivery.payload.clone())))
+        .context("Invalid webhook request")?;
+    let response = tokio::time::timeout(timeout, client.request(request))
+        .await
+        .map_err(|_| anyhow!("No response within {} seconds", timeout.as_secs()))?
+        .context("Request failed")?;
+    Ok(response.status())
+}
+
+/// Makes one attempt at a delivery and records its outcome: delivered on a
+/// 2xx response, otherwise retried later until the attempts run out.
+async fn attempt(
+    pool: &SqlitePool,
+    client: &WebhookClient,
+    config: &WebhooksConfig,
+    delivery: DueDelivery,
+) -> Result<()> {
+    let attempts = u32::try_from(delivery.attempts + 1).unwrap_or(u32::MAX);
+    let (response_status, error) = match post(client, &delivery, config.timeout).await {
+        Ok(status) if status.is_success() => (Some(status.as_u16()), None),
+        Ok(status) => (
+            Some(status.as_u16()),
+            Some(format!("Receiver answered {status}")),
+        ),
+        Err(e) => (None, Some(format!("{e:#}"))),
+    };
+    let attempted_at = clock::now();
+    let (status, next_attempt_at) = match &error {
+        None => {
+            debug!(
+                delivery = delivery.id,
+                event = delivery.event.as_str(),
+                "Webhook delivered."
+            );
+            (DeliveryStatus::Delivered, None)
+        }
+        Some(error) if attempts >= config.max_attempts => {
+            warn!(
+                delivery = delivery.id,
+                url = %delivery.url,
+                attempts,
+                "Webhook delivery failed, giving up: {}",
+                error
+            );
+            (DeliveryStatus::Failed, None)
+        }
+        Some(error) => {
+            let delay = retry_delay(config, attempts);
+            info!(
+                delivery = delivery.id,
+                url = %delivery.url,
+                attempts,
+                retry_in_secs = delay.as_secs(),
+                "Webhook delivery failed, retrying: {}",
+                error
+            );
+            let delay = chrono::Duration::seconds(delay.as_secs() as i64);
+            (DeliveryStatus::Pending, Some(attempted_at + delay))
+        }
+    };
+    database::record_webhook_attempt_in_db(
+        pool,
+        WebhookAttempt {
+            delivery_id: delivery.id,
+            attempted_at,
+            status,
+            next_attempt_at,
+            response_status,
+            error: error.as_deref(),
+        },
+    )
+    .await
+}
+
+/// Sends the deliveries due, at once, returning how many were attempted.
+pub async fn deliver_due(
+    pool: &SqlitePool,
+    client: &WebhookClient,
+    config: &WebhooksConfig,
+) -> Result<usize> {
+    let now = clock::now();
+    // Other workers leave the claimed deliveries alone until every attempt
+    // of the batch has had time to time out.
+    let lease = chrono::Duration::seconds(2 * config.timeout.as_secs() as i64);
+    let due = database::claim_webhook_deliveries_in_db(pool, now, now + lease, BATCH_SIZE).await?;
+    let count = due.len();
+    let mut attempts = JoinSet::new();
+    for delivery in due {
+        let (pool, client, config) = (pool.clone(), client.clone(), config.clone());
+        attempts.spawn(async move { attempt(&pool, &client, &config, delivery).await });
+    }
+    while let Some(result) = attempts.join_next().await {
+        result.context("Webhook delivery panicked")??;
+    }
+    Ok(count)
+}
+
+/// Sends the queued webhook deliveries as they fall due, forever.
+pub async fn run_delivery_worker(pool: SqlitePool, config: WebhooksConfig) {
+    let client = match client() {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Webhooks will not be delivered: {:?}", e);
+            return;
+        }
+    };
+    loop {
+        match deliver_due(&pool, &client, &config).await {
+            // More may be due already
+            Ok(count) if count as i64 == BATCH_SIZE => continue,
+            Ok(_) => {}
+            Err(e) => error!("Failed to deliver webhooks: {:?}", e),
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        let config = WebhooksConfig {
+            retry_backoff: Duration::from_secs(30),
+            ..WebhooksConfig::default()
+        };
+        assert_eq!(retry_delay(&config, 1), Duration::from_secs(30));
+        assert_eq!(retry_delay(&config, 3), Duration::from_secs(120));
+        assert_eq!(retry_delay(&config, 80), MAX_RETRY_DELAY);
+    }
+}
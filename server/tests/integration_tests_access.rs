@@ -0,0 +1,379 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+
+// Who may call each API route. Every route of `ROUTES` must have an entry
+// below, so that adding one forces a decision on who may
+// reach it instead of leaving it open by accident.
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+    Router,
+};
+use common::SessionInfo;
+use http_body_util::BodyExt;
+use server::auth::SessionConfig;
+use server::config::Config;
+use server::database::init_schema;
+use server::routes::create_router_with_config;
+use sqlx::SqlitePool;
+use std::fs;
+use std::path::PathBuf;
+use support::{uri, METHODS, ROUTES};
+use tower::ServiceExt; // For `oneshot`
+
+mod support;
+
+/// What it takes to call a route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    /// One of the `API_KEYS`, or a browser session and its CSRF token; open
+    /// to anyone who can reach the server when no key is configured.
+    ApiKey,
+    /// One of the `API_KEYS`, or a browser session and its CSRF token; not
+    /// mounted at all when no key is configured.
+    Admin,
+    /// Anyone, to sign in or out.
+    SignIn,
+    /// Anyone, to read the description of the API.
+    Docs,
+    /// A browser session, without a CSRF token as it only reads.
+    Session,
+    /// The signed token of a share link, in the path.
+    ShareLink,
+    /// One of the `EXPORT_API_TOKENS`.
+    ExportToken,
+    /// Only mounted with `FAULT_INJECTION=true`, in debug builds.
+    FaultInjection,
+    /// Only mounted with `E2E_MODE=true`.
+    E2eMode,
+}
+
+use Access::*;
+
+const ROUTE_ACCESS: &[(&str, Access)] = &[
+    ("/api/tasks", ApiKey),
+    ("/api/tasks/bulk", ApiKey),
+    ("/api/capture", ApiKey),
+    ("/api/tasks/bulk-move", ApiKey),
+    ("/api/batch", ApiKey),
+    ("/api/tasks/today", ApiKey),
+    ("/api/tasks/search", ApiKey),
+    ("/api/tasks/suggest", ApiKey),
+    ("/api/tasks/week", ApiKey),
+    ("/api/ws", ApiKey),
+    ("/api/tasks/{id}", ApiKey),
+    ("/api/tasks/{id}/move", ApiKey),
+    ("/api/tasks/{id}/pin", ApiKey),
+    ("/api/tasks/{id}/rollover-target", ApiKey),
+    ("/api/tasks/{id}/slots", ApiKey),
+    ("/api/tasks/{id}/slots/{slot_id}", ApiKey),
+    ("/api/tasks/{id}/slots/{slot_id}/done", ApiKey),
+    ("/api/tasks/rollover", ApiKey),
+    ("/api/tasks/rollover/preview", ApiKey),
+    ("/api/rollover/history", ApiKey),
+    ("/api/admin/jobs", ApiKey),
+    ("/api/admin/jobs/{job}/pause", ApiKey),
+    ("/api/webhooks", ApiKey),
+    ("/api/webhooks/{id}", ApiKey),
+    ("/api/webhooks/{id}/deliveries", ApiKey),
+    ("/api/admin/dead-letters", ApiKey),
+    ("/api/notifications/test", ApiKey),
+    ("/api/tasks/stale", ApiKey),
+    ("/api/holidays", ApiKey),
+    ("/api/holidays/{day}", ApiKey),
+    ("/api/plan/next-week", ApiKey),
+    ("/api/days/{date}/focus", ApiKey),
+    ("/api/days/{date}/normalize-priorities", ApiKey),
+    ("/api/clients", ApiKey),
+    ("/api/clients/{name}", ApiKey),
+    ("/api/clients/{name}/color", ApiKey),
+    ("/api/clients/{name}/defaults", ApiKey),
+    ("/api/clients/color-changes", ApiKey),
+    ("/api/settings/palette", ApiKey),
+    ("/api/settings/timezone", ApiKey),
+    ("/api/settings/goal", ApiKey),
+    ("/api/stats/goal", ApiKey),
+    ("/api/shares", ApiKey),
+    ("/api/clients/{name}/archive", ApiKey),
+    ("/api/clients/resolve", ApiKey),
+    ("/api/clients/{name}/aliases", ApiKey),
+    ("/api/clients/{name}/aliases/{alias}", ApiKey),
+    ("/api/graphql", ApiKey),
+    ("/api/weeks/{week}/archive", ApiKey),
+    ("/api/import", ApiKey),
+    ("/api/import/{id}/preview", ApiKey),
+    ("/api/import/{id}/commit", ApiKey),
+    ("/api/import/full", Admin),
+    ("/api/export/archive", ApiKey),
+    ("/api/export/full", Admin),
+    ("/api/export/table", ExportToken),
+    ("/api/export/calendar.ics", ExportToken),
+    ("/api/export/tasks.csv", ApiKey),
+    ("/api/sync", ApiKey),
+    ("/api/admin/backup", Admin),
+    ("/api/clients/{name}/stats", ApiKey),
+    ("/api/admin/restore", Admin),
+    ("/api/session", SignIn),
+    ("/api/session/csrf", Session),
+    ("/api/shared/{token}", ShareLink),
+    ("/api/docs", Docs),
+    ("/api/openapi.json", Docs),
+    ("/api/debug/faults", FaultInjection),
+    ("/api/test/reset", E2eMode),
+];
+
+const EXPORT_TOKEN: &str = "matrix-test-token-0001";
+const API_KEY: &str = "matrix-test-api-key-0001";
+
+/// Removes the directory where the client colors are saved.
+fn teardown_test_env_for_file_cleanup() {
+    let db_dir = PathBuf::from("database");
+    if db_dir.exists()
+        && let Err(e) = fs::remove_dir_all(&db_dir)
+    {
+        eprintln!(
+            "Error: Failed to remove test database directory {:?}: {}",
+            db_dir, e
+        );
+    }
+}
+
+async fn app(config: Config) -> Router {
+    let pool = SqlitePool::connect("sqlite::memory:")
+        .await
+        .expect("Failed to connect to in-memory SQLite");
+    init_schema(&pool)
+        .await
+        .expect("Failed to create tasks table in test DB");
+    create_router_with_config(pool, config)
+}
+
+/// How a request proves who sent it.
+#[derive(Debug, Clone, Copy)]
+enum Credentials<'a> {
+    Anonymous,
+    Bearer(&'a str),
+    /// The session cookie, with the CSRF token or not.
+    Cookie(&'a str, Option<&'a str>),
+}
+
+use Credentials::*;
+
+async fn status(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    credentials: Credentials<'_>,
+) -> StatusCode {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("Content-Type", "application/json");
+    match credentials {
+        Anonymous => {}
+        Bearer(token) => request = request.header("Authorization", format!("Bearer {token}")),
+        Cookie(cookie, csrf) => {
+            request = request.header("Cookie", cookie);
+            if let Some(csrf) = csrf {
+                request = request.header("X-CSRF-Token", csrf);
+            }
+        }
+    }
+    let request = request.body(Body::from("{}")).unwrap();
+    app.clone().oneshot(request).await.unwrap().status()
+}
+
+/// Signs in with `API_KEY`, returning the session cookie and CSRF token.
+async fn sign_in(app: &Router) -> (String, String) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/session")
+        .header("Content-Type", "application/json")
+        .body(Body::from(format!(r#"{{"api_key":"{API_KEY}"}}"#)))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let cookie = response.headers()["set-cookie"]
+        .to_str()
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let session: SessionInfo = serde_json::from_slice(&body).unwrap();
+    (cookie, session.csrf_token)
+}
+
+fn is_denied(status: StatusCode) -> bool {
+    status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN
+}
+
+#[test]
+fn test_every_route_has_an_access_entry() {
+    for route in ROUTES {
+        assert!(
+            ROUTE_ACCESS.iter().any(|(known, _)| known == route),
+            "{route} is missing from ROUTE_ACCESS: decide who may call it"
+        );
+    }
+    for (route, _) in ROUTE_ACCESS {
+        assert!(
+            ROUTES.contains(route),
+            "{route} is in ROUTE_ACCESS but no longer in ROUTES"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_route_access_matrix() {
+    let unlocked = app(Config::default()).await;
+    let locked = app(Config {
+        api_keys: API_KEY.parse().unwrap(),
+        sessions: Some(SessionConfig::new(
+            "matrix-test-session-secret-000001".parse().unwrap(),
+        )),
+        share_secret: Some("matrix-test-share-secret-00000001".parse().unwrap()),
+        ..Config::default()
+    })
+    .await;
+    let opened = app(Config {
+        api_keys: API_KEY.parse().unwrap(),
+        export_tokens: EXPORT_TOKEN.parse().unwrap(),
+        fault_injection: true,
+        e2e_mode: true,
+        ..Config::default()
+    })
+    .await;
+    let (cookie, csrf) = sign_in(&locked).await;
+
+    for (route, access) in ROUTE_ACCESS {
+        let uri = uri(route);
+        for method in METHODS {
+            let anonymous = status(&locked, method.clone(), &uri, Anonymous).await;
+            let context = format!("{method} {uri} ({access:?})");
+            match access {
+                ApiKey | Admin => {
+                    assert!(
+                        matches!(
+                            anonymous,
+                            StatusCode::UNAUTHORIZED | StatusCode::METHOD_NOT_ALLOWED
+                        ),
+                        "{context}: {anonymous}"
+                    );
+                    let without_keys = status(&unlocked, method.clone(), &uri, Anonymous).await;
+                    if *access == Admin {
+                        // Never reachable anonymously, not even without keys
+                        assert!(
+                            matches!(
+                                without_keys,
+                                StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED
+                            ),
+                            "{context}: {without_keys}"
+                        );
+                    } else {
+                        assert!(!is_denied(without_keys), "{context}: {without_keys}");
+                    }
+                    let key = status(&locked, method.clone(), &uri, Bearer(API_KEY)).await;
+                    assert!(!is_denied(key), "{context}: {key}");
+                    let wrong_key =
+                        status(&locked, method.clone(), &uri, Bearer("not-the-key-at-all")).await;
+                    assert!(
+                        matches!(
+                            wrong_key,
+                            StatusCode::UNAUTHORIZED | StatusCode::METHOD_NOT_ALLOWED
+                        ),
+                        "{context}: {wrong_key}"
+                    );
+
+                    // Sessions need the CSRF token to change anything
+                    let session =
+                        status(&locked, method.clone(), &uri, Cookie(&cookie, None)).await;
+                    if method == Method::GET {
+                        assert!(!is_denied(session), "{context}: {session}");
+                    } else {
+                        assert!(
+                            matches!(
+                                session,
+                                StatusCode::FORBIDDEN | StatusCode::METHOD_NOT_ALLOWED
+                            ),
+                            "{context}: {session}"
+                        );
+                    }
+                    let with_csrf =
+                        status(&locked, method.clone(), &uri, Cookie(&cookie, Some(&csrf))).await;
+                    assert!(!is_denied(with_csrf), "{context}: {with_csrf}");
+                }
+                SignIn | Docs => assert!(!is_denied(anonymous), "{context}: {anonymous}"),
+                Session => {
+                    assert!(
+                        matches!(
+                            anonymous,
+                            StatusCode::UNAUTHORIZED | StatusCode::METHOD_NOT_ALLOWED
+                        ),
+                        "{context}: {anonymous}"
+                    );
+                    let session =
+                        status(&locked, method.clone(), &uri, Cookie(&cookie, None)).await;
+                    assert!(!is_denied(session), "{context}: {session}");
+                }
+                // Forged links find nothing
+                ShareLink => assert!(
+                    matches!(
+                        anonymous,
+                        StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED
+                    ),
+                    "{context}: {anonymous}"
+                ),
+                ExportToken => {
+                    assert!(
+                        matches!(
+                            anonymous,
+                            StatusCode::UNAUTHORIZED | StatusCode::METHOD_NOT_ALLOWED
+                        ),
+                        "{context}: {anonymous}"
+                    );
+                    // The API keys do not open the export
+                    let key = status(&opened, method.clone(), &uri, Bearer(API_KEY)).await;
+                    assert_ne!(key, StatusCode::OK, "{context}");
+                    let wrong = status(
+                        &opened,
+                        method.clone(),
+                        &uri,
+                        Bearer("not-the-token-at-all"),
+                    )
+                    .await;
+                    assert_ne!(wrong, StatusCode::OK, "{context}");
+                }
+                // Not mounted at all unless enabled
+                FaultInjection | E2eMode => assert!(
+                    matches!(
+                        anonymous,
+                        StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED
+                    ),
+                    "{context}: {anonymous}"
+                ),
+            }
+        }
+    }
+
+    // Assert: Each gated route opens up once its condition is met
+    let export = status(
+        &opened,
+        Method::GET,
+        &uri("/api/export/table"),
+        Bearer(EXPORT_TOKEN),
+    )
+    .await;
+    assert_eq!(export, StatusCode::OK);
+    if cfg!(debug_assertions) {
+        let faults = status(&opened, Method::GET, "/api/debug/faults", Bearer(API_KEY)).await;
+        assert_eq!(faults, StatusCode::OK);
+    }
+    let reset = status(&opened, Method::POST, "/api/test/reset", Anonymous).await;
+    assert!(reset.is_success(), "{reset}");
+
+    teardown_test_env_for_file_cleanup();
+}
@@ -0,0 +1,134 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+
+// The end-to-end test mode freezes the process-wide clock, so it is tested in
+// its own binary to leave the other integration tests on the system clock.
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use chrono::Duration;
+use common::Task;
+use http_body_util::BodyExt; // For `collect`
+use serde_json::json;
+use server::clock;
+use server::config::Config;
+use server::database::init_schema;
+use server::routes::{create_router, create_router_with_config};
+use sqlx::SqlitePool;
+use tokio::sync::Mutex;
+use tower::ServiceExt; // For `oneshot`
+
+// The frozen clock ticks on every reading, so the tests that count its ticks
+// take turns with it.
+static CLOCK: Mutex<()> = Mutex::const_new(());
+
+async fn setup_test_db_pool() -> SqlitePool {
+    let pool = SqlitePool::connect("sqlite::memory:")
+        .await
+        .expect("Failed to connect to in-memory SQLite");
+    init_schema(&pool)
+        .await
+        .expect("Failed to create tasks table in test DB");
+    pool
+}
+
+async fn create_task(app: &Router) -> Task {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/tasks")
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!({ "client_name": "E2E Client", "description": "Reproducible" }).to_string(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_reset_restores_a_reproducible_state() {
+    let _clock = CLOCK.lock().await;
+    let config = Config {
+        e2e_mode: true,
+        ..Default::default()
+    };
+    clock::freeze(config.e2e_start_time);
+    let start = config.e2e_start_time;
+    let app = create_router_with_config(setup_test_db_pool().await, config);
+
+    let reset = || {
+        Request::builder()
+            .method("POST")
+            .uri("/api/test/reset")
+            .body(Body::empty())
+            .unwrap()
+    };
+    let response = app.clone().oneshot(reset()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let first = create_task(&app).await;
+    create_task(&app).await;
+
+    // Act
+    let response = app.clone().oneshot(reset()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let again = create_task(&app).await;
+
+    // Assert: IDs, dates, timestamps and colors are the same as after the first reset
+    assert_eq!(first.id, 1);
+    assert_eq!(first.task_date, start.date_naive());
+    // The clock ticked once when the client was registered
+    assert_eq!(first.created_at, start + Duration::seconds(1));
+    assert_eq!(first.client_color, "#1f77b4");
+    assert_eq!(again.id, first.id);
+    assert_eq!(again.client_id, first.client_id);
+    assert_eq!(again.created_at, first.created_at);
+    assert_eq!(again.client_color, first.client_color);
+
+    // The reset endpoint only exists in end-to-end test mode
+    let app = create_router(setup_test_db_pool().await);
+    let response = app.oneshot(reset()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_task_edits_take_their_version_from_the_frozen_clock() {
+    let _clock = CLOCK.lock().await;
+    let config = Config {
+        e2e_mode: true,
+        ..Default::default()
+    };
+    clock::freeze(config.e2e_start_time);
+    let start = config.e2e_start_time;
+    let app = create_router_with_config(setup_test_db_pool().await, config);
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/test/reset")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let task = create_task(&app).await;
+    assert_eq!(task.created_at, start + Duration::seconds(1));
+    assert_eq!(task.updated_at, task.created_at);
+
+    // Act
+    let request = Request::builder()
+        .method("POST")
+        .uri(format!("/api/tasks/{}/pin", task.id))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+
+    // Assert: the pin is versioned from the frozen clock, one tick after the
+    // webhook event queued by the creation
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let pinned: Task = serde_json::from_slice(&body).unwrap();
+    assert_eq!(pinned.updated_at, start + Duration::seconds(3));
+}
@@ -0,0 +1,232 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+
+// Kept apart from the other suites: the `replace` strategy resets the client
+// colors, which are shared by the whole process.
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use chrono::Weekday;
+use common::{FullExport, FullImportSummary, ImportCounts, ImportStrategy, Priority};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use server::config::Config;
+use server::database::init_schema;
+use server::routes::create_router_with_config;
+use server::test_support::{ScenarioBuilder, TaskSpec};
+use sqlx::SqlitePool;
+use std::fs;
+use std::path::PathBuf;
+use tower::ServiceExt; // For `oneshot`
+
+/// Removes the directory where the client colors are saved.
+fn teardown_test_env_for_file_cleanup() {
+    let db_dir = PathBuf::from("database");
+    if db_dir.exists()
+        && let Err(e) = fs::remove_dir_all(&db_dir)
+    {
+        eprintln!(
+            "Error: Failed to remove test database directory {:?}: {}",
+            db_dir, e
+        );
+    }
+}
+
+/// Key the app is configured with: the full export and import are only
+/// mounted when there is one.
+const API_KEY: &str = "export-key-0123456789";
+
+fn create_router(pool: SqlitePool) -> Router {
+    create_router_with_config(
+        pool,
+        Config {
+            api_keys: API_KEY.parse().unwrap(),
+            ..Config::default()
+        },
+    )
+}
+
+/// Sends a JSON request to the app, returning the status and the body.
+async fn call(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("Content-Type", "application/json")
+        .header("Authorization", format!("Bearer {API_KEY}"))
+        .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn export(app: &Router) -> FullExport {
+    let (status, body) = call(app, "GET", "/api/export/full", None).await;
+    assert_eq!(status, StatusCode::OK);
+    serde_json::from_value(body).unwrap()
+}
+
+async fn import(app: &Router, export: &FullExport, strategy: &str) -> FullImportSummary {
+    let (status, body) = call(
+        app,
+        "POST",
+        &format!("/api/import/full?on_conflict={strategy}"),
+        Some(serde_json::to_value(export).unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    serde_json::from_value(body).unwrap()
+}
+
+fn counts(created: usize, updated: usize, skipped: usize) -> ImportCounts {
+    ImportCounts {
+        created,
+        updated,
+        skipped,
+    }
+}
+
+#[tokio::test]
+async fn test_full_export_round_trip() {
+    // Arrange: A database with a bit of everything
+    let source_pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    init_schema(&source_pool).await.unwrap();
+    let scenario = ScenarioBuilder::new()
+        .task(TaskSpec::new("ACME", "Write report").on(Weekday::Mon))
+        .task(TaskSpec::new("ACME", "Send invoice").week(-1).done())
+        .task(TaskSpec::new("Globex", "Migrate servers").on(Weekday::Tue))
+        .seed(&source_pool)
+        .await
+        .unwrap();
+    let source = create_router(source_pool);
+    let migrate = scenario.task("Migrate servers");
+    for (method, uri, body) in [
+        (
+            "POST",
+            "/api/clients/ACME/aliases",
+            json!({ "alias": "Acme Corp" }),
+        ),
+        (
+            "POST",
+            "/api/holidays",
+            json!({ "day": "2025-12-25", "name": "Christmas" }),
+        ),
+        (
+            "PUT",
+            "/api/settings/goal",
+            json!({ "unit": "tasks", "target": 12 }),
+        ),
+        (
+            "POST",
+            &format!("/api/tasks/{}/slots", migrate.id),
+            json!({ "slot_date": migrate.task_date, "estimate_minutes": 90 }),
+        ),
+    ] {
+        let (status, body) = call(&source, method, uri, Some(body)).await;
+        assert!(status.is_success(), "{uri}: {body}");
+    }
+
+    // Act: Export, then import into an empty database
+    let document = export(&source).await;
+    let target_pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    init_schema(&target_pool).await.unwrap();
+    let target = create_router(target_pool);
+    let summary = import(&target, &document, "skip").await;
+
+    // Assert: The copy exports the same document
+    assert_eq!(summary.clients, counts(2, 0, 0));
+    assert_eq!(summary.tasks, counts(3, 0, 0));
+    let copy = export(&target).await;
+    assert_eq!(copy.version, 1);
+    assert_eq!(copy.clients, document.clients);
+    assert_eq!(copy.tasks, document.tasks);
+    assert_eq!(
+        serde_json::to_value(&copy.settings).unwrap(),
+        serde_json::to_value(&document.settings).unwrap()
+    );
+    assert_eq!(copy.clients[0].aliases, ["Acme Corp"]);
+    let migrate = copy
+        .tasks
+        .iter()
+        .find(|task| task.description == "Migrate servers")
+        .unwrap();
+    assert_eq!(migrate.slots[0].estimate_minutes, Some(90));
+    assert!(copy.tasks.iter().any(|task| task.done_at.is_some()));
+
+    // Act & Assert: Importing again skips or updates what is already there
+    let summary = import(&target, &document, "skip").await;
+    assert_eq!(summary.tasks, counts(0, 0, 3));
+    let mut edited = document.clone();
+    edited.tasks[0].priority = Some(Priority::Critical);
+    edited.clients[0].color = "#ABC".parse().unwrap();
+    let summary = import(&target, &edited, "overwrite").await;
+    assert_eq!(summary.strategy, ImportStrategy::Overwrite);
+    assert_eq!(summary.clients, counts(0, 2, 0));
+    assert_eq!(summary.tasks, counts(0, 3, 0));
+    let copy = export(&target).await;
+    assert_eq!(copy.tasks.len(), 3);
+    assert_eq!(copy.tasks[0].priority, Some(Priority::Critical));
+    assert_eq!(copy.clients[0].color, "#aabbcc");
+
+    // Act & Assert: Replacing leaves only what is imported
+    let mut single = document.clone();
+    single.tasks.retain(|task| task.client_name == "Globex");
+    let summary = import(&target, &single, "replace").await;
+    assert_eq!(summary.clients, counts(2, 0, 0));
+    assert_eq!(summary.tasks, counts(1, 0, 0));
+    let copy = export(&target).await;
+    assert_eq!(copy.tasks, single.tasks);
+
+    teardown_test_env_for_file_cleanup();
+}
+
+#[tokio::test]
+async fn test_full_import_rejects_invalid_documents() {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    init_schema(&pool).await.unwrap();
+    ScenarioBuilder::new()
+        .task(TaskSpec::new("ACME", "Write report"))
+        .seed(&pool)
+        .await
+        .unwrap();
+    let app = create_router(pool);
+    let document = export(&app).await;
+
+    let mut newer = serde_json::to_value(&document).unwrap();
+    newer["version"] = json!(99);
+    let mut orphan = serde_json::to_value(&document).unwrap();
+    orphan["tasks"][0]["client_name"] = json!("Initech");
+    let mut miscolored = serde_json::to_value(&document).unwrap();
+    miscolored["clients"][0]["color"] = json!("blue");
+    for payload in [newer, orphan.clone()] {
+        let (status, body) = call(&app, "POST", "/api/import/full", Some(payload)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    }
+    // Malformed colors are refused as the document is read
+    let (status, _) = call(&app, "POST", "/api/import/full", Some(miscolored)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (_, body) = call(&app, "POST", "/api/import/full", Some(orphan)).await;
+    assert_eq!(body["errors"][0]["index"], 0, "{body}");
+    let (status, _) = call(
+        &app,
+        "POST",
+        "/api/import/full?on_conflict=merge",
+        Some(serde_json::to_value(&document).unwrap()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Assert: Nothing was changed
+    let (_, tasks) = call(&app, "GET", "/api/tasks", None).await;
+    assert_eq!(tasks.as_array().unwrap().len(), 1);
+
+    teardown_test_env_for_file_cleanup();
+}
@@ -0,0 +1,324 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+
+// GraphQL is checked through `/api/graphql` only; the resolvers share their
+// database functions and checks with the REST handlers tested elsewhere.
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use chrono::Weekday;
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use server::clock;
+use server::database::init_schema;
+use server::routes::create_router;
+use server::test_support::{Scenario, ScenarioBuilder, TaskSpec};
+use sqlx::SqlitePool;
+use std::fs;
+use std::path::PathBuf;
+use tower::ServiceExt; // For `oneshot`
+
+/// Removes the directory where the client colors are saved.
+fn teardown_test_env_for_file_cleanup() {
+    let db_dir = PathBuf::from("database");
+    if db_dir.exists()
+        && let Err(e) = fs::remove_dir_all(&db_dir)
+    {
+        eprintln!(
+            "Error: Failed to remove test database directory {:?}: {}",
+            db_dir, e
+        );
+    }
+}
+
+async fn app(scenario: ScenarioBuilder) -> (Router, Scenario) {
+    let pool = SqlitePool::connect("sqlite::memory:")
+        .await
+        .expect("Failed to connect to in-memory SQLite");
+    init_schema(&pool)
+        .await
+        .expect("Failed to create tasks table in test DB");
+    let scenario = scenario.seed(&pool).await.unwrap();
+    (create_router(pool), scenario)
+}
+
+/// Posts a GraphQL request, returning its `data` and its `errors`.
+async fn graphql(app: &Router, query: &str, variables: Value) -> (Value, Value) {
+    let request = Request::post("/api/graphql")
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!({ "query": query, "variables": variables }).to_string(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let mut body: Value = serde_json::from_slice(&bytes).unwrap();
+    (body["data"].take(), body["errors"].take())
+}
+
+#[tokio::test]
+async fn test_tasks_are_queried_with_their_client() {
+    let (app, scenario) = app(ScenarioBuilder::new()
+        .task(TaskSpec::new("ACME", "Write report").on(Weekday::Tue))
+        .task(TaskSpec::new("ACME", "Send invoice").on(Weekday::Mon))
+        .task(
+            TaskSpec::new("ACME", "Already done")
+                .on(Weekday::Mon)
+                .done(),
+        )
+        .task(TaskSpec::new("Globex", "Call back").on(Weekday::Mon))
+        .task(TaskSpec::new("ACME", "Next week").week(1)))
+    .await;
+
+    // Act
+    let (data, errors) = graphql(
+        &app,
+        "query($client: String) {
+            tasks(filter: { client: $client }) {
+                description taskDate done client { name color }
+            }
+        }",
+        json!({ "client": "ACME" }),
+    )
+    .await;
+
+    // Assert: The open tasks of the client this week, by date
+    assert!(errors.is_null(), "{errors}");
+    let tasks = data["tasks"].as_array().unwrap();
+    let descriptions: Vec<&str> = tasks
+        .iter()
+        .map(|task| task["description"].as_str().unwrap())
+        .collect();
+    assert_eq!(descriptions, ["Send invoice", "Write report"]);
+    assert_eq!(
+        tasks[0]["taskDate"],
+        scenario.day(0, Weekday::Mon).to_string()
+    );
+    assert_eq!(tasks[0]["done"], false);
+    assert_eq!(tasks[0]["client"]["name"], "ACME");
+    assert!(tasks[0]["client"]["color"]
+        .as_str()
+        .unwrap()
+        .starts_with('#'));
+
+    // Act & Assert: Done tasks and other weeks when asked for, through the client
+    let (data, errors) = graphql(
+        &app,
+        "query($from: NaiveDate, $to: NaiveDate) {
+            client(name: \"ACME\") {
+                tasks(from: $from, to: $to, status: ALL) { description }
+            }
+        }",
+        json!({
+            "from": scenario.day(0, Weekday::Mon),
+            "to": scenario.day(1, Weekday::Sun),
+        }),
+    )
+    .await;
+    assert!(errors.is_null(), "{errors}");
+    assert_eq!(data["client"]["tasks"].as_array().unwrap().len(), 4);
+
+    // Act & Assert: A reversed range is refused as a bad request
+    let (_, errors) = graphql(
+        &app,
+        "query($from: NaiveDate, $to: NaiveDate) {
+            tasks(filter: { from: $from, to: $to }) { id }
+        }",
+        json!({
+            "from": scenario.day(0, Weekday::Sun),
+            "to": scenario.day(0, Weekday::Mon),
+        }),
+    )
+    .await;
+    assert_eq!(errors[0]["extensions"]["status"], 400);
+
+    teardown_test_env_for_file_cleanup();
+}
+
+#[tokio::test]
+async fn test_tasks_are_created_updated_and_deleted() {
+    let (app, scenario) = app(ScenarioBuilder::new()).await;
+    let today = clock::today();
+
+    // Act: Create
+    let (data, errors) = graphql(
+        &app,
+        "mutation {
+            createTask(input: { clientName: \"ACME\", description: \"Write report\", priority: 2 }) {
+                id taskDate priority pinToDate
+            }
+        }",
+        json!({}),
+    )
+    .await;
+
+    // Assert
+    assert!(errors.is_null(), "{errors}");
+    let task = &data["createTask"];
+    assert_eq!(task["taskDate"], today.to_string());
+    assert_eq!(task["priority"], 2);
+    let id = task["id"].as_i64().unwrap();
+
+    // Act & Assert: Invalid tasks are refused as by the REST route
+    let (data, errors) = graphql(
+        &app,
+        "mutation {
+            createTask(input: { clientName: \"ACME\", description: \"\" }) { id }
+        }",
+        json!({}),
+    )
+    .await;
+    assert!(data.is_null());
+    assert_eq!(errors[0]["extensions"]["status"], 400);
+
+    // Act & Assert: Moved, pinned and given a rollover target at once
+    let sunday = scenario.day(0, Weekday::Sun);
+    let (data, errors) = graphql(
+        &app,
+        "mutation($id: Int!, $date: NaiveDate) {
+            updateTask(id: $id, input: { taskDate: $date, pinToDate: true, rolloverTarget: MONDAY }) {
+                taskDate pinToDate rolloverTarget
+            }
+        }",
+        json!({ "id": id, "date": sunday }),
+    )
+    .await;
+    assert!(errors.is_null(), "{errors}");
+    assert_eq!(
+        data["updateTask"],
+        json!({ "taskDate": sunday.to_string(), "pinToDate": true, "rolloverTarget": "MONDAY" })
+    );
+
+    // Act & Assert: A null target clears it, the other fields are left alone
+    let (data, errors) = graphql(
+        &app,
+        "mutation($id: Int!) {
+            updateTask(id: $id, input: { rolloverTarget: null }) { pinToDate rolloverTarget }
+        }",
+        json!({ "id": id }),
+    )
+    .await;
+    assert!(errors.is_null(), "{errors}");
+    assert_eq!(
+        data["updateTask"],
+        json!({ "pinToDate": true, "rolloverTarget": null })
+    );
+
+    // Act & Assert: Deleted, which marks it done
+    let (data, errors) = graphql(
+        &app,
+        "mutation($id: Int!) { deleteTask(id: $id) }",
+        json!({ "id": id }),
+    )
+    .await;
+    assert!(errors.is_null(), "{errors}");
+    assert_eq!(data["deleteTask"], true);
+    let (data, _) = graphql(
+        &app,
+        "query($id: Int!) { task(id: $id) { done } }",
+        json!({ "id": id }),
+    )
+    .await;
+    assert_eq!(data["task"]["done"], true);
+
+    // Act & Assert: Done tasks can be neither moved nor deleted again
+    let (_, errors) = graphql(
+        &app,
+        "mutation($id: Int!, $date: NaiveDate) {
+            updateTask(id: $id, input: { taskDate: $date }) { id }
+        }",
+        json!({ "id": id, "date": sunday }),
+    )
+    .await;
+    assert_eq!(errors[0]["extensions"]["status"], 409);
+    let (_, errors) = graphql(
+        &app,
+        "mutation($id: Int!) { deleteTask(id: $id) }",
+        json!({ "id": id }),
+    )
+    .await;
+    assert_eq!(errors[0]["extensions"]["status"], 404);
+
+    teardown_test_env_for_file_cleanup();
+}
+
+#[tokio::test]
+async fn test_a_failed_update_leaves_the_task_unchanged() {
+    let pool = SqlitePool::connect("sqlite::memory:")
+        .await
+        .expect("Failed to connect to in-memory SQLite");
+    init_schema(&pool)
+        .await
+        .expect("Failed to create tasks table in test DB");
+    let scenario = ScenarioBuilder::new()
+        .task(TaskSpec::new("ACME", "Write report").on(Weekday::Mon))
+        .seed(&pool)
+        .await
+        .unwrap();
+    let task = scenario.task("Write report");
+    // The last change, to the rollover target, fails after the move and the pin
+    sqlx::query(
+        "CREATE TRIGGER refuse_target BEFORE UPDATE OF rollover_target ON tasks
+         BEGIN SELECT RAISE(ABORT, 'refused'); END",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let app = create_router(pool.clone());
+
+    // Act
+    let (data, errors) = graphql(
+        &app,
+        "mutation($id: Int!, $date: NaiveDate) {
+            updateTask(id: $id, input: { taskDate: $date, pinToDate: true, rolloverTarget: MONDAY }) {
+                id
+            }
+        }",
+        json!({ "id": task.id, "date": scenario.day(0, Weekday::Fri) }),
+    )
+    .await;
+
+    // Assert: Neither the move nor the pin was kept
+    assert!(data.is_null());
+    assert_eq!(errors[0]["extensions"]["status"], 500);
+    let (data, errors) = graphql(
+        &app,
+        "query($id: Int!) { task(id: $id) { taskDate pinToDate rolloverTarget } }",
+        json!({ "id": task.id }),
+    )
+    .await;
+    assert!(errors.is_null(), "{errors}");
+    assert_eq!(
+        data["task"],
+        json!({
+            "taskDate": task.task_date.to_string(),
+            "pinToDate": false,
+            "rolloverTarget": null,
+        })
+    );
+
+    teardown_test_env_for_file_cleanup();
+}
+
+#[tokio::test]
+async fn test_rollover_mutation() {
+    let today = clock::today();
+    let (app, _) = app(ScenarioBuilder::new()
+        .task(TaskSpec::new("ACME", "Still open").on_date(today))
+        .task(TaskSpec::new("ACME", "Pinned").on_date(today).pinned()))
+    .await;
+
+    // Act
+    let (data, errors) = graphql(&app, "mutation { rollover }", json!({})).await;
+
+    // Assert: Only the task not pinned to its day is moved
+    assert!(errors.is_null(), "{errors}");
+    assert_eq!(data["rollover"], 1);
+
+    teardown_test_env_for_file_cleanup();
+}
@@ -0,0 +1,188 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+
+// Live connections need a real server to upgrade to a WebSocket, unlike the
+// other integration tests which call the router directly.
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use futures_util::{SinkExt, StreamExt};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use server::database::init_schema;
+use server::routes::create_router;
+use server::test_support::{ScenarioBuilder, TaskSpec};
+use sqlx::SqlitePool;
+use std::fs;
+use std::path::PathBuf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tower::ServiceExt; // For `oneshot`
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Removes the directory where the client colors are saved.
+fn teardown_test_env_for_file_cleanup() {
+    let db_dir = PathBuf::from("database");
+    if db_dir.exists()
+        && let Err(e) = fs::remove_dir_all(&db_dir)
+    {
+        eprintln!(
+            "Error: Failed to remove test database directory {:?}: {}",
+            db_dir, e
+        );
+    }
+}
+
+/// Serves `app` on a free port, returning the address of its live endpoint.
+async fn spawn_server(app: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("ws://{addr}/api/ws")
+}
+
+/// Next JSON message of the server, skipping anything else.
+async fn next_message(socket: &mut Socket) -> Value {
+    loop {
+        let message = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+            .await
+            .expect("No message from the server")
+            .unwrap()
+            .unwrap();
+        if let Message::Text(text) = message {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+async fn send_command(socket: &mut Socket, command: Value) {
+    socket
+        .send(Message::Text(command.to_string().into()))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_live_updates_and_commands() {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    init_schema(&pool).await.unwrap();
+    let scenario = ScenarioBuilder::new()
+        .task(TaskSpec::new("ACME", "Seeded").on_date(server::clock::today()))
+        .seed(&pool)
+        .await
+        .unwrap();
+    let app = create_router(pool);
+    let url = spawn_server(app.clone()).await;
+
+    // Act: Open the connection
+    let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .unwrap();
+    let ready = next_message(&mut socket).await;
+    assert_eq!(ready["type"], "ready");
+    assert_eq!(ready["columns"]["tasks"][0], "id");
+
+    // Assert: A task created with a request is pushed
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/tasks")
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!({ "client_name": "ACME", "description": "Pushed" }).to_string(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let pushed: Value = serde_json::from_slice(&body).unwrap();
+    let changes = next_message(&mut socket).await;
+    assert_eq!(changes["type"], "changes");
+    let tasks = changes["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0][2], "Pushed");
+
+    // Act: Complete the seeded task over the connection
+    let seeded = scenario.task("Seeded").id;
+    send_command(
+        &mut socket,
+        json!({ "command": "complete", "task_id": seeded }),
+    )
+    .await;
+
+    // Assert: The command is acknowledged, then its effect pushed
+    let done = next_message(&mut socket).await;
+    assert_eq!(done["type"], "done");
+    assert_eq!(done["command"]["task_id"], seeded);
+    let changes = next_message(&mut socket).await;
+    assert_eq!(changes["type"], "changes");
+    assert_eq!(changes["tasks"][0][0], seeded);
+
+    // Assert: Commands are checked like the matching routes, a stale
+    // version included
+    for command in ["complete", "move"] {
+        send_command(
+            &mut socket,
+            json!({
+                "command": command,
+                "task_id": pushed["id"],
+                "task_date": server::clock::today(),
+                "updated_at": "2025-01-01T00:00:00Z",
+            }),
+        )
+        .await;
+        let error = next_message(&mut socket).await;
+        assert_eq!(error["type"], "error", "{command}");
+        assert_eq!(
+            error["error"],
+            format!(
+                "Task with ID {} was changed since it was read; reload it and try again.",
+                pushed["id"]
+            )
+        );
+    }
+    // Assert: The version last read is accepted
+    send_command(
+        &mut socket,
+        json!({
+            "command": "move",
+            "task_id": pushed["id"],
+            "task_date": server::clock::today(),
+            "updated_at": pushed["updated_at"],
+        }),
+    )
+    .await;
+    assert_eq!(next_message(&mut socket).await["type"], "done");
+    assert_eq!(next_message(&mut socket).await["type"], "changes");
+    send_command(
+        &mut socket,
+        json!({ "command": "move", "task_id": 999, "task_date": server::clock::today() }),
+    )
+    .await;
+    let error = next_message(&mut socket).await;
+    assert_eq!(error["type"], "error");
+    assert_eq!(error["error"], "Task with ID 999 not found.");
+    send_command(&mut socket, json!({ "command": "archive" })).await;
+    let error = next_message(&mut socket).await;
+    assert!(error["error"]
+        .as_str()
+        .unwrap()
+        .starts_with("Invalid command"));
+
+    // Assert: A client coming back catches up from its cursor
+    let (mut late, _) = tokio_tungstenite::connect_async(format!(
+        "{url}?since={}",
+        ready["cursor"].as_str().unwrap()
+    ))
+    .await
+    .unwrap();
+    assert_eq!(next_message(&mut late).await["type"], "ready");
+    let missed = next_message(&mut late).await;
+    assert_eq!(missed["tasks"].as_array().unwrap().len(), 2);
+
+    teardown_test_env_for_file_cleanup();
+}
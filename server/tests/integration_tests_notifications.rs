@@ -0,0 +1,364 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+
+// Notifications are posted to receivers standing in for Slack and for an
+// SMTP server on local ports, by running the jobs directly rather than
+// waiting for their schedule.
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    routing::post,
+    Json, Router,
+};
+use chrono::Days;
+use common::{ChannelCheck, GoalUnit, Priority};
+use http_body_util::BodyExt;
+use serde_json::Value;
+use server::clock;
+use server::config::{
+    parse_cron, Config, DigestPeriod, EmailConfig, NotificationsConfig, RolloverPolicy, SmtpTls,
+};
+use server::database::{self, init_schema};
+use server::email::{EmailDigestJob, Mailer};
+use server::jobs::Job;
+use server::notify::{Channel, DigestJob, Notifier, Template, Templates};
+use server::plan;
+use server::routes::create_router_with_config;
+use server::scheduler::RolloverJob;
+use server::summary::Locale;
+use server::test_support::{ScenarioBuilder, TaskSpec};
+use server::webhooks;
+use sqlx::SqlitePool;
+use std::fs;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tower::ServiceExt; // For `oneshot`
+
+/// Removes the directory where the client colors are saved.
+fn teardown_test_env_for_file_cleanup() {
+    let db_dir = PathBuf::from("database");
+    if db_dir.exists()
+        && let Err(e) = fs::remove_dir_all(&db_dir)
+    {
+        eprintln!(
+            "Error: Failed to remove test database directory {:?}: {}",
+            db_dir, e
+        );
+    }
+}
+
+/// A notifier posting to a local receiver, which passes on the texts.
+async fn notifier(templates: Templates) -> (Notifier, mpsc::UnboundedReceiver<String>) {
+    let (url, received) = slack_receiver().await;
+    let channel = Channel::Slack {
+        url,
+        client: webhooks::client().unwrap(),
+    };
+    (Notifier::new(vec![channel], templates), received)
+}
+
+/// A local receiver standing in for Slack, which passes on the texts.
+async fn slack_receiver() -> (String, mpsc::UnboundedReceiver<String>) {
+    let (sender, received) = mpsc::unbounded_channel();
+    let receiver = Router::new().route(
+        "/slack",
+        post(move |Json(body): Json<Value>| async move {
+            sender
+                .send(body["text"].as_str().unwrap().to_string())
+                .unwrap();
+            StatusCode::OK
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, receiver).await });
+    (format!("http://{addr}/slack"), received)
+}
+
+/// `AUTH PLAIN` answer of the user `digest` with the password `s3cret`.
+const SMTP_CREDENTIALS: &str = "AGRpZ2VzdABzM2NyZXQ=";
+
+/// A bare SMTP server on a local port, taking mail from the user `digest`
+/// signed in with the password `s3cret`. It passes on the data of each
+/// message.
+async fn smtp_server() -> (u16, mpsc::UnboundedReceiver<String>) {
+    let (sender, received) = mpsc::unbounded_channel();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                writer.write_all(b"220 localhost ESMTP\r\n").await?;
+                while let Some(line) = lines.next_line().await? {
+                    let reply = match line.split(' ').next().unwrap().to_uppercase().as_str() {
+                        "EHLO" => "250-localhost\r\n250 AUTH PLAIN",
+                        "AUTH" if line.ends_with(SMTP_CREDENTIALS) => "235 Authenticated",
+                        "AUTH" => "535 Invalid credentials",
+                        "DATA" => {
+                            writer.write_all(b"354 Go ahead\r\n").await?;
+                            let mut data = String::new();
+                            while let Some(line) = lines.next_line().await? {
+                                if line == "." {
+                                    break;
+                                }
+                                data.push_str(&line);
+                                data.push('\n');
+                            }
+                            sender.send(data).unwrap();
+                            "250 Queued"
+                        }
+                        "QUIT" => {
+                            writer.write_all(b"221 Bye\r\n").await?;
+                            break;
+                        }
+                        _ => "250 OK",
+                    };
+                    writer.write_all(format!("{reply}\r\n").as_bytes()).await?;
+                }
+                std::io::Result::Ok(())
+            });
+        }
+    });
+    (port, received)
+}
+
+fn email_config(port: u16, password: &str) -> EmailConfig {
+    EmailConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        tls: SmtpTls::None,
+        credentials: Some(("digest".to_string(), password.to_string())),
+        from: "Tasks <tasks@example.com>".parse().unwrap(),
+        recipients: vec!["team@example.com".parse().unwrap()],
+        digest_schedule: parse_cron("0 8 * * 1").unwrap(),
+        digest_period: DigestPeriod::Week,
+        digest_locale: Locale::En,
+    }
+}
+
+#[tokio::test]
+async fn test_morning_digest() {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    init_schema(&pool).await.unwrap();
+    let today = clock::today();
+    ScenarioBuilder::new()
+        .task(
+            TaskSpec::new("ACME", "Write report")
+                .on_date(today)
+                .priority(Priority::Critical),
+        )
+        .task(TaskSpec::new("Globex", "Call back").on_date(today))
+        .task(TaskSpec::new("ACME", "Already done").on_date(today).done())
+        .seed(&pool)
+        .await
+        .unwrap();
+    let templates = Templates {
+        digest_task: Template::parse(
+            "- [{priority}] {description} ({client})",
+            Templates::DIGEST_TASK_NAMES,
+        )
+        .unwrap(),
+        ..Templates::default()
+    };
+    let (notifier, mut received) = notifier(templates).await;
+    let job = DigestJob {
+        notifier,
+        schedule: NotificationsConfig::default().digest_schedule,
+    };
+
+    // Act
+    let summary = job.run(&pool).await.unwrap();
+
+    // Assert: The open tasks of the day are posted, one line each
+    assert_eq!(summary, format!("Sent the digest of 2 tasks of {today}."));
+    assert_eq!(
+        received.recv().await.unwrap(),
+        format!("*Tasks for {today}* (2)\n- [1] Write report (ACME)\n- [] Call back (Globex)")
+    );
+
+    // Act & Assert: With a weekly goal, its progress follows the tasks
+    database::set_weekly_goal_in_db(&pool, Some((GoalUnit::Tasks, 4.0)))
+        .await
+        .unwrap();
+    job.run(&pool).await.unwrap();
+    let text = received.recv().await.unwrap();
+    assert!(
+        text.contains("\nWeekly goal: 1 of 4 tasks done, "),
+        "{text}"
+    );
+
+    teardown_test_env_for_file_cleanup();
+}
+
+#[tokio::test]
+async fn test_large_rollover_is_notified() {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    init_schema(&pool).await.unwrap();
+    let today = clock::today();
+    ScenarioBuilder::new()
+        .tasks(3, |i| {
+            TaskSpec::new("ACME", format!("Task {i}")).on_date(today)
+        })
+        .seed(&pool)
+        .await
+        .unwrap();
+    let (notifier, mut received) = notifier(Templates::default()).await;
+    let job = |notify_above| RolloverJob {
+        schedule: Default::default(),
+        policy: RolloverPolicy::NextDay,
+        stale_after: None,
+        notifier: notifier.clone(),
+        notify_above,
+    };
+
+    // Act & Assert: Small rollovers are not notified
+    job(3).run(&pool).await.unwrap();
+    assert!(received.try_recv().is_err());
+
+    // Act & Assert: Larger ones are
+    ScenarioBuilder::new()
+        .tasks(3, |i| {
+            TaskSpec::new("ACME", format!("Later {i}")).on_date(today)
+        })
+        .seed(&pool)
+        .await
+        .unwrap();
+    job(2).run(&pool).await.unwrap();
+    assert_eq!(
+        received.recv().await.unwrap(),
+        format!("The rollover moved 3 open tasks on from {today}.")
+    );
+
+    teardown_test_env_for_file_cleanup();
+}
+
+#[tokio::test]
+async fn test_weekly_email_digest() {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    init_schema(&pool).await.unwrap();
+    let today = clock::today();
+    let last_week = today - Days::new(7);
+    ScenarioBuilder::new()
+        .task(TaskSpec::new("Globex", "Call back").on_date(today))
+        .task(TaskSpec::new("ACME", "Write report").on_date(today))
+        .task(TaskSpec::new("ACME", "Already done").on_date(today).done())
+        .task(
+            TaskSpec::new("ACME", "Done last week")
+                .on_date(last_week)
+                .done(),
+        )
+        .seed(&pool)
+        .await
+        .unwrap();
+    let week = last_week.format("%G-W%V").to_string();
+    let range = plan::parse_iso_week(&week).unwrap();
+    database::archive_week_in_db(&pool, &week, range, false)
+        .await
+        .unwrap();
+    let (port, mut received) = smtp_server().await;
+    let config = email_config(port, "s3cret");
+    let job = EmailDigestJob {
+        mailer: Mailer::from_config(&config).unwrap(),
+        schedule: config.digest_schedule.clone(),
+        period: config.digest_period,
+        locale: config.digest_locale,
+    };
+
+    // Act
+    let summary = job.run(&pool).await.unwrap();
+
+    // Assert: The open tasks are sent, by client
+    assert_eq!(summary, "Sent the email digest of 2 tasks to 1 recipients.");
+    let message = received.recv().await.unwrap();
+    assert!(message.contains("To: team@example.com"), "{message}");
+    assert!(
+        message.contains("Subject: Open tasks for the week of"),
+        "{message}"
+    );
+    assert!(message.contains("Content-Type: text/html"), "{message}");
+    let acme = message.find("ACME (1)").unwrap();
+    let globex = message.find("Globex (1)").unwrap();
+    assert!(acme < globex, "{message}");
+    assert!(!message.contains("Already done"), "{message}");
+    // Assert: Opened by the summary of the week before, once archived
+    assert!(message.contains("<h2>Last week</h2>"), "{message}");
+    assert!(
+        message.contains("You completed 1 task for 1 client."),
+        "{message}"
+    );
+
+    teardown_test_env_for_file_cleanup();
+}
+
+#[tokio::test]
+async fn test_notification_channels_are_tested() {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    init_schema(&pool).await.unwrap();
+    let (slack_url, mut slack) = slack_receiver().await;
+    let (port, _) = smtp_server().await;
+    let test = |config: Config| {
+        let app = create_router_with_config(pool.clone(), config);
+        async move {
+            let request = Request::post("/api/notifications/test")
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            (status, body)
+        }
+    };
+
+    // Act & Assert: Nothing to test without any channel
+    let (status, _) = test(Config::default()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Act & Assert: Slack is sent a test message, the SMTP server signed in to
+    let mut config = Config {
+        email: Some(email_config(port, "s3cret")),
+        ..Config::default()
+    };
+    config.notifications.slack_webhook_url = Some(slack_url);
+    let (status, body) = test(config.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    let checks: Vec<ChannelCheck> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        checks,
+        [
+            ChannelCheck {
+                channel: "slack".to_string(),
+                ok: true,
+                error: None,
+            },
+            ChannelCheck {
+                channel: "email".to_string(),
+                ok: true,
+                error: None,
+            },
+        ]
+    );
+    assert_eq!(
+        slack.recv().await.unwrap(),
+        "Test notification from the weekly task manager."
+    );
+
+    // Act & Assert: Wrong credentials are reported
+    config.email = Some(email_config(port, "wrong"));
+    let (status, body) = test(config).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    let checks: Vec<ChannelCheck> = serde_json::from_slice(&body).unwrap();
+    assert!(checks[0].ok);
+    assert!(!checks[1].ok);
+    let error = checks[1].error.as_deref().unwrap();
+    assert!(error.contains("Invalid credentials"), "{error}");
+
+    teardown_test_env_for_file_cleanup();
+}
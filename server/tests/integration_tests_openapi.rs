@@ -0,0 +1,482 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+
+// The OpenAPI description is checked against the router itself, so that a
+// route added, removed or given another method without updating its
+// annotation fails here rather than in the generated clients. The responses
+// of the routes are checked against the schemas it gives them too.
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use common::FULL_EXPORT_VERSION;
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use server::auth::SessionConfig;
+use server::config::Config;
+use server::database::init_schema;
+use server::routes::create_router_with_config;
+use server::test_support::{ScenarioBuilder, TaskSpec};
+use sqlx::SqlitePool;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use support::{uri, METHODS, ROUTES};
+use tower::ServiceExt; // For `oneshot`
+
+mod support;
+
+/// Routes left out of the description: the description itself, GraphQL
+/// which describes itself, and the routes only mounted for tests.
+const UNDOCUMENTED: &[&str] = &[
+    "/api/docs",
+    "/api/openapi.json",
+    "/api/graphql",
+    "/api/debug/faults",
+    "/api/test/reset",
+];
+
+const API_KEY: &str = "openapi-test-api-key-0001";
+
+/// Removes the directory where the client colors are saved.
+fn teardown_test_env_for_file_cleanup() {
+    let db_dir = PathBuf::from("database");
+    if db_dir.exists()
+        && let Err(e) = fs::remove_dir_all(&db_dir)
+    {
+        eprintln!(
+            "Error: Failed to remove test database directory {:?}: {}",
+            db_dir, e
+        );
+    }
+}
+
+async fn app() -> Router {
+    let pool = SqlitePool::connect("sqlite::memory:")
+        .await
+        .expect("Failed to connect to in-memory SQLite");
+    init_schema(&pool)
+        .await
+        .expect("Failed to create tasks table in test DB");
+    // With a key, so that the admin routes are mounted
+    create_router_with_config(
+        pool,
+        Config {
+            api_keys: API_KEY.parse().unwrap(),
+            ..Config::default()
+        },
+    )
+}
+
+async fn get(app: &Router, uri: &str) -> (StatusCode, String, Vec<u8>) {
+    let request = Request::get(uri).body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .map(|value| value.to_str().unwrap().to_string())
+        .unwrap_or_default();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, content_type, body.to_vec())
+}
+
+/// API paths of the route table that are described.
+fn declared_routes() -> BTreeSet<&'static str> {
+    ROUTES
+        .iter()
+        .copied()
+        .filter(|route| !UNDOCUMENTED.contains(route))
+        .collect()
+}
+
+/// Collects the targets of every `$ref` in `value`.
+fn references<'a>(value: &'a Value, found: &mut Vec<&'a str>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                match (key.as_str(), value) {
+                    ("$ref", Value::String(target)) => found.push(target),
+                    _ => references(value, found),
+                }
+            }
+        }
+        Value::Array(values) => values.iter().for_each(|value| references(value, found)),
+        _ => {}
+    }
+}
+
+#[tokio::test]
+async fn test_description_matches_the_router() {
+    let app = app().await;
+    let (status, content_type, body) = get(&app, "/api/openapi.json").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(
+        content_type.starts_with("application/json"),
+        "{content_type}"
+    );
+    let spec: Value = serde_json::from_slice(&body).unwrap();
+    let paths = spec["paths"].as_object().unwrap();
+
+    // Assert: The same paths...
+    let declared = declared_routes();
+    let documented: BTreeSet<&str> = paths.keys().map(String::as_str).collect();
+    assert_eq!(documented, declared);
+
+    // ...with the same methods, those the router answers `405` to left out
+    for route in declared {
+        let uri = uri(route);
+        for method in METHODS {
+            let request = Request::builder()
+                .method(method.clone())
+                .uri(&uri)
+                .header("Authorization", format!("Bearer {API_KEY}"))
+                .header("Content-Type", "application/json")
+                .body(Body::from("{}"))
+                .unwrap();
+            let routed = app.clone().oneshot(request).await.unwrap().status()
+                != StatusCode::METHOD_NOT_ALLOWED;
+            let operation = &paths[route][method.as_str().to_lowercase()];
+            assert_eq!(
+                operation.is_object(),
+                routed,
+                "{method} {route}: documented {}, routed {routed}",
+                operation.is_object()
+            );
+        }
+    }
+
+    // Assert: Every schema referred to is described
+    let mut targets = Vec::new();
+    references(&spec, &mut targets);
+    assert!(!targets.is_empty());
+    for target in targets {
+        let name = target.strip_prefix("#/components/schemas/").unwrap();
+        assert!(
+            spec["components"]["schemas"][name].is_object(),
+            "{target} is not described"
+        );
+    }
+
+    teardown_test_env_for_file_cleanup();
+}
+
+#[tokio::test]
+async fn test_documentation_pages_are_public() {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    init_schema(&pool).await.unwrap();
+    let app = create_router_with_config(
+        pool,
+        Config {
+            api_keys: "docs-test-api-key-000001".parse().unwrap(),
+            ..Config::default()
+        },
+    );
+
+    // Act & Assert: The description and its pages need no key
+    let (status, _, _) = get(&app, "/api/openapi.json").await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, content_type, body) = get(&app, "/api/docs/").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(content_type.starts_with("text/html"), "{content_type}");
+    assert!(String::from_utf8(body).unwrap().contains("swagger-ui"));
+
+    // Act & Assert: The API itself still does
+    let (status, _, _) = get(&app, "/api/tasks").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    teardown_test_env_for_file_cleanup();
+}
+
+/// Routes the contract test leaves alone, and why.
+const NOT_CALLED: &[(&str, &str)] = &[
+    (
+        "/api/admin/backup",
+        "is taken with VACUUM INTO, which needs a database file",
+    ),
+    (
+        "/api/admin/restore",
+        "replaces the database with a binary upload",
+    ),
+    ("/api/ws", "needs a WebSocket upgrade"),
+];
+
+/// Routes the contract test expects a documented error from, and why.
+const ANSWERED_WITH_ERRORS: &[(&str, &str)] = &[(
+    "/api/notifications/test",
+    "no notification channel is configured",
+)];
+
+const EXPORT_TOKEN: &str = "openapi-test-export-token-0001";
+
+/// What the calls of the contract test refer to, filled in as the routes
+/// creating it are called.
+#[derive(Debug, Default)]
+struct Fixture {
+    today: NaiveDate,
+    task_id: i64,
+    slot_id: i64,
+    webhook_id: i64,
+    import_id: i64,
+    share_token: String,
+    cookie: String,
+}
+
+impl Fixture {
+    /// ISO week of `day`, e.g. `2025-W29`.
+    fn week(day: NaiveDate) -> String {
+        let week = day.iso_week();
+        format!("{}-W{:02}", week.year(), week.week())
+    }
+
+    /// A concrete URI for `route`, with the query parameters it requires.
+    fn uri(&self, route: &str) -> String {
+        let id = if route.starts_with("/api/webhooks/") {
+            self.webhook_id
+        } else if route.starts_with("/api/import/") {
+            self.import_id
+        } else {
+            self.task_id
+        };
+        let path = route
+            .replace("{id}", &id.to_string())
+            .replace("{slot_id}", &self.slot_id.to_string())
+            .replace("{job}", "rollover")
+            .replace("{name}", "Acme")
+            .replace("{alias}", "acme-corp")
+            .replace("{day}", &self.today.to_string())
+            .replace("{date}", &self.today.to_string())
+            // Only weeks that are over can be archived
+            .replace("{week}", &Fixture::week(self.today - Duration::weeks(1)))
+            .replace("{token}", &self.share_token);
+        match route {
+            "/api/clients/resolve" => format!("{path}?name=acme-corp"),
+            "/api/export/table" => format!("{path}?entity=tasks"),
+            "/api/tasks/suggest" => format!("{path}?minutes=60"),
+            _ => path,
+        }
+    }
+
+    /// An example body for the `POST` routes taking one. Tasks created after
+    /// the archiving of `Acme` go to `Globex`.
+    fn example_body(&self, route: &str) -> Option<Value> {
+        let today = self.today;
+        let body = match route {
+            "/api/admin/dead-letters" => json!({}),
+            "/api/batch" => json!([{ "op": "move", "task_id": self.task_id, "task_date": today }]),
+            "/api/capture" => json!({ "client_name": "Acme", "description": "Call back" }),
+            "/api/clients" => json!({ "name": "Globex" }),
+            "/api/clients/{name}/aliases" => json!({ "alias": "acme-corp" }),
+            "/api/days/{date}/focus" => json!({ "top_n": 3 }),
+            "/api/holidays" => {
+                json!({ "day": today + Duration::weeks(2), "name": "Founders' Day" })
+            }
+            "/api/import" => {
+                json!([{ "client_name": "Globex", "description": "Imported", "task_date": today }])
+            }
+            "/api/import/full" => json!({
+                "version": FULL_EXPORT_VERSION,
+                "exported_at": Utc::now(),
+                "clients": [],
+                "tasks": []
+            }),
+            "/api/plan/next-week" => json!({ "carry_over": [], "new_tasks": [] }),
+            "/api/session" => json!({ "api_key": API_KEY }),
+            "/api/shares" => json!({ "week": Fixture::week(today) }),
+            "/api/tasks" => json!({ "client_name": "Globex", "description": "Created" }),
+            "/api/tasks/bulk" => json!([{ "client_name": "Globex", "description": "In bulk" }]),
+            "/api/tasks/bulk-move" => json!({
+                "filter": { "client_name": "Globex", "task_date": today },
+                "target_date": today
+            }),
+            "/api/tasks/{id}/slots" => json!({ "slot_date": today }),
+            "/api/webhooks" => json!({
+                "url": "https://hooks.example.com/tasks",
+                "events": ["task.created"]
+            }),
+            _ => return None,
+        };
+        Some(body)
+    }
+
+    /// Keeps what a `POST` to `route` created for the later calls.
+    fn record(&mut self, route: &str, body: &Value, set_cookie: Option<&str>) {
+        match route {
+            "/api/tasks/{id}/slots" => self.slot_id = body["id"].as_i64().unwrap(),
+            "/api/webhooks" => self.webhook_id = body["id"].as_i64().unwrap(),
+            "/api/import" => self.import_id = body["import_id"].as_i64().unwrap(),
+            "/api/shares" => self.share_token = body["token"].as_str().unwrap().to_string(),
+            "/api/session" => {
+                let cookie = set_cookie.unwrap().split(';').next().unwrap();
+                self.cookie = cookie.to_string();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Checks a response against the description of `method` on `route`: its
+/// status must be documented, and a JSON body must match the schema of that
+/// status.
+fn check_response(
+    spec: &Value,
+    route: &str,
+    method: &str,
+    status: StatusCode,
+    content_type: &str,
+    body: &[u8],
+) {
+    let context = format!("{} {route} answered {status}", method.to_uppercase());
+    let response = &spec["paths"][route][method]["responses"][status.as_str()];
+    assert!(response.is_object(), "{context}, which is not documented");
+    let media_type = content_type.split(';').next().unwrap_or_default().trim();
+    if body.is_empty() {
+        return;
+    }
+    let content = &response["content"][media_type];
+    assert!(
+        content.is_object(),
+        "{context} with an undocumented {media_type} body"
+    );
+    if media_type != "application/json" {
+        return;
+    }
+
+    // The schema is checked at the root of a document holding the
+    // components, so that its references resolve.
+    let mut schema = content["schema"].clone();
+    schema["components"] = spec["components"].clone();
+    let validator = jsonschema::draft202012::new(&schema)
+        .unwrap_or_else(|e| panic!("{context}: invalid schema: {e}"));
+    let instance: Value = serde_json::from_slice(body).unwrap();
+    let errors: Vec<String> = validator
+        .iter_errors(&instance)
+        .map(|e| format!("{} at {}", e, e.instance_path))
+        .collect();
+    assert!(
+        errors.is_empty(),
+        "{context}, not matching its schema: {errors:#?}\n{instance:#}"
+    );
+}
+
+#[tokio::test]
+async fn test_responses_match_their_schemas() {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    init_schema(&pool).await.unwrap();
+    let today = Utc::now().date_naive();
+    let scenario = ScenarioBuilder::new()
+        .task(TaskSpec::new("Acme", "Report").on_date(today).estimate(90))
+        .task(TaskSpec::new("Acme", "Invoice").on_date(today).done())
+        .seed(&pool)
+        .await
+        .unwrap();
+    let app = create_router_with_config(
+        pool,
+        Config {
+            api_keys: API_KEY.parse().unwrap(),
+            export_tokens: EXPORT_TOKEN.parse().unwrap(),
+            sessions: Some(SessionConfig::new(
+                "openapi-test-session-secret-00001".parse().unwrap(),
+            )),
+            share_secret: Some("openapi-test-share-secret-000001".parse().unwrap()),
+            ..Config::default()
+        },
+    );
+    let (_, _, body) = get(&app, "/api/openapi.json").await;
+    let spec: Value = serde_json::from_slice(&body).unwrap();
+    let paths = spec["paths"].as_object().unwrap();
+    // The weekly goal is only set with PUT, which is not called below
+    let request = Request::put("/api/settings/goal")
+        .header(header::AUTHORIZATION, format!("Bearer {API_KEY}"))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({ "unit": "tasks", "target": 5 }).to_string(),
+        ))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut fixture = Fixture {
+        today,
+        task_id: scenario.task("Report").id,
+        ..Fixture::default()
+    };
+
+    // Act & Assert: The routes creating things go first, so that the others
+    // have something to read
+    let mut called = 0;
+    for method in ["post", "get"] {
+        for (route, operations) in paths {
+            let route = route.as_str();
+            let Some(operation) = operations.get(method) else {
+                continue;
+            };
+            if NOT_CALLED.iter().any(|(skipped, _)| *skipped == route) {
+                continue;
+            }
+            let body = match method {
+                "post" => fixture.example_body(route),
+                _ => None,
+            };
+            assert_eq!(
+                body.is_some(),
+                method == "post" && operation["requestBody"].is_object(),
+                "POST {route}: give it an example body, and only if it takes one"
+            );
+
+            let token = if route.starts_with("/api/export/table")
+                || route.starts_with("/api/export/calendar")
+            {
+                EXPORT_TOKEN
+            } else {
+                API_KEY
+            };
+            let mut request = Request::builder()
+                .method(method.to_uppercase().as_str())
+                .uri(fixture.uri(route))
+                .header(header::AUTHORIZATION, format!("Bearer {token}"));
+            if !fixture.cookie.is_empty() {
+                request = request.header(header::COOKIE, &fixture.cookie);
+            }
+            let request = match &body {
+                Some(body) => request
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string())),
+                None => request.body(Body::empty()),
+            }
+            .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            let content_type = response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .map(|value| value.to_str().unwrap().to_string())
+                .unwrap_or_default();
+            let set_cookie = response
+                .headers()
+                .get(header::SET_COOKIE)
+                .map(|value| value.to_str().unwrap().to_string());
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            check_response(&spec, route, method, status, &content_type, &body);
+            let expects_error = ANSWERED_WITH_ERRORS
+                .iter()
+                .any(|(known, _)| *known == route);
+            assert!(
+                status.is_success() != expects_error,
+                "{method} {route} failed with {status}: {}",
+                String::from_utf8_lossy(&body)
+            );
+            if method == "post" && content_type.starts_with("application/json") {
+                let body: Value = serde_json::from_slice(&body).unwrap();
+                fixture.record(route, &body, set_cookie.as_deref());
+            }
+            called += 1;
+        }
+    }
+    assert!(called > 50, "{called}");
+
+    teardown_test_env_for_file_cleanup();
+}
@@ -0,0 +1,185 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+
+// Snapshots of the JSON sent to clients, so that a renamed field or a change
+// in how options are serialized shows up as a failing test. Review changes
+// with `cargo insta review`, or rerun with `INSTA_UPDATE=always` to accept them.
+//
+// The clock is frozen so that "today" is the same on every run, which needs
+// a binary of its own. Timestamps and colors still depend on the order the
+// tests run in, so they are redacted.
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    Router,
+};
+use chrono::DateTime;
+use http_body_util::BodyExt; // For `collect`
+use insta::assert_json_snapshot;
+use serde_json::{json, Value};
+use server::clock;
+use server::database::init_schema;
+use server::routes::create_router;
+use sqlx::SqlitePool;
+use std::fs;
+use std::path::PathBuf;
+use tower::ServiceExt; // For `oneshot`
+
+/// Removes the directory where the client colors are saved.
+fn teardown_test_env_for_file_cleanup() {
+    let db_dir = PathBuf::from("database");
+    if db_dir.exists()
+        && let Err(e) = fs::remove_dir_all(&db_dir)
+    {
+        eprintln!(
+            "Error: Failed to remove test database directory {:?}: {}",
+            db_dir, e
+        );
+    }
+}
+
+/// Builds a router on a fresh in-memory database, with the clock frozen on
+/// Wednesday 2025-07-16.
+async fn setup_app() -> Router {
+    clock::freeze(DateTime::from_timestamp(1_752_656_400, 0).unwrap());
+    let pool = SqlitePool::connect("sqlite::memory:")
+        .await
+        .expect("Failed to connect to in-memory SQLite");
+    init_schema(&pool)
+        .await
+        .expect("Failed to create tasks table in test DB");
+    create_router(pool)
+}
+
+/// Sends a request and returns the response status and JSON body.
+async fn send(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder().method(method).uri(uri);
+    let request = match body {
+        Some(body) => request
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    // Bodies are JSON, except for empty ones like `204 No Content`
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn create_tasks(app: &Router) {
+    for task in [
+        json!({ "client_name": "Acme", "description": "Write the report", "task_date": "2025-07-16", "priority": 2, "estimate_minutes": 90, "context": "@office" }),
+        json!({ "client_name": "Acme", "description": "Call the accountant", "task_date": "2025-07-17" }),
+        json!({ "client_name": "Globex", "description": "Sync with the team", "task_date": "2025-07-16", "energy": "shallow" }),
+    ] {
+        let (status, _) = send(app, "POST", "/api/tasks", Some(task)).await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+}
+
+#[tokio::test]
+async fn test_task_snapshot() {
+    let app = setup_app().await;
+
+    let (status, task) = send(
+        &app,
+        "POST",
+        "/api/tasks",
+        Some(json!({ "client_name": "Acme", "description": "Write the report", "task_date": "2025-07-16", "priority": 2, "estimate_minutes": 90, "context": "@office" })),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::CREATED);
+    assert_json_snapshot!(task, {
+        ".created_at" => "[timestamp]",
+        ".updated_at" => "[timestamp]",
+        ".client_color" => "[color]",
+        ".client_text_color" => "[color]",
+        ".client_background_color" => "[color]",
+        ".display.color" => "[color]",
+    });
+
+    teardown_test_env_for_file_cleanup();
+}
+
+#[tokio::test]
+async fn test_list_responses_snapshot() {
+    let app = setup_app().await;
+    create_tasks(&app).await;
+
+    let (status, tasks) = send(&app, "GET", "/api/tasks", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_json_snapshot!("task_list", tasks, {
+        "[].created_at" => "[timestamp]",
+        "[].updated_at" => "[timestamp]",
+        "[].client_color" => "[color]",
+        "[].client_text_color" => "[color]",
+        "[].client_background_color" => "[color]",
+        "[].display.color" => "[color]",
+    });
+
+    let (status, board) = send(&app, "GET", "/api/tasks/week", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_json_snapshot!("week_board", board, {
+        ".*[].client_color" => "[color]",
+        ".*[].client_text_color" => "[color]",
+        ".*[].client_background_color" => "[color]",
+        ".*[].display.color" => "[color]",
+    });
+
+    let (status, clients) = send(&app, "GET", "/api/clients", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_json_snapshot!("client_list", clients, {
+        "[].color" => "[color]",
+        "[].text_color" => "[color]",
+        "[].background_color" => "[color]",
+    });
+
+    teardown_test_env_for_file_cleanup();
+}
+
+#[tokio::test]
+async fn test_client_stats_snapshot() {
+    let app = setup_app().await;
+    create_tasks(&app).await;
+    let (status, _) = send(&app, "DELETE", "/api/tasks/1", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, stats) = send(
+        &app,
+        "GET",
+        "/api/clients/Acme/stats?from=2025-07-14&to=2025-07-20",
+        None,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_json_snapshot!(stats);
+
+    teardown_test_env_for_file_cleanup();
+}
+
+#[tokio::test]
+async fn test_error_body_snapshots() {
+    let app = setup_app().await;
+
+    let (status, not_found) = send(&app, "DELETE", "/api/tasks/999", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_json_snapshot!("not_found", not_found);
+
+    let (status, invalid) = send(
+        &app,
+        "POST",
+        "/api/tasks",
+        Some(json!({ "client_name": "", "description": "Some description" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_json_snapshot!("bad_request", invalid);
+
+    teardown_test_env_for_file_cleanup();
+}
@@ -0,0 +1,291 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+
+// Deliveries are posted to a receiver listening on a local port, and sent by
+// calling the worker's pass directly rather than waiting for it.
+use axum::{
+    body::Body,
+    http::{HeaderMap, Request, StatusCode},
+    routing::post,
+    Router,
+};
+use common::{DeliveryStatus, Task, Webhook, WebhookDelivery};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use server::auth::SigningSecret;
+use server::config::WebhooksConfig;
+use server::database::init_schema;
+use server::routes::create_router;
+use server::webhooks;
+use sqlx::SqlitePool;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tower::ServiceExt; // For `oneshot`
+
+/// Removes the directory where the client colors are saved.
+fn teardown_test_env_for_file_cleanup() {
+    let db_dir = PathBuf::from("database");
+    if db_dir.exists()
+        && let Err(e) = fs::remove_dir_all(&db_dir)
+    {
+        eprintln!(
+            "Error: Failed to remove test database directory {:?}: {}",
+            db_dir, e
+        );
+    }
+}
+
+/// Sends a JSON request to the app, returning the status and the body.
+async fn call(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("Content-Type", "application/json")
+        .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+/// Receiver of the deliveries: `/hook` records them and accepts them,
+/// `/broken` always fails.
+async fn spawn_receiver() -> (String, mpsc::UnboundedReceiver<(HeaderMap, String)>) {
+    let (sender, received) = mpsc::unbounded_channel();
+    let receiver = Router::new()
+        .route(
+            "/hook",
+            post(move |headers: HeaderMap, body: String| async move {
+                sender.send((headers, body)).unwrap();
+                StatusCode::NO_CONTENT
+            }),
+        )
+        .route(
+            "/broken",
+            post(|| async { StatusCode::SERVICE_UNAVAILABLE }),
+        );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, receiver).await });
+    (format!("http://{addr}"), received)
+}
+
+async fn deliveries(app: &Router, webhook_id: i64) -> Vec<WebhookDelivery> {
+    let (status, body) = call(
+        app,
+        "GET",
+        &format!("/api/webhooks/{webhook_id}/deliveries"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    serde_json::from_value(body).unwrap()
+}
+
+#[tokio::test]
+async fn test_webhook_registration_is_checked() {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    init_schema(&pool).await.unwrap();
+    let app = create_router(pool);
+
+    for (payload, expected) in [
+        (
+            json!({ "url": "ftp://example.com/hook", "events": ["task.created"] }),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            json!({ "url": "/relative", "events": ["task.created"] }),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            json!({ "url": "https://example.com/hook", "events": [] }),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            json!({ "url": "https://example.com/hook", "events": ["task.moved"] }),
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+    ] {
+        let (status, _) = call(&app, "POST", "/api/webhooks", Some(payload.clone())).await;
+        assert_eq!(status, expected, "{payload}");
+    }
+
+    // Events are listed once each, in a fixed order
+    let (status, body) = call(
+        &app,
+        "POST",
+        "/api/webhooks",
+        Some(json!({
+            "url": "https://example.com/hook",
+            "events": ["task.deleted", "task.created", "task.deleted"]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["events"], json!(["task.created", "task.deleted"]));
+    assert_eq!(body["secret"].as_str().unwrap().len(), 64);
+
+    // The secret is only shown once
+    let (_, listed) = call(&app, "GET", "/api/webhooks", None).await;
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert!(listed[0].get("secret").is_none(), "{listed}");
+
+    let (status, _) = call(&app, "DELETE", "/api/webhooks/1", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = call(&app, "GET", "/api/webhooks/1/deliveries", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    teardown_test_env_for_file_cleanup();
+}
+
+#[tokio::test]
+async fn test_webhook_deliveries_are_signed_and_retried() {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    init_schema(&pool).await.unwrap();
+    let app = create_router(pool.clone());
+    let (receiver_url, mut received) = spawn_receiver().await;
+
+    // Arrange: One working webhook for every event, one broken
+    let (_, body) = call(
+        &app,
+        "POST",
+        "/api/webhooks",
+        Some(json!({
+            "url": format!("{receiver_url}/hook"),
+            "events": ["task.created", "task.deleted", "rollover.completed"]
+        })),
+    )
+    .await;
+    let hook: Webhook = serde_json::from_value(body).unwrap();
+    let (_, body) = call(
+        &app,
+        "POST",
+        "/api/webhooks",
+        Some(json!({ "url": format!("{receiver_url}/broken"), "events": ["task.created"] })),
+    )
+    .await;
+    let broken: Webhook = serde_json::from_value(body).unwrap();
+
+    // Act: Create a task, complete it and roll over
+    let (status, body) = call(
+        &app,
+        "POST",
+        "/api/tasks",
+        Some(json!({ "client_name": "ACME", "description": "Hooked" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let task: Task = serde_json::from_value(body).unwrap();
+    let (status, _) = call(&app, "DELETE", &format!("/api/tasks/{}", task.id), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = call(&app, "PATCH", "/api/tasks/rollover", None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let client = webhooks::client().unwrap();
+    let config = WebhooksConfig {
+        max_attempts: 2,
+        retry_backoff: Duration::ZERO,
+        ..WebhooksConfig::default()
+    };
+    let sent = webhooks::deliver_due(&pool, &client, &config)
+        .await
+        .unwrap();
+    assert_eq!(sent, 4);
+
+    // Assert: Every event reached the working webhook, signed
+    let secret: SigningSecret = hook.secret.unwrap().parse().unwrap();
+    let mut events = Vec::new();
+    for _ in 0..3 {
+        let (headers, body) = received.recv().await.unwrap();
+        let signature = headers["x-webhook-signature"].to_str().unwrap();
+        assert!(secret.verify(&body, signature.strip_prefix("sha256=").unwrap()));
+        let payload: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            headers["x-webhook-event"],
+            payload["event"].as_str().unwrap()
+        );
+        events.push(payload);
+    }
+    events.sort_by_key(|payload| payload["occurred_at"].as_str().unwrap().to_string());
+    assert_eq!(events[0]["event"], "task.created");
+    assert_eq!(events[0]["data"]["id"], task.id);
+    assert_eq!(events[1]["event"], "task.deleted");
+    assert!(events[1]["data"]["deleted_at"].is_string());
+    assert_eq!(events[2]["event"], "rollover.completed");
+    assert_eq!(events[2]["data"]["triggered_by"], "manual");
+
+    let log = deliveries(&app, hook.id).await;
+    assert_eq!(log.len(), 3);
+    for delivery in &log {
+        assert_eq!(delivery.status, DeliveryStatus::Delivered);
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(delivery.response_status, Some(204));
+    }
+
+    // Assert: The broken webhook is retried, then given up on
+    let log = deliveries(&app, broken.id).await;
+    assert_eq!(log[0].status, DeliveryStatus::Pending);
+    assert_eq!(
+        log[0].error.as_deref(),
+        Some("Receiver answered 503 Service Unavailable")
+    );
+    assert_eq!(
+        webhooks::deliver_due(&pool, &client, &config)
+            .await
+            .unwrap(),
+        1
+    );
+    let log = deliveries(&app, broken.id).await;
+    assert_eq!(log[0].status, DeliveryStatus::Failed);
+    assert_eq!(log[0].attempts, 2);
+    assert_eq!(log[0].next_attempt_at, None);
+    assert_eq!(
+        webhooks::deliver_due(&pool, &client, &config)
+            .await
+            .unwrap(),
+        0
+    );
+
+    // Assert: It is listed with the dead letters, alone
+    let failed = log[0].id;
+    let (status, body) = call(&app, "GET", "/api/admin/dead-letters", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let dead: Vec<WebhookDelivery> = serde_json::from_value(body).unwrap();
+    assert_eq!(dead, log);
+
+    // Act: Queue the delivered ones and the failed one again
+    let delivered = deliveries(&app, hook.id).await[0].id;
+    let (status, body) = call(
+        &app,
+        "POST",
+        "/api/admin/dead-letters",
+        Some(json!({ "delivery_ids": [delivered, failed] })),
+    )
+    .await;
+
+    // Assert: Only the failed one is sent again, with a fresh count of attempts
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "delivery_ids": [failed] }));
+    let log = deliveries(&app, broken.id).await;
+    assert_eq!(log[0].status, DeliveryStatus::Pending);
+    assert_eq!(log[0].attempts, 0);
+    assert_eq!(
+        webhooks::deliver_due(&pool, &client, &config)
+            .await
+            .unwrap(),
+        1
+    );
+    let (_, body) = call(&app, "GET", "/api/admin/dead-letters", None).await;
+    assert_eq!(body, json!([]));
+
+    teardown_test_env_for_file_cleanup();
+}
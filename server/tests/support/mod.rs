@@ -0,0 +1,45 @@
+// Copyright (c) 2025 sbksba
+//
+// This software is licensed under the terms of the MIT License.
+// See the LICENSE file in the project root for the full license text.
+
+// Routes of the API as the tests enumerate them, from the table the router
+// itself mounts them from.
+use axum::http::Method;
+
+pub use server::routes::ROUTES;
+
+pub const METHODS: [Method; 5] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::DELETE,
+];
+
+/// Query parameters without which a route answers `400` before checking
+/// access.
+const REQUIRED_QUERIES: &[(&str, &str)] = &[("/api/export/table", "entity=tasks")];
+
+/// A concrete URI for `route`, its parameters filled with plausible values.
+pub fn uri(route: &str) -> String {
+    let path = [
+        ("{id}", "1"),
+        ("{slot_id}", "1"),
+        ("{job}", "rollover"),
+        ("{name}", "ACME"),
+        ("{alias}", "acme"),
+        ("{day}", "2025-07-14"),
+        ("{date}", "2025-07-14"),
+        ("{week}", "2025-W29"),
+        ("{token}", "2025-W29.1752483600.forged"),
+    ]
+    .iter()
+    .fold(route.to_string(), |uri, (param, value)| {
+        uri.replace(param, value)
+    });
+    match REQUIRED_QUERIES.iter().find(|(known, _)| *known == route) {
+        Some((_, query)) => format!("{path}?{query}"),
+        None => path,
+    }
+}